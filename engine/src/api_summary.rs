@@ -0,0 +1,615 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::{
+    parse_quote, Attribute, Block, Fields, ForeignItem, ImplItem, Item, ItemConst, ItemEnum,
+    ItemFn, ItemStatic, ItemStruct, ItemTrait, ItemType, Meta, Signature, TraitItem, Type, UseTree,
+    Variant, Visibility,
+};
+
+/// Generate a summary of the Rust API in the given bindings, one line per
+/// item, to be checked in so that code review shows how a change to the
+/// C++ headers changes the bindings. Each line starts with the kind of
+/// item and gives its full path, e.g. `fn ffi::Goat::count(&self) -> u32`.
+/// Lines are sorted within each `include_cpp!` so that the summary doesn't
+/// change when the headers are merely reordered.
+pub fn generate_api_summary(bindings: &[syn::File]) -> String {
+    let mut summary = String::new();
+    for ffi in bindings.iter().flat_map(|file| file.items.iter()) {
+        let Item::Mod(ffi) = ffi else {
+            continue;
+        };
+        let Some((_, items)) = &ffi.content else {
+            continue;
+        };
+        let mut lines = Vec::new();
+        summarize_mod(items, items, &ffi.ident.to_string(), &mut lines);
+        lines.sort();
+        lines.dedup();
+        for line in lines {
+            summary.push_str(&line);
+            summary.push('\n');
+        }
+    }
+    summary
+}
+
+/// An item which a `pub use` refers to.
+enum Resolved<'a> {
+    Item(&'a Item),
+    ForeignItem(&'a ForeignItem),
+    Mod(&'a [Item]),
+}
+
+/// Summarize the public items in one of the mods making up the API, whose
+/// path is `path`. `root` is the top level of the `include_cpp!` mod, to
+/// which the private mods holding the cxx and bindgen bindings belong.
+fn summarize_mod(root: &[Item], items: &[Item], path: &str, lines: &mut Vec<String>) {
+    for item in items {
+        match item {
+            Item::Use(use_item) if is_public(&use_item.vis) => {
+                summarize_use(root, &use_item.tree, &mut Vec::new(), path, lines)
+            }
+            Item::Impl(imp) if !is_hidden(&imp.attrs) => {
+                let self_ty = qualify_type(&imp.self_ty, path);
+                match &imp.trait_ {
+                    Some((_, trait_path, _)) => {
+                        let unsafety = if imp.unsafety.is_some() {
+                            "unsafe "
+                        } else {
+                            ""
+                        };
+                        let trait_ty: Type = parse_quote! { #trait_path };
+                        lines.push(format!(
+                            "{unsafety}impl {} for {self_ty}",
+                            render_type(&trait_ty)
+                        ))
+                    }
+                    None => {
+                        for impl_item in &imp.items {
+                            if let ImplItem::Fn(f) = impl_item {
+                                if is_public(&f.vis) && !is_hidden(&f.attrs) {
+                                    lines.push(describe_fn(
+                                        &f.sig,
+                                        &format!("{self_ty}::{}", f.sig.ident),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                if let Some(ident) = item_ident(item) {
+                    if item_is_public(item) {
+                        summarize_item(root, item, &format!("{path}::{ident}"), lines)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Follow a `pub use` to the item it re-exports, and summarize that under
+/// the name by which it's exported.
+fn summarize_use(
+    root: &[Item],
+    tree: &UseTree,
+    prefix: &mut Vec<String>,
+    path: &str,
+    lines: &mut Vec<String>,
+) {
+    let (name, exported_as) = match tree {
+        UseTree::Path(use_path) => {
+            prefix.push(use_path.ident.to_string());
+            summarize_use(root, &use_path.tree, prefix, path, lines);
+            prefix.pop();
+            return;
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                summarize_use(root, tree, prefix, path, lines);
+            }
+            return;
+        }
+        UseTree::Glob(_) => {
+            if let Some(Resolved::Mod(items)) = resolve(root, prefix) {
+                summarize_mod(root, items, path, lines)
+            }
+            return;
+        }
+        UseTree::Name(use_name) => (&use_name.ident, &use_name.ident),
+        UseTree::Rename(rename) => (&rename.ident, &rename.rename),
+    };
+    // `use Trait as _` brings a trait into scope without exporting it.
+    if exported_as == "_" {
+        return;
+    }
+    let exported_path = format!("{path}::{exported_as}");
+    prefix.push(name.to_string());
+    match resolve(root, prefix) {
+        Some(Resolved::Item(item)) => summarize_item(root, item, &exported_path, lines),
+        Some(Resolved::ForeignItem(ForeignItem::Fn(f))) => {
+            lines.push(describe_fn(&f.sig, &exported_path))
+        }
+        Some(Resolved::ForeignItem(_)) => lines.push(format!("type {exported_path}")),
+        Some(Resolved::Mod(items)) => summarize_mod(root, items, &exported_path, lines),
+        None => lines.push(format!("use {} as {exported_path}", prefix.join("::"))),
+    }
+    prefix.pop();
+}
+
+/// Find the item at this path, relative to the top level of the
+/// `include_cpp!` mod. The output mods are nested to match the C++
+/// namespaces, so refer to the top level via `super`.
+fn resolve<'a>(root: &'a [Item], path: &[String]) -> Option<Resolved<'a>> {
+    let mut segments = path
+        .iter()
+        .skip_while(|segment| *segment == "super" || *segment == "self")
+        .peekable();
+    let mut items = root;
+    while let Some(segment) = segments.next() {
+        let is_last = segments.peek().is_none();
+        let found = items.iter().find_map(|item| match item {
+            Item::Mod(m) if m.ident == segment => Some(Resolved::Mod(
+                m.content.as_ref().map_or(&[][..], |(_, items)| items),
+            )),
+            Item::ForeignMod(foreign_mod) if is_last => {
+                foreign_mod
+                    .items
+                    .iter()
+                    .find_map(|foreign_item| match foreign_item {
+                        ForeignItem::Fn(f) if f.sig.ident == segment => {
+                            Some(Resolved::ForeignItem(foreign_item))
+                        }
+                        ForeignItem::Type(t) if t.ident == segment => {
+                            Some(Resolved::ForeignItem(foreign_item))
+                        }
+                        _ => None,
+                    })
+            }
+            _ if is_last && item_ident(item).is_some_and(|ident| ident == segment) => {
+                Some(Resolved::Item(item))
+            }
+            _ => None,
+        })?;
+        match found {
+            Resolved::Mod(mod_items) if !is_last => items = mod_items,
+            _ if is_last => return Some(found),
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn summarize_item(root: &[Item], item: &Item, path: &str, lines: &mut Vec<String>) {
+    if is_hidden(item_attrs(item)) {
+        return;
+    }
+    match item {
+        Item::Fn(f) => lines.push(describe_fn(&f.sig, path)),
+        Item::Struct(s) => {
+            let item = Item::Struct(ItemStruct {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                fields: Fields::Unit,
+                semi_token: Some(Default::default()),
+                ..s.clone()
+            });
+            lines.push(qualify(&render(&item), "struct", &s.ident, path));
+            for field in &s.fields {
+                if let (Some(ident), true) = (&field.ident, is_public(&field.vis)) {
+                    lines.push(format!("field {path}::{ident}: {}", render_type(&field.ty)));
+                }
+            }
+        }
+        Item::Enum(e) => {
+            let item = Item::Enum(ItemEnum {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                variants: Default::default(),
+                ..e.clone()
+            });
+            lines.push(qualify(&render(&item), "enum", &e.ident, path));
+            for variant in &e.variants {
+                lines.push(format!("variant {path}::{}", render_variant(variant)));
+            }
+        }
+        Item::Trait(t) => {
+            let item = Item::Trait(ItemTrait {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                items: Vec::new(),
+                ..t.clone()
+            });
+            lines.push(qualify(&render(&item), "trait", &t.ident, path));
+            for trait_item in &t.items {
+                if let TraitItem::Fn(f) = trait_item {
+                    lines.push(describe_fn(&f.sig, &format!("{path}::{}", f.sig.ident)));
+                }
+            }
+        }
+        Item::Const(c) => {
+            let item = Item::Const(ItemConst {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                ..c.clone()
+            });
+            lines.push(qualify(&render(&item), "const", &c.ident, path))
+        }
+        Item::Static(s) => {
+            let item = Item::Static(ItemStatic {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                ..s.clone()
+            });
+            lines.push(qualify(&render(&item), "static", &s.ident, path))
+        }
+        Item::Type(t) => {
+            let item = Item::Type(ItemType {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                ..t.clone()
+            });
+            lines.push(qualify(&render(&item), "type", &t.ident, path))
+        }
+        Item::Union(u) => lines.push(format!("union {path}{}", render_generics(&u.generics))),
+        Item::Mod(m) => {
+            if let Some((_, items)) = &m.content {
+                summarize_mod(root, items, path, lines)
+            }
+        }
+        _ => {}
+    }
+}
+
+/// e.g. `fn ffi::Goat::count(&self) -> u32`.
+fn describe_fn(sig: &Signature, path: &str) -> String {
+    let block: Block = parse_quote! { {} };
+    let item = Item::Fn(ItemFn {
+        attrs: Vec::new(),
+        vis: Visibility::Inherited,
+        sig: sig.clone(),
+        block: Box::new(block),
+    });
+    qualify(&render(&item), "fn", &sig.ident, path)
+}
+
+/// Replace the name of the item in its rendering with its full path.
+fn qualify(rendered: &str, keyword: &str, ident: &syn::Ident, path: &str) -> String {
+    rendered.replacen(
+        &format!("{keyword} {ident}"),
+        &format!("{keyword} {path}"),
+        1,
+    )
+}
+
+/// The type of the `Self` of an `impl` within the mod at `path`.
+fn qualify_type(ty: &Type, path: &str) -> String {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let rendered = render_type(ty);
+            let last_segment = type_path
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .last()
+                .unwrap_or_default();
+            match rendered.strip_prefix(&last_segment) {
+                Some(generics) if type_path.path.segments.len() == 1 => {
+                    format!("{path}::{last_segment}{generics}")
+                }
+                _ => rendered,
+            }
+        }
+        _ => render_type(ty),
+    }
+}
+
+fn render_type(ty: &Type) -> String {
+    let rendered = render(&parse_quote! { type T = #ty; });
+    rendered["type T = ".len()..].to_string()
+}
+
+fn render_generics(generics: &syn::Generics) -> String {
+    let rendered = render(&parse_quote! { type T #generics = (); });
+    rendered["type T".len()..rendered.len() - " = ()".len()].to_string()
+}
+
+fn render_variant(variant: &Variant) -> String {
+    let variant = Variant {
+        attrs: Vec::new(),
+        ..variant.clone()
+    };
+    let rendered = render(&parse_quote! { enum E { #variant } });
+    rendered
+        .trim_start_matches("enum E {")
+        .trim_end_matches('}')
+        .trim()
+        .trim_end_matches(',')
+        .to_string()
+}
+
+/// Render an item on a single line, without its trailing `;` or `{}`.
+fn render(item: &Item) -> String {
+    let file = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item.clone()],
+    };
+    let rendered = prettyplease::unparse(&file)
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace(" )", ")")
+        .replace("< ", "<")
+        .replace(", >", ">")
+        .replace(" >", ">");
+    let rendered = rendered.trim_end();
+    rendered
+        .strip_suffix(" {}")
+        .or_else(|| rendered.strip_suffix(';'))
+        .unwrap_or(rendered)
+        .to_string()
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn is_hidden(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        matches!(&attr.meta, Meta::List(list)
+            if list.path.is_ident("doc") && list.tokens.to_string() == "hidden")
+    })
+}
+
+fn item_ident(item: &Item) -> Option<&syn::Ident> {
+    match item {
+        Item::Fn(f) => Some(&f.sig.ident),
+        Item::Struct(s) => Some(&s.ident),
+        Item::Enum(e) => Some(&e.ident),
+        Item::Trait(t) => Some(&t.ident),
+        Item::Const(c) => Some(&c.ident),
+        Item::Static(s) => Some(&s.ident),
+        Item::Type(t) => Some(&t.ident),
+        Item::Union(u) => Some(&u.ident),
+        Item::Mod(m) => Some(&m.ident),
+        _ => None,
+    }
+}
+
+fn item_is_public(item: &Item) -> bool {
+    match item {
+        Item::Fn(f) => is_public(&f.vis),
+        Item::Struct(s) => is_public(&s.vis),
+        Item::Enum(e) => is_public(&e.vis),
+        Item::Trait(t) => is_public(&t.vis),
+        Item::Const(c) => is_public(&c.vis),
+        Item::Static(s) => is_public(&s.vis),
+        Item::Type(t) => is_public(&t.vis),
+        Item::Union(u) => is_public(&u.vis),
+        Item::Mod(m) => is_public(&m.vis),
+        _ => false,
+    }
+}
+
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Fn(f) => &f.attrs,
+        Item::Struct(s) => &s.attrs,
+        Item::Enum(e) => &e.attrs,
+        Item::Trait(t) => &t.attrs,
+        Item::Const(c) => &c.attrs,
+        Item::Static(s) => &s.attrs,
+        Item::Type(t) => &t.attrs,
+        Item::Union(u) => &u.attrs,
+        Item::Mod(m) => &m.attrs,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::generate_api_summary;
+
+    #[test]
+    fn test_generate_api_summary() {
+        let bindings: syn::File = parse_quote! {
+            mod ffi {
+                mod bindgen {
+                    pub(super) mod root {
+                        #[repr(C)]
+                        pub struct Goat {
+                            pub horns: u32,
+                            _pinned: ::core::marker::PhantomData<::core::marker::PhantomPinned>,
+                        }
+                        #[repr(u32)]
+                        pub enum Color {
+                            Red = 0,
+                            Blue = 2,
+                        }
+                        pub mod farm {
+                            pub const ACRES: u32 = 40;
+                        }
+                    }
+                }
+                #[cxx::bridge]
+                mod cxxbridge {
+                    unsafe extern "C++" {
+                        fn DoMath(a: u32) -> u32;
+                        fn Describe(goat: &Goat) -> UniquePtr<CxxString>;
+                        type Barn;
+                    }
+                }
+                #[allow(unused_imports)]
+                use bindgen::root;
+                pub use bindgen::root::Goat;
+                pub use bindgen::root::Color;
+                pub use cxxbridge::DoMath;
+                pub use cxxbridge::Describe as describe;
+                pub use cxxbridge::Barn;
+                impl Goat {
+                    pub fn count(&self) -> u32 {
+                        0
+                    }
+                    fn private(&self) {}
+                    #[doc(hidden)]
+                    pub fn hidden(&self) {}
+                }
+                unsafe impl autocxx::moveit::MakeCppStorage for Goat {}
+                pub mod farm {
+                    #[allow(unused_imports)]
+                    use super::{cxxbridge, output, bindgen};
+                    pub use super::bindgen::root::farm::ACRES;
+                }
+            }
+        };
+        let summary = generate_api_summary(&[bindings]);
+        assert_eq!(
+            summary,
+            concat!(
+                "const ffi::farm::ACRES: u32 = 40\n",
+                "enum ffi::Color\n",
+                "field ffi::Goat::horns: u32\n",
+                "fn ffi::DoMath(a: u32) -> u32\n",
+                "fn ffi::Goat::count(&self) -> u32\n",
+                "fn ffi::describe(goat: &Goat) -> UniquePtr<CxxString>\n",
+                "struct ffi::Goat\n",
+                "type ffi::Barn\n",
+                "unsafe impl autocxx::moveit::MakeCppStorage for ffi::Goat\n",
+                "variant ffi::Color::Blue = 2\n",
+                "variant ffi::Color::Red = 0\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_order_independent() {
+        let forwards: syn::File = parse_quote! {
+            mod ffi {
+                pub fn b() {}
+                pub fn a() {}
+            }
+        };
+        let backwards: syn::File = parse_quote! {
+            mod ffi {
+                pub fn a() {}
+                pub fn b() {}
+                pub fn a() {}
+            }
+        };
+        assert_eq!(
+            generate_api_summary(&[forwards]),
+            generate_api_summary(&[backwards])
+        );
+    }
+
+    #[test]
+    fn test_each_mod_in_turn() {
+        let first: syn::File = parse_quote! {
+            mod zoo {
+                pub fn z() {}
+            }
+            fn not_a_mod() {}
+        };
+        let second: syn::File = parse_quote! {
+            mod farm {
+                pub fn a() {}
+            }
+        };
+        assert_eq!(
+            generate_api_summary(&[first, second]),
+            "fn zoo::z()\nfn farm::a()\n"
+        );
+    }
+
+    #[test]
+    fn test_uses() {
+        let bindings: syn::File = parse_quote! {
+            mod ffi {
+                mod bindgen {
+                    pub(super) mod root {
+                        pub mod animals {
+                            pub struct Goat {}
+                            pub struct Sheep {}
+                        }
+                        pub struct _Private {}
+                    }
+                }
+                pub use bindgen::root::animals::*;
+                pub use bindgen::root::{animals::Goat as Billy, _Private as _};
+                pub use elsewhere::Thing;
+            }
+        };
+        assert_eq!(
+            generate_api_summary(&[bindings]),
+            concat!(
+                "struct ffi::Billy\n",
+                "struct ffi::Goat\n",
+                "struct ffi::Sheep\n",
+                "use elsewhere::Thing as ffi::Thing\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_item_kinds() {
+        let bindings: syn::File = parse_quote! {
+            mod ffi {
+                pub struct Wrapper<T> {
+                    pub inner: T,
+                    hidden: u32,
+                }
+                impl<T> Wrapper<T> {
+                    pub fn get(&self) -> &T {
+                        &self.inner
+                    }
+                }
+                pub enum Shape {
+                    Circle(f32),
+                    Square { side: f32 },
+                }
+                pub trait Speak {
+                    fn speak(&self) -> String;
+                }
+                pub type Id = u64;
+                pub static COUNT: u32 = 0;
+                pub union Bits {
+                    pub i: u32,
+                    pub f: f32,
+                }
+                #[doc(hidden)]
+                pub struct Hidden {}
+                struct Private {}
+            }
+        };
+        assert_eq!(
+            generate_api_summary(&[bindings]),
+            concat!(
+                "enum ffi::Shape\n",
+                "field ffi::Wrapper::inner: T\n",
+                "fn ffi::Speak::speak(&self) -> String\n",
+                "fn ffi::Wrapper<T>::get(&self) -> &T\n",
+                "static ffi::COUNT: u32 = 0\n",
+                "struct ffi::Wrapper<T>\n",
+                "trait ffi::Speak\n",
+                "type ffi::Id = u64\n",
+                "union ffi::Bits\n",
+                "variant ffi::Shape::Circle(f32)\n",
+                "variant ffi::Shape::Square { side: f32 }\n",
+            )
+        );
+    }
+}
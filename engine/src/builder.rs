@@ -10,14 +10,20 @@ use autocxx_parser::file_locations::FileLocationStrategy;
 use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::{generate_rs_single, CodegenOptions};
-use crate::{get_cxx_header_bytes, CppCodegenOptions, ParseError, RebuildDependencyRecorder};
+use crate::symbol_check::{defined_symbols, find_missing_symbols, undefined_symbols};
+use crate::{generate_rs_single, CodegenOptions, HeaderPreprocessor, HeadersToPreprocess};
+use crate::{
+    get_clang_path, get_cxx_header_bytes, CppCodegenOptions, ParseError, RebuildDependencyRecorder,
+};
+use autocxx_parser::module_import_header_name;
+use indexmap::set::IndexSet as HashSet;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs::File;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// Errors returned during creation of a [`cc::Build`] from an include_cxx
 /// macro.
@@ -35,6 +41,14 @@ pub enum BuilderError {
     NoIncludeCxxMacrosFound,
     #[error("could not create a directory {1}: {0}")]
     UnableToCreateDirectory(std::io::Error, PathBuf),
+    #[error("could not precompile the interface of C++ module {0}: {1}")]
+    ModulePrecompileFailed(String, String),
+    #[error("C++ modules can only be precompiled by clang, but {0} is a different compiler. Set CLANG_PATH to a clang++ binary, and have the cc::Build use it too.")]
+    ModulesNeedClang(String),
+    #[error("could not check that the symbols needed by the bindings exist: {0}")]
+    SymbolCheckFailed(String),
+    #[error("the generated bindings need these symbols, which aren't defined in any of the libraries given to verify_symbols:\n{}", .0.join("\n"))]
+    MissingSymbols(Vec<String>),
 }
 
 #[cfg_attr(feature = "nightly", doc(cfg(feature = "build")))]
@@ -59,6 +73,12 @@ pub trait BuilderContext {
 
     /// Create a dependency recorder, if any.
     fn get_dependency_recorder() -> Option<Box<dyn RebuildDependencyRecorder>>;
+
+    /// Show a warning about the bindings, for instance a directive which
+    /// `conversion_errors!(lenient)` stopped from being an error.
+    fn report_warning(message: &str) {
+        log::warn!("{message}");
+    }
 }
 
 /// An object to allow building of bindings from a `build.rs` file.
@@ -81,6 +101,10 @@ pub struct Builder<'a, BuilderContext> {
     dependency_recorder: Option<Box<dyn RebuildDependencyRecorder>>,
     custom_gendir: Option<PathBuf>,
     auto_allowlist: bool,
+    cpp_modules: Vec<(String, PathBuf)>,
+    symbol_check_libraries: Vec<PathBuf>,
+    cpp_opt_level: Option<String>,
+    cpp_debug_info: Option<bool>,
     codegen_options: CodegenOptions<'a>,
     // This member is to ensure that this type is parameterized
     // by a BuilderContext. The goal is to balance three needs:
@@ -92,7 +116,7 @@ pub struct Builder<'a, BuilderContext> {
     ctx: PhantomData<BuilderContext>,
 }
 
-impl<CTX: BuilderContext> Builder<'_, CTX> {
+impl<'a, CTX: BuilderContext> Builder<'a, CTX> {
     /// Create a new Builder object. You'll need to pass in the Rust file
     /// which contains the bindings (typically an `include_cpp!` macro
     /// though `autocxx` can also handle manually-crafted `cxx::bridge`
@@ -116,6 +140,10 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
             dependency_recorder: CTX::get_dependency_recorder(),
             custom_gendir: None,
             auto_allowlist: false,
+            cpp_modules: Vec::new(),
+            symbol_check_libraries: Vec::new(),
+            cpp_opt_level: None,
+            cpp_debug_info: None,
             codegen_options: CodegenOptions::default(),
             ctx: PhantomData,
         }
@@ -144,6 +172,22 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         self
     }
 
+    /// Rewrite or add to the headers for each `include_cpp!` before bindgen
+    /// parses them, for example to strip out macros which bindgen can't
+    /// handle. The function is given each header named in an `#include`
+    /// directive, along with the clang arguments, and may alter the
+    /// headers' contents or add a prologue to go before them all.
+    /// autocxx puts the rewritten headers in a temporary directory which
+    /// it searches before the others, for as long as bindgen needs them.
+    /// See [`HeaderPreprocessor`] for details.
+    pub fn header_preprocessor<F>(mut self, preprocessor: F) -> Self
+    where
+        F: 'a + Fn(&mut HeadersToPreprocess) -> Result<(), String>,
+    {
+        self.codegen_options.header_preprocessor = Some(HeaderPreprocessor(Box::new(preprocessor)));
+        self
+    }
+
     /// Automatically discover uses of the C++ `ffi` mod and generate the allowlist
     /// from that.
     /// This is a highly experimental option, not currently recommended.
@@ -163,6 +207,60 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         self
     }
 
+    /// Provide the interface unit of a C++20 module, for use by an
+    /// `import_module!` directive with the same module name. Before
+    /// generating bindings, the interface is precompiled into a binary
+    /// module interface (BMI) using the same clang which autocxx uses
+    /// for preprocessing (see `CLANG_PATH`), and the BMI is then used
+    /// both when parsing headers and when building the generated C++.
+    ///
+    /// This requires C++20, so pass `-std=c++20` (or later) to
+    /// [`extra_clang_args`] and to the resulting [`cc::Build`], and the
+    /// [`cc::Build`] must use that same clang. If either compiler turns out
+    /// not to be clang, the build fails with [`BuilderError::ModulesNeedClang`].
+    pub fn cpp_module(mut self, module_name: &str, interface: impl AsRef<Path>) -> Self {
+        self.cpp_modules
+            .push((module_name.to_string(), interface.as_ref().to_path_buf()));
+        self
+    }
+
+    /// After generating the bindings, compile them and check that every
+    /// symbol they need from the C++ items named in `generate!` and
+    /// `generate_pod!` directives is defined in one of these libraries
+    /// or object files. Any which are missing are reported together as a
+    /// [`BuilderError::MissingSymbols`], rather than as many separate link
+    /// errors later on - which is what usually happens if a `generate!`
+    /// directive is mistyped, or the headers don't match the version of
+    /// the library.
+    ///
+    /// This runs `nm`, or whatever the `AUTOCXX_NM` environment variable
+    /// names. For MSVC targets, set that to an `llvm-nm`.
+    pub fn verify_symbols(mut self, libraries: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        self.symbol_check_libraries
+            .extend(libraries.into_iter().map(|lib| lib.as_ref().to_path_buf()));
+        self
+    }
+
+    /// The optimization level for the generated C++ code, such as `"2"`,
+    /// `"s"` or `"z"`. By default [`cc::Build`] follows the crate's own
+    /// profile, so in debug builds the shims are unoptimized; this lets
+    /// you (for instance) always build them at `-O2`, which can matter
+    /// when the shims inline large parts of a header-only library.
+    /// It only affects the generated code and anything else you
+    /// subsequently add to the returned [`cc::Build`].
+    pub fn cpp_opt_level(mut self, opt_level: &str) -> Self {
+        self.cpp_opt_level = Some(opt_level.to_string());
+        self
+    }
+
+    /// Whether to include debug information in the generated C++ code,
+    /// regardless of the crate's profile. Like [Self::cpp_opt_level], this
+    /// applies to the returned [`cc::Build`].
+    pub fn cpp_debug_info(mut self, debug: bool) -> Self {
+        self.cpp_debug_info = Some(debug);
+        self
+    }
+
     #[doc(hidden)]
     /// Whether to force autocxx always to generate extra Rust and C++
     /// side shims. This is only used by the integration test suite to
@@ -253,8 +351,37 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         let autocxx_inc = build_autocxx_inc(self.autocxx_incs, &incdir);
         gen_location_strategy.set_cargo_env_vars_for_build();
 
+        let mut module_args = Vec::new();
+        let mut module_bmis = Vec::new();
+        for (module_name, interface) in &self.cpp_modules {
+            if let Some(dependency_recorder) = &self.dependency_recorder {
+                dependency_recorder.record_header_file_dependency(&interface.to_string_lossy());
+            }
+            let bmi = precompile_module(module_name, interface, &autocxx_inc, clang_args, &incdir)?;
+            write_to_file(
+                &incdir,
+                &module_import_header_name(module_name),
+                format!("#pragma once\nimport {module_name};\n").as_bytes(),
+            )?;
+            module_args.push(format!("-fmodule-file={module_name}={}", bmi.display()));
+            module_bmis.push(bmi);
+        }
+        let clang_args = &clang_args
+            .iter()
+            .copied()
+            .chain(module_args.iter().map(|s| &s[..]))
+            .collect::<Vec<_>>();
+
         let mut parsed_file = crate::parse_file(self.rs_file, self.auto_allowlist)
             .map_err(BuilderError::ParseError)?;
+        if let Some(dependency_recorder) = &self.dependency_recorder {
+            for config_file in parsed_file
+                .get_autocxxes()
+                .flat_map(|include_cpp| include_cpp.config.get_config_files())
+            {
+                dependency_recorder.record_header_file_dependency(&config_file.to_string_lossy());
+            }
+        }
         parsed_file
             .resolve_all(
                 autocxx_inc,
@@ -263,15 +390,46 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
                 &self.codegen_options,
             )
             .map_err(BuilderError::ParseError)?;
+        for warning in parsed_file
+            .get_autocxxes()
+            .flat_map(|include_cpp| include_cpp.get_warnings())
+        {
+            CTX::report_warning(warning);
+        }
         let mut counter = 0;
         let mut builder = cc::Build::new();
         builder.cpp(true);
+        if let Some(opt_level) = &self.cpp_opt_level {
+            builder.opt_level_str(opt_level);
+        }
+        if let Some(debug) = self.cpp_debug_info {
+            builder.debug(debug);
+        }
         if std::env::var_os("AUTOCXX_ASAN").is_some() {
             builder.flag_if_supported("-fsanitize=address");
         }
         let mut generated_rs = Vec::new();
         let mut generated_cpp = Vec::new();
         builder.includes(parsed_file.include_dirs());
+        if !module_args.is_empty() {
+            // If cc can't yet tell which compiler it'll use, because we're
+            // not in a build script, the caller must choose one later.
+            if let Ok(compiler) = builder.try_get_compiler() {
+                if !compiler.is_like_clang() {
+                    return Err(BuilderError::ModulesNeedClang(
+                        compiler.path().display().to_string(),
+                    ));
+                }
+            }
+        }
+        for module_arg in &module_args {
+            builder.flag(module_arg);
+        }
+        // The BMIs contain the definitions from the module interfaces, which
+        // must be compiled and linked like any other code.
+        for bmi in &module_bmis {
+            builder.file(bmi);
+        }
         for include_cpp in parsed_file.get_cpp_buildables() {
             let generated_code = include_cpp
                 .generate_h_and_cxx(&self.codegen_options.cpp_codegen_options)
@@ -294,10 +452,23 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
             generated_rs.push(write_to_file(&rsdir, &rs.filename, rs.code.as_bytes())?);
         }
         if counter == 0 {
-            Err(BuilderError::NoIncludeCxxMacrosFound)
-        } else {
-            Ok(BuilderSuccess(builder, generated_rs, generated_cpp))
+            return Err(BuilderError::NoIncludeCxxMacrosFound);
         }
+        if !self.symbol_check_libraries.is_empty() {
+            let requested_items: Vec<String> = parsed_file
+                .get_autocxxes()
+                .flat_map(|include_cpp| include_cpp.config.must_generate_list())
+                .collect();
+            let objdir = cxxdir.join("symbol_check");
+            ensure_created(&objdir)?;
+            verify_symbols(
+                &builder,
+                &self.symbol_check_libraries,
+                &requested_items,
+                &objdir,
+            )?;
+        }
+        Ok(BuilderSuccess(builder, generated_rs, generated_cpp))
     }
 }
 
@@ -306,6 +477,96 @@ fn ensure_created(dir: &Path) -> Result<(), BuilderError> {
         .map_err(|e| BuilderError::UnableToCreateDirectory(e, dir.to_path_buf()))
 }
 
+/// Check that the compiler we'd use to precompile modules is clang, since
+/// the way BMIs are made and used is specific to each compiler. `CXX` may
+/// well name gcc, for instance.
+fn check_compiler_is_clang(compiler: &str, module_name: &str) -> Result<(), BuilderError> {
+    let output = Command::new(compiler)
+        .arg("--version")
+        .output()
+        .map_err(|e| {
+            BuilderError::ModulePrecompileFailed(module_name.to_string(), e.to_string())
+        })?;
+    if is_clang_version(&String::from_utf8_lossy(&output.stdout)) {
+        Ok(())
+    } else {
+        Err(BuilderError::ModulesNeedClang(compiler.to_string()))
+    }
+}
+
+/// Whether the output of `--version` is from clang, including Apple's
+/// and other vendors' builds of it.
+fn is_clang_version(version: &str) -> bool {
+    version
+        .lines()
+        .next()
+        .is_some_and(|first_line| first_line.contains("clang version"))
+}
+
+/// Precompile a module interface unit into a BMI within the given directory.
+fn precompile_module(
+    module_name: &str,
+    interface: &Path,
+    incs: &[PathBuf],
+    clang_args: &[&str],
+    outdir: &Path,
+) -> Result<PathBuf, BuilderError> {
+    let clang = get_clang_path();
+    check_compiler_is_clang(&clang, module_name)?;
+    let bmi = outdir.join(format!("{module_name}.pcm"));
+    let output = Command::new(clang)
+        .arg("-std=c++20")
+        .args(incs.iter().map(|inc| format!("-I{}", inc.display())))
+        .args(clang_args)
+        .arg("--precompile")
+        .arg("-x")
+        .arg("c++-module")
+        .arg(interface)
+        .arg("-o")
+        .arg(&bmi)
+        .output()
+        .map_err(|e| {
+            BuilderError::ModulePrecompileFailed(module_name.to_string(), e.to_string())
+        })?;
+    if !output.status.success() {
+        return Err(BuilderError::ModulePrecompileFailed(
+            module_name.to_string(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    Ok(bmi)
+}
+
+/// Compile the generated code to object files, then check that everything
+/// those objects need from the requested C++ items is defined somewhere.
+fn verify_symbols(
+    builder: &cc::Build,
+    libraries: &[PathBuf],
+    requested_items: &[String],
+    objdir: &Path,
+) -> Result<(), BuilderError> {
+    let objects = builder
+        .clone()
+        .out_dir(objdir)
+        .try_compile_intermediates()
+        .map_err(|e| BuilderError::SymbolCheckFailed(e.to_string()))?;
+    let mut defined = HashSet::new();
+    let mut undefined = Vec::new();
+    for object in &objects {
+        defined.extend(defined_symbols(object)?);
+        undefined.extend(undefined_symbols(object)?);
+    }
+    for library in libraries {
+        defined.extend(defined_symbols(library)?);
+    }
+    let missing = find_missing_symbols(&undefined, &defined, requested_items);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(BuilderError::MissingSymbols(missing))
+    }
+}
+
 fn build_autocxx_inc<I, T>(paths: I, extra_path: &Path) -> Vec<PathBuf>
 where
     I: IntoIterator<Item = T>,
@@ -341,3 +602,23 @@ fn rust_version_check() {
         panic!("Rust 1.54 or later is required.")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_clang_version;
+
+    #[test]
+    fn test_is_clang_version() {
+        assert!(is_clang_version(
+            "clang version 17.0.6\nTarget: x86_64-pc-linux-gnu\nThread model: posix\n"
+        ));
+        assert!(is_clang_version(
+            "Apple clang version 15.0.0 (clang-1500.3.9.4)\nTarget: arm64-apple-darwin23.4.0\n"
+        ));
+        assert!(is_clang_version("Ubuntu clang version 14.0.0-1ubuntu1.1\n"));
+        assert!(!is_clang_version(
+            "g++ (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0\nCopyright (C) 2021 Free Software Foundation, Inc.\n"
+        ));
+        assert!(!is_clang_version(""));
+    }
+}
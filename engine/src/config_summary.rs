@@ -0,0 +1,109 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::PathBuf;
+
+use autocxx_parser::{AppliedDirective, IncludeCppConfig, UnsafePolicy};
+
+/// The effective configuration of one `include_cpp!`, once all its
+/// directives, including those read from config files, have been applied,
+/// and defaults filled in for anything they didn't mention. This lets
+/// build scripts and other tooling check the configuration without
+/// parsing the macro themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSummary {
+    /// The name of the generated mod, `ffi` unless `name!` was given.
+    pub mod_name: String,
+    /// The headers to be `#include`d, in order.
+    pub inclusions: Vec<String>,
+    /// The policy chosen by `safety!`, or the default of requiring
+    /// `unsafe` for all functions.
+    pub unsafe_policy: UnsafePolicy,
+    /// The C++ standard chosen by `cpp_std!`, if any.
+    pub cpp_std: Option<String>,
+    /// Whether `generate_all!` was given, rather than a list of items.
+    pub generate_all: bool,
+    /// Whether `exclude_utilities!` was given.
+    pub exclude_utilities: bool,
+    /// Whether `exclude_impls!` was given.
+    pub exclude_impls: bool,
+    /// Whether `parse_only!` was given, so no bindings are generated.
+    pub parse_only: bool,
+    /// The files read by `config_file!`, resolved relative to the crate.
+    pub config_files: Vec<PathBuf>,
+    /// Every directive applied, in order, with where it came from.
+    pub directives: Vec<AppliedDirective>,
+}
+
+impl ConfigSummary {
+    pub(crate) fn new(config: &IncludeCppConfig) -> Self {
+        Self {
+            mod_name: config.get_mod_name().to_string(),
+            inclusions: config.inclusions.clone(),
+            unsafe_policy: config.unsafe_policy.clone(),
+            cpp_std: config.get_cpp_std().map(str::to_string),
+            generate_all: config.is_allowlist_all(),
+            exclude_utilities: config.exclude_utilities(),
+            exclude_impls: config.exclude_impls,
+            parse_only: config.parse_only,
+            config_files: config.get_config_files().collect(),
+            directives: config.get_applied_directives().to_vec(),
+        }
+    }
+
+    /// The applied directives with the given name, e.g. `generate`.
+    pub fn directives_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a AppliedDirective> + 'a {
+        self.directives
+            .iter()
+            .filter(move |directive| directive.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autocxx_parser::UnsafePolicy;
+
+    use crate::IncludeCppEngine;
+
+    #[test]
+    fn test_config_summary() {
+        let engine: IncludeCppEngine = syn::parse_str(
+            r#"
+            #include "foo.h"
+            safety!(unsafe_ffi)
+            generate!("ns::Foo")
+            generate_pod!("ns::Bar")
+            "#,
+        )
+        .unwrap();
+        let summary = engine.config_summary();
+        assert_eq!(summary.mod_name, "ffi");
+        assert_eq!(summary.inclusions, vec!["foo.h"]);
+        assert_eq!(summary.unsafe_policy, UnsafePolicy::AllFunctionsSafe);
+        assert!(!summary.generate_all);
+        assert!(summary.config_files.is_empty());
+        let names: Vec<_> = summary
+            .directives
+            .iter()
+            .map(|directive| directive.name.as_str())
+            .collect();
+        assert_eq!(names, ["include", "safety", "generate", "generate_pod"]);
+        let generated: Vec<_> = summary
+            .directives_named("generate")
+            .map(|directive| directive.args.as_str())
+            .collect();
+        assert_eq!(generated, ["\"ns::Foo\""]);
+        assert!(summary
+            .directives
+            .iter()
+            .all(|directive| directive.config_file.is_none()));
+    }
+}
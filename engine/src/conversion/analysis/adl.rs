@@ -0,0 +1,134 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! C++ code calls `swap(a, b)` and free operators such as `a + b`
+//! unqualified, relying upon argument-dependent lookup to find the right
+//! overload, which may well be declared in a different namespace from the
+//! type itself. Rust has no such lookup, so such functions are hard to
+//! discover as loose functions in some other mod. Here we spot them so that
+//! codegen can also expose them as methods on the type of their first
+//! parameter.
+//!
+//! C++ would complain of ambiguity if two such overloads were equally good
+//! matches; we don't attempt overload resolution, so we expose only the
+//! first `swap`, or the first of each operator, found for any given type.
+//! The others remain available as free functions.
+
+use indexmap::set::IndexSet as HashSet;
+use syn::Type;
+
+use crate::{
+    conversion::{
+        api::{Api, Provenance},
+        apivec::ApiVec,
+    },
+    types::QualifiedName,
+};
+
+use super::fun::{ArgumentAnalysis, FnKind, FnPhase, ReceiverMutability};
+
+pub(crate) fn associate_adl_functions(apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
+    let structs: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let existing_methods: HashSet<(QualifiedName, String)> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Function { analysis, .. } => match &analysis.kind {
+                FnKind::Method { impl_for, .. } => {
+                    Some((impl_for.clone(), analysis.rust_name.clone()))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    // The C++ names of the functions we've already exposed on each type.
+    let mut claimed: HashSet<(QualifiedName, String)> = HashSet::new();
+    let mut results = ApiVec::new();
+    results.extend(apis.into_iter().map(|mut api| {
+        if let Api::Function {
+            name,
+            fun,
+            analysis,
+        } = &mut api
+        {
+            let cpp_name = name.cpp_name().to_string_for_cpp_generation();
+            if matches!(analysis.kind, FnKind::Function)
+                && matches!(fun.provenance, Provenance::Bindgen)
+                && analysis.ignore_reason.is_ok()
+                && analysis.externally_callable
+            {
+                analysis.adl_method_for = adl_receiver(&cpp_name, &analysis.param_details)
+                    .filter(|(ty, _)| structs.contains(*ty))
+                    .filter(|(ty, _)| {
+                        !existing_methods.contains(&((*ty).clone(), analysis.rust_name.clone()))
+                    })
+                    .filter(|(ty, _)| claimed.insert(((*ty).clone(), cpp_name.clone())))
+                    .map(|(ty, mutability)| (ty.clone(), mutability));
+            }
+        }
+        api
+    }));
+    results
+}
+
+/// If this is a function which C++ would find by argument-dependent lookup,
+/// the type on which to expose it as a method, and how that method should
+/// take `self`.
+fn adl_receiver<'a>(
+    cpp_name: &str,
+    param_details: &'a [ArgumentAnalysis],
+) -> Option<(&'a QualifiedName, ReceiverMutability)> {
+    if cpp_name == "swap" {
+        swapped_type(param_details).map(|ty| (ty, ReceiverMutability::Mutable))
+    } else if cpp_name.starts_with("operator") {
+        operand_type(param_details)
+    } else {
+        None
+    }
+}
+
+/// If these are the parameters of a `swap(T&, T&)` function, return `T`.
+fn swapped_type(param_details: &[ArgumentAnalysis]) -> Option<&QualifiedName> {
+    match param_details {
+        [a, b] if a.is_mutable_reference && b.is_mutable_reference => {
+            let ty = single_dep(a)?;
+            (single_dep(b)? == ty).then_some(ty)
+        }
+        _ => None,
+    }
+}
+
+/// If the first of these parameters is a reference to a type, return that
+/// type. Operators taking their left hand side by value aren't exposed as
+/// methods, since that would consume the receiver.
+fn operand_type(
+    param_details: &[ArgumentAnalysis],
+) -> Option<(&QualifiedName, ReceiverMutability)> {
+    let first = param_details.first()?;
+    let mutability = if first.is_mutable_reference {
+        ReceiverMutability::Mutable
+    } else if matches!(first.conversion.cxxbridge_type(), Type::Reference(_)) {
+        ReceiverMutability::Const
+    } else {
+        return None;
+    };
+    Some((single_dep(first)?, mutability))
+}
+
+fn single_dep(param: &ArgumentAnalysis) -> Option<&QualifiedName> {
+    match param.deps.len() {
+        1 => param.deps.iter().next(),
+        _ => None,
+    }
+}
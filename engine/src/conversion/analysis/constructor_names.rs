@@ -0,0 +1,62 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::conversion::{
+    api::Api, apivec::ApiVec, convert_error::LocatedConvertErrorFromCpp, ConvertError,
+    ConvertErrorFromCpp,
+};
+
+use super::fun::{FnAnalysis, FnKind, FnPrePhase2, MethodKind};
+
+/// Fail if a `name_constructor!` directive didn't match any constructor,
+/// which usually means the constructor's signature has changed in the
+/// header. Otherwise code which used the pinned name would silently
+/// start calling whichever constructor now gets the default name.
+pub(crate) fn check_constructor_names(
+    apis: &ApiVec<FnPrePhase2>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+    warnings: &mut Vec<ConvertError>,
+) -> Result<(), ConvertError> {
+    for (signature, name) in config.get_constructor_names() {
+        let self_ty = signature
+            .split_once('(')
+            .map(|(self_ty, _)| self_ty.trim())
+            .unwrap_or(signature);
+        let found = apis.iter().any(|api| {
+            matches!(api, Api::Function {
+                analysis:
+                    FnAnalysis {
+                        kind:
+                            FnKind::Method {
+                                impl_for,
+                                method_kind: MethodKind::Constructor { .. },
+                            },
+                        rust_name,
+                        ..
+                    },
+                ..
+            } if rust_name == name && impl_for.to_cpp_name() == self_ty)
+        });
+        if !found {
+            let err = ConvertErrorFromCpp::ConstructorNotFound(signature.to_string());
+            match config.get_directive_span(signature) {
+                Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                    err,
+                    &span,
+                    source_file_contents,
+                )),
+                None => ConvertError::Cpp(err),
+            }
+            .for_directive_miss(config, warnings)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen doesn't tell us about conversion operators, so for types
+//! listed in `operator_bool!` or `conversion_operator!` directives we
+//! synthesize methods which perform the conversion in C++.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    synthetic_methods::create_synthetic_method,
+};
+
+pub(crate) fn add_conversion_operator_methods(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { name, .. } = &api {
+            let cpp_name = name.name.to_cpp_name();
+            if config.has_operator_bool(&cpp_name) {
+                results.push(create_conversion_method(
+                    &name.name,
+                    "is_truthy".into(),
+                    "bool".into(),
+                    false,
+                ));
+            }
+            for conversion in config.get_conversion_operators(&cpp_name) {
+                results.push(create_conversion_method(
+                    &name.name,
+                    conversion_method_name(&conversion.to),
+                    conversion.to.clone(),
+                    conversion.impl_from,
+                ));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+/// Make a method name such as `to_string` or `to_unsigned_int` from the
+/// final segment of a C++ type name.
+fn conversion_method_name(cpp_type: &str) -> String {
+    let final_segment = cpp_type.rsplit("::").next().unwrap_or(cpp_type);
+    let mut name = "to".to_string();
+    let mut previous_was_lower = false;
+    for c in final_segment.chars() {
+        if c == ' ' {
+            name.push('_');
+            previous_was_lower = false;
+            continue;
+        }
+        if name == "to" || (c.is_ascii_uppercase() && previous_was_lower) {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+        previous_was_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    name
+}
+
+fn create_conversion_method(
+    ty_name: &QualifiedName,
+    method_name: String,
+    to: String,
+    impl_from: bool,
+) -> Api<PodPhase> {
+    let output_type = QualifiedName::new_from_cpp_name(&to).to_type_path();
+    create_synthetic_method(
+        ty_name,
+        &method_name,
+        Some(format!(
+            "Converts this to `{to}` using its C++ conversion operator."
+        )),
+        CppFunctionKind::Method,
+        parse_quote! { -> #output_type },
+        CppFunctionBody::ConversionOperator { to, impl_from },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::conversion_method_name;
+
+    #[test]
+    fn test_conversion_method_name() {
+        assert_eq!(conversion_method_name("int"), "to_int");
+        assert_eq!(conversion_method_name("unsigned int"), "to_unsigned_int");
+        assert_eq!(conversion_method_name("std::string"), "to_string");
+        assert_eq!(conversion_method_name("ns::FooBar"), "to_foo_bar");
+        assert_eq!(conversion_method_name("uint32_t"), "to_uint32_t");
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::conversion::{
+    api::Api, apivec::ApiVec, convert_error::LocatedConvertErrorFromCpp, ConvertError,
+    ConvertErrorFromCpp,
+};
+
+use super::{fun::FnPrePhase2, not_generated::function_directive_name};
+
+/// Report any `covariant_return!` directive naming a method or type which
+/// doesn't exist. Otherwise subclasses would quietly go back to returning
+/// the superclass's type.
+pub(crate) fn check_covariant_returns(
+    apis: &ApiVec<FnPrePhase2>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+    warnings: &mut Vec<ConvertError>,
+) -> Result<(), ConvertError> {
+    for (method, derived) in config.get_covariant_returns() {
+        let method_found = apis.iter().any(|api| {
+            matches!(api, Api::Function { name, fun, .. }
+                if function_directive_name(name, fun) == method)
+        });
+        let missing = if !method_found {
+            method
+        } else if !apis.iter().any(|api| api.name().to_cpp_name() == derived) {
+            derived
+        } else {
+            continue;
+        };
+        let err = ConvertErrorFromCpp::CovariantReturnNotFound(missing.to_string());
+        match config.get_directive_span(method) {
+            Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                err,
+                &span,
+                source_file_contents,
+            )),
+            None => ConvertError::Cpp(err),
+        }
+        .for_directive_miss(config, warnings)?;
+    }
+    Ok(())
+}
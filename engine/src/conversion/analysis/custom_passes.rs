@@ -0,0 +1,83 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, Provenance},
+        apivec::ApiVec,
+        error_reporter::convert_item_apis,
+        ConvertErrorFromCpp,
+    },
+    minisyn::Attribute,
+    plugins::{registered_passes, ApiEditor, ApiKind},
+};
+
+use super::fun::{FnKind, FnPhase};
+
+/// Run any [`crate::plugins::AnalysisPass`]es which downstream code has
+/// registered. APIs which a pass drops become ignored items, so that
+/// anything depending upon them is removed by the next phase.
+pub(crate) fn run_custom_passes(apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
+    let passes = registered_passes();
+    if passes.is_empty() {
+        return apis;
+    }
+    let mut results = ApiVec::new();
+    convert_item_apis(apis, &mut results, |mut api| {
+        let (cpp_name, kind) = match &api {
+            Api::Function {
+                name,
+                fun,
+                analysis,
+            } if matches!(fun.provenance, Provenance::Bindgen) => match &analysis.kind {
+                FnKind::Function => (name.qualified_cpp_name(), ApiKind::Function),
+                FnKind::Method { impl_for, .. } => (
+                    format!(
+                        "{}::{}",
+                        impl_for.to_cpp_name(),
+                        name.cpp_name().to_string_for_cpp_generation()
+                    ),
+                    ApiKind::Method,
+                ),
+                FnKind::TraitMethod { .. } => return Ok(Box::new(std::iter::once(api))),
+            },
+            Api::Struct { name, .. } => (name.qualified_cpp_name(), ApiKind::Struct),
+            Api::Enum { name, .. } => (name.qualified_cpp_name(), ApiKind::Enum),
+            _ => return Ok(Box::new(std::iter::once(api))),
+        };
+        let mut editor = ApiEditor {
+            cpp_name,
+            kind,
+            extra_docs: Vec::new(),
+            drop_reason: None,
+        };
+        for pass in &passes {
+            pass.visit(&mut editor);
+            if let Some(reason) = editor.drop_reason.take() {
+                return Err(ConvertErrorFromCpp::DroppedByAnalysisPass(
+                    pass.name().to_string(),
+                    reason,
+                ));
+            }
+        }
+        let doc_attrs = editor
+            .extra_docs
+            .iter()
+            .map(|doc| -> Attribute { Attribute(parse_quote! { #[doc = #doc] }) });
+        match &mut api {
+            Api::Function { fun, .. } => fun.doc_attrs.extend(doc_attrs),
+            Api::Struct { details, .. } => details.item.attrs.extend(doc_attrs.map(|a| a.0)),
+            Api::Enum { item, .. } => item.attrs.extend(doc_attrs.map(|a| a.0)),
+            _ => {}
+        }
+        Ok(Box::new(std::iter::once(api)))
+    });
+    results
+}
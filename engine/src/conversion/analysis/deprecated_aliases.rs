@@ -0,0 +1,68 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::{
+    conversion::{
+        api::Api, apivec::ApiVec, convert_error::LocatedConvertErrorFromCpp, ConvertError,
+        ConvertErrorFromCpp,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::fun::{FnAnalysis, FnKind, FnPrePhase2};
+
+/// Report any `deprecated_alias!` directive which didn't match anything
+/// we'll generate, most likely because the item was renamed again, so that
+/// downstream code doesn't lose the old name without warning.
+pub(crate) fn check_deprecated_aliases(
+    apis: &ApiVec<FnPrePhase2>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+    warnings: &mut Vec<ConvertError>,
+) -> Result<(), ConvertError> {
+    for (item, alias) in config.get_all_deprecated_aliases() {
+        let found = apis.iter().any(|api| match api {
+            Api::Function {
+                name,
+                analysis:
+                    FnAnalysis {
+                        kind,
+                        rust_name,
+                        deprecated_aliases,
+                        ..
+                    },
+                ..
+            } => {
+                let rust_item = match kind {
+                    FnKind::Method { impl_for, .. } => {
+                        format!("{}::{rust_name}", impl_for.to_cpp_name())
+                    }
+                    _ => QualifiedName::new(name.name.get_namespace(), make_ident(rust_name))
+                        .to_cpp_name(),
+                };
+                rust_item == item && deprecated_aliases.iter().any(|a| a == alias)
+            }
+            _ => api.name().to_cpp_name() == item,
+        });
+        if !found {
+            let err = ConvertErrorFromCpp::DeprecatedAliasNotFound(item.to_string());
+            match config.get_directive_span(item) {
+                Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                    err,
+                    &span,
+                    source_file_contents,
+                )),
+                None => ConvertError::Cpp(err),
+            }
+            .for_directive_miss(config, warnings)?;
+        }
+    }
+    Ok(())
+}
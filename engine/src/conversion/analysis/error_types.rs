@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Types listed in `error_type!` get an `error_message` method which
+//! copies their message (from `what()` or similar) into a `std::string`.
+//! Code generation uses that method to implement `Display` and
+//! `std::error::Error`.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    synthetic_methods::create_synthetic_method,
+};
+
+pub(crate) fn add_error_message_methods(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { name, .. } = &api {
+            if let Some(method) = config.get_error_message_method(&name.name.to_cpp_name()) {
+                results.push(create_error_message_method(&name.name, method));
+            }
+        }
+        results.push(api);
+    }
+    results
+}
+
+fn create_error_message_method(ty_name: &QualifiedName, method: &str) -> Api<PodPhase> {
+    let string_type = QualifiedName::new_from_cpp_name("std::string").to_type_path();
+    create_synthetic_method(
+        ty_name,
+        "error_message",
+        Some(format!(
+            "The message of this error, from its C++ `{method}()` method."
+        )),
+        CppFunctionKind::Method,
+        parse_quote! { -> #string_type },
+        CppFunctionBody::ErrorMessage(method.to_string()),
+    )
+}
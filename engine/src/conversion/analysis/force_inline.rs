@@ -0,0 +1,46 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::conversion::{
+    api::Api, apivec::ApiVec, convert_error::LocatedConvertErrorFromCpp, ConvertError,
+    ConvertErrorFromCpp,
+};
+
+use super::{fun::FnPrePhase2, not_generated::function_directive_name};
+
+/// Report any `force_inline!` directive which didn't match a function or
+/// method, so that a typo or header change doesn't silently leave a hot
+/// wrapper out of line.
+pub(crate) fn check_force_inline(
+    apis: &ApiVec<FnPrePhase2>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+    warnings: &mut Vec<ConvertError>,
+) -> Result<(), ConvertError> {
+    for function in config.get_force_inline_functions() {
+        let found = apis.iter().any(|api| {
+            matches!(api, Api::Function { name, fun, .. }
+                if function_directive_name(name, fun) == function)
+        });
+        if !found {
+            let err = ConvertErrorFromCpp::ForceInlineNotFound(function.to_string());
+            match config.get_directive_span(function) {
+                Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                    err,
+                    &span,
+                    source_file_contents,
+                )),
+                None => ConvertError::Cpp(err),
+            }
+            .for_directive_miss(config, warnings)?;
+        }
+    }
+    Ok(())
+}
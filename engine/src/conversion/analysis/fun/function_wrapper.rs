@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::conversion::CppEffectiveName;
+use crate::conversion::{ConvertErrorFromCpp, CppEffectiveName};
 use crate::minisyn::Ident;
 use crate::{
     conversion::{api::SubclassName, type_helpers::extract_pinned_mutable_reference_type},
@@ -28,6 +28,17 @@ pub(crate) enum CppConversionType {
     FromReturnValueToPlacementPtr,
     FromPointerToReference, // unwrapped_type is always Type::Ptr
     FromReferenceToPointer, // unwrapped_type is always Type::Ptr
+    FromStdArrayToCArray,   // unwrapped_type is always a reference to Type::Array
+    FromCArrayToStdArray,   // unwrapped_type is always a reference to Type::Array
+    FromU8ToStdByte,        // unwrapped_type involves u8 where C++ has std::byte
+    FromStdByteToU8,        // unwrapped_type involves u8 where C++ has std::byte
+    /// Wrap the address of a Rust writer in a temporary `std::ostream` of
+    /// this adapter class. See `ostream_as_writer!`.
+    FromWriterToOstream(String),
+    /// Pass the start and end of a slice to a function expecting a pair of
+    /// iterators. See `iterator_range!`. unwrapped_type is always a
+    /// reference to a Type::Slice.
+    FromSliceToIteratorPair,
 }
 
 impl CppConversionType {
@@ -43,6 +54,10 @@ impl CppConversionType {
             CppConversionType::FromValueToUniquePtr => CppConversionType::FromUniquePtrToValue,
             CppConversionType::FromPointerToReference => CppConversionType::FromReferenceToPointer,
             CppConversionType::FromReferenceToPointer => CppConversionType::FromPointerToReference,
+            CppConversionType::FromStdArrayToCArray => CppConversionType::FromCArrayToStdArray,
+            CppConversionType::FromCArrayToStdArray => CppConversionType::FromStdArrayToCArray,
+            CppConversionType::FromU8ToStdByte => CppConversionType::FromStdByteToU8,
+            CppConversionType::FromStdByteToU8 => CppConversionType::FromU8ToStdByte,
             _ => panic!("Did not expect to have to invert this conversion"),
         }
     }
@@ -61,6 +76,8 @@ pub(crate) enum RustConversionType {
     FromRValueParamToPtr,
     FromReferenceWrapperToPointer, // unwrapped_type is always Type::Ptr
     FromPointerToReferenceWrapper, // unwrapped_type is always Type::Ptr
+    ToOptionalUniquePtr,           // unwrapped_type is a UniquePtr or Type::Ptr
+    FromWriterToAddress,           // unwrapped_type is always usize
 }
 
 impl RustConversionType {
@@ -93,6 +110,9 @@ pub(crate) struct TypeConversionPolicy {
     unwrapped_type: crate::minisyn::Type,
     pub(crate) cpp_conversion: CppConversionType,
     pub(crate) rust_conversion: RustConversionType,
+    /// Whether C++ has a `std::pmr` container where cxx has its `std`
+    /// equivalent, so that the C++ wrapper must copy between the two.
+    pub(crate) copies_pmr: bool,
 }
 
 impl TypeConversionPolicy {
@@ -109,6 +129,7 @@ impl TypeConversionPolicy {
             unwrapped_type: ty.into(),
             cpp_conversion,
             rust_conversion,
+            copies_pmr: false,
         }
     }
 
@@ -138,6 +159,7 @@ impl TypeConversionPolicy {
             },
             cpp_conversion: CppConversionType::FromReferenceToPointer,
             rust_conversion: RustConversionType::FromPointerToReferenceWrapper,
+            copies_pmr: false,
         }
     }
 
@@ -146,6 +168,7 @@ impl TypeConversionPolicy {
             unwrapped_type: ty.into(),
             cpp_conversion: CppConversionType::FromValueToUniquePtr,
             rust_conversion: RustConversionType::None,
+            copies_pmr: false,
         }
     }
 
@@ -157,11 +180,100 @@ impl TypeConversionPolicy {
             // will be applied to the return value, and the Rust-side
             // shenanigans applies to the placement new *parameter*
             rust_conversion: RustConversionType::None,
+            copies_pmr: false,
         }
     }
 
+    /// For a returned `UniquePtr`, or raw pointer which conveys ownership,
+    /// give Rust an `Option<UniquePtr>` which is `None` if it was null.
+    pub(crate) fn into_optional_unique_ptr(self) -> Self {
+        Self {
+            rust_conversion: RustConversionType::ToOptionalUniquePtr,
+            ..self
+        }
+    }
+
+    /// `std::byte` is presented to Rust as `u8`, so the C++ wrapper must
+    /// cast between the two. We can only do that for types we'd otherwise
+    /// pass straight through. A container of `std::byte` can't be cast
+    /// at all, so it has to be copied, which we only do for parameters
+    /// taken by `const` reference.
+    pub(crate) fn cast_std_bytes(
+        self,
+        cast: CppConversionType,
+    ) -> Result<Self, ConvertErrorFromCpp> {
+        let castable = match self.cpp_conversion {
+            CppConversionType::None => {
+                !self.copies_std_bytes()
+                    || (matches!(cast, CppConversionType::FromU8ToStdByte)
+                        && matches!(
+                            self.cxxbridge_type(),
+                            Type::Reference(TypeReference {
+                                mutability: None,
+                                ..
+                            })
+                        ))
+            }
+            _ => false,
+        };
+        if !castable {
+            return Err(ConvertErrorFromCpp::UnsupportedStdByteType(
+                self.unwrapped_type.to_token_stream().to_string(),
+            ));
+        }
+        Ok(Self {
+            cpp_conversion: cast,
+            ..self
+        })
+    }
+
+    /// Whether this casts to or from `std::byte` within some container,
+    /// e.g. a `std::vector<std::byte>`, rather than a `std::byte` itself
+    /// or a pointer or reference to one.
+    pub(crate) fn copies_std_bytes(&self) -> bool {
+        fn is_u8(ty: &Type) -> bool {
+            matches!(ty, Type::Path(tp) if tp.path.is_ident("u8"))
+        }
+        let ty = self.cxxbridge_type();
+        let is_byte_itself = match ty {
+            Type::Ptr(tp) => is_u8(&tp.elem),
+            Type::Reference(tr) => is_u8(&tr.elem),
+            Type::Path(tp) => {
+                is_u8(ty) || extract_pinned_mutable_reference_type(tp).is_some_and(is_u8)
+            }
+            _ => false,
+        };
+        !is_byte_itself
+    }
+
+    /// `std::pmr` containers are presented to Rust as their `std`
+    /// equivalents, so the C++ wrapper must copy between the two. We can
+    /// only do that for values and `const` references.
+    pub(crate) fn copy_pmr_container(
+        self,
+        is_const_reference: bool,
+    ) -> Result<Self, ConvertErrorFromCpp> {
+        match self.cpp_conversion {
+            CppConversionType::None if is_const_reference => {}
+            CppConversionType::FromUniquePtrToValue
+            | CppConversionType::FromPtrToValue
+            | CppConversionType::FromPtrToMove
+            | CppConversionType::FromValueToUniquePtr
+            | CppConversionType::FromReturnValueToPlacementPtr => {}
+            _ => {
+                return Err(ConvertErrorFromCpp::UnsupportedPmrType(
+                    self.unwrapped_type.to_token_stream().to_string(),
+                ))
+            }
+        }
+        Ok(Self {
+            copies_pmr: true,
+            ..self
+        })
+    }
+
     pub(crate) fn cpp_work_needed(&self) -> bool {
-        !matches!(self.cpp_conversion, CppConversionType::None)
+        !matches!(self.cpp_conversion, CppConversionType::None) || self.copies_pmr
     }
 
     pub(crate) fn unconverted_rust_type(&self) -> Type {
@@ -203,6 +315,9 @@ impl TypeConversionPolicy {
             unwrapped_type: self.unwrapped_type.clone(),
             cpp_conversion: self.cpp_conversion.inverse(),
             rust_conversion: self.rust_conversion.clone(),
+            // We don't yet copy `std::pmr` containers in calls from C++
+            // into Rust.
+            copies_pmr: false,
         }
     }
 
@@ -214,6 +329,7 @@ impl TypeConversionPolicy {
                 | RustConversionType::FromPlacementParamToNewReturn
                 | RustConversionType::FromPointerToReferenceWrapper { .. }
                 | RustConversionType::FromReferenceWrapperToPointer { .. }
+                | RustConversionType::FromWriterToAddress
         )
     }
 
@@ -235,7 +351,42 @@ impl TypeConversionPolicy {
 #[derive(Clone, Debug)]
 pub(crate) enum CppFunctionBody {
     FunctionCall(Namespace, CppEffectiveName),
+    /// A call to a non-const method via a receiver which we've
+    /// pretended is const; we need to `const_cast` it back.
+    ConstCastMethodCall(QualifiedName, CppEffectiveName),
     StaticMethodCall(Namespace, Ident, CppEffectiveName),
+    /// Access a (possibly nested) data member of the receiver,
+    /// optionally casting it to some other type.
+    MemberAccess {
+        member: String,
+        cast_to: Option<&'static str>,
+    },
+    /// Convert the receiver to another type using its (possibly explicit)
+    /// conversion operator. If `impl_from` is set, we'll also implement
+    /// `From` in Rust.
+    ConversionOperator {
+        to: String,
+        impl_from: bool,
+    },
+    /// Return the name of the dynamic type of the receiver, from
+    /// `typeid`.
+    TypeName,
+    /// Return the address of the most-derived object of which the receiver
+    /// is part, from `dynamic_cast<const void*>`.
+    ObjectIdentity,
+    /// Copy the receiver's message, from the given method, into a
+    /// `std::string`. See `error_type!`.
+    ErrorMessage(String),
+    /// Return a value-initialized instance of the type. See `pod_default!`.
+    ValueInitialize(QualifiedName),
+    /// Return a reference to a const global. See `parse_foreign_mod`.
+    GlobalAccessor {
+        /// The global's name in C++.
+        cpp_name: String,
+        /// The name by which it's found in the allowlist: that of its
+        /// class, if it's a static member.
+        allowlisted_as: QualifiedName,
+    },
     PlacementNew(Namespace, Ident),
     ConstructSuperclass(String),
     Cast,
@@ -265,4 +416,6 @@ pub(crate) struct CppFunction {
     pub(crate) kind: CppFunctionKind,
     pub(crate) pass_obs_field: bool,
     pub(crate) qualification: Option<QualifiedName>,
+    /// Whether the user asked for this wrapper to be always inlined.
+    pub(crate) force_inline: bool,
 }
@@ -0,0 +1,196 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! With `iterator_range!`, a C++ function taking a `(begin, end)` pair of
+//! pointers is presented to Rust as taking a single slice. The C++ wrapper
+//! function passes the start and end of the slice's data. Iterator classes
+//! such as `std::vector<T>::iterator`, and references to containers,
+//! aren't supported.
+
+use autocxx_parser::IncludeCppConfig;
+use itertools::Itertools;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, Type, TypePtr};
+
+use crate::{
+    conversion::{
+        analysis::not_generated::function_directive_name,
+        api::{Api, FuncToConvert, UnsafetyNeeded},
+        apivec::ApiVec,
+        convert_error::LocatedConvertErrorFromCpp,
+        ConvertError, ConvertErrorFromCpp,
+    },
+    minisyn::FnArg,
+    types::QualifiedName,
+};
+
+use super::{
+    function_wrapper::{CppConversionType, RustConversionType, TypeConversionPolicy},
+    ArgumentAnalysis, FnAnalyzer, FnPrePhase2,
+};
+
+impl FnAnalyzer<'_> {
+    /// Replace the pointer parameters at `begin` and `begin + 1` with a
+    /// single slice parameter. As in the directive, `begin` doesn't count
+    /// any receiver. Returns whether they were replaced.
+    pub(super) fn combine_iterator_pair(
+        &self,
+        fun: &FuncToConvert,
+        params: &mut Punctuated<FnArg, Comma>,
+        param_details: &mut Vec<ArgumentAnalysis>,
+        begin: usize,
+    ) -> Result<bool, ConvertErrorFromCpp> {
+        if params.len() != fun.inputs.len() {
+            // Some argument couldn't be converted at all, e.g. because it's
+            // an iterator class. That's already been reported.
+            return Ok(false);
+        }
+        let unsupported = || ConvertErrorFromCpp::UnsupportedIteratorRange(begin, begin + 1);
+        if fun.virtualness.is_some() {
+            return Err(unsupported());
+        }
+        let receiver_count = param_details
+            .iter()
+            .filter(|pd| pd.self_type.is_some())
+            .count();
+        let (Some(begin_details), Some(end_details)) = (
+            param_details.get(begin + receiver_count),
+            param_details.get(begin + receiver_count + 1),
+        ) else {
+            return Err(unsupported());
+        };
+        for (idx, details) in [(begin, begin_details), (begin + 1, end_details)] {
+            match details.conversion.cxxbridge_type() {
+                Type::Ptr(_) => {}
+                Type::Reference(_) => {
+                    return Err(ConvertErrorFromCpp::IteratorRangeNotPointer(
+                        idx,
+                        "a reference, for example to a container".into(),
+                    ))
+                }
+                _ => {
+                    return Err(ConvertErrorFromCpp::IteratorRangeNotPointer(
+                        idx,
+                        "passed by value, for example an iterator class".into(),
+                    ))
+                }
+            }
+        }
+        let begin = begin + receiver_count;
+        let slice_ty: Type = match (
+            begin_details.conversion.cxxbridge_type(),
+            end_details.conversion.cxxbridge_type(),
+        ) {
+            (
+                Type::Ptr(TypePtr {
+                    mutability: begin_mutability,
+                    elem: begin_elem,
+                    ..
+                }),
+                Type::Ptr(TypePtr {
+                    mutability: end_mutability,
+                    elem: end_elem,
+                    ..
+                }),
+            ) if begin_mutability.is_some() == end_mutability.is_some()
+                && begin_elem == end_elem
+                && self.is_pod_safe_elem(begin_elem) =>
+            {
+                if begin_mutability.is_some() {
+                    parse_quote! { &mut [#begin_elem] }
+                } else {
+                    parse_quote! { &[#begin_elem] }
+                }
+            }
+            _ => return Err(unsupported()),
+        };
+        let is_mutable_reference =
+            matches!(&slice_ty, Type::Reference(r) if r.mutability.is_some());
+
+        let mut new_params: Punctuated<FnArg, Comma> = Punctuated::new();
+        for (idx, mut param) in params.clone().into_iter().enumerate() {
+            if idx == begin + 1 {
+                continue;
+            }
+            if idx == begin {
+                if let syn::FnArg::Typed(pt) = &mut param.0 {
+                    pt.ty = Box::new(slice_ty.clone());
+                }
+            }
+            new_params.push(param);
+        }
+        *params = new_params;
+        param_details.remove(begin + 1);
+        let details = &mut param_details[begin];
+        details.conversion = TypeConversionPolicy::new(
+            slice_ty,
+            CppConversionType::FromSliceToIteratorPair,
+            RustConversionType::None,
+        );
+        details.has_lifetime = true;
+        details.is_mutable_reference = is_mutable_reference;
+        details.requires_unsafe = UnsafetyNeeded::None;
+        Ok(true)
+    }
+
+    /// cxx can only pass slices of types whose layout Rust knows.
+    fn is_pod_safe_elem(&self, elem: &Type) -> bool {
+        match elem {
+            Type::Path(tp) => self
+                .pod_safe_types
+                .contains(&QualifiedName::from_type_path(tp)),
+            _ => false,
+        }
+    }
+}
+
+/// Fail if nothing named by an `iterator_range!` directive could be
+/// generated. Otherwise, a function taking iterators which we can't turn
+/// into a slice would silently vanish.
+pub(crate) fn check_iterator_ranges(
+    apis: &ApiVec<FnPrePhase2>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+) -> Result<(), ConvertError> {
+    for function in config.get_iterator_range_functions() {
+        let outcomes: Vec<_> = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Function {
+                    name,
+                    fun,
+                    analysis,
+                } if function_directive_name(name, fun) == function => {
+                    Some(&analysis.ignore_reason)
+                }
+                _ => None,
+            })
+            .collect();
+        if outcomes.iter().any(|outcome| outcome.is_ok()) {
+            continue;
+        }
+        let reasons = if outcomes.is_empty() {
+            "no such function was found".to_string()
+        } else {
+            outcomes
+                .iter()
+                .filter_map(|outcome| outcome.as_ref().err())
+                .map(|err| err.0.to_string())
+                .join("; ")
+        };
+        let err = ConvertErrorFromCpp::IteratorRangeNotGenerated(function.to_string(), reasons);
+        return Err(match config.get_directive_span(function) {
+            Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                err,
+                &span,
+                source_file_contents,
+            )),
+            None => ConvertError::Cpp(err),
+        });
+    }
+    Ok(())
+}
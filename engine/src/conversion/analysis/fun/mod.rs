@@ -9,7 +9,9 @@
 mod bridge_name_tracker;
 pub(crate) mod function_wrapper;
 mod implicit_constructors;
+pub(crate) mod iterator_ranges;
 mod overload_tracker;
+pub(crate) mod small_pods;
 mod subclass;
 
 use crate::{
@@ -25,26 +27,36 @@ use crate::{
         apivec::ApiVec,
         convert_error::{ConvertErrorWithContext, ErrorContext, ErrorContextType},
         error_reporter::{convert_apis, report_any_error},
-        type_helpers::{type_is_reference, unwrap_has_opaque},
+        extern_rust_closures::has_boxed_fns,
+        ostream_writers::ostream_writer_names,
+        protobuf_messages::is_protobuf_runtime_method,
+        type_helpers::{
+            extract_array_reference_element_type, extract_pinned_mutable_reference_type,
+            find_pmr_use, type_is_ostream_reference, type_is_reference, type_mentions_std_byte,
+            unwrap_has_opaque, PmrUse,
+        },
         CppEffectiveName, CppOriginalName,
     },
     known_types::known_types,
     minisyn::{minisynize_punctuated, FnArg},
+    msvc_declarations::MsvcDeclarations,
+    plugins::{registered_name_transforms, ApiKind, NameToTransform, NameTransform},
     types::validate_ident_ok_for_rust,
 };
 use autocxx_bindgen::callbacks::Visibility as CppVisibility;
 use autocxx_bindgen::callbacks::{Explicitness, SpecialMemberKind, Virtualness};
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
+use std::sync::Arc;
 
 use autocxx_parser::{ExternCppType, IncludeCppConfig, UnsafePolicy};
 use function_wrapper::{CppFunction, CppFunctionBody, TypeConversionPolicy};
 use itertools::Itertools;
 use proc_macro2::Span;
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::{
-    parse_quote, punctuated::Punctuated, token::Comma, Ident, Pat, PatType, ReturnType, Type,
-    TypePath, TypePtr, TypeReference, Visibility,
+    parse_quote, punctuated::Punctuated, token::Comma, GenericArgument, Ident, Pat, PatType,
+    PathArguments, PathSegment, ReturnType, Type, TypePath, TypePtr, TypeReference, Visibility,
 };
 
 use crate::{
@@ -62,13 +74,14 @@ use self::{
     overload_tracker::OverloadTracker,
     subclass::{
         create_subclass_constructor, create_subclass_fn_wrapper, create_subclass_function,
-        create_subclass_trait_item,
+        create_subclass_trait_item, make_covariant,
     },
 };
 
 use super::{
     depth_first::HasFieldsAndBases,
     doc_label::make_doc_attrs,
+    not_generated::function_directive_name,
     pod::{PodAnalysis, PodPhase},
     tdef::TypedefAnalysis,
     type_converter::Annotated,
@@ -174,6 +187,52 @@ pub(crate) struct FnAnalysis {
     pub(crate) externally_callable: bool,
     /// Whether we need to generate a Rust-side calling function
     pub(crate) rust_wrapper_needed: bool,
+    /// Whether to generate an `async` wrapper which runs this
+    /// function on a blocking executor.
+    pub(crate) async_wrapper_needed: bool,
+    /// Whether this method returns a view type, whose Rust wrapper
+    /// should borrow from the receiver.
+    pub(crate) returns_view: bool,
+    /// The index within `param_details` of the parameter from which the
+    /// returned reference or view borrows, if `returns_lifetime_of_arg!`
+    /// names one in place of the usual rules.
+    pub(crate) returns_lifetime_of_arg: Option<usize>,
+    /// If overload handling, a name collision or an `overload_names!`
+    /// directive means this has a Rust name other than its natural one,
+    /// the C++ name from which it was derived.
+    pub(crate) renamed_from: Option<String>,
+    /// For free functions such as `swap` or operators which C++ would find
+    /// by argument-dependent lookup, the type on which we should also
+    /// expose this as a method, and how that method takes `self`.
+    pub(crate) adl_method_for: Option<(QualifiedName, ReceiverMutability)>,
+    /// The name of a flat `extern "C"` function to generate, calling
+    /// the C++ wrapper, as requested by `export_c!`.
+    pub(crate) c_export_name: Option<String>,
+    /// The name of a flat `extern "C"` function through which Rust should
+    /// call this, rather than through cxx, so that small POD types are
+    /// passed in registers. See `small_pods_in_registers!`.
+    pub(crate) c_abi_name: Option<String>,
+    /// Whether this is an accessor renamed by `getters!`, which should
+    /// therefore be `#[must_use]`.
+    pub(crate) is_getter: bool,
+    /// Whether the Rust wrapper should call `ensure_initialized()` first
+    /// in debug builds, as requested by `init_function!`.
+    pub(crate) ensure_initialized: bool,
+    /// The `thread_affine!` type of which this is a method, whose Rust
+    /// wrapper should check the calling thread in debug builds, or a
+    /// constructor, whose Rust wrapper should record it.
+    pub(crate) thread_affine_type: Option<QualifiedName>,
+    /// Old names from `deprecated_alias!` under which this should also be
+    /// generated, marked `#[deprecated]`.
+    pub(crate) deprecated_aliases: Vec<String>,
+    /// `get_`/`put_` names under which this method should also be
+    /// generated, because it's the accessor of a `__declspec(property)`
+    /// found by `msvc_extensions!`.
+    pub(crate) property_accessors: Vec<String>,
+    /// Whether the Rust wrapper should check for null `UniquePtr`s passed
+    /// by value or rvalue reference, and return a `Result` rather than
+    /// panicking. See `panic_free!`.
+    pub(crate) checks_null_params: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -254,6 +313,7 @@ pub(crate) struct FnPhase;
 #[derive(Debug, Default, Copy, Clone)]
 pub(crate) struct PublicConstructors {
     pub(crate) move_constructor: bool,
+    pub(crate) copy_constructor: bool,
     pub(crate) destructor: bool,
 }
 
@@ -261,6 +321,7 @@ impl PublicConstructors {
     fn from_items_found(items_found: &ItemsFound) -> Self {
         Self {
             move_constructor: items_found.move_constructor.callable_any(),
+            copy_constructor: items_found.const_copy_constructor.callable_any(),
             destructor: items_found.destructor.callable_any(),
         }
     }
@@ -287,6 +348,11 @@ pub(crate) struct FnAnalyzer<'a> {
     type_converter: TypeConverter<'a>,
     bridge_name_tracker: BridgeNameTracker,
     pod_safe_types: HashSet<QualifiedName>,
+    /// `export_c!` directives which name an overloaded function.
+    overloaded_c_exports: HashSet<String>,
+    /// POD types small enough to pass in registers, if
+    /// `small_pods_in_registers!` was given.
+    small_pod_types: HashSet<QualifiedName>,
     moveit_safe_types: HashSet<QualifiedName>,
     config: &'a IncludeCppConfig,
     overload_trackers_by_mod: HashMap<Namespace, OverloadTracker>,
@@ -296,6 +362,8 @@ pub(crate) struct FnAnalyzer<'a> {
     types_in_anonymous_namespace: HashSet<QualifiedName>,
     existing_superclass_trait_api_names: HashSet<QualifiedName>,
     force_wrapper_generation: bool,
+    msvc_declarations: &'a MsvcDeclarations,
+    name_transforms: Vec<Arc<dyn NameTransform>>,
 }
 
 impl<'a> FnAnalyzer<'a> {
@@ -304,6 +372,7 @@ impl<'a> FnAnalyzer<'a> {
         unsafe_policy: &'a UnsafePolicy,
         config: &'a IncludeCppConfig,
         force_wrapper_generation: bool,
+        msvc_declarations: &'a MsvcDeclarations,
     ) -> ApiVec<FnPrePhase2> {
         let mut me = Self {
             unsafe_policy,
@@ -312,7 +381,13 @@ impl<'a> FnAnalyzer<'a> {
             bridge_name_tracker: BridgeNameTracker::new(),
             config,
             overload_trackers_by_mod: HashMap::new(),
-            pod_safe_types: Self::build_pod_safe_type_set(&apis),
+            pod_safe_types: Self::build_pod_safe_type_set(config, &apis),
+            overloaded_c_exports: Self::build_overloaded_c_export_set(config, &apis),
+            small_pod_types: if config.small_pods_in_registers() {
+                small_pods::find_small_pod_types(&apis)
+            } else {
+                HashSet::new()
+            },
             moveit_safe_types: Self::build_correctly_sized_type_set(&apis),
             subclasses_by_superclass: subclass::subclasses_by_superclass(&apis),
             nested_type_name_map: Self::build_nested_type_map(&apis),
@@ -320,7 +395,10 @@ impl<'a> FnAnalyzer<'a> {
             existing_superclass_trait_api_names: HashSet::new(),
             types_in_anonymous_namespace: Self::build_types_in_anonymous_namespace(&apis),
             force_wrapper_generation,
+            msvc_declarations,
+            name_transforms: registered_name_transforms(),
         };
+        me.reserve_natural_names(&apis);
         let mut results = ApiVec::new();
         convert_apis(
             apis,
@@ -336,7 +414,50 @@ impl<'a> FnAnalyzer<'a> {
         results
     }
 
-    fn build_pod_safe_type_set(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    /// Tell the overload trackers the name each function and method would
+    /// naturally have, so that they don't hand it out to an overload of
+    /// something else which happens to be declared first.
+    fn reserve_natural_names(&mut self, apis: &ApiVec<PodPhase>) {
+        for api in apis.iter() {
+            let Api::Function { name, fun, .. } = api else {
+                continue;
+            };
+            if !matches!(fun.provenance, Provenance::Bindgen) {
+                continue;
+            }
+            let ns = name.name.get_namespace();
+            let natural_name = ideal_rust_name(name.cpp_name_if_present(), fun.ident.to_string());
+            match &fun.self_ty {
+                // A `constructor_from!` factory is always called `new`, and
+                // real constructors are numbered around it.
+                Some(self_ty)
+                    if self
+                        .config
+                        .is_constructor_factory(&function_directive_name(name, fun)) =>
+                {
+                    self.overload_trackers_by_mod
+                        .entry(ns.clone())
+                        .or_default()
+                        .claim_method_name(self_ty.get_final_item(), "new")
+                }
+                Some(self_ty) => self
+                    .overload_trackers_by_mod
+                    .entry(ns.clone())
+                    .or_default()
+                    .reserve_method_name(self_ty.get_final_item(), &natural_name),
+                None => self
+                    .overload_trackers_by_mod
+                    .entry(ns.without_anonymous())
+                    .or_default()
+                    .reserve_function_name(&natural_name),
+            }
+        }
+    }
+
+    fn build_pod_safe_type_set(
+        config: &IncludeCppConfig,
+        apis: &ApiVec<PodPhase>,
+    ) -> HashSet<QualifiedName> {
         apis.iter()
             .filter_map(|api| match api {
                 Api::Struct {
@@ -349,6 +470,9 @@ impl<'a> FnAnalyzer<'a> {
                 } => Some(api.name().clone()),
                 Api::Enum { .. } => Some(api.name().clone()),
                 Api::ExternCppType { pod: true, .. } => Some(api.name().clone()),
+                Api::Typedef { name, .. } if config.is_newtype(&name.name.to_cpp_name()) => {
+                    Some(api.name().clone())
+                }
                 _ => None,
             })
             .chain(
@@ -367,6 +491,26 @@ impl<'a> FnAnalyzer<'a> {
             .collect()
     }
 
+    /// Find the `export_c!` directives which name more than one function.
+    /// Each overload would need the same symbol, so we export none of them.
+    fn build_overloaded_c_export_set(
+        config: &IncludeCppConfig,
+        apis: &ApiVec<PodPhase>,
+    ) -> HashSet<String> {
+        apis.iter()
+            .filter_map(|api| match api {
+                Api::Function { name, fun, .. }
+                    if matches!(fun.provenance, Provenance::Bindgen) =>
+                {
+                    Some(function_directive_name(name, fun))
+                }
+                _ => None,
+            })
+            .filter(|directive_name| config.get_c_export_name(directive_name).is_some())
+            .duplicates()
+            .collect()
+    }
+
     /// Return the set of 'moveit safe' types. That must include only types where
     /// the size is known to be correct.
     fn build_correctly_sized_type_set(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
@@ -581,6 +725,18 @@ impl<'a> FnAnalyzer<'a> {
         name: ApiName,
         fun: Box<FuncToConvert>,
     ) -> Result<Box<dyn Iterator<Item = Api<FnPrePhase1>>>, ConvertErrorWithContext> {
+        if let Some(self_ty) = &fun.self_ty {
+            let ident = fun.ident.to_string();
+            let cpp_name = name
+                .cpp_name_if_present()
+                .map(|n| n.for_validation())
+                .unwrap_or(&ident);
+            if self.config.is_protobuf_message(&self_ty.to_cpp_name())
+                && is_protobuf_runtime_method(cpp_name, &fun)
+            {
+                return Ok(Box::new(std::iter::empty()));
+            }
+        }
         let (analysis, name) =
             self.analyze_foreign_fn(name, &fun, TypeConversionSophistication::Regular, None);
         let mut results = ApiVec::new();
@@ -599,6 +755,27 @@ impl<'a> FnAnalyzer<'a> {
                 TypeConversionSophistication::SimpleForSubclasses,
                 Some(analysis.rust_name.clone()),
             );
+            let ident = fun.ident.to_string();
+            let directive_name = format!(
+                "{}::{}",
+                sup.to_cpp_name(),
+                name.cpp_name_if_present()
+                    .map(|n| n.for_validation())
+                    .unwrap_or(&ident)
+            );
+            let simpler_analysis = match self.config.get_covariant_return(&directive_name) {
+                Some(derived) => make_covariant(
+                    &simpler_analysis,
+                    &QualifiedName::new_from_cpp_name(derived),
+                )
+                .ok_or_else(|| {
+                    ConvertErrorWithContext(
+                        ConvertErrorFromCpp::CovariantReturnNotPointer(directive_name.clone()),
+                        Some(self.error_context_for_method(sup, &analysis.rust_name)),
+                    )
+                })?,
+                None => simpler_analysis,
+            };
             for sub in self.subclasses_by_superclass(sup) {
                 // For each subclass, we need to create a plain-C++ method to call its superclass
                 // and a Rust/C++ bridge API to call _that_.
@@ -646,7 +823,6 @@ impl<'a> FnAnalyzer<'a> {
                     receiver_mutability,
                     sup,
                     subclass_fn_deps,
-                    self.unsafe_policy,
                 ));
 
                 // Create the trait item for the <superclass>_methods and <superclass>_supers
@@ -721,7 +897,17 @@ impl<'a> FnAnalyzer<'a> {
         predetermined_rust_name: Option<String>,
     ) -> (FnAnalysis, ApiName) {
         let cpp_original_name = name.cpp_name_if_present();
-        let ns = name.name.get_namespace();
+        // Free functions in anonymous namespaces have internal linkage, so
+        // cxx can't link to them directly. We can only call them from a C++
+        // wrapper in a translation unit which includes their definition,
+        // where they're named without the anonymous namespace.
+        let in_anonymous_namespace =
+            fun.self_ty.is_none() && name.name.get_namespace().contains_anonymous();
+        let ns = &if in_anonymous_namespace {
+            name.name.get_namespace().without_anonymous()
+        } else {
+            name.name.get_namespace().clone()
+        };
 
         // Let's gather some pre-wisdom about the name of the function.
         // We're shortly going to plunge into analyzing the parameters,
@@ -733,6 +919,26 @@ impl<'a> FnAnalyzer<'a> {
             .map(|n| n.diagnostic_display_name())
             .unwrap_or(&initial_rust_name);
         let diagnostic_name = QualifiedName::new(ns, make_ident(diagnostic_name));
+        // The name by which the user refers to this function or method in
+        // directives, e.g. `ns::Type::method`.
+        let directive_name = match &fun.self_ty {
+            Some(self_ty) => format!(
+                "{}::{}",
+                self_ty.to_cpp_name(),
+                cpp_original_name
+                    .map(|n| n.for_validation())
+                    .unwrap_or(initial_rust_name.as_str())
+            ),
+            None => diagnostic_name.to_cpp_name(),
+        };
+
+        // Some C++ methods aren't const, but are logically read-only. If the
+        // user has told us so, we'll pretend the receiver is const, and the
+        // C++ wrapper function will cast away the constness before making the call.
+        let treat_as_const = fun.virtualness.is_none()
+            && fun.special_member.is_none()
+            && fun.self_ty.is_some()
+            && self.config.is_treated_as_const(&directive_name);
 
         // Now let's analyze all the parameters.
         // See if any have annotations which our fork of bindgen has craftily inserted...
@@ -740,8 +946,13 @@ impl<'a> FnAnalyzer<'a> {
             .inputs
             .iter()
             .map(|i| {
+                let i = if treat_as_const {
+                    make_receiver_const(i)
+                } else {
+                    i.clone()
+                };
                 self.convert_fn_arg(
-                    i,
+                    &i,
                     ns,
                     &diagnostic_name,
                     &fun.synthesized_this_type,
@@ -752,7 +963,7 @@ impl<'a> FnAnalyzer<'a> {
                     false,
                 )
                 .map_err(|err| ConvertErrorFromCpp::Argument {
-                    arg: describe_arg(i),
+                    arg: describe_arg(&i),
                     err: Box::new(err),
                 })
             })
@@ -783,18 +994,7 @@ impl<'a> FnAnalyzer<'a> {
         //   method,   IRN=A_foo,  CN=foo                       output: foo    case 4
         //   method,   IRN=A_move, CN=move   (keyword problem)  output: move_  case 5
         //   method,   IRN=A_foo1, CN=foo    (overload)         output: foo    case 6
-        let ideal_rust_name = match cpp_original_name {
-            None => initial_rust_name, // case 1
-            Some(cpp_original_name) => {
-                if initial_rust_name.ends_with('_') {
-                    initial_rust_name // case 2
-                } else if validate_ident_ok_for_rust(cpp_original_name).is_err() {
-                    format!("{}_", cpp_original_name.to_string_for_rust_name()) // case 5
-                } else {
-                    cpp_original_name.to_string_for_rust_name() // cases 3, 4, 6
-                }
-            }
-        };
+        let ideal_rust_name = ideal_rust_name(cpp_original_name, initial_rust_name);
 
         // Let's spend some time figuring out the kind of this function (i.e. method,
         // virtual function, etc.)
@@ -819,6 +1019,13 @@ impl<'a> FnAnalyzer<'a> {
             &ideal_rust_name,
             &self_ty,
         );
+        // Set if overload or collision handling, or a name transform, gives
+        // this a Rust name other than the one we'd naturally pick.
+        let mut renamed_from = None;
+        // Set if a registered name transform chose an unusable name.
+        let mut transform_error = None;
+        let mut is_getter = false;
+        let mut is_constructor_factory = false;
         let (kind, error_context, rust_name) = if let Some(trait_details) = trait_details {
             trait_details
         } else if let Some(self_ty) = self_ty {
@@ -845,8 +1052,15 @@ impl<'a> FnAnalyzer<'a> {
                 if let Some(constructor_suffix) = rust_name.strip_prefix(nested_type_ident) {
                     rust_name = format!("new{constructor_suffix}");
                 }
-                rust_name = predetermined_rust_name
-                    .unwrap_or_else(|| self.get_overload_name(ns, type_ident, rust_name));
+                rust_name = predetermined_rust_name.unwrap_or_else(|| {
+                    self.get_overload_name(
+                        ns,
+                        type_ident,
+                        rust_name,
+                        &directive_name,
+                        &mut renamed_from,
+                    )
+                });
                 let error_context = self.error_context_for_method(&self_ty, &rust_name);
 
                 // If this is 'None', then something weird is going on. We'll check for that
@@ -907,8 +1121,15 @@ impl<'a> FnAnalyzer<'a> {
                     )
                 }
             } else if matches!(fun.special_member, Some(SpecialMemberKind::Destructor)) {
-                rust_name = predetermined_rust_name
-                    .unwrap_or_else(|| self.get_overload_name(ns, type_ident, rust_name));
+                rust_name = predetermined_rust_name.unwrap_or_else(|| {
+                    self.get_overload_name(
+                        ns,
+                        type_ident,
+                        rust_name,
+                        &directive_name,
+                        &mut renamed_from,
+                    )
+                });
                 let error_context = self.error_context_for_method(&self_ty, &rust_name);
                 let ty = Type::Path(self_ty.to_type_path());
                 (
@@ -966,9 +1187,80 @@ impl<'a> FnAnalyzer<'a> {
                         }
                     }
                 };
+                // A `getters!` directive renames accessors which take no
+                // parameters, e.g. GetWidth() to width(). It's matched against
+                // the C++ name, so it takes precedence over any name transform.
+                let getter_name = match method_kind {
+                    MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_)
+                        if param_details.len() == 1
+                            && !matches!(fun.output.0, syn::ReturnType::Default) =>
+                    {
+                        self.config
+                            .get_getter_name(&self_ty.to_cpp_name(), &rust_name)
+                    }
+                    _ => None,
+                };
+                if let Some(getter_name) = getter_name {
+                    rust_name = getter_name;
+                    is_getter = true;
+                }
+                if !is_getter
+                    && !matches!(method_kind, MethodKind::Constructor { .. })
+                    && matches!(fun.provenance, Provenance::Bindgen)
+                {
+                    rust_name = self
+                        .transform_name(
+                            &directive_name,
+                            ApiKind::Method,
+                            rust_name,
+                            &mut renamed_from,
+                        )
+                        .unwrap_or_else(|(name, err)| {
+                            transform_error = Some(err);
+                            name
+                        });
+                }
+                // A `constructor_from!` directive presents a static factory
+                // method as though it were a constructor.
+                if matches!(method_kind, MethodKind::Static)
+                    && self.config.is_constructor_factory(&directive_name)
+                {
+                    rust_name = "new".to_string();
+                    is_constructor_factory = true;
+                }
+                // A `name_constructor!` directive pins the name of the
+                // constructor with a given signature, so that it doesn't
+                // change as other constructors come and go.
+                let constructor_name = match method_kind {
+                    MethodKind::Constructor { .. } => {
+                        self.get_constructor_name(&self_ty, &param_details)
+                    }
+                    _ => None,
+                };
                 // Disambiguate overloads.
-                let rust_name = predetermined_rust_name
-                    .unwrap_or_else(|| self.get_overload_name(ns, type_ident, rust_name));
+                let rust_name = predetermined_rust_name.unwrap_or_else(|| {
+                    if is_constructor_factory {
+                        return self
+                            .overload_trackers_by_mod
+                            .entry(ns.clone())
+                            .or_default()
+                            .get_claimed_method_name(type_ident, rust_name);
+                    }
+                    let overload_name = self.get_overload_name(
+                        ns,
+                        type_ident,
+                        rust_name,
+                        &directive_name,
+                        &mut renamed_from,
+                    );
+                    match constructor_name {
+                        Some(name) => {
+                            renamed_from = Some(directive_name.clone());
+                            name
+                        }
+                        None => overload_name,
+                    }
+                });
                 let error_context = self.error_context_for_method(&self_ty, &rust_name);
                 (
                     FnKind::Method {
@@ -982,7 +1274,26 @@ impl<'a> FnAnalyzer<'a> {
         } else {
             // Not a method.
             // What shall we call this function? It may be overloaded.
-            let rust_name = self.get_function_overload_name(ns, ideal_rust_name);
+            let ideal_rust_name = if matches!(fun.provenance, Provenance::Bindgen) {
+                self.transform_name(
+                    &directive_name,
+                    ApiKind::Function,
+                    ideal_rust_name,
+                    &mut renamed_from,
+                )
+                .unwrap_or_else(|(name, err)| {
+                    transform_error = Some(err);
+                    name
+                })
+            } else {
+                ideal_rust_name
+            };
+            let rust_name = self.get_function_overload_name(
+                ns,
+                ideal_rust_name,
+                &directive_name,
+                &mut renamed_from,
+            );
             (
                 FnKind::Function,
                 ErrorContext::new_for_item(make_ident(&rust_name)),
@@ -997,6 +1308,9 @@ impl<'a> FnAnalyzer<'a> {
         let mut ignore_reason = Ok(());
         let mut set_ignore_reason =
             |err| ignore_reason = Err(ConvertErrorWithContext(err, Some(error_context.clone())));
+        if let Some(err) = transform_error {
+            set_ignore_reason(err);
+        }
 
         // Now we have figured out the type of function (from its parameters)
         // we might have determined that we have a constructor. If so,
@@ -1139,6 +1453,8 @@ impl<'a> FnAnalyzer<'a> {
             set_ignore_reason(ConvertErrorFromCpp::RValueReturn)
         } else if matches!(fun.is_deleted, Some(Explicitness::Deleted)) {
             set_ignore_reason(ConvertErrorFromCpp::Deleted)
+        } else if in_anonymous_namespace && !self.config.internal_linkage_shims() {
+            set_ignore_reason(ConvertErrorFromCpp::FunctionInAnonymousNamespace)
         } else {
             match kind {
                 FnKind::Method {
@@ -1200,11 +1516,60 @@ impl<'a> FnAnalyzer<'a> {
         // Analyze the return type, just as we previously did for the
         // parameters.
         let mut return_analysis = self
-            .convert_return_type(&fun.output, ns, &diagnostic_name, sophistication)
+            .convert_return_type(
+                &fun.output,
+                ns,
+                &diagnostic_name,
+                sophistication,
+                self.config.is_copy_string_return(&directive_name),
+            )
             .unwrap_or_else(|err| {
                 set_ignore_reason(err);
                 ReturnTypeAnalysis::default()
             });
+        // A pair of pointers which the user has told us delimit a range
+        // becomes a single slice.
+        let mut combined_iterator_pair = None;
+        if let Some(begin) = self.config.get_iterator_range(&directive_name) {
+            match self.combine_iterator_pair(fun, &mut params, &mut param_details, begin) {
+                Ok(true) => combined_iterator_pair = Some(begin),
+                Ok(false) => {}
+                Err(err) => set_ignore_reason(err),
+            }
+        }
+        // The directive counts arguments as C++ does, ignoring `this`, so
+        // those after an iterator pair have since moved down one.
+        let receiver_count = param_details
+            .iter()
+            .filter(|pd| pd.self_type.is_some())
+            .count();
+        let returns_lifetime_of_arg = self
+            .config
+            .get_returns_lifetime_of_arg(&directive_name)
+            .map(|arg| match combined_iterator_pair {
+                Some(begin) if arg > begin => arg - 1,
+                _ => arg,
+            })
+            .map(|arg| arg + receiver_count);
+        // A method returning a `view_type!` by value hands back something
+        // which points into the receiver, so we'll tie the two together.
+        // Any function can instead tie it to an argument.
+        let returns_view = (returns_lifetime_of_arg.is_some()
+            || matches!(
+                kind,
+                FnKind::Method {
+                    method_kind: MethodKind::Normal
+                        | MethodKind::Virtual(_)
+                        | MethodKind::PureVirtual(_),
+                    ..
+                }
+            ))
+            && !return_analysis.was_reference
+            && return_analysis.placement_param_needed.is_none()
+            && return_analysis
+                .deps
+                .iter()
+                .any(|dep| self.config.is_view_type(&dep.to_cpp_name()));
         let mut deps = params_deps;
         deps.extend(return_analysis.deps.drain(..));
 
@@ -1216,12 +1581,55 @@ impl<'a> FnAnalyzer<'a> {
             params.push(extra_param);
         }
 
-        let requires_unsafe = self.should_be_unsafe(&param_details, &kind);
+        let mut requires_unsafe = self.should_be_unsafe(&param_details, &kind);
+
+        // A static method of a singleton type, or the accessor for a const
+        // global, returns a reference to an instance which outlives
+        // everything, so it needs no input reference from which to borrow.
+        // Each call to such a method could hand out another `Pin<&mut T>`
+        // to the same instance, so those are unsafe.
+        let returns_static_reference = match kind {
+            FnKind::Method {
+                ref impl_for,
+                method_kind: MethodKind::Static,
+            } if self.config.is_singleton(&impl_for.to_cpp_name()) => {
+                add_static_lifetime(&mut return_analysis.rt)
+            }
+            FnKind::Function
+                if matches!(
+                    fun.synthetic_cpp,
+                    Some((CppFunctionBody::GlobalAccessor { .. }, _))
+                ) =>
+            {
+                add_static_lifetime(&mut return_analysis.rt)
+            }
+            _ => false,
+        };
+        if returns_static_reference && return_analysis.was_mutable_reference {
+            requires_unsafe = UnsafetyNeeded::Always;
+        }
 
         // The following sections reject some types of function because of the arrangement
         // of Rust references. We could lift these restrictions when/if we switch to using
         // CppRef to represent C++ references.
-        if return_analysis.was_reference {
+        if let Some(arg) = returns_lifetime_of_arg {
+            let arg_is_suitable = param_details.get(arg).is_some_and(|pd| {
+                pd.has_lifetime
+                    && pd.self_type.is_none()
+                    && !pd.is_placement_return_destination
+                    && (pd.is_mutable_reference || !return_analysis.was_mutable_reference)
+            });
+            if !return_analysis.was_reference && !returns_view {
+                set_ignore_reason(ConvertErrorFromCpp::NothingBorrowsLifetimeArg(
+                    directive_name.clone(),
+                ));
+            } else if !arg_is_suitable {
+                set_ignore_reason(ConvertErrorFromCpp::LifetimeArgNotReference(
+                    directive_name.clone(),
+                    arg - receiver_count,
+                ));
+            }
+        } else if return_analysis.was_reference && !returns_static_reference {
             // cxx only allows functions to return a reference if they take exactly
             // one reference as a parameter. Let's see.
             let num_input_references = param_details.iter().filter(|pd| pd.has_lifetime).count();
@@ -1234,7 +1642,10 @@ impl<'a> FnAnalyzer<'a> {
                 ));
             }
         }
-        if return_analysis.was_mutable_reference {
+        if return_analysis.was_mutable_reference
+            && !returns_static_reference
+            && returns_lifetime_of_arg.is_none()
+        {
             // This one's a bit more subtle. We can't have:
             //    fn foo(thing: &Thing) -> &mut OtherThing
             // because Rust doesn't allow it.
@@ -1259,7 +1670,45 @@ impl<'a> FnAnalyzer<'a> {
         }
 
         let mut ret_type = return_analysis.rt;
-        let ret_type_conversion = return_analysis.conversion;
+        let mut ret_type_conversion = return_analysis.conversion;
+        if let FnKind::Method { ref impl_for, .. } = kind {
+            if is_constructor_factory {
+                match ret_type_conversion {
+                    Some(conversion) if returns_owning_pointer_to(&conversion, impl_for) => {
+                        // We can only adopt a raw pointer into a `UniquePtr`
+                        // if it came from `new`, and nobody else owns it,
+                        // which only the caller can promise.
+                        if matches!(conversion.cxxbridge_type(), Type::Ptr(_)) {
+                            requires_unsafe = UnsafetyNeeded::Always;
+                        }
+                        ret_type_conversion = Some(conversion.into_optional_unique_ptr())
+                    }
+                    _ => {
+                        set_ignore_reason(ConvertErrorFromCpp::NotAFactory(directive_name.clone()))
+                    }
+                }
+            }
+        }
+        // With panic_free!, a `std::unique_ptr` which C++ might have left
+        // null becomes an `Option`.
+        if self.config.panic_free() {
+            ret_type_conversion = ret_type_conversion.map(|conversion| {
+                if returns_unconverted_unique_ptr(&conversion) {
+                    conversion.into_optional_unique_ptr()
+                } else {
+                    conversion
+                }
+            });
+        }
+        let checks_null_params = self.config.panic_free()
+            && !matches!(kind, FnKind::TraitMethod { .. })
+            && param_details.iter().any(|pd| {
+                matches!(
+                    pd.conversion.rust_conversion,
+                    RustConversionType::FromValueParamToPtr
+                        | RustConversionType::FromRValueParamToPtr
+                )
+            });
 
         // Do we need to convert either parameters or return type?
         let param_conversion_needed = param_details.iter().any(|b| b.conversion.cpp_work_needed());
@@ -1280,6 +1729,65 @@ impl<'a> FnAnalyzer<'a> {
         // C++ API and we need to create a C++ wrapper function which is more cxx-compliant.
         // That wrapper function is included in the cxx::bridge, and calls through to the
         // original function.
+        let c_export_name = self
+            .config
+            .get_c_export_name(&directive_name)
+            .map(|name| name.to_string());
+        let c_abi_name = self
+            .passes_small_pods(&param_details, &ret_type_conversion)
+            .then(|| {
+                self.config
+                    .uniquify_name_per_mod(&format!("{cxxbridge_name}_autocxx_c_abi"))
+            });
+        // Instance methods of thread_affine! types need Rust wrappers, in
+        // which to check the thread. Giving them C++ wrappers too means the
+        // cxx bridge functions get different names from the Rust methods.
+        // Constructors record the thread which owns the new object.
+        let thread_affine_type = match &kind {
+            FnKind::Method {
+                impl_for,
+                method_kind:
+                    MethodKind::Normal
+                    | MethodKind::Virtual(_)
+                    | MethodKind::PureVirtual(_)
+                    | MethodKind::Constructor { .. },
+            } if self.config.is_thread_affine(&impl_for.to_cpp_name()) => Some(impl_for.clone()),
+            _ => None,
+        };
+        // deprecated_alias! copies of methods are Rust wrappers, so need
+        // C++ wrappers for the same reason as thread_affine! methods.
+        let deprecated_aliases: Vec<String> = match &kind {
+            FnKind::Function => self
+                .config
+                .get_deprecated_aliases(
+                    &QualifiedName::new(ns, make_ident(&rust_name)).to_cpp_name(),
+                )
+                .map(|alias| alias.to_string())
+                .collect(),
+            FnKind::Method {
+                impl_for,
+                method_kind:
+                    MethodKind::Normal
+                    | MethodKind::Static
+                    | MethodKind::Virtual(_)
+                    | MethodKind::PureVirtual(_),
+            } => self
+                .config
+                .get_deprecated_aliases(&format!("{}::{}", impl_for.to_cpp_name(), rust_name))
+                .map(|alias| alias.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        // Likewise the accessors of MSVC properties.
+        let property_accessors = match &kind {
+            FnKind::Method {
+                method_kind:
+                    MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+                ..
+            } => self.msvc_declarations.property_accessors(&directive_name),
+            _ => Vec::new(),
+        };
+        let force_inline = self.config.is_force_inline(&directive_name);
         let wrapper_function_needed = match kind {
             FnKind::Method {
                 method_kind:
@@ -1297,11 +1805,21 @@ impl<'a> FnAnalyzer<'a> {
                 ..
             } => true,
             FnKind::Method { .. } if cxxbridge_name != rust_name => true,
+            FnKind::Method { .. } if thread_affine_type.is_some() => true,
+            FnKind::Method { .. } if !deprecated_aliases.is_empty() => true,
+            FnKind::Method { .. } if !property_accessors.is_empty() => true,
             _ if param_conversion_needed => true,
             _ if ret_type_conversion_needed => true,
             _ if cpp_name_incompatible_with_cxx => true,
             _ if fun.synthetic_cpp.is_some() => true,
+            _ if treat_as_const => true,
             _ if self.force_wrapper_generation => true,
+            // There must be a wrapper for force_inline! to inline.
+            _ if force_inline => true,
+            _ if in_anonymous_namespace => true,
+            _ if c_export_name.is_some() || c_abi_name.is_some() => true,
+            // So that there's a Rust wrapper to which we can add `#[must_use]`.
+            _ if is_getter => true,
             _ => false,
         };
 
@@ -1353,6 +1871,13 @@ impl<'a> FnAnalyzer<'a> {
                         ),
                         CppFunctionKind::Function,
                     ),
+                    FnKind::Method { ref impl_for, .. } if treat_as_const => (
+                        CppFunctionBody::ConstCastMethodCall(
+                            impl_for.clone(),
+                            underlying_cpp_function_name,
+                        ),
+                        CppFunctionKind::Method,
+                    ),
                     FnKind::Method { .. } => (
                         CppFunctionBody::FunctionCall(ns.clone(), underlying_cpp_function_name),
                         CppFunctionKind::Method,
@@ -1401,25 +1926,79 @@ impl<'a> FnAnalyzer<'a> {
                 kind: cpp_function_kind,
                 pass_obs_field: false,
                 qualification: None,
+                force_inline,
             })
         } else {
             None
         };
 
+        // The C export calls the wrapper we've just made, with references
+        // turned into pointers. Anything else which C can't express, such
+        // as a std::unique_ptr, means we can't do it.
+        if c_export_name.is_some() {
+            if self.overloaded_c_exports.contains(&directive_name) {
+                set_ignore_reason(ConvertErrorFromCpp::OverloadedCExport(
+                    c_export_name.clone().unwrap(),
+                ));
+            } else if !(params.iter().all(|param| match &param.0 {
+                syn::FnArg::Typed(pt) => self.is_c_compatible(&pt.ty),
+                syn::FnArg::Receiver(_) => false,
+            }) && match &ret_type {
+                ReturnType::Type(_, ty) => self.is_c_compatible(ty),
+                ReturnType::Default => true,
+            }) {
+                set_ignore_reason(ConvertErrorFromCpp::NotCExportable(directive_name.clone()));
+            }
+        }
+
         let vis = fun.vis.clone();
 
         let any_param_needs_rust_conversion = param_details
             .iter()
             .any(|pd| pd.conversion.rust_work_needed());
 
+        // The initialization function itself mustn't call
+        // ensure_initialized(), or it would run twice.
+        let ensure_initialized = self
+            .config
+            .get_init_function()
+            .is_some_and(|init| init.auto_in_debug && init.function != directive_name);
+
         let rust_wrapper_needed = match kind {
             _ if any_param_needs_rust_conversion || return_needs_rust_conversion => true,
+            _ if returns_view => true,
             FnKind::TraitMethod { .. } => true,
+            // Before the arm for methods, so that renamed methods don't
+            // miss out on the initialization check.
+            _ if self.force_wrapper_generation || ensure_initialized => true,
             FnKind::Method { .. } => cxxbridge_name != rust_name,
-            _ if self.force_wrapper_generation => true,
+            _ if !deprecated_aliases.is_empty() => true,
+            _ if !property_accessors.is_empty() => true,
+            _ if c_abi_name.is_some() => true,
             _ => false,
         };
 
+        // Async wrappers move their parameters onto another thread,
+        // so only make them if nothing is borrowed, no raw pointers are
+        // involved and no Rust-side parameter conversions (e.g. moveit)
+        // are required.
+        let async_requested = self
+            .config
+            .is_async_function(&diagnostic_name.to_cpp_name());
+        let async_wrapper_needed = async_requested
+            && matches!(kind, FnKind::Function)
+            && !any_param_needs_rust_conversion
+            && !return_needs_rust_conversion
+            && !return_analysis.was_reference
+            && param_details.iter().all(|pd| {
+                !pd.has_lifetime && !matches!(pd.conversion.cxxbridge_type(), Type::Ptr(_))
+            });
+        if async_requested && !async_wrapper_needed {
+            set_ignore_reason(ConvertErrorFromCpp::NotAsyncCompatible(
+                directive_name.clone(),
+            ));
+        }
+
         // Naming, part two.
         // Work out our final naming strategy.
         validate_ident_ok_for_cxx(&cxxbridge_name.to_string())
@@ -1451,6 +2030,19 @@ impl<'a> FnAnalyzer<'a> {
             ignore_reason,
             externally_callable,
             rust_wrapper_needed,
+            async_wrapper_needed,
+            returns_view,
+            returns_lifetime_of_arg,
+            renamed_from,
+            adl_method_for: None,
+            c_export_name,
+            c_abi_name,
+            is_getter,
+            ensure_initialized,
+            thread_affine_type,
+            deprecated_aliases,
+            property_accessors,
+            checks_null_params,
         };
         // For everything other than functions, the API name is immutable.
         // It would be nice to get to that point with functions, but at present
@@ -1460,6 +2052,69 @@ impl<'a> FnAnalyzer<'a> {
         (analysis, name)
     }
 
+    /// Whether this function should be called through a flat `extern "C"`
+    /// function so that small POD types can be passed in registers. That's
+    /// only worthwhile if it involves at least one such type, and only
+    /// possible if everything else is a scalar which needs no conversion.
+    /// Whether a parameter or return type of a C++ wrapper function can be
+    /// represented in a C function signature, once references (pinned or
+    /// otherwise) have been turned into pointers. Anything passed by value
+    /// must be a scalar or POD, since C can't copy or destroy anything else.
+    fn is_c_compatible(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Ptr(_) => true,
+            Type::Reference(typr) => match typr.elem.as_ref() {
+                Type::Path(typ) => !typ.path.is_ident("str"),
+                Type::Slice(_) => false,
+                _ => true,
+            },
+            Type::Path(typ) => {
+                extract_pinned_mutable_reference_type(typ).is_some()
+                    || small_pods::is_scalar(ty)
+                    || self
+                        .pod_safe_types
+                        .contains(&QualifiedName::from_type_path(typ))
+            }
+            _ => false,
+        }
+    }
+
+    fn passes_small_pods(
+        &self,
+        param_details: &[ArgumentAnalysis],
+        ret_conversion: &Option<TypeConversionPolicy>,
+    ) -> bool {
+        if self.small_pod_types.is_empty() {
+            return false;
+        }
+        let conversions: Option<Vec<_>> = param_details
+            .iter()
+            .map(|pd| {
+                (pd.self_type.is_none() && !pd.is_placement_return_destination)
+                    .then_some(&pd.conversion)
+            })
+            .chain(ret_conversion.iter().map(Some))
+            .collect();
+        let conversions = match conversions {
+            Some(conversions) => conversions,
+            None => return false,
+        };
+        let is_small_pod = |ty: &Type| match ty {
+            Type::Path(typ) => self
+                .small_pod_types
+                .contains(&QualifiedName::from_type_path(typ)),
+            _ => false,
+        };
+        conversions.iter().all(|conversion| {
+            !conversion.cpp_work_needed()
+                && !conversion.rust_work_needed()
+                && (is_small_pod(conversion.cxxbridge_type())
+                    || small_pods::is_scalar(conversion.cxxbridge_type()))
+        }) && conversions
+            .iter()
+            .any(|conversion| is_small_pod(conversion.cxxbridge_type()))
+    }
+
     fn error_context_for_method(&self, self_ty: &QualifiedName, rust_name: &str) -> ErrorContext {
         if self.is_generic_type(self_ty) {
             // A 'method' error context would end up in an
@@ -1516,9 +2171,82 @@ impl<'a> FnAnalyzer<'a> {
         })
     }
 
-    fn get_overload_name(&mut self, ns: &Namespace, type_ident: &str, rust_name: String) -> String {
+    fn get_overload_name(
+        &mut self,
+        ns: &Namespace,
+        type_ident: &str,
+        rust_name: String,
+        directive_name: &str,
+        renamed_from: &mut Option<String>,
+    ) -> String {
+        let overrides = self.config.get_overload_names(directive_name);
         let overload_tracker = self.overload_trackers_by_mod.entry(ns.clone()).or_default();
-        overload_tracker.get_method_real_name(type_ident, rust_name)
+        let real_name =
+            overload_tracker.get_method_real_name(type_ident, rust_name.clone(), overrides);
+        if real_name != rust_name {
+            *renamed_from = Some(directive_name.to_string());
+        }
+        real_name
+    }
+
+    /// Apply each registered [`NameTransform`] in turn to the Rust name of a
+    /// function or method. On failure, returns the name as it was before
+    /// the offending transform, along with the error.
+    fn transform_name(
+        &self,
+        directive_name: &str,
+        kind: ApiKind,
+        rust_name: String,
+        renamed_from: &mut Option<String>,
+    ) -> Result<String, (String, ConvertErrorFromCpp)> {
+        let mut current = rust_name;
+        for transform in &self.name_transforms {
+            let item = NameToTransform {
+                cpp_name: directive_name,
+                kind,
+                rust_name: &current,
+            };
+            if let Some(new_name) = transform.rust_name(&item) {
+                if let Err(err) = validate_ident_ok_for_cxx(&new_name) {
+                    return Err((
+                        current,
+                        ConvertErrorFromCpp::InvalidTransformedName(
+                            transform.name().to_string(),
+                            new_name,
+                            err,
+                        ),
+                    ));
+                }
+                if new_name != current {
+                    *renamed_from = Some(directive_name.to_string());
+                }
+                current = new_name;
+            }
+        }
+        Ok(current)
+    }
+
+    /// The name requested by `name_constructor!` for a constructor of
+    /// `self_ty` taking these parameters, if any. The signature is
+    /// spelled as in C++, e.g. `ns::Foo(int, const std::string&)`.
+    fn get_constructor_name(
+        &self,
+        self_ty: &QualifiedName,
+        param_details: &[ArgumentAnalysis],
+    ) -> Option<String> {
+        let params = param_details
+            .iter()
+            .filter(|param| param.self_type.is_none())
+            .map(|param| {
+                self.type_converter
+                    .type_to_cpp(param.conversion.cxxbridge_type())
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let signature = format!("{}({})", self_ty.to_cpp_name(), params.join(", "));
+        self.config
+            .get_constructor_name(&signature)
+            .map(str::to_string)
     }
 
     /// Determine if this synthetic function should actually result in the implementation
@@ -1532,7 +2260,7 @@ impl<'a> FnAnalyzer<'a> {
     ) -> Option<(FnKind, ErrorContext, String)> {
         synthesis.as_ref().and_then(|synthesis| match synthesis {
             TraitSynthesis::Cast { to_type, mutable } => {
-                let rust_name = self.get_function_overload_name(ns, ideal_rust_name.to_string());
+                let rust_name = self.get_synthesized_function_name(ns, ideal_rust_name.to_string());
                 let from_type = self_ty.as_ref().unwrap();
                 let from_type_path = from_type.to_type_path();
                 let to_type = to_type.to_type_path();
@@ -1606,7 +2334,7 @@ impl<'a> FnAnalyzer<'a> {
         kind: TraitMethodKind,
     ) -> Option<(FnKind, ErrorContext, String)> {
         let rust_name =
-            self.get_function_overload_name(ty.get_namespace(), ideal_rust_name.to_string());
+            self.get_synthesized_function_name(ty.get_namespace(), ideal_rust_name.to_string());
         let typ = ty.to_type_path();
         Some((
             FnKind::TraitMethod {
@@ -1628,9 +2356,27 @@ impl<'a> FnAnalyzer<'a> {
         ))
     }
 
-    fn get_function_overload_name(&mut self, ns: &Namespace, ideal_rust_name: String) -> String {
+    fn get_function_overload_name(
+        &mut self,
+        ns: &Namespace,
+        ideal_rust_name: String,
+        directive_name: &str,
+        renamed_from: &mut Option<String>,
+    ) -> String {
+        let overrides = self.config.get_overload_names(directive_name);
+        let overload_tracker = self.overload_trackers_by_mod.entry(ns.clone()).or_default();
+        let real_name = overload_tracker.get_function_real_name(ideal_rust_name.clone(), overrides);
+        if real_name != ideal_rust_name {
+            *renamed_from = Some(directive_name.to_string());
+        }
+        real_name
+    }
+
+    /// Pick a name for a function we've synthesized ourselves. Users can't
+    /// refer to these in directives, and any renaming isn't reported.
+    fn get_synthesized_function_name(&mut self, ns: &Namespace, ideal_rust_name: String) -> String {
         let overload_tracker = self.overload_trackers_by_mod.entry(ns.clone()).or_default();
-        overload_tracker.get_function_real_name(ideal_rust_name)
+        overload_tracker.get_function_real_name(ideal_rust_name, &[])
     }
 
     fn subclasses_by_superclass(&self, sup: &QualifiedName) -> impl Iterator<Item = SubclassName> {
@@ -1730,7 +2476,39 @@ impl<'a> FnAnalyzer<'a> {
                         force_rust_conversion,
                         Some(RustConversionType::FromPlacementParamToNewReturn)
                     );
+                if self.config.ostream_as_writer()
+                    && self_type.is_none()
+                    && matches!(sophistication, TypeConversionSophistication::Regular)
+                    && type_is_ostream_reference(&ty_to_convert)
+                {
+                    // Rust passes the address of its writer, which the C++
+                    // wrapper wraps in a stream.
+                    pt.pat = Box::new(new_pat.clone());
+                    pt.ty = parse_quote! { usize };
+                    return Ok((
+                        syn::FnArg::Typed(pt).into(),
+                        ArgumentAnalysis {
+                            self_type,
+                            name: new_pat.into(),
+                            conversion: TypeConversionPolicy::new(
+                                parse_quote! { usize },
+                                CppConversionType::FromWriterToOstream(
+                                    ostream_writer_names(self.config).adapter,
+                                ),
+                                RustConversionType::FromWriterToAddress,
+                            ),
+                            has_lifetime: false,
+                            is_mutable_reference: false,
+                            deps: HashSet::new(),
+                            requires_unsafe: UnsafetyNeeded::JustBridge,
+                            is_placement_return_destination: false,
+                        },
+                    ));
+                }
+                let mentions_std_byte = type_mentions_std_byte(&ty_to_convert);
+                let pmr_use = find_pmr_use(&ty_to_convert);
                 let annotated_type = self.convert_boxed_type(ty_to_convert, ns)?;
+                self.ensure_array_elements_are_pod(&annotated_type.ty)?;
                 let conversion = self.argument_conversion_details(
                     &annotated_type,
                     is_move_constructor,
@@ -1739,6 +2517,26 @@ impl<'a> FnAnalyzer<'a> {
                     self_type.is_some(),
                     is_placement_return_destination,
                 );
+                let conversion = if mentions_std_byte {
+                    conversion.cast_std_bytes(CppConversionType::FromU8ToStdByte)?
+                } else {
+                    conversion
+                };
+                let conversion = match pmr_use {
+                    PmrUse::None => conversion,
+                    PmrUse::Direct => conversion.copy_pmr_container(matches!(
+                        annotated_type.ty.as_ref(),
+                        Type::Reference(TypeReference {
+                            mutability: None,
+                            ..
+                        })
+                    ))?,
+                    PmrUse::Nested => {
+                        return Err(ConvertErrorFromCpp::UnsupportedPmrType(
+                            conversion.cxxbridge_type().to_token_stream().to_string(),
+                        ))
+                    }
+                };
                 let new_ty = annotated_type.ty;
                 pt.pat = Box::new(new_pat.clone());
                 pt.ty = new_ty;
@@ -1822,6 +2620,14 @@ impl<'a> FnAnalyzer<'a> {
             );
         }
         match ty {
+            Type::Reference(_) if extract_array_reference_element_type(ty).is_some() => {
+                let rust_conversion = force_rust_conversion.unwrap_or(RustConversionType::None);
+                TypeConversionPolicy::new(
+                    ty.clone(),
+                    CppConversionType::FromStdArrayToCArray,
+                    rust_conversion,
+                )
+            }
             Type::Path(p) => {
                 let ty = ty.clone();
                 let tn = QualifiedName::from_type_path(p);
@@ -1929,18 +2735,64 @@ impl<'a> FnAnalyzer<'a> {
         }
     }
 
+    /// cxx can only represent `std::array`s of types it can hold by value,
+    /// so references to C arrays are only supported for POD elements.
+    fn ensure_array_elements_are_pod(&self, ty: &Type) -> Result<(), ConvertErrorFromCpp> {
+        match extract_array_reference_element_type(ty) {
+            Some(Type::Path(elem))
+                if self
+                    .pod_safe_types
+                    .contains(&QualifiedName::from_type_path(elem)) =>
+            {
+                Ok(())
+            }
+            Some(elem) => Err(ConvertErrorFromCpp::ArrayOfNonPodType(
+                elem.to_token_stream().to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     fn convert_return_type(
         &mut self,
         rt: &ReturnType,
         ns: &Namespace,
         diagnostic_name: &QualifiedName,
         sophistication: TypeConversionSophistication,
+        copy_string: bool,
     ) -> Result<ReturnTypeAnalysis, ConvertErrorFromCpp> {
-        Ok(match rt {
+        let mentions_std_byte = match rt {
+            ReturnType::Type(_, boxed_type) => type_mentions_std_byte(boxed_type),
+            ReturnType::Default => false,
+        };
+        let copies_pmr = match rt {
+            ReturnType::Type(_, boxed_type) => match find_pmr_use(boxed_type) {
+                PmrUse::None => false,
+                PmrUse::Direct => true,
+                PmrUse::Nested => {
+                    return Err(ConvertErrorFromCpp::UnsupportedPmrType(
+                        boxed_type.to_token_stream().to_string(),
+                    ))
+                }
+            },
+            ReturnType::Default => false,
+        };
+        let mut analysis = match rt {
             ReturnType::Default => ReturnTypeAnalysis::default(),
             ReturnType::Type(rarrow, boxed_type) => {
                 let annotated_type = self.convert_boxed_type(boxed_type.clone(), ns)?;
-                let boxed_type = annotated_type.ty;
+                let mut boxed_type = annotated_type.ty;
+                if copy_string {
+                    // `copy_string_return!`: return the string which is
+                    // referred to by value instead, which copies it.
+                    boxed_type = referenced_string(&boxed_type, &annotated_type.kind)
+                        .map(Box::new)
+                        .ok_or_else(|| {
+                            ConvertErrorFromCpp::CopyStringReturnNotStringReference(
+                                diagnostic_name.to_cpp_name(),
+                            )
+                        })?;
+                }
                 let ty: &Type = boxed_type.as_ref();
                 match ty {
                     Type::Path(p)
@@ -1949,7 +2801,9 @@ impl<'a> FnAnalyzer<'a> {
                             .contains(&QualifiedName::from_type_path(p)) =>
                     {
                         let tn = QualifiedName::from_type_path(p);
-                        if self.moveit_safe_types.contains(&tn)
+                        let is_string_by_value = self.config.strings_by_value()
+                            && known_types().convertible_from_strs(&tn);
+                        if (self.moveit_safe_types.contains(&tn) || is_string_by_value)
                             && matches!(sophistication, TypeConversionSophistication::Regular)
                         {
                             // This is a non-POD type we want to return to Rust as an `impl New` so that callers
@@ -1983,7 +2837,7 @@ impl<'a> FnAnalyzer<'a> {
                         } else {
                             // There are some types which we can't currently represent within a moveit::new::New.
                             // That's either because we are obliged to stick to existing protocols for compatibility
-                            // (CxxString) or because they're a concrete type where we haven't attempted to do
+                            // (CxxString, unless strings_by_value!() is used) or because they're a concrete type where we haven't attempted to do
                             // the analysis to work out the type's size. For these, we always return a plain old
                             // UniquePtr<T>. These restrictions may be fixed in future.
                             let conversion =
@@ -2003,8 +2857,15 @@ impl<'a> FnAnalyzer<'a> {
                         );
                         let was_reference = was_mutable_reference
                             || matches!(annotated_type.kind, type_converter::TypeKind::Reference);
-                        let conversion = Some(
-                            if was_reference
+                        self.ensure_array_elements_are_pod(ty)?;
+                        let conversion =
+                            Some(if extract_array_reference_element_type(ty).is_some() {
+                                TypeConversionPolicy::new(
+                                    ty.clone(),
+                                    CppConversionType::FromCArrayToStdArray,
+                                    RustConversionType::None,
+                                )
+                            } else if was_reference
                                 && matches!(
                                     self.config.unsafe_policy,
                                     UnsafePolicy::ReferencesWrappedAllFunctionsSafe
@@ -2013,8 +2874,7 @@ impl<'a> FnAnalyzer<'a> {
                                 TypeConversionPolicy::return_reference_into_wrapper(ty.clone())
                             } else {
                                 TypeConversionPolicy::new_unconverted(ty.clone())
-                            },
-                        );
+                            });
                         ReturnTypeAnalysis {
                             rt: ReturnType::Type(*rarrow, boxed_type),
                             conversion,
@@ -2026,7 +2886,21 @@ impl<'a> FnAnalyzer<'a> {
                     }
                 }
             }
-        })
+        };
+        if mentions_std_byte {
+            analysis.conversion = analysis
+                .conversion
+                .map(|conversion| conversion.cast_std_bytes(CppConversionType::FromStdByteToU8))
+                .transpose()?;
+        }
+        if copies_pmr {
+            // A returned reference would refer to the temporary copy.
+            analysis.conversion = analysis
+                .conversion
+                .map(|conversion| conversion.copy_pmr_container(false))
+                .transpose()?;
+        }
+        Ok(analysis)
     }
 
     /// If a type has explicit constructors, bindgen will generate corresponding
@@ -2188,6 +3062,27 @@ impl<'a> FnAnalyzer<'a> {
     }
 }
 
+/// The Rust name which a function or method would have, were it not for
+/// overloads and other collisions. See the cases listed where this is
+/// called.
+fn ideal_rust_name(
+    cpp_original_name: Option<&CppOriginalName>,
+    initial_rust_name: String,
+) -> String {
+    match cpp_original_name {
+        None => initial_rust_name, // case 1
+        Some(cpp_original_name) => {
+            if initial_rust_name.ends_with('_') {
+                initial_rust_name // case 2
+            } else if validate_ident_ok_for_rust(cpp_original_name).is_err() {
+                format!("{}_", cpp_original_name.to_string_for_rust_name()) // case 5
+            } else {
+                cpp_original_name.to_string_for_rust_name() // cases 3, 4, 6
+            }
+        }
+    }
+}
+
 fn special_member_to_string(special_member: SpecialMemberKind) -> &'static str {
     match special_member {
         SpecialMemberKind::DefaultConstructor => "default constructor",
@@ -2217,9 +3112,12 @@ impl Api<FnPhase> {
             Api::Function { fun, analysis, .. } => match analysis.kind {
                 FnKind::Method { ref impl_for, .. } => impl_for.clone(),
                 FnKind::TraitMethod { ref impl_for, .. } => impl_for.clone(),
-                FnKind::Function => {
-                    QualifiedName::new(self.name().get_namespace(), fun.ident.clone())
-                }
+                FnKind::Function => match &fun.synthetic_cpp {
+                    Some((CppFunctionBody::GlobalAccessor { allowlisted_as, .. }, _)) => {
+                        allowlisted_as.clone()
+                    }
+                    _ => QualifiedName::new(self.name().get_namespace(), fun.ident.clone()),
+                },
             },
             Api::RustSubclassFn { subclass, .. } => subclass.0.name.clone(),
             Api::IgnoredItem {
@@ -2270,6 +3168,12 @@ impl Api<FnPhase> {
                     },
                     ..
                 }
+        ) || matches!(
+            &self,
+            Api::ForwardDeclaration { name, .. } if name.cpp_name().is_nested()
+        ) || matches!(
+            &self,
+            Api::RustFn { details, .. } if has_boxed_fns(&details.sig)
         )
     }
 
@@ -2293,6 +3197,53 @@ fn return_type_is_reference(output: &crate::minisyn::ReturnType) -> bool {
     }
 }
 
+/// The string type referred to by a returned `const std::string&`, which
+/// may be either a Rust reference or, for C++ references, a pointer.
+fn referenced_string(ty: &Type, kind: &type_converter::TypeKind) -> Option<Type> {
+    let elem = match (ty, kind) {
+        (Type::Reference(r), type_converter::TypeKind::Reference) => &r.elem,
+        (Type::Ptr(p), type_converter::TypeKind::Reference) => &p.elem,
+        _ => return None,
+    };
+    match elem.as_ref() {
+        Type::Path(p) if known_types().convertible_from_strs(&QualifiedName::from_type_path(p)) => {
+            Some(elem.as_ref().clone())
+        }
+        _ => None,
+    }
+}
+
+/// Give a returned `&T` or `Pin<&mut T>` the `'static` lifetime. Returns
+/// whether the return type was such a reference.
+fn add_static_lifetime(rt: &mut ReturnType) -> bool {
+    let ReturnType::Type(_, ty) = rt else {
+        return false;
+    };
+    let reference = match ty.as_mut() {
+        Type::Reference(tyr) => Some(tyr),
+        Type::Path(typ) => typ
+            .path
+            .segments
+            .last_mut()
+            .filter(|seg| seg.ident == "Pin")
+            .and_then(|seg| match &mut seg.arguments {
+                syn::PathArguments::AngleBracketed(aba) => match aba.args.first_mut() {
+                    Some(syn::GenericArgument::Type(Type::Reference(tyr))) => Some(tyr),
+                    _ => None,
+                },
+                _ => None,
+            }),
+        _ => None,
+    };
+    match reference {
+        Some(tyr) => {
+            tyr.lifetime = Some(parse_quote! { 'static });
+            true
+        }
+        None => false,
+    }
+}
+
 fn extract_type_from_pinned_mut_ref(ty: &TypePath) -> Type {
     match ty
         .path
@@ -2360,6 +3311,61 @@ impl HasFieldsAndBases for Api<FnPrePhase2> {
     }
 }
 
+/// Whether a factory function returns ownership of a new instance of
+/// `ty`, either as a `UniquePtr` or as a raw pointer.
+fn returns_owning_pointer_to(conversion: &TypeConversionPolicy, ty: &QualifiedName) -> bool {
+    let pointee = match conversion.cxxbridge_type() {
+        Type::Ptr(TypePtr {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => elem.as_ref(),
+        Type::Path(typ) => match typ.path.segments.last() {
+            Some(PathSegment {
+                ident,
+                arguments: PathArguments::AngleBracketed(args),
+            }) if ident == "UniquePtr" => match args.args.first() {
+                Some(GenericArgument::Type(pointee)) => pointee,
+                _ => return false,
+            },
+            _ => return false,
+        },
+        _ => return false,
+    };
+    matches!(pointee, Type::Path(typ) if QualifiedName::from_type_path(typ) == *ty)
+}
+
+/// Whether this returns a `UniquePtr` which C++ gave us directly, rather
+/// than one we made from a value, and which therefore might be null.
+fn returns_unconverted_unique_ptr(conversion: &TypeConversionPolicy) -> bool {
+    if conversion.cpp_work_needed() || conversion.rust_work_needed() {
+        return false;
+    }
+    match conversion.cxxbridge_type() {
+        Type::Path(typ) => typ
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "UniquePtr"),
+        _ => false,
+    }
+}
+
+/// Turn a `this: *mut T` parameter into `this: *const T`. Other parameters
+/// are returned unchanged.
+fn make_receiver_const(arg: &FnArg) -> FnArg {
+    let mut arg = arg.clone();
+    if let syn::FnArg::Typed(PatType { pat, ty, .. }) = &mut arg.0 {
+        if matches!(pat.as_ref(), Pat::Ident(pp) if pp.ident == "this") {
+            if let Type::Ptr(ptr) = ty.as_mut() {
+                ptr.mutability = None;
+                ptr.const_token = Some(syn::Token![const](Span::call_site()));
+            }
+        }
+    }
+    arg
+}
+
 /// Stringify a function argument for diagnostics
 fn describe_arg(arg: &syn::FnArg) -> String {
     match arg {
@@ -6,9 +6,29 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-type Offsets = HashMap<String, usize>;
+/// The overloads seen within a given scope (a namespace, or a type within
+/// that namespace), and the names we've handed out in it.
+#[derive(Default)]
+struct Scope {
+    offset_by_name: HashMap<String, usize>,
+    names_used: HashSet<String>,
+    /// The names which functions in this scope have naturally, before
+    /// any overload numbering.
+    names_reserved: HashSet<String>,
+    /// Names which only [`OverloadTracker::get_claimed_method_name`] may
+    /// hand out, even to functions which naturally have them.
+    names_claimed: HashSet<String>,
+}
+
+impl Scope {
+    fn is_free(&self, candidate: &str, natural: bool) -> bool {
+        !self.names_used.contains(candidate)
+            && !self.names_claimed.contains(candidate)
+            && (natural || !self.names_reserved.contains(candidate))
+    }
+}
 
 /// Registry of all the overloads of a function found within a given
 /// namespace (i.e. mod in bindgen's output). If necessary we'll append
@@ -20,36 +40,111 @@ type Offsets = HashMap<String, usize>;
 /// If bindgen adds a suffix it will be included in 'found_name'
 /// but not 'original_name' which is an annotation added by our autocxx-bindgen
 /// fork.
+///
+/// The policy is deterministic: overloads are numbered in the order in which
+/// they're declared, the first keeping its original name. Every function's
+/// natural name should be reserved up front, so that a number is never given
+/// to an overload if another function is genuinely called that (for
+/// instance, `foo1`); we keep counting until we find a free name instead.
+/// The user may instead specify names for each overload using the
+/// `overload_names!` directive, which are passed in here as `overrides`.
 #[derive(Default)]
 pub(crate) struct OverloadTracker {
-    offset_by_name: Offsets,
-    offset_by_type_and_name: HashMap<String, Offsets>,
+    by_type: HashMap<Option<String>, Scope>,
 }
 
 impl OverloadTracker {
-    pub(crate) fn get_function_real_name(&mut self, found_name: String) -> String {
-        self.get_name(None, found_name)
+    pub(crate) fn reserve_function_name(&mut self, natural_name: &str) {
+        self.reserve_name(None, natural_name)
     }
 
-    pub(crate) fn get_method_real_name(&mut self, type_name: &str, found_name: String) -> String {
-        self.get_name(Some(type_name), found_name)
+    pub(crate) fn reserve_method_name(&mut self, type_name: &str, natural_name: &str) {
+        self.reserve_name(Some(type_name), natural_name)
+    }
+
+    /// Keep `name` for a method which is renamed to it, such as a
+    /// `constructor_from!` factory renamed to `new`, so that it's not handed
+    /// to anything else in the type, even something naturally called that.
+    pub(crate) fn claim_method_name(&mut self, type_name: &str, name: &str) {
+        self.by_type
+            .entry(Some(type_name.to_string()))
+            .or_default()
+            .names_claimed
+            .insert(name.to_string());
+    }
+
+    /// Give a method the name it claimed with
+    /// [`OverloadTracker::claim_method_name`], or if several methods claimed
+    /// the same name, the first free number after it.
+    pub(crate) fn get_claimed_method_name(&mut self, type_name: &str, name: String) -> String {
+        let scope = self.by_type.entry(Some(type_name.to_string())).or_default();
+        let mut candidate = name.clone();
+        let mut candidate_offset = 0;
+        while scope.names_used.contains(&candidate) {
+            candidate_offset += 1;
+            candidate = format!("{name}{candidate_offset}");
+        }
+        scope.names_used.insert(candidate.clone());
+        candidate
     }
 
-    fn get_name(&mut self, type_name: Option<&str>, cpp_method_name: String) -> String {
-        let registry = match type_name {
-            Some(type_name) => self
-                .offset_by_type_and_name
-                .entry(type_name.to_string())
-                .or_default(),
-            None => &mut self.offset_by_name,
-        };
-        let offset = registry.entry(cpp_method_name.clone()).or_default();
-        let this_offset = *offset;
+    fn reserve_name(&mut self, type_name: Option<&str>, natural_name: &str) {
+        self.by_type
+            .entry(type_name.map(str::to_string))
+            .or_default()
+            .names_reserved
+            .insert(natural_name.to_string());
+    }
+
+    pub(crate) fn get_function_real_name(
+        &mut self,
+        found_name: String,
+        overrides: &[String],
+    ) -> String {
+        self.get_name(None, found_name, overrides)
+    }
+
+    pub(crate) fn get_method_real_name(
+        &mut self,
+        type_name: &str,
+        found_name: String,
+        overrides: &[String],
+    ) -> String {
+        self.get_name(Some(type_name), found_name, overrides)
+    }
+
+    fn get_name(
+        &mut self,
+        type_name: Option<&str>,
+        cpp_method_name: String,
+        overrides: &[String],
+    ) -> String {
+        let scope = self
+            .by_type
+            .entry(type_name.map(str::to_string))
+            .or_default();
+        let offset = scope
+            .offset_by_name
+            .entry(cpp_method_name.clone())
+            .or_default();
+        let this_overload = *offset;
         *offset += 1;
-        if this_offset == 0 {
-            cpp_method_name
-        } else {
-            format!("{cpp_method_name}{this_offset}")
+        if let Some(name) = overrides.get(this_overload) {
+            scope.names_used.insert(name.clone());
+            return name.clone();
+        }
+        let mut candidate_offset = this_overload;
+        loop {
+            let candidate = if candidate_offset == 0 {
+                cpp_method_name.clone()
+            } else {
+                format!("{cpp_method_name}{candidate_offset}")
+            };
+            if scope.is_free(&candidate, candidate_offset == 0) {
+                scope.names_used.insert(candidate.clone());
+                return candidate;
+            }
+            candidate_offset += 1;
         }
     }
 }
@@ -61,17 +156,77 @@ mod tests {
     #[test]
     fn test_by_function() {
         let mut ot = OverloadTracker::default();
-        assert_eq!(ot.get_function_real_name("bob".into()), "bob");
-        assert_eq!(ot.get_function_real_name("bob".into()), "bob1");
-        assert_eq!(ot.get_function_real_name("bob".into()), "bob2");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob1");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob2");
     }
 
     #[test]
     fn test_by_method() {
         let mut ot = OverloadTracker::default();
-        assert_eq!(ot.get_method_real_name("Ty1", "bob".into()), "bob");
-        assert_eq!(ot.get_method_real_name("Ty1", "bob".into()), "bob1");
-        assert_eq!(ot.get_method_real_name("Ty2", "bob".into()), "bob");
-        assert_eq!(ot.get_method_real_name("Ty2", "bob".into()), "bob1");
+        assert_eq!(ot.get_method_real_name("Ty1", "bob".into(), &[]), "bob");
+        assert_eq!(ot.get_method_real_name("Ty1", "bob".into(), &[]), "bob1");
+        assert_eq!(ot.get_method_real_name("Ty2", "bob".into(), &[]), "bob");
+        assert_eq!(ot.get_method_real_name("Ty2", "bob".into(), &[]), "bob1");
+    }
+
+    #[test]
+    fn test_suffix_collision() {
+        let mut ot = OverloadTracker::default();
+        ot.reserve_function_name("bob");
+        ot.reserve_function_name("bob1");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob2");
+        assert_eq!(ot.get_function_real_name("bob1".into(), &[]), "bob1");
+        assert_eq!(ot.get_function_real_name("bob".into(), &[]), "bob3");
+    }
+
+    #[test]
+    fn test_suffix_collision_declared_first() {
+        let mut ot = OverloadTracker::default();
+        ot.reserve_method_name("Ty", "get1");
+        ot.reserve_method_name("Ty", "get");
+        assert_eq!(ot.get_method_real_name("Ty", "get1".into(), &[]), "get1");
+        assert_eq!(ot.get_method_real_name("Ty", "get".into(), &[]), "get");
+        assert_eq!(ot.get_method_real_name("Ty", "get".into(), &[]), "get2");
+    }
+
+    #[test]
+    fn test_names_differing_in_case() {
+        let mut ot = OverloadTracker::default();
+        ot.reserve_function_name("Get");
+        ot.reserve_function_name("get");
+        ot.reserve_function_name("get1");
+        assert_eq!(ot.get_function_real_name("Get".into(), &[]), "Get");
+        assert_eq!(ot.get_function_real_name("get".into(), &[]), "get");
+        assert_eq!(ot.get_function_real_name("get".into(), &[]), "get2");
+        assert_eq!(ot.get_function_real_name("get1".into(), &[]), "get1");
+        assert_eq!(ot.get_function_real_name("Get".into(), &[]), "Get1");
+    }
+
+    #[test]
+    fn test_claimed_name() {
+        let mut ot = OverloadTracker::default();
+        ot.reserve_method_name("Ty", "new");
+        ot.claim_method_name("Ty", "new");
+        assert_eq!(ot.get_method_real_name("Ty", "new".into(), &[]), "new1");
+        assert_eq!(ot.get_claimed_method_name("Ty", "new".into()), "new");
+        assert_eq!(ot.get_method_real_name("Ty", "new".into(), &[]), "new2");
+        assert_eq!(ot.get_method_real_name("Ty2", "new".into(), &[]), "new");
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut ot = OverloadTracker::default();
+        let overrides = vec!["bob_int".to_string(), "bob_float".to_string()];
+        assert_eq!(
+            ot.get_function_real_name("bob".into(), &overrides),
+            "bob_int"
+        );
+        assert_eq!(
+            ot.get_function_real_name("bob".into(), &overrides),
+            "bob_float"
+        );
+        assert_eq!(ot.get_function_real_name("bob".into(), &overrides), "bob2");
     }
 }
@@ -0,0 +1,103 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! With `small_pods_in_registers!`, functions whose parameters and return
+//! value are all small POD types or scalars are called through a plain
+//! `extern "C"` function rather than through cxx, which would pass any
+//! struct through memory. This module decides which types qualify.
+
+use indexmap::set::IndexSet as HashSet;
+use syn::{Fields, ItemStruct, Type};
+
+use crate::{
+    conversion::{
+        analysis::pod::{PodAnalysis, PodPhase},
+        api::{Api, TypeKind},
+        apivec::ApiVec,
+    },
+    types::QualifiedName,
+};
+
+/// The largest number of fields a POD can have and still be passed in
+/// registers. Since none is bigger than a 64-bit scalar, this also keeps
+/// them within the 16 bytes which the common C calling conventions will
+/// pass in a pair of registers.
+const MAX_FIELDS: usize = 2;
+
+const SCALARS: &[&str] = &[
+    "bool",
+    "u8",
+    "i8",
+    "u16",
+    "i16",
+    "u32",
+    "i32",
+    "u64",
+    "i64",
+    "f32",
+    "f64",
+    "usize",
+    "isize",
+    "c_char",
+    "c_schar",
+    "c_uchar",
+    "c_short",
+    "c_ushort",
+    "c_int",
+    "c_uint",
+    "c_long",
+    "c_ulong",
+    "c_longlong",
+    "c_ulonglong",
+];
+
+/// Whether this is a primitive number, `bool` or raw pointer, each of
+/// which fits in a single register.
+pub(crate) fn is_scalar(ty: &Type) -> bool {
+    match ty {
+        Type::Ptr(_) => true,
+        Type::Path(typ) => {
+            typ.qself.is_none()
+                && typ.path.segments.last().is_some_and(|seg| {
+                    seg.arguments.is_empty() && SCALARS.contains(&seg.ident.to_string().as_str())
+                })
+        }
+        _ => false,
+    }
+}
+
+fn fits_in_registers(item: &ItemStruct) -> bool {
+    match &item.fields {
+        Fields::Named(fields) => {
+            !fields.named.is_empty()
+                && fields.named.len() <= MAX_FIELDS
+                && fields.named.iter().all(|field| is_scalar(&field.ty))
+        }
+        _ => false,
+    }
+}
+
+/// The non-generic POD types with at most two fields, each of which is a
+/// scalar.
+pub(super) fn find_small_pod_types(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                details,
+                analysis:
+                    PodAnalysis {
+                        kind: TypeKind::Pod,
+                        num_generics: 0,
+                        ..
+                    },
+            } if fits_in_registers(&details.item) => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
@@ -10,8 +10,9 @@ use std::ops::DerefMut;
 
 use indexmap::map::IndexMap as HashMap;
 
-use syn::{parse_quote, FnArg, PatType, Type, TypePtr};
+use syn::{parse_quote, FnArg, PatType, ReturnType, Type, TypePtr};
 
+use crate::conversion::analysis::fun::function_wrapper::RustConversionType;
 use crate::conversion::analysis::fun::{FnKind, MethodKind, ReceiverMutability, UnsafePolicy};
 use crate::conversion::analysis::pod::PodPhase;
 use crate::conversion::api::{
@@ -25,7 +26,7 @@ use crate::parse_callbacks::CppOriginalName;
 use crate::{
     conversion::{
         analysis::fun::function_wrapper::{
-            CppFunction, CppFunctionBody, CppFunctionKind, TypeConversionPolicy,
+            CppConversionType, CppFunction, CppFunctionBody, CppFunctionKind, TypeConversionPolicy,
         },
         api::{Api, ApiName},
     },
@@ -116,7 +117,6 @@ pub(super) fn create_subclass_function(
     receiver_mutability: &ReceiverMutability,
     superclass: &QualifiedName,
     dependencies: Vec<QualifiedName>,
-    unsafe_policy: &UnsafePolicy,
 ) -> Api<FnPrePhase1> {
     let cpp = sub.cpp();
     let holder_name = sub.holder();
@@ -125,8 +125,11 @@ pub(super) fn create_subclass_function(
         sub.0.name.get_final_item(),
         name.name.get_final_item()
     );
+    // The holder lives only as long as the C++ object, which C++ may
+    // delete during the call, so this can't be a reference: Rust assumes
+    // those remain valid until the function returns.
     let params = std::iter::once(crate::minisyn::FnArg(parse_quote! {
-        me: & #holder_name
+        me: *const #holder_name
     }))
     .chain(analysis.params.iter().skip(1).cloned())
     .collect();
@@ -141,11 +144,6 @@ pub(super) fn create_subclass_function(
         .skip(1)
         .map(|p| p.conversion.clone())
         .collect();
-    let requires_unsafe = if matches!(unsafe_policy, UnsafePolicy::AllFunctionsUnsafe) {
-        UnsafetyNeeded::Always
-    } else {
-        UnsafetyNeeded::from_param_details(&analysis.param_details, false)
-    };
     Api::RustSubclassFn {
         name: ApiName::new_in_root_namespace(make_ident(rust_call_name.clone())),
         subclass: sub.clone(),
@@ -165,11 +163,14 @@ pub(super) fn create_subclass_function(
                 kind,
                 pass_obs_field: true,
                 qualification: Some(cpp),
+                force_inline: false,
             },
             superclass: superclass.clone(),
             receiver_mutability: *receiver_mutability,
             dependencies,
-            requires_unsafe,
+            // cxx requires this of functions taking pointers. Only C++
+            // calls it.
+            requires_unsafe: UnsafetyNeeded::Always,
             is_pure_virtual: matches!(
                 analysis.kind,
                 FnKind::Method {
@@ -181,6 +182,48 @@ pub(super) fn create_subclass_function(
     }
 }
 
+/// The analysis of a virtual method as subclasses will override it, if
+/// `covariant_return!` asks that they return a pointer or reference to
+/// `derived` in place of the superclass's own return type. The
+/// superclass's implementation can't provide one of those, so subclasses
+/// must implement the method, as if it were pure virtual. Returns `None`
+/// if the method doesn't return a plain pointer or reference.
+pub(super) fn make_covariant(analysis: &FnAnalysis, derived: &QualifiedName) -> Option<FnAnalysis> {
+    let conversion = analysis.ret_conversion.as_ref()?;
+    if !matches!(
+        (&conversion.cpp_conversion, &conversion.rust_conversion),
+        (CppConversionType::None, RustConversionType::None)
+    ) {
+        return None;
+    }
+    let derived_path = Box::new(Type::Path(derived.to_type_path()));
+    let ty = match conversion.cxxbridge_type() {
+        Type::Ptr(ptr) if matches!(*ptr.elem, Type::Path(_)) => Type::Ptr(TypePtr {
+            elem: derived_path,
+            ..ptr.clone()
+        }),
+        Type::Reference(reference) if matches!(*reference.elem, Type::Path(_)) => {
+            Type::Reference(syn::TypeReference {
+                elem: derived_path,
+                ..reference.clone()
+            })
+        }
+        _ => return None,
+    };
+    let mut covariant = analysis.clone();
+    if let ReturnType::Type(_, ret_type) = &mut covariant.ret_type.0 {
+        **ret_type = ty.clone();
+    }
+    covariant.ret_conversion = Some(TypeConversionPolicy::new_unconverted(ty));
+    covariant.deps.insert(derived.clone());
+    if let FnKind::Method { method_kind, .. } = &mut covariant.kind {
+        if let MethodKind::Virtual(receiver_mutability) = method_kind {
+            *method_kind = MethodKind::PureVirtual(*receiver_mutability);
+        }
+    }
+    Some(covariant)
+}
+
 pub(super) fn create_subclass_constructor(
     sub: SubclassName,
     analysis: &FnAnalysis,
@@ -208,6 +251,7 @@ pub(super) fn create_subclass_constructor(
         kind: CppFunctionKind::SynthesizedConstructor,
         pass_obs_field: false,
         qualification: Some(cpp.clone()),
+        force_inline: false,
         original_cpp_name: CppEffectiveName::from_fully_qualified_name_for_subclass(
             &cpp.to_cpp_name(),
         ),
@@ -7,21 +7,38 @@
 // except according to those terms.
 
 pub(crate) mod abstract_types;
+mod adl;
 pub(crate) mod allocators;
 pub(crate) mod casts;
 pub(crate) mod constructor_deps;
+pub(crate) mod constructor_names;
+pub(crate) mod conversion_operators;
+pub(crate) mod covariant_returns;
 pub(crate) mod ctypes;
+mod custom_passes;
+pub(crate) mod deprecated_aliases;
 pub(crate) mod deps;
 mod depth_first;
 mod doc_label;
+pub(crate) mod error_types;
+pub(crate) mod force_inline;
 pub(crate) mod fun;
 pub(crate) mod gc;
 mod name_check;
+pub(crate) mod not_generated;
+pub(crate) mod object_identity;
 pub(crate) mod pod; // hey, that rhymes
+pub(crate) mod pod_defaults;
 pub(crate) mod remove_ignored;
 mod replace_hopeless_typedef_targets;
+mod synthetic_methods;
+pub(crate) mod tagged_unions;
 pub(crate) mod tdef;
 mod type_converter;
+pub(crate) mod type_names;
+pub(crate) mod virtual_destructors;
 
+pub(crate) use adl::associate_adl_functions;
+pub(crate) use custom_passes::run_custom_passes;
 pub(crate) use name_check::check_names;
 pub(crate) use replace_hopeless_typedef_targets::replace_hopeless_typedef_targets;
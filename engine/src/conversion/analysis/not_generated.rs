@@ -0,0 +1,166 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{ConversionErrorPolicy, IncludeCppConfig};
+
+use crate::conversion::{
+    api::{Api, ApiName, FuncToConvert, TypeKind},
+    apivec::ApiVec,
+    convert_error::ErrorContextType,
+    ConvertErrorFromCpp,
+};
+
+use super::{
+    fun::{FnAnalysis, FnPhase, PodAndDepAnalysis},
+    pod::PodAnalysis,
+};
+
+/// Fail if anything named in an `assert_not_generated!` directive has
+/// survived garbage collection and is therefore about to be generated.
+pub(crate) fn check_not_generated(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertErrorFromCpp> {
+    let asserted: Vec<_> = config.get_asserted_not_generated().collect();
+    if asserted.is_empty() {
+        return Ok(());
+    }
+    match apis
+        .iter()
+        .flat_map(names_for_directives)
+        .find(|name| asserted.contains(&name.as_str()))
+    {
+        Some(name) => Err(ConvertErrorFromCpp::AssertedNotGenerated(name)),
+        None => Ok(()),
+    }
+}
+
+/// Under `conversion_errors!(strict)`, fail if anything requested by
+/// `generate!`, `generate_pod!` or `generate_ns!` has been skipped,
+/// listing all such items and the reasons. Methods aren't requested
+/// individually, so a type whose methods were skipped still passes.
+pub(crate) fn check_requested_items_generated(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertErrorFromCpp> {
+    if config.conversion_error_policy() != ConversionErrorPolicy::Strict {
+        return Ok(());
+    }
+    let skipped: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::IgnoredItem { ctx, .. }
+                if matches!(
+                    ctx.as_ref().map(|ctx| ctx.get_type()),
+                    Some(ErrorContextType::Method { .. })
+                ) =>
+            {
+                None
+            }
+            Api::IgnoredItem { name, err, .. } => {
+                let cpp_name = name.qualified_cpp_name();
+                (config.is_explicitly_requested(&cpp_name)
+                    || config.is_explicitly_requested(&name.name.to_cpp_name()))
+                .then(|| format!("{cpp_name}: {err}"))
+            }
+            _ => None,
+        })
+        .collect();
+    if skipped.is_empty() {
+        Ok(())
+    } else {
+        Err(ConvertErrorFromCpp::RequestedItemsNotGenerated(
+            skipped.join("\n"),
+        ))
+    }
+}
+
+/// Fail if a `rust_equivalent!` type can't be converted: it must be a POD
+/// type which we've generated, or one given by `extern_cpp_type!`, in
+/// which case we don't know its fields and need to be told how to convert
+/// it.
+pub(crate) fn check_rust_equivalents(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertErrorFromCpp> {
+    for rust_equivalent in config.get_rust_equivalents() {
+        let ty = &rust_equivalent.ty;
+        match config.externs.0.get(ty) {
+            Some(extern_type) if extern_type.opaque => {
+                return Err(ConvertErrorFromCpp::RustEquivalentNotPod(ty.clone()))
+            }
+            Some(_) if rust_equivalent.conversions.is_none() => {
+                return Err(ConvertErrorFromCpp::RustEquivalentNeedsConversions(
+                    ty.clone(),
+                ))
+            }
+            Some(_) => {}
+            None => {
+                let is_generated_pod = apis.iter().any(|api| {
+                    matches!(api, Api::Struct {
+                        name,
+                        analysis:
+                            PodAndDepAnalysis {
+                                pod:
+                                    PodAnalysis {
+                                        kind: TypeKind::Pod,
+                                        num_generics: 0,
+                                        ..
+                                    },
+                                ..
+                            },
+                        ..
+                    } if name.name.to_cpp_name() == *ty)
+                });
+                if !is_generated_pod {
+                    return Err(ConvertErrorFromCpp::RustEquivalentNotPod(ty.clone()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The names by which the user might refer to this API in directives.
+fn names_for_directives(api: &Api<FnPhase>) -> Vec<String> {
+    match api {
+        Api::IgnoredItem { .. }
+        | Api::Function {
+            analysis:
+                FnAnalysis {
+                    ignore_reason: Err(_),
+                    ..
+                },
+            ..
+        } => Vec::new(),
+        Api::Function { name, fun, .. } => vec![function_directive_name(name, fun)],
+        _ => {
+            let name = api.name_info();
+            vec![name.name.to_cpp_name(), name.qualified_cpp_name()]
+        }
+    }
+}
+
+/// The name by which the user refers to this function or method in
+/// directives, e.g. `ns::Type::method`.
+pub(crate) fn function_directive_name(name: &ApiName, fun: &FuncToConvert) -> String {
+    let ident = fun.ident.to_string();
+    let cpp_name = name
+        .cpp_name_if_present()
+        .map(|cpp_name| cpp_name.for_validation())
+        .unwrap_or(&ident);
+    match &fun.self_ty {
+        Some(self_ty) => format!("{}::{cpp_name}", self_ty.to_cpp_name()),
+        None => name
+            .name
+            .ns_segment_iter()
+            .chain(std::iter::once(cpp_name))
+            .collect::<Vec<_>>()
+            .join("::"),
+    }
+}
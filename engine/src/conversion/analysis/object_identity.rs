@@ -0,0 +1,59 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! If `object_identity!()` is given, we add an `object_identity()` method
+//! to each polymorphic type, returning the address of the most-derived
+//! object, so that Rust can tell whether references to different base
+//! subobjects refer to the same object.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    synthetic_methods::create_synthetic_method,
+    type_names::find_polymorphic_types,
+};
+
+pub(crate) fn add_object_identity_methods(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    if !config.object_identity() {
+        return apis;
+    }
+    let polymorphic_types = find_polymorphic_types(&apis);
+    apis.into_iter()
+        .flat_map(|api| {
+            let method = match &api {
+                Api::Struct { name, .. } if polymorphic_types.contains(&name.name) => {
+                    Some(create_object_identity_method(&name.name))
+                }
+                _ => None,
+            };
+            method.into_iter().chain(std::iter::once(api))
+        })
+        .collect()
+}
+
+fn create_object_identity_method(ty_name: &QualifiedName) -> Api<PodPhase> {
+    create_synthetic_method(
+        ty_name,
+        "object_identity",
+        Some("The address of the most-derived object of which this is a part, which is the same whichever base class it's referred to through.".into()),
+        CppFunctionKind::Method,
+        parse_quote! { -> usize },
+        CppFunctionBody::ObjectIdentity,
+    )
+}
@@ -11,13 +11,14 @@ use crate::{conversion::ConvertErrorFromCpp, known_types::known_types};
 use crate::{
     conversion::{
         analysis::tdef::TypedefPhase,
-        api::{Api, TypedefKind},
+        api::{Api, FuncToConvert, TypedefKind},
     },
     types::{Namespace, QualifiedName},
 };
+use autocxx_bindgen::callbacks::{Explicitness, SpecialMemberKind};
 use autocxx_parser::IncludeCppConfig;
-use std::collections::HashMap;
-use syn::{ItemStruct, Type};
+use std::collections::{HashMap, HashSet};
+use syn::{ItemStruct, Type, TypePtr};
 
 #[derive(Clone)]
 enum PodState {
@@ -30,14 +31,35 @@ enum PodState {
 #[derive(Clone)]
 struct StructDetails {
     state: PodState,
-    dependent_structs: Vec<QualifiedName>,
+    /// Each field's name and type.
+    fields: Vec<(String, QualifiedName)>,
+    /// The field, if any, which made this unsafe to be POD.
+    culprit: Option<(String, QualifiedName)>,
 }
 
 impl StructDetails {
     fn new(state: PodState) -> Self {
         StructDetails {
             state,
-            dependent_structs: Vec::new(),
+            fields: Vec::new(),
+            culprit: None,
+        }
+    }
+}
+
+/// Why a type can't be POD: the chain of fields leading to the problem,
+/// outermost first, and the problem itself.
+struct PodBlocker {
+    fields: Vec<String>,
+    reason: String,
+}
+
+impl std::fmt::Display for PodBlocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{} (via {})", self.reason, self.fields.join(" -> "))
         }
     }
 }
@@ -54,6 +76,13 @@ impl StructDetails {
 pub struct ByValueChecker {
     // Mapping from type name to whether it is safe to be POD
     results: HashMap<QualifiedName, StructDetails>,
+    // Types with a user-declared destructor, or copy or move constructor.
+    // Such types may still be made POD on request, but we don't do so
+    // unasked.
+    nontrivial_special_members: HashSet<QualifiedName>,
+    // Types requested as POD which `allow_pod_downgrade!` let us generate
+    // as non-POD instead, and why.
+    pod_downgrades: HashMap<QualifiedName, String>,
 }
 
 impl ByValueChecker {
@@ -67,7 +96,11 @@ impl ByValueChecker {
             };
             results.insert(tn.clone(), StructDetails::new(safety));
         }
-        ByValueChecker { results }
+        ByValueChecker {
+            results,
+            nontrivial_special_members: HashSet::new(),
+            pod_downgrades: HashMap::new(),
+        }
     }
 
     /// Scan APIs to work out which are by-value safe. Constructs a [ByValueChecker]
@@ -126,6 +159,11 @@ impl ByValueChecker {
                         .results
                         .insert(api.name().clone(), StructDetails::new(PodState::IsPod));
                 }
+                Api::Function { fun, .. } => {
+                    if let Some(ty) = nontrivial_special_member_of(fun) {
+                        byvalue_checker.nontrivial_special_members.insert(ty);
+                    }
+                }
                 _ => {}
             }
         }
@@ -134,14 +172,38 @@ impl ByValueChecker {
                 byvalue_checker.ingest_struct(&details.item, api.name().get_namespace())
             }
         }
-        let pod_requests = config
-            .get_pod_requests()
-            .iter()
-            .map(|ty| QualifiedName::new_from_cpp_name(ty))
-            .collect();
-        byvalue_checker
-            .satisfy_requests(pod_requests)
-            .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+        for cpp_name in config.get_pod_requests() {
+            let ty_id = QualifiedName::new_from_cpp_name(cpp_name);
+            if let Some(blocker) = byvalue_checker.find_pod_blocker(&ty_id, &mut HashSet::new()) {
+                if config.is_pod_downgrade_allowed(cpp_name) {
+                    log::warn!("Generating {} as non-POD, since: {}", cpp_name, blocker);
+                    byvalue_checker
+                        .pod_downgrades
+                        .insert(ty_id, blocker.to_string());
+                    continue;
+                }
+                if config.strict_pod() {
+                    return Err(ConvertErrorFromCpp::PodBlocked(
+                        cpp_name.clone(),
+                        blocker.to_string(),
+                    ));
+                }
+            }
+            byvalue_checker
+                .satisfy_requests(vec![ty_id])
+                .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+        }
+        for api in apis.iter() {
+            if let Api::Struct { name, .. } = api {
+                if config.is_pod_if_possible(&name.name.to_cpp_name())
+                    && byvalue_checker.could_be_pod(&name.name)
+                {
+                    byvalue_checker
+                        .satisfy_requests(vec![name.name.clone()])
+                        .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+                }
+            }
+        }
         Ok(byvalue_checker)
     }
 
@@ -149,8 +211,9 @@ impl ByValueChecker {
         // For this struct, work out whether it _could_ be safe as a POD.
         let tyname = QualifiedName::new(ns, def.ident.clone().into());
         let mut field_safety_problem = PodState::SafeToBePod;
-        let fieldlist = Self::get_field_types(def);
-        for ty_id in &fieldlist {
+        let mut culprit = None;
+        let fieldlist = Self::get_fields(def);
+        for (field, ty_id) in &fieldlist {
             match self.results.get(ty_id) {
                 None if ty_id.get_final_item() == "__BindgenUnionField" => {
                     field_safety_problem = PodState::UnsafeToBePod(format!(
@@ -168,12 +231,14 @@ impl ByValueChecker {
                     field_safety_problem = PodState::UnsafeToBePod(format!(
                         "Type {tyname} could not be POD because its dependent type {ty_id} isn't known"
                     ));
+                    culprit = Some((field.clone(), ty_id.clone()));
                     break;
                 }
                 Some(deets) => {
                     if let PodState::UnsafeToBePod(reason) = &deets.state {
                         let new_reason = format!("Type {tyname} could not be POD because its dependent type {ty_id} isn't safe to be POD. Because: {reason}");
                         field_safety_problem = PodState::UnsafeToBePod(new_reason);
+                        culprit = Some((field.clone(), ty_id.clone()));
                         break;
                     }
                 }
@@ -183,9 +248,11 @@ impl ByValueChecker {
             let reason =
                 format!("Type {tyname} could not be POD because it has virtual functions.");
             field_safety_problem = PodState::UnsafeToBePod(reason);
+            culprit = None;
         }
         let mut my_details = StructDetails::new(field_safety_problem);
-        my_details.dependent_structs = fieldlist;
+        my_details.fields = fieldlist;
+        my_details.culprit = culprit;
         self.results.insert(tyname, my_details);
     }
 
@@ -213,7 +280,7 @@ impl ByValueChecker {
                     PodState::IsPod => {}
                     PodState::SafeToBePod => {
                         deets.state = PodState::IsPod;
-                        requests.extend_from_slice(&deets.dependent_structs);
+                        requests.extend(deets.fields.iter().map(|(_, ty)| ty.clone()));
                     }
                     PodState::IsAlias(target_type) => {
                         alias_to_consider = Some(target_type.clone());
@@ -234,6 +301,99 @@ impl ByValueChecker {
         Ok(())
     }
 
+    /// Whether `satisfy_requests` would succeed for this type, and it (and
+    /// everything within it) is trivially movable and destructible, so
+    /// that we can make it POD without the user asking.
+    fn could_be_pod(&self, ty_id: &QualifiedName) -> bool {
+        let mut todo = vec![ty_id.clone()];
+        let mut done = HashSet::new();
+        while let Some(ty_id) = todo.pop() {
+            if !done.insert(ty_id.clone()) {
+                continue;
+            }
+            if self.nontrivial_special_members.contains(&ty_id) {
+                return false;
+            }
+            match self.results.get(&ty_id).map(|deets| &deets.state) {
+                None | Some(PodState::UnsafeToBePod(_)) => return false,
+                Some(PodState::IsPod) => {}
+                Some(PodState::SafeToBePod) => {
+                    todo.extend(self.results[&ty_id].fields.iter().map(|(_, ty)| ty.clone()))
+                }
+                Some(PodState::IsAlias(target)) => todo.push(target.clone()),
+            }
+        }
+        true
+    }
+
+    /// Find out why `satisfy_requests` would fail for this type, if it
+    /// would, following the fields responsible down to the problem.
+    fn find_pod_blocker(
+        &self,
+        ty_id: &QualifiedName,
+        done: &mut HashSet<QualifiedName>,
+    ) -> Option<PodBlocker> {
+        if !done.insert(ty_id.clone()) {
+            return None;
+        }
+        let deets = match self.results.get(ty_id) {
+            None => {
+                return Some(PodBlocker {
+                    fields: Vec::new(),
+                    reason: format!("we never saw a struct definition for {ty_id}"),
+                })
+            }
+            Some(deets) => deets,
+        };
+        let blocked_field = |(field, field_ty): &(String, QualifiedName),
+                             done: &mut HashSet<QualifiedName>| {
+            let mut blocker = self.find_pod_blocker(field_ty, done)?;
+            blocker.fields.insert(0, Self::describe_field(ty_id, field));
+            Some(blocker)
+        };
+        match &deets.state {
+            PodState::IsPod => None,
+            PodState::IsAlias(target) => self.find_pod_blocker(target, done),
+            PodState::UnsafeToBePod(reason) => {
+                // The field's own explanation is more specific than ours,
+                // unless it's something bindgen made up which we never saw.
+                let field_blocker = deets
+                    .culprit
+                    .as_ref()
+                    .filter(|(_, field_ty)| self.results.contains_key(field_ty))
+                    .and_then(|culprit| blocked_field(culprit, done));
+                field_blocker.or_else(|| {
+                    Some(PodBlocker {
+                        fields: deets
+                            .culprit
+                            .iter()
+                            .map(|(field, _)| Self::describe_field(ty_id, field))
+                            .collect(),
+                        reason: reason.clone(),
+                    })
+                })
+            }
+            PodState::SafeToBePod => deets
+                .fields
+                .iter()
+                .find_map(|field| blocked_field(field, done)),
+        }
+    }
+
+    fn describe_field(ty_id: &QualifiedName, field: &str) -> String {
+        if field.starts_with("_base") {
+            format!("a base class of {ty_id}")
+        } else {
+            format!("{ty_id}::{field}")
+        }
+    }
+
+    /// If `allow_pod_downgrade!` let this type be generated as non-POD
+    /// despite a `generate_pod!` directive, why it couldn't be POD.
+    pub(crate) fn pod_downgrade_reason(&self, ty_id: &QualifiedName) -> Option<&str> {
+        self.pod_downgrades.get(ty_id).map(String::as_str)
+    }
+
     /// Return whether a given type is POD (i.e. can be represented by value in Rust) or not.
     /// Unless we've got a definite record that it _is_, we return false.
     /// Some types won't be in our `results` map. For example: (a) AutocxxConcrete types
@@ -244,7 +404,7 @@ impl ByValueChecker {
             self.results.get(ty_id),
             Some(StructDetails {
                 state: PodState::IsPod,
-                dependent_structs: _,
+                ..
             })
         )
     }
@@ -253,12 +413,12 @@ impl ByValueChecker {
     /// It would be nice to unify them. However, this version only cares about spotting
     /// fields which may be non-POD, so can largely concern itself with just `Type::Path`
     /// fields.
-    fn get_field_types(def: &ItemStruct) -> Vec<QualifiedName> {
+    fn get_fields(def: &ItemStruct) -> Vec<(String, QualifiedName)> {
         let mut results = Vec::new();
         for f in &def.fields {
             let fty = &f.ty;
-            if let Type::Path(p) = fty {
-                results.push(QualifiedName::from_type_path(p));
+            if let (Some(id), Type::Path(p)) = (&f.ident, fty) {
+                results.push((id.to_string(), QualifiedName::from_type_path(p)));
             }
             // TODO handle anything else which bindgen might spit out, e.g. arrays?
         }
@@ -275,11 +435,38 @@ impl ByValueChecker {
     }
 }
 
+/// If this is a destructor, copy constructor or move constructor which the
+/// user wrote themselves, the type to which it belongs.
+fn nontrivial_special_member_of(fun: &FuncToConvert) -> Option<QualifiedName> {
+    if !matches!(
+        fun.special_member,
+        Some(
+            SpecialMemberKind::Destructor
+                | SpecialMemberKind::CopyConstructor
+                | SpecialMemberKind::MoveConstructor
+        )
+    ) || matches!(fun.is_deleted, Some(Explicitness::Defaulted))
+    {
+        return None;
+    }
+    match &fun.inputs.first()?.0 {
+        syn::FnArg::Typed(pt) => match pt.ty.as_ref() {
+            Type::Ptr(TypePtr { elem, .. }) => match elem.as_ref() {
+                Type::Path(typ) => Some(QualifiedName::from_type_path(typ)),
+                _ => None,
+            },
+            _ => None,
+        },
+        syn::FnArg::Receiver(_) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::ByValueChecker;
     use crate::minisyn::ItemStruct;
     use crate::types::{Namespace, QualifiedName};
+    use std::collections::HashSet;
     use syn::parse_quote;
 
     fn ty_from_ident(id: &syn::Ident) -> QualifiedName {
@@ -358,4 +545,27 @@ mod tests {
         bvc.ingest_struct(&t, &Namespace::new());
         assert!(bvc.satisfy_requests(vec![t_id]).is_err());
     }
+
+    #[test]
+    fn test_blocker_names_fields() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Inner {
+                a: i32,
+                name: CxxString,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            struct Outer {
+                b: i64,
+                inner: Inner,
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        let blocker = bvc.find_pod_blocker(&t_id, &mut HashSet::new()).unwrap();
+        assert_eq!(blocker.fields, vec!["Outer::inner", "Inner::name"]);
+        assert!(bvc.satisfy_requests(vec![t_id]).is_err());
+    }
 }
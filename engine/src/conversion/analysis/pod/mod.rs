@@ -23,6 +23,7 @@ use crate::{
         check_for_fatal_attrs,
         convert_error::{ConvertErrorWithContext, ErrorContext},
         error_reporter::convert_apis,
+        type_helpers::shared_struct_field_type,
         ConvertErrorFromCpp,
     },
     types::{Namespace, QualifiedName},
@@ -57,6 +58,13 @@ pub(crate) struct PodAnalysis {
     pub(crate) field_info: Vec<FieldInfo>,
     pub(crate) num_generics: usize,
     pub(crate) in_anonymous_namespace: bool,
+    /// Whether the destructor is virtual, either explicitly or because
+    /// that of a base class is. Filled in by
+    /// [`super::virtual_destructors::mark_virtual_destructors`].
+    pub(crate) has_virtual_destructor: bool,
+    /// If this was requested as POD but `allow_pod_downgrade!` let it be
+    /// non-POD instead, why it couldn't be POD.
+    pub(crate) pod_downgrade_reason: Option<String>,
 }
 
 #[derive(std::fmt::Debug)]
@@ -154,10 +162,12 @@ fn analyze_struct(
     let mut field_deps = HashSet::new();
     let mut field_definition_deps = HashSet::new();
     let mut field_info = Vec::new();
+    let cpp_name = name.name.to_cpp_name();
     let field_conversion_errors = get_struct_field_types(
         type_converter,
         name.name.get_namespace(),
         &details.item,
+        |field| config.is_member_fn_field(&cpp_name, field),
         &mut field_deps,
         &mut field_definition_deps,
         &mut field_info,
@@ -180,10 +190,30 @@ fn analyze_struct(
                 Some(ErrorContext::new_for_item(id)),
             ));
         }
+        if config
+            .get_member_fn_fields()
+            .any(|member_fn_field| member_fn_field.table == cpp_name)
+        {
+            return Err(ConvertErrorWithContext(
+                ConvertErrorFromCpp::MemberFnFieldInPod(name.name),
+                Some(ErrorContext::new_for_item(id)),
+            ));
+        }
+        if config.is_thread_affine(&name.name.to_cpp_name()) {
+            return Err(ConvertErrorWithContext(
+                ConvertErrorFromCpp::ThreadAffinePod(name.name),
+                Some(ErrorContext::new_for_item(id)),
+            ));
+        }
         TypeKind::Pod
     } else {
         TypeKind::NonPod
     };
+    if config.is_shared_struct(&name.name.to_cpp_name()) {
+        check_shared_struct(&name, &details.item, &type_kind, &bases).map_err(|err| {
+            ConvertErrorWithContext(err, Some(ErrorContext::new_for_item(id.clone())))
+        })?;
+    }
     let castable_bases = bases
         .iter()
         .filter(|(_, is_public)| **is_public)
@@ -192,10 +222,7 @@ fn analyze_struct(
         .cloned()
         .collect();
     let num_generics = details.item.generics.params.len();
-    let in_anonymous_namespace = name
-        .name
-        .ns_segment_iter()
-        .any(|ns| ns.starts_with("_bindgen_mod"));
+    let in_anonymous_namespace = name.name.get_namespace().contains_anonymous();
     Ok(Box::new(std::iter::once(Api::Struct {
         name,
         details,
@@ -208,14 +235,74 @@ fn analyze_struct(
             field_info,
             num_generics,
             in_anonymous_namespace,
+            has_virtual_destructor: false,
+            pod_downgrade_reason: byvalue_checker
+                .pod_downgrade_reason(&name.name)
+                .map(str::to_string),
         },
     })))
 }
 
+/// cxx defines a shared struct itself, so it must be a plain collection
+/// of fields which it knows how to represent.
+fn check_shared_struct(
+    name: &ApiName,
+    item: &ItemStruct,
+    type_kind: &TypeKind,
+    bases: &HashMap<QualifiedName, bool>,
+) -> Result<(), ConvertErrorFromCpp> {
+    let unsupported =
+        |reason: String| ConvertErrorFromCpp::UnsupportedSharedStruct(name.name.clone(), reason);
+    if !matches!(type_kind, TypeKind::Pod) {
+        return Err(unsupported(
+            "it must also be generated with generate_pod!".into(),
+        ));
+    }
+    if !item.generics.params.is_empty() || name.cpp_name_if_present().is_some() {
+        return Err(unsupported(
+            "it must be a non-template type which isn't nested in another".into(),
+        ));
+    }
+    if !bases.is_empty() {
+        return Err(unsupported("it mustn't have base classes".into()));
+    }
+    // bindgen gives an empty struct a placeholder field, so that it has
+    // the size of one byte as in C++.
+    if item.fields.iter().all(|field| {
+        field
+            .ident
+            .as_ref()
+            .map_or(true, |id| id.to_string().starts_with('_'))
+    }) {
+        return Err(unsupported(
+            "it must have fields, since cxx doesn't allow empty shared structs".into(),
+        ));
+    }
+    for field in &item.fields {
+        let field_name = field
+            .ident
+            .as_ref()
+            .map(|id| id.to_string())
+            .unwrap_or_default();
+        if !matches!(field.vis, Visibility::Public(_)) || field_name.starts_with('_') {
+            return Err(unsupported(format!(
+                "field {field_name} isn't a plain public field"
+            )));
+        }
+        if shared_struct_field_type(&field.ty).is_none() {
+            return Err(unsupported(format!(
+                "field {field_name} isn't a number, bool, or array of them"
+            )));
+        }
+    }
+    Ok(())
+}
+
 fn get_struct_field_types(
     type_converter: &mut TypeConverter,
     ns: &Namespace,
     s: &ItemStruct,
+    is_member_fn_field: impl Fn(&str) -> bool,
     field_deps: &mut HashSet<QualifiedName>,
     field_definition_deps: &mut HashSet<QualifiedName>,
     field_info: &mut Vec<FieldInfo>,
@@ -229,6 +316,15 @@ fn get_struct_field_types(
         .collect();
     let type_conversion_context = TypeConversionContext::WithinStructField { struct_type_params };
     for f in &s.fields {
+        // bindgen doesn't know what to make of member function pointers,
+        // so skip any which member_fn_field! will handle.
+        if f.ident
+            .as_ref()
+            .map(|id| is_member_fn_field(&id.to_string()))
+            .unwrap_or_default()
+        {
+            continue;
+        }
         let annotated = type_converter.convert_type(f.ty.clone(), ns, &type_conversion_context);
         match annotated {
             Ok(mut r) => {
@@ -0,0 +1,77 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! POD types listed in `pod_default!` get a `value_initialized` function
+//! which returns `T{}` from C++, so that default member initializers apply
+//! and any other fields are zeroed. Code generation uses that function to
+//! implement `Default`, so that Rust code can write
+//! `T { x: 1, ..Default::default() }` much as C++ would write `T{ .x = 1 }`.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{
+        api::{Api, TypeKind},
+        apivec::ApiVec,
+        ConvertErrorFromCpp,
+    },
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::{PodAnalysis, PodPhase},
+    synthetic_methods::create_synthetic_method,
+};
+
+pub(crate) fn add_value_initializers(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> Result<ApiVec<PodPhase>, ConvertErrorFromCpp> {
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        if let Api::Struct { name, analysis, .. } = &api {
+            if config.has_pod_default(&name.name.to_cpp_name()) {
+                // Only a POD type can be returned by value, so only for
+                // those can `Default` be implemented in terms of this.
+                if !matches!(
+                    analysis,
+                    PodAnalysis {
+                        kind: TypeKind::Pod,
+                        ..
+                    }
+                ) {
+                    return Err(ConvertErrorFromCpp::PodDefaultNotPod(
+                        name.name.to_cpp_name(),
+                    ));
+                }
+                results.push(create_value_initializer(&name.name));
+            }
+        }
+        results.push(api);
+    }
+    Ok(results)
+}
+
+fn create_value_initializer(ty_name: &QualifiedName) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let doc = format!(
+        "A value-initialized `{}`, as from `{}{{}}` in C++.",
+        ty_name.get_final_item(),
+        ty_name.to_cpp_name()
+    );
+    create_synthetic_method(
+        ty_name,
+        "value_initialized",
+        Some(doc),
+        CppFunctionKind::Function,
+        parse_quote! { -> #typ },
+        CppFunctionBody::ValueInitialize(ty_name.clone()),
+    )
+}
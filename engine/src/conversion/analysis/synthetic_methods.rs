@@ -0,0 +1,77 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::parse_quote;
+
+use crate::{
+    conversion::api::{Api, ApiName, CppVisibility, FuncToConvert, Provenance},
+    minisyn::{Attribute, FnArg, ReturnType},
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+/// Make a method of `ty_name` whose C++ implementation we write ourselves,
+/// as `body`. If `kind` is [`CppFunctionKind::Method`] it takes a const
+/// receiver and nothing else; otherwise it's a static method taking
+/// nothing at all.
+///
+/// Code generation relies on these methods being safe to call on any
+/// valid reference: if they're `unsafe`, that's only because of the
+/// unsafe policy.
+pub(crate) fn create_synthetic_method(
+    ty_name: &QualifiedName,
+    method_name: &str,
+    doc: Option<String>,
+    kind: CppFunctionKind,
+    output: ReturnType,
+    body: CppFunctionBody,
+) -> Api<PodPhase> {
+    let inputs = match kind {
+        CppFunctionKind::Method => {
+            let typ = ty_name.to_type_path();
+            let this: FnArg = parse_quote! {
+                this: *const #typ
+            };
+            [this].into_iter().collect()
+        }
+        _ => Default::default(),
+    };
+    let api_name = QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!("{}_{}", ty_name.get_final_item(), method_name)),
+    );
+    Api::Function {
+        name: ApiName::new_from_qualified_name(api_name),
+        fun: Box::new(FuncToConvert {
+            ident: make_ident(method_name),
+            doc_attrs: doc
+                .map(|doc| Attribute(parse_quote! { #[doc = #doc] }))
+                .into_iter()
+                .collect(),
+            inputs,
+            output,
+            vis: parse_quote! { pub },
+            virtualness: None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            original_name: None,
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            add_to_trait: None,
+            synthetic_cpp: Some((body, kind)),
+            is_deleted: None,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+        }),
+        analysis: (),
+    }
+}
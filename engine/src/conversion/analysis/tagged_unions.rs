@@ -0,0 +1,92 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create accessor functions for the tag and union members
+//! of types listed in `tagged_union!` directives. The Rust enum view
+//! which uses these accessors is generated in `codegen_rs`.
+
+use autocxx_parser::{IncludeCppConfig, TaggedUnion};
+use syn::parse_quote;
+
+use crate::{
+    conversion::{api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    synthetic_methods::create_synthetic_method,
+};
+
+pub(crate) fn add_tagged_union_accessors(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    apis.into_iter()
+        .flat_map(|api| -> Box<dyn Iterator<Item = Api<PodPhase>>> {
+            match &api {
+                Api::Struct { name, .. } => {
+                    match config.get_tagged_union(&name.name.to_cpp_name()) {
+                        Some(tagged_union) => Box::new(
+                            create_accessors(&name.name, tagged_union).chain(std::iter::once(api)),
+                        ),
+                        None => Box::new(std::iter::once(api)),
+                    }
+                }
+                _ => Box::new(std::iter::once(api)),
+            }
+        })
+        .collect()
+}
+
+/// The name of the method which returns the tag of a tagged union.
+pub(crate) fn tag_accessor_name() -> String {
+    "tagged_union_tag".into()
+}
+
+/// The name of the method which returns a given union member.
+pub(crate) fn member_accessor_name(variant: &syn::Ident) -> String {
+    format!("tagged_union_{}", variant.to_string().to_lowercase())
+}
+
+fn create_accessors(
+    ty_name: &QualifiedName,
+    tagged_union: &TaggedUnion,
+) -> impl Iterator<Item = Api<PodPhase>> {
+    let tag_accessor = create_synthetic_method(
+        ty_name,
+        &tag_accessor_name(),
+        None,
+        CppFunctionKind::Method,
+        parse_quote! { -> i64 },
+        CppFunctionBody::MemberAccess {
+            member: tagged_union.tag.clone(),
+            cast_to: Some("int64_t"),
+        },
+    );
+    let member_accessors = tagged_union
+        .variants
+        .iter()
+        .map(|variant| {
+            let member_ty = QualifiedName::new_from_cpp_name(&variant.ty).to_type_path();
+            create_synthetic_method(
+                ty_name,
+                &member_accessor_name(&variant.name),
+                None,
+                CppFunctionKind::Method,
+                parse_quote! { -> __bindgen_marker_Reference < *const #member_ty > },
+                CppFunctionBody::MemberAccess {
+                    member: variant.member.clone(),
+                    cast_to: None,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    std::iter::once(tag_accessor).chain(member_accessors)
+}
@@ -6,10 +6,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
 use autocxx_parser::IncludeCppConfig;
-use syn::ItemType;
+use quote::ToTokens;
+use syn::{ItemType, Type};
 
 use crate::{
     conversion::{
@@ -60,6 +62,7 @@ pub(crate) fn convert_typedef_targets(
         |name, item, old_tyname, _| {
             Ok(Box::new(std::iter::once(match item {
                 TypedefKind::Type(ity) => get_replacement_typedef(
+                    config,
                     name,
                     ity.into(),
                     old_tyname,
@@ -80,10 +83,83 @@ pub(crate) fn convert_typedef_targets(
         },
     );
     results.extend(extra_apis.into_iter().map(add_analysis));
-    results
+    resolve_typedef_chains(config, results)
+}
+
+/// Point each typedef directly at the type to which it ultimately refers,
+/// rather than at another typedef. That intermediate typedef may be in some
+/// other namespace, and may be ignored or garbage collected, which would
+/// otherwise take this typedef down with it. Newtypes are distinct types
+/// in Rust, so chains stop there.
+fn resolve_typedef_chains(
+    config: &IncludeCppConfig,
+    apis: ApiVec<TypedefPhase>,
+) -> ApiVec<TypedefPhase> {
+    let targets: HashMap<QualifiedName, (Type, HashSet<QualifiedName>)> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Typedef { name, .. } if config.is_newtype(&name.name.to_cpp_name()) => None,
+            Api::Typedef {
+                name,
+                analysis:
+                    TypedefAnalysis {
+                        kind: TypedefKind::Type(ity),
+                        deps,
+                    },
+                ..
+            } => Some((name.name.clone(), ((*ity.ty).clone(), deps.clone()))),
+            _ => None,
+        })
+        .collect();
+    apis.into_iter()
+        .map(|mut api| {
+            if let Api::Typedef {
+                name,
+                analysis:
+                    TypedefAnalysis {
+                        kind: TypedefKind::Type(ity),
+                        deps,
+                    },
+                ..
+            } = &mut api
+            {
+                if let Some((ty, final_deps)) = find_ultimate_target(&name.name, &targets) {
+                    ity.0.ty = Box::new(ty.clone());
+                    *deps = final_deps.clone();
+                }
+            }
+            api
+        })
+        .collect()
+}
+
+/// Follow a chain of typedefs starting at `name`, returning the final
+/// target and its dependencies if there was more than one link.
+fn find_ultimate_target<'a>(
+    name: &QualifiedName,
+    targets: &'a HashMap<QualifiedName, (Type, HashSet<QualifiedName>)>,
+) -> Option<&'a (Type, HashSet<QualifiedName>)> {
+    let mut encountered = HashSet::new();
+    let mut current = targets.get(name)?;
+    let mut followed = false;
+    while let Type::Path(typ) = &current.0 {
+        let next_name = QualifiedName::from_type_path(typ);
+        let next = match targets.get(&next_name) {
+            Some(next) => next,
+            None => break,
+        };
+        if !encountered.insert(next_name) {
+            // A loop; this is reported elsewhere.
+            return None;
+        }
+        current = next;
+        followed = true;
+    }
+    followed.then_some(current)
 }
 
 fn get_replacement_typedef(
+    config: &IncludeCppConfig,
     name: ApiName,
     ity: ItemType,
     old_tyname: Option<QualifiedName>,
@@ -116,6 +192,16 @@ fn get_replacement_typedef(
             ConvertErrorFromCpp::InfinitelyRecursiveTypedef(name.name.clone()),
             Some(ErrorContext::new_for_item(name.name.get_final_ident())),
         )),
+        Ok(final_type)
+            if config.is_newtype(&name.name.to_cpp_name()) && !is_integer_type(&final_type.ty) =>
+        {
+            Err(ConvertErrorWithContext(
+                ConvertErrorFromCpp::NewtypeOfNonIntegerType(
+                    final_type.ty.to_token_stream().to_string(),
+                ),
+                Some(ErrorContext::new_for_item(name.name.get_final_ident())),
+            ))
+        }
         Ok(mut final_type) => {
             converted_type.ty = Box::new(final_type.ty.clone());
             extra_apis.append(&mut final_type.extra_apis);
@@ -131,3 +217,42 @@ fn get_replacement_typedef(
         }
     }
 }
+
+/// Whether a typedef target is one of the integer types which `newtype!`
+/// knows how to wrap: a Rust primitive, or one of autocxx's C integer
+/// wrappers such as `autocxx::c_int`.
+fn is_integer_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(typ) => typ
+            .path
+            .segments
+            .last()
+            .map(|seg| {
+                matches!(
+                    seg.ident.to_string().as_str(),
+                    "i8" | "i16"
+                        | "i32"
+                        | "i64"
+                        | "isize"
+                        | "u8"
+                        | "u16"
+                        | "u32"
+                        | "u64"
+                        | "usize"
+                        | "c_char"
+                        | "c_schar"
+                        | "c_uchar"
+                        | "c_short"
+                        | "c_ushort"
+                        | "c_int"
+                        | "c_uint"
+                        | "c_long"
+                        | "c_ulong"
+                        | "c_longlong"
+                        | "c_ulonglong"
+                )
+            })
+            .unwrap_or_default(),
+        _ => false,
+    }
+}
@@ -126,7 +126,7 @@ impl<'a> TypeConverter<'a> {
     {
         Self {
             types_found: find_types(apis),
-            typedefs: Self::find_typedefs(apis),
+            typedefs: Self::find_typedefs(config, apis),
             concrete_templates: Self::find_concrete_templates(apis),
             forward_declarations: Self::find_incomplete_types(apis),
             ignored_types: Self::find_ignored_types(apis),
@@ -135,6 +135,11 @@ impl<'a> TypeConverter<'a> {
         }
     }
 
+    /// Spell a type as it would be written in C++.
+    pub(crate) fn type_to_cpp(&self, ty: &Type) -> Result<String, ConvertErrorFromCpp> {
+        self.original_name_map.type_to_cpp(ty)
+    }
+
     pub(crate) fn convert_boxed_type(
         &mut self,
         ty: Box<Type>,
@@ -167,6 +172,15 @@ impl<'a> TypeConverter<'a> {
                 let innerty =
                     self.convert_type(*arr.elem, ns, &TypeConversionContext::WithinReference)?;
                 arr.elem = Box::new(innerty.ty);
+                // bindgen emits lengths such as `16usize`; cxx wants a
+                // plain integer literal.
+                if let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }) = &mut arr.len
+                {
+                    *len = syn::LitInt::new(len.base10_digits(), len.span());
+                }
                 Annotated::new(
                     Type::Array(arr),
                     innerty.types_encountered,
@@ -209,11 +223,17 @@ impl<'a> TypeConverter<'a> {
             // a wobbler if not. rust::Str should only be seen _by value_ in C++
             // headers; it manifests as &str in Rust but on the C++ side it must
             // be a plain value. We should detect and abort.
-            let mut outer = elem.map(|elem| match mutability {
-                Some(_) => Type::Path(parse_quote! {
+            let mut outer = elem.map(|elem| match (mutability, elem.as_ref()) {
+                // References to C arrays of known length become plain Rust
+                // array references; cxx represents the arrays themselves as
+                // std::array and our C++ wrapper converts between the two.
+                (_, Type::Array(_)) => Type::Reference(parse_quote! {
+                    & #mutability #elem
+                }),
+                (Some(_), _) => Type::Path(parse_quote! {
                     ::core::pin::Pin < & #mutability #elem >
                 }),
-                None => Type::Reference(parse_quote! {
+                (None, _) => Type::Reference(parse_quote! {
                     & #elem
                 }),
             });
@@ -595,7 +615,14 @@ impl<'a> TypeConverter<'a> {
             match inner {
                 GenericArgument::Type(Type::Path(typ)) => {
                     let inner_qn = QualifiedName::from_type_path(typ);
-                    if !forward_declarations_ok && self.forward_declarations.contains(&inner_qn) {
+                    if !forward_declarations_ok
+                        && self.forward_declarations.contains(&inner_qn)
+                        && !(generic_behavior == CxxGenericType::CppPtr
+                            && self
+                                .config
+                                .get_complete_type(&inner_qn.to_cpp_name())
+                                .is_some())
+                    {
                         return Err(ConvertErrorFromCpp::TypeContainingForwardDeclaration(
                             inner_qn,
                         ));
@@ -651,12 +678,19 @@ impl<'a> TypeConverter<'a> {
         Ok(TypeKind::Regular)
     }
 
-    fn find_typedefs<A: AnalysisPhase>(apis: &ApiVec<A>) -> HashMap<QualifiedName, Type>
+    /// Typedefs which should be replaced by their targets wherever they're
+    /// used. Newtypes are excluded, because they have a Rust type of
+    /// their own.
+    fn find_typedefs<A: AnalysisPhase>(
+        config: &IncludeCppConfig,
+        apis: &ApiVec<A>,
+    ) -> HashMap<QualifiedName, Type>
     where
         A::TypedefAnalysis: TypedefTarget,
     {
         apis.iter()
             .filter_map(|api| match &api {
+                Api::Typedef { name, .. } if config.is_newtype(&name.name.to_cpp_name()) => None,
                 Api::Typedef { analysis, .. } => analysis
                     .get_target()
                     .cloned()
@@ -0,0 +1,102 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! If `rtti_type_names!()` is given, we add a `type_name()` method to
+//! each polymorphic type, so that Rust code holding a reference to a base
+//! class can find out (for instance for logging) which derived class it
+//! really refers to.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::set::IndexSet as HashSet;
+use syn::parse_quote;
+
+use crate::{
+    conversion::{api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    synthetic_methods::create_synthetic_method,
+};
+
+pub(crate) fn add_type_name_methods(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    if !config.rtti_type_names() {
+        return apis;
+    }
+    let polymorphic_types = find_polymorphic_types(&apis);
+    apis.into_iter()
+        .flat_map(|api| {
+            let method = match &api {
+                Api::Struct { name, .. } if polymorphic_types.contains(&name.name) => {
+                    Some(create_type_name_method(&name.name))
+                }
+                _ => None,
+            };
+            method.into_iter().chain(std::iter::once(api))
+        })
+        .collect()
+}
+
+/// bindgen gives a `vtable_` field only to the root of each class
+/// hierarchy, so we also need to find everything derived from those.
+pub(super) fn find_polymorphic_types(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    let mut polymorphic_types: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, details, .. }
+                if details
+                    .item
+                    .fields
+                    .iter()
+                    .any(|f| f.ident.as_ref().map(|id| id == "vtable_").unwrap_or(false)) =>
+            {
+                Some(name.name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    loop {
+        let derived: Vec<_> = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Struct { name, analysis, .. }
+                    if !polymorphic_types.contains(&name.name)
+                        && analysis
+                            .bases
+                            .iter()
+                            .any(|base| polymorphic_types.contains(base)) =>
+                {
+                    Some(name.name.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        if derived.is_empty() {
+            break;
+        }
+        polymorphic_types.extend(derived);
+    }
+    polymorphic_types
+}
+
+fn create_type_name_method(ty_name: &QualifiedName) -> Api<PodPhase> {
+    let string_type = QualifiedName::new_from_cpp_name("std::string").to_type_path();
+    create_synthetic_method(
+        ty_name,
+        "type_name",
+        Some("The name of the most-derived C++ type of this object, found using RTTI and demangled where possible.".into()),
+        CppFunctionKind::Method,
+        parse_quote! { -> #string_type },
+        CppFunctionBody::TypeName,
+    )
+}
@@ -0,0 +1,148 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_bindgen::callbacks::{Explicitness, SpecialMemberKind};
+use autocxx_parser::{IncludeCppConfig, NonVirtualDestructor};
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use crate::{
+    conversion::{
+        api::{Api, CppVisibility, FuncToConvert},
+        apivec::ApiVec,
+        ConvertErrorFromCpp,
+    },
+    types::QualifiedName,
+};
+
+use super::pod::{PodAnalysis, PodPhase};
+
+struct Destructor {
+    is_virtual: bool,
+    is_public: bool,
+}
+
+fn find_destructors(apis: &ApiVec<PodPhase>) -> HashMap<&QualifiedName, Destructor> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Function { fun, .. } => match fun.as_ref() {
+                FuncToConvert {
+                    special_member: Some(SpecialMemberKind::Destructor),
+                    self_ty: Some(self_ty),
+                    virtualness,
+                    cpp_vis,
+                    is_deleted,
+                    ..
+                } => Some((
+                    self_ty,
+                    Destructor {
+                        is_virtual: virtualness.is_some(),
+                        is_public: matches!(cpp_vis, CppVisibility::Public)
+                            && !matches!(is_deleted, Some(Explicitness::Deleted)),
+                    },
+                )),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Record which types have virtual destructors.
+pub(crate) fn mark_virtual_destructors(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let destructors = find_destructors(&apis);
+    let bases: HashMap<&QualifiedName, &HashSet<QualifiedName>> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                analysis: PodAnalysis { bases, .. },
+                ..
+            } => Some((&name.name, bases)),
+            _ => None,
+        })
+        .collect();
+    let virtual_destructors: HashSet<QualifiedName> = bases
+        .keys()
+        .filter(|ty| has_virtual_destructor(ty, &destructors, &bases))
+        .map(|ty| (*ty).clone())
+        .collect();
+    apis.into_iter()
+        .map(|mut api| {
+            if let Api::Struct { name, analysis, .. } = &mut api {
+                analysis.has_virtual_destructor = virtual_destructors.contains(&name.name);
+            }
+            api
+        })
+        .collect()
+}
+
+/// A destructor is virtual if it's declared so, or if that of any base
+/// class is.
+fn has_virtual_destructor(
+    ty: &QualifiedName,
+    destructors: &HashMap<&QualifiedName, Destructor>,
+    bases: &HashMap<&QualifiedName, &HashSet<QualifiedName>>,
+) -> bool {
+    destructors
+        .get(ty)
+        .map(|destructor| destructor.is_virtual)
+        .unwrap_or_default()
+        || bases
+            .get(ty)
+            .into_iter()
+            .flat_map(|bases| bases.iter())
+            .any(|base| has_virtual_destructor(base, destructors, bases))
+}
+
+/// A Rust subclass may be handed to C++ as a `std::unique_ptr` to its
+/// superclass, and C++ may then delete it through that pointer. That's
+/// undefined behavior unless the superclass destructor is virtual, so
+/// refuse to generate such subclasses unless the user has told us how to
+/// avoid that. If the destructor isn't public, C++ can't delete the object
+/// through a superclass pointer anyway.
+pub(crate) fn check_subclass_destructors(
+    apis: &ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertErrorFromCpp> {
+    let destructors = find_destructors(apis);
+    let non_virtual_destructors: HashSet<&QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                analysis:
+                    PodAnalysis {
+                        has_virtual_destructor: false,
+                        ..
+                    },
+                ..
+            } => Some(&name.name),
+            _ => None,
+        })
+        .filter(|ty| {
+            destructors
+                .get(ty)
+                .map(|destructor| destructor.is_public)
+                .unwrap_or(true)
+        })
+        .collect();
+    for api in apis.iter() {
+        if let Api::Subclass { name, superclass } = api {
+            if non_virtual_destructors.contains(superclass)
+                && config.get_non_virtual_destructor_policy(&name.id().to_string())
+                    == NonVirtualDestructor::Error
+            {
+                return Err(ConvertErrorFromCpp::SubclassOfNonVirtualDestructor(
+                    superclass.to_cpp_name(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
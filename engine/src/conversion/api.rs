@@ -56,6 +56,9 @@ pub(crate) enum TypeKind {
 pub(crate) struct StructDetails {
     pub(crate) item: ItemStruct,
     pub(crate) has_rvalue_reference_fields: bool,
+    /// The offset of each field as clang laid it out, by Rust field name,
+    /// taken from bindgen's layout tests.
+    pub(crate) field_offsets: Vec<(String, usize)>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -205,7 +208,7 @@ impl AnalysisPhase for NullPhase {
     type FunAnalysis = ();
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum TypedefKind {
     Use(Box<Type>),
     Type(ItemType),
@@ -508,6 +511,29 @@ impl<T: AnalysisPhase> Api<T> {
         }
     }
 
+    /// The kind of this API, for diagnostics.
+    pub(crate) fn kind_name(&self) -> &'static str {
+        match self {
+            Api::ForwardDeclaration { .. } => "ForwardDeclaration",
+            Api::OpaqueTypedef { .. } => "OpaqueTypedef",
+            Api::ConcreteType { .. } => "ConcreteType",
+            Api::StringConstructor { .. } => "StringConstructor",
+            Api::Function { .. } => "Function",
+            Api::Const { .. } => "Const",
+            Api::Typedef { .. } => "Typedef",
+            Api::Enum { .. } => "Enum",
+            Api::Struct { .. } => "Struct",
+            Api::CType { .. } => "CType",
+            Api::IgnoredItem { .. } => "IgnoredItem",
+            Api::RustType { .. } => "RustType",
+            Api::RustFn { .. } => "RustFn",
+            Api::RustSubclassFn { .. } => "RustSubclassFn",
+            Api::Subclass { .. } => "Subclass",
+            Api::SubclassTraitItem { .. } => "SubclassTraitItem",
+            Api::ExternCppType { .. } => "ExternCppType",
+        }
+    }
+
     /// The name of this API as used in Rust code.
     /// For types, it's important that this never changes, since
     /// functions or other types may refer to this.
@@ -536,6 +562,21 @@ impl<T: AnalysisPhase> Api<T> {
         matches!(self, Api::IgnoredItem { .. })
     }
 
+    /// Whether this is a typedef with exactly the same target as `other`.
+    /// bindgen sometimes gives us the same typedef twice (see
+    /// test_issue_264) in which case we can keep either.
+    pub(crate) fn is_identical_typedef(&self, other: &Api<T>) -> bool {
+        match (self, other) {
+            (
+                Api::Typedef { item, .. },
+                Api::Typedef {
+                    item: other_item, ..
+                },
+            ) => item == other_item,
+            _ => false,
+        }
+    }
+
     pub(crate) fn valid_types(&self) -> Box<dyn Iterator<Item = QualifiedName>> {
         match self {
             Api::Subclass { name, .. } => Box::new(
@@ -57,6 +57,12 @@ impl<P: AnalysisPhase> ApiVec<P> {
                 // This is already an IgnoredItem or something else where
                 // we can silently drop it.
                 log::info!("Discarding duplicate API for {}", name);
+            } else if self
+                .apis
+                .iter()
+                .any(|existing| existing.name() == name && existing.is_identical_typedef(&api))
+            {
+                log::info!("Discarding identical duplicate typedef {}", name);
             } else {
                 log::info!(
                     "Duplicate API for {} - removing all of them and replacing with an IgnoredItem.",
@@ -0,0 +1,164 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For each type listed in `batch_methods!`, we generate a Rust builder
+//! which records calls to the given methods, and a C++ function which
+//! makes all the recorded calls in a single trip across the FFI boundary.
+//! Each call is recorded as a run of 64-bit words: the index of the method
+//! followed by its arguments, padded so that every call has the same
+//! stride. Hence only methods which take numbers or `bool`s and return
+//! nothing can be batched.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::Type;
+
+use crate::{
+    minisyn::{Ident, Pat},
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    analysis::fun::{small_pods::is_scalar, FnAnalysis, FnKind, FnPhase, MethodKind},
+    api::{Api, UnsafetyNeeded},
+    apivec::ApiVec,
+    extra_shims::shim_name,
+    ConvertErrorFromCpp,
+};
+
+pub(crate) struct BatchOp {
+    /// The C++ method.
+    pub(crate) method: String,
+    /// The name of the method in Rust, which the builder shares.
+    pub(crate) rust_name: Ident,
+    /// The names and Rust types of its parameters, excluding the receiver.
+    pub(crate) params: Vec<(Pat, Type)>,
+}
+
+pub(crate) struct Batch {
+    pub(crate) ty: QualifiedName,
+    pub(crate) ops: Vec<BatchOp>,
+    /// Whether any of the methods is `unsafe` to call, in which case so is
+    /// running the batch.
+    pub(crate) requires_unsafe: bool,
+    /// The C++ function which makes the calls.
+    pub(crate) shim: String,
+}
+
+impl Batch {
+    /// The number of words recorded for each call.
+    pub(crate) fn stride(&self) -> usize {
+        1 + self.ops.iter().map(|op| op.params.len()).max().unwrap_or(0)
+    }
+}
+
+pub(crate) fn find_batches(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Result<Vec<Batch>, ConvertErrorFromCpp> {
+    config
+        .get_batch_methods()
+        .filter_map(|(ty, methods)| {
+            // The type may have failed to generate, in which case we can't
+            // refer to it.
+            apis.iter()
+                .find_map(|api| match api {
+                    Api::Struct { name, .. } if name.name.to_cpp_name() == ty => Some(&name.name),
+                    _ => None,
+                })
+                .map(|ty| (ty, methods))
+        })
+        .map(|(ty, methods)| {
+            let mut requires_unsafe = false;
+            let ops = methods
+                .iter()
+                .map(|method| {
+                    let analysis = find_method(apis, ty, method).ok_or_else(|| {
+                        ConvertErrorFromCpp::BatchMethodUnsuitable(format!(
+                            "{}::{method}",
+                            ty.to_cpp_name()
+                        ))
+                    })?;
+                    requires_unsafe |= !matches!(analysis.requires_unsafe, UnsafetyNeeded::None);
+                    Ok(BatchOp {
+                        method: method.clone(),
+                        rust_name: make_ident(&analysis.rust_name),
+                        params: analysis
+                            .param_details
+                            .iter()
+                            .filter(|pd| pd.self_type.is_none())
+                            .map(|pd| (pd.name.clone(), pd.conversion.cxxbridge_type().clone()))
+                            .collect(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Batch {
+                ty: ty.clone(),
+                ops,
+                requires_unsafe,
+                shim: shim_name(config, ty, "run_batch_autocxx_wrapper"),
+            })
+        })
+        .collect()
+}
+
+/// The single, non-static method of this name which can be batched.
+fn find_method<'a>(
+    apis: &'a ApiVec<FnPhase>,
+    ty: &QualifiedName,
+    method: &str,
+) -> Option<&'a FnAnalysis> {
+    let candidates: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Function {
+                name,
+                fun,
+                analysis:
+                    analysis @ FnAnalysis {
+                        kind:
+                            FnKind::Method {
+                                impl_for,
+                                method_kind:
+                                    MethodKind::Normal
+                                    | MethodKind::Virtual(_)
+                                    | MethodKind::PureVirtual(_),
+                            },
+                        ignore_reason: Ok(_),
+                        externally_callable: true,
+                        ..
+                    },
+            } if impl_for == ty
+                && name
+                    .cpp_name_if_present()
+                    .map_or(fun.ident.to_string() == method, |n| {
+                        n.for_validation() == method
+                    }) =>
+            {
+                Some(analysis)
+            }
+            _ => None,
+        })
+        .collect();
+    match candidates.as_slice() {
+        [analysis] if analysis.ret_conversion.is_none() && takes_only_scalars(analysis) => {
+            Some(analysis)
+        }
+        _ => None,
+    }
+}
+
+fn takes_only_scalars(analysis: &FnAnalysis) -> bool {
+    analysis
+        .param_details
+        .iter()
+        .filter(|pd| pd.self_type.is_none())
+        .all(|pd| {
+            let ty = pd.conversion.cxxbridge_type();
+            is_scalar(ty) && !matches!(ty, Type::Ptr(_))
+        })
+}
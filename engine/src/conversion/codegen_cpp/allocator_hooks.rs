@@ -0,0 +1,38 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Every heap allocation made by our generated C++ goes through one of
+/// these macros, so that embedders with their own heaps (or who want to
+/// track allocations) can substitute their own functions. They can be
+/// defined on the compiler command line, or in a header named by
+/// `AUTOCXX_ALLOCATOR_HEADER`.
+/// `AUTOCXX_MAKE_UNIQUE` names a function template used like
+/// `std::make_unique`; since the results are freed by `std::default_delete`
+/// it must allocate memory which `delete` can free.
+/// `AUTOCXX_OPERATOR_NEW` and `AUTOCXX_OPERATOR_DELETE` are used as a pair
+/// to allocate and free uninitialized storage for types which don't
+/// have class-specific `operator new` and `operator delete`.
+pub(super) static ALLOCATOR_HOOKS: &str = indoc! {"
+    #ifndef AUTOCXX_ALLOCATOR_HOOKS
+    #define AUTOCXX_ALLOCATOR_HOOKS
+    #ifdef AUTOCXX_ALLOCATOR_HEADER
+    #include AUTOCXX_ALLOCATOR_HEADER
+    #endif
+    #ifndef AUTOCXX_MAKE_UNIQUE
+    #define AUTOCXX_MAKE_UNIQUE std::make_unique
+    #endif
+    #ifndef AUTOCXX_OPERATOR_NEW
+    #define AUTOCXX_OPERATOR_NEW ::operator new
+    #endif
+    #ifndef AUTOCXX_OPERATOR_DELETE
+    #define AUTOCXX_OPERATOR_DELETE ::operator delete
+    #endif
+    #endif // AUTOCXX_ALLOCATOR_HOOKS
+"};
@@ -0,0 +1,21 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Used by wrappers listed in `force_inline!`. There's no standard
+/// spelling of this, so we pick the right one for the compiler.
+pub(super) static ALWAYS_INLINE_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_ALWAYS_INLINE
+    #if defined(_MSC_VER)
+    #define AUTOCXX_ALWAYS_INLINE __forceinline
+    #else
+    #define AUTOCXX_ALWAYS_INLINE inline __attribute__((always_inline))
+    #endif
+    #endif // AUTOCXX_ALWAYS_INLINE
+"};
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use syn::{Type, TypePtr};
+use syn::{Type, TypePtr, TypeReference};
 
 use crate::conversion::{
     analysis::fun::function_wrapper::{CppConversionType, TypeConversionPolicy},
@@ -25,6 +25,7 @@ impl TypeConversionPolicy {
             CppConversionType::FromPtrToValue => {
                 Ok(format!("{}*", self.unwrapped_type_as_string(cpp_name_map)?))
             }
+            CppConversionType::FromSliceToIteratorPair => self.slice_type(cpp_name_map),
             _ => self.unwrapped_type_as_string(cpp_name_map),
         }
     }
@@ -62,6 +63,74 @@ impl TypeConversionPolicy {
         cpp_name_map.type_to_cpp(self.cxxbridge_type())
     }
 
+    /// For a policy whose type is `&[T; N]` or `&mut [T; N]`, the C++
+    /// reference-to-array type, e.g. `const float(&)[16]`.
+    fn c_array_reference_type(
+        &self,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        match self.cxxbridge_type() {
+            Type::Reference(TypeReference {
+                mutability, elem, ..
+            }) => match elem.as_ref() {
+                Type::Array(arr) => Ok(format!(
+                    "{}{}(&)[{}]",
+                    if mutability.is_some() { "" } else { "const " },
+                    cpp_name_map.type_to_cpp(&arr.elem)?,
+                    CppNameMap::array_len(arr)
+                )),
+                _ => panic!("Not a reference to an array"),
+            },
+            _ => panic!("Not a reference to an array"),
+        }
+    }
+
+    /// For a policy whose type is `&[T]` or `&mut [T]`, the cxx slice type,
+    /// e.g. `rust::Slice<const float>`.
+    fn slice_type(&self, cpp_name_map: &CppNameMap) -> Result<String, ConvertErrorFromCpp> {
+        match self.cxxbridge_type() {
+            Type::Reference(TypeReference {
+                mutability, elem, ..
+            }) => match elem.as_ref() {
+                Type::Slice(slice) => Ok(format!(
+                    "rust::Slice<{}{}>",
+                    if mutability.is_some() { "" } else { "const " },
+                    cpp_name_map.type_to_cpp(&slice.elem)?
+                )),
+                _ => panic!("Not a reference to a slice"),
+            },
+            _ => panic!("Not a reference to a slice"),
+        }
+    }
+
+    /// Cast to or from `std::byte` for a policy whose type has `u8` in
+    /// place of it, e.g. `const uint8_t*` to `const std::byte*`. Both are
+    /// allowed to alias anything, but containers of them aren't, so those
+    /// we copy.
+    fn std_byte_cast(
+        &self,
+        var_name: &str,
+        cpp_name_map: &CppNameMap,
+        to: &str,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        if self.copies_std_bytes() {
+            return Ok(format!(
+                "autocxx_std_byte_copy<{}>({var_name})",
+                self.container_value_type(cpp_name_map, false)?
+                    .replace("uint8_t", to)
+            ));
+        }
+        Ok(match self.cxxbridge_type() {
+            Type::Path(typ) if typ.path.is_ident("u8") => format!("static_cast<{to}>({var_name})"),
+            _ => format!(
+                "reinterpret_cast<{}>({})",
+                self.unwrapped_type_as_string(cpp_name_map)?
+                    .replace("uint8_t", to),
+                var_name
+            ),
+        })
+    }
+
     fn unique_ptr_wrapped_type(
         &self,
         original_name_map: &CppNameMap,
@@ -72,11 +141,54 @@ impl TypeConversionPolicy {
         ))
     }
 
+    /// The value type of a policy whose type is a `std::string` or
+    /// `std::vector`, or a reference to one, optionally as its `std::pmr`
+    /// equivalent.
+    fn container_value_type(
+        &self,
+        cpp_name_map: &CppNameMap,
+        pmr: bool,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let ty = match self.cxxbridge_type() {
+            Type::Reference(TypeReference { elem, .. }) => elem.as_ref(),
+            ty => ty,
+        };
+        let ty = cpp_name_map.type_to_cpp(ty)?;
+        Ok(if pmr {
+            ty.replacen("std::", "std::pmr::", 1)
+        } else {
+            ty
+        })
+    }
+
     pub(super) fn cpp_conversion(
         &self,
         var_name: &str,
         cpp_name_map: &CppNameMap,
         is_return: bool,
+    ) -> Result<Option<String>, ConvertErrorFromCpp> {
+        if !self.copies_pmr {
+            return self.plain_cpp_conversion(var_name, cpp_name_map, is_return);
+        }
+        if is_return {
+            let copy = format!(
+                "autocxx_pmr_copy<{}>({var_name})",
+                self.container_value_type(cpp_name_map, false)?
+            );
+            self.plain_cpp_conversion(&copy, cpp_name_map, true)
+        } else {
+            let pmr_type = self.container_value_type(cpp_name_map, true)?;
+            Ok(self
+                .plain_cpp_conversion(var_name, cpp_name_map, false)?
+                .map(|converted| format!("autocxx_pmr_copy<{pmr_type}>({converted})")))
+        }
+    }
+
+    fn plain_cpp_conversion(
+        &self,
+        var_name: &str,
+        cpp_name_map: &CppNameMap,
+        is_return: bool,
     ) -> Result<Option<String>, ConvertErrorFromCpp> {
         // If is_return we want to avoid unnecessary std::moves because they
         // make RVO less effective
@@ -90,7 +202,7 @@ impl TypeConversionPolicy {
                 Some(format!("std::move(*{var_name})"))
             }
             CppConversionType::FromValueToUniquePtr => Some(format!(
-                "std::make_unique<{}>({})",
+                "AUTOCXX_MAKE_UNIQUE<{}>({})",
                 self.unconverted_type(cpp_name_map)?,
                 var_name
             )),
@@ -103,7 +215,32 @@ impl TypeConversionPolicy {
                 })
             }
             CppConversionType::IgnoredPlacementPtrParameter => None,
-            CppConversionType::FromReferenceToPointer { .. } => Some(format!("&{var_name}")),
+            // std::addressof in case the type overloads or deletes `operator&`.
+            CppConversionType::FromReferenceToPointer { .. } => {
+                Some(format!("std::addressof({var_name})"))
+            }
+            CppConversionType::FromStdArrayToCArray => Some(format!(
+                "reinterpret_cast<{}>({})",
+                self.c_array_reference_type(cpp_name_map)?,
+                var_name
+            )),
+            CppConversionType::FromCArrayToStdArray => Some(format!(
+                "reinterpret_cast<{}>({})",
+                self.unwrapped_type_as_string(cpp_name_map)?,
+                var_name
+            )),
+            CppConversionType::FromU8ToStdByte => {
+                Some(self.std_byte_cast(var_name, cpp_name_map, "std::byte")?)
+            }
+            CppConversionType::FromStdByteToU8 => {
+                Some(self.std_byte_cast(var_name, cpp_name_map, "uint8_t")?)
+            }
+            CppConversionType::FromWriterToOstream(ref adapter) => {
+                Some(format!("{adapter}({var_name}).get()"))
+            }
+            CppConversionType::FromSliceToIteratorPair => Some(format!(
+                "{var_name}.data(), {var_name}.data() + {var_name}.size()"
+            )),
         })
     }
 }
@@ -6,32 +6,65 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod allocator_hooks;
+mod always_inline_prelude;
 mod function_wrapper_cpp;
 mod new_and_delete_prelude;
+mod pmr_prelude;
+mod std_byte_prelude;
+mod string_value_prelude;
+mod type_name_prelude;
 pub(crate) mod type_to_cpp;
 
 use crate::{
     conversion::analysis::fun::{function_wrapper::CppFunctionKind, FnAnalysis},
+    known_types::known_types,
+    minisyn::{FnArg, ReturnType},
+    source_declarations::is_cpp_source,
     types::QualifiedName,
     CppCodegenOptions, CppFilePair,
 };
-use autocxx_parser::IncludeCppConfig;
+use autocxx_parser::{
+    CompleteType, CompletionFuture, ContextCallback, DropBehaviour, IncludeCppConfig, InitFunction,
+    NonVirtualDestructor,
+};
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 use itertools::Itertools;
 use std::borrow::Cow;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, Ident, Signature, Type, TypeReference,
+};
 use type_to_cpp::CppNameMap;
 
 use super::{
     analysis::{
         fun::{
-            function_wrapper::{CppFunction, CppFunctionBody},
+            function_wrapper::{CppConversionType, CppFunction, CppFunctionBody},
             FnPhase, PodAndDepAnalysis,
         },
         pod::PodAnalysis,
     },
-    api::{Api, Provenance, SubclassName, TypeKind},
+    api::{Api, Provenance, StructDetails, SubclassName, TypeKind},
     apivec::ApiVec,
+    batches::Batch,
+    completion_futures::completion_future_names,
+    container_accessors::{find_container_accessor, ContainerAccessor, ContainerLookup},
+    context_callbacks::context_callback_names,
+    extern_rust_closures::{
+        as_boxed_fn, cpp_scalar, extern_rust_closure_names, has_boxed_fns, BoxedFn,
+    },
+    extra_shims::ExtraShims,
+    init_function::init_function_wrapper_name,
+    intrusive_ptrs::IntrusivePtrType,
+    member_fn_fields::MemberFnFieldShims,
+    ostream_writers::ostream_writer_names,
+    pod_vectors::PodVectorElement,
+    protobuf_messages::ProtobufMessage,
+    string_values::string_value_names,
+    type_helpers::extract_pinned_mutable_reference_type,
+    upcasts::Upcast,
+    vector_slices::VectorSliceMethod,
     ConvertErrorFromCpp, CppEffectiveName,
 };
 
@@ -41,9 +74,16 @@ static GENERATED_FILE_HEADER: &str =
 #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 enum Header {
     System(&'static str),
+    User(String),
     CxxH,
     CxxgenH,
+    AllocatorHooks,
     NewDeletePrelude,
+    TypeNamePrelude,
+    AlwaysInlinePrelude,
+    StringValuePrelude,
+    PmrPrelude,
+    StdBytePrelude,
 }
 
 impl Header {
@@ -55,6 +95,7 @@ impl Header {
         let blank = "".to_string();
         match self {
             Self::System(name) => format!("#include <{name}>"),
+            Self::User(name) => format!("#include \"{name}\""),
             Self::CxxH => {
                 let prefix = cpp_codegen_options.path_to_cxx_h.as_ref().unwrap_or(&blank);
                 format!("#include \"{prefix}cxx.h\"")
@@ -66,7 +107,13 @@ impl Header {
                     .unwrap_or(&blank);
                 format!("#include \"{prefix}{cxxgen_header_name}\"")
             }
+            Header::AllocatorHooks => allocator_hooks::ALLOCATOR_HOOKS.to_string(),
             Header::NewDeletePrelude => new_and_delete_prelude::NEW_AND_DELETE_PRELUDE.to_string(),
+            Header::TypeNamePrelude => type_name_prelude::TYPE_NAME_PRELUDE.to_string(),
+            Header::AlwaysInlinePrelude => always_inline_prelude::ALWAYS_INLINE_PRELUDE.to_string(),
+            Header::StringValuePrelude => string_value_prelude::STRING_VALUE_PRELUDE.to_string(),
+            Header::PmrPrelude => pmr_prelude::PMR_PRELUDE.to_string(),
+            Header::StdBytePrelude => std_byte_prelude::STD_BYTE_PRELUDE.to_string(),
         }
     }
 
@@ -117,6 +164,7 @@ impl<'a> CppCodeGenerator<'a> {
         inclusions: String,
         apis: &ApiVec<FnPhase>,
         config: &'a IncludeCppConfig,
+        extra_shims: &ExtraShims,
         cpp_codegen_options: &CppCodegenOptions,
         cxxgen_header_name: &str,
     ) -> Result<Option<CppFilePair>, ConvertErrorFromCpp> {
@@ -131,6 +179,45 @@ impl<'a> CppCodeGenerator<'a> {
         // The 'filter' on the following line is designed to ensure we don't accidentally
         // end up out of sync with needs_cpp_codegen
         gen.add_needs(apis.iter().filter(|api| api.needs_cpp_codegen()))?;
+        for complete_type in config.get_complete_types() {
+            gen.generate_complete_type_assertion(complete_type);
+        }
+        for completion_future in config.get_completion_futures() {
+            gen.generate_completion_future(completion_future);
+        }
+        for context_callback in config.get_context_callbacks() {
+            gen.generate_context_callback(context_callback);
+        }
+        if config.ostream_as_writer() {
+            gen.generate_ostream_writer();
+        }
+        if config.strings_by_value() {
+            gen.generate_string_value_shims();
+        }
+        for upcast in &extra_shims.upcasts {
+            gen.generate_upcast(upcast);
+        }
+        for pod_vector_element in &extra_shims.pod_vector_elements {
+            gen.generate_pod_vector_shims(pod_vector_element);
+        }
+        for intrusive_ptr in &extra_shims.intrusive_ptr_types {
+            gen.generate_intrusive_ptr_shims(intrusive_ptr);
+        }
+        for protobuf_message in &extra_shims.protobuf_messages {
+            gen.generate_protobuf_message_shims(protobuf_message);
+        }
+        for member_fn_field in &extra_shims.member_fn_fields {
+            gen.generate_member_fn_field_shims(member_fn_field);
+        }
+        for vector_slice_method in &extra_shims.vector_slice_methods {
+            gen.generate_vector_slice_shim(vector_slice_method);
+        }
+        for batch in &extra_shims.batches {
+            gen.generate_batch_shim(batch)?;
+        }
+        if let Some(init_function) = config.get_init_function() {
+            gen.generate_init_function(init_function);
+        }
         Ok(gen.generate())
     }
 
@@ -151,6 +238,10 @@ impl<'a> CppCodeGenerator<'a> {
                             cpp_wrapper: Some(cpp_wrapper),
                             ignore_reason: Ok(_),
                             externally_callable: true,
+                            c_export_name,
+                            c_abi_name,
+                            params,
+                            ret_type,
                             ..
                         },
                     fun,
@@ -162,7 +253,14 @@ impl<'a> CppCodeGenerator<'a> {
                             .or_default()
                             .push(&details.cpp_impl);
                     }
-                    self.generate_cpp_function(cpp_wrapper)?
+                    self.generate_cpp_function(cpp_wrapper)?;
+                    if let Some(c_export_name) = c_export_name {
+                        self.generate_c_export(c_export_name, cpp_wrapper, params, ret_type)?
+                    }
+                    if let Some(c_abi_name) = c_abi_name {
+                        self.generate_register_passing_assertions(params, ret_type)?;
+                        self.generate_c_export(c_abi_name, cpp_wrapper, params, ret_type)?
+                    }
                 }
                 Api::ConcreteType {
                     rs_definition,
@@ -176,9 +274,29 @@ impl<'a> CppCodeGenerator<'a> {
                         None => Cow::Borrowed(cpp_definition),
                     };
 
-                    self.generate_typedef(api.name(), &effective_cpp_definition)
+                    self.generate_typedef(api.name(), &effective_cpp_definition);
+                    if let Some(accessor) = rs_definition.as_ref().and_then(|rs_definition| {
+                        find_container_accessor(
+                            api.name(),
+                            &effective_cpp_definition,
+                            rs_definition,
+                            self.config,
+                        )
+                    }) {
+                        self.generate_container_accessor(api.name(), &accessor)
+                    }
                 }
                 Api::CType { typename, .. } => self.generate_ctype_typedef(typename),
+                Api::ForwardDeclaration { .. } => {
+                    let definition = format!("::{}", self.original_name_map.map(api.name()));
+                    self.additional_functions.push(ExtraCpp {
+                        type_definition: Some(format!(
+                            "typedef {definition} {};",
+                            nested_forward_declaration_typedef_name(api.name())
+                        )),
+                        ..Default::default()
+                    })
+                }
                 Api::Subclass { .. } => deferred_apis.push(api),
                 Api::RustSubclassFn {
                     subclass, details, ..
@@ -193,6 +311,7 @@ impl<'a> CppCodeGenerator<'a> {
                 }
                 Api::Struct {
                     name,
+                    details,
                     analysis:
                         PodAndDepAnalysis {
                             pod:
@@ -202,9 +321,15 @@ impl<'a> CppCodeGenerator<'a> {
                                 },
                             ..
                         },
-                    ..
                 } => {
                     self.generate_pod_assertion(name.qualified_cpp_name());
+                    self.generate_field_offset_assertions(name.qualified_cpp_name(), details);
+                    if self.config.is_shared_struct(&name.name.to_cpp_name()) {
+                        self.generate_shared_struct_checks(name.qualified_cpp_name());
+                    }
+                }
+                Api::RustFn { details, .. } if has_boxed_fns(&details.sig) => {
+                    self.generate_extern_rust_closure_fn(&details.sig)
                 }
                 _ => panic!("Should have filtered on needs_cpp_codegen"),
             }
@@ -225,7 +350,30 @@ impl<'a> CppCodeGenerator<'a> {
     }
 
     fn generate(&self) -> Option<CppFilePair> {
-        if self.additional_functions.is_empty() {
+        // Any header_implementation! must be included, with its macros
+        // defined, before anything else includes the header.
+        let header_implementations: String = self
+            .config
+            .get_header_implementations()
+            .map(|(header, defines)| {
+                let defines: String = defines
+                    .iter()
+                    .map(|define| format!("#define {define}\n"))
+                    .collect();
+                format!("{defines}#include \"{header}\"\n")
+            })
+            .collect();
+        // The declarations from any C++ source file are only available
+        // from our header.
+        let has_cpp_sources = self
+            .config
+            .inclusions
+            .iter()
+            .any(|path| is_cpp_source(path));
+        if self.additional_functions.is_empty()
+            && header_implementations.is_empty()
+            && !has_cpp_sources
+        {
             None
         } else {
             let headers = self.collect_headers(|additional_need| &additional_need.headers);
@@ -241,14 +389,15 @@ impl<'a> CppCodeGenerator<'a> {
                 .cpp_codegen_options
                 .autocxxgen_header_namer
                 .name_header(self.config.get_mod_name().to_string());
-            let implementation = if self
-                .additional_functions
-                .iter()
-                .any(|x| x.definition.is_some())
+            let implementation = if !header_implementations.is_empty()
+                || self
+                    .additional_functions
+                    .iter()
+                    .any(|x| x.definition.is_some())
             {
                 let definitions = self.concat_additional_items(|x| x.definition.as_ref());
                 let definitions =
-                    format!("{GENERATED_FILE_HEADER}\n#include \"{header_name}\"\n{cpp_headers}\n{definitions}");
+                    format!("{GENERATED_FILE_HEADER}\n{header_implementations}#include \"{header_name}\"\n{cpp_headers}\n{definitions}");
                 log::info!("Additional C++ defs:\n{}", definitions);
                 Some(definitions.into_bytes())
             } else {
@@ -307,13 +456,537 @@ impl<'a> CppCodeGenerator<'a> {
         })
     }
 
+    /// The Rust `FIELD_OFFSETS` of a POD type are where bindgen put each
+    /// field; check that's where the C++ compiler puts it too. Only public
+    /// fields can be named in `offsetof`, and it's only reliable for
+    /// standard-layout types, so we skip types with base classes.
+    fn generate_field_offset_assertions(&mut self, name: String, details: &StructDetails) {
+        let public_fields: HashSet<_> = details
+            .item
+            .fields
+            .iter()
+            .filter(|f| matches!(f.vis, syn::Visibility::Public(_)))
+            .filter_map(|f| f.ident.as_ref())
+            .map(|id| id.to_string())
+            .collect();
+        if public_fields.iter().any(|field| field.starts_with("_base")) {
+            return;
+        }
+        let assertions = details
+            .field_offsets
+            .iter()
+            .filter(|(field, _)| !field.starts_with('_') && public_fields.contains(field))
+            .map(|(field, offset)| {
+                // bindgen appends an underscore to fields named after Rust
+                // keywords.
+                let cpp_field = field
+                    .strip_suffix('_')
+                    .filter(|stripped| syn::parse_str::<syn::Ident>(stripped).is_err())
+                    .unwrap_or(field);
+                format!("static_assert(offsetof({name}, {cpp_field}) == {offset}, \"field {name}::{cpp_field} should be at offset {offset}, as bindgen laid it out for autocxx\");")
+            })
+            .join("\n");
+        if assertions.is_empty() {
+            return;
+        }
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(assertions),
+            headers: vec![Header::System("cstddef")],
+            ..Default::default()
+        })
+    }
+
+    /// cxx would otherwise define each shared struct itself, within a
+    /// guard which we define instead so that the existing definition is
+    /// used. cxx passes shared structs by value, so they must be trivially
+    /// copyable; and our Rust code checks that the fields are laid out
+    /// as bindgen saw them.
+    fn generate_shared_struct_checks(&mut self, name: String) {
+        let guard = format!("CXXBRIDGE1_STRUCT_{}", name.replace("::", "$"));
+        let declaration = Some(format!("static_assert(std::is_trivially_copyable<{name}>::value && std::is_standard_layout<{name}>::value, \"type {name} should be trivially copyable and standard layout to be used with shared_struct! in autocxx\");"));
+        self.additional_functions.push(ExtraCpp {
+            type_definition: Some(format!("#define {guard}")),
+            declaration,
+            headers: vec![Header::System("type_traits")],
+            ..Default::default()
+        })
+    }
+
+    fn generate_complete_type_assertion(&mut self, complete_type: &CompleteType) {
+        // sizeof fails to compile for an incomplete type, whereas
+        // deleting one (as cxx's UniquePtr glue would) at worst warns.
+        let CompleteType {
+            ty, size, align, ..
+        } = complete_type;
+        let declaration = Some(format!("static_assert(sizeof({ty}) == {size} && alignof({ty}) == {align}, \"type {ty} should have size {size} and alignment {align} as declared by complete_type! in autocxx\");"));
+        self.additional_functions.push(ExtraCpp {
+            declaration,
+            headers: complete_type
+                .header
+                .iter()
+                .map(|header| Header::User(header.clone()))
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Call the function with a lambda as its callback. The lambda passes
+    /// the result to Rust along with the boxed sender, which it can only
+    /// do once; the box is shared because callbacks such as
+    /// `std::function` must be copyable.
+    fn generate_completion_future(&mut self, completion_future: &CompletionFuture) {
+        let names = completion_future_names(completion_future, self.config);
+        let completion_type = &names.completion_type;
+        let boxed = format!("rust::Box<{completion_type}>");
+        let cpp_type = |ty: &Ident| QualifiedName::new_from_cpp_name(&ty.to_string()).to_cpp_name();
+        let args = completion_future
+            .params
+            .iter()
+            .enumerate()
+            .map(|(counter, ty)| format!("{} arg{counter}, ", cpp_type(ty)))
+            .join("");
+        let call_args = (0..completion_future.params.len())
+            .map(|counter| format!("arg{counter}, "))
+            .join("");
+        let (lambda_params, value) = match &completion_future.result {
+            Some(result) => (format!("{} value", cpp_type(result)), ", value"),
+            None => (String::new(), ""),
+        };
+        let declaration = format!("void {}({args}{boxed} completion)", names.cpp_wrapper);
+        let definition = format!(
+            "{declaration} {{ auto holder = std::make_shared<std::unique_ptr<{boxed}>>(AUTOCXX_MAKE_UNIQUE<{boxed}>(std::move(completion))); {}({call_args}[holder]({lambda_params}) {{ std::unique_ptr<{boxed}> completion = std::move(*holder); if (completion) {{ {}(std::move(*completion){value}); }} }}); }}",
+            completion_future.function, names.complete_fn
+        );
+        self.additional_functions.push(ExtraCpp {
+            type_definition: Some(format!("struct {completion_type};")),
+            declaration: Some(format!("{declaration};")),
+            definition: Some(definition),
+            headers: vec![
+                Header::System("memory"),
+                Header::System("utility"),
+                Header::AllocatorHooks,
+                Header::CxxH,
+            ],
+            cpp_headers: vec![Header::CxxgenH],
+        })
+    }
+
+    /// Register a trampoline as the callback, with the address of the
+    /// boxed Rust closure as its context. The unregistration wrapper must
+    /// pass the same trampoline, so is defined alongside it.
+    fn generate_context_callback(&mut self, context_callback: &ContextCallback) {
+        let names = context_callback_names(context_callback, self.config);
+        let trampoline = &names.cpp_trampoline;
+        let args = context_callback
+            .params
+            .iter()
+            .enumerate()
+            .map(|(counter, ty)| {
+                format!(
+                    ", {} arg{counter}",
+                    QualifiedName::new_from_cpp_name(&ty.to_string()).to_cpp_name()
+                )
+            })
+            .join("");
+        let call_args = (0..context_callback.params.len())
+            .map(|counter| format!(", arg{counter}"))
+            .join("");
+        let mut definition = format!(
+            "static void {trampoline}(void* context{args}) {{ {}(reinterpret_cast<std::size_t>(context){call_args}); }}",
+            names.call_fn
+        );
+        let mut declarations = Vec::new();
+        let registrations = std::iter::once((&names.cpp_register, &context_callback.function))
+            .chain(
+                names
+                    .cpp_unregister
+                    .iter()
+                    .zip(&context_callback.unregister),
+            );
+        for (wrapper, function) in registrations {
+            let declaration = format!("void {wrapper}(std::size_t context)");
+            definition.push_str(&format!(
+                "\n{declaration} {{ {function}({trampoline}, reinterpret_cast<void*>(context)); }}"
+            ));
+            declarations.push(format!("{declaration};"));
+        }
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(declarations.join("\n")),
+            definition: Some(definition),
+            headers: vec![Header::System("cstddef")],
+            cpp_headers: vec![Header::CxxgenH],
+            ..Default::default()
+        })
+    }
+
+    /// A `std::ostream` wrapping the address of a Rust writer, for
+    /// `ostream_as_writer!`. Its buffer is unbuffered, passing each write
+    /// straight to Rust, so there's nothing to lose if the caller forgets
+    /// to flush.
+    fn generate_ostream_writer(&mut self) {
+        let names = ostream_writer_names(self.config);
+        let adapter = &names.adapter;
+        let type_definition = format!(
+            "class {adapter} : public std::ostream {{ public: explicit {adapter}(std::size_t writer) : std::ostream(nullptr), buf(writer) {{ rdbuf(&buf); }} std::ostream& get() {{ return *this; }} private: class Buf : public std::streambuf {{ public: explicit Buf(std::size_t writer) : writer(writer) {{}} protected: int_type overflow(int_type c) override; std::streamsize xsputn(const char* s, std::streamsize n) override; int sync() override; private: std::size_t writer; }}; Buf buf; }};"
+        );
+        let definition = format!(
+            "{adapter}::Buf::int_type {adapter}::Buf::overflow(int_type c) {{ if (traits_type::eq_int_type(c, traits_type::eof())) {{ return traits_type::not_eof(c); }} char ch = traits_type::to_char_type(c); return xsputn(&ch, 1) == 1 ? c : traits_type::eof(); }}\nstd::streamsize {adapter}::Buf::xsputn(const char* s, std::streamsize n) {{ return {}(writer, rust::Slice<const uint8_t>(reinterpret_cast<const uint8_t*>(s), static_cast<std::size_t>(n))) ? n : 0; }}\nint {adapter}::Buf::sync() {{ return {}(writer) ? 0 : -1; }}",
+            names.write_fn, names.flush_fn
+        );
+        self.additional_functions.push(ExtraCpp {
+            type_definition: Some(type_definition),
+            definition: Some(definition),
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("cstdint"),
+                Header::System("ostream"),
+                Header::System("streambuf"),
+            ],
+            cpp_headers: vec![Header::CxxgenH],
+            ..Default::default()
+        })
+    }
+
+    /// These construct and destroy a `std::string` in storage owned by
+    /// Rust, so they include the prelude which checks that storage is the
+    /// right size.
+    fn generate_string_value_shims(&mut self) {
+        let names = string_value_names(self.config);
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline void {}(std::string* self, const uint8_t* ptr, size_t len) {{ new (self) std::string(reinterpret_cast<const char*>(ptr), len); }}\ninline void {}(std::string* self) {{ self->~basic_string(); }}",
+                names.init_fn, names.destroy_fn
+            )),
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("cstdint"),
+                Header::System("new"),
+                Header::System("string"),
+                Header::StringValuePrelude,
+            ],
+            ..Default::default()
+        });
+    }
+
+    /// Each `std::function` argument is moved to the heap, and deleted by
+    /// Rust through the delete function once it drops the closure. A
+    /// returned closure is owned by a `shared_ptr`, since `std::function`
+    /// must be copyable.
+    fn generate_extern_rust_closure_fn(&mut self, sig: &Signature) {
+        let names = extern_rust_closure_names(sig, self.config);
+        let mut declarations = Vec::new();
+        let mut params = Vec::new();
+        let mut shim_args = Vec::new();
+        for (index, (input, fns)) in sig.inputs.iter().zip(names.param_fns.iter()).enumerate() {
+            let syn::FnArg::Typed(pt) = input else {
+                continue;
+            };
+            let arg = format!("arg{index}");
+            match (as_boxed_fn(&pt.ty), fns) {
+                (Some(boxed_fn), Some((invoke, delete))) => {
+                    let function = std_function(&boxed_fn);
+                    let (closure_params, closure_args) = closure_params(&boxed_fn);
+                    let ret = closure_ret(&boxed_fn);
+                    declarations.push(format!("inline {ret} {invoke}(std::size_t function{}) {{ return (*reinterpret_cast<{function}*>(function))({closure_args}); }}", closure_params.iter().map(|param| format!(", {param}")).join("")));
+                    declarations.push(format!("inline void {delete}(std::size_t function) {{ delete reinterpret_cast<{function}*>(function); }}"));
+                    params.push(format!("{function} {arg}"));
+                    shim_args.push(format!(
+                        "reinterpret_cast<std::size_t>(new {function}(std::move({arg})))"
+                    ));
+                }
+                _ => {
+                    params.push(format!("{} {arg}", cpp_scalar(&pt.ty).unwrap()));
+                    shim_args.push(arg);
+                }
+            }
+        }
+        let shim_call = format!("{}({})", names.shim, shim_args.join(", "));
+        let (ret, body) = match (&sig.output, &names.ret_fns) {
+            (syn::ReturnType::Type(_, ty), Some((call_fn, drop_fn))) => {
+                let boxed_fn = as_boxed_fn(ty).unwrap();
+                let (closure_params, closure_args) = closure_params(&boxed_fn);
+                let closure_args = if closure_args.is_empty() {
+                    String::new()
+                } else {
+                    format!(", {closure_args}")
+                };
+                (
+                    std_function(&boxed_fn),
+                    format!("std::shared_ptr<void> closure(reinterpret_cast<void*>({shim_call}), [](void* ptr) {{ {drop_fn}(reinterpret_cast<std::size_t>(ptr)); }}); return [closure]({}) {{ return {call_fn}(reinterpret_cast<std::size_t>(closure.get()){closure_args}); }};", closure_params.join(", ")),
+                )
+            }
+            (syn::ReturnType::Type(_, ty), None) => (
+                cpp_scalar(ty).unwrap().to_string(),
+                format!("return {shim_call};"),
+            ),
+            (syn::ReturnType::Default, _) => ("void".to_string(), format!("{shim_call};")),
+        };
+        let signature = format!("{ret} {}({})", sig.ident, params.join(", "));
+        declarations.push(format!("{signature};"));
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(declarations.join("\n")),
+            definition: Some(format!("{signature} {{ {body} }}")),
+            headers: vec![
+                Header::CxxH,
+                Header::System("cstddef"),
+                Header::System("cstdint"),
+                Header::System("functional"),
+                Header::System("memory"),
+                Header::System("utility"),
+            ],
+            cpp_headers: vec![Header::CxxgenH],
+            ..Default::default()
+        })
+    }
+
+    /// The cast to void discards any status the initialization function
+    /// returns, so that we needn't know its return type.
+    fn generate_init_function(&mut self, init_function: &InitFunction) {
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline void {}() {{ static_cast<void>({}()); }}",
+                init_function_wrapper_name(self.config),
+                init_function.function
+            )),
+            ..Default::default()
+        });
+    }
+
+    /// The output buffer is uninitialized Rust memory, so we copy-construct
+    /// into it rather than assigning.
+    fn generate_pod_vector_shims(&mut self, element: &PodVectorElement) {
+        let ty = format!("::{}", self.original_name_map.map(&element.ty));
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline void {}(std::vector<{ty}>& vec, const {ty}* data, size_t len) {{ vec.insert(vec.end(), data, data + len); }}",
+                element.extend_shim
+            )),
+            headers: vec![Header::System("cstddef"), Header::System("vector")],
+            ..Default::default()
+        });
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline void {}(const std::vector<{ty}>& vec, {ty}* out) {{ std::uninitialized_copy(vec.begin(), vec.end(), out); }}",
+                element.copy_shim
+            )),
+            headers: vec![Header::System("memory"), Header::System("vector")],
+            ..Default::default()
+        });
+    }
+
+    fn generate_intrusive_ptr_shims(&mut self, intrusive_ptr: &IntrusivePtrType) {
+        let ty = format!("::{}", self.original_name_map.map(&intrusive_ptr.ty));
+        for (shim, method) in [
+            (&intrusive_ptr.add_ref_shim, &intrusive_ptr.add_ref),
+            (&intrusive_ptr.release_shim, &intrusive_ptr.release),
+        ] {
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!(
+                    "inline void {shim}({ty}* obj) {{ obj->{method}(); }}"
+                )),
+                ..Default::default()
+            });
+        }
+    }
+
+    /// `ParseFromArray` takes an `int` length, so longer input can't be
+    /// parsed.
+    fn generate_protobuf_message_shims(&mut self, message: &ProtobufMessage) {
+        let ty = format!("::{}", self.original_name_map.map(&message.ty));
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline std::unique_ptr<std::string> {}(const {ty}& msg) {{ return std::unique_ptr<std::string>(new std::string(msg.SerializeAsString())); }}",
+                message.serialize_shim
+            )),
+            headers: vec![Header::System("memory"), Header::System("string")],
+            ..Default::default()
+        });
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline bool {}({ty}& msg, rust::Slice<const uint8_t> data) {{ return data.size() <= static_cast<std::size_t>(std::numeric_limits<int>::max()) && msg.ParseFromArray(data.data(), static_cast<int>(data.size())); }}",
+                message.parse_shim
+            )),
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("cstdint"),
+                Header::System("limits"),
+                Header::CxxH,
+            ],
+            ..Default::default()
+        });
+    }
+
+    /// The storage holds the field's own type, so checking that matches
+    /// the signature given in the directive is enough to make the call
+    /// shim's signature right.
+    fn generate_member_fn_field_shims(&mut self, shims: &MemberFnFieldShims) {
+        let field = &shims.field.field;
+        let table = format!("::{}", self.original_name_map.map(&shims.table));
+        let class = format!("::{}", self.original_name_map.map(&shims.class));
+        let storage = &shims.storage;
+        let cpp_type = |ty: &Ident| QualifiedName::new_from_cpp_name(&ty.to_string()).to_cpp_name();
+        let param_types = shims.field.params.iter().map(cpp_type).join(", ");
+        let ret_type = shims
+            .field
+            .result
+            .as_ref()
+            .map_or_else(|| "void".to_string(), cpp_type);
+        let constness = if shims.field.is_const { "const " } else { "" };
+        let args = shims
+            .field
+            .params
+            .iter()
+            .enumerate()
+            .map(|(counter, ty)| format!(", {} arg{counter}", cpp_type(ty)))
+            .join("");
+        let call_args = (0..shims.field.params.len())
+            .map(|counter| format!("arg{counter}"))
+            .join(", ");
+        let type_definition = format!(
+            "struct {storage} {{ decltype({table}::{field}) ptr; }};\nstatic_assert(std::is_same<decltype({table}::{field}), {ret_type} ({class}::*)({param_types}) {constness}>::value, \"member_fn_field! signature doesn't match {}::{field}\");",
+            shims.field.table
+        );
+        let declaration = [
+            format!(
+                "inline std::unique_ptr<{storage}> {}(const {table}& table) {{ return std::unique_ptr<{storage}>(new {storage}{{table.{field}}}); }}",
+                shims.get
+            ),
+            format!(
+                "inline void {}({table}& table, const {storage}& storage) {{ table.{field} = storage.ptr; }}",
+                shims.set
+            ),
+            format!(
+                "inline void {}({table}& table) {{ table.{field} = nullptr; }}",
+                shims.clear
+            ),
+            format!(
+                "inline bool {}(const {table}& table) {{ return table.{field} != nullptr; }}",
+                shims.has
+            ),
+            format!(
+                "inline {ret_type} {}(const {table}& table, {constness}{class}& obj{args}) {{ return (obj.*(table.{field}))({call_args}); }}",
+                shims.call
+            ),
+        ]
+        .into_iter()
+        .chain(shims.targets.iter().map(|(target, make)| {
+            format!(
+                "inline std::unique_ptr<{storage}> {make}() {{ return std::unique_ptr<{storage}>(new {storage}{{&{class}::{target}}}); }}"
+            )
+        }))
+        .join("\n");
+        self.additional_functions.push(ExtraCpp {
+            type_definition: Some(type_definition),
+            declaration: Some(declaration),
+            headers: vec![
+                Header::System("cstdint"),
+                Header::System("memory"),
+                Header::System("type_traits"),
+            ],
+            ..Default::default()
+        });
+    }
+
+    /// The vector is returned by reference, so its data stays valid for as
+    /// long as Rust borrows the receiver.
+    fn generate_vector_slice_shim(&mut self, method: &VectorSliceMethod) {
+        let class = format!("::{}", self.original_name_map.map(&method.impl_for));
+        let element = if method.element_is_numeric {
+            method.element.to_cpp_name()
+        } else {
+            format!("::{}", self.original_name_map.map(&method.element))
+        };
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline const {element}* {}(const {class}& obj, size_t& len) {{ const auto& vec = obj.{}(); len = vec.size(); return vec.data(); }}",
+                method.shim, method.cpp_method
+            )),
+            headers: vec![
+                Header::System("cstddef"),
+                Header::System("cstdint"),
+                Header::System("vector"),
+            ],
+            ..Default::default()
+        });
+    }
+
+    /// Make each call recorded in the batch, copying its arguments out of
+    /// the words which follow the index of the method.
+    fn generate_batch_shim(&mut self, batch: &Batch) -> Result<(), ConvertErrorFromCpp> {
+        let ty = format!("::{}", self.original_name_map.map(&batch.ty));
+        let stride = batch.stride();
+        let cases = batch
+            .ops
+            .iter()
+            .enumerate()
+            .map(|(i, op)| {
+                let args = op
+                    .params
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (_, param_ty))| {
+                        let cpp_ty = self.original_name_map.type_to_cpp(param_ty)?;
+                        Ok(format!(
+                            "{cpp_ty} arg{j}; std::memcpy(&arg{j}, &op[{}], sizeof(arg{j}));",
+                            j + 1
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, ConvertErrorFromCpp>>()?;
+                let arg_list = (0..args.len()).map(|j| format!("arg{j}")).join(", ");
+                Ok(format!(
+                    "case {i}: {{ {} obj.{}({arg_list}); break; }}",
+                    args.join(" "),
+                    op.method
+                ))
+            })
+            .collect::<Result<Vec<_>, ConvertErrorFromCpp>>()?
+            .join(" ");
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline void {}({ty}& obj, ::rust::Slice<const uint64_t> ops) {{ for (size_t i = 0; i + {stride} <= ops.size(); i += {stride}) {{ const uint64_t* op = ops.data() + i; switch (op[0]) {{ {cases} }} }} }}",
+                batch.shim
+            )),
+            headers: vec![
+                Header::System("cstdint"),
+                Header::System("cstring"),
+                Header::CxxH,
+            ],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Implicit conversion to a base class pointer makes any adjustment
+    /// needed if the base isn't at the start of the derived object.
+    fn generate_upcast(&mut self, upcast: &Upcast) {
+        let derived = format!("::{}", self.original_name_map.map(&upcast.derived));
+        let base = format!("::{}", self.original_name_map.map(&upcast.base));
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!(
+                "inline {base}* {}({derived}* derived) {{ return derived; }}",
+                upcast.cpp_shim
+            )),
+            ..Default::default()
+        });
+        if let Some(unique_ptr_shim) = &upcast.unique_ptr_shim {
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!(
+                    "inline std::unique_ptr<{base}> {unique_ptr_shim}(std::unique_ptr<{derived}> derived) {{ return std::unique_ptr<{base}>(std::move(derived)); }}"
+                )),
+                headers: vec![Header::System("memory"), Header::System("utility")],
+                ..Default::default()
+            })
+        }
+    }
+
     fn generate_string_constructor(&mut self) {
         let makestring_name = self.config.get_makestring_name();
-        let declaration = Some(format!("inline std::unique_ptr<std::string> {makestring_name}(::rust::Str str) {{ return std::make_unique<std::string>(std::string(str)); }}"));
+        let declaration = Some(format!("inline std::unique_ptr<std::string> {makestring_name}(::rust::Str str) {{ return AUTOCXX_MAKE_UNIQUE<std::string>(std::string(str)); }}"));
         self.additional_functions.push(ExtraCpp {
             declaration,
             headers: vec![
                 Header::System("memory"),
+                Header::AllocatorHooks,
                 Header::System("string"),
                 Header::CxxH,
             ],
@@ -333,6 +1006,100 @@ impl<'a> CppCodeGenerator<'a> {
         Ok(())
     }
 
+    /// Assert that the types in this signature may be passed in registers.
+    /// Rust will pass them by value using the C calling convention,
+    /// which C++ will only also use if they're trivially copyable. Their
+    /// size was worked out from bindgen's idea of their fields, so check
+    /// that too.
+    fn generate_register_passing_assertions(
+        &mut self,
+        params: &Punctuated<FnArg, Comma>,
+        ret_type: &ReturnType,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let ret_type = match &ret_type.0 {
+            syn::ReturnType::Type(_, ty) => Some(ty.as_ref()),
+            syn::ReturnType::Default => None,
+        };
+        for ty in params
+            .iter()
+            .filter_map(|param| match &param.0 {
+                syn::FnArg::Typed(pt) => Some(pt.ty.as_ref()),
+                syn::FnArg::Receiver(_) => None,
+            })
+            .chain(ret_type)
+            .filter(|ty| matches!(ty, Type::Path(_)))
+        {
+            let ty = self.original_name_map.type_to_cpp(ty)?;
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!("static_assert(std::is_trivially_copyable<{ty}>::value && sizeof({ty}) <= 16, \"type {ty} should be trivially copyable and at most 16 bytes to be passed in registers by small_pods_in_registers! in autocxx\");")),
+                headers: vec![Header::System("type_traits")],
+                ..Default::default()
+            })
+        }
+        Ok(())
+    }
+
+    /// Generate a flat `extern "C"` function which calls the given
+    /// wrapper function, so that languages other than Rust can make use
+    /// of our shims. `params` and `ret_type` are the wrapper's signature
+    /// as cxx sees it; references become pointers, and analysis has
+    /// already rejected anything else which C couldn't represent.
+    fn generate_c_export(
+        &mut self,
+        c_export_name: &str,
+        cpp_wrapper: &CppFunction,
+        params: &Punctuated<FnArg, Comma>,
+        ret_type: &ReturnType,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        let mut args = Vec::new();
+        let mut call_args = Vec::new();
+        for (counter, param) in params.iter().enumerate() {
+            let ty = match &param.0 {
+                syn::FnArg::Typed(pt) => pt.ty.as_ref(),
+                syn::FnArg::Receiver(_) => panic!("C++ wrapper functions have no receivers"),
+            };
+            let arg_name = format!("arg{counter}");
+            let (ty, call_arg) = match reference_to_pointer(ty) {
+                Some(ptr) => (ptr, format!("*{arg_name}")),
+                None => (ty.clone(), arg_name.clone()),
+            };
+            args.push(format!(
+                "{} {arg_name}",
+                self.original_name_map.type_to_cpp(&ty)?
+            ));
+            call_args.push(call_arg);
+        }
+        let call = format!(
+            "{}({})",
+            cpp_wrapper.wrapper_function_name,
+            call_args.join(", ")
+        );
+        let (ret_type, body) = match &ret_type.0 {
+            syn::ReturnType::Default => ("void".to_string(), format!("{call};")),
+            syn::ReturnType::Type(_, ty) => match reference_to_pointer(ty) {
+                Some(ptr) => (
+                    self.original_name_map.type_to_cpp(&ptr)?,
+                    format!("return std::addressof({call});"),
+                ),
+                None => (
+                    self.original_name_map.type_to_cpp(ty)?,
+                    format!("return {call};"),
+                ),
+            },
+        };
+        let signature = format!(
+            "extern \"C\" {ret_type} {c_export_name}({})",
+            args.join(", ")
+        );
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(format!("{signature};")),
+            definition: Some(format!("{signature} {{ {body} }}")),
+            headers: vec![Header::System("memory")],
+            ..Default::default()
+        });
+        Ok(())
+    }
+
     fn generate_cpp_function_inner(
         &self,
         details: &CppFunction,
@@ -465,7 +1232,7 @@ impl<'a> CppCodeGenerator<'a> {
             arg_list.next();
         }
         let arg_list = if details.pass_obs_field {
-            std::iter::once("*obs".to_string())
+            std::iter::once("&*obs".to_string())
                 .chain(arg_list)
                 .join(",")
         } else {
@@ -512,6 +1279,20 @@ impl<'a> CppCodeGenerator<'a> {
                         format!("{{ using {path}; {destructor_call}; }}")
                     }
                 };
+                // Objects of no_drop! types are owned by C++, so if Rust
+                // drops one we leave it be rather than destroying it. Rust
+                // still frees the storage it allocated, as documented.
+                let destructor_call = match self
+                    .config
+                    .get_drop_behaviour(&full_name.to_cpp_name())
+                {
+                    None => destructor_call,
+                    Some(DropBehaviour::Leak) => format!("(void){arg_list}"),
+                    Some(DropBehaviour::Assert) => format!(
+                        "{{ assert(!\"{} is owned by C++ and must not be destroyed from Rust\"); (void){arg_list}; }}",
+                        full_name.to_cpp_name()
+                    ),
+                };
                 (destructor_call, "".to_string(), false)
             }
             CppFunctionBody::FunctionCall(ns, id) => match receiver {
@@ -538,6 +1319,61 @@ impl<'a> CppCodeGenerator<'a> {
                     )
                 }
             },
+            CppFunctionBody::ConstCastMethodCall(ty, id) => (
+                format!(
+                    "const_cast<{}&>({}).{}({arg_list})",
+                    self.namespaced_name(ty),
+                    receiver.unwrap(),
+                    id.to_string_for_cpp_generation()
+                ),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::MemberAccess { member, cast_to } => {
+                let access = format!("{}.{member}", receiver.unwrap());
+                let access = match cast_to {
+                    Some(cast_to) => format!("static_cast<{cast_to}>({access})"),
+                    None => access,
+                };
+                (access, "".to_string(), false)
+            }
+            CppFunctionBody::TypeName => (
+                format!("autocxx_type_name(typeid({}))", receiver.unwrap()),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::ObjectIdentity => (
+                format!(
+                    "reinterpret_cast<size_t>(dynamic_cast<const void*>(std::addressof({})))",
+                    receiver.unwrap()
+                ),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::ErrorMessage(method) => (
+                format!("std::string({}.{method}())", receiver.unwrap()),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::ValueInitialize(ty) => (
+                format!("{}{{}}", self.namespaced_name(ty)),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::GlobalAccessor { cpp_name, .. } => (
+                // Bind the global to a function-local static, which C++
+                // initializes on first use in a thread-safe fashion.
+                format!(
+                    "[]() -> decltype({cpp_name})& {{ static decltype({cpp_name})& instance = {cpp_name}; return instance; }}()"
+                ),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::ConversionOperator { to, .. } => (
+                format!("static_cast<{to}>({})", receiver.unwrap()),
+                "".to_string(),
+                false,
+            ),
             CppFunctionBody::StaticMethodCall(ns, ty_id, fn_id) => {
                 let underlying_function_call = ns
                     .into_iter()
@@ -616,17 +1452,107 @@ impl<'a> CppCodeGenerator<'a> {
                 Some(format!("{declaration};")),
                 Some(format!("{qualified_declaration} {definition_after_sig}")),
             )
+        } else if details.force_inline {
+            (
+                Some(format!(
+                    "AUTOCXX_ALWAYS_INLINE {declaration} {definition_after_sig}"
+                )),
+                None,
+            )
         } else {
             (
                 Some(format!("inline {declaration} {definition_after_sig}")),
                 None,
             )
         };
-        let mut headers = vec![Header::System("memory")];
+        let mut headers = vec![Header::System("memory"), Header::AllocatorHooks];
+        if details.force_inline && !requires_rust_declarations {
+            headers.push(Header::AlwaysInlinePrelude);
+        }
         if need_allocators {
             headers.push(Header::System("stddef.h"));
             headers.push(Header::NewDeletePrelude);
         }
+        if let CppFunctionBody::Destructor(ns, id) = &details.payload {
+            let full_name = QualifiedName::new(ns, id.clone());
+            if matches!(
+                self.config.get_drop_behaviour(&full_name.to_cpp_name()),
+                Some(DropBehaviour::Assert)
+            ) {
+                headers.push(Header::System("cassert"));
+            }
+        }
+        let returns_string_value = details.return_conversion.as_ref().is_some_and(|ret| {
+            !ret.populate_return_value()
+                && match ret.cxxbridge_type() {
+                    Type::Path(typ) => {
+                        known_types().convertible_from_strs(&QualifiedName::from_type_path(typ))
+                    }
+                    _ => false,
+                }
+        });
+        if returns_string_value {
+            headers.push(Header::System("string"));
+            headers.push(Header::StringValuePrelude);
+        }
+        let uses_std_array = details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| {
+                matches!(
+                    conv.cpp_conversion,
+                    CppConversionType::FromStdArrayToCArray
+                        | CppConversionType::FromCArrayToStdArray
+                )
+            });
+        if uses_std_array {
+            headers.push(Header::System("array"));
+        }
+        let uses_std_byte = details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| {
+                matches!(
+                    conv.cpp_conversion,
+                    CppConversionType::FromU8ToStdByte | CppConversionType::FromStdByteToU8
+                )
+            });
+        if uses_std_byte {
+            headers.push(Header::System("cstddef"));
+        }
+        let copies_std_bytes = details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| {
+                matches!(
+                    conv.cpp_conversion,
+                    CppConversionType::FromU8ToStdByte | CppConversionType::FromStdByteToU8
+                ) && conv.copies_std_bytes()
+            });
+        if copies_std_bytes {
+            headers.push(Header::StdBytePrelude);
+        }
+        let copies_pmr = details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| conv.copies_pmr);
+        if copies_pmr {
+            headers.push(Header::System("memory_resource"));
+            headers.push(Header::PmrPrelude);
+        }
+        if matches!(details.payload, CppFunctionBody::ObjectIdentity) {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("memory"));
+        }
+        if matches!(details.payload, CppFunctionBody::TypeName) {
+            headers.push(Header::System("string"));
+            headers.push(Header::System("typeinfo"));
+            headers.push(Header::TypeNamePrelude);
+        }
         Ok(ExtraCpp {
             declaration,
             definition,
@@ -652,6 +1578,20 @@ impl<'a> CppCodeGenerator<'a> {
         })
     }
 
+    fn generate_container_accessor(&mut self, tn: &QualifiedName, accessor: &ContainerAccessor) {
+        let ty = tn.get_final_item();
+        let wrapper_name = &accessor.wrapper_name;
+        let declaration = match accessor.lookup {
+            ContainerLookup::Index => format!("inline const {ty}::value_type* {wrapper_name}(const {ty}& container, size_t index) {{ return index < container.size() ? std::addressof(container[index]) : nullptr; }}"),
+            ContainerLookup::Key(_) => format!("inline const {ty}::mapped_type* {wrapper_name}(const {ty}& container, const {ty}::key_type& key) {{ auto it = container.find(key); return it == container.end() ? nullptr : std::addressof(it->second); }}"),
+        };
+        self.additional_functions.push(ExtraCpp {
+            declaration: Some(declaration),
+            headers: vec![Header::System("cstddef"), Header::System("memory")],
+            ..Default::default()
+        })
+    }
+
     fn generate_subclass(
         &mut self,
         superclass: &QualifiedName,
@@ -709,13 +1649,27 @@ impl<'a> CppCodeGenerator<'a> {
         method_decls.push(format!(
             "{super_name}& As_{super_name}_mut() {{ return *this; }}"
         ));
-        self.additional_functions.push(ExtraCpp {
-            declaration: Some(format!(
-                "inline std::unique_ptr<{}> {}_As_{}_UniquePtr(std::unique_ptr<{}> u) {{ return std::unique_ptr<{}>(u.release()); }}",
-                superclass.to_cpp_name(), subclass.cpp(), super_name, subclass.cpp(), superclass.to_cpp_name(),
+        let non_virtual_destructor = self
+            .config
+            .get_non_virtual_destructor_policy(&subclass.id().to_string());
+        if non_virtual_destructor != NonVirtualDestructor::Leak {
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!(
+                    "inline std::unique_ptr<{}> {}_As_{}_UniquePtr(std::unique_ptr<{}> u) {{ return std::unique_ptr<{}>(u.release()); }}",
+                    superclass.to_cpp_name(), subclass.cpp(), super_name, subclass.cpp(), superclass.to_cpp_name(),
                 )),
                 ..Default::default()
-        });
+            });
+        }
+        if non_virtual_destructor == NonVirtualDestructor::Downcast {
+            self.additional_functions.push(ExtraCpp {
+                declaration: Some(format!(
+                    "inline void {}_Delete_As_{}(std::unique_ptr<{}> u) {{ delete static_cast<{}*>(u.release()); }}",
+                    subclass.cpp(), super_name, superclass.to_cpp_name(), subclass.cpp(),
+                )),
+                ..Default::default()
+            });
+        }
         // And now constructors
         let mut constructor_decls: Vec<String> = Vec::new();
         for constructor in constructors {
@@ -754,3 +1708,62 @@ impl<'a> CppCodeGenerator<'a> {
         Ok(())
     }
 }
+
+/// The pointer type corresponding to a reference, or a pinned mutable
+/// reference, for use in C function signatures.
+fn reference_to_pointer(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => Some(parse_quote! { *mut #elem }),
+        Type::Reference(TypeReference { elem, .. }) => Some(parse_quote! { *const #elem }),
+        Type::Path(typ) => {
+            extract_pinned_mutable_reference_type(typ).map(|elem| parse_quote! { *mut #elem })
+        }
+        _ => None,
+    }
+}
+
+/// The name of the global typedef we generate for a forward declaration
+/// nested within a class, e.g. `ns_Outer_Inner` for `ns::Outer::Inner`.
+/// cxx can't be told that `Outer` is a class rather than a namespace, so
+/// we refer to the type via this typedef instead.
+pub(crate) fn nested_forward_declaration_typedef_name(name: &QualifiedName) -> String {
+    name.segment_iter().join("_")
+}
+
+/// The `std::function` C++ sees for a closure.
+fn std_function(boxed_fn: &BoxedFn) -> String {
+    format!(
+        "std::function<{}({})>",
+        closure_ret(boxed_fn),
+        boxed_fn
+            .inputs
+            .iter()
+            .map(|ty| cpp_scalar(ty).unwrap())
+            .join(", ")
+    )
+}
+
+/// The parameters of a closure, and the arguments which pass them on.
+fn closure_params(boxed_fn: &BoxedFn) -> (Vec<String>, String) {
+    let params = boxed_fn
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, ty)| format!("{} a{index}", cpp_scalar(ty).unwrap()))
+        .collect();
+    let args = (0..boxed_fn.inputs.len())
+        .map(|index| format!("a{index}"))
+        .join(", ");
+    (params, args)
+}
+
+fn closure_ret(boxed_fn: &BoxedFn) -> &'static str {
+    boxed_fn
+        .output
+        .as_ref()
+        .map_or("void", |ty| cpp_scalar(ty).unwrap())
+}
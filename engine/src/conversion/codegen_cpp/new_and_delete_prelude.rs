@@ -12,7 +12,8 @@ use indoc::indoc;
 /// or the standard one.
 /// The SFINAE magic here is: int is a better match than long,
 /// and so the versions which match class-specific operator new/delete
-/// will be used in preference to the general global ::operator new/delete.
+/// will be used in preference to the general global ::operator new/delete,
+/// or whatever has been substituted for them in the allocator hooks.
 pub(super) static NEW_AND_DELETE_PRELUDE: &str = indoc! {"
     #ifndef AUTOCXX_NEW_AND_DELETE_PRELUDE
     #define AUTOCXX_NEW_AND_DELETE_PRELUDE
@@ -21,7 +22,7 @@ pub(super) static NEW_AND_DELETE_PRELUDE: &str = indoc! {"
     auto delete_imp(T *ptr, int) -> decltype((void)T::operator delete(ptr)) {
       T::operator delete(ptr);
     }
-    template <typename T> void delete_imp(T *ptr, long) { ::operator delete(ptr); }
+    template <typename T> void delete_imp(T *ptr, long) { AUTOCXX_OPERATOR_DELETE(ptr); }
     template <typename T> void delete_appropriately(T *obj) {
       // 0 is a better match for the first 'delete_imp' so will match
       // preferentially.
@@ -32,7 +33,7 @@ pub(super) static NEW_AND_DELETE_PRELUDE: &str = indoc! {"
       return T::operator new(count);
     }
     template <typename T> void *new_imp(size_t count, long) {
-      return ::operator new(count);
+      return AUTOCXX_OPERATOR_NEW(count);
     }
     template <typename T> T *new_appropriately() {
       // 0 is a better match for the first 'delete_imp' so will match
@@ -0,0 +1,22 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Used where a `std::pmr` container is presented to Rust as its `std`
+/// equivalent, to copy between the two. A copy into a `std::pmr`
+/// container uses the default memory resource.
+pub(super) static PMR_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_PMR_PRELUDE
+    #define AUTOCXX_PMR_PRELUDE
+    template <typename To, typename From>
+    To autocxx_pmr_copy(const From& from) {
+        return To(from.begin(), from.end());
+    }
+    #endif // AUTOCXX_PMR_PRELUDE
+"};
@@ -0,0 +1,26 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Used where a container of `std::byte` is presented to Rust as a
+/// container of `uint8_t`. The two are unrelated types, so we can't cast
+/// one to the other, and instead copy each element.
+pub(super) static STD_BYTE_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_STD_BYTE_PRELUDE
+    #define AUTOCXX_STD_BYTE_PRELUDE
+    template <typename To, typename From>
+    To autocxx_std_byte_copy(const From& from) {
+        To to;
+        for (auto element : from) {
+            to.push_back(static_cast<typename To::value_type>(element));
+        }
+        return to;
+    }
+    #endif // AUTOCXX_STD_BYTE_PRELUDE
+"};
@@ -0,0 +1,25 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Used where a `std::string` is constructed into an
+/// `autocxx::CxxStringValue`. That type's size is chosen by Rust according
+/// to the target platform, so check that the standard library we're
+/// really using agrees. The platform list must match the Rust side.
+pub(super) static STRING_VALUE_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_STRING_VALUE_PRELUDE
+    #define AUTOCXX_STRING_VALUE_PRELUDE
+    #if defined(__APPLE__) || defined(__ANDROID__) || defined(__FreeBSD__) || defined(__OpenBSD__) || defined(__Fuchsia__)
+    static_assert(sizeof(std::string) == 3 * sizeof(void*), \"std::string is not the size autocxx::CxxStringValue expects (libc++ layout)\");
+    #else
+    static_assert(sizeof(std::string) == 16 + 2 * sizeof(void*), \"std::string is not the size autocxx::CxxStringValue expects (libstdc++/MSVC release layout)\");
+    #endif
+    static_assert(alignof(std::string) == alignof(void*), \"std::string is not aligned as autocxx::CxxStringValue expects\");
+    #endif // AUTOCXX_STRING_VALUE_PRELUDE
+"};
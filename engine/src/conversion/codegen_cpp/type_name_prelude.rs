@@ -0,0 +1,34 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Used by `type_name()` methods. Only the Itanium C++ ABI offers a way
+/// to demangle type names; elsewhere (e.g. MSVC) `type_info::name` is
+/// already human-readable.
+pub(super) static TYPE_NAME_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_TYPE_NAME_PRELUDE
+    #define AUTOCXX_TYPE_NAME_PRELUDE
+    #if defined(__GXX_ABI_VERSION)
+    #include <cstdlib>
+    #include <cxxabi.h>
+    #endif
+    inline std::string autocxx_type_name(const std::type_info& info) {
+    #if defined(__GXX_ABI_VERSION)
+      int status = 0;
+      char* demangled = abi::__cxa_demangle(info.name(), nullptr, nullptr, &status);
+      if (status == 0 && demangled) {
+        std::string result(demangled);
+        std::free(demangled);
+        return result;
+      }
+    #endif
+      return info.name();
+    }
+    #endif // AUTOCXX_TYPE_NAME_PRELUDE
+"};
@@ -15,7 +15,7 @@ use indexmap::map::IndexMap as HashMap;
 use itertools::Itertools;
 use quote::ToTokens;
 use std::iter::once;
-use syn::{Token, Type};
+use syn::{Expr, ExprLit, Lit, Token, Type, TypeArray};
 
 /// Map from QualifiedName to original C++ name. Original C++ name does not
 /// include the namespace; this can be assumed to be the same as the namespace
@@ -120,8 +120,12 @@ impl CppNameMap {
                 get_mut_string(&typp.mutability),
                 self.type_to_cpp(typp.elem.as_ref())?
             )),
-            Type::Array(_)
-            | Type::BareFn(_)
+            Type::Array(arr) => Ok(format!(
+                "std::array<{}, {}>",
+                self.type_to_cpp(&arr.elem)?,
+                Self::array_len(arr)
+            )),
+            Type::BareFn(_)
             | Type::Group(_)
             | Type::ImplTrait(_)
             | Type::Infer(_)
@@ -140,6 +144,17 @@ impl CppNameMap {
         }
     }
 
+    /// The length of an array type as it should be written in C++,
+    /// without any Rust integer suffix.
+    pub(crate) fn array_len(arr: &TypeArray) -> String {
+        match &arr.len {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(len), ..
+            }) => len.base10_digits().to_string(),
+            len => len.to_token_stream().to_string(),
+        }
+    }
+
     /// Check an individual item in the name map. Returns a thing if
     /// it's an inner type, otherwise returns none.
     pub(crate) fn get(&self, name: &QualifiedName) -> Option<&CppOriginalName> {
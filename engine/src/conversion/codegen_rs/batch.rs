@@ -0,0 +1,105 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::batches::Batch,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// For each type listed in `batch_methods!`, generate `T_batch`, which
+/// records calls to the methods, and `T::run_batch`, which makes them.
+pub(super) fn generate_batches(batches: &[Batch]) -> Vec<(QualifiedName, RsCodegenResult)> {
+    batches
+        .iter()
+        .map(|batch| {
+            let shim = make_ident(&batch.shim);
+            let id = batch.ty.get_final_ident();
+            let ty = batch.ty.to_type_path();
+            let builder = make_ident(format!("{id}_batch"));
+            let stride = batch.stride();
+            let doc = format!(
+                "Calls to methods of `{}`, to be made all at once by `run_batch`, which needs only a single call into C++.",
+                batch.ty.to_cpp_name()
+            );
+            let recorders = batch.ops.iter().enumerate().map(|(i, op)| {
+                let rust_name = &op.rust_name;
+                let doc = format!("Record a call to `{}`.", op.method);
+                let index = i as u64;
+                let names: Vec<_> = op.params.iter().map(|(name, _)| name).collect();
+                let types = op.params.iter().map(|(_, ty)| ty);
+                let padding = (op.params.len() + 1..stride).map(|_| quote! { 0 });
+                quote! {
+                    #[doc = #doc]
+                    pub fn #rust_name(&mut self, #(#names: #types),*) -> &mut Self {
+                        self.ops.extend_from_slice(&[
+                            #index,
+                            #(autocxx::BatchArg::to_batch_word(#names),)*
+                            #(#padding,)*
+                        ]);
+                        self
+                    }
+                }
+            });
+            let unsafety = batch.requires_unsafe.then(|| quote! { unsafe });
+            let extern_c_mod_items: Vec<ForeignItem> = vec![parse_quote! {
+                fn #shim(obj: Pin<&mut #id>, ops: &[u64]);
+            }];
+            let output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[doc = #doc]
+                    #[derive(Default, Clone, Debug)]
+                    pub struct #builder {
+                        ops: ::std::vec::Vec<u64>,
+                    }
+                },
+                parse_quote! {
+                    impl #builder {
+                        pub fn new() -> Self {
+                            Self::default()
+                        }
+
+                        #(#recorders)*
+
+                        /// The number of calls recorded.
+                        pub fn len(&self) -> usize {
+                            self.ops.len() / #stride
+                        }
+
+                        pub fn is_empty(&self) -> bool {
+                            self.ops.is_empty()
+                        }
+
+                        /// Forget the calls recorded, so that this can be reused.
+                        pub fn clear(&mut self) {
+                            self.ops.clear()
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl #ty {
+                        /// Make all the calls recorded in the batch, in order.
+                        pub #unsafety fn run_batch(self: ::core::pin::Pin<&mut Self>, batch: &#builder) {
+                            cxxbridge::#shim(self, &batch.ops)
+                        }
+                    }
+                },
+            ];
+            let result = RsCodegenResult {
+                extern_c_mod_items,
+                output_mod_items,
+                ..Default::default()
+            };
+            (batch.ty.clone(), result)
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
+use quote::quote;
+use syn::parse_quote;
+
+use crate::{
+    conversion::completion_futures::completion_future_names,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Generate the Rust side of each `completion_future!`: the function
+/// returning a future, and the extern "Rust" type and function through
+/// which the C++ callback delivers the result.
+pub(super) fn generate_completion_futures(
+    config: &IncludeCppConfig,
+    unsafe_policy: &UnsafePolicy,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    config
+        .get_completion_futures()
+        .map(|completion_future| {
+            let names = completion_future_names(completion_future, config);
+            let completion_type = make_ident(&names.completion_type);
+            let cpp_wrapper = make_ident(&names.cpp_wrapper);
+            let complete_fn = make_ident(&names.complete_fn);
+            let future_fn = names.future_fn.get_final_ident();
+            let params = &completion_future.params;
+            let param_names: Vec<_> = (0..params.len())
+                .map(|n| make_ident(format!("arg{n}")))
+                .collect();
+            let (result_type, complete_params, value) = match &completion_future.result {
+                Some(result) => (
+                    quote! { #result },
+                    quote! { completion: Box<#completion_type>, value: #result },
+                    quote! { value },
+                ),
+                None => (
+                    quote! { () },
+                    quote! { completion: Box<#completion_type> },
+                    quote! { () },
+                ),
+            };
+            let unsafety = matches!(unsafe_policy, UnsafePolicy::AllFunctionsUnsafe)
+                .then(|| quote! { unsafe });
+            let doc = format!(
+                "Calls `{}`, returning a future which resolves to the value passed to its callback.",
+                completion_future.function
+            );
+            let result = RsCodegenResult {
+                global_items: vec![
+                    parse_quote! {
+                        #[doc(hidden)]
+                        #[allow(non_camel_case_types)]
+                        pub struct #completion_type(autocxx::oneshot::Sender<#result_type>);
+                    },
+                    parse_quote! {
+                        #[allow(non_snake_case)]
+                        fn #complete_fn(#complete_params) {
+                            completion.0.send(#value);
+                        }
+                    },
+                ],
+                extern_rust_mod_items: vec![
+                    parse_quote! {
+                        type #completion_type;
+                    },
+                    parse_quote! {
+                        fn #complete_fn(#complete_params);
+                    },
+                ],
+                extern_c_mod_items: vec![parse_quote! {
+                    fn #cpp_wrapper(#(#param_names: #params,)* completion: Box<#completion_type>);
+                }],
+                output_mod_items: vec![parse_quote! {
+                    #[doc = #doc]
+                    pub #unsafety fn #future_fn(#(#param_names: #params),*) -> impl std::future::Future<Output = Result<#result_type, autocxx::oneshot::Canceled>> {
+                        let (sender, receiver) = autocxx::oneshot::channel();
+                        cxxbridge::#cpp_wrapper(#(#param_names,)* Box::new(output::#completion_type(sender)));
+                        receiver
+                    }
+                }],
+                ..Default::default()
+            };
+            (names.future_fn, result)
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
+use quote::quote;
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::context_callbacks::context_callback_names,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Generate the Rust side of each `context_callback!`: the function which
+/// registers a closure, and the extern "Rust" function through which the
+/// C++ trampoline calls it.
+pub(super) fn generate_context_callbacks(
+    config: &IncludeCppConfig,
+    unsafe_policy: &UnsafePolicy,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    config
+        .get_context_callbacks()
+        .map(|context_callback| {
+            let names = context_callback_names(context_callback, config);
+            let cpp_register = make_ident(&names.cpp_register);
+            let call_fn = make_ident(&names.call_fn);
+            let closure_fn = names.closure_fn.get_final_ident();
+            let params = &context_callback.params;
+            let param_names: Vec<_> = (0..params.len())
+                .map(|n| make_ident(format!("arg{n}")))
+                .collect();
+            // C++ may call the closure on any thread, concurrently or re-entrantly,
+            // so it can only ever be borrowed immutably.
+            let boxed = quote! { Box<dyn Fn(#(#params),*) + Send + Sync> };
+            let unsafety = matches!(unsafe_policy, UnsafePolicy::AllFunctionsUnsafe)
+                .then(|| quote! { unsafe });
+            let mut extern_c_mod_items: Vec<ForeignItem> = vec![parse_quote! {
+                fn #cpp_register(context: usize);
+            }];
+            let closure_fn_item: Item = match &names.cpp_unregister {
+                Some(cpp_unregister) => {
+                    let cpp_unregister = make_ident(cpp_unregister);
+                    extern_c_mod_items.push(parse_quote! {
+                        fn #cpp_unregister(context: usize);
+                    });
+                    let doc = format!(
+                        "Registers a closure with `{}`. Dropping the result unregisters it with `{}`.",
+                        context_callback.function,
+                        context_callback.unregister.as_ref().unwrap()
+                    );
+                    parse_quote! {
+                        #[doc = #doc]
+                        pub #unsafety fn #closure_fn(callback: impl Fn(#(#params),*) + Send + Sync + 'static) -> autocxx::CallbackRegistration {
+                            let callback: Box<#boxed> = Box::new(Box::new(callback));
+                            let context = Box::into_raw(callback) as usize;
+                            cxxbridge::#cpp_register(context);
+                            autocxx::CallbackRegistration::new(move || {
+                                cxxbridge::#cpp_unregister(context);
+                                // C++ can no longer call the closure.
+                                drop(unsafe { Box::from_raw(context as *mut #boxed) });
+                            })
+                        }
+                    }
+                }
+                None => {
+                    let doc = format!(
+                        "Registers a closure with `{}`. The closure is never freed.",
+                        context_callback.function
+                    );
+                    parse_quote! {
+                        #[doc = #doc]
+                        pub #unsafety fn #closure_fn(callback: impl Fn(#(#params),*) + Send + Sync + 'static) {
+                            let callback: Box<#boxed> = Box::new(Box::new(callback));
+                            cxxbridge::#cpp_register(Box::into_raw(callback) as usize);
+                        }
+                    }
+                }
+            };
+            let result = RsCodegenResult {
+                global_items: vec![parse_quote! {
+                    #[allow(non_snake_case)]
+                    unsafe fn #call_fn(context: usize, #(#param_names: #params),*) {
+                        let callback = unsafe { &*(context as *const #boxed) };
+                        callback(#(#param_names),*)
+                    }
+                }],
+                extern_rust_mod_items: vec![parse_quote! {
+                    unsafe fn #call_fn(context: usize, #(#param_names: #params),*);
+                }],
+                extern_c_mod_items,
+                output_mod_items: vec![closure_fn_item],
+                ..Default::default()
+            };
+            (names.closure_fn, result)
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, RustPath};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, FnArg, ForeignItem, Item, ReturnType, Signature};
+
+use crate::{
+    conversion::extern_rust_closures::{as_boxed_fn, extern_rust_closure_names, BoxedFn},
+    types::make_ident,
+};
+
+use super::RsCodegenResult;
+
+/// Generate the Rust side of an `extern_rust_function` which takes or
+/// returns closures: an extern "Rust" shim which C++ calls instead, which
+/// passes closures by address, and the functions either side use to call
+/// and free the closures it exchanges.
+pub(super) fn generate_extern_rust_closure_fn(
+    path: &RustPath,
+    sig: &Signature,
+    config: &IncludeCppConfig,
+) -> RsCodegenResult {
+    let names = extern_rust_closure_names(sig, config);
+    let shim = make_ident(&names.shim);
+    let mut global_items: Vec<Item> = vec![parse_quote! {
+        use super::#path;
+    }];
+    let mut extern_c_mod_items: Vec<ForeignItem> = Vec::new();
+    let mut extern_rust_mod_items: Vec<ForeignItem> = Vec::new();
+    let mut shim_params = Vec::new();
+    let mut arg_conversions = Vec::new();
+    let mut args = Vec::new();
+    for (index, (input, fns)) in sig.inputs.iter().zip(names.param_fns.iter()).enumerate() {
+        let FnArg::Typed(pt) = input else {
+            continue;
+        };
+        let arg = make_ident(format!("arg{index}"));
+        args.push(arg.clone());
+        let ty = &pt.ty;
+        let Some((invoke, delete)) = fns else {
+            shim_params.push(quote! { #arg: #ty });
+            continue;
+        };
+        shim_params.push(quote! { #arg: usize });
+        let boxed_fn = as_boxed_fn(ty).unwrap();
+        let invoke = make_ident(invoke);
+        let delete = make_ident(delete);
+        let (params, param_names) = closure_params(&boxed_fn);
+        let ret = closure_ret(&boxed_fn);
+        extern_c_mod_items.push(parse_quote! {
+            unsafe fn #invoke(function: usize, #(#params),*) #ret;
+        });
+        extern_c_mod_items.push(parse_quote! {
+            unsafe fn #delete(function: usize);
+        });
+        arg_conversions.push(quote! {
+            // We now own the std::function, and delete it once the
+            // closure is dropped.
+            let function = unsafe { autocxx::CppCallable::new(#arg, cxxbridge::#delete) };
+            let #arg: #ty = Box::new(move |#(#params),*| unsafe {
+                cxxbridge::#invoke(function.get(), #(#param_names),*)
+            });
+        });
+    }
+    let fn_id = path.get_final_ident();
+    let call = quote! { #fn_id(#(#args),*) };
+    let (shim_ret, shim_body) = match (&sig.output, names.ret_fns) {
+        (ReturnType::Type(_, ty), Some((call_fn, drop_fn))) => {
+            let boxed_fn = as_boxed_fn(ty).unwrap();
+            let call_fn = make_ident(call_fn);
+            let drop_fn = make_ident(drop_fn);
+            let (params, param_names) = closure_params(&boxed_fn);
+            let ret = closure_ret(&boxed_fn);
+            global_items.push(parse_quote! {
+                #[allow(non_snake_case)]
+                unsafe fn #call_fn(closure: usize, #(#params),*) #ret {
+                    let closure = unsafe { &*(closure as *const #ty) };
+                    closure(#(#param_names),*)
+                }
+            });
+            global_items.push(parse_quote! {
+                #[allow(non_snake_case)]
+                unsafe fn #drop_fn(closure: usize) {
+                    drop(unsafe { Box::from_raw(closure as *mut #ty) });
+                }
+            });
+            extern_rust_mod_items.push(parse_quote! {
+                unsafe fn #call_fn(closure: usize, #(#params),*) #ret;
+            });
+            extern_rust_mod_items.push(parse_quote! {
+                unsafe fn #drop_fn(closure: usize);
+            });
+            // The closure is a fat pointer, so box it once more to pass it
+            // to C++ as a single address.
+            (
+                quote! { -> usize },
+                quote! {
+                    let closure: Box<#ty> = Box::new(#call);
+                    Box::into_raw(closure) as usize
+                },
+            )
+        }
+        (output, _) => (quote! { #output }, call),
+    };
+    global_items.push(parse_quote! {
+        #[allow(non_snake_case)]
+        fn #shim(#(#shim_params),*) #shim_ret {
+            #(#arg_conversions)*
+            #shim_body
+        }
+    });
+    extern_rust_mod_items.push(parse_quote! {
+        fn #shim(#(#shim_params),*) #shim_ret;
+    });
+    RsCodegenResult {
+        global_items,
+        extern_c_mod_items,
+        extern_rust_mod_items,
+        ..Default::default()
+    }
+}
+
+fn closure_params(boxed_fn: &BoxedFn) -> (Vec<TokenStream>, Vec<syn::Ident>) {
+    let param_names: Vec<_> = (0..boxed_fn.inputs.len())
+        .map(|n| make_ident(format!("a{n}")))
+        .collect();
+    let params = param_names
+        .iter()
+        .zip(boxed_fn.inputs.iter())
+        .map(|(name, ty)| quote! { #name: #ty })
+        .collect();
+    (params, param_names)
+}
+
+fn closure_ret(boxed_fn: &BoxedFn) -> Option<TokenStream> {
+    boxed_fn.output.as_ref().map(|ty| quote! { -> #ty })
+}
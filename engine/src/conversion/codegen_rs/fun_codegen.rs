@@ -6,6 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use autocxx_parser::IncludeCppConfig;
 use indexmap::set::IndexSet as HashSet;
 use std::borrow::Cow;
 
@@ -16,12 +17,14 @@ use syn::{
     parse_quote,
     punctuated::Punctuated,
     token::{Comma, Unsafe},
-    Attribute, ForeignItem, Ident, ImplItem, Item, ReturnType,
+    Attribute, ForeignItem, GenericArgument, Ident, ImplItem, Item, PathArguments, ReturnType,
+    Type,
 };
 
 use super::{
     function_wrapper_rs::RustParamConversion,
     maybe_unsafes_to_tokens,
+    thread_affinity::{generate_thread_affinity_check, generate_thread_affinity_record},
     unqualify::{unqualify_params_minisyn, unqualify_ret_type},
     utils::generate_cxx_use_stmt,
     ImplBlockDetails, MaybeUnsafeStmt, RsCodegenResult, TraitImplBlockDetails,
@@ -29,16 +32,23 @@ use super::{
 use crate::{
     conversion::{
         analysis::fun::{
-            function_wrapper::TypeConversionPolicy, ArgumentAnalysis, FnAnalysis, FnKind,
-            MethodKind, RustRenameStrategy, TraitMethodDetails,
+            function_wrapper::{CppFunctionBody, RustConversionType, TypeConversionPolicy},
+            ArgumentAnalysis, FnAnalysis, FnKind, MethodKind, RustRenameStrategy,
+            TraitMethodDetails,
         },
-        api::UnsafetyNeeded,
+        api::{TraitImplSignature, UnsafetyNeeded},
     },
     minisyn::{minisynize_vec, FnArg},
     types::QualifiedName,
 };
 use crate::{
-    conversion::{api::FuncToConvert, codegen_rs::lifetime::add_explicit_lifetime_if_necessary},
+    conversion::{
+        api::FuncToConvert,
+        codegen_rs::lifetime::{
+            add_explicit_lifetime_if_necessary, add_lifetime_to_param, add_lifetime_to_receiver,
+        },
+        string_values::string_value_names,
+    },
     types::make_ident,
 };
 
@@ -90,6 +100,7 @@ pub(super) fn gen_function(
     fun: FuncToConvert,
     analysis: FnAnalysis,
     non_pod_types: &HashSet<QualifiedName>,
+    config: &IncludeCppConfig,
 ) -> RsCodegenResult {
     if analysis.ignore_reason.is_err() || !analysis.externally_callable {
         return RsCodegenResult::default();
@@ -105,6 +116,12 @@ pub(super) fn gen_function(
     let vis = analysis.vis;
     let kind = analysis.kind;
     let doc_attrs = minisynize_vec(fun.doc_attrs);
+    let returns_lifetime_of = analysis
+        .returns_lifetime_of_arg
+        .map(|arg| &param_details[arg].name.0);
+
+    let c_abi_fn = analysis.c_abi_name.as_ref().map(make_ident);
+    let string_value_destroy = make_ident(string_value_names(config).destroy_fn);
 
     let mut cpp_name_attr = Vec::new();
     let mut impl_entry = None;
@@ -119,6 +136,14 @@ pub(super) fn gen_function(
         non_pod_types,
         ret_type: &ret_type,
         ret_conversion: &ret_conversion,
+        returns_view: analysis.returns_view,
+        returns_lifetime_of,
+        must_use: analysis.is_getter,
+        ensure_initialized: analysis.ensure_initialized,
+        thread_affine_type: analysis.thread_affine_type.as_ref(),
+        c_abi_fn: c_abi_fn.as_ref(),
+        checks_null_params: analysis.checks_null_params,
+        string_value_destroy: &string_value_destroy,
     };
     // In rare occasions, we might need to give an explicit lifetime.
     let (lifetime_tokens, params, ret_type) = add_explicit_lifetime_if_necessary(
@@ -127,10 +152,21 @@ pub(super) fn gen_function(
         Cow::Borrowed(&ret_type),
         non_pod_types,
         &ret_conversion,
+        returns_lifetime_of,
     );
 
     let mut output_mod_items = Vec::new();
 
+    if let Some(c_abi_fn) = &c_abi_fn {
+        // Everything here is a scalar or a small POD, which bindgen has
+        // made `#[repr(C)]`, so this matches the C++ declaration.
+        output_mod_items.push(parse_quote! {
+            extern "C" {
+                fn #c_abi_fn(#params) #ret_type;
+            }
+        });
+    }
+
     if analysis.rust_wrapper_needed {
         match kind {
             FnKind::Method {
@@ -140,6 +176,7 @@ pub(super) fn gen_function(
             } => {
                 // Constructor.
                 impl_entry = Some(fn_generator.generate_constructor_impl(impl_for));
+                trait_impl_entry = fn_generator.generate_cpp_new_impl(impl_for);
             }
             FnKind::Method {
                 ref impl_for,
@@ -168,6 +205,66 @@ pub(super) fn gen_function(
         output_mod_items.push(generate_cxx_use_stmt(name, alias));
     }
 
+    if analysis.rust_wrapper_needed {
+        let impl_for = match &kind {
+            FnKind::Method { impl_for, .. } => Some(impl_for),
+            _ => None,
+        };
+        for alias in &analysis.deprecated_aliases {
+            let alias_generator = FnGenerator {
+                rust_name: alias,
+                ..fn_generator.clone()
+            };
+            output_mod_items.push(alias_generator.generate_alias(Some(rust_name), impl_for));
+        }
+        for accessor in &analysis.property_accessors {
+            let accessor_generator = FnGenerator {
+                rust_name: accessor,
+                ..fn_generator.clone()
+            };
+            output_mod_items.push(accessor_generator.generate_alias(None, impl_for));
+        }
+    }
+
+    if let (
+        Some((
+            CppFunctionBody::ConversionOperator {
+                impl_from: true, ..
+            },
+            _,
+        )),
+        FnKind::Method { ref impl_for, .. },
+    ) = (&fun.synthetic_cpp, &kind)
+    {
+        output_mod_items.extend(fn_generator.generate_from_impl(impl_for));
+    }
+
+    if let (Some((CppFunctionBody::ErrorMessage(_), _)), FnKind::Method { ref impl_for, .. }) =
+        (&fun.synthetic_cpp, &kind)
+    {
+        output_mod_items.extend(fn_generator.generate_error_impls(impl_for));
+    }
+
+    if let (Some((CppFunctionBody::ValueInitialize(_), _)), FnKind::Method { ref impl_for, .. }) =
+        (&fun.synthetic_cpp, &kind)
+    {
+        output_mod_items.push(fn_generator.generate_default_impl(impl_for));
+    }
+
+    if analysis.async_wrapper_needed {
+        output_mod_items.push(fn_generator.generate_async_function_impl());
+    }
+
+    if let Some((adl_method_for, receiver_mutability)) = &analysis.adl_method_for {
+        let mut param_details = param_details.clone();
+        param_details[0].self_type = Some((adl_method_for.clone(), *receiver_mutability));
+        let method_generator = FnGenerator {
+            param_details: &param_details,
+            ..fn_generator.clone()
+        };
+        output_mod_items.push(method_generator.generate_adl_method_impl(adl_method_for));
+    }
+
     if let Some(cpp_call_name) = cpp_call_name {
         if cpp_call_name.does_not_match_cxxbridge_name(&cxxbridge_name) && !wrapper_function_needed
         {
@@ -227,6 +324,20 @@ struct FnGenerator<'a> {
     unsafety: &'a UnsafetyNeeded,
     doc_attrs: &'a Vec<Attribute>,
     non_pod_types: &'a HashSet<QualifiedName>,
+    returns_view: bool,
+    returns_lifetime_of: Option<&'a syn::Pat>,
+    must_use: bool,
+    ensure_initialized: bool,
+    thread_affine_type: Option<&'a QualifiedName>,
+    /// The flat `extern "C"` function to call instead of the cxx bridge,
+    /// if any.
+    c_abi_fn: Option<&'a Ident>,
+    /// Whether to return `Err(NullUniquePtr)` for a null value parameter
+    /// rather than panicking. See `panic_free!`.
+    checks_null_params: bool,
+    /// The C++ function which destroys a `std::string` returned within an
+    /// `autocxx::CxxStringValue`. See `strings_by_value!`.
+    string_value_destroy: &'a Ident,
 }
 
 impl<'a> FnGenerator<'a> {
@@ -245,17 +356,34 @@ impl<'a> FnGenerator<'a> {
         let mut local_variables = Vec::new();
         let mut arg_list = Vec::new();
         let mut ptr_arg_name = None;
+        let mut receiver_address = None;
+        let mut returns_string_value = false;
         let mut ret_type: Cow<'a, _> = ret_type
             .map(Cow::Owned)
             .unwrap_or_else(|| Cow::Borrowed(self.ret_type));
         let mut any_conversion_requires_unsafe = false;
         let mut variable_counter = 0usize;
+        let mut null_checks = Vec::new();
         for pd in self.param_details {
             let wrapper_arg_name: syn::Pat = if pd.self_type.is_some() && !avoid_self {
                 parse_quote!(self)
             } else {
                 pd.name.clone().into()
             };
+            if self.checks_null_params {
+                let param_trait = match pd.conversion.rust_conversion {
+                    RustConversionType::FromValueParamToPtr => Some(quote! { ValueParam }),
+                    RustConversionType::FromRValueParamToPtr => Some(quote! { RValueParam }),
+                    _ => None,
+                };
+                if let Some(param_trait) = param_trait {
+                    null_checks.push(quote! {
+                        if autocxx::#param_trait::is_null(&#wrapper_arg_name) {
+                            return Err(autocxx::NullUniquePtr);
+                        }
+                    });
+                }
+            }
             let rust_for_param = pd
                 .conversion
                 .rust_conversion(parse_quote! { #wrapper_arg_name }, &mut variable_counter);
@@ -271,6 +399,14 @@ impl<'a> FnGenerator<'a> {
                     if pd.is_placement_return_destination {
                         ptr_arg_name = Some(conversion);
                     } else {
+                        if pd.self_type.is_some() && receiver_address.is_none() {
+                            receiver_address = Some(match pd.conversion.rust_conversion {
+                                RustConversionType::FromReferenceWrapperToPointer => {
+                                    quote! { #conversion as usize }
+                                }
+                                _ => quote! { &*#wrapper_arg_name as *const _ as usize },
+                            });
+                        }
                         let param_mutability = pd.conversion.rust_conversion.requires_mutability();
                         wrapper_params.push(parse_quote!(
                             #param_mutability #wrapper_arg_name: #ty
@@ -279,12 +415,18 @@ impl<'a> FnGenerator<'a> {
                     any_conversion_requires_unsafe =
                         conversion_requires_unsafe || any_conversion_requires_unsafe;
                 }
-                RustParamConversion::ReturnValue { ty } => {
-                    ptr_arg_name = Some(pd.name.to_token_stream());
+                RustParamConversion::ReturnValue { ty, needs_cast } => {
+                    let name = pd.name.to_token_stream();
+                    ptr_arg_name = Some(name.clone());
+                    returns_string_value = needs_cast;
                     ret_type = Cow::Owned(parse_quote! {
                         -> impl autocxx::moveit::new::New<Output = #ty>
                     });
-                    arg_list.push(pd.name.to_token_stream());
+                    arg_list.push(if needs_cast {
+                        quote! { #name.cast() }
+                    } else {
+                        name
+                    });
                 }
             }
         }
@@ -297,19 +439,25 @@ impl<'a> FnGenerator<'a> {
             ret_type,
             self.non_pod_types,
             self.ret_conversion,
+            self.returns_lifetime_of,
         );
 
         let cxxbridge_name = self.cxxbridge_name;
-        let call_body = MaybeUnsafeStmt::maybe_unsafe(
-            quote! {
-                cxxbridge::#cxxbridge_name ( #(#arg_list),* )
-            },
-            any_conversion_requires_unsafe
-                || matches!(
-                    self.unsafety,
-                    UnsafetyNeeded::JustBridge | UnsafetyNeeded::Always
-                ),
-        );
+        let call_body = match self.c_abi_fn {
+            Some(c_abi_fn) => MaybeUnsafeStmt::needs_unsafe(quote! {
+                #c_abi_fn ( #(#arg_list),* )
+            }),
+            None => MaybeUnsafeStmt::maybe_unsafe(
+                quote! {
+                    cxxbridge::#cxxbridge_name ( #(#arg_list),* )
+                },
+                any_conversion_requires_unsafe
+                    || matches!(
+                        self.unsafety,
+                        UnsafetyNeeded::JustBridge | UnsafetyNeeded::Always
+                    ),
+            ),
+        };
 
         // Per RFC 2585 (https://rust-lang.github.io/rfcs/2585-unsafe-block-in-unsafe-fn.html),
         // all calls to unsafe functions must be wrapped in an unsafe block, even within an unsafe function.
@@ -358,12 +506,29 @@ impl<'a> FnGenerator<'a> {
             _ => (call_body, ret_type),
         };
 
-        let call_stmts = if let Some(ptr_arg_name) = ptr_arg_name {
+        let mut call_stmts = if let Some(ptr_arg_name) = ptr_arg_name {
             let mut closure_stmts = local_variables;
             closure_stmts.push(MaybeUnsafeStmt::binary(
                 quote! { let #ptr_arg_name = unsafe { #ptr_arg_name.get_unchecked_mut().as_mut_ptr() };},
                 quote! { let #ptr_arg_name = #ptr_arg_name.get_unchecked_mut().as_mut_ptr();},
             ));
+            // A thread_affine! method has a receiver to check; otherwise
+            // this is one of its constructors, so the new object belongs
+            // to this thread.
+            if let (Some(ty), None) = (self.thread_affine_type, &receiver_address) {
+                closure_stmts.push(MaybeUnsafeStmt::new(generate_thread_affinity_record(
+                    ty,
+                    &ptr_arg_name,
+                )));
+            }
+            // Neither field overlaps the string's own storage, so the
+            // destroy function can be set before C++ constructs the string.
+            if returns_string_value {
+                let destroy_fn = self.string_value_destroy;
+                closure_stmts.push(MaybeUnsafeStmt::needs_unsafe(quote! {
+                    autocxx::CxxStringValue::set_destroy(#ptr_arg_name, cxxbridge::#destroy_fn);
+                }));
+            }
             closure_stmts.push(call_body);
             let closure_stmts = maybe_unsafes_to_tokens(closure_stmts, true);
             vec![MaybeUnsafeStmt::needs_unsafe(parse_quote! {
@@ -376,8 +541,78 @@ impl<'a> FnGenerator<'a> {
             call_stmts.push(call_body);
             call_stmts
         };
+        if self.ensure_initialized {
+            call_stmts.insert(
+                0,
+                MaybeUnsafeStmt::new(quote! {
+                    #[cfg(debug_assertions)]
+                    output::ensure_initialized();
+                }),
+            );
+        }
+        if let (Some(ty), Some(object)) = (self.thread_affine_type, receiver_address) {
+            call_stmts.insert(
+                0,
+                MaybeUnsafeStmt::new(generate_thread_affinity_check(ty, object)),
+            );
+        }
         let call_body = maybe_unsafes_to_tokens(call_stmts, context_is_unsafe);
-        (lifetime_tokens, wrapper_params, ret_type, call_body)
+        if null_checks.is_empty() {
+            return (lifetime_tokens, wrapper_params, ret_type, call_body);
+        }
+        // Check every parameter before any is moved into C++.
+        let ok_type: Type = match ret_type.as_ref() {
+            ReturnType::Default => parse_quote! { () },
+            ReturnType::Type(_, ty) => ty.as_ref().clone(),
+        };
+        (
+            lifetime_tokens,
+            wrapper_params,
+            Cow::Owned(parse_quote! { -> Result<#ok_type, autocxx::NullUniquePtr> }),
+            quote! {
+                #(#null_checks)*
+                Ok({ #call_body })
+            },
+        )
+    }
+
+    /// Tie any returned view to the lifetime of the receiver, or of the
+    /// argument named by `returns_lifetime_of_arg!`.
+    fn borrowing_view<'b>(
+        &self,
+        (lifetime_tokens, mut wrapper_params, ret_type, call_body): (
+            Option<TokenStream>,
+            Punctuated<FnArg, Comma>,
+            Cow<'b, ReturnType>,
+            TokenStream,
+        ),
+    ) -> (
+        Option<TokenStream>,
+        Punctuated<FnArg, Comma>,
+        Cow<'b, ReturnType>,
+        TokenStream,
+    ) {
+        let view_ty = match ret_type.as_ref() {
+            ReturnType::Type(_, view_ty) if self.returns_view => view_ty.clone(),
+            _ => return (lifetime_tokens, wrapper_params, ret_type, call_body),
+        };
+        let borrowed = match self.returns_lifetime_of {
+            Some(arg) => add_lifetime_to_param(&mut wrapper_params, arg),
+            None => add_lifetime_to_receiver(&mut wrapper_params),
+        };
+        if !borrowed {
+            return (lifetime_tokens, wrapper_params, ret_type, call_body);
+        }
+        (
+            Some(quote! { <'a> }),
+            wrapper_params,
+            Cow::Owned(parse_quote! {
+                -> autocxx::CppView<'a, #view_ty>
+            }),
+            quote! {
+                autocxx::CppView::new({ #call_body })
+            },
+        )
     }
 
     /// Generate an 'impl Type { methods-go-here }' item
@@ -387,14 +622,16 @@ impl<'a> FnGenerator<'a> {
         impl_block_type_name: &QualifiedName,
     ) -> Box<ImplBlockDetails> {
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
-            self.common_parts(avoid_self, &None, None);
+            self.borrowing_view(self.common_parts(avoid_self, &None, None));
         let rust_name = make_ident(self.rust_name);
         let unsafety = self.unsafety.wrapper_token();
         let doc_attrs = self.doc_attrs;
+        let must_use = self.must_use.then(|| quote! { #[must_use] });
         let ty = impl_block_type_name.get_final_ident();
         Box::new(ImplBlockDetails {
             item: ImplItem::Fn(parse_quote! {
                 #(#doc_attrs)*
+                #must_use
                 pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
                     #call_body
                 }
@@ -403,6 +640,156 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// Generate an 'impl Type { method }' for a free function which C++
+    /// would find by argument-dependent lookup. This is emitted alongside
+    /// the free function, which may be in a different mod from the type.
+    fn generate_adl_method_impl(&self, impl_block_type_name: &QualifiedName) -> Item {
+        let (lifetime_tokens, wrapper_params, ret_type, call_body) =
+            self.common_parts(false, &None, None);
+        let rust_name = make_ident(self.rust_name);
+        let unsafety = self.unsafety.wrapper_token();
+        let doc_attrs = self.doc_attrs;
+        let ty = impl_block_type_name.to_type_path();
+        Item::Impl(parse_quote! {
+            impl #ty {
+                #(#doc_attrs)*
+                pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
+                    #call_body
+                }
+            }
+        })
+    }
+
+    /// Generate a copy of this function or method under another name:
+    /// either an old name given by `deprecated_alias!`, marked
+    /// `#[deprecated]` so that callers can move to `new_name` at their own
+    /// pace, or the `get_`/`put_` accessor of an MSVC property.
+    fn generate_alias(&self, new_name: Option<&str>, impl_for: Option<&QualifiedName>) -> Item {
+        let (lifetime_tokens, wrapper_params, ret_type, call_body) =
+            self.borrowing_view(self.common_parts(false, &None, None));
+        let alias = make_ident(self.rust_name);
+        let unsafety = self.unsafety.wrapper_token();
+        let doc_attrs = self.doc_attrs;
+        let deprecation = new_name.map(|new_name| {
+            let note = format!("renamed to `{new_name}`");
+            quote! { #[deprecated(note = #note)] }
+        });
+        let fun = quote! {
+            #(#doc_attrs)*
+            #deprecation
+            #[allow(non_snake_case)]
+            pub #unsafety fn #alias #lifetime_tokens ( #wrapper_params ) #ret_type {
+                #call_body
+            }
+        };
+        match impl_for {
+            Some(ty) => {
+                let ty = ty.get_final_ident();
+                Item::Impl(parse_quote! {
+                    impl #ty {
+                        #fun
+                    }
+                })
+            }
+            None => Item::Fn(parse_quote! { #fun }),
+        }
+    }
+
+    /// Call this method, which must be one made by `create_synthetic_method`
+    /// taking only a const receiver, on `receiver`, a `&Self`. Under the
+    /// wrapped references policy the method takes a `CppRef` instead.
+    /// If the method is `unsafe`, that's only because of the unsafe policy:
+    /// these methods are sound to call on any valid reference.
+    fn generate_synthetic_method_call(&self, receiver: TokenStream) -> TokenStream {
+        let rust_name = make_ident(self.rust_name);
+        let references_wrapped = self.param_details.iter().any(|pd| {
+            pd.self_type.is_some()
+                && matches!(
+                    pd.conversion.rust_conversion,
+                    RustConversionType::FromReferenceWrapperToPointer
+                )
+        });
+        let receiver = if references_wrapped {
+            quote! { autocxx::CppRef::from_ptr(#receiver) }
+        } else {
+            receiver
+        };
+        let call = quote! { #receiver.#rust_name() };
+        match self.unsafety.wrapper_token() {
+            Some(_) => quote! { unsafe { #call } },
+            None => call,
+        }
+    }
+
+    /// Generate an 'impl From<&Type> for ReturnType' which calls this
+    /// method.
+    fn generate_from_impl(&self, impl_block_type_name: &QualifiedName) -> Option<Item> {
+        let (_, _, ret_type, _) = self.common_parts(false, &None, None);
+        let ret_type = match ret_type.as_ref() {
+            ReturnType::Type(_, ty) => ty.clone(),
+            ReturnType::Default => return None,
+        };
+        let ty = impl_block_type_name.get_final_ident();
+        let call = self.generate_synthetic_method_call(quote! { value });
+        Some(Item::Impl(parse_quote! {
+            impl<'a> From<&'a #ty> for #ret_type {
+                fn from(value: &'a #ty) -> Self {
+                    #call
+                }
+            }
+        }))
+    }
+
+    /// Implement `Default` for a type listed in `pod_default!`, in terms
+    /// of its synthesized value-initializing function.
+    fn generate_default_impl(&self, impl_block_type_name: &QualifiedName) -> Item {
+        let rust_name = make_ident(self.rust_name);
+        let ty = impl_block_type_name.get_final_ident();
+        let call = quote! { Self::#rust_name() };
+        // Value-initialization is always safe, even if our policy is to
+        // mark all C++ functions as unsafe.
+        let call = match self.unsafety.wrapper_token() {
+            Some(_) => quote! { unsafe { #call } },
+            None => call,
+        };
+        Item::Impl(parse_quote! {
+            impl Default for #ty {
+                fn default() -> Self {
+                    #call
+                }
+            }
+        })
+    }
+
+    /// Implement `Display`, `Debug` and `std::error::Error` for a type
+    /// listed in `error_type!`, in terms of its synthesized message method.
+    fn generate_error_impls(&self, impl_block_type_name: &QualifiedName) -> Vec<Item> {
+        let ty = impl_block_type_name.get_final_ident();
+        let ty_name = impl_block_type_name.to_cpp_name();
+        let call = self.generate_synthetic_method_call(quote! { self });
+        vec![
+            Item::Impl(parse_quote! {
+                impl std::fmt::Display for #ty {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str(&#call.to_string_lossy())
+                    }
+                }
+            }),
+            Item::Impl(parse_quote! {
+                impl std::fmt::Debug for #ty {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.debug_tuple(#ty_name)
+                            .field(&#call.to_string_lossy())
+                            .finish()
+                    }
+                }
+            }),
+            Item::Impl(parse_quote! {
+                impl std::error::Error for #ty {}
+            }),
+        ]
+    }
+
     /// Generate an 'impl Trait for Type { methods-go-here }' in its entrety.
     fn generate_trait_impl(&self, details: &TraitMethodDetails) -> Box<TraitImplBlockDetails> {
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
@@ -446,10 +833,52 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// Generate an `impl autocxx::CppNew<(params...)> for Type` which
+    /// calls this constructor, if it's safe and none of its parameter types
+    /// borrow or are `impl Trait`, which would need generic parameters on
+    /// the impl.
+    fn generate_cpp_new_impl(
+        &self,
+        impl_block_type_name: &QualifiedName,
+    ) -> Option<Box<TraitImplBlockDetails>> {
+        if self.unsafety.wrapper_token().is_some() {
+            return None;
+        }
+        let ret_type: ReturnType = parse_quote! { -> impl autocxx::moveit::new::New<Output=Self> };
+        let (lifetime_tokens, wrapper_params, _, _) =
+            self.common_parts(true, &None, Some(ret_type.clone()));
+        if lifetime_tokens.is_some() {
+            return None;
+        }
+        let types = wrapper_params
+            .iter()
+            .map(|param| match &param.0 {
+                syn::FnArg::Typed(pt) if !needs_generics(&pt.ty) => Some(pt.ty.as_ref().clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let names: Vec<_> = (0..types.len())
+            .map(|i| make_ident(format!("arg{i}")))
+            .collect();
+        let rust_name = make_ident(self.rust_name);
+        let ty = impl_block_type_name.get_final_ident();
+        let item = parse_quote! {
+            fn cpp_new((#(#names,)*): (#(#types,)*)) #ret_type {
+                Self::#rust_name(#(#names),*)
+            }
+        };
+        let key = TraitImplSignature {
+            ty: parse_quote! { #ty },
+            trait_signature: parse_quote! { autocxx::CppNew<(#(#types,)*)> },
+            unsafety: None,
+        };
+        Some(Box::new(TraitImplBlockDetails { item, key }))
+    }
+
     /// Generate a function call wrapper
     fn generate_function_impl(&self) -> Item {
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
-            self.common_parts(false, &None, None);
+            self.borrowing_view(self.common_parts(false, &None, None));
         let rust_name = make_ident(self.rust_name);
         let doc_attrs = self.doc_attrs;
         let unsafety = self.unsafety.wrapper_token();
@@ -461,6 +890,25 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// Generate an `async` version of a function call wrapper, which
+    /// runs the call using the registered blocking executor.
+    fn generate_async_function_impl(&self) -> Item {
+        let (lifetime_tokens, wrapper_params, ret_type, call_body) =
+            self.common_parts(false, &None, None);
+        let async_name = make_ident(format!("{}_async", self.rust_name));
+        let doc_attrs = self.doc_attrs;
+        let unsafety = self.unsafety.wrapper_token();
+        Item::Fn(parse_quote! {
+            #(#doc_attrs)*
+            ///
+            /// This runs the call using the executor registered with
+            /// [`autocxx::blocking::set_blocking_executor`].
+            pub #unsafety async fn #async_name #lifetime_tokens ( #wrapper_params ) #ret_type {
+                autocxx::blocking::run_blocking(move || { #call_body }).await
+            }
+        })
+    }
+
     fn reorder_parameters(
         params: Punctuated<FnArg, Comma>,
         parameter_ordering: &[usize],
@@ -472,3 +920,23 @@ impl<'a> FnGenerator<'a> {
             .collect()
     }
 }
+
+/// Whether this type borrows, or is an `impl Trait`.
+fn needs_generics(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(_) | Type::ImplTrait(_) => true,
+        Type::Path(typ) => typ.path.segments.iter().any(|seg| match &seg.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+                GenericArgument::Type(ty) => needs_generics(ty),
+                GenericArgument::Lifetime(_) => true,
+                _ => false,
+            }),
+            _ => false,
+        }),
+        Type::Tuple(tuple) => tuple.elems.iter().any(needs_generics),
+        Type::Array(array) => needs_generics(&array.elem),
+        Type::Paren(paren) => needs_generics(&paren.elem),
+        Type::Group(group) => needs_generics(&group.elem),
+        _ => false,
+    }
+}
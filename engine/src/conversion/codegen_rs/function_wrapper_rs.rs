@@ -11,7 +11,8 @@ use syn::{Expr, Type, TypePtr};
 
 use crate::{
     conversion::analysis::fun::function_wrapper::{RustConversionType, TypeConversionPolicy},
-    types::make_ident,
+    known_types::known_types,
+    types::{make_ident, QualifiedName},
 };
 use quote::quote;
 use syn::parse_quote;
@@ -28,6 +29,9 @@ pub(super) enum RustParamConversion {
     },
     ReturnValue {
         ty: Type,
+        /// Whether the placement pointer is of a different type from the
+        /// one which cxx expects, and so needs casting.
+        needs_cast: bool,
     },
 }
 
@@ -162,7 +166,23 @@ impl TypeConversionPolicy {
                     Type::Ptr(TypePtr { elem, .. }) => *(*elem).clone(),
                     _ => panic!("Not a ptr"),
                 };
-                RustParamConversion::ReturnValue { ty }
+                // cxx's own CxxString can't be held by value, so strings
+                // are constructed within our storage type instead.
+                match &ty {
+                    Type::Path(typ)
+                        if known_types()
+                            .convertible_from_strs(&QualifiedName::from_type_path(typ)) =>
+                    {
+                        RustParamConversion::ReturnValue {
+                            ty: parse_quote! { autocxx::CxxStringValue },
+                            needs_cast: true,
+                        }
+                    }
+                    _ => RustParamConversion::ReturnValue {
+                        ty,
+                        needs_cast: false,
+                    },
+                }
             }
             RustConversionType::FromPointerToReferenceWrapper => {
                 let (is_mut, ty) = match self.cxxbridge_type() {
@@ -189,6 +209,42 @@ impl TypeConversionPolicy {
                     conversion_requires_unsafe: false,
                 }
             }
+            RustConversionType::ToOptionalUniquePtr => {
+                let (ty, conversion) = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => (
+                        parse_quote! { Option<cxx::UniquePtr<#elem>> },
+                        quote! {
+                            {
+                                let ptr = #var;
+                                if ptr.is_null() {
+                                    None
+                                } else {
+                                    Some(unsafe { cxx::UniquePtr::from_raw(ptr) })
+                                }
+                            }
+                        },
+                    ),
+                    ty => (
+                        parse_quote! { Option<#ty> },
+                        quote! {
+                            {
+                                let up = #var;
+                                if up.is_null() {
+                                    None
+                                } else {
+                                    Some(up)
+                                }
+                            }
+                        },
+                    ),
+                };
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion,
+                    conversion_requires_unsafe: false,
+                }
+            }
             RustConversionType::FromReferenceWrapperToPointer => {
                 let (is_mut, ty) = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr {
@@ -216,6 +272,21 @@ impl TypeConversionPolicy {
                     conversion_requires_unsafe: false,
                 }
             }
+            RustConversionType::FromWriterToAddress => {
+                let var_counter = *counter;
+                *counter += 1;
+                let writer_var_name = make_ident(format!("writer{var_counter}"));
+                RustParamConversion::Param {
+                    ty: parse_quote! { &mut dyn ::std::io::Write },
+                    local_variables: vec![MaybeUnsafeStmt::new(
+                        quote! { let mut #writer_var_name = autocxx::OstreamWriter::new(#var); },
+                    )],
+                    conversion: quote! {
+                        #writer_var_name.as_context()
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
         }
     }
 }
@@ -0,0 +1,46 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::init_function::init_function_wrapper_name,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Generate `ensure_initialized()` for an `init_function!`.
+pub(super) fn generate_init_function(
+    config: &IncludeCppConfig,
+) -> Option<(QualifiedName, RsCodegenResult)> {
+    let init_function = config.get_init_function()?;
+    let wrapper = make_ident(init_function_wrapper_name(config));
+    let doc = format!(
+        "Calls `{}`, unless it's already been called through this function.",
+        init_function.function
+    );
+    let result = RsCodegenResult {
+        extern_c_mod_items: vec![parse_quote! {
+            fn #wrapper();
+        }],
+        output_mod_items: vec![parse_quote! {
+            #[doc = #doc]
+            pub fn ensure_initialized() {
+                static INITIALIZED: ::std::sync::Once = ::std::sync::Once::new();
+                INITIALIZED.call_once(|| cxxbridge::#wrapper());
+            }
+        }],
+        ..Default::default()
+    };
+    Some((
+        QualifiedName::new_from_cpp_name("ensure_initialized"),
+        result,
+    ))
+}
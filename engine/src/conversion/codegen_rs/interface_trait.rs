@@ -0,0 +1,170 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::map::IndexMap as HashMap;
+use syn::{parse_quote, FnArg, ImplItem, Item, Pat, ReturnType, TraitItem, Type};
+
+use crate::{
+    conversion::{
+        analysis::fun::{FnAnalysis, FnKind, FnPhase, MethodKind, ReceiverMutability},
+        api::Api,
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// For each type listed in a `mockable!` directive, generate a trait
+/// containing its methods, implemented for `Pin<&mut T>` by calling into
+/// C++. Code which accepts an `impl T_interface` can then be given either
+/// the real C++ object or, in tests, a mock. If the user's crate has a
+/// `mockall` feature enabled, `mockall` generates that mock, named
+/// `MockT_interface`.
+///
+/// Only methods whose Rust signature is the same as their cxx signature,
+/// and which don't return references, are included; others would need
+/// lifetimes or parameter conversions which mocks couldn't easily mimic.
+pub(super) fn generate_interface_traits(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    let mut methods_by_type: HashMap<QualifiedName, Vec<(TraitItem, ImplItem)>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Struct { name, .. } = api {
+            if config.is_mockable(&name.name.to_cpp_name()) {
+                methods_by_type.entry(name.name.clone()).or_default();
+            }
+        }
+    }
+    for api in apis.iter() {
+        if let Api::Function { analysis, .. } = api {
+            if let FnKind::Method {
+                impl_for,
+                method_kind:
+                    MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+            } = &analysis.kind
+            {
+                if let Some(methods) = methods_by_type.get_mut(impl_for) {
+                    methods.extend(generate_interface_method(impl_for, analysis));
+                }
+            }
+        }
+    }
+    methods_by_type
+        .into_iter()
+        .map(|(name, methods)| {
+            let id = name.get_final_ident();
+            let trait_id = make_ident(format!("{id}_interface"));
+            let (trait_items, impl_items): (Vec<_>, Vec<_>) = methods.into_iter().unzip();
+            let output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[allow(non_snake_case)]
+                    #[cfg_attr(feature = "mockall", mockall::automock)]
+                    pub trait #trait_id {
+                        #(#trait_items)*
+                    }
+                },
+                parse_quote! {
+                    impl #trait_id for ::core::pin::Pin<&mut #id> {
+                        #(#impl_items)*
+                    }
+                },
+            ];
+            (
+                name,
+                RsCodegenResult {
+                    output_mod_items,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+fn generate_interface_method(
+    impl_for: &QualifiedName,
+    analysis: &FnAnalysis,
+) -> Option<(TraitItem, ImplItem)> {
+    if analysis.ignore_reason.is_err()
+        || !analysis.externally_callable
+        || analysis.returns_view
+        || analysis.returns_lifetime_of_arg.is_some()
+        || analysis.async_wrapper_needed
+        || analysis
+            .param_details
+            .iter()
+            .any(|pd| pd.conversion.rust_work_needed())
+        || analysis
+            .ret_conversion
+            .as_ref()
+            .map(|conversion| conversion.rust_work_needed())
+            .unwrap_or_default()
+        || returns_reference(&analysis.ret_type.0)
+    {
+        return None;
+    }
+    let receiver_mutability = analysis
+        .param_details
+        .first()
+        .and_then(|pd| pd.self_type.as_ref())
+        .map(|(_, mutability)| *mutability)?;
+    let params: Vec<FnArg> = analysis
+        .params
+        .iter()
+        .skip(1)
+        .map(|param| param.0.clone())
+        .collect();
+    let param_names = params
+        .iter()
+        .map(|param| match param {
+            FnArg::Typed(pt) => Some(pt.pat.as_ref().clone()),
+            FnArg::Receiver(_) => None,
+        })
+        .collect::<Option<Vec<Pat>>>()?;
+    let (receiver, this): (FnArg, syn::Expr) = match receiver_mutability {
+        ReceiverMutability::Const => (parse_quote! { &self }, parse_quote! { &**self }),
+        ReceiverMutability::Mutable => (parse_quote! { &mut self }, parse_quote! { self.as_mut() }),
+    };
+    let id = make_ident(&analysis.rust_name);
+    let ret_type = &analysis.ret_type.0;
+    let unsafety = analysis.requires_unsafe.wrapper_token();
+    let ty = impl_for.get_final_ident();
+    let call = if unsafety.is_some() {
+        quote::quote! { unsafe { #ty::#id(#this, #(#param_names),*) } }
+    } else {
+        quote::quote! { #ty::#id(#this, #(#param_names),*) }
+    };
+    Some((
+        parse_quote! {
+            #unsafety fn #id(#receiver, #(#params),*) #ret_type;
+        },
+        parse_quote! {
+            #unsafety fn #id(#receiver, #(#params),*) #ret_type {
+                #call
+            }
+        },
+    ))
+}
+
+pub(super) fn returns_reference(ret_type: &ReturnType) -> bool {
+    match ret_type {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Reference(_) => true,
+            Type::Path(typ) => typ
+                .path
+                .segments
+                .last()
+                .map(|seg| seg.ident == "Pin")
+                .unwrap_or_default(),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
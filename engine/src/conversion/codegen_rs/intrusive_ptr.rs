@@ -0,0 +1,57 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::intrusive_ptrs::IntrusivePtrType,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Implement `autocxx::IntrusiveRefCounted` for each type listed in
+/// `intrusive_ptr!`, so that it can be owned by an `autocxx::IntrusivePtr`.
+pub(super) fn generate_intrusive_ptr_types(
+    intrusive_ptr_types: &[IntrusivePtrType],
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    intrusive_ptr_types
+        .iter()
+        .map(|intrusive_ptr| {
+            let add_ref_shim = make_ident(&intrusive_ptr.add_ref_shim);
+            let release_shim = make_ident(&intrusive_ptr.release_shim);
+            let id = intrusive_ptr.ty.get_final_ident();
+            let ty = intrusive_ptr.ty.to_type_path();
+            let extern_c_mod_items: Vec<ForeignItem> = vec![
+                parse_quote! {
+                    unsafe fn #add_ref_shim(this: *mut #id);
+                },
+                parse_quote! {
+                    unsafe fn #release_shim(this: *mut #id);
+                },
+            ];
+            let output_mod_items: Vec<Item> = vec![parse_quote! {
+                unsafe impl autocxx::IntrusiveRefCounted for #ty {
+                    unsafe fn add_ref(this: *mut Self) {
+                        cxxbridge::#add_ref_shim(this)
+                    }
+
+                    unsafe fn release(this: *mut Self) {
+                        cxxbridge::#release_shim(this)
+                    }
+                }
+            }];
+            let result = RsCodegenResult {
+                extern_c_mod_items,
+                output_mod_items,
+                ..Default::default()
+            };
+            (intrusive_ptr.ty.clone(), result)
+        })
+        .collect()
+}
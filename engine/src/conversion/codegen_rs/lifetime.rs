@@ -34,17 +34,28 @@ use syn::{
 /// 3) Any parameter is any form of reference, and we're returning an `impl New`
 ///    3a) an 'impl ValueParam' counts as a reference.
 /// 4) If we're using CppRef<'a, T> as a param or return type
+/// Where `returns_lifetime_of_arg!` names the parameter from which the
+/// return value borrows, that parameter alone gets the lifetime instead.
 pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     param_details: &[ArgumentAnalysis],
     mut params: Punctuated<FnArg, Comma>,
     ret_type: Cow<'r, ReturnType>,
     non_pod_types: &HashSet<QualifiedName>,
     ret_conversion: &Option<TypeConversionPolicy>,
+    returns_lifetime_of: Option<&syn::Pat>,
 ) -> (
     Option<TokenStream>,
     Punctuated<FnArg, Comma>,
     Cow<'r, ReturnType>,
 ) {
+    if let Some(arg) = returns_lifetime_of {
+        return match add_lifetime_to_return_type(&ret_type) {
+            Some(new_return_type) if add_lifetime_to_param(&mut params, arg) => {
+                (Some(quote! { <'a> }), params, Cow::Owned(new_return_type))
+            }
+            _ => (None, params, ret_type),
+        };
+    }
     let has_mutable_receiver = param_details.iter().any(|pd| {
         matches!(pd.self_type, Some((_, ReceiverMutability::Mutable)))
             && !pd.is_placement_return_destination
@@ -84,7 +95,30 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     {
         return (None, params, ret_type);
     }
-    let new_return_type = match ret_type.as_ref() {
+    let new_return_type = add_lifetime_to_return_type(&ret_type);
+
+    match new_return_type {
+        None if return_type_is_cppref || any_param_is_cppref => {
+            (Some(quote! { <'a> }), params, ret_type)
+        }
+        None => (None, params, ret_type),
+        Some(new_return_type) => {
+            for syn::FnArg::Typed(PatType { ty, .. })
+            | syn::FnArg::Receiver(syn::Receiver { ty, .. }) in
+                params.iter_mut().map(|minifnarg| &mut minifnarg.0)
+            {
+                add_lifetime_to_param_type(ty);
+            }
+
+            (Some(quote! { <'a> }), params, Cow::Owned(new_return_type))
+        }
+    }
+}
+
+/// Give a reference, pinned reference or `impl Trait` return type the
+/// lifetime `'a`.
+fn add_lifetime_to_return_type(ret_type: &ReturnType) -> Option<ReturnType> {
+    match ret_type {
         ReturnType::Type(rarrow, boxed_type) => match boxed_type.as_ref() {
             Type::Reference(rtr) => {
                 let mut new_rtr = rtr.clone();
@@ -109,32 +143,69 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
             _ => None,
         },
         _ => None,
-    };
+    }
+}
 
-    match new_return_type {
-        None if return_type_is_cppref || any_param_is_cppref => {
-            (Some(quote! { <'a> }), params, ret_type)
+/// Returns whether the type could carry the lifetime `'a`.
+fn add_lifetime_to_param_type(ty: &mut Type) -> bool {
+    match ty {
+        Type::Path(TypePath {
+            path: Path { segments, .. },
+            ..
+        }) => add_lifetime_to_pinned_reference(segments).is_ok(),
+        Type::Reference(tyr) => {
+            add_lifetime_to_reference(tyr);
+            true
         }
-        None => (None, params, ret_type),
-        Some(new_return_type) => {
-            for syn::FnArg::Typed(PatType { ty, .. })
-            | syn::FnArg::Receiver(syn::Receiver { ty, .. }) in
-                params.iter_mut().map(|minifnarg| &mut minifnarg.0)
+        Type::ImplTrait(tyit) => {
+            add_lifetime_to_impl_trait(tyit);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Give the parameter with the given name the lifetime `'a`, so that the
+/// return value can borrow from it alone, as `returns_lifetime_of_arg!`
+/// requests. Returns false if it can't carry a lifetime.
+pub(crate) fn add_lifetime_to_param(
+    params: &mut Punctuated<FnArg, Comma>,
+    name: &syn::Pat,
+) -> bool {
+    params.iter_mut().any(|param| match &mut param.0 {
+        syn::FnArg::Typed(PatType { pat, ty, .. }) if pat.as_ref() == name => {
+            add_lifetime_to_param_type(ty)
+        }
+        _ => false,
+    })
+}
+
+/// Give the receiver among these parameters the lifetime `'a`, so that
+/// the return value can borrow from it. Returns false if there's no
+/// receiver which can carry a lifetime, e.g. because it's a `CppRef`.
+pub(crate) fn add_lifetime_to_receiver(params: &mut Punctuated<FnArg, Comma>) -> bool {
+    params.iter_mut().any(|param| {
+        let ty = match &mut param.0 {
+            syn::FnArg::Receiver(syn::Receiver { ty, .. }) => ty,
+            syn::FnArg::Typed(PatType { pat, ty, .. })
+                if matches!(pat.as_ref(), syn::Pat::Ident(pi) if pi.ident == "self") =>
             {
-                match ty.as_mut() {
-                    Type::Path(TypePath {
-                        path: Path { segments, .. },
-                        ..
-                    }) => add_lifetime_to_pinned_reference(segments).unwrap_or(()),
-                    Type::Reference(tyr) => add_lifetime_to_reference(tyr),
-                    Type::ImplTrait(tyit) => add_lifetime_to_impl_trait(tyit),
-                    _ => {}
-                }
+                ty
             }
-
-            (Some(quote! { <'a> }), params, Cow::Owned(new_return_type))
+            _ => return false,
+        };
+        match ty.as_mut() {
+            Type::Reference(tyr) => {
+                add_lifetime_to_reference(tyr);
+                true
+            }
+            Type::Path(TypePath {
+                path: Path { segments, .. },
+                ..
+            }) => add_lifetime_to_pinned_reference(segments).is_ok(),
+            _ => false,
         }
-    }
+    })
 }
 
 fn reference_parameter_is_non_pod_reference(
@@ -0,0 +1,163 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::member_fn_fields::MemberFnFieldShims,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Generate the Rust side of each `member_fn_field!`: the opaque storage
+/// type, and methods on the struct which read, set, clear and call the
+/// field.
+pub(super) fn generate_member_fn_fields(
+    member_fn_fields: &[MemberFnFieldShims],
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    member_fn_fields
+        .iter()
+        .map(|shims| {
+            let storage = make_ident(&shims.storage);
+            let get = make_ident(&shims.get);
+            let set = make_ident(&shims.set);
+            let clear = make_ident(&shims.clear);
+            let has = make_ident(&shims.has);
+            let call = make_ident(&shims.call);
+            let table_id = shims.table.get_final_ident();
+            let class_id = shims.class.get_final_ident();
+            let class_ty = shims.class.to_type_path();
+            let alias = shims.storage_alias.get_final_ident();
+            let field = &shims.field.field;
+            let field_id = make_ident(field);
+            let set_id = make_ident(format!("set_{field}"));
+            let clear_id = make_ident(format!("clear_{field}"));
+            let has_id = make_ident(format!("has_{field}"));
+            let call_id = make_ident(format!("call_{field}_on"));
+            let (bridge_obj, obj) = if shims.field.is_const {
+                (quote! { &#class_id }, quote! { &#class_ty })
+            } else {
+                (
+                    quote! { Pin<&mut #class_id> },
+                    quote! { ::core::pin::Pin<&mut #class_ty> },
+                )
+            };
+            let params = &shims.field.params;
+            let param_names: Vec<_> = (0..params.len())
+                .map(|n| make_ident(format!("arg{n}")))
+                .collect();
+            let result = shims.field.result.iter();
+            let result = quote! { #(-> #result)* };
+            let targets: Vec<_> = shims
+                .targets
+                .iter()
+                .map(|(target, make)| (make_ident(target), make_ident(make)))
+                .collect();
+            let mut extern_c_mod_items: Vec<ForeignItem> = vec![
+                parse_quote! {
+                    type #storage;
+                },
+                parse_quote! {
+                    fn #get(table: &#table_id) -> UniquePtr<#storage>;
+                },
+                parse_quote! {
+                    fn #set(table: Pin<&mut #table_id>, storage: &#storage);
+                },
+                parse_quote! {
+                    fn #clear(table: Pin<&mut #table_id>);
+                },
+                parse_quote! {
+                    fn #has(table: &#table_id) -> bool;
+                },
+                parse_quote! {
+                    fn #call(table: &#table_id, obj: #bridge_obj, #(#param_names: #params),*) #result;
+                },
+            ];
+            extern_c_mod_items.extend(targets.iter().map(|(_, make)| -> ForeignItem {
+                parse_quote! {
+                    fn #make() -> UniquePtr<#storage>;
+                }
+            }));
+            let alias_doc = format!(
+                "A copy of a `{}::{field}` member function pointer, which can be stored back into any `{table_id}` with [`{table_id}::{set_id}`].",
+                shims.field.table
+            );
+            let get_doc = format!("A copy of the `{field}` member function pointer.");
+            let set_doc = format!("Sets `{field}` to a copy of a member function pointer.");
+            let clear_doc = format!("Sets `{field}` to null.");
+            let has_doc = format!("Whether `{field}` is non-null.");
+            let call_doc = format!(
+                "Calls the member function which `{field}` points to on `obj`. Panics if it's null."
+            );
+            let panic_msg = format!("{}::{field} is null", shims.field.table);
+            let mut output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[doc = #alias_doc]
+                    #[allow(non_camel_case_types)]
+                    pub type #alias = cxxbridge::#storage;
+                },
+                parse_quote! {
+                    impl #table_id {
+                        #[doc = #get_doc]
+                        pub fn #field_id(&self) -> cxx::UniquePtr<#alias> {
+                            cxxbridge::#get(self)
+                        }
+
+                        #[doc = #set_doc]
+                        pub fn #set_id(self: ::core::pin::Pin<&mut Self>, ptr: &#alias) {
+                            cxxbridge::#set(self, ptr)
+                        }
+
+                        #[doc = #clear_doc]
+                        pub fn #clear_id(self: ::core::pin::Pin<&mut Self>) {
+                            cxxbridge::#clear(self)
+                        }
+
+                        #[doc = #has_doc]
+                        pub fn #has_id(&self) -> bool {
+                            cxxbridge::#has(self)
+                        }
+
+                        #[doc = #call_doc]
+                        pub fn #call_id(&self, obj: #obj, #(#param_names: #params),*) #result {
+                            assert!(self.#has_id(), #panic_msg);
+                            cxxbridge::#call(self, obj, #(#param_names),*)
+                        }
+                    }
+                },
+            ];
+            if !targets.is_empty() {
+                let class = &shims.field.class;
+                let (target_ids, makes): (Vec<_>, Vec<_>) = targets.into_iter().unzip();
+                let target_docs = target_ids.iter().map(|target| {
+                    format!("A pointer to `{class}::{target}`, which can be stored in `{field}`.")
+                });
+                output_mod_items.push(parse_quote! {
+                    impl #alias {
+                        #(
+                            #[doc = #target_docs]
+                            pub fn #target_ids() -> cxx::UniquePtr<Self> {
+                                cxxbridge::#makes()
+                            }
+                        )*
+                    }
+                });
+            }
+            (
+                shims.storage_alias.clone(),
+                RsCodegenResult {
+                    extern_c_mod_items,
+                    output_mod_items,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
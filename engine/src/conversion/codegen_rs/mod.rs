@@ -6,19 +6,45 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod batch;
+mod completion_future;
+mod context_callback;
+mod extern_rust_closure;
 mod fun_codegen;
 mod function_wrapper_rs;
 mod impl_item_creator;
+mod init_function;
+mod interface_trait;
+mod intrusive_ptr;
 mod lifetime;
+mod member_fn_field;
+mod name_table;
 mod namespace_organizer;
+mod newtype;
 mod non_pod_struct;
+mod ostream_writer;
+mod outlives;
+mod pod_vector;
+mod property_accessors;
+mod protobuf_message;
+mod rust_equivalent;
+mod scope_guard;
+mod shared_struct;
+mod string_value;
+mod tagged_union;
+mod thread_affinity;
+mod thread_handle;
 pub(crate) mod unqualify;
+mod upcast;
 mod utils;
+mod vector_slice;
 
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
-use autocxx_parser::{ExternCppType, IncludeCppConfig, RustFun, UnsafePolicy};
+use autocxx_parser::{
+    ExternCppType, IncludeCppConfig, NonVirtualDestructor, RustFun, UnsafePolicy,
+};
 
 use itertools::Itertools;
 use proc_macro2::{Span, TokenStream};
@@ -31,9 +57,32 @@ use utils::{find_output_mod_root, generate_cxx_use_stmt};
 use crate::{
     conversion::codegen_rs::unqualify::{unqualify_params, unqualify_ret_type},
     minisyn::minisynize_punctuated,
+    msvc_declarations::MsvcDeclarations,
+    source_declarations::is_cpp_source,
     types::{make_ident, Namespace, QualifiedName},
 };
+use batch::generate_batches;
+use completion_future::generate_completion_futures;
+use context_callback::generate_context_callbacks;
+use extern_rust_closure::generate_extern_rust_closure_fn;
 use impl_item_creator::create_impl_items;
+use init_function::generate_init_function;
+use interface_trait::generate_interface_traits;
+use intrusive_ptr::generate_intrusive_ptr_types;
+use member_fn_field::generate_member_fn_fields;
+use name_table::generate_name_table;
+use ostream_writer::generate_ostream_writer;
+use outlives::generate_outlives;
+use pod_vector::generate_pod_vector_elements;
+use property_accessors::generate_property_accessors;
+use protobuf_message::generate_protobuf_messages;
+use rust_equivalent::generate_rust_equivalents;
+use scope_guard::generate_scope_guards;
+use string_value::generate_string_value_shims;
+use thread_affinity::generate_thread_affinity_static;
+use thread_handle::generate_thread_handles;
+use upcast::generate_upcasts;
+use vector_slice::generate_vector_slices;
 
 use self::{
     fun_codegen::gen_function,
@@ -42,17 +91,21 @@ use self::{
 
 use super::{
     analysis::{
-        fun::{FnPhase, PodAndDepAnalysis, ReceiverMutability},
+        fun::{FnKind, FnPhase, PodAndDepAnalysis, ReceiverMutability},
         pod::PodAnalysis,
+        tdef::TypedefAnalysis,
     },
-    api::{AnalysisPhase, Api, SubclassName, TypeKind},
+    api::{AnalysisPhase, Api, SubclassName, TypeKind, TypedefKind},
     convert_error::ErrorContextType,
     doc_attr::get_doc_attrs,
 };
 use super::{
     api::{Provenance, RustSubclassFnDetails, SuperclassMethod, TraitImplSignature},
     apivec::ApiVec,
-    codegen_cpp::type_to_cpp::CppNameMap,
+    codegen_cpp::{nested_forward_declaration_typedef_name, type_to_cpp::CppNameMap},
+    container_accessors::{find_container_accessor, ContainerAccessor, ContainerLookup},
+    extern_rust_closures::has_boxed_fns,
+    extra_shims::ExtraShims,
 };
 use super::{convert_error::ErrorContext, ConvertErrorFromCpp};
 use quote::quote;
@@ -126,7 +179,9 @@ pub(crate) struct RsCodeGenerator<'a> {
     bindgen_mod: ItemMod,
     original_name_map: CppNameMap,
     config: &'a IncludeCppConfig,
+    extra_shims: &'a ExtraShims<'a>,
     header_name: Option<String>,
+    msvc_declarations: &'a MsvcDeclarations,
 }
 
 impl<'a> RsCodeGenerator<'a> {
@@ -137,7 +192,9 @@ impl<'a> RsCodeGenerator<'a> {
         include_list: &'a [String],
         bindgen_mod: ItemMod,
         config: &'a IncludeCppConfig,
+        extra_shims: &'a ExtraShims<'a>,
         header_name: Option<String>,
+        msvc_declarations: &'a MsvcDeclarations,
     ) -> Vec<Item> {
         let c = Self {
             unsafe_policy,
@@ -145,7 +202,9 @@ impl<'a> RsCodeGenerator<'a> {
             bindgen_mod,
             original_name_map: CppNameMap::new_from_apis(&all_apis),
             config,
+            extra_shims,
             header_name,
+            msvc_declarations,
         };
         c.rs_codegen(all_apis)
     }
@@ -158,6 +217,30 @@ impl<'a> RsCodeGenerator<'a> {
         let subclasses_with_a_single_trivial_constructor =
             find_trivially_constructed_subclasses(&all_apis);
         let non_pod_types = find_non_pod_types(&all_apis);
+        let renamed_items_report = Self::generate_renamed_items_report(&all_apis);
+        let pod_downgrades_report = Self::generate_pod_downgrades_report(&all_apis);
+        let name_table = generate_name_table(&all_apis, self.config);
+        let interface_traits = generate_interface_traits(&all_apis, self.config);
+        let property_accessors = generate_property_accessors(&all_apis, self.config);
+        let completion_futures = generate_completion_futures(self.config, self.unsafe_policy);
+        let context_callbacks = generate_context_callbacks(self.config, self.unsafe_policy);
+        let init_function = generate_init_function(self.config);
+        let ostream_writer = generate_ostream_writer(self.config);
+        let string_value_shims = generate_string_value_shims(self.config);
+        let upcasts = generate_upcasts(&self.extra_shims.upcasts);
+        let pod_vector_elements =
+            generate_pod_vector_elements(&self.extra_shims.pod_vector_elements);
+        let intrusive_ptr_types =
+            generate_intrusive_ptr_types(&self.extra_shims.intrusive_ptr_types);
+        let protobuf_messages =
+            generate_protobuf_messages(&all_apis, &self.extra_shims.protobuf_messages);
+        let batches = generate_batches(&self.extra_shims.batches);
+        let member_fn_fields = generate_member_fn_fields(&self.extra_shims.member_fn_fields);
+        let thread_handles = generate_thread_handles(&all_apis, self.config);
+        let rust_equivalents = generate_rust_equivalents(&all_apis, self.config);
+        let outlives = generate_outlives(&all_apis, self.config);
+        let scope_guards = generate_scope_guards(&all_apis, self.config);
+        let vector_slices = generate_vector_slices(&self.extra_shims.vector_slice_methods);
         // Now let's generate the Rust code.
         let (rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) = all_apis
             .into_iter()
@@ -173,6 +256,27 @@ impl<'a> RsCodeGenerator<'a> {
                 ((name, gen), more_cpp_needed)
             })
             .unzip();
+        let rs_codegen_results_and_namespaces: Vec<_> = rs_codegen_results_and_namespaces
+            .into_iter()
+            .chain(interface_traits)
+            .chain(property_accessors)
+            .chain(completion_futures)
+            .chain(context_callbacks)
+            .chain(init_function)
+            .chain(ostream_writer)
+            .chain(string_value_shims)
+            .chain(upcasts)
+            .chain(pod_vector_elements)
+            .chain(intrusive_ptr_types)
+            .chain(protobuf_messages)
+            .chain(batches)
+            .chain(member_fn_fields)
+            .chain(thread_handles)
+            .chain(rust_equivalents)
+            .chain(outlives)
+            .chain(scope_guards)
+            .chain(vector_slices)
+            .collect();
         // First, the hierarchy of mods containing lots of 'use' statements
         // and other items which are the final API exposed as 'ffi'.
         let mut output_mod_items =
@@ -207,7 +311,19 @@ impl<'a> RsCodeGenerator<'a> {
         // And a list of global items to include at the top level.
         let mut all_items: Vec<Item> = all_items.into_iter().flatten().collect();
         // And finally any C++ we need to generate. And by "we" I mean autocxx not cxx.
-        let has_additional_cpp_needs = additional_cpp_needs.into_iter().any(std::convert::identity);
+        let has_additional_cpp_needs = additional_cpp_needs.into_iter().any(std::convert::identity)
+            || self.config.get_complete_types().next().is_some()
+            || self.config.get_completion_futures().next().is_some()
+            || self.config.get_context_callbacks().next().is_some()
+            || self.config.get_init_function().is_some()
+            || self.config.ostream_as_writer()
+            || self.config.strings_by_value()
+            || !self.extra_shims.is_empty()
+            || self
+                .config
+                .inclusions
+                .iter()
+                .any(|path| is_cpp_source(path));
         extern_c_mod_items.extend(self.build_include_foreign_items(has_additional_cpp_needs));
         // We will always create an extern "C" mod even if bindgen
         // didn't generate one, e.g. because it only generated types.
@@ -249,9 +365,91 @@ impl<'a> RsCodeGenerator<'a> {
             use super::#ffi_mod_name as output;
         }));
         all_items.append(&mut output_mod_items);
+        all_items.extend(renamed_items_report);
+        all_items.extend(pod_downgrades_report);
+        all_items.extend(name_table);
         all_items
     }
 
+    /// List every function and method whose Rust name isn't the one it
+    /// would naturally have, because of overloads, name collisions or
+    /// `overload_names!` directives.
+    fn generate_renamed_items_report(apis: &ApiVec<FnPhase>) -> Option<Item> {
+        let renames: Vec<_> = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Function { name, analysis, .. }
+                    if analysis.ignore_reason.is_ok() && analysis.externally_callable =>
+                {
+                    let cpp_name = analysis.renamed_from.as_ref()?;
+                    let rust_name = match &analysis.kind {
+                        FnKind::Function => QualifiedName::new(
+                            name.name.get_namespace(),
+                            make_ident(&analysis.rust_name),
+                        )
+                        .to_cpp_name(),
+                        FnKind::Method { impl_for, .. } => {
+                            format!("{}::{}", impl_for.to_cpp_name(), analysis.rust_name)
+                        }
+                        FnKind::TraitMethod { .. } => return None,
+                    };
+                    log::info!("Renamed C++ {} to Rust {}", cpp_name, rust_name);
+                    Some((cpp_name.clone(), rust_name))
+                }
+                _ => None,
+            })
+            .collect();
+        if renames.is_empty() {
+            return None;
+        }
+        let (cpp_names, rust_names): (Vec<_>, Vec<_>) = renames.into_iter().unzip();
+        Some(parse_quote! {
+            /// Every C++ function or method which autocxx has given a Rust
+            /// name other than its C++ name, because of overloading, a name
+            /// collision, or an `overload_names!` directive. Each entry is
+            /// `(C++ name, Rust name)`.
+            pub const AUTOCXX_RENAMED_ITEMS: &[(&str, &str)] = &[
+                #((#cpp_names, #rust_names)),*
+            ];
+        })
+    }
+
+    /// List every type requested by `generate_pod!` which has instead been
+    /// generated as non-POD, as `allow_pod_downgrade!` permits.
+    fn generate_pod_downgrades_report(apis: &ApiVec<FnPhase>) -> Option<Item> {
+        let (cpp_names, reasons): (Vec<_>, Vec<_>) = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Struct {
+                    name,
+                    analysis:
+                        PodAndDepAnalysis {
+                            pod:
+                                PodAnalysis {
+                                    pod_downgrade_reason: Some(reason),
+                                    ..
+                                },
+                            ..
+                        },
+                    ..
+                } => Some((name.name.to_cpp_name(), reason.clone())),
+                _ => None,
+            })
+            .unzip();
+        if cpp_names.is_empty() {
+            return None;
+        }
+        Some(parse_quote! {
+            /// Every type which a `generate_pod!` directive asked to be POD,
+            /// but which couldn't be and so has been generated as non-POD,
+            /// as allowed by `allow_pod_downgrade!`. Each entry is
+            /// `(C++ name, reason)`.
+            pub const AUTOCXX_POD_DOWNGRADES: &[(&str, &str)] = &[
+                #((#cpp_names, #reasons)),*
+            ];
+        })
+    }
+
     fn accumulate_superclass_methods(
         &self,
         apis: &ApiVec<FnPhase>,
@@ -326,10 +524,17 @@ impl<'a> RsCodeGenerator<'a> {
                     .push(&impl_entry.item);
             }
             if let Some(trait_impl_entry) = &item.1.trait_impl_entry {
-                trait_impl_entries_by_trait_and_ty
+                let entries = trait_impl_entries_by_trait_and_ty
                     .entry(trait_impl_entry.key.clone())
-                    .or_default()
-                    .push(&trait_impl_entry.item);
+                    .or_default();
+                // Overloaded constructors may take the same Rust types, and
+                // so implement the same `CppNew`. Only the first can.
+                if !entries
+                    .iter()
+                    .any(|existing| is_same_trait_fn(existing, &trait_impl_entry.item))
+                {
+                    entries.push(&trait_impl_entry.item);
+                }
             }
         }
         for (ty, entries) in impl_entries_by_type.into_iter() {
@@ -400,7 +605,27 @@ impl<'a> RsCodeGenerator<'a> {
                 }
             }
             Api::Function { fun, analysis, .. } => {
-                gen_function(&name, *fun, analysis, non_pod_types)
+                gen_function(&name, *fun, analysis, non_pod_types, self.config)
+            }
+            Api::Typedef {
+                analysis:
+                    TypedefAnalysis {
+                        kind: TypedefKind::Type(item),
+                        ..
+                    },
+                ..
+            } if self.config.is_newtype(&name.to_cpp_name()) => {
+                let mut output_mod_items = newtype::generate_newtype(
+                    &name,
+                    &item.ty,
+                    self.config.is_opaque_handle(&name.to_cpp_name()),
+                );
+                output_mod_items.append(&mut self.generate_extern_type_impl(TypeKind::Pod, &name));
+                RsCodegenResult {
+                    extern_c_mod_items: vec![self.generate_cxxbridge_type_alias(&name)],
+                    output_mod_items,
+                    ..Default::default()
+                }
             }
             Api::Const { .. } | Api::Typedef { .. } => RsCodegenResult {
                 output_mod_items: vec![Self::generate_bindgen_use_stmt(&name)],
@@ -420,20 +645,67 @@ impl<'a> RsCodeGenerator<'a> {
                 ..
             } => {
                 let doc_attrs = get_doc_attrs(&details.item.attrs);
-                self.generate_type(
+                let is_pod = matches!(kind, TypeKind::Pod) && num_generics == 0;
+                let field_offsets = if is_pod {
+                    Self::generate_field_offsets(&id, &details.item)
+                } else {
+                    None
+                };
+                if is_pod && self.config.is_shared_struct(&name.to_cpp_name()) {
+                    let (bridge_item, output_mod_items) = shared_struct::generate_shared_struct(
+                        &name,
+                        &details.item,
+                        self.generate_cxxbridge_type_attrs(&name),
+                        &doc_attrs,
+                    );
+                    let mut bridge_items = create_impl_items(
+                        &id,
+                        constructors.move_constructor,
+                        constructors.destructor,
+                        self.config,
+                    );
+                    bridge_items.push(bridge_item);
+                    return RsCodegenResult {
+                        bridge_items,
+                        output_mod_items,
+                        impl_entry: field_offsets,
+                        ..Default::default()
+                    };
+                }
+                // Singletons are only ever reached through a reference to
+                // the one instance, so aren't owned by any smart pointer.
+                // Nor are no_drop! types, since a smart pointer would
+                // delete them from the C++ side.
+                let is_singleton = self.config.is_singleton(&name.to_cpp_name());
+                let is_no_drop = self
+                    .config
+                    .get_drop_behaviour(&name.to_cpp_name())
+                    .is_some();
+                let mut result = self.generate_type(
                     &name,
                     id,
                     kind,
-                    constructors.move_constructor,
-                    constructors.destructor,
+                    constructors.move_constructor && !is_singleton,
+                    constructors.destructor && !is_singleton && !is_no_drop,
                     || Some((Item::Struct(details.item.into()), doc_attrs)),
                     associated_methods,
                     num_generics,
-                )
+                );
+                result.impl_entry = field_offsets;
+                if let Some(tagged_union) = self.config.get_tagged_union(&name.to_cpp_name()) {
+                    result
+                        .output_mod_items
+                        .extend(tagged_union::generate_tagged_union_view(
+                            &name,
+                            tagged_union,
+                        ));
+                }
+                result
             }
             Api::Enum { item, .. } => {
                 let doc_attrs = get_doc_attrs(&item.attrs);
-                self.generate_type(
+                let enum_helpers = Self::generate_enum_helpers(&id, &item);
+                let mut result = self.generate_type(
                     &name,
                     id,
                     TypeKind::Pod,
@@ -442,24 +714,66 @@ impl<'a> RsCodeGenerator<'a> {
                     || Some((Item::Enum(item.into()), doc_attrs)),
                     associated_methods,
                     0,
-                )
+                );
+                result.impl_entry = Some(enum_helpers);
+                result
+            }
+            Api::ConcreteType {
+                ref rs_definition,
+                ref cpp_definition,
+                ..
+            } => {
+                if let Some(rust_type) = self.config.get_extern_instantiation(cpp_definition) {
+                    return self.generate_extern_instantiation(&name, &id, rust_type);
+                }
+                let accessor = rs_definition.as_ref().and_then(|rs_definition| {
+                    find_container_accessor(&name, cpp_definition, rs_definition, self.config)
+                });
+                let mut result = self.generate_type(
+                    &name,
+                    id.clone(),
+                    TypeKind::Abstract,
+                    false, // assume for now that these types can't be kept in a Vector
+                    true,  // assume for now that these types can be put in a smart pointer
+                    || None,
+                    associated_methods,
+                    0,
+                );
+                if let Some(accessor) = accessor {
+                    let (bridge_fn, output_impl) = Self::generate_container_accessor(&id, accessor);
+                    result.extern_c_mod_items.push(bridge_fn);
+                    result.output_mod_items.push(output_impl);
+                }
+                result
+            }
+            Api::ForwardDeclaration { name: api_name, .. } if api_name.cpp_name().is_nested() => {
+                // cxx can't be told that the outer type is a class, so we
+                // point it at the typedef generated by codegen_cpp instead.
+                let mut result = self.generate_type(
+                    &name,
+                    id.clone(),
+                    TypeKind::Abstract,
+                    false,
+                    self.config.get_complete_type(&name.to_cpp_name()).is_some(),
+                    || None,
+                    associated_methods,
+                    0,
+                );
+                let cxx_name = nested_forward_declaration_typedef_name(&name);
+                result.extern_c_mod_items = vec![ForeignItem::Verbatim(quote! {
+                    #[cxx_name = #cxx_name]
+                    type #id;
+                })];
+                result
             }
-            Api::ConcreteType { .. } => self.generate_type(
-                &name,
-                id,
-                TypeKind::Abstract,
-                false, // assume for now that these types can't be kept in a Vector
-                true,  // assume for now that these types can be put in a smart pointer
-                || None,
-                associated_methods,
-                0,
-            ),
             Api::ForwardDeclaration { .. } | Api::OpaqueTypedef { .. } => self.generate_type(
                 &name,
                 id,
                 TypeKind::Abstract,
                 false, // these types can't be kept in a Vector
-                false, // these types can't be put in a smart pointer
+                // these types can't be put in a smart pointer, unless
+                // the user has promised they're complete
+                self.config.get_complete_type(&name.to_cpp_name()).is_some(),
                 || None,
                 associated_methods,
                 0,
@@ -494,6 +808,9 @@ impl<'a> RsCodeGenerator<'a> {
             } => {
                 sig.inputs = unqualify_params(sig.inputs);
                 sig.output = unqualify_ret_type(sig.output);
+                if has_boxed_fns(&sig) {
+                    return generate_extern_rust_closure_fn(&path, &sig, self.config);
+                }
                 RsCodegenResult {
                     global_items: if !has_receiver {
                         vec![parse_quote! {
@@ -552,6 +869,10 @@ impl<'a> RsCodeGenerator<'a> {
         let cpp_id = full_cpp.get_final_ident();
         let mut global_items = Vec::new();
         let relinquish_ownership_call = sub.cpp_remove_ownership();
+        let references_wrapped = matches!(
+            self.unsafe_policy,
+            UnsafePolicy::ReferencesWrappedAllFunctionsSafe
+        );
         let mut output_mod_items: Vec<Item> = vec![
             parse_quote! {
                 pub use cxxbridge::#cpp_id;
@@ -589,13 +910,25 @@ impl<'a> RsCodeGenerator<'a> {
                         ReceiverMutability::Mutable => ("peer_mut", parse_quote!(&mut self)),
                     };
                     let peer_fn = make_ident(peer_fn);
+                    // Under the wrapped references policy, the super method
+                    // takes a CppRef or CppMutRef receiver, so convert the peer
+                    // reference rather than calling it directly.
+                    let peer = match (references_wrapped, m.receiver_mutability) {
+                        (false, _) => quote! { self.#peer_fn() },
+                        (true, ReceiverMutability::Const) => {
+                            quote! { autocxx::CppRef::from_ptr(self.#peer_fn()) }
+                        }
+                        (true, ReceiverMutability::Mutable) => {
+                            quote! { autocxx::CppMutRef::from(self.#peer_fn()) }
+                        }
+                    };
                     *(params.iter_mut().next().unwrap()) = first_param;
                     let param_names = m.param_names.iter().skip(1);
                     let unsafe_token = m.requires_unsafe.wrapper_token();
                     parse_quote! {
                         #unsafe_token fn #cpp_super_method_name(#params) #ret {
                             use autocxx::subclass::CppSubclass;
-                            self.#peer_fn().#cpp_super_method_name(#(#param_names),*)
+                            #peer.#cpp_super_method_name(#(#param_names),*)
                         }
                     }
                 })
@@ -629,10 +962,92 @@ impl<'a> RsCodeGenerator<'a> {
         extern_c_mod_items.push(parse_quote! {
             fn #as_mut_id(self: Pin<&mut #cpp_id>) -> Pin<&mut #super_cxxxbridge_id>;
         });
-        let as_unique_ptr_id = make_ident(format!("{cpp_id}_As_{super_name}_UniquePtr"));
-        extern_c_mod_items.push(parse_quote! {
-            fn #as_unique_ptr_id(u: UniquePtr<#cpp_id>) -> UniquePtr<#super_cxxxbridge_id>;
-        });
+        let non_virtual_destructor = self
+            .config
+            .get_non_virtual_destructor_policy(&id.to_string());
+        if non_virtual_destructor != NonVirtualDestructor::Leak {
+            let as_unique_ptr_id = make_ident(format!("{cpp_id}_As_{super_name}_UniquePtr"));
+            extern_c_mod_items.push(parse_quote! {
+                fn #as_unique_ptr_id(u: UniquePtr<#cpp_id>) -> UniquePtr<#super_cxxxbridge_id>;
+            });
+            let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
+            if non_virtual_destructor == NonVirtualDestructor::Downcast {
+                let rs_delete_id = make_ident(format!("delete_{super_name}_unique_ptr"));
+                let doc = format!(
+                    "Convert to a `UniquePtr` to the superclass. The superclass \
+                    doesn't have a virtual destructor, so the result must be \
+                    destroyed using [`Self::{rs_delete_id}`]."
+                );
+                output_mod_items.push(parse_quote! {
+                    impl super::#id {
+                        #[doc = #doc]
+                        ///
+                        /// # Safety
+                        ///
+                        /// The returned `UniquePtr` must not be dropped, nor
+                        /// passed to C++ which might delete it, since either
+                        /// would be undefined behavior.
+                        pub unsafe fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
+                            cxxbridge::#as_unique_ptr_id(u)
+                        }
+                    }
+                });
+            } else {
+                output_mod_items.push(parse_quote! {
+                    impl super::#id {
+                        pub fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
+                            cxxbridge::#as_unique_ptr_id(u)
+                        }
+                    }
+                });
+            }
+        }
+        match non_virtual_destructor {
+            NonVirtualDestructor::Error => {}
+            NonVirtualDestructor::Leak => {
+                let rs_leak_id = make_ident(format!("leak_as_{super_name}_ptr"));
+                output_mod_items.push(parse_quote! {
+                    impl super::#id {
+                        /// Give up ownership of the C++ peer, returning a pointer to the
+                        /// superclass. The peer is never destroyed.
+                        pub fn #rs_leak_id(u: cxx::UniquePtr<#cpp_id>) -> *mut cxxbridge::#super_cxxxbridge_id {
+                            let ptr = u.into_raw();
+                            if ptr.is_null() {
+                                ::core::ptr::null_mut()
+                            } else {
+                                unsafe {
+                                    let base = ::core::pin::Pin::new_unchecked(&mut *ptr).#as_mut_id();
+                                    ::core::pin::Pin::into_inner_unchecked(base)
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+            NonVirtualDestructor::Downcast => {
+                let delete_id = make_ident(format!("{cpp_id}_Delete_As_{super_name}"));
+                extern_c_mod_items.push(parse_quote! {
+                    fn #delete_id(u: UniquePtr<#super_cxxxbridge_id>);
+                });
+                let rs_delete_id = make_ident(format!("delete_{super_name}_unique_ptr"));
+                output_mod_items.push(parse_quote! {
+                    impl super::#id {
+                        /// Destroy a C++ peer which was converted to a `UniquePtr` to
+                        /// its superclass, as the subclass. Dropping the `UniquePtr`
+                        /// instead would be undefined behavior, since the superclass
+                        /// doesn't have a virtual destructor.
+                        ///
+                        /// # Safety
+                        ///
+                        /// `u` must be null or have come from the corresponding
+                        /// `as_..._unique_ptr` function of this type.
+                        pub unsafe fn #rs_delete_id(u: cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id>) {
+                            cxxbridge::#delete_id(u)
+                        }
+                    }
+                });
+            }
+        }
         output_mod_items.push(parse_quote! {
             impl AsRef<#super_path> for super::#id {
                 fn as_ref(&self) -> &cxxbridge::#super_cxxxbridge_id {
@@ -650,14 +1065,24 @@ impl<'a> RsCodeGenerator<'a> {
                 }
             }
         });
-        let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
-        output_mod_items.push(parse_quote! {
-            impl super::#id {
-                pub fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
-                    cxxbridge::#as_unique_ptr_id(u)
+        if references_wrapped {
+            // Superclass methods take CppRef/CppMutRef receivers, so make
+            // those easy to obtain too.
+            output_mod_items.push(parse_quote! {
+                impl autocxx::AsCppRef<#super_path> for super::#id {
+                    fn as_cpp_ref(&self) -> autocxx::CppRef<#super_path> {
+                        autocxx::CppRef::from_ptr(AsRef::<#super_path>::as_ref(self))
+                    }
                 }
-            }
-        });
+            });
+            output_mod_items.push(parse_quote! {
+                impl autocxx::AsCppMutRef<#super_path> for super::#id {
+                    fn as_cpp_mut_ref(&mut self) -> autocxx::CppMutRef<#super_path> {
+                        autocxx::CppMutRef::from(self.pin_mut())
+                    }
+                }
+            });
+        }
         let remove_ownership = sub.remove_ownership();
         global_items.push(parse_quote! {
             #[allow(non_snake_case)]
@@ -718,11 +1143,13 @@ impl<'a> RsCodeGenerator<'a> {
         let borrow = make_ident(borrow);
         let destroy_panic_msg = format!("Rust subclass API (method {} of subclass {} of superclass {}) called after subclass destroyed", method_name, subclass.0.name, superclass_id);
         let reentrancy_panic_msg = format!("Rust subclass API (method {} of subclass {} of superclass {}) called whilst subclass already borrowed - likely a re-entrant call",  method_name, subclass.0.name, superclass_id);
+        // `rc` keeps the Rust object alive until the method returns, even if
+        // C++ deletes its peer (and thus `me`) during the call. So `me` is a
+        // pointer, which we only dereference briefly in this first line.
         RsCodegenResult {
             global_items: vec![parse_quote! {
                 #global_def {
-                    let rc = me.0
-                        .get()
+                    let rc = unsafe { (*me).0.get() }
                         .expect(#destroy_panic_msg);
                     let #mut_token b = rc
                         .as_ref()
@@ -770,6 +1197,12 @@ impl<'a> RsCodeGenerator<'a> {
             &mut output_mod_items,
             associated_methods.get(name),
         );
+        let thread_affine = self.config.is_thread_affine(&name.to_cpp_name());
+        if thread_affine {
+            output_mod_items.push(generate_thread_affinity_static(name));
+        }
+        output_mod_items.extend(self.generate_deprecated_type_aliases(name));
+        output_mod_items.extend(self.generate_iid(name));
         let orig_item = item_creator();
         let doc_attrs = orig_item
             .as_ref()
@@ -792,7 +1225,12 @@ impl<'a> RsCodeGenerator<'a> {
                 //    A is a class rather than a namespace.
                 output_mod_items.push(match type_kind {
                     TypeKind::Pod => Self::generate_bindgen_use_stmt(name),
-                    _ => non_pod_struct::generate_opaque_type(name, num_generics, &doc_attrs),
+                    _ => non_pod_struct::generate_opaque_type(
+                        name,
+                        num_generics,
+                        &doc_attrs,
+                        thread_affine,
+                    ),
                 });
                 if num_generics > 0 {
                     // Still generate the type as emitted by bindgen,
@@ -834,6 +1272,173 @@ impl<'a> RsCodeGenerator<'a> {
         }
     }
 
+    /// For POD types, users may want to do zero-copy parsing or similar,
+    /// which depends on knowing where each field lives. We expose
+    /// the offset of each public field, as laid out by the `#[repr(C)]`
+    /// struct which bindgen generated for us. The generated C++
+    /// `static_assert`s that C++ `offsetof` agrees.
+    fn generate_field_offsets(
+        id: &crate::minisyn::Ident,
+        item: &syn::ItemStruct,
+    ) -> Option<Box<ImplBlockDetails>> {
+        let fields = match &item.fields {
+            syn::Fields::Named(fields) => fields,
+            _ => return None,
+        };
+        let offsets: Vec<_> = fields
+            .named
+            .iter()
+            .filter(|f| matches!(f.vis, syn::Visibility::Public(_)))
+            .filter_map(|f| f.ident.as_ref())
+            // Skip bindgen's own fields, e.g. for bitfield storage.
+            .filter(|field_id| !field_id.to_string().starts_with('_'))
+            .map(|field_id| {
+                let field_name = field_id.to_string();
+                quote! { (#field_name, ::core::mem::offset_of!(Self, #field_id)) }
+            })
+            .collect();
+        let doc = format!(
+            "The byte offset of each public field of [`{id}`], in declaration order.\n\n\
+            These are identical to the results of C++ `offsetof` for this type."
+        );
+        Some(Box::new(ImplBlockDetails {
+            item: ImplItem::Const(parse_quote! {
+                #[doc = #doc]
+                pub const FIELD_OFFSETS: &'static [(&'static str, usize)] = &[#(#offsets),*];
+            }),
+            ty: parse_quote! { #id },
+        }))
+    }
+
+    /// Declare the C++ function which looks up an element of a container,
+    /// and wrap it in a `try_get` method which returns `None` if there's no
+    /// such element.
+    fn generate_container_accessor(
+        id: &crate::minisyn::Ident,
+        accessor: ContainerAccessor,
+    ) -> (ForeignItem, Item) {
+        let wrapper_name = make_ident(&accessor.wrapper_name);
+        let element = &accessor.element;
+        let bridge_element = unqualify::unqualify_type(element.clone());
+        let (param, bridge_param, arg, what) = match &accessor.lookup {
+            ContainerLookup::Index => (
+                quote! { index: usize },
+                quote! { index: usize },
+                quote! { index },
+                "the element at `index`, or `None` if `index` is out of range",
+            ),
+            ContainerLookup::Key(key) => {
+                let bridge_key = unqualify::unqualify_type(key.clone());
+                (
+                    quote! { key: &#key },
+                    quote! { key: &#bridge_key },
+                    quote! { key },
+                    "the value for `key`, or `None` if there is no such key",
+                )
+            }
+        };
+        let doc = format!("Returns {what}. Unlike C++ `at` or `operator[]`, this never throws and is never undefined behavior.");
+        (
+            ForeignItem::Verbatim(quote! {
+                unsafe fn #wrapper_name(container: &#id, #bridge_param) -> *const #bridge_element;
+            }),
+            parse_quote! {
+                impl #id {
+                    #[doc = #doc]
+                    pub fn try_get(&self, #param) -> Option<autocxx::CppRef<#element>> {
+                        // Safety: the C++ returns either null or a pointer into
+                        // the container.
+                        let ptr = unsafe { cxxbridge::#wrapper_name(self, #arg) };
+                        (!ptr.is_null()).then(|| autocxx::CppRef::from_ptr(ptr))
+                    }
+                }
+            },
+        )
+    }
+
+    /// bindgen gives enums the same underlying integer type as they have
+    /// in C++, via `#[repr(...)]`. Generate helpers to convert to and from
+    /// that type without any lossy casting.
+    fn generate_enum_helpers(
+        id: &crate::minisyn::Ident,
+        item: &syn::ItemEnum,
+    ) -> Box<ImplBlockDetails> {
+        let variants: Vec<_> = item.variants.iter().map(|v| &v.ident).collect();
+        let variant_names: Vec<_> = variants.iter().map(|v| v.to_string()).collect();
+        // Enumerators share a namespace with associated items, so skip any
+        // helper which would be shadowed by an enumerator of the same name.
+        let unclashed = |helper: &str| !variant_names.iter().any(|name| name == helper);
+        let all_variants = unclashed("ALL_VARIANTS").then(|| {
+            quote! {
+                /// Every enumerator, in declaration order.
+                pub const ALL_VARIANTS: &'static [Self] = &[#(Self::#variants),*];
+            }
+        });
+        let name = unclashed("name").then(|| {
+            quote! {
+                /// The name of this enumerator, as spelled in Rust. That's the
+                /// C++ name unless it clashed with a Rust keyword.
+                pub const fn name(self) -> &'static str {
+                    match self {
+                        #(Self::#variants => #variant_names,)*
+                    }
+                }
+            }
+        });
+        let from_name = unclashed("from_name").then(|| {
+            quote! {
+                /// Finds the enumerator with the given name, as spelled in Rust.
+                pub fn from_name(name: &str) -> Option<Self> {
+                    match name {
+                        #(#variant_names => Some(Self::#variants),)*
+                        _ => None,
+                    }
+                }
+            }
+        });
+        let repr = item
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("repr"))
+            .find_map(|attr| attr.parse_args::<Ident>().ok());
+        let bits = repr.as_ref().filter(|_| unclashed("bits")).map(|repr| {
+            let doc = format!(
+                "Returns the underlying C++ value of this [`{id}`], which is of type `{repr}`."
+            );
+            quote! {
+                #[doc = #doc]
+                pub const fn bits(self) -> #repr {
+                    self as #repr
+                }
+            }
+        });
+        let from_bits = repr
+            .as_ref()
+            .filter(|_| unclashed("from_bits"))
+            .map(|repr| {
+                quote! {
+                    /// Creates this enum from its underlying C++ value, returning
+                    /// `None` if the value doesn't match any known enumerator.
+                    pub const fn from_bits(bits: #repr) -> Option<Self> {
+                        match bits {
+                            #(bits if bits == Self::#variants as #repr => Some(Self::#variants),)*
+                            _ => None,
+                        }
+                    }
+                }
+            });
+        Box::new(ImplBlockDetails {
+            item: ImplItem::Verbatim(quote! {
+                #bits
+                #from_bits
+                #all_variants
+                #name
+                #from_name
+            }),
+            ty: parse_quote! { #id },
+        })
+    }
+
     fn add_superclass_stuff_to_type(
         name: &QualifiedName,
         output_mod_items: &mut Vec<Item>,
@@ -917,6 +1522,26 @@ impl<'a> RsCodeGenerator<'a> {
         }
     }
 
+    /// An instantiation of a C++ template which `extern_cpp_type!` says
+    /// corresponds to a generic Rust type. The C++ typedef is generated as
+    /// for any other concrete type, but rather than have cxx make an opaque
+    /// type for it, we point cxx at the Rust type.
+    fn generate_extern_instantiation(
+        &self,
+        name: &QualifiedName,
+        id: &crate::minisyn::Ident,
+        rust_type: Type,
+    ) -> RsCodegenResult {
+        let mut output_mod_items = vec![parse_quote! { pub type #id = #rust_type; }];
+        output_mod_items.extend(self.generate_extern_type_impl(TypeKind::Abstract, name));
+        RsCodegenResult {
+            extern_c_mod_items: vec![self.generate_cxxbridge_type(name, true, Vec::new())],
+            bridge_items: create_impl_items(id, false, true, self.config),
+            output_mod_items,
+            ..Default::default()
+        }
+    }
+
     /// Generates something in the output mod that will carry a docstring
     /// explaining why a given type or function couldn't have bindings
     /// generated.
@@ -957,6 +1582,54 @@ impl<'a> RsCodeGenerator<'a> {
         })
     }
 
+    /// `deprecated_alias!` keeps a type available under an old name.
+    fn generate_deprecated_type_aliases(&self, name: &QualifiedName) -> Vec<Item> {
+        let id = name.get_final_ident();
+        let note = format!("renamed to `{id}`");
+        self.config
+            .get_deprecated_aliases(&name.to_cpp_name())
+            .map(|alias| {
+                let alias = make_ident(alias);
+                Item::Type(parse_quote! {
+                    #[deprecated(note = #note)]
+                    #[allow(non_camel_case_types)]
+                    pub type #alias = #id;
+                })
+            })
+            .collect()
+    }
+
+    /// A type declared with `__declspec(uuid)` gets an `IID` constant, if
+    /// `msvc_extensions!` found one.
+    fn generate_iid(&self, name: &QualifiedName) -> Option<Item> {
+        let uuid = self.msvc_declarations.uuid(&name.to_cpp_name())?;
+        let groups: Vec<&str> = uuid.split('-').collect();
+        let [data1, data2, data3, data4a, data4b] = groups[..] else {
+            return None;
+        };
+        let data1 = u32::from_str_radix(data1, 16).ok()?;
+        let data2 = u16::from_str_radix(data2, 16).ok()?;
+        let data3 = u16::from_str_radix(data3, 16).ok()?;
+        let data4 = format!("{data4a}{data4b}");
+        if data4.len() != 16 {
+            return None;
+        }
+        let data4 = (0..8)
+            .map(|i| u8::from_str_radix(&data4[i * 2..i * 2 + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let id = name.get_final_ident();
+        Some(Item::Impl(parse_quote! {
+            impl #id {
+                pub const IID: autocxx::Guid = autocxx::Guid {
+                    data1: #data1,
+                    data2: #data2,
+                    data3: #data3,
+                    data4: [#(#data4),*],
+                };
+            }
+        }))
+    }
+
     fn generate_extern_type_impl(&self, type_kind: TypeKind, tyname: &QualifiedName) -> Vec<Item> {
         let tynamestring = self.original_name_map.map(tyname);
         let ty_ident = tyname.get_final_ident();
@@ -981,6 +1654,50 @@ impl<'a> RsCodeGenerator<'a> {
     ) -> ForeignItem {
         let ns = name.get_namespace();
         let id = name.get_final_ident();
+        let mut for_extern_c_ts = self.generate_cxxbridge_type_attrs(name);
+
+        for_extern_c_ts.extend(quote! {
+            #(#doc_attrs)*
+        });
+
+        if references_bindgen {
+            for_extern_c_ts.extend(quote! {
+                type #id = super::
+            });
+            for_extern_c_ts.extend(ns.iter().map(make_ident).map(|id| {
+                quote! {
+                    #id::
+                }
+            }));
+            for_extern_c_ts.extend(quote! {
+                #id;
+            });
+        } else {
+            for_extern_c_ts.extend(quote! {
+                type #id;
+            });
+        }
+        ForeignItem::Verbatim(for_extern_c_ts)
+    }
+
+    /// Tell cxx about a type which we've defined ourselves in the output
+    /// mod, rather than one which bindgen made.
+    fn generate_cxxbridge_type_alias(&self, name: &QualifiedName) -> ForeignItem {
+        let mut for_extern_c_ts = self.generate_cxxbridge_type_attrs(name);
+        let segs = name
+            .get_namespace()
+            .iter()
+            .map(make_ident)
+            .chain(std::iter::once(name.get_final_ident()));
+        let id = name.get_final_ident();
+        for_extern_c_ts.extend(quote! {
+            type #id = super::#(#segs)::*;
+        });
+        ForeignItem::Verbatim(for_extern_c_ts)
+    }
+
+    fn generate_cxxbridge_type_attrs(&self, name: &QualifiedName) -> TokenStream {
+        let ns = name.get_namespace();
         // The following lines actually Tell A Lie.
         // If we have a nested class, B::C, within namespace A,
         // we actually have to tell cxx that we have nested class C
@@ -1007,29 +1724,7 @@ impl<'a> RsCodeGenerator<'a> {
                 #[cxx_name = #n]
             });
         }
-
-        for_extern_c_ts.extend(quote! {
-            #(#doc_attrs)*
-        });
-
-        if references_bindgen {
-            for_extern_c_ts.extend(quote! {
-                type #id = super::
-            });
-            for_extern_c_ts.extend(ns.iter().map(make_ident).map(|id| {
-                quote! {
-                    #id::
-                }
-            }));
-            for_extern_c_ts.extend(quote! {
-                #id;
-            });
-        } else {
-            for_extern_c_ts.extend(quote! {
-                type #id;
-            });
-        }
-        ForeignItem::Verbatim(for_extern_c_ts)
+        for_extern_c_ts
     }
 }
 
@@ -1056,6 +1751,10 @@ fn find_trivially_constructed_subclasses(apis: &ApiVec<FnPhase>) -> HashSet<Qual
         .collect()
 }
 
+fn is_same_trait_fn(a: &TraitItem, b: &TraitItem) -> bool {
+    matches!((a, b), (TraitItem::Fn(a), TraitItem::Fn(b)) if a.sig.ident == b.sig.ident)
+}
+
 fn find_non_pod_types(apis: &ApiVec<FnPhase>) -> HashSet<QualifiedName> {
     apis.iter()
         .filter_map(|api| match api {
@@ -0,0 +1,86 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use itertools::Itertools;
+use syn::{parse_quote, Item};
+
+use crate::conversion::{analysis::fun::FnPhase, api::Api, apivec::ApiVec};
+
+/// Generate the `meta` mod requested by `name_table!`, which lets code
+/// find the original C++ name of each generated type, and vice versa.
+pub(super) fn generate_name_table(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Option<Item> {
+    if !config.name_table() {
+        return None;
+    }
+    let mod_name = config.get_mod_name().to_string();
+    // Typedefs are omitted because in Rust they're the same type as their
+    // target, so we couldn't implement the trait for both.
+    let entries = apis.iter().filter_map(|api| {
+        let cpp_name = match api {
+            Api::Struct { name, .. } | Api::Enum { name, .. } => name.qualified_cpp_name(),
+            Api::ConcreteType { cpp_definition, .. } => cpp_definition.clone(),
+            _ => return None,
+        };
+        let name = api.name();
+        let rust_path = std::iter::once(mod_name.as_str())
+            .chain(name.segment_iter())
+            .join("::");
+        Some((name.to_type_path(), cpp_name, rust_path))
+    });
+    let (types, cpp_names, rust_paths): (Vec<_>, Vec<_>, Vec<_>) = entries.multiunzip();
+    Some(parse_quote! {
+        /// Runtime mapping between the original C++ names of the types in
+        /// this mod and their Rust paths, for use in logs and error messages.
+        pub mod meta {
+            #[allow(unused_imports)]
+            use super::output;
+
+            /// Every type in this mod, as `(C++ name, Rust path)`.
+            pub const NAMES: &[(&str, &str)] = &[
+                #((#cpp_names, #rust_paths)),*
+            ];
+
+            /// Implemented by each type in this mod to give its C++ name.
+            pub trait CppName {
+                /// The fully-qualified name of this type in C++.
+                const CPP_NAME: &'static str;
+            }
+
+            #(impl CppName for #types {
+                const CPP_NAME: &'static str = #cpp_names;
+            })*
+
+            /// Returns the fully-qualified C++ name of a type.
+            pub fn cpp_name_of<T: CppName>() -> &'static str {
+                T::CPP_NAME
+            }
+
+            /// Returns the Rust path of the type with the given C++ name,
+            /// if it's in this mod.
+            pub fn rust_path_of(cpp_name: &str) -> Option<&'static str> {
+                NAMES
+                    .iter()
+                    .find(|(candidate, _)| *candidate == cpp_name)
+                    .map(|(_, rust_path)| *rust_path)
+            }
+
+            /// Returns the C++ name of the type with the given Rust path,
+            /// if it's in this mod.
+            pub fn cpp_name_of_path(rust_path: &str) -> Option<&'static str> {
+                NAMES
+                    .iter()
+                    .find(|(_, candidate)| *candidate == rust_path)
+                    .map(|(cpp_name, _)| *cpp_name)
+            }
+        }
+    })
+}
@@ -0,0 +1,111 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, Item, Type};
+
+use crate::types::QualifiedName;
+
+/// Generate a `#[repr(transparent)]` newtype for a C++ typedef of an
+/// integer type, along with conversions to and from plain integers.
+/// `inner` is the Rust type to which the typedef refers, which analysis
+/// has already checked is an integer. If `opaque`, the integer isn't
+/// exposed except through explicit `from_raw` and `to_raw` calls.
+pub(super) fn generate_newtype(name: &QualifiedName, inner: &Type, opaque: bool) -> Vec<Item> {
+    if opaque {
+        return generate_opaque_handle(name, inner);
+    }
+    let id = name.get_final_ident();
+    // autocxx's own C integer types such as `autocxx::c_int` are themselves
+    // wrappers around the corresponding `std::os::raw` type; users will
+    // mostly want to convert to and from the latter.
+    let raw = match inner {
+        Type::Path(typ) if typ.path.segments.len() > 1 => typ
+            .path
+            .segments
+            .first()
+            .filter(|seg| seg.ident == "autocxx")
+            .and(typ.path.segments.last())
+            .map(|seg| {
+                let raw_id = &seg.ident;
+                quote! { ::std::os::raw::#raw_id }
+            }),
+        _ => None,
+    };
+    let doc = format!("Newtype for the C++ typedef `{}`.", name.to_cpp_name());
+    let mut items: Vec<Item> = vec![parse_quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        pub struct #id(pub #inner);
+    }];
+    items.push(parse_quote! {
+        impl From<#id> for #inner {
+            fn from(value: #id) -> Self {
+                value.0
+            }
+        }
+    });
+    let (primitive, construct) = match &raw {
+        Some(raw) => {
+            items.push(parse_quote! {
+                impl From<#id> for #raw {
+                    fn from(value: #id) -> Self {
+                        value.0.into()
+                    }
+                }
+            });
+            (raw.clone(), quote! { #inner(value) })
+        }
+        None => (quote! { #inner }, quote! { value }),
+    };
+    for source in [quote! { i64 }, quote! { u64 }] {
+        items.push(parse_quote! {
+            impl ::core::convert::TryFrom<#source> for #id {
+                type Error = <#primitive as ::core::convert::TryFrom<#source>>::Error;
+                fn try_from(value: #source) -> Result<Self, Self::Error> {
+                    <#primitive as ::core::convert::TryFrom<#source>>::try_from(value)
+                        .map(|value| Self(#construct))
+                }
+            }
+        });
+    }
+    items
+}
+
+fn generate_opaque_handle(name: &QualifiedName, inner: &Type) -> Vec<Item> {
+    let id = name.get_final_ident();
+    let doc = format!(
+        "Opaque handle for the C++ typedef `{}`. Values are normally only \
+        obtained from C++; this deliberately can't be mixed up with plain \
+        integers or with other kinds of handle.",
+        name.to_cpp_name()
+    );
+    vec![
+        parse_quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #[repr(transparent)]
+            pub struct #id(#inner);
+        },
+        parse_quote! {
+            impl #id {
+                /// Make a handle from its underlying value, for example one
+                /// which was previously obtained using `to_raw`.
+                pub fn from_raw(raw: #inner) -> Self {
+                    Self(raw)
+                }
+
+                /// The underlying value of this handle.
+                pub fn to_raw(self) -> #inner {
+                    self.0
+                }
+            }
+        },
+    ]
+}
@@ -24,7 +24,9 @@ use quote::quote;
 // Rustc can use least-significant bits of the reference for other storage.
 // (if we have layout information from bindgen we use that instead)
 // (2) We want to ensure the type is !Unpin
-// (3) We want to ensure it's not Send or Sync
+// (3) We want to ensure it's not Send or Sync. (UnsafeCell only ensures
+//     it's not Sync; thread_affine! types get a marker field so they're
+//     not Send either.)
 // In addition, we want to avoid UB:
 // (4) By marking the data as MaybeUninit we ensure there's no UB
 //     by Rust assuming it's initialized
@@ -71,6 +73,7 @@ pub(super) fn generate_opaque_type(
     name: &QualifiedName,
     num_generics: usize,
     doc_attrs: &[Attribute],
+    thread_affine: bool,
 ) -> Item {
     let segs = find_output_mod_root(name.get_namespace()).chain(name.get_bindgen_path_idents());
     let final_name = name.get_final_ident().0;
@@ -85,11 +88,17 @@ pub(super) fn generate_opaque_type(
             < #(#generics),* >
         }
     };
+    let thread_affinity_marker = thread_affine.then(|| {
+        quote! {
+            _thread_affine: ::core::marker::PhantomData<*const ()>,
+        }
+    });
     Item::Struct(parse_quote! {
         #[repr(transparent)]
         #(#doc_attrs)*
         pub struct #final_name #generics {
             _hidden_contents: ::core::cell::UnsafeCell<::core::mem::MaybeUninit<#(#segs)::* #generics>>,
+            #thread_affinity_marker
         }
     })
 }
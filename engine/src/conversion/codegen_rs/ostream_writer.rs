@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::ostream_writers::ostream_writer_names,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Generate the extern "Rust" functions through which the C++ stream
+/// buffer for `ostream_as_writer!` writes to, and flushes, a Rust writer.
+pub(super) fn generate_ostream_writer(
+    config: &IncludeCppConfig,
+) -> Option<(QualifiedName, RsCodegenResult)> {
+    if !config.ostream_as_writer() {
+        return None;
+    }
+    let names = ostream_writer_names(config);
+    let write_fn = make_ident(&names.write_fn);
+    let flush_fn = make_ident(&names.flush_fn);
+    let result = RsCodegenResult {
+        global_items: vec![
+            parse_quote! {
+                #[allow(non_snake_case)]
+                unsafe fn #write_fn(writer: usize, data: &[u8]) -> bool {
+                    unsafe { autocxx::OstreamWriter::write(writer, data) }
+                }
+            },
+            parse_quote! {
+                #[allow(non_snake_case)]
+                unsafe fn #flush_fn(writer: usize) -> bool {
+                    unsafe { autocxx::OstreamWriter::flush(writer) }
+                }
+            },
+        ],
+        extern_rust_mod_items: vec![
+            parse_quote! {
+                unsafe fn #write_fn(writer: usize, data: &[u8]) -> bool;
+            },
+            parse_quote! {
+                unsafe fn #flush_fn(writer: usize) -> bool;
+            },
+        ],
+        ..Default::default()
+    };
+    Some((QualifiedName::new_from_cpp_name(&names.write_fn), result))
+}
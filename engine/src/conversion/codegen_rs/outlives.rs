@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, Item};
+
+use crate::{
+    conversion::{analysis::fun::FnPhase, api::Api, apivec::ApiVec},
+    types::QualifiedName,
+};
+
+use super::RsCodegenResult;
+
+/// Implement `autocxx::DependsOn` for the first type in each `outlives!`
+/// directive, so that it can be owned by an `autocxx::Dependent` which
+/// keeps the second alive.
+pub(super) fn generate_outlives(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    let structs: Vec<&QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } => Some(&name.name),
+            _ => None,
+        })
+        .collect();
+    structs
+        .iter()
+        .filter_map(|dependent| {
+            let dependency_name = config.get_outlives_dependency(&dependent.to_cpp_name())?;
+            let dependency = structs
+                .iter()
+                .find(|name| name.to_cpp_name() == dependency_name)?;
+            let dependent_ty = dependent.to_type_path();
+            let dependency_ty = dependency.to_type_path();
+            let output_mod_items: Vec<Item> = vec![parse_quote! {
+                impl autocxx::DependsOn for #dependent_ty {
+                    type Dependency = #dependency_ty;
+                }
+            }];
+            Some((
+                (*dependent).clone(),
+                RsCodegenResult {
+                    output_mod_items,
+                    ..Default::default()
+                },
+            ))
+        })
+        .collect()
+}
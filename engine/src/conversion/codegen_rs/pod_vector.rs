@@ -0,0 +1,62 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::pod_vectors::PodVectorElement,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Implement `autocxx::PodVectorElement` for each POD type which can go
+/// in a `CxxVector`, which in turn gives `CxxVector<T>` the bulk copying
+/// methods of `autocxx::PodVectorExt`.
+pub(super) fn generate_pod_vector_elements(
+    elements: &[PodVectorElement],
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    elements
+        .iter()
+        .map(|element| {
+            let extend_shim = make_ident(&element.extend_shim);
+            let copy_shim = make_ident(&element.copy_shim);
+            let id = element.ty.get_final_ident();
+            let ty = element.ty.to_type_path();
+            let extern_c_mod_items: Vec<ForeignItem> = vec![
+                parse_quote! {
+                    unsafe fn #extend_shim(vec: Pin<&mut CxxVector<#id>>, data: *const #id, len: usize);
+                },
+                parse_quote! {
+                    unsafe fn #copy_shim(vec: &CxxVector<#id>, out: *mut #id);
+                },
+            ];
+            let output_mod_items: Vec<Item> = vec![parse_quote! {
+                unsafe impl autocxx::PodVectorElement for #ty {
+                    unsafe fn extend_vector(
+                        vec: ::core::pin::Pin<&mut cxx::CxxVector<Self>>,
+                        data: *const Self,
+                        len: usize,
+                    ) {
+                        cxxbridge::#extend_shim(vec, data, len)
+                    }
+
+                    unsafe fn copy_from_vector(vec: &cxx::CxxVector<Self>, out: *mut Self) {
+                        cxxbridge::#copy_shim(vec, out)
+                    }
+                }
+            }];
+            let result = RsCodegenResult {
+                extern_c_mod_items,
+                output_mod_items,
+                ..Default::default()
+            };
+            (element.ty.clone(), result)
+        })
+        .collect()
+}
@@ -0,0 +1,218 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{to_snake_case, IncludeCppConfig};
+use indexmap::map::IndexMap as HashMap;
+use syn::{parse_quote, FnArg, ImplItem, Item};
+
+use crate::{
+    conversion::{
+        analysis::fun::{FnAnalysis, FnKind, FnPhase, MethodKind, ReceiverMutability},
+        api::Api,
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::{interface_trait::returns_reference, RsCodegenResult};
+
+/// The getter and setter found for a single property.
+#[derive(Default)]
+struct Property<'a> {
+    getter: Option<&'a FnAnalysis>,
+    setter: Option<&'a FnAnalysis>,
+}
+
+/// For each type listed in a `property_accessors!` directive, generate a
+/// `TFields` view of it, obtained with `T::fields`, which has a `x()` and
+/// `set_x()` method for each `GetX`/`SetX` pair of methods.
+///
+/// As for `mockable!`, only methods whose Rust signature is the same as
+/// their cxx signature, and which don't return references, are included.
+pub(super) fn generate_property_accessors(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    let mut properties_by_type: HashMap<QualifiedName, HashMap<String, Property>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Struct { name, .. } = api {
+            if config.has_property_accessors(&name.name.to_cpp_name()) {
+                properties_by_type.entry(name.name.clone()).or_default();
+            }
+        }
+    }
+    for api in apis.iter() {
+        if let Api::Function { analysis, .. } = api {
+            if let FnKind::Method {
+                impl_for,
+                method_kind:
+                    MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+            } = &analysis.kind
+            {
+                if let Some(properties) = properties_by_type.get_mut(impl_for) {
+                    add_accessor(properties, analysis);
+                }
+            }
+        }
+    }
+    properties_by_type
+        .into_iter()
+        .map(|(name, properties)| {
+            let id = name.get_final_ident();
+            let fields_id = make_ident(format!("{id}Fields"));
+            let accessors: Vec<ImplItem> = properties
+                .into_iter()
+                .filter_map(|(property, accessors)| {
+                    generate_accessor_pair(&name, &property, accessors.getter?, accessors.setter?)
+                })
+                .flatten()
+                .collect();
+            let doc = format!(
+                "Property-style access to the getter and setter pairs of [`{id}`]: \
+                `x()` calls `GetX()` and `set_x(value)` calls `SetX(value)`. \
+                Obtain one with [`{id}::fields`]."
+            );
+            let output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[doc = #doc]
+                    pub struct #fields_id<'a>(::core::pin::Pin<&'a mut #id>);
+                },
+                parse_quote! {
+                    impl #id {
+                        #[doc = "Property-style access to this object's getters and setters."]
+                        pub fn fields(self: ::core::pin::Pin<&mut Self>) -> #fields_id<'_> {
+                            #fields_id(self)
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl #fields_id<'_> {
+                        #(#accessors)*
+                    }
+                },
+            ];
+            (
+                name,
+                RsCodegenResult {
+                    output_mod_items,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+/// If this method is a usable `GetX` or `SetX`, note it as the getter or
+/// setter of property `X`.
+fn add_accessor<'a>(properties: &mut HashMap<String, Property<'a>>, analysis: &'a FnAnalysis) {
+    if analysis.ignore_reason.is_err()
+        || !analysis.externally_callable
+        || analysis.returns_view
+        || analysis.async_wrapper_needed
+        || analysis.requires_unsafe.wrapper_token().is_some()
+        || analysis
+            .param_details
+            .iter()
+            .any(|pd| pd.conversion.rust_work_needed())
+        || analysis
+            .ret_conversion
+            .as_ref()
+            .map(|conversion| conversion.rust_work_needed())
+            .unwrap_or_default()
+        || returns_reference(&analysis.ret_type.0)
+    {
+        return;
+    }
+    let Some(receiver_mutability) = analysis
+        .param_details
+        .first()
+        .and_then(|pd| pd.self_type.as_ref())
+        .map(|(_, mutability)| *mutability)
+    else {
+        return;
+    };
+    // Overloads are renamed, so look at the original name.
+    let cpp_name = analysis
+        .renamed_from
+        .as_deref()
+        .unwrap_or(&analysis.rust_name);
+    let returns_value = !matches!(analysis.ret_type.0, syn::ReturnType::Default);
+    let (property, is_getter) = if let Some(property) = cpp_name.strip_prefix("Get") {
+        if !returns_value
+            || analysis.params.len() != 1
+            || !matches!(receiver_mutability, ReceiverMutability::Const)
+        {
+            return;
+        }
+        (property, true)
+    } else if let Some(property) = cpp_name.strip_prefix("Set") {
+        if returns_value || analysis.params.len() != 2 {
+            return;
+        }
+        (property, false)
+    } else {
+        return;
+    };
+    // Don't turn `Getaway` into `away`.
+    if !property.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return;
+    }
+    let accessors = properties.entry(property.to_string()).or_default();
+    let slot = if is_getter {
+        &mut accessors.getter
+    } else {
+        &mut accessors.setter
+    };
+    slot.get_or_insert(analysis);
+}
+
+fn generate_accessor_pair(
+    impl_for: &QualifiedName,
+    property: &str,
+    getter: &FnAnalysis,
+    setter: &FnAnalysis,
+) -> Option<[ImplItem; 2]> {
+    let ty = impl_for.get_final_ident();
+    let snake_name = to_snake_case(property);
+    let getter_id = make_ident(&snake_name);
+    let setter_id = make_ident(format!("set_{}", snake_name.trim_end_matches('_')));
+    let cpp_getter = make_ident(&getter.rust_name);
+    let cpp_setter = make_ident(&setter.rust_name);
+    let ret_type = &getter.ret_type.0;
+    let param: FnArg = setter.params.iter().nth(1)?.0.clone();
+    let FnArg::Typed(pt) = &param else {
+        return None;
+    };
+    let param_name = &pt.pat;
+    let this = match setter
+        .param_details
+        .first()
+        .and_then(|pd| pd.self_type.as_ref())
+        .map(|(_, mutability)| *mutability)?
+    {
+        ReceiverMutability::Const => quote::quote! { &*self.0 },
+        ReceiverMutability::Mutable => quote::quote! { self.0.as_mut() },
+    };
+    let getter_doc = format!("Calls `{ty}::Get{property}`.");
+    let setter_doc = format!("Calls `{ty}::Set{property}`.");
+    Some([
+        parse_quote! {
+            #[doc = #getter_doc]
+            #[must_use]
+            pub fn #getter_id(&self) #ret_type {
+                #ty::#cpp_getter(&*self.0)
+            }
+        },
+        parse_quote! {
+            #[doc = #setter_doc]
+            pub fn #setter_id(&mut self, #param) {
+                #ty::#cpp_setter(#this, #param_name)
+            }
+        },
+    ])
+}
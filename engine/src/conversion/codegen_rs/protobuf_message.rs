@@ -0,0 +1,241 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Each `protobuf_message!` type implements `autocxx::ProtobufMessage`,
+//! and gets accessors in terms of the methods protoc generates for each
+//! field: `foo_mut()` wrapping `mutable_foo()`, which returns a pointer
+//! that's never null, and `foo_iter()` for a repeated field, using
+//! `foo_size()` and `foo(int index)`.
+
+use syn::{parse_quote, ForeignItem, ImplItem, Item, Type, TypePtr};
+
+use crate::{
+    conversion::{
+        analysis::fun::{
+            function_wrapper::RustConversionType, small_pods::is_scalar, FnAnalysis, FnKind,
+            FnPhase, MethodKind, ReceiverMutability,
+        },
+        api::{Api, UnsafetyNeeded},
+        apivec::ApiVec,
+        protobuf_messages::ProtobufMessage,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::{ImplBlockDetails, RsCodegenResult};
+
+pub(super) fn generate_protobuf_messages(
+    apis: &ApiVec<FnPhase>,
+    messages: &[ProtobufMessage],
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    messages
+        .iter()
+        .flat_map(|message| {
+            let serialize_shim = make_ident(&message.serialize_shim);
+            let parse_shim = make_ident(&message.parse_shim);
+            let id = message.ty.get_final_ident();
+            let extern_c_mod_items: Vec<ForeignItem> = vec![
+                parse_quote! {
+                    fn #serialize_shim(msg: &#id) -> UniquePtr<CxxString>;
+                },
+                parse_quote! {
+                    fn #parse_shim(msg: Pin<&mut #id>, data: &[u8]) -> bool;
+                },
+            ];
+            let output_mod_items: Vec<Item> = vec![parse_quote! {
+                impl autocxx::ProtobufMessage for #id {
+                    fn to_bytes(&self) -> Vec<u8> {
+                        cxxbridge::#serialize_shim(self).as_bytes().to_vec()
+                    }
+
+                    fn parse_from_bytes(self: ::core::pin::Pin<&mut Self>, data: &[u8]) -> bool {
+                        cxxbridge::#parse_shim(self, data)
+                    }
+                }
+            }];
+            let result = RsCodegenResult {
+                extern_c_mod_items,
+                output_mod_items,
+                ..Default::default()
+            };
+            let accessors = generate_accessors(apis, &message.ty)
+                .into_iter()
+                .map(|(accessor, item)| {
+                    let name = QualifiedName::new(
+                        message.ty.get_namespace(),
+                        make_ident(format!("{id}_{accessor}")),
+                    );
+                    let result = RsCodegenResult {
+                        impl_entry: Some(Box::new(ImplBlockDetails {
+                            item,
+                            ty: parse_quote! { #id },
+                        })),
+                        ..Default::default()
+                    };
+                    (name, result)
+                })
+                .collect::<Vec<_>>();
+            std::iter::once((message.ty.clone(), result)).chain(accessors)
+        })
+        .collect()
+}
+
+/// The accessors for this type's fields, with their names.
+fn generate_accessors(apis: &ApiVec<FnPhase>, ty: &QualifiedName) -> Vec<(String, ImplItem)> {
+    let methods: Vec<_> = methods(apis, ty).collect();
+    let mut accessors = Vec::new();
+    for (cpp_name, analysis) in &methods {
+        if let Some(field) = cpp_name.strip_prefix("mutable_") {
+            if let Some(pointee) = never_null_pointer_return(analysis) {
+                let accessor = format!("{field}_mut");
+                let accessor_id = make_ident(&accessor);
+                let rust_name = make_ident(&analysis.rust_name);
+                let doc = format!(
+                    "Mutable access to `{field}`, which the message owns. If it isn't set, it's set to its default first."
+                );
+                accessors.push((
+                    accessor,
+                    parse_quote! {
+                        #[doc = #doc]
+                        pub fn #accessor_id(self: ::core::pin::Pin<&mut Self>) -> ::core::pin::Pin<&mut #pointee> {
+                            // protoc's mutable accessors never return null.
+                            unsafe { ::core::pin::Pin::new_unchecked(&mut *self.#rust_name()) }
+                        }
+                    },
+                ));
+            }
+        } else if let Some(field) = cpp_name.strip_suffix("_size") {
+            if !is_size_method(analysis) {
+                continue;
+            }
+            let Some((_, getter)) = methods
+                .iter()
+                .find(|(cpp_name, analysis)| cpp_name == field && is_element_getter(analysis))
+            else {
+                continue;
+            };
+            let item = getter.ret_conversion.as_ref().unwrap().cxxbridge_type();
+            let accessor = format!("{field}_iter");
+            let accessor_id = make_ident(&accessor);
+            let size = make_ident(&analysis.rust_name);
+            let getter = make_ident(&getter.rust_name);
+            let doc = format!("Iterate over the elements of the repeated field `{field}`.");
+            accessors.push((
+                accessor,
+                parse_quote! {
+                    #[doc = #doc]
+                    pub fn #accessor_id(&self) -> impl Iterator<Item = #item> + '_ {
+                        (0..::std::os::raw::c_int::from(self.#size()))
+                            .map(move |index| self.#getter(index.into()))
+                    }
+                },
+            ));
+        }
+    }
+    accessors
+}
+
+/// The public, non-static methods of this type which generated
+/// successfully, with their C++ names.
+fn methods<'a>(
+    apis: &'a ApiVec<FnPhase>,
+    ty: &'a QualifiedName,
+) -> impl Iterator<Item = (String, &'a FnAnalysis)> {
+    apis.iter().filter_map(move |api| match api {
+        Api::Function {
+            name,
+            fun,
+            analysis:
+                analysis @ FnAnalysis {
+                    kind:
+                        FnKind::Method {
+                            impl_for,
+                            method_kind: MethodKind::Normal,
+                        },
+                    ignore_reason: Ok(_),
+                    externally_callable: true,
+                    vis: syn::Visibility::Public(_),
+                    ..
+                },
+        } if impl_for == ty && fun.add_to_trait.is_none() => Some((
+            name.cpp_name_if_present()
+                .map_or_else(|| fun.ident.to_string(), |n| n.for_validation().to_string()),
+            analysis,
+        )),
+        _ => None,
+    })
+}
+
+fn non_self_params(analysis: &FnAnalysis) -> impl Iterator<Item = &Type> {
+    analysis
+        .param_details
+        .iter()
+        .filter(|pd| pd.self_type.is_none())
+        .map(|pd| pd.conversion.cxxbridge_type())
+}
+
+fn has_receiver(analysis: &FnAnalysis, mutability: ReceiverMutability) -> bool {
+    analysis
+        .param_details
+        .iter()
+        .any(|pd| matches!(&pd.self_type, Some((_, m)) if *m == mutability))
+}
+
+/// The type pointed to by the return value of a `mutable_foo()` method,
+/// which is exposed as an unsafe method returning a raw pointer.
+fn never_null_pointer_return(analysis: &FnAnalysis) -> Option<&Type> {
+    if non_self_params(analysis).next().is_some()
+        || !has_receiver(analysis, ReceiverMutability::Mutable)
+        || matches!(analysis.requires_unsafe, UnsafetyNeeded::None)
+    {
+        return None;
+    }
+    let conversion = analysis.ret_conversion.as_ref()?;
+    if !matches!(conversion.rust_conversion, RustConversionType::None) {
+        return None;
+    }
+    match conversion.cxxbridge_type() {
+        Type::Ptr(TypePtr {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => Some(elem),
+        _ => None,
+    }
+}
+
+/// Whether this is `int foo_size() const`.
+fn is_size_method(analysis: &FnAnalysis) -> bool {
+    non_self_params(analysis).next().is_none()
+        && has_receiver(analysis, ReceiverMutability::Const)
+        && matches!(analysis.requires_unsafe, UnsafetyNeeded::None)
+        && analysis
+            .ret_conversion
+            .as_ref()
+            .is_some_and(|conversion| is_c_int(conversion.cxxbridge_type()))
+}
+
+/// Whether this is `T foo(int index) const`, returning a number, `bool`
+/// or reference.
+fn is_element_getter(analysis: &FnAnalysis) -> bool {
+    let params: Vec<_> = non_self_params(analysis).collect();
+    matches!(params.as_slice(), [index] if is_c_int(index))
+        && has_receiver(analysis, ReceiverMutability::Const)
+        && matches!(analysis.requires_unsafe, UnsafetyNeeded::None)
+        && analysis.ret_conversion.as_ref().is_some_and(|conversion| {
+            matches!(conversion.rust_conversion, RustConversionType::None)
+                && match conversion.cxxbridge_type() {
+                    Type::Reference(_) => true,
+                    ty => is_scalar(ty) && !matches!(ty, Type::Ptr(_)),
+                }
+        })
+}
+
+fn is_c_int(ty: &Type) -> bool {
+    matches!(ty, Type::Path(typ) if typ.path.segments.last().is_some_and(|seg| seg.ident == "c_int"))
+}
@@ -0,0 +1,196 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::{IncludeCppConfig, RustEquivalentConversions};
+use syn::{parse_quote, Fields, Item, ItemStruct, TypePath};
+
+use crate::{
+    conversion::{
+        analysis::{
+            fun::{FnPhase, PodAndDepAnalysis},
+            pod::PodAnalysis,
+        },
+        api::{Api, TypeKind},
+        apivec::ApiVec,
+    },
+    types::QualifiedName,
+};
+
+use super::RsCodegenResult;
+
+/// Generate conversions and comparisons in both directions between each
+/// type listed in `rust_equivalent!` and its Rust equivalent. The C++ type
+/// is either a POD struct generated here, or a type from
+/// `extern_cpp_type!`. Types which can't be handled have already been
+/// reported by `check_rust_equivalents`.
+pub(super) fn generate_rust_equivalents(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    config
+        .get_rust_equivalents()
+        .filter_map(|rust_equivalent| {
+            let rust_path = &rust_equivalent.rust_path;
+            let (name, output_mod_items) =
+                if let Some(extern_type) = config.externs.0.get(&rust_equivalent.ty) {
+                    (
+                        QualifiedName::new_from_cpp_name(&rust_equivalent.ty),
+                        generate_converted_impls(
+                            &extern_type.rust_path,
+                            rust_path,
+                            rust_equivalent.conversions.as_ref()?,
+                        ),
+                    )
+                } else {
+                    let (name, item) = find_pod(apis, &rust_equivalent.ty)?;
+                    let cpp_path = name.to_type_path();
+                    let items = match &rust_equivalent.conversions {
+                        Some(conversions) => {
+                            generate_converted_impls(&cpp_path, rust_path, conversions)
+                        }
+                        None => generate_field_by_field_impls(&cpp_path, item, rust_path),
+                    };
+                    (name.clone(), items)
+                };
+            Some((
+                name,
+                RsCodegenResult {
+                    output_mod_items,
+                    ..Default::default()
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The POD struct generated for this C++ type, if any.
+fn find_pod<'a>(
+    apis: &'a ApiVec<FnPhase>,
+    cpp_name: &str,
+) -> Option<(&'a QualifiedName, &'a ItemStruct)> {
+    apis.iter().find_map(|api| match api {
+        Api::Struct {
+            name,
+            details,
+            analysis:
+                PodAndDepAnalysis {
+                    pod:
+                        PodAnalysis {
+                            kind: TypeKind::Pod,
+                            num_generics: 0,
+                            ..
+                        },
+                    ..
+                },
+        } if name.name.to_cpp_name() == cpp_name => Some((&name.name, &*details.item)),
+        _ => None,
+    })
+}
+
+/// Conversions and comparisons which call the user's functions.
+/// Comparisons convert the C++ value to the Rust type, which must
+/// implement `PartialEq`.
+fn generate_converted_impls(
+    cpp_path: &TypePath,
+    rust_path: &TypePath,
+    conversions: &RustEquivalentConversions,
+) -> Vec<Item> {
+    let from_cpp = &conversions.from_cpp;
+    let to_cpp = &conversions.to_cpp;
+    vec![
+        parse_quote! {
+            impl ::core::convert::From<#cpp_path> for #rust_path {
+                fn from(value: #cpp_path) -> Self {
+                    #from_cpp(&value)
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::convert::From<#rust_path> for #cpp_path {
+                fn from(value: #rust_path) -> Self {
+                    #to_cpp(&value)
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::cmp::PartialEq<#rust_path> for #cpp_path {
+                fn eq(&self, other: &#rust_path) -> bool {
+                    #from_cpp(self) == *other
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::cmp::PartialEq<#cpp_path> for #rust_path {
+                fn eq(&self, other: &#cpp_path) -> bool {
+                    other == self
+                }
+            }
+        },
+    ]
+}
+
+/// Conversions and comparisons which work field-by-field, by name, so the
+/// Rust type must have the same field names. Conversions use `Into` for
+/// each field, whilst comparisons use `==`.
+fn generate_field_by_field_impls(
+    cpp_path: &TypePath,
+    item: &ItemStruct,
+    rust_path: &TypePath,
+) -> Vec<Item> {
+    let all_fields: Vec<_> = match &item.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .filter_map(|f| f.ident.as_ref())
+            .collect(),
+        _ => return Vec::new(),
+    };
+    // Skip bindgen's own fields, e.g. for bitfield storage. If there are
+    // any such, we can't construct the C++ type from the Rust type.
+    let fields: Vec<_> = all_fields
+        .iter()
+        .filter(|field_id| !field_id.to_string().starts_with('_'))
+        .collect();
+    let mut items = vec![
+        parse_quote! {
+            impl ::core::convert::From<#cpp_path> for #rust_path {
+                fn from(value: #cpp_path) -> Self {
+                    Self {
+                        #(#fields: value.#fields.into(),)*
+                    }
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::cmp::PartialEq<#rust_path> for #cpp_path {
+                fn eq(&self, other: &#rust_path) -> bool {
+                    true #(&& self.#fields == other.#fields)*
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::cmp::PartialEq<#cpp_path> for #rust_path {
+                fn eq(&self, other: &#cpp_path) -> bool {
+                    other == self
+                }
+            }
+        },
+    ];
+    if fields.len() == all_fields.len() {
+        items.push(parse_quote! {
+            impl ::core::convert::From<#rust_path> for #cpp_path {
+                fn from(value: #rust_path) -> Self {
+                    Self {
+                        #(#fields: value.#fields.into(),)*
+                    }
+                }
+            }
+        });
+    }
+    items
+}
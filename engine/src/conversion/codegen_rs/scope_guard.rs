@@ -0,0 +1,155 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::map::IndexMap as HashMap;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, ImplItem, Item};
+
+use crate::{
+    conversion::{
+        analysis::fun::{FnAnalysis, FnKind, FnPhase, MethodKind},
+        api::Api,
+        apivec::ApiVec,
+    },
+    minisyn::FnArg,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{lifetime::add_lifetime_to_param, RsCodegenResult};
+
+/// For each type listed in a `scope_guard!` directive, generate a
+/// `TGuard<'a>` which constructs a `T` on the C++ heap, borrows whatever
+/// was passed to the constructor for `'a`, and destroys the `T` when
+/// dropped.
+///
+/// As for `property_accessors!`, only constructors whose Rust signature is
+/// the same as their cxx signature are included.
+pub(super) fn generate_scope_guards(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    let mut constructors_by_type: HashMap<QualifiedName, Vec<&FnAnalysis>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Struct { name, .. } = api {
+            if config.is_scope_guard(&name.name.to_cpp_name()) {
+                constructors_by_type.entry(name.name.clone()).or_default();
+            }
+        }
+    }
+    for api in apis.iter() {
+        if let Api::Function { analysis, .. } = api {
+            if let FnKind::Method {
+                impl_for,
+                method_kind: MethodKind::Constructor { .. },
+            } = &analysis.kind
+            {
+                if let Some(constructors) = constructors_by_type.get_mut(impl_for) {
+                    if is_usable_constructor(analysis) {
+                        constructors.push(analysis);
+                    }
+                }
+            }
+        }
+    }
+    constructors_by_type
+        .into_iter()
+        .map(|(name, constructors)| {
+            let id = name.get_final_ident();
+            let guard_id = make_ident(format!("{id}Guard"));
+            let constructors: Vec<ImplItem> = constructors
+                .into_iter()
+                .map(|constructor| generate_guard_constructor(&name, constructor))
+                .collect();
+            let doc = format!(
+                "A [`{id}`] which lives on the C++ heap for as long as this guard \
+                exists, and which is destroyed when the guard is dropped. The guard \
+                borrows everything passed to the constructor, so whatever the `{id}` \
+                refers to outlives it."
+            );
+            let output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[doc = #doc]
+                    #[must_use]
+                    pub struct #guard_id<'a> {
+                        guard: cxx::UniquePtr<#id>,
+                        _owner: ::core::marker::PhantomData<&'a ()>,
+                    }
+                },
+                parse_quote! {
+                    impl<'a> #guard_id<'a> {
+                        #(#constructors)*
+
+                        #[doc = "Returns a pinned mutable reference to the guarded object."]
+                        pub fn pin_mut(&mut self) -> ::core::pin::Pin<&mut #id> {
+                            self.guard.pin_mut()
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl ::core::ops::Deref for #guard_id<'_> {
+                        type Target = #id;
+                        fn deref(&self) -> &#id {
+                            &self.guard
+                        }
+                    }
+                },
+            ];
+            (
+                name,
+                RsCodegenResult {
+                    output_mod_items,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+fn is_usable_constructor(analysis: &FnAnalysis) -> bool {
+    analysis.ignore_reason.is_ok()
+        && analysis.externally_callable
+        && analysis.requires_unsafe.wrapper_token().is_none()
+        && !analysis
+            .param_details
+            .iter()
+            .any(|pd| pd.conversion.rust_work_needed())
+}
+
+/// Generate a guard constructor which calls the given constructor, with
+/// every reference parameter given the guard's lifetime.
+fn generate_guard_constructor(impl_for: &QualifiedName, constructor: &FnAnalysis) -> ImplItem {
+    let ty = impl_for.get_final_ident();
+    let rust_name = make_ident(&constructor.rust_name);
+    let mut params: Punctuated<FnArg, Comma> = constructor
+        .params
+        .iter()
+        .zip(constructor.param_details.iter())
+        .filter(|(_, pd)| !pd.is_placement_return_destination)
+        .map(|(param, _)| param.clone())
+        .collect();
+    let param_names: Vec<syn::Pat> = params
+        .iter()
+        .filter_map(|param| match &param.0 {
+            syn::FnArg::Typed(pt) => Some(pt.pat.as_ref().clone()),
+            _ => None,
+        })
+        .collect();
+    for name in &param_names {
+        add_lifetime_to_param(&mut params, name);
+    }
+    let doc = format!("Constructs a `{ty}` using `{ty}::{rust_name}`, guarded until dropped.");
+    parse_quote! {
+        #[doc = #doc]
+        pub fn #rust_name(#params) -> Self {
+            Self {
+                guard: autocxx::WithinUniquePtr::within_unique_ptr(#ty::#rust_name(#(#param_names),*)),
+                _owner: ::core::marker::PhantomData,
+            }
+        }
+    }
+}
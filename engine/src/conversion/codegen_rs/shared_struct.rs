@@ -0,0 +1,102 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! With `shared_struct!`, a POD type is given to cxx as a shared struct,
+//! defined within the `cxx::bridge`, rather than as an extern type
+//! defined by bindgen. The C++ already defines the type, so the generated
+//! C++ stops cxx from defining it too; and since the fields cxx knows
+//! about were worked out from bindgen's version, we check at compile time
+//! that the two have the same layout.
+//!
+//! cxx would implement `PartialEq` using a C++ `operator==` which it
+//! declares within its own definition of the type, so we implement that
+//! in Rust instead.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Attribute, Field, Item, ItemStruct};
+
+use crate::{
+    conversion::{doc_attr::get_doc_attrs, type_helpers::shared_struct_field_type},
+    types::{make_ident, QualifiedName},
+};
+
+use super::utils::{find_output_mod_root, generate_cxx_use_stmt};
+
+/// The cxx bridge definition of the shared struct, and the items for the
+/// output mod which expose it and check its layout.
+pub(super) fn generate_shared_struct(
+    name: &QualifiedName,
+    item: &ItemStruct,
+    cxxbridge_attrs: TokenStream,
+    doc_attrs: &[Attribute],
+) -> (Item, Vec<Item>) {
+    let id = name.get_final_ident();
+    let fields: Vec<&Field> = item.fields.iter().collect();
+    let field_defs = fields.iter().map(|field| {
+        let field_id = &field.ident;
+        // Analysis already rejected any other types.
+        let ty = shared_struct_field_type(&field.ty).unwrap();
+        let field_docs = get_doc_attrs(&field.attrs);
+        quote! {
+            #(#field_docs)*
+            pub #field_id: #ty
+        }
+    });
+    let bridge_item: Item = parse_quote! {
+        #cxxbridge_attrs
+        #(#doc_attrs)*
+        #[derive(Clone, Copy, Debug)]
+        pub struct #id {
+            #(#field_defs),*
+        }
+    };
+    let cxx_segs: Vec<_> = find_output_mod_root(name.get_namespace())
+        .chain(std::iter::once(make_ident("cxxbridge")))
+        .chain(std::iter::once(id.clone()))
+        .collect();
+    let bindgen_segs: Vec<_> = find_output_mod_root(name.get_namespace())
+        .chain(name.get_bindgen_path_idents())
+        .collect();
+    let message = format!(
+        "shared_struct! {} has a different layout in Rust and C++",
+        name.to_cpp_name()
+    );
+    let field_checks = fields.iter().map(|field| {
+        let field_id = &field.ident;
+        quote! {
+            assert!(
+                ::core::mem::offset_of!(#(#cxx_segs)::*, #field_id)
+                    == ::core::mem::offset_of!(#(#bindgen_segs)::*, #field_id),
+                #message
+            );
+        }
+    });
+    let field_ids: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let partial_eq: Item = parse_quote! {
+        impl ::core::cmp::PartialEq for #(#cxx_segs)::* {
+            fn eq(&self, other: &Self) -> bool {
+                true #(&& self.#field_ids == other.#field_ids)*
+            }
+        }
+    };
+    let layout_check: Item = parse_quote! {
+        const _: () = {
+            assert!(
+                ::core::mem::size_of::<#(#cxx_segs)::*>() == ::core::mem::size_of::<#(#bindgen_segs)::*>()
+                    && ::core::mem::align_of::<#(#cxx_segs)::*>() == ::core::mem::align_of::<#(#bindgen_segs)::*>(),
+                #message
+            );
+            #(#field_checks)*
+        };
+    };
+    (
+        bridge_item,
+        vec![generate_cxx_use_stmt(name, None), partial_eq, layout_check],
+    )
+}
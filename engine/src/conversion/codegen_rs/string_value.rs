@@ -0,0 +1,61 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::parse_quote;
+
+use crate::{
+    conversion::string_values::string_value_names,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Declare the C++ functions which construct and destroy a
+/// `std::string` within an `autocxx::CxxStringValue`, and generate
+/// `make_string_value()`, the by-value equivalent of `make_string()`.
+pub(super) fn generate_string_value_shims(
+    config: &IncludeCppConfig,
+) -> Option<(QualifiedName, RsCodegenResult)> {
+    if !config.strings_by_value() {
+        return None;
+    }
+    let names = string_value_names(config);
+    let init_fn = make_ident(&names.init_fn);
+    let destroy_fn = make_ident(&names.destroy_fn);
+    let result = RsCodegenResult {
+        extern_c_mod_items: vec![
+            parse_quote! {
+                unsafe fn #init_fn(this: *mut CxxString, ptr: *const u8, len: usize);
+            },
+            parse_quote! {
+                unsafe fn #destroy_fn(this: *mut CxxString);
+            },
+        ],
+        output_mod_items: vec![parse_quote! {
+            /// Make a C++ `std::string`, held by value in an
+            /// [`autocxx::CxxStringValue`], containing a copy of the given bytes.
+            pub fn make_string_value(
+                bytes: impl AsRef<[u8]>,
+            ) -> impl autocxx::moveit::new::New<Output = autocxx::CxxStringValue> {
+                unsafe {
+                    autocxx::CxxStringValue::new_with(
+                        bytes,
+                        cxxbridge::#init_fn,
+                        cxxbridge::#destroy_fn,
+                    )
+                }
+            }
+        }],
+        ..Default::default()
+    };
+    Some((
+        QualifiedName::new_from_cpp_name("make_string_value"),
+        result,
+    ))
+}
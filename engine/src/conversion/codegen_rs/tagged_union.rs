@@ -0,0 +1,68 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use autocxx_parser::TaggedUnion;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::{
+    conversion::analysis::tagged_unions::{member_accessor_name, tag_accessor_name},
+    types::{make_ident, QualifiedName},
+};
+
+use super::utils::find_output_mod_root;
+
+/// Generate a Rust enum which borrows from a C++ tagged union, and a
+/// `TryFrom` implementation which inspects the tag to decide which union
+/// member is active. The accessors this calls are synthesized during
+/// analysis.
+pub(super) fn generate_tagged_union_view(
+    name: &QualifiedName,
+    tagged_union: &TaggedUnion,
+) -> Vec<Item> {
+    let ty = name.get_final_ident();
+    let view = make_ident(format!("{ty}View"));
+    let tag_accessor = make_ident(tag_accessor_name());
+    let variant_names: Vec<_> = tagged_union.variants.iter().map(|v| &v.name).collect();
+    let variant_types = tagged_union.variants.iter().map(|v| {
+        let member_ty = QualifiedName::new_from_cpp_name(&v.ty);
+        let segs = find_output_mod_root(name.get_namespace())
+            .chain(member_ty.ns_segment_iter().map(make_ident))
+            .chain(std::iter::once(member_ty.get_final_ident()));
+        quote! { #(#segs)::* }
+    });
+    let tag_values = tagged_union.variants.iter().map(|v| v.tag_value);
+    let member_accessors = tagged_union
+        .variants
+        .iter()
+        .map(|v| make_ident(member_accessor_name(&v.name)));
+    let doc =
+        format!("A view of the [`{ty}`] tagged union, indicating which union member is active.");
+    vec![
+        parse_quote! {
+            #[doc = #doc]
+            pub enum #view<'a> {
+                #(#variant_names(&'a #variant_types),)*
+            }
+        },
+        parse_quote! {
+            impl<'a> ::core::convert::TryFrom<&'a #ty> for #view<'a> {
+                type Error = autocxx::UnknownUnionTag;
+                #[allow(unused_unsafe)]
+                fn try_from(value: &'a #ty) -> Result<Self, Self::Error> {
+                    // The accessors may be unsafe depending on the safety policy.
+                    // Only the member corresponding to the tag is ever read.
+                    match unsafe { value.#tag_accessor() } {
+                        #(#tag_values => Ok(Self::#variant_names(unsafe { value.#member_accessors() })),)*
+                        tag => Err(autocxx::UnknownUnionTag(tag)),
+                    }
+                }
+            }
+        },
+    ]
+}
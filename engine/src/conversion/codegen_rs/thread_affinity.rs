@@ -0,0 +1,59 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Each `thread_affine!` type gets a static recording which thread owns
+//! each of its objects. It's generated alongside the type, so the type's
+//! methods, which are in the same mod, can refer to it by its bare name.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::{
+    minisyn::Ident,
+    types::{make_ident, QualifiedName},
+};
+
+fn thread_affinity_static_name(ty: &QualifiedName) -> Ident {
+    make_ident(format!("{}_autocxx_thread_affinity", ty.get_final_item()))
+}
+
+pub(super) fn generate_thread_affinity_static(ty: &QualifiedName) -> Item {
+    let id = thread_affinity_static_name(ty);
+    parse_quote! {
+        #[allow(non_upper_case_globals)]
+        static #id: autocxx::ThreadAffinity = autocxx::ThreadAffinity::new();
+    }
+}
+
+/// A statement which panics, in debug builds, if this isn't the thread
+/// which owns the `ty` at address `object` (a `usize`).
+pub(super) fn generate_thread_affinity_check(
+    ty: &QualifiedName,
+    object: TokenStream,
+) -> TokenStream {
+    let id = thread_affinity_static_name(ty);
+    let cpp_name = ty.to_cpp_name();
+    quote! {
+        #[cfg(debug_assertions)]
+        #id.check(#object, #cpp_name);
+    }
+}
+
+/// A statement which records, in debug builds, that the `ty` about to be
+/// constructed at `ptr` belongs to this thread.
+pub(super) fn generate_thread_affinity_record(
+    ty: &QualifiedName,
+    ptr: &TokenStream,
+) -> TokenStream {
+    let id = thread_affinity_static_name(ty);
+    quote! {
+        #[cfg(debug_assertions)]
+        #id.record_owner(#ptr as usize);
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Each `thread_handle!` type gets a `TypeHandle`, wrapping an
+//! `autocxx::ThreadHandle`, with a method forwarding to each method of
+//! the type that can be called on another thread. Since the arguments are
+//! moved into a closure which must be `Send`, and the result sent back,
+//! that's only the methods which take and return numbers or `bool`s.
+
+use autocxx_parser::IncludeCppConfig;
+use quote::quote;
+use syn::{parse_quote, Item, Type, Visibility};
+
+use crate::{
+    conversion::{
+        analysis::fun::{small_pods::is_scalar, FnAnalysis, FnKind, FnPhase, MethodKind},
+        api::{Api, UnsafetyNeeded},
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+pub(super) fn generate_thread_handles(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } if config.has_thread_handle(&name.name.to_cpp_name()) => {
+                Some(&name.name)
+            }
+            _ => None,
+        })
+        .map(|ty| {
+            let id = ty.get_final_ident();
+            let handle = make_ident(format!("{id}Handle"));
+            let doc = format!(
+                "A handle to a `{}` which can be sent to other threads. Each method makes the call on the object's own thread, using the `autocxx::Dispatcher` given to `new`, and returns a future which resolves to the result.",
+                ty.to_cpp_name()
+            );
+            let forwarders = forwardable_methods(apis, ty).map(|analysis| {
+                let rust_name = make_ident(&analysis.rust_name);
+                let doc = format!("Call `{}` on the object's own thread.", analysis.rust_name);
+                let (names, types): (Vec<_>, Vec<_>) = analysis
+                    .param_details
+                    .iter()
+                    .filter(|pd| pd.self_type.is_none())
+                    .map(|pd| (&pd.name, pd.conversion.cxxbridge_type()))
+                    .unzip();
+                let ret_type = analysis
+                    .ret_conversion
+                    .as_ref()
+                    .map(|conversion| conversion.cxxbridge_type().clone())
+                    .unwrap_or_else(|| parse_quote! { () });
+                quote! {
+                    #[doc = #doc]
+                    pub fn #rust_name(&self, #(#names: #types),*) -> autocxx::oneshot::Receiver<#ret_type> {
+                        self.0.run(move |obj| obj.#rust_name(#(#names),*))
+                    }
+                }
+            });
+            let output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    #[doc = #doc]
+                    #[derive(Clone)]
+                    pub struct #handle(autocxx::ThreadHandle<#id>);
+                },
+                parse_quote! {
+                    impl #handle {
+                        /// Take ownership of an object belonging to the
+                        /// dispatcher's thread.
+                        ///
+                        /// # Safety
+                        ///
+                        /// As for `autocxx::ThreadHandle::new`: `object`
+                        /// must belong to the thread on which `dispatcher`
+                        /// runs its tasks.
+                        pub unsafe fn new(
+                            object: cxx::UniquePtr<#id>,
+                            dispatcher: ::std::sync::Arc<dyn autocxx::Dispatcher>,
+                        ) -> Self {
+                            Self(autocxx::ThreadHandle::new(object, dispatcher))
+                        }
+
+                        /// Call `f` with the object on its own thread.
+                        pub fn run<R: Send + 'static>(
+                            &self,
+                            f: impl FnOnce(::core::pin::Pin<&mut #id>) -> R + Send + 'static,
+                        ) -> autocxx::oneshot::Receiver<R> {
+                            self.0.run(f)
+                        }
+
+                        #(#forwarders)*
+                    }
+                },
+            ];
+            let result = RsCodegenResult {
+                output_mod_items,
+                ..Default::default()
+            };
+            (ty.clone(), result)
+        })
+        .collect()
+}
+
+/// The public, safe, synchronous instance methods of this type, whose
+/// parameters and return value are all numbers or `bool`s.
+fn forwardable_methods<'a>(
+    apis: &'a ApiVec<FnPhase>,
+    ty: &'a QualifiedName,
+) -> impl Iterator<Item = &'a FnAnalysis> {
+    apis.iter().filter_map(move |api| match api {
+        Api::Function {
+            fun,
+            analysis:
+                analysis @ FnAnalysis {
+                    kind:
+                        FnKind::Method {
+                            impl_for,
+                            method_kind:
+                                MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+                        },
+                    ignore_reason: Ok(_),
+                    externally_callable: true,
+                    requires_unsafe: UnsafetyNeeded::None,
+                    vis: Visibility::Public(_),
+                    async_wrapper_needed: false,
+                    ..
+                },
+            ..
+        } if impl_for == ty
+            && fun.add_to_trait.is_none()
+            && analysis
+                .param_details
+                .iter()
+                .filter(|pd| pd.self_type.is_none())
+                .all(|pd| is_plain_scalar(pd.conversion.cxxbridge_type()))
+            && analysis
+                .ret_conversion
+                .as_ref()
+                .map_or(true, |conversion| is_plain_scalar(conversion.cxxbridge_type())) =>
+        {
+            Some(analysis)
+        }
+        _ => None,
+    })
+}
+
+/// A number or `bool`, which unlike a raw pointer is `Send`.
+fn is_plain_scalar(ty: &Type) -> bool {
+    is_scalar(ty) && !matches!(ty, Type::Ptr(_))
+}
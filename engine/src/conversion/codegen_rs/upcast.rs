@@ -0,0 +1,81 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::upcasts::Upcast,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// Implement `AsCppRef<Base>` and `AsCppMutRef<Base>` for `CppPin<Derived>`,
+/// going via C++ to get the base pointer, since it may not have the same
+/// address as the derived object. Also allow a `UniquePtr<Derived>` to
+/// become a `UniquePtr<Base>`, if that's safe.
+pub(super) fn generate_upcasts(upcasts: &[Upcast]) -> Vec<(QualifiedName, RsCodegenResult)> {
+    upcasts
+        .iter()
+        .map(|upcast| {
+            let cpp_shim = make_ident(&upcast.cpp_shim);
+            let derived_id = upcast.derived.get_final_ident();
+            let base_id = upcast.base.get_final_ident();
+            let derived = upcast.derived.to_type_path();
+            let base = upcast.base.to_type_path();
+            let mut extern_c_mod_items: Vec<ForeignItem> = vec![parse_quote! {
+                unsafe fn #cpp_shim(derived: *mut #derived_id) -> *mut #base_id;
+            }];
+            let mut output_mod_items: Vec<Item> = vec![
+                parse_quote! {
+                    impl autocxx::AsCppRef<#base> for autocxx::CppPin<#derived> {
+                        fn as_cpp_ref(&self) -> autocxx::CppRef<#base> {
+                            // The C++ doesn't modify the object, so casting
+                            // away constness is OK.
+                            autocxx::CppRef::from_ptr(unsafe {
+                                cxxbridge::#cpp_shim(self.as_ptr() as *mut #derived)
+                            })
+                        }
+                    }
+                },
+                parse_quote! {
+                    impl autocxx::AsCppMutRef<#base> for autocxx::CppPin<#derived> {
+                        fn as_cpp_mut_ref(&mut self) -> autocxx::CppMutRef<#base> {
+                            autocxx::CppMutRef::from_ptr(unsafe {
+                                cxxbridge::#cpp_shim(self.as_mut_ptr())
+                            })
+                        }
+                    }
+                },
+            ];
+            // Ideally this would be `From<UniquePtr<Derived>> for UniquePtr<Base>`
+            // but the orphan rule forbids that.
+            if let Some(unique_ptr_shim) = &upcast.unique_ptr_shim {
+                let unique_ptr_shim = make_ident(unique_ptr_shim);
+                let as_unique_ptr = make_ident(format!("as_{base_id}_unique_ptr"));
+                extern_c_mod_items.push(parse_quote! {
+                    fn #unique_ptr_shim(derived: UniquePtr<#derived_id>) -> UniquePtr<#base_id>;
+                });
+                output_mod_items.push(parse_quote! {
+                    impl #derived {
+                        #[allow(non_snake_case)]
+                        pub fn #as_unique_ptr(derived: cxx::UniquePtr<Self>) -> cxx::UniquePtr<#base> {
+                            cxxbridge::#unique_ptr_shim(derived)
+                        }
+                    }
+                });
+            }
+            let result = RsCodegenResult {
+                extern_c_mod_items,
+                output_mod_items,
+                ..Default::default()
+            };
+            (upcast.derived.clone(), result)
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, ForeignItem, Item};
+
+use crate::{
+    conversion::vector_slices::VectorSliceMethod,
+    types::{make_ident, QualifiedName},
+};
+
+use super::RsCodegenResult;
+
+/// For each method returning `const std::vector<T>&` of numbers or POD
+/// types, generate an `x_as_slice` method alongside `x` which views the
+/// vector's elements as a slice borrowed from the receiver.
+pub(super) fn generate_vector_slices(
+    methods: &[VectorSliceMethod],
+) -> Vec<(QualifiedName, RsCodegenResult)> {
+    methods
+        .iter()
+        .map(|method| {
+            let shim = make_ident(&method.shim);
+            let id = method.impl_for.get_final_ident();
+            let element_id = method.element.get_final_ident();
+            let element = if method.element_is_numeric {
+                quote! { #element_id }
+            } else {
+                let ty = method.element.to_type_path();
+                quote! { #ty }
+            };
+            let rust_name = make_ident(&method.rust_name);
+            let extern_c_mod_items: Vec<ForeignItem> = vec![parse_quote! {
+                unsafe fn #shim(obj: &#id, len: &mut usize) -> *const #element_id;
+            }];
+            let doc = format!(
+                "The elements of the vector returned by `{}`, viewed in place.",
+                method.cpp_method
+            );
+            let output_mod_items: Vec<Item> = vec![parse_quote! {
+                impl #id {
+                    #[doc = #doc]
+                    pub fn #rust_name(&self) -> &[#element] {
+                        let mut len = 0usize;
+                        let data = unsafe { cxxbridge::#shim(self, &mut len) };
+                        if len == 0 {
+                            &[]
+                        } else {
+                            // The vector belongs to `self`, which it can't be
+                            // modified through while borrowed.
+                            unsafe { ::core::slice::from_raw_parts(data, len) }
+                        }
+                    }
+                }
+            }];
+            (
+                method.impl_for.clone(),
+                RsCodegenResult {
+                    extern_c_mod_items,
+                    output_mod_items,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
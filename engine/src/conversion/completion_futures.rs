@@ -0,0 +1,50 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For each `completion_future!` we generate a C++ wrapper which calls
+//! the function with a lambda as its callback, and a Rust function which
+//! passes that wrapper the sending half of a oneshot channel (boxed, as
+//! an extern "Rust" type) and returns the receiving half. The lambda
+//! hands the result back to Rust through an extern "Rust" function.
+//! This module names those items consistently for the Rust and C++
+//! code generators.
+
+use autocxx_parser::{CompletionFuture, IncludeCppConfig};
+
+use crate::types::{make_ident, QualifiedName};
+
+pub(crate) struct CompletionFutureNames {
+    /// The Rust function which returns the future, e.g. `ns::fetch_future`.
+    pub(crate) future_fn: QualifiedName,
+    /// The extern "Rust" type which owns the sender.
+    pub(crate) completion_type: String,
+    /// The C++ function which calls the original function.
+    pub(crate) cpp_wrapper: String,
+    /// The extern "Rust" function which C++ calls with the result.
+    pub(crate) complete_fn: String,
+}
+
+pub(crate) fn completion_future_names(
+    completion_future: &CompletionFuture,
+    config: &IncludeCppConfig,
+) -> CompletionFutureNames {
+    let function = QualifiedName::new_from_cpp_name(&completion_future.function);
+    let future_fn = QualifiedName::new(
+        function.get_namespace(),
+        make_ident(format!("{}_future", function.get_final_item())),
+    );
+    // These are all visible at the top level of the C++, so need to be
+    // unique across every include_cpp! in the build.
+    let flat_name = completion_future.function.replace("::", "_");
+    CompletionFutureNames {
+        future_fn,
+        completion_type: config.uniquify_name_per_mod(&format!("{flat_name}_completion")),
+        cpp_wrapper: config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_future")),
+        complete_fn: config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_complete")),
+    }
+}
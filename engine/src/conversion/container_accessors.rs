@@ -0,0 +1,94 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Instantiated C++ containers are opaque to Rust, and their own element
+//! accessors either throw (`at`) or have undefined behavior when out of
+//! range (`operator[]`). For each such container type we generate a
+//! `try_get` which does the bounds check (or lookup) in C++ and returns
+//! a null pointer on failure, which Rust turns into `None`.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{GenericArgument, PathArguments, Type};
+
+use crate::types::QualifiedName;
+
+/// How elements of a container are found.
+pub(crate) enum ContainerLookup {
+    /// By `usize` index, e.g. `std::vector`.
+    Index,
+    /// By key, e.g. `std::map`.
+    Key(Type),
+}
+
+pub(crate) struct ContainerAccessor {
+    pub(crate) lookup: ContainerLookup,
+    /// The Rust type of the elements returned.
+    pub(crate) element: Type,
+    /// The name of the C++ function which does the lookup.
+    pub(crate) wrapper_name: String,
+}
+
+const INDEXED_CONTAINERS: &[&str] = &["std::vector<", "std::deque<"];
+const KEYED_CONTAINERS: &[&str] = &["std::map<", "std::unordered_map<"];
+
+/// If this concrete instantiation of a template is a container which we
+/// know how to index into, describe how to do so.
+pub(crate) fn find_container_accessor(
+    name: &QualifiedName,
+    cpp_definition: &str,
+    rs_definition: &Type,
+    config: &IncludeCppConfig,
+) -> Option<ContainerAccessor> {
+    let type_args = type_args(rs_definition)?;
+    // `std::vector<bool>` elements can't be referenced.
+    let lookup = if INDEXED_CONTAINERS
+        .iter()
+        .any(|prefix| cpp_definition.starts_with(prefix))
+        && cpp_definition != "std::vector<bool>"
+    {
+        ContainerLookup::Index
+    } else if KEYED_CONTAINERS
+        .iter()
+        .any(|prefix| cpp_definition.starts_with(prefix))
+    {
+        ContainerLookup::Key(Type::clone(type_args.first()?))
+    } else {
+        return None;
+    };
+    let element = match lookup {
+        ContainerLookup::Index => type_args.first()?,
+        ContainerLookup::Key(_) => type_args.get(1)?,
+    };
+    let element = Type::clone(element);
+    Some(ContainerAccessor {
+        lookup,
+        element,
+        wrapper_name: config.uniquify_name_per_mod(&format!(
+            "{}_try_get_autocxx_wrapper",
+            name.get_final_item()
+        )),
+    })
+}
+
+fn type_args(ty: &Type) -> Option<Vec<&Type>> {
+    match ty {
+        Type::Path(typ) => match &typ.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(ab) => Some(
+                ab.args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For each `context_callback!` the Rust side boxes the closure and
+//! passes its address, as a `usize`, to a C++ wrapper. That registers a
+//! C++ trampoline as the callback, with the address as its context, and
+//! the trampoline hands the address and arguments back to an extern
+//! "Rust" function which calls the closure. A second wrapper, if there's
+//! an unregistration function, passes the same pair to that. This module
+//! names those items consistently for the Rust and C++ code generators.
+
+use autocxx_parser::{ContextCallback, IncludeCppConfig};
+
+use crate::types::{make_ident, QualifiedName};
+
+pub(crate) struct ContextCallbackNames {
+    /// The Rust function which takes a closure, e.g. `ns::on_tick_closure`.
+    pub(crate) closure_fn: QualifiedName,
+    /// The C++ function which calls the registration function.
+    pub(crate) cpp_register: String,
+    /// The C++ function which calls the unregistration function, if any.
+    pub(crate) cpp_unregister: Option<String>,
+    /// The C++ function registered as the callback.
+    pub(crate) cpp_trampoline: String,
+    /// The extern "Rust" function which the trampoline calls.
+    pub(crate) call_fn: String,
+}
+
+pub(crate) fn context_callback_names(
+    context_callback: &ContextCallback,
+    config: &IncludeCppConfig,
+) -> ContextCallbackNames {
+    let function = QualifiedName::new_from_cpp_name(&context_callback.function);
+    let closure_fn = QualifiedName::new(
+        function.get_namespace(),
+        make_ident(format!("{}_closure", function.get_final_item())),
+    );
+    // These are all visible at the top level of the C++, so need to be
+    // unique across every include_cpp! in the build.
+    let flat_name = context_callback.function.replace("::", "_");
+    ContextCallbackNames {
+        closure_fn,
+        cpp_register: config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_register")),
+        cpp_unregister: context_callback
+            .unregister
+            .as_ref()
+            .map(|_| config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_unregister"))),
+        cpp_trampoline: config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_trampoline")),
+        call_fn: config.uniquify_name_per_mod(&format!("{flat_name}_autocxx_call")),
+    }
+}
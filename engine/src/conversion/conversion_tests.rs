@@ -11,7 +11,10 @@ use autocxx_parser::UnsafePolicy;
 use syn::parse_quote;
 use syn::ItemMod;
 
-use crate::{CodegenOptions, UnindexedParseCallbackResults};
+use crate::{
+    declaring_headers::DeclaringHeaders, msvc_declarations::MsvcDeclarations,
+    time_report::PhaseTimer, CodegenOptions, UnindexedParseCallbackResults,
+};
 
 use super::BridgeConverter;
 
@@ -37,6 +40,9 @@ fn do_test(input: ItemMod) {
         inclusions,
         &CodegenOptions::default(),
         "",
+        &MsvcDeclarations::default(),
+        &DeclaringHeaders::default(),
+        &mut PhaseTimer::new(),
     )
     .unwrap();
 }
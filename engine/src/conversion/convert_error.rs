@@ -6,6 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use autocxx_parser::{ConversionErrorPolicy, IncludeCppConfig};
 use indexmap::set::IndexSet as HashSet;
 
 use crate::minisyn::Ident;
@@ -29,6 +30,28 @@ pub enum ConvertError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     Rust(LocatedConvertErrorFromRust),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Directive(LocatedConvertErrorFromCpp),
+}
+
+impl ConvertError {
+    /// Report that a directive named something which doesn't exist: an
+    /// error, unless `conversion_errors!(lenient)` asks for just a warning,
+    /// in which case it's added to `warnings` for the builder to show.
+    pub(crate) fn for_directive_miss(
+        self,
+        config: &IncludeCppConfig,
+        warnings: &mut Vec<ConvertError>,
+    ) -> Result<(), Self> {
+        match config.conversion_error_policy() {
+            ConversionErrorPolicy::Lenient => {
+                warnings.push(self);
+                Ok(())
+            }
+            _ => Err(self),
+        }
+    }
 }
 
 /// Errors that can occur during conversion which are detected from some C++
@@ -39,6 +62,16 @@ pub enum ConvertError {
 pub enum ConvertErrorFromCpp {
     #[error("An item was requested using 'generate_pod' which was not safe to hold by value in Rust. {0}")]
     UnsafePodType(String),
+    #[error("generate_pod! was requested for {0}, which can't be POD: {1}. Use allow_pod_downgrade! if it should be generated as non-POD instead.")]
+    PodBlocked(String, String),
+    #[error("{} is thread_affine!, but is POD, so Rust code could freely copy it to another thread. Use generate! rather than generate_pod! for this type.", .0.to_cpp_name())]
+    ThreadAffinePod(QualifiedName),
+    #[error("{} can't be a shared_struct!: {1}", .0.to_cpp_name())]
+    UnsupportedSharedStruct(QualifiedName, String),
+    #[error("{} has a member_fn_field!, but is POD, and Rust doesn't know how large a member function pointer is. Use generate! rather than generate_pod! for this type.", .0.to_cpp_name())]
+    MemberFnFieldInPod(QualifiedName),
+    #[error("Name transform '{0}' chose the Rust name '{1}', which isn't a valid identifier: {2}")]
+    InvalidTransformedName(String, String, InvalidIdentError),
     #[error("Bindgen generated some unexpected code in a foreign mod section. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")]
     UnexpectedForeignItem,
     #[error("Bindgen generated some unexpected code in an inner namespace mod. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")]
@@ -75,12 +108,28 @@ pub enum ConvertErrorFromCpp {
     TemplatedTypeContainingNonPathArg(QualifiedName),
     #[error("Pointer pointed to an array, which is not yet supported")]
     InvalidArrayPointee,
+    #[error("Reference to an array of {0}, but only arrays of primitives or generate_pod! types are supported")]
+    ArrayOfNonPodType(String),
+    #[error("iterator_range! needs arguments {0} and {1} to be pointers to the same primitive or generate_pod! type, and not to belong to a virtual function")]
+    UnsupportedIteratorRange(usize, usize),
+    #[error("iterator_range! only supports pairs of pointers, but argument {0} is {1}. Iterator classes such as std::vector<T>::iterator, and references to containers, aren't supported")]
+    IteratorRangeNotPointer(usize, String),
+    #[error("iterator_range! was given for {0}, but it couldn't be generated: {1}")]
+    IteratorRangeNotGenerated(String, String),
+    #[error("std::byte is only supported by value, by pointer or by reference, or in a container passed by const reference, but this uses {0}")]
+    UnsupportedStdByteType(String),
+    #[error(
+        "std::pmr containers are only supported by value or by const reference, but this uses {0}"
+    )]
+    UnsupportedPmrType(String),
     #[error("Pointer pointed to another pointer, which is not yet supported")]
     InvalidPointerPointee,
     #[error("Pointer pointed to something unsupported (autocxx only supports pointers to named types): {0}")]
     InvalidPointee(String),
     #[error("The 'generate' or 'generate_pod' directive for '{0}' did not result in any code being generated. Perhaps this was mis-spelled or you didn't qualify the name with any namespaces? Otherwise please report a bug.")]
     DidNotGenerateAnything(String),
+    #[error("generate_member! names {0}, but there's no such member function. Perhaps the header has changed?")]
+    MemberNotFound(String),
     #[error("Found an attempt at using a forward declaration ({}) inside a templated cxx type such as UniquePtr or CxxVector. If the forward declaration is a typedef, perhaps autocxx wasn't sure whether or not it involved a forward declaration. If you're sure it didn't, then you may be able to solve this by using instantiable!.", .0.to_cpp_name())]
     TypeContainingForwardDeclaration(QualifiedName),
     #[error("Found an attempt at using a type marked as blocked! ({})", .0.to_cpp_name())]
@@ -145,12 +194,58 @@ pub enum ConvertErrorFromCpp {
     MethodInAnonymousNamespace,
     #[error("We're unable to make a concrete version of this template, because we found an error handling the template.")]
     ConcreteVersionOfIgnoredTemplate,
+    #[error("This function is in an anonymous namespace, so has internal linkage and can't be linked from Rust. If it's defined inline in a header, internal_linkage_shims!() can call it from a generated C++ wrapper instead.")]
+    FunctionInAnonymousNamespace,
     #[error("This is a typedef to a type in an anonymous namespace, not currently supported.")]
     TypedefToTypeInAnonymousNamespace,
     #[error("This type refers to a generic type parameter of an outer type, which is not yet supported.")]
     ReferringToGenericTypeParam,
-    #[error("This forward declaration was nested within another struct/class. autocxx is unable to represent inner types if they are forward declarations.")]
-    ForwardDeclaredNestedType,
+    #[error(
+        "newtype! and opaque_handle! can only be used on typedefs of integer types, but this typedef refers to {0}."
+    )]
+    NewtypeOfNonIntegerType(String),
+    #[error("export_c! was requested for {0}, but its parameters or return type can't be represented in C.")]
+    NotCExportable(String),
+    #[error("generate_async! was requested for {0}, but only free functions which take and return owned values, with no conversions needed in Rust, can be called on another thread.")]
+    NotAsyncCompatible(String),
+    #[error("export_c! can't be used for overloaded functions, since each overload would need to be exported as {0}.")]
+    OverloadedCExport(String),
+    #[error("The custom analysis pass '{0}' chose not to generate this item: {1}")]
+    DroppedByAnalysisPass(String, String),
+    #[error("{0} was generated, but an assert_not_generated! directive says it must not be. Check whether a header change has made it reachable from something on the allowlist.")]
+    AssertedNotGenerated(String),
+    #[error("conversion_errors!(strict) was given, but these requested items couldn't be generated:\n{0}")]
+    RequestedItemsNotGenerated(String),
+    #[error("subclass! was used with superclass {0}, which has a public destructor that isn't virtual, so C++ deleting the subclass through a pointer to {0} would be undefined behavior. Make the destructor virtual, or add leak or downcast to the subclass! directive.")]
+    SubclassOfNonVirtualDestructor(String),
+    #[error("constructor_from! was requested for {0}, but it doesn't return a std::unique_ptr or pointer to its own type.")]
+    NotAFactory(String),
+    #[error("name_constructor! names the constructor {0}, but there's no constructor with that signature. Perhaps the header has changed?")]
+    ConstructorNotFound(String),
+    #[error("force_inline! was given for {0}, but there's no such function or method. Perhaps the header has changed?")]
+    ForceInlineNotFound(String),
+    #[error("deprecated_alias! names {0}, but nothing of that name was generated. Items are named by their Rust names, as in AUTOCXX_RENAMED_ITEMS. Perhaps the header has changed?")]
+    DeprecatedAliasNotFound(String),
+    #[error("member_fn_field! was given for {0}, but {1} wasn't generated. Use generate! for it.")]
+    MemberFnFieldTypeNotGenerated(String, String),
+    #[error("pod_default! was requested for {0}, but it isn't POD. Use generate_pod! for it, or construct it using new() instead.")]
+    PodDefaultNotPod(String),
+    #[error("covariant_return! was requested for {0}, but it doesn't return a plain pointer or reference, so the return type can't be made covariant.")]
+    CovariantReturnNotPointer(String),
+    #[error("covariant_return! names {0}, but there's no such method or type. Perhaps the header has changed?")]
+    CovariantReturnNotFound(String),
+    #[error("batch_methods! names {0}, which must be a single non-static method (not overloaded) which returns nothing and takes only numbers or bools.")]
+    BatchMethodUnsuitable(String),
+    #[error("returns_lifetime_of_arg! was requested for {0}, but it doesn't return a reference or a view type.")]
+    NothingBorrowsLifetimeArg(String),
+    #[error("returns_lifetime_of_arg! was requested for {0}, but its argument {1} isn't a reference, or isn't a mutable reference while the return value is.")]
+    LifetimeArgNotReference(String, usize),
+    #[error("copy_string_return! was requested for {0}, but it doesn't return a const std::string reference.")]
+    CopyStringReturnNotStringReference(String),
+    #[error("rust_equivalent! was given for {0}, which must be a POD type, either generated by this include_cpp! using generate_pod!, or given by extern_cpp_type!.")]
+    RustEquivalentNotPod(String),
+    #[error("rust_equivalent! was given for {0}, which comes from extern_cpp_type!, so its fields aren't known here. Give from_cpp and to_cpp functions to convert it.")]
+    RustEquivalentNeedsConversions(String),
     #[error("Problem handling function argument {arg}: {err}")]
     Argument {
         arg: String,
@@ -173,6 +268,8 @@ pub enum ConvertErrorFromRust {
     NamespacesNotSupportedForExternFun,
     #[error("extern_rust_function signatures must never reference Self: instead, spell out the type explicitly.")]
     ExplicitSelf,
+    #[error("extern_rust_function can only return a closure which is Send + Sync, such as Box<dyn Fn(u32) -> u32 + Send + Sync>, since C++ may call or destroy the std::function on any thread")]
+    ClosureReturnedToCppNotSendSync,
 }
 
 /// A [`ConvertErrorFromRust`] which also implements [`miette::Diagnostic`] so can be pretty-printed
@@ -197,6 +294,28 @@ impl LocatedConvertErrorFromRust {
     }
 }
 
+/// A [`ConvertErrorFromCpp`] about an item named in a directive, pointing
+/// at that directive in the user's source.
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[error("{err}")]
+pub struct LocatedConvertErrorFromCpp {
+    err: ConvertErrorFromCpp,
+    #[source_code]
+    file: String,
+    #[label("requested here")]
+    span: SourceSpan,
+}
+
+impl LocatedConvertErrorFromCpp {
+    pub(crate) fn new(err: ConvertErrorFromCpp, span: &Span, file: &str) -> Self {
+        Self {
+            err,
+            span: proc_macro_span_to_miette_span(span),
+            file: file.to_string(),
+        }
+    }
+}
+
 /// Ensures that error contexts are always created using the constructors in this
 /// mod, therefore undergoing identifier sanitation.
 #[derive(Clone, Debug)]
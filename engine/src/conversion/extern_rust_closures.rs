@@ -0,0 +1,232 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An `extern_rust_function` may take or return a `Box<dyn Fn(..) -> ..>`,
+//! which C++ sees as a `std::function`. cxx can't pass either, so C++ and
+//! Rust instead exchange the address of a heap allocation holding one.
+//!
+//! C++ calls a function of ours in place of the Rust function. That
+//! allocates each `std::function` argument and passes its address to an
+//! extern "Rust" shim, which gives the Rust function a closure calling
+//! and finally deleting the `std::function` through a pair of C++
+//! functions. If the Rust function returns a closure, the shim boxes it
+//! and returns its address, which C++ wraps in a `std::function` calling
+//! and finally dropping it through a pair of extern "Rust" functions.
+//!
+//! To keep the C++ simple, these functions may otherwise take and return
+//! only numbers and `bool`s, as may the closures themselves. This module
+//! recognizes such signatures, and names the generated items consistently
+//! for the Rust and C++ code generators.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{
+    FnArg, GenericArgument, PathArguments, ReturnType, Signature, Type, TypeParamBound, TypePath,
+};
+
+/// The numbers and `bool`s which may be passed, and the C++ types cxx
+/// gives them.
+static SCALARS: &[(&str, &str)] = &[
+    ("bool", "bool"),
+    ("u8", "std::uint8_t"),
+    ("i8", "std::int8_t"),
+    ("u16", "std::uint16_t"),
+    ("i16", "std::int16_t"),
+    ("u32", "std::uint32_t"),
+    ("i32", "std::int32_t"),
+    ("u64", "std::uint64_t"),
+    ("i64", "std::int64_t"),
+    ("usize", "std::size_t"),
+    ("isize", "rust::isize"),
+    ("f32", "float"),
+    ("f64", "double"),
+];
+
+/// The signature of a `Box<dyn Fn(..) -> ..>`.
+pub(crate) struct BoxedFn {
+    pub(crate) inputs: Vec<Type>,
+    pub(crate) output: Option<Type>,
+    /// Whether this is also `Send`.
+    pub(crate) is_send: bool,
+    /// Whether this is also `Sync`.
+    pub(crate) is_sync: bool,
+}
+
+/// If this is a number or `bool`, which C++ and Rust can pass directly,
+/// its C++ type.
+pub(crate) fn cpp_scalar(ty: &Type) -> Option<&'static str> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    SCALARS
+        .iter()
+        .find(|(rust, _)| path.is_ident(rust))
+        .map(|(_, cpp)| *cpp)
+}
+
+fn is_closure_scalar(ty: &Type) -> bool {
+    cpp_scalar(ty).is_some()
+}
+
+/// If this is a `Box<dyn Fn(..) -> ..>`, optionally also `Send` or
+/// `Sync`, whose parameters and return value are all numbers or `bool`s,
+/// its signature.
+pub(crate) fn as_boxed_fn(ty: &Type) -> Option<BoxedFn> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let [segment] = path.segments.iter().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let [GenericArgument::Type(Type::TraitObject(trait_object))] =
+        args.args.iter().collect::<Vec<_>>()[..]
+    else {
+        return None;
+    };
+    if segment.ident != "Box" {
+        return None;
+    }
+    let mut boxed_fn = None;
+    let mut is_send = false;
+    let mut is_sync = false;
+    for bound in &trait_object.bounds {
+        let TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last()?;
+        match &segment.arguments {
+            PathArguments::Parenthesized(args) if segment.ident == "Fn" && boxed_fn.is_none() => {
+                let output = match &args.output {
+                    ReturnType::Default => None,
+                    ReturnType::Type(_, ty) => match ty.as_ref() {
+                        Type::Tuple(tuple) if tuple.elems.is_empty() => None,
+                        ty => Some(ty.clone()),
+                    },
+                };
+                boxed_fn = Some(BoxedFn {
+                    inputs: args.inputs.iter().cloned().collect(),
+                    output,
+                    is_send: false,
+                    is_sync: false,
+                });
+            }
+            PathArguments::None if segment.ident == "Send" => is_send = true,
+            PathArguments::None if segment.ident == "Sync" => is_sync = true,
+            _ => return None,
+        }
+    }
+    boxed_fn
+        .filter(|boxed_fn| {
+            boxed_fn.inputs.iter().all(is_closure_scalar)
+                && boxed_fn.output.as_ref().map_or(true, is_closure_scalar)
+        })
+        .map(|boxed_fn| BoxedFn {
+            is_send,
+            is_sync,
+            ..boxed_fn
+        })
+}
+
+/// The type of each parameter, then the return type, if any.
+fn sig_types(sig: &Signature) -> impl Iterator<Item = &Type> {
+    sig.inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(pt) => Some(pt.ty.as_ref()),
+            FnArg::Receiver(_) => None,
+        })
+        .chain(match &sig.output {
+            ReturnType::Type(_, ty) => Some(ty.as_ref()),
+            ReturnType::Default => None,
+        })
+}
+
+/// Whether this `extern_rust_function` takes or returns any closures.
+pub(crate) fn has_boxed_fns(sig: &Signature) -> bool {
+    sig_types(sig).any(|ty| as_boxed_fn(ty).is_some())
+}
+
+/// Whether we can generate the C++ for this function: every parameter and
+/// the return type, other than its closures, must be a number or `bool`.
+/// Closures passed from C++ can't be `Send` or `Sync`, since we know
+/// nothing of whether the `std::function` may be used from other threads.
+/// A closure returned to C++ is checked separately by
+/// [`returns_thread_unsafe_closure`].
+pub(crate) fn is_supported_closure_signature(sig: &Signature) -> bool {
+    let params_ok = sig.inputs.iter().all(|input| match input {
+        FnArg::Typed(pt) => match as_boxed_fn(&pt.ty) {
+            Some(boxed_fn) => !boxed_fn.is_send && !boxed_fn.is_sync,
+            None => is_closure_scalar(&pt.ty),
+        },
+        FnArg::Receiver(_) => false,
+    });
+    let ret_ok = match &sig.output {
+        ReturnType::Type(_, ty) => as_boxed_fn(ty).is_some() || is_closure_scalar(ty),
+        ReturnType::Default => true,
+    };
+    params_ok && ret_ok
+}
+
+/// Whether this function returns a closure which isn't both `Send` and
+/// `Sync`. C++ may copy the resulting `std::function`, then call or
+/// destroy the copies on any thread, so it must be both.
+pub(crate) fn returns_thread_unsafe_closure(sig: &Signature) -> bool {
+    match &sig.output {
+        ReturnType::Type(_, ty) => {
+            as_boxed_fn(ty).is_some_and(|boxed_fn| !boxed_fn.is_send || !boxed_fn.is_sync)
+        }
+        ReturnType::Default => false,
+    }
+}
+
+pub(crate) struct ExternRustClosureNames {
+    /// The extern "Rust" function which C++ calls in place of the Rust
+    /// function itself.
+    pub(crate) shim: String,
+    /// For each parameter which is a closure, the C++ functions which call
+    /// and delete the `std::function` passed for it.
+    pub(crate) param_fns: Vec<Option<(String, String)>>,
+    /// If the return value is a closure, the extern "Rust" functions which
+    /// call and drop it.
+    pub(crate) ret_fns: Option<(String, String)>,
+}
+
+pub(crate) fn extern_rust_closure_names(
+    sig: &Signature,
+    config: &IncludeCppConfig,
+) -> ExternRustClosureNames {
+    // These are all visible at the top level of the C++, so need to be
+    // unique across every include_cpp! in the build.
+    let name = sig.ident.to_string();
+    let param_fns = sig
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| match input {
+            FnArg::Typed(pt) if as_boxed_fn(&pt.ty).is_some() => Some((
+                config.uniquify_name_per_mod(&format!("{name}_autocxx_invoke{index}")),
+                config.uniquify_name_per_mod(&format!("{name}_autocxx_delete{index}")),
+            )),
+            _ => None,
+        })
+        .collect();
+    let ret_fns = match &sig.output {
+        ReturnType::Type(_, ty) if as_boxed_fn(ty).is_some() => Some((
+            config.uniquify_name_per_mod(&format!("{name}_autocxx_call")),
+            config.uniquify_name_per_mod(&format!("{name}_autocxx_drop")),
+        )),
+        _ => None,
+    };
+    ExternRustClosureNames {
+        shim: config.uniquify_name_per_mod(&format!("{name}_autocxx_shim")),
+        param_fns,
+        ret_fns,
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Besides the wrappers for individual functions, some features need
+//! C++ shims which are found by looking across all the APIs: upcasts,
+//! bulk copies to and from `std::vector`, and so on. We find them all
+//! once analysis is complete, so that the C++ and Rust code generators
+//! agree on what to generate and what it's called.
+
+use autocxx_parser::IncludeCppConfig;
+use itertools::Itertools;
+
+use crate::types::QualifiedName;
+
+use super::{
+    analysis::fun::FnPhase,
+    apivec::ApiVec,
+    batches::{find_batches, Batch},
+    intrusive_ptrs::{find_intrusive_ptr_types, IntrusivePtrType},
+    member_fn_fields::{find_member_fn_fields, MemberFnFieldShims},
+    pod_vectors::{find_pod_vector_elements, PodVectorElement},
+    protobuf_messages::{find_protobuf_messages, ProtobufMessage},
+    upcasts::{find_upcasts, Upcast},
+    vector_slices::{find_vector_slice_methods, VectorSliceMethod},
+    ConvertErrorFromCpp,
+};
+
+pub(crate) struct ExtraShims<'a> {
+    pub(crate) upcasts: Vec<Upcast>,
+    pub(crate) pod_vector_elements: Vec<PodVectorElement>,
+    pub(crate) intrusive_ptr_types: Vec<IntrusivePtrType>,
+    pub(crate) protobuf_messages: Vec<ProtobufMessage>,
+    pub(crate) batches: Vec<Batch>,
+    pub(crate) member_fn_fields: Vec<MemberFnFieldShims<'a>>,
+    pub(crate) vector_slice_methods: Vec<VectorSliceMethod>,
+}
+
+impl<'a> ExtraShims<'a> {
+    pub(crate) fn find(
+        apis: &ApiVec<FnPhase>,
+        config: &'a IncludeCppConfig,
+    ) -> Result<Self, ConvertErrorFromCpp> {
+        let pod_vector_elements = find_pod_vector_elements(apis, config);
+        let vector_slice_methods = find_vector_slice_methods(apis, config, &pod_vector_elements);
+        Ok(Self {
+            upcasts: find_upcasts(apis, config),
+            pod_vector_elements,
+            intrusive_ptr_types: find_intrusive_ptr_types(apis, config),
+            protobuf_messages: find_protobuf_messages(apis, config),
+            batches: find_batches(apis, config)?,
+            member_fn_fields: find_member_fn_fields(apis, config),
+            vector_slice_methods,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.upcasts.is_empty()
+            && self.pod_vector_elements.is_empty()
+            && self.intrusive_ptr_types.is_empty()
+            && self.protobuf_messages.is_empty()
+            && self.batches.is_empty()
+            && self.member_fn_fields.is_empty()
+            && self.vector_slice_methods.is_empty()
+    }
+}
+
+/// Name a shim for something to do with the given type. The shims are
+/// visible at the top level of the C++, so their names need to be unique
+/// across every `include_cpp!` in the build.
+pub(crate) fn shim_name(config: &IncludeCppConfig, ty: &QualifiedName, suffix: &str) -> String {
+    config.uniquify_name_per_mod(&format!("{}_{suffix}", ty.segment_iter().join("_")))
+}
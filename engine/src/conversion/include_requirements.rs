@@ -0,0 +1,121 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indexmap::map::IndexMap as HashMap;
+
+use crate::{
+    declaring_headers::DeclaringHeaders,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    analysis::fun::{FnKind, FnPhase},
+    api::Api,
+    apivec::ApiVec,
+};
+
+/// The headers which C++ code must `#include` in order to use a
+/// given generated item.
+#[derive(Debug, Clone)]
+pub struct IncludeRequirement {
+    /// The path to the item within the generated mod, e.g. `ns::Foo`
+    /// or `ns::Foo::method`.
+    pub rust_path: String,
+    /// The headers required, in the order they should be included.
+    pub headers: Vec<String>,
+    /// Whether this item is implemented in Rust, i.e. part of the
+    /// surface which C++ can call into.
+    pub extern_rust: bool,
+}
+
+/// Work out which headers each generated item requires.
+///
+/// Each C++ item requires the header which declares it, if we looked for
+/// it, or otherwise all the `#include`s in the `include_cpp!` macro. A
+/// method requires the header declaring its type. Either way, it also
+/// requires the autocxx-generated header if we had to generate a C++
+/// wrapper for the item. Items implemented in Rust are declared in the
+/// header generated by cxx.
+pub(crate) fn find_include_requirements(
+    apis: &ApiVec<FnPhase>,
+    include_list: &[String],
+    declaring_headers: &DeclaringHeaders,
+    autocxxgen_header_name: Option<&str>,
+    cxxgen_header_name: &str,
+) -> Vec<IncludeRequirement> {
+    let cpp_names: HashMap<_, _> = apis
+        .iter()
+        .map(|api| (api.name(), api.name_info().qualified_cpp_name()))
+        .collect();
+    let cpp_item = |rust_path: String, cpp_name: Option<&str>, needs_cpp_codegen: bool| {
+        let declared_in = cpp_name
+            .and_then(|cpp_name| declaring_headers.header_for(cpp_name))
+            .map(|header| vec![header.to_string()])
+            .unwrap_or_else(|| include_list.to_vec());
+        IncludeRequirement {
+            rust_path,
+            headers: declared_in
+                .into_iter()
+                .chain(
+                    autocxxgen_header_name
+                        .filter(|_| needs_cpp_codegen)
+                        .map(str::to_string),
+                )
+                .collect(),
+            extern_rust: false,
+        }
+    };
+    let rust_item = |rust_path: String| IncludeRequirement {
+        rust_path,
+        headers: vec![cxxgen_header_name.to_string()],
+        extern_rust: true,
+    };
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. }
+            | Api::Enum { name, .. }
+            | Api::Typedef { name, .. }
+            | Api::Const { name, .. }
+            | Api::ForwardDeclaration { name, .. }
+            | Api::OpaqueTypedef { name, .. } => Some(cpp_item(
+                name.name.to_string(),
+                Some(&name.qualified_cpp_name()),
+                false,
+            )),
+            Api::Function { name, analysis, .. }
+                if analysis.ignore_reason.is_ok() && analysis.externally_callable =>
+            {
+                let (rust_path, cpp_name) = match &analysis.kind {
+                    FnKind::Function => (
+                        QualifiedName::new(
+                            name.name.get_namespace(),
+                            make_ident(&analysis.rust_name),
+                        )
+                        .to_string(),
+                        Some(name.qualified_cpp_name()),
+                    ),
+                    FnKind::Method { impl_for, .. } => (
+                        format!("{impl_for}::{}", analysis.rust_name),
+                        cpp_names.get(impl_for).cloned(),
+                    ),
+                    FnKind::TraitMethod { .. } => return None,
+                };
+                Some(cpp_item(
+                    rust_path,
+                    cpp_name.as_deref(),
+                    api.needs_cpp_codegen(),
+                ))
+            }
+            Api::RustType { name, .. } | Api::RustFn { name, .. } => {
+                Some(rust_item(name.name.to_string()))
+            }
+            Api::Subclass { name, .. } => Some(rust_item(name.0.name.to_string())),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,20 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For an `init_function!`, we generate a C++ wrapper which calls the
+//! initialization function and discards anything it returns, and a Rust
+//! `ensure_initialized()` which calls that wrapper at most once.
+
+use autocxx_parser::IncludeCppConfig;
+
+/// The name of the C++ wrapper which calls the initialization function.
+/// It's visible at the top level of the C++, so must be unique across
+/// every include_cpp! in the build.
+pub(crate) fn init_function_wrapper_name(config: &IncludeCppConfig) -> String {
+    config.uniquify_name_per_mod("autocxx_init_function")
+}
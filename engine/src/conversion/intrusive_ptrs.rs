@@ -0,0 +1,53 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For each type listed in `intrusive_ptr!`, we generate a pair of C++
+//! functions which call its methods to increment and decrement its
+//! reference count, given a pointer to it. These back
+//! `autocxx::IntrusiveRefCounted`, and thus `autocxx::IntrusivePtr`.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::types::QualifiedName;
+
+use super::{analysis::fun::FnPhase, api::Api, apivec::ApiVec, extra_shims::shim_name};
+
+pub(crate) struct IntrusivePtrType {
+    pub(crate) ty: QualifiedName,
+    /// The C++ method which increments the reference count.
+    pub(crate) add_ref: String,
+    /// The C++ method which decrements the reference count.
+    pub(crate) release: String,
+    /// The C++ function which calls `add_ref`.
+    pub(crate) add_ref_shim: String,
+    /// The C++ function which calls `release`.
+    pub(crate) release_shim: String,
+}
+
+pub(crate) fn find_intrusive_ptr_types(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<IntrusivePtrType> {
+    // The type may have failed to generate, in which case we can't refer
+    // to it.
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } => config
+                .get_intrusive_ptr_methods(&name.name.to_cpp_name())
+                .map(|methods| (&name.name, methods)),
+            _ => None,
+        })
+        .map(|(ty, (add_ref, release))| IntrusivePtrType {
+            ty: ty.clone(),
+            add_ref: add_ref.to_string(),
+            release: release.to_string(),
+            add_ref_shim: shim_name(config, ty, "add_ref_autocxx_wrapper"),
+            release_shim: shim_name(config, ty, "release_autocxx_wrapper"),
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rust can't represent a C++ pointer to member function, whose size
+//! depends on the C++ ABI. For each `member_fn_field!` we generate a C++
+//! struct holding one, which Rust treats as an opaque type, plus C++
+//! functions which copy the field into and out of that storage, clear it,
+//! check whether it's null, and call it on an object. For each of the
+//! directive's `targets` we also generate a C++ function which makes
+//! storage pointing to that member function. This module names those
+//! items consistently for the Rust and C++ code generators.
+
+use autocxx_parser::{IncludeCppConfig, MemberFnField};
+
+use crate::types::{make_ident, QualifiedName};
+
+use super::{
+    analysis::fun::FnPhase,
+    api::Api,
+    apivec::ApiVec,
+    convert_error::{ConvertErrorFromCpp, LocatedConvertErrorFromCpp},
+    extra_shims::shim_name,
+    ConvertError,
+};
+
+pub(crate) struct MemberFnFieldShims<'a> {
+    pub(crate) field: &'a MemberFnField,
+    /// The struct containing the field.
+    pub(crate) table: QualifiedName,
+    /// The class whose member function it points to.
+    pub(crate) class: QualifiedName,
+    /// The opaque C++ type holding a copy of the field.
+    pub(crate) storage: String,
+    /// The name of the opaque type within the struct's namespace in the
+    /// output mod, e.g. `ns::Callbacks_on_event`.
+    pub(crate) storage_alias: QualifiedName,
+    /// The C++ function which copies the field into new storage.
+    pub(crate) get: String,
+    /// The C++ function which copies storage into the field.
+    pub(crate) set: String,
+    /// The C++ function which sets the field to null.
+    pub(crate) clear: String,
+    /// The C++ function which returns whether the field is non-null.
+    pub(crate) has: String,
+    /// The C++ function which calls the field on an object.
+    pub(crate) call: String,
+    /// Each member function which Rust can point the field at, with the
+    /// C++ function which makes storage pointing to it.
+    pub(crate) targets: Vec<(&'a str, String)>,
+}
+
+fn find_struct(apis: &ApiVec<FnPhase>, cpp_name: &str) -> Option<QualifiedName> {
+    apis.iter().find_map(|api| match api {
+        Api::Struct { name, .. } if name.name.to_cpp_name() == cpp_name => Some(name.name.clone()),
+        _ => None,
+    })
+}
+
+/// Report any `member_fn_field!` whose struct or class wasn't generated.
+pub(crate) fn check_member_fn_fields(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+    source_file_contents: &str,
+    warnings: &mut Vec<ConvertError>,
+) -> Result<(), ConvertError> {
+    for field in config.get_member_fn_fields() {
+        let path = format!("{}::{}", field.table, field.field);
+        for ty in [&field.table, &field.class] {
+            if find_struct(apis, ty).is_some() {
+                continue;
+            }
+            let err = ConvertErrorFromCpp::MemberFnFieldTypeNotGenerated(path.clone(), ty.clone());
+            match config.get_directive_span(&path) {
+                Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                    err,
+                    &span,
+                    source_file_contents,
+                )),
+                None => ConvertError::Cpp(err),
+            }
+            .for_directive_miss(config, warnings)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn find_member_fn_fields<'a>(
+    apis: &ApiVec<FnPhase>,
+    config: &'a IncludeCppConfig,
+) -> Vec<MemberFnFieldShims<'a>> {
+    // Either type may have failed to generate, in which case we can't
+    // refer to it. check_member_fn_fields has already reported that.
+    config
+        .get_member_fn_fields()
+        .filter_map(|field| {
+            let table = find_struct(apis, &field.table)?;
+            let class = find_struct(apis, &field.class)?;
+            let name =
+                |suffix: &str| shim_name(config, &table, &format!("{}_{suffix}", field.field));
+            let storage_alias = QualifiedName::new(
+                table.get_namespace(),
+                make_ident(format!("{}_{}", table.get_final_item(), field.field)),
+            );
+            Some(MemberFnFieldShims {
+                field,
+                storage: name("autocxx_member_fn"),
+                get: name("autocxx_get"),
+                set: name("autocxx_set"),
+                clear: name("autocxx_clear"),
+                has: name("autocxx_has"),
+                call: name("autocxx_call"),
+                targets: field
+                    .targets
+                    .iter()
+                    .map(|target| (target.as_str(), name(&format!("autocxx_make_{target}"))))
+                    .collect(),
+                storage_alias,
+                table,
+                class,
+            })
+        })
+        .collect()
+}
@@ -9,16 +9,32 @@
 mod analysis;
 mod api;
 mod apivec;
+mod batches;
 mod codegen_cpp;
 mod codegen_rs;
+mod completion_futures;
+mod container_accessors;
+mod context_callbacks;
 #[cfg(test)]
 mod conversion_tests;
 mod convert_error;
 mod doc_attr;
 mod error_reporter;
+mod extern_rust_closures;
+mod extra_shims;
+mod include_requirements;
+mod init_function;
+mod intrusive_ptrs;
+mod member_fn_fields;
+mod ostream_writers;
 mod parse;
+mod pod_vectors;
+mod protobuf_messages;
+mod string_values;
 mod type_helpers;
+mod upcasts;
 mod utilities;
+mod vector_slices;
 
 pub(crate) use super::parse_callbacks::CppOriginalName;
 use analysis::fun::FnAnalyzer;
@@ -27,25 +43,48 @@ use autocxx_parser::IncludeCppConfig;
 pub(crate) use codegen_cpp::CppCodeGenerator;
 pub(crate) use convert_error::ConvertError;
 use convert_error::{ConvertErrorFromCpp, ConvertErrorWithContext, ErrorContext};
+use extra_shims::ExtraShims;
+pub use include_requirements::IncludeRequirement;
 use itertools::Itertools;
+use member_fn_fields::check_member_fn_fields;
+use std::cell::Cell;
+use std::path::PathBuf;
 use syn::{Item, ItemMod};
 
 use crate::{
-    types::QualifiedName, CodegenOptions, CppFilePair, ParseCallbackResults, UnsafePolicy,
+    declaring_headers::DeclaringHeaders, msvc_declarations::MsvcDeclarations,
+    time_report::PhaseTimer, types::QualifiedName, CodegenOptions, CppFilePair,
+    ParseCallbackResults, UnsafePolicy,
 };
 
 use self::{
     analysis::{
         abstract_types::{discard_ignored_functions, mark_types_abstract},
         allocators::create_alloc_and_frees,
+        associate_adl_functions,
         casts::add_casts,
         check_names,
         constructor_deps::decorate_types_with_constructor_deps,
+        constructor_names::check_constructor_names,
+        conversion_operators::add_conversion_operator_methods,
+        covariant_returns::check_covariant_returns,
+        deprecated_aliases::check_deprecated_aliases,
+        error_types::add_error_message_methods,
+        force_inline::check_force_inline,
+        fun::iterator_ranges::check_iterator_ranges,
         gc::filter_apis_by_following_edges_from_allowlist,
+        not_generated::{
+            check_not_generated, check_requested_items_generated, check_rust_equivalents,
+        },
+        object_identity::add_object_identity_methods,
         pod::analyze_pod_apis,
+        pod_defaults::add_value_initializers,
         remove_ignored::filter_apis_by_ignored_dependents,
-        replace_hopeless_typedef_targets,
+        replace_hopeless_typedef_targets, run_custom_passes,
+        tagged_unions::add_tagged_union_accessors,
         tdef::convert_typedef_targets,
+        type_names::add_type_name_methods,
+        virtual_destructors::{check_subclass_destructors, mark_virtual_destructors},
     },
     api::AnalysisPhase,
     apivec::ApiVec,
@@ -70,6 +109,8 @@ const LOG_APIS: bool = true;
 pub(crate) struct BridgeConverter<'a> {
     include_list: &'a [String],
     config: &'a IncludeCppConfig,
+    /// How many times we've called `dump_apis`.
+    phases_dumped: Cell<usize>,
 }
 
 /// C++ and Rust code generation output.
@@ -77,6 +118,8 @@ pub(crate) struct CodegenResults {
     pub(crate) rs: Vec<Item>,
     pub(crate) cpp: Option<CppFilePair>,
     pub(crate) cxxgen_header_name: String,
+    pub(crate) include_requirements: Vec<IncludeRequirement>,
+    pub(crate) warnings: Vec<ConvertError>,
 }
 
 impl<'a> BridgeConverter<'a> {
@@ -84,10 +127,11 @@ impl<'a> BridgeConverter<'a> {
         Self {
             include_list,
             config,
+            phases_dumped: Cell::new(0),
         }
     }
 
-    fn dump_apis<T: AnalysisPhase>(label: &str, apis: &ApiVec<T>) {
+    fn dump_apis<T: AnalysisPhase>(&self, label: &str, apis: &ApiVec<T>) {
         if LOG_APIS {
             log::info!(
                 "##### APIs after {}:\n{}",
@@ -98,6 +142,35 @@ impl<'a> BridgeConverter<'a> {
                     .join("\n")
             )
         }
+        let phase = self.phases_dumped.get();
+        self.phases_dumped.set(phase + 1);
+        if let Ok(dir) = std::env::var("AUTOCXX_DUMP_PHASES") {
+            // Number the files so that a directory listing shows the phases
+            // in the order they ran.
+            let path = PathBuf::from(dir).join(format!(
+                "{}-{:02}-{}.json",
+                self.config.get_mod_name(),
+                phase,
+                label.replace(' ', "_")
+            ));
+            let json = serde_json::json!({
+                "phase": label,
+                "apis": apis
+                    .iter()
+                    .map(|api| serde_json::json!({
+                        "name": api.name().to_cpp_name(),
+                        "kind": api.kind_name(),
+                        "details": format!("{api:?}"),
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            if let Err(err) = std::fs::create_dir_all(path.parent().unwrap())
+                .and_then(|_| std::fs::File::create(&path))
+                .and_then(|f| serde_json::to_writer_pretty(f, &json).map_err(Into::into))
+            {
+                log::warn!("Unable to dump APIs to {}: {}", path.display(), err);
+            }
+        }
     }
 
     /// Convert a TokenStream of bindgen-generated bindings to a form
@@ -106,6 +179,7 @@ impl<'a> BridgeConverter<'a> {
     /// This is really the heart of autocxx. It parses the output of `bindgen`
     /// (although really by "parse" we mean to interpret the structures already built
     /// up by the `syn` crate).
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn convert(
         &self,
         bindgen_mod: ItemMod,
@@ -114,21 +188,29 @@ impl<'a> BridgeConverter<'a> {
         inclusions: String,
         codegen_options: &CodegenOptions,
         source_file_contents: &str,
+        msvc_declarations: &MsvcDeclarations,
+        declaring_headers: &DeclaringHeaders,
+        timer: &mut PhaseTimer,
     ) -> Result<CodegenResults, ConvertError> {
         match &bindgen_mod.content {
             None => Err(ConvertError::NoContent),
             Some((_, items)) => {
                 // Parse the bindgen mod.
+                // Directive misses which conversion_errors!(lenient) has
+                // downgraded to warnings.
+                let mut warnings = Vec::new();
                 let parser = ParseBindgen::new(self.config, &parse_callback_results);
-                let apis = parser.parse_items(items, source_file_contents)?;
-                Self::dump_apis("parsing", &apis);
+                let apis = parser.parse_items(items, source_file_contents, &mut warnings)?;
+                self.dump_apis("parsing", &apis);
+                timer.record_namespaces(apis.iter().map(|api| api.name()));
+                timer.phase_done("parsing APIs");
                 // Inside parse_results, we now have a list of APIs.
                 // We now enter various analysis phases.
                 // First, convert any typedefs.
                 // "Convert" means replacing bindgen-style type targets
                 // (e.g. root::std::unique_ptr) with cxx-style targets (e.g. UniquePtr).
                 let apis = convert_typedef_targets(self.config, apis, &parse_callback_results);
-                Self::dump_apis("typedefs", &apis);
+                self.dump_apis("typedefs", &apis);
                 // Now analyze which of them can be POD (i.e. trivial, movable, pass-by-value
                 // versus which need to be opaque).
                 // Specifically, let's confirm that the items requested by the user to be
@@ -137,75 +219,147 @@ impl<'a> BridgeConverter<'a> {
                 // the analysis results.
                 let analyzed_apis = analyze_pod_apis(apis, self.config, &parse_callback_results)
                     .map_err(ConvertError::Cpp)?;
-                Self::dump_apis("pod analysis", &analyzed_apis);
+                self.dump_apis("pod analysis", &analyzed_apis);
+                let analyzed_apis = mark_virtual_destructors(analyzed_apis);
+                check_subclass_destructors(&analyzed_apis, self.config)
+                    .map_err(ConvertError::Cpp)?;
+                timer.phase_done("type analysis");
                 let analyzed_apis = replace_hopeless_typedef_targets(self.config, analyzed_apis);
                 let analyzed_apis = add_casts(analyzed_apis);
+                let analyzed_apis = add_tagged_union_accessors(analyzed_apis, self.config);
+                let analyzed_apis = add_conversion_operator_methods(analyzed_apis, self.config);
+                let analyzed_apis = add_type_name_methods(analyzed_apis, self.config);
+                let analyzed_apis = add_object_identity_methods(analyzed_apis, self.config);
+                let analyzed_apis = add_error_message_methods(analyzed_apis, self.config);
+                let analyzed_apis = add_value_initializers(analyzed_apis, self.config)
+                    .map_err(ConvertError::Cpp)?;
                 let analyzed_apis = create_alloc_and_frees(analyzed_apis);
                 // Next, figure out how we materialize different functions.
                 // Some will be simple entries in the cxx::bridge module; others will
                 // require C++ wrapper functions. This is probably the most complex
                 // part of `autocxx`. Again, this returns a new set of `Api`s, but
                 // parameterized by a richer set of metadata.
-                Self::dump_apis("adding casts", &analyzed_apis);
+                self.dump_apis("adding casts", &analyzed_apis);
                 let analyzed_apis = FnAnalyzer::analyze_functions(
                     analyzed_apis,
                     &unsafe_policy,
                     self.config,
                     codegen_options.force_wrapper_gen,
+                    msvc_declarations,
                 );
                 // If any of those functions turned out to be pure virtual, don't attempt
                 // to generate UniquePtr implementations for the type, since it can't
                 // be instantiated.
-                Self::dump_apis("analyze fns", &analyzed_apis);
+                self.dump_apis("analyze fns", &analyzed_apis);
+                check_constructor_names(
+                    &analyzed_apis,
+                    self.config,
+                    source_file_contents,
+                    &mut warnings,
+                )?;
+                check_iterator_ranges(&analyzed_apis, self.config, source_file_contents)?;
+                check_force_inline(
+                    &analyzed_apis,
+                    self.config,
+                    source_file_contents,
+                    &mut warnings,
+                )?;
+                check_deprecated_aliases(
+                    &analyzed_apis,
+                    self.config,
+                    source_file_contents,
+                    &mut warnings,
+                )?;
+                check_covariant_returns(
+                    &analyzed_apis,
+                    self.config,
+                    source_file_contents,
+                    &mut warnings,
+                )?;
+                timer.phase_done("function analysis");
                 let analyzed_apis = mark_types_abstract(analyzed_apis);
-                Self::dump_apis("marking abstract", &analyzed_apis);
+                self.dump_apis("marking abstract", &analyzed_apis);
                 // Annotate structs with a note of any copy/move constructors which
                 // we may want to retain to avoid garbage collecting them later.
                 let analyzed_apis = decorate_types_with_constructor_deps(analyzed_apis);
-                Self::dump_apis("adding constructor deps", &analyzed_apis);
+                self.dump_apis("adding constructor deps", &analyzed_apis);
                 let analyzed_apis = discard_ignored_functions(analyzed_apis);
-                Self::dump_apis("ignoring ignorable fns", &analyzed_apis);
+                self.dump_apis("ignoring ignorable fns", &analyzed_apis);
                 // Remove any APIs whose names are not compatible with cxx.
                 let analyzed_apis = check_names(analyzed_apis);
+                // Give any custom passes registered by the user a chance to
+                // drop or annotate APIs.
+                let analyzed_apis = run_custom_passes(analyzed_apis);
                 // During parsing or subsequent processing we might have encountered
                 // items which we couldn't process due to as-yet-unsupported features.
                 // There might be other items depending on such things. Let's remove them
                 // too.
                 let analyzed_apis = filter_apis_by_ignored_dependents(analyzed_apis);
-                Self::dump_apis("removing ignored dependents", &analyzed_apis);
+                self.dump_apis("removing ignored dependents", &analyzed_apis);
+                // Functions like `swap` which C++ finds by argument-dependent
+                // lookup should also be discoverable as methods on their type.
+                let analyzed_apis = associate_adl_functions(analyzed_apis);
 
                 // We now garbage collect the ones we don't need...
                 let mut analyzed_apis =
                     filter_apis_by_following_edges_from_allowlist(analyzed_apis, self.config);
                 // Determine what variably-sized C types (e.g. int) we need to include
                 analysis::ctypes::append_ctype_information(&mut analyzed_apis);
-                Self::dump_apis("GC", &analyzed_apis);
+                self.dump_apis("GC", &analyzed_apis);
+                // Make sure nothing the user has forbidden has crept in.
+                check_not_generated(&analyzed_apis, self.config).map_err(ConvertError::Cpp)?;
+                check_requested_items_generated(&analyzed_apis, self.config)
+                    .map_err(ConvertError::Cpp)?;
+                check_rust_equivalents(&analyzed_apis, self.config).map_err(ConvertError::Cpp)?;
+                check_member_fn_fields(
+                    &analyzed_apis,
+                    self.config,
+                    source_file_contents,
+                    &mut warnings,
+                )?;
+                timer.phase_done("other analysis");
                 // And finally pass them to the code gen phases, which outputs
                 // code suitable for cxx to consume.
                 let cxxgen_header_name = codegen_options
                     .cpp_codegen_options
                     .cxxgen_header_namer
-                    .name_header();
+                    .name_header(self.config.get_mod_name().to_string());
+                let extra_shims =
+                    ExtraShims::find(&analyzed_apis, self.config).map_err(ConvertError::Cpp)?;
                 let cpp = CppCodeGenerator::generate_cpp_code(
                     inclusions,
                     &analyzed_apis,
                     self.config,
+                    &extra_shims,
                     &codegen_options.cpp_codegen_options,
                     &cxxgen_header_name,
                 )
                 .map_err(ConvertError::Cpp)?;
+                timer.phase_done("C++ codegen");
+                let include_requirements = include_requirements::find_include_requirements(
+                    &analyzed_apis,
+                    self.include_list,
+                    declaring_headers,
+                    cpp.as_ref().map(|file_pair| file_pair.header_name.as_str()),
+                    &cxxgen_header_name,
+                );
                 let rs = RsCodeGenerator::generate_rs_code(
                     analyzed_apis,
                     &unsafe_policy,
                     self.include_list,
                     bindgen_mod,
                     self.config,
+                    &extra_shims,
                     cpp.as_ref().map(|file_pair| file_pair.header_name.clone()),
+                    msvc_declarations,
                 );
+                timer.phase_done("Rust codegen");
                 Ok(CodegenResults {
                     rs,
                     cpp,
                     cxxgen_header_name,
+                    include_requirements,
+                    warnings,
                 })
             }
         }
@@ -0,0 +1,36 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! With `ostream_as_writer!`, each `std::ostream&` parameter is presented
+//! to Rust as a `&mut dyn std::io::Write`. The Rust wrapper passes the
+//! address of the writer, as a `usize`, to the C++ wrapper, which gives the
+//! C++ function a temporary stream built around that address. Its stream
+//! buffer hands each write, and each flush, back to an extern "Rust"
+//! function along with the address. This module names those items
+//! consistently for the Rust and C++ code generators.
+
+use autocxx_parser::IncludeCppConfig;
+
+pub(crate) struct OstreamWriterNames {
+    /// The C++ `std::ostream` subclass which writes to a Rust writer.
+    pub(crate) adapter: String,
+    /// The extern "Rust" function which writes bytes to the writer.
+    pub(crate) write_fn: String,
+    /// The extern "Rust" function which flushes the writer.
+    pub(crate) flush_fn: String,
+}
+
+pub(crate) fn ostream_writer_names(config: &IncludeCppConfig) -> OstreamWriterNames {
+    // These are all visible at the top level of the C++, so need to be
+    // unique across every include_cpp! in the build.
+    OstreamWriterNames {
+        adapter: config.uniquify_name_per_mod("autocxx_ostream_writer"),
+        write_fn: config.uniquify_name_per_mod("autocxx_ostream_writer_write"),
+        flush_fn: config.uniquify_name_per_mod("autocxx_ostream_writer_flush"),
+    }
+}
@@ -14,7 +14,13 @@ use syn::{
 };
 
 use crate::{
-    conversion::convert_error::{ConvertErrorFromRust, LocatedConvertErrorFromRust},
+    conversion::{
+        convert_error::{ConvertErrorFromRust, LocatedConvertErrorFromRust},
+        extern_rust_closures::{
+            as_boxed_fn, has_boxed_fns, is_supported_closure_signature,
+            returns_thread_unsafe_closure,
+        },
+    },
     types::QualifiedName,
 };
 
@@ -32,6 +38,20 @@ pub(super) fn assemble_extern_fun_deps(
     // Also, by doing it ourselves here, we're in a better place to emit
     // meaningful errors about types which can't be supported within
     // extern_rust_fun.
+    if returns_thread_unsafe_closure(sig) {
+        return Err(LocatedConvertErrorFromRust::new(
+            ConvertErrorFromRust::ClosureReturnedToCppNotSendSync,
+            &sig.output.span(),
+            file,
+        ));
+    }
+    if has_boxed_fns(sig) && !is_supported_closure_signature(sig) {
+        return Err(LocatedConvertErrorFromRust::new(
+            ConvertErrorFromRust::UnsupportedTypeForExternFun,
+            &sig.span(),
+            file,
+        ));
+    }
     if let ReturnType::Type(_, ty) = &sig.output {
         add_type_to_deps(ty, &mut deps, file)?;
     }
@@ -57,6 +77,10 @@ fn add_type_to_deps(
     deps: &mut HashSet<QualifiedName>,
     file: &str,
 ) -> Result<(), LocatedConvertErrorFromRust> {
+    if as_boxed_fn(ty).is_some() {
+        // A closure, which refers to no types we need to generate.
+        return Ok(());
+    }
     match ty {
         Type::Reference(TypeReference {
             mutability: Some(_),
@@ -240,6 +264,12 @@ mod tests {
         run_test_expect_fail(parse_quote! { fn function(a: Box<A, B>)});
         run_test_expect_fail(parse_quote! { fn function(a: a::Pin<&mut A>)});
         run_test_expect_fail(parse_quote! { fn function(a: Pin<&A>)});
+        run_test_expect_fail(parse_quote! { fn function(a: Box<dyn Fn(A)>)});
+        run_test_expect_fail(parse_quote! { fn function(a: Box<dyn FnMut(u32)>)});
+        run_test_expect_fail(parse_quote! { fn function(a: Box<dyn Fn(u32) -> A>)});
+        run_test_expect_fail(parse_quote! { fn function(a: A, b: Box<dyn Fn(u32)>)});
+        run_test_expect_fail(parse_quote! { fn function() -> Box<dyn Fn(&u32)>});
+        run_test_expect_fail(parse_quote! { fn function(a: Box<dyn Fn(u32) + Send>)});
         run_test_expect_ok(parse_quote! { fn function(a: A, b: B)}, &["A", "B"]);
         run_test_expect_ok(parse_quote! { fn function(a: Box<A>)}, &["A"]);
         run_test_expect_ok(parse_quote! { fn function(a: Vec<A>)}, &["A"]);
@@ -249,5 +279,10 @@ mod tests {
             parse_quote! { fn function(a: A, b: B) -> Box<C>},
             &["A", "B", "C"],
         );
+        run_test_expect_ok(parse_quote! { fn function(a: Box<dyn Fn(u32) -> f64>)}, &[]);
+        run_test_expect_ok(
+            parse_quote! { fn function(a: i32) -> Box<dyn Fn(bool) + Send>},
+            &[],
+        );
     }
 }
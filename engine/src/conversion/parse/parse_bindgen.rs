@@ -11,16 +11,17 @@ use indexmap::set::IndexSet as HashSet;
 
 use crate::{
     conversion::{
+        analysis::not_generated::function_directive_name,
         api::{Api, ApiName, NullPhase, StructDetails, SubclassName, TypedefKind, UnanalyzedApi},
         apivec::ApiVec,
         check_for_fatal_attrs,
-        convert_error::LocatedConvertErrorFromRust,
+        convert_error::{LocatedConvertErrorFromCpp, LocatedConvertErrorFromRust},
         type_helpers::type_is_reference,
         utilities::generate_utilities,
         ConvertError, ConvertErrorFromCpp,
     },
     known_types, minisyn,
-    types::{Namespace, QualifiedName},
+    types::{make_ident, Namespace, QualifiedName},
     ParseCallbackResults,
 };
 use crate::{
@@ -31,7 +32,9 @@ use crate::{
     types::validate_ident_ok_for_cxx,
 };
 use autocxx_parser::{IncludeCppConfig, RustPath};
-use syn::{parse_quote, Fields, Ident, Item, Type, TypePath, UseTree};
+use syn::{
+    parse_quote, Expr, ExprLit, Fields, Ident, Item, ItemConst, Lit, Stmt, Type, TypePath, UseTree,
+};
 
 use super::parse_foreign_mod::ParseForeignMod;
 
@@ -40,6 +43,7 @@ pub(crate) struct ParseBindgen<'a> {
     config: &'a IncludeCppConfig,
     apis: ApiVec<NullPhase>,
     parse_callback_results: &'a ParseCallbackResults,
+    field_offsets: HashMap<QualifiedName, Vec<(String, usize)>>,
 }
 
 fn api_name(ns: &Namespace, id: Ident, callback_results: &ParseCallbackResults) -> ApiName {
@@ -74,6 +78,7 @@ impl<'a> ParseBindgen<'a> {
             config,
             apis: ApiVec::new(),
             parse_callback_results,
+            field_offsets: HashMap::new(),
         }
     }
 
@@ -83,6 +88,7 @@ impl<'a> ParseBindgen<'a> {
         mut self,
         items: &[Item],
         source_file_contents: &str,
+        warnings: &mut Vec<ConvertError>,
     ) -> Result<ApiVec<NullPhase>, ConvertError> {
         let items = Self::find_items_in_root(items).map_err(ConvertError::Cpp)?;
         if !self.config.exclude_utilities() {
@@ -92,9 +98,10 @@ impl<'a> ParseBindgen<'a> {
             .map_err(ConvertError::Rust)?;
         let root_ns = Namespace::new();
         self.parse_mod_items(items, root_ns);
-        self.confirm_all_generate_directives_obeyed()
-            .map_err(ConvertError::Cpp)?;
+        self.confirm_all_generate_directives_obeyed(source_file_contents, warnings)?;
+        self.confirm_all_generate_member_directives_obeyed(source_file_contents, warnings)?;
         self.replace_extern_cpp_types();
+        self.add_field_offsets();
         Ok(self.apis)
     }
 
@@ -201,7 +208,7 @@ impl<'a> ParseBindgen<'a> {
             });
         }
         self.apis.append(&mut more_apis);
-        mod_converter.finished(&mut self.apis);
+        mod_converter.finished(&mut self.apis, self.config);
     }
 
     fn parse_item(
@@ -228,7 +235,7 @@ impl<'a> ParseBindgen<'a> {
                     // root::string replacing root::std::string
                     return Ok(());
                 }
-                let mut err = check_for_fatal_attrs(self.parse_callback_results, &name.name).err();
+                let err = check_for_fatal_attrs(self.parse_callback_results, &name.name).err();
                 let api = if (ns.is_empty() && self.config.is_rust_type(&s.ident))
                     || known_types().is_known_type(&name.name)
                 {
@@ -242,15 +249,6 @@ impl<'a> ParseBindgen<'a> {
                     // we spot in the previous clause) but instead with an _address field.
                     // So, solely in the case where we're storing up an error about such
                     // a templated type, we'll also treat such cases as forward declarations.
-                    //
-                    // We'll also at this point check for one specific problem with
-                    // forward declarations.
-                    if err.is_none() && name.cpp_name().is_nested() {
-                        err = Some(ConvertErrorWithContext(
-                            ConvertErrorFromCpp::ForwardDeclaredNestedType,
-                            Some(ErrorContext::new_for_item(s.ident.clone().into())),
-                        ));
-                    }
                     Some(UnanalyzedApi::ForwardDeclaration { name, err })
                 } else {
                     let has_rvalue_reference_fields = Self::spot_rvalue_reference_fields(&s.fields);
@@ -259,6 +257,7 @@ impl<'a> ParseBindgen<'a> {
                         details: Box::new(StructDetails {
                             item: s.clone().into(),
                             has_rvalue_reference_fields,
+                            field_offsets: Vec::new(),
                         }),
                         analysis: (),
                     })
@@ -271,8 +270,13 @@ impl<'a> ParseBindgen<'a> {
                 Ok(())
             }
             Item::Enum(e) => {
+                let name = api_name_qualified(ns, e.ident.clone(), self.parse_callback_results)?;
+                if known_types().is_known_type(&name.name) {
+                    // e.g. std::byte, which we replace with u8.
+                    return Ok(());
+                }
                 let api = UnanalyzedApi::Enum {
-                    name: api_name_qualified(ns, e.ident.clone(), self.parse_callback_results)?,
+                    name,
                     item: e.clone().into(),
                 };
                 if !self.config.is_on_blocklist(&api.name().to_cpp_name()) {
@@ -348,6 +352,12 @@ impl<'a> ParseBindgen<'a> {
                 }
                 Ok(())
             }
+            Item::Const(const_item) if const_item.ident == "_" => {
+                // bindgen's layout tests, which we don't emit, but which
+                // tell us where clang put each field.
+                self.record_field_offsets(ns, const_item);
+                Ok(())
+            }
             Item::Const(const_item) => {
                 // Bindgen generates const expressions for nested unnamed enums,
                 // but autcxx will refuse to expand those enums, making these consts
@@ -386,6 +396,73 @@ impl<'a> ParseBindgen<'a> {
         }
     }
 
+    /// bindgen asserts the offset of each field `Foo::x` with an item like
+    /// `["Offset of field: Foo::x"][offset_of!(Foo, x) - 4usize];`
+    fn record_field_offsets(&mut self, ns: &Namespace, const_item: &ItemConst) {
+        let Expr::Block(block) = const_item.expr.as_ref() else {
+            return;
+        };
+        for stmt in &block.block.stmts {
+            let Stmt::Expr(Expr::Index(index), _) = stmt else {
+                continue;
+            };
+            let Expr::Array(label) = index.expr.as_ref() else {
+                continue;
+            };
+            let Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(label),
+                ..
+            })) = label.elems.first()
+            else {
+                continue;
+            };
+            let Expr::Binary(offset) = index.index.as_ref() else {
+                continue;
+            };
+            let Expr::Lit(ExprLit {
+                lit: Lit::Int(offset),
+                ..
+            }) = offset.right.as_ref()
+            else {
+                continue;
+            };
+            let label = label.value();
+            let Some((ty, field)) = label
+                .strip_prefix("Offset of field: ")
+                .and_then(|field| field.split_once("::"))
+            else {
+                continue;
+            };
+            if let Ok(offset) = offset.base10_parse() {
+                self.field_offsets
+                    .entry(QualifiedName::new(ns, make_ident(ty)))
+                    .or_default()
+                    .push((field.to_string(), offset));
+            }
+        }
+    }
+
+    /// bindgen emits layout tests after the struct they test, so we only
+    /// know the offsets once we've seen everything.
+    fn add_field_offsets(&mut self) {
+        let apis = std::mem::replace(&mut self.apis, ApiVec::new());
+        self.apis.extend(apis.into_iter().map(|api| match api {
+            Api::Struct {
+                name,
+                mut details,
+                analysis,
+            } => {
+                details.field_offsets = self.field_offsets.remove(&name.name).unwrap_or_default();
+                Api::Struct {
+                    name,
+                    details,
+                    analysis,
+                }
+            }
+            _ => api,
+        }));
+    }
+
     fn spot_forward_declaration(s: &Fields) -> bool {
         Self::spot_field(s, "_unused")
     }
@@ -404,7 +481,49 @@ impl<'a> ParseBindgen<'a> {
         s.iter().any(|f| type_is_reference(&f.ty, true))
     }
 
-    fn confirm_all_generate_directives_obeyed(&self) -> Result<(), ConvertErrorFromCpp> {
+    /// `generate_member!` directives naming a member function which doesn't
+    /// exist would otherwise leave the type with no member functions at all.
+    fn confirm_all_generate_member_directives_obeyed(
+        &self,
+        source_file_contents: &str,
+        warnings: &mut Vec<ConvertError>,
+    ) -> Result<(), ConvertError> {
+        let member_names: HashSet<_> = self
+            .apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Function { name, fun, .. } if fun.self_ty.is_some() => {
+                    Some(function_directive_name(name, fun))
+                }
+                _ => None,
+            })
+            .collect();
+        for (ty, member) in self.config.get_allowed_members() {
+            let member = format!("{ty}::{member}");
+            if !member_names.contains(&member) {
+                let span = self.config.get_directive_span(&member);
+                let err = ConvertErrorFromCpp::MemberNotFound(member);
+                match span {
+                    Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                        err,
+                        &span,
+                        source_file_contents,
+                    )),
+                    None => ConvertError::Cpp(err),
+                }
+                .for_directive_miss(self.config, warnings)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Where we know which directive asked for a missing item, the error
+    /// points at it.
+    fn confirm_all_generate_directives_obeyed(
+        &self,
+        source_file_contents: &str,
+        warnings: &mut Vec<ConvertError>,
+    ) -> Result<(), ConvertError> {
         let api_names: HashSet<_> = self
             .apis
             .iter()
@@ -412,9 +531,17 @@ impl<'a> ParseBindgen<'a> {
             .collect();
         for generate_directive in self.config.must_generate_list() {
             if !api_names.contains(&generate_directive) {
-                return Err(ConvertErrorFromCpp::DidNotGenerateAnything(
-                    generate_directive,
-                ));
+                let span = self.config.get_directive_span(&generate_directive);
+                let err = ConvertErrorFromCpp::DidNotGenerateAnything(generate_directive);
+                match span {
+                    Some(span) => ConvertError::Directive(LocatedConvertErrorFromCpp::new(
+                        err,
+                        &span,
+                        source_file_contents,
+                    )),
+                    None => ConvertError::Cpp(err),
+                }
+                .for_directive_miss(self.config, warnings)?;
             }
         }
         Ok(())
@@ -6,7 +6,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::conversion::api::{ApiName, NullPhase, Provenance};
+use crate::conversion::analysis::fun::function_wrapper::{CppFunctionBody, CppFunctionKind};
+use crate::conversion::api::{ApiName, CppVisibility, NullPhase, Provenance};
 use crate::conversion::apivec::ApiVec;
 use crate::conversion::doc_attr::get_doc_attrs;
 use crate::conversion::error_reporter::report_any_error;
@@ -16,14 +17,18 @@ use crate::conversion::{
     convert_error::ErrorContext,
 };
 use crate::minisyn::{minisynize_punctuated, minisynize_vec};
-use crate::types::strip_bindgen_original_suffix_from_ident;
+use crate::types::{make_ident, strip_bindgen_original_suffix_from_ident};
 use crate::ParseCallbackResults;
 use crate::{
     conversion::ConvertErrorFromCpp,
     types::{Namespace, QualifiedName},
 };
+use autocxx_parser::{to_snake_case, IncludeCppConfig};
 use std::collections::HashMap;
-use syn::{Block, Expr, ExprCall, ForeignItem, Ident, ImplItem, ItemImpl, Stmt, Type};
+use syn::{
+    parse_quote, Attribute, Block, Expr, ExprCall, ExprLit, ForeignItem, Ident, ImplItem, ItemImpl,
+    Lit, Meta, MetaNameValue, StaticMutability, Stmt, Type,
+};
 
 /// Parses a given bindgen-generated 'mod' into suitable
 /// [Api]s. In bindgen output, a given mod concerns
@@ -93,6 +98,61 @@ impl<'a> ParseForeignMod<'a> {
                 });
                 Ok(())
             }
+            ForeignItem::Static(item) if matches!(item.mutability, StaticMutability::None) => {
+                // A const global. cxx can't refer to statics, so we expose
+                // an accessor which returns a reference from within C++.
+                let unsuffixed_name = strip_bindgen_original_suffix_from_ident(&item.ident);
+                // bindgen names a static member of a class `Class_member`,
+                // so find the real C++ name from the symbol.
+                let (cpp_name, allowlisted_as) = match get_link_name(&item.attrs)
+                    .filter(|symbol| is_mangled(symbol))
+                {
+                    None => {
+                        let global = QualifiedName::new(&self.ns, unsuffixed_name.clone().into());
+                        (global.to_cpp_name(), global)
+                    }
+                    Some(symbol) => match cpp_name_from_symbol(&symbol)
+                        .and_then(|cpp_name| allowlist_name_for_global(&self.ns, &cpp_name))
+                    {
+                        Some(names) => names,
+                        None => {
+                            return Err(ConvertErrorWithContext(
+                                ConvertErrorFromCpp::StaticData(item.ident.to_string()),
+                                Some(ErrorContext::new_for_item(item.ident.clone().into())),
+                            ))
+                        }
+                    },
+                };
+                let mut doc_attrs = get_doc_attrs(&item.attrs);
+                let doc = format!("A reference to the C++ global `{cpp_name}`.");
+                doc_attrs.push(parse_quote! { #[doc = #doc] });
+                let ty = &item.ty;
+                self.funcs_to_convert.push(FuncToConvert {
+                    provenance: Provenance::SynthesizedOther,
+                    self_ty: None,
+                    ident: make_ident(to_snake_case(&unsuffixed_name.to_string())),
+                    doc_attrs: minisynize_vec(doc_attrs),
+                    inputs: Default::default(),
+                    output: parse_quote! { -> & #ty },
+                    vis: item.vis.clone().into(),
+                    virtualness: None,
+                    cpp_vis: CppVisibility::Public,
+                    special_member: None,
+                    original_name: None,
+                    synthesized_this_type: None,
+                    add_to_trait: None,
+                    is_deleted: None,
+                    synthetic_cpp: Some((
+                        CppFunctionBody::GlobalAccessor {
+                            cpp_name,
+                            allowlisted_as,
+                        },
+                        CppFunctionKind::Function,
+                    )),
+                    variadic: false,
+                });
+                Ok(())
+            }
             ForeignItem::Static(item) => Err(ConvertErrorWithContext(
                 ConvertErrorFromCpp::StaticData(item.ident.to_string()),
                 Some(ErrorContext::new_for_item(item.ident.clone().into())),
@@ -129,12 +189,26 @@ impl<'a> ParseForeignMod<'a> {
 
     /// Indicate that all foreign mods and all impl blocks have been
     /// fed into us, and we should process that information to generate
-    /// the resulting APIs.
-    pub(crate) fn finished(mut self, apis: &mut ApiVec<NullPhase>) {
+    /// the resulting APIs. Member functions left out by `generate_member!`
+    /// are dropped here, before we try to analyze them.
+    pub(crate) fn finished(mut self, apis: &mut ApiVec<NullPhase>, config: &IncludeCppConfig) {
         apis.append(&mut self.ignored_apis);
         while !self.funcs_to_convert.is_empty() {
             let mut fun = self.funcs_to_convert.remove(0);
             fun.self_ty = self.method_receivers.get(&fun.ident).cloned();
+            // Special members are kept, since they govern what we can do
+            // with the type as a whole.
+            if let (Some(self_ty), None) = (&fun.self_ty, &fun.special_member) {
+                let ident = fun.ident.to_string();
+                let member = fun
+                    .original_name
+                    .as_ref()
+                    .map(|n| n.for_validation())
+                    .unwrap_or(&ident);
+                if !config.is_member_allowed(&self_ty.to_cpp_name(), member) {
+                    continue;
+                }
+            }
             apis.push(UnanalyzedApi::Function {
                 name: ApiName::new_with_cpp_name(
                     &self.ns,
@@ -162,12 +236,165 @@ fn get_called_function(block: &Block) -> Option<&Ident> {
     }
 }
 
+/// The symbol given by a `#[link_name]` attribute, if any.
+fn get_link_name(attrs: &[Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        Meta::NameValue(MetaNameValue {
+            path,
+            value:
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(link_name),
+                    ..
+                }),
+            ..
+        }) if path.is_ident("link_name") => Some(link_name.value()),
+        _ => None,
+    })
+}
+
+/// Remove the prefixes which bindgen and some platforms add to a symbol.
+fn unprefixed_symbol(symbol: &str) -> &str {
+    // bindgen's prefix stops LLVM mangling the symbol further, and macOS
+    // adds an underscore.
+    let symbol = symbol.trim_start_matches('\u{1}');
+    symbol
+        .strip_prefix('_')
+        .filter(|symbol| symbol.starts_with("_Z"))
+        .unwrap_or(symbol)
+}
+
+/// Whether a symbol has been mangled by the Itanium or MSVC ABIs, rather
+/// than just being the variable's name.
+fn is_mangled(symbol: &str) -> bool {
+    let symbol = unprefixed_symbol(symbol);
+    symbol.starts_with("_Z") || symbol.starts_with('?')
+}
+
+/// The qualified C++ name of a variable, from its symbol as mangled by
+/// the Itanium or MSVC ABIs. Only names made up of plain identifiers are
+/// understood, not those involving templates or anonymous namespaces.
+fn cpp_name_from_symbol(symbol: &str) -> Option<String> {
+    let symbol = unprefixed_symbol(symbol);
+    let segments = if let Some(msvc) = symbol.strip_prefix('?') {
+        // `?member@Class@ns@@...`, innermost first.
+        let (scopes, _) = msvc.split_once("@@")?;
+        let mut segments: Vec<&str> = scopes.split('@').collect();
+        segments.reverse();
+        segments
+    } else {
+        let mut rest = symbol.strip_prefix("_Z")?;
+        rest = rest.strip_prefix('L').unwrap_or(rest);
+        let nested = rest.strip_prefix('N');
+        rest = nested.unwrap_or(rest);
+        let mut segments = Vec::new();
+        // `_ZN2ns5Class6memberE`, each preceded by its length.
+        while let Some(len_end) = rest.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0) {
+            let len: usize = rest[..len_end].parse().ok()?;
+            segments.push(rest.get(len_end..len_end + len)?);
+            rest = &rest[len_end + len..];
+            if nested.is_none() {
+                break;
+            }
+        }
+        if rest != if nested.is_some() { "E" } else { "" } {
+            return None;
+        }
+        segments
+    };
+    let is_identifier = |segment: &&str| {
+        segment.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !segment.starts_with("_GLOBAL__N")
+    };
+    (!segments.is_empty() && segments.iter().all(is_identifier)).then(|| segments.join("::"))
+}
+
+/// Given the C++ name of a global found in namespace `ns`, return it
+/// along with the name by which it's found in the allowlist. That's the
+/// global itself, or the class of which it's a static member, named as
+/// bindgen does.
+fn allowlist_name_for_global(ns: &Namespace, cpp_name: &str) -> Option<(String, QualifiedName)> {
+    let segments: Vec<&str> = cpp_name.split("::").collect();
+    let (name, scopes) = segments.split_last()?;
+    let classes = scopes.strip_prefix(ns.iter().collect::<Vec<_>>().as_slice())?;
+    let allowlisted_as = if classes.is_empty() {
+        name.to_string()
+    } else {
+        classes.join("_")
+    };
+    Some((
+        cpp_name.to_string(),
+        QualifiedName::new(ns, make_ident(allowlisted_as)),
+    ))
+}
+
 #[cfg(test)]
 mod test {
-    use super::get_called_function;
+    use super::{allowlist_name_for_global, cpp_name_from_symbol, get_called_function, is_mangled};
+    use crate::types::Namespace;
     use syn::parse_quote;
     use syn::Block;
 
+    #[test]
+    fn test_allowlist_name_for_global() {
+        let ns = Namespace::from_user_input("ns");
+        let name = |cpp_name| {
+            allowlist_name_for_global(&ns, cpp_name)
+                .map(|(cpp_name, allowlisted_as)| (cpp_name, allowlisted_as.to_cpp_name()))
+        };
+        assert_eq!(
+            name("ns::kDefault"),
+            Some(("ns::kDefault".into(), "ns::kDefault".into()))
+        );
+        assert_eq!(
+            name("ns::Widget::kDefault"),
+            Some(("ns::Widget::kDefault".into(), "ns::Widget".into()))
+        );
+        assert_eq!(
+            name("ns::Outer::Inner::kDefault"),
+            Some((
+                "ns::Outer::Inner::kDefault".into(),
+                "ns::Outer_Inner".into()
+            ))
+        );
+        assert_eq!(name("other::kDefault"), None);
+    }
+
+    #[test]
+    fn test_cpp_name_from_symbol() {
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}_ZN3cfg14kDefaultConfigE").as_deref(),
+            Some("cfg::kDefaultConfig")
+        );
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}_ZN2ns6Widget8kDefaultE").as_deref(),
+            Some("ns::Widget::kDefault")
+        );
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}_ZL8kDefault").as_deref(),
+            Some("kDefault")
+        );
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}__ZN2ns6Widget8kDefaultE").as_deref(),
+            Some("ns::Widget::kDefault")
+        );
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}?kDefault@Widget@ns@@2UConfig@2@B").as_deref(),
+            Some("ns::Widget::kDefault")
+        );
+        // A static member of a class template.
+        assert_eq!(cpp_name_from_symbol("\u{1}_ZN6WidgetIiE8kDefaultE"), None);
+        assert_eq!(
+            cpp_name_from_symbol("\u{1}_ZN12_GLOBAL__N_18kDefaultE"),
+            None
+        );
+        assert_eq!(cpp_name_from_symbol("\u{1}?kDefault@?$Widget@H@@2HB"), None);
+        assert!(!is_mangled("kDefault"));
+        assert!(!is_mangled("\u{1}_kDefault"));
+    }
+
     #[test]
     fn test_get_called_function() {
         let b: Block = parse_quote! {
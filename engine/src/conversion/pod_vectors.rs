@@ -0,0 +1,75 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! cxx lets Rust view a `std::vector` of trivial types as a slice, but
+//! only offers element-by-element `push` to add to one. For each POD type
+//! which can live in a `CxxVector`, we generate a pair of C++ functions
+//! to copy a whole buffer into, or out of, a `std::vector` in one call.
+//! These back `autocxx::PodVectorExt`.
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::types::QualifiedName;
+
+use super::{
+    analysis::{
+        fun::{FnPhase, PodAndDepAnalysis},
+        pod::PodAnalysis,
+    },
+    api::{Api, TypeKind},
+    apivec::ApiVec,
+    extra_shims::shim_name,
+};
+
+pub(crate) struct PodVectorElement {
+    pub(crate) ty: QualifiedName,
+    /// The C++ function which appends a buffer of elements to a vector.
+    pub(crate) extend_shim: String,
+    /// The C++ function which copies a vector's elements into a buffer.
+    pub(crate) copy_shim: String,
+}
+
+pub(crate) fn find_pod_vector_elements(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<PodVectorElement> {
+    // Without the `impl CxxVector<T> {}`, cxx won't let us use the vector.
+    if config.exclude_impls {
+        return Vec::new();
+    }
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                analysis:
+                    PodAndDepAnalysis {
+                        pod:
+                            PodAnalysis {
+                                kind: TypeKind::Pod,
+                                num_generics: 0,
+                                ..
+                            },
+                        constructors,
+                        ..
+                    },
+                ..
+            } if constructors.move_constructor
+                && constructors.copy_constructor
+                && !config.is_singleton(&name.name.to_cpp_name()) =>
+            {
+                Some(&name.name)
+            }
+            _ => None,
+        })
+        .map(|ty| PodVectorElement {
+            ty: ty.clone(),
+            extend_shim: shim_name(config, ty, "vector_extend_autocxx_wrapper"),
+            copy_shim: shim_name(config, ty, "vector_copy_autocxx_wrapper"),
+        })
+        .collect()
+}
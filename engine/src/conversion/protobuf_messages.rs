@@ -0,0 +1,104 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Classes generated by protoc have many methods which exist for the
+//! protobuf runtime's own use: arena management, reflection, and internals
+//! named with a leading underscore. For each type listed in
+//! `protobuf_message!` we drop those, and generate a pair of C++ functions
+//! which serialize and parse the message, backing
+//! `autocxx::ProtobufMessage`.
+
+use autocxx_parser::IncludeCppConfig;
+use quote::ToTokens;
+
+use crate::types::QualifiedName;
+
+use super::{
+    analysis::fun::FnPhase,
+    api::{Api, FuncToConvert},
+    apivec::ApiVec,
+    extra_shims::shim_name,
+};
+
+/// Methods which only the protobuf runtime should call.
+static RUNTIME_METHODS: &[&str] = &[
+    "New",
+    "UnsafeArenaSwap",
+    "GetArena",
+    "GetOwningArena",
+    "GetMetadata",
+    "GetDescriptor",
+    "GetReflection",
+    "GetClassData",
+    "descriptor",
+    "default_instance",
+    "internal_default_instance",
+    "SharedCtor",
+    "SharedDtor",
+    "SetCachedSize",
+    "GetCachedSize",
+    "ArenaDtor",
+    "RegisterArenaDtor",
+    "FullMessageName",
+];
+
+/// Prefixes of methods which are either internal, or transfer ownership
+/// through raw pointers.
+static RUNTIME_METHOD_PREFIXES: &[&str] = &[
+    "_",
+    "Internal",
+    "unsafe_arena_",
+    "set_allocated_",
+    "release_",
+];
+
+pub(crate) struct ProtobufMessage {
+    pub(crate) ty: QualifiedName,
+    /// The C++ function which serializes the message to a `std::string`.
+    pub(crate) serialize_shim: String,
+    /// The C++ function which parses the message from bytes.
+    pub(crate) parse_shim: String,
+}
+
+pub(crate) fn find_protobuf_messages(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Vec<ProtobufMessage> {
+    // The type may have failed to generate, in which case we can't refer
+    // to it.
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } if config.is_protobuf_message(&name.name.to_cpp_name()) => {
+                Some(&name.name)
+            }
+            _ => None,
+        })
+        .map(|ty| ProtobufMessage {
+            ty: ty.clone(),
+            serialize_shim: shim_name(config, ty, "serialize_autocxx_wrapper"),
+            parse_shim: shim_name(config, ty, "parse_autocxx_wrapper"),
+        })
+        .collect()
+}
+
+/// Whether this method of a `protobuf_message!` type is one for the
+/// protobuf runtime, rather than its users, or mentions the runtime's own
+/// types such as `google::protobuf::Arena` or `RepeatedField`, which we
+/// can't usefully bind.
+pub(crate) fn is_protobuf_runtime_method(cpp_name: &str, fun: &FuncToConvert) -> bool {
+    RUNTIME_METHODS.contains(&cpp_name)
+        || RUNTIME_METHOD_PREFIXES
+            .iter()
+            .any(|prefix| cpp_name.starts_with(prefix))
+        || fun
+            .inputs
+            .iter()
+            .map(|arg| arg.to_token_stream())
+            .chain(std::iter::once(fun.output.to_token_stream()))
+            .any(|tokens| tokens.to_string().contains("google :: protobuf ::"))
+}
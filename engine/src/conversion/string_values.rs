@@ -0,0 +1,33 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! With `strings_by_value!`, a `std::string` is constructed within an
+//! `autocxx::CxxStringValue`, which is just suitably-sized Rust storage.
+//! We generate C++ functions to construct a string within such storage,
+//! and to destroy it, and each `CxxStringValue` remembers the destroy
+//! function for its eventual `Drop`. This module names those functions
+//! consistently for the Rust and C++ code generators.
+
+use autocxx_parser::IncludeCppConfig;
+
+pub(crate) struct StringValueNames {
+    /// The C++ function which constructs a string from some bytes.
+    pub(crate) init_fn: String,
+    /// The C++ function which destroys a string in place.
+    pub(crate) destroy_fn: String,
+}
+
+pub(crate) fn string_value_names(config: &IncludeCppConfig) -> StringValueNames {
+    // These are visible at the top level of the C++, and cxx's own
+    // symbols for them are named after them too, so they need to be
+    // unique across every include_cpp! in the build.
+    StringValueNames {
+        init_fn: config.uniquify_name_per_mod("autocxx_string_value_init"),
+        destroy_fn: config.uniquify_name_per_mod("autocxx_string_value_destroy"),
+    }
+}
@@ -7,10 +7,12 @@
 // except according to those terms.
 
 use syn::{
-    AngleBracketedGenericArguments, GenericArgument, Path, PathArguments, PathSegment, Type,
-    TypePath, TypeReference,
+    parse_quote, AngleBracketedGenericArguments, GenericArgument, Path, PathArguments, PathSegment,
+    Type, TypePath, TypeReference,
 };
 
+use crate::types::{make_ident, QualifiedName};
+
 /// Looks in a `core::pin::Pin<&mut Something>` and returns the `Something`
 /// if it's found.
 /// This code could _almost_ be used from various other places around autocxx
@@ -39,6 +41,150 @@ pub(crate) fn extract_pinned_mutable_reference_type(tp: &TypePath) -> Option<&Ty
     None
 }
 
+/// Looks in a `&[Something; N]` or `&mut [Something; N]` and returns the
+/// `Something` if it's found.
+pub(crate) fn extract_array_reference_element_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(TypeReference { elem, .. }) => match elem.as_ref() {
+            Type::Array(arr) => Some(&arr.elem),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether bindgen's version of a type refers to `std::byte` anywhere,
+/// e.g. `*const root::std::byte` or `root::std::vector<root::std::byte>`.
+pub(crate) fn type_mentions_std_byte(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => {
+            QualifiedName::from_type_path(tp).to_cpp_name() == "std::byte"
+                || tp.path.segments.iter().any(|seg| match &seg.arguments {
+                    PathArguments::AngleBracketed(ab) => ab.args.iter().any(|arg| match arg {
+                        GenericArgument::Type(ty) => type_mentions_std_byte(ty),
+                        _ => false,
+                    }),
+                    _ => false,
+                })
+        }
+        Type::Ptr(tp) => type_mentions_std_byte(&tp.elem),
+        Type::Reference(tr) => type_mentions_std_byte(&tr.elem),
+        Type::Array(ta) => type_mentions_std_byte(&ta.elem),
+        _ => false,
+    }
+}
+
+/// The `std::pmr` containers which we present to Rust as their `std`
+/// equivalents. libstdc++ declares `std::pmr::string` within an inline
+/// namespace, so these are compared without any such namespaces.
+const PMR_CONTAINERS: [&str; 2] = ["std::pmr::string", "std::pmr::vector"];
+
+/// How bindgen's version of a type refers to a `std::pmr` container.
+#[derive(PartialEq, Eq)]
+pub(crate) enum PmrUse {
+    None,
+    /// The type is such a container, or a pointer or reference to one, so
+    /// a C++ wrapper can copy it to or from its `std` equivalent.
+    Direct,
+    /// Such a container is within some other type, e.g.
+    /// `std::vector<std::pmr::string>`, and so can't be copied.
+    Nested,
+}
+
+/// Whether bindgen's version of a type refers to a `std::pmr` container,
+/// e.g. `*const root::std::pmr::string`.
+pub(crate) fn find_pmr_use(ty: &Type) -> PmrUse {
+    match ty {
+        Type::Path(tp) => {
+            // Look through bindgen's markers, such as
+            // `__bindgen_marker_Reference<*const T>`.
+            let is_marker = tp
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident.to_string().starts_with("__bindgen_marker_"));
+            if is_marker {
+                return match &tp.path.segments.last().unwrap().arguments {
+                    PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                        Some(GenericArgument::Type(ty)) => find_pmr_use(ty),
+                        _ => PmrUse::None,
+                    },
+                    _ => PmrUse::None,
+                };
+            }
+            let is_pmr = PMR_CONTAINERS.contains(
+                &QualifiedName::from_type_path(tp)
+                    .without_inline_namespaces()
+                    .to_cpp_name()
+                    .as_str(),
+            );
+            let nested = tp.path.segments.iter().any(|seg| match &seg.arguments {
+                PathArguments::AngleBracketed(ab) => ab.args.iter().any(|arg| match arg {
+                    GenericArgument::Type(ty) => find_pmr_use(ty) != PmrUse::None,
+                    _ => false,
+                }),
+                _ => false,
+            });
+            match (is_pmr, nested) {
+                (_, true) => PmrUse::Nested,
+                (true, false) => PmrUse::Direct,
+                (false, false) => PmrUse::None,
+            }
+        }
+        Type::Ptr(tp) => find_pmr_use(&tp.elem),
+        Type::Reference(tr) => find_pmr_use(&tr.elem),
+        Type::Array(ta) => match find_pmr_use(&ta.elem) {
+            PmrUse::None => PmrUse::None,
+            _ => PmrUse::Nested,
+        },
+        _ => PmrUse::None,
+    }
+}
+
+/// The type to use in a cxx shared struct for a field of this type, as
+/// generated by bindgen, if cxx can represent it: that is, for numbers,
+/// `bool`s and arrays of them. The C `c_*` integer types become their
+/// fixed-size equivalents, since cxx doesn't know them.
+pub(crate) fn shared_struct_field_type(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::Path(tp) if tp.qself.is_none() => {
+            let last = tp.path.segments.last()?;
+            if !last.arguments.is_none() {
+                return None;
+            }
+            let name = last.ident.to_string();
+            let primitive = match name.as_str() {
+                "bool" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "usize" | "f32" | "f64"
+                    if tp.path.segments.len() == 1 =>
+                {
+                    name.as_str()
+                }
+                "c_char" => "c_char",
+                "c_schar" => "i8",
+                "c_uchar" => "u8",
+                "c_short" => "i16",
+                "c_ushort" => "u16",
+                "c_int" => "i32",
+                "c_uint" => "u32",
+                "c_longlong" => "i64",
+                "c_ulonglong" => "u64",
+                "c_float" => "f32",
+                "c_double" => "f64",
+                _ => return None,
+            };
+            let primitive = make_ident(primitive);
+            Some(parse_quote! { #primitive })
+        }
+        Type::Array(ta) => {
+            let elem = shared_struct_field_type(&ta.elem)?;
+            let len = &ta.len;
+            Some(parse_quote! { [#elem; #len] })
+        }
+        _ => None,
+    }
+}
+
 /// Whether this type path is a `Pin`
 fn is_pin(tp: &TypePath) -> bool {
     if tp.path.segments.len() != 3 {
@@ -108,3 +254,29 @@ pub(crate) fn unwrap_has_unused_template_param(ty: &TypePath) -> Option<&syn::Ty
 pub(crate) fn unwrap_has_opaque(ty: &TypePath) -> Option<&syn::Type> {
     unwrap_bindgen_marker(ty, "__bindgen_marker_Opaque")
 }
+
+/// Whether bindgen's version of a type is a `std::ostream&`, which
+/// `ostream_as_writer!` presents to Rust as a `&mut dyn std::io::Write`.
+pub(crate) fn type_is_ostream_reference(ty: &Type) -> bool {
+    let Type::Path(tp) = ty else {
+        return false;
+    };
+    let Some(ptr) = unwrap_reference(tp, false) else {
+        return false;
+    };
+    if ptr.mutability.is_none() {
+        return false;
+    }
+    let Type::Path(pointee) = ptr.elem.as_ref() else {
+        return false;
+    };
+    let pointee = match unwrap_has_opaque(pointee) {
+        Some(Type::Path(inner)) => inner,
+        _ => pointee,
+    };
+    let cpp_name = QualifiedName::from_type_path(pointee).to_cpp_name();
+    // It may be named through the typedef, or through libstdc++'s or
+    // libc++'s inline namespaces.
+    cpp_name.starts_with("std::")
+        && (cpp_name.ends_with("::ostream") || cpp_name.ends_with("::basic_ostream"))
+}
@@ -0,0 +1,72 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! For each public base class of a type, we generate a C++ function which
+//! converts a pointer to the derived type into a pointer to the base, so
+//! that C++ can make whatever adjustment is needed (for instance, where
+//! there's multiple inheritance and the base isn't at offset zero). The
+//! Rust side uses this to implement `AsCppRef<Base>` and
+//! `AsCppMutRef<Base>` for `CppPin<Derived>`. Where the base has a virtual
+//! destructor, so that it's OK for it to own the derived object, we also
+//! generate a similar function to convert a `std::unique_ptr`. This module
+//! finds those pairs of types and names the functions consistently for
+//! the Rust and C++ code generators.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::map::IndexMap as HashMap;
+use itertools::Itertools;
+
+use crate::types::QualifiedName;
+
+use super::{analysis::fun::FnPhase, api::Api, apivec::ApiVec, extra_shims::shim_name};
+
+pub(crate) struct Upcast {
+    pub(crate) derived: QualifiedName,
+    pub(crate) base: QualifiedName,
+    /// The C++ function which converts a derived pointer to a base pointer.
+    pub(crate) cpp_shim: String,
+    /// The C++ function which converts a `std::unique_ptr` to the derived
+    /// type into one to the base, if the base has a virtual destructor.
+    pub(crate) unique_ptr_shim: Option<String>,
+}
+
+pub(crate) fn find_upcasts(apis: &ApiVec<FnPhase>, config: &IncludeCppConfig) -> Vec<Upcast> {
+    // Bases may be on the allowlist yet still have failed to generate,
+    // in which case we can't refer to them.
+    let structs: HashMap<_, _> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. } => {
+                Some((&name.name, analysis.pod.has_virtual_destructor))
+            }
+            _ => None,
+        })
+        .collect();
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. } => Some((&name.name, &analysis.pod.castable_bases)),
+            _ => None,
+        })
+        .flat_map(|(derived, bases)| {
+            bases
+                .iter()
+                .filter(|base| structs.contains_key(base))
+                .map(move |base| (derived, base))
+        })
+        .map(|(derived, base)| {
+            let suffix = format!("upcast_to_{}", base.segment_iter().join("_"));
+            Upcast {
+                derived: derived.clone(),
+                base: base.clone(),
+                cpp_shim: shim_name(config, derived, &suffix),
+                unique_ptr_shim: structs[base]
+                    .then(|| shim_name(config, derived, &format!("{suffix}_unique_ptr"))),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A method returning `const std::vector<T>&` gives Rust a `&CxxVector<T>`,
+//! which is awkward to read in bulk. Where `T` is a number or a POD type,
+//! we also generate a C++ function which calls the method and returns the
+//! vector's `data()` and `size()`, so that Rust can view the elements as a
+//! slice borrowed from the receiver in a single call.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::set::IndexSet as HashSet;
+use syn::{GenericArgument, PathArguments, ReturnType, Type};
+
+use crate::types::QualifiedName;
+
+use super::{
+    analysis::fun::{FnAnalysis, FnKind, FnPhase, MethodKind, ReceiverMutability},
+    api::Api,
+    apivec::ApiVec,
+    extra_shims::shim_name,
+    pod_vectors::PodVectorElement,
+};
+
+/// Rust numeric types which cxx allows in a `CxxVector`.
+const NUMERIC_ELEMENTS: &[&str] = &[
+    "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "f32", "f64",
+];
+
+pub(crate) struct VectorSliceMethod {
+    /// The type with the method.
+    pub(crate) impl_for: QualifiedName,
+    /// The vector's element type.
+    pub(crate) element: QualifiedName,
+    /// Whether the element type is a Rust numeric type, rather than a POD
+    /// type generated by autocxx.
+    pub(crate) element_is_numeric: bool,
+    /// The name of the C++ method returning the vector.
+    pub(crate) cpp_method: String,
+    /// The name of the Rust method returning the slice.
+    pub(crate) rust_name: String,
+    /// The C++ function which calls the method and returns the vector's
+    /// data and size.
+    pub(crate) shim: String,
+}
+
+pub(crate) fn find_vector_slice_methods(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+    pod_elements: &[PodVectorElement],
+) -> Vec<VectorSliceMethod> {
+    // A slice method isn't generated if its name is already taken.
+    let existing_methods: HashSet<(&QualifiedName, &str)> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Function { analysis, .. } => match &analysis.kind {
+                FnKind::Method { impl_for, .. } => Some((impl_for, analysis.rust_name.as_str())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Function { fun, analysis, .. } => {
+                let FnKind::Method {
+                    impl_for,
+                    method_kind:
+                        MethodKind::Normal | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+                } = &analysis.kind
+                else {
+                    return None;
+                };
+                if !is_usable_method(analysis) {
+                    return None;
+                }
+                let element = const_vector_element(&analysis.ret_type.0)?;
+                let element_is_numeric = element.get_namespace().is_empty()
+                    && NUMERIC_ELEMENTS.contains(&element.get_final_item());
+                if !element_is_numeric && !pod_elements.iter().any(|pod| pod.ty == element) {
+                    return None;
+                }
+                let rust_name = format!("{}_as_slice", analysis.rust_name);
+                if existing_methods.contains(&(impl_for, rust_name.as_str())) {
+                    return None;
+                }
+                let cpp_method = fun.original_name.as_ref()?.to_string();
+                Some(VectorSliceMethod {
+                    impl_for: impl_for.clone(),
+                    element,
+                    element_is_numeric,
+                    cpp_method,
+                    rust_name,
+                    shim: shim_name(
+                        config,
+                        impl_for,
+                        &format!("{}_as_slice_autocxx_wrapper", analysis.rust_name),
+                    ),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Only const methods taking no parameters, whose Rust signature is the
+/// same as their cxx signature, are included.
+fn is_usable_method(analysis: &FnAnalysis) -> bool {
+    analysis.ignore_reason.is_ok()
+        && analysis.externally_callable
+        && !analysis.async_wrapper_needed
+        && analysis.params.len() == 1
+        && matches!(
+            analysis
+                .param_details
+                .first()
+                .and_then(|pd| pd.self_type.as_ref()),
+            Some((_, ReceiverMutability::Const))
+        )
+        && !analysis
+            .ret_conversion
+            .as_ref()
+            .map(|conversion| conversion.rust_work_needed())
+            .unwrap_or_default()
+}
+
+/// If this returns `&CxxVector<T>`, the name of `T`.
+fn const_vector_element(ret_type: &ReturnType) -> Option<QualifiedName> {
+    let ReturnType::Type(_, ty) = ret_type else {
+        return None;
+    };
+    let Type::Reference(reference) = ty.as_ref() else {
+        return None;
+    };
+    if reference.mutability.is_some() {
+        return None;
+    }
+    let Type::Path(vector) = reference.elem.as_ref() else {
+        return None;
+    };
+    let last_seg = vector.path.segments.last()?;
+    if last_seg.ident != "CxxVector" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &last_seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(Type::Path(element)) => Some(QualifiedName::from_type_path(element)),
+        _ => None,
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A rough C++ tokenizer, for the few places where we need to find
+//! declarations which bindgen doesn't tell us about. It knows nothing of
+//! the grammar; callers just need to be able to match up brackets without
+//! being fooled by comments and literals.
+
+/// Split C++ source into tokens and their offsets: whole preprocessor
+/// directives, identifiers and numbers, string and character literals,
+/// `::`, and single punctuation characters. Comments are dropped.
+pub(crate) fn tokenize(text: &str) -> Vec<(usize, &str)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut at_line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        if c == b'\n' {
+            at_line_start = true;
+            i += 1;
+            continue;
+        } else if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if text[i..].starts_with("//") {
+            i = text[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            continue;
+        } else if text[i..].starts_with("/*") {
+            i = text[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + 2 + end + 2);
+            continue;
+        } else if c == b'#' && at_line_start {
+            // Up to the end of the line, following any continuations.
+            loop {
+                i = text[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                if i == bytes.len() || !text[..i].trim_end_matches('\r').ends_with('\\') {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push((start, text[start..i].trim_end()));
+            continue;
+        } else if c.is_ascii_digit() {
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'_' | b'.' | b'\''))
+            {
+                i += 1;
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            if text[start..i].ends_with('R') && bytes.get(i) == Some(&b'"') {
+                i = raw_string_end(text, i);
+            }
+        } else if c == b'"' || c == b'\'' {
+            let mut escaped = false;
+            i += 1;
+            while i < bytes.len() {
+                let b = bytes[i];
+                i += 1;
+                if b == c && !escaped {
+                    break;
+                }
+                escaped = b == b'\\' && !escaped;
+            }
+        } else if text[i..].starts_with("::") {
+            i += 2;
+        } else {
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
+        }
+        at_line_start = false;
+        tokens.push((start, &text[start..i]));
+    }
+    tokens
+}
+
+/// The end of a raw string literal whose `"` is at `i`.
+fn raw_string_end(text: &str, i: usize) -> usize {
+    let Some(open) = text[i..].find('(') else {
+        return text.len();
+    };
+    let terminator = format!("){}\"", &text[i + 1..i + open]);
+    text[i + open..]
+        .find(&terminator)
+        .map_or(text.len(), |end| i + open + end + terminator.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokenize;
+
+    fn tokens(text: &str) -> Vec<&str> {
+        tokenize(text).into_iter().map(|(_, tok)| tok).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokens("Foo::Foo() : a{1}, b(2.5f) {} // done { not a brace"),
+            vec![
+                "Foo", "::", "Foo", "(", ")", ":", "a", "{", "1", "}", ",", "b", "(", "2.5f", ")",
+                "{", "}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_directives() {
+        assert_eq!(
+            tokens("#define X(a) \\\n  { a }\n  #include \"x.h\"\nint x = 1'000; /* } */"),
+            vec![
+                "#define X(a) \\\n  { a }",
+                "#include \"x.h\"",
+                "int",
+                "x",
+                "=",
+                "1'000",
+                ";"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_literals() {
+        assert_eq!(
+            tokens(r##"f("}\"{", '{', R"x(")}")x", u8"#")"##),
+            vec![
+                "f",
+                "(",
+                r#""}\"{""#,
+                ",",
+                "'{'",
+                ",",
+                r#"R"x(")}")x""#,
+                ",",
+                "u8",
+                "\"#\"",
+                ")"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(
+            tokenize("a /* b */ c\n#x"),
+            vec![(0, "a"), (10, "c"), (12, "#x")]
+        );
+    }
+}
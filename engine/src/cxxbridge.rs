@@ -17,12 +17,14 @@ use crate::{do_cxx_cpp_generation, parse_file::CppBuildable, CppCodegenOptions,
 /// for both manual and automatic bindings using the same tooling.
 pub struct CxxBridge {
     tokens: TokenStream,
+    mod_name: String,
 }
 
 impl From<ItemMod> for CxxBridge {
     fn from(itm: ItemMod) -> Self {
         Self {
             tokens: itm.to_token_stream(),
+            mod_name: itm.ident.to_string(),
         }
     }
 }
@@ -38,7 +40,9 @@ impl CppBuildable for CxxBridge {
         &self,
         cpp_codegen_options: &CppCodegenOptions,
     ) -> Result<GeneratedCpp, cxx_gen::Error> {
-        let header_name = cpp_codegen_options.cxxgen_header_namer.name_header();
+        let header_name = cpp_codegen_options
+            .cxxgen_header_namer
+            .name_header(self.mod_name.clone());
         let fp = do_cxx_cpp_generation(self.tokens.clone(), cpp_codegen_options, header_name)?;
         Ok(GeneratedCpp(vec![fp]))
     }
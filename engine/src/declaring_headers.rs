@@ -0,0 +1,465 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finds which of the `#include`d headers declares each C++ item. bindgen
+//! doesn't tell us, so we ask clang for a JSON dump of the AST and use the
+//! source location of each declaration. This needs an extra run of clang,
+//! so is only done if the include metadata is wanted.
+//!
+//! The dump can be huge, so we walk it as it's deserialized rather than
+//! building it in memory. clang only names the file of a source location
+//! when it differs from the last location it wrote, so we must visit every
+//! location in the order clang wrote them.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::map::IndexMap as HashMap;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::{get_clang_path, make_clang_args};
+
+/// The name clang gives the file it reads from stdin.
+const MAIN_FILE: &str = "<stdin>";
+
+/// The header declaring each C++ item.
+#[derive(Debug, Default)]
+pub(crate) struct DeclaringHeaders {
+    /// Keyed by the item's name as used in directives, e.g. `ns::Foo`.
+    /// Each header is named as in the `include_cpp!` macro.
+    headers: HashMap<String, String>,
+}
+
+impl DeclaringHeaders {
+    /// Run clang over the headers in the same way as bindgen will see them,
+    /// and find the declaration of each item.
+    pub(crate) fn find(
+        header: &str,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+        config: &IncludeCppConfig,
+        include_list: &[String],
+    ) -> std::io::Result<Self> {
+        let mut child = Command::new(get_clang_path())
+            .arg("-fsyntax-only")
+            .arg("-Xclang")
+            .arg("-ast-dump=json")
+            .args(make_clang_args(inc_dirs, extra_clang_args))
+            .args(
+                config
+                    .get_cpp_std()
+                    .map(|standard| format!("-std={standard}")),
+            )
+            .args(config.get_clang_args())
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(header.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(Self::from_ast_dump(&output.stdout, include_list))
+    }
+
+    fn from_ast_dump(json: &[u8], include_list: &[String]) -> Self {
+        let mut walker = AstWalker::default();
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let root = NodeSeed {
+            walker: &mut walker,
+            scope: Some(&[]),
+        };
+        if let Err(err) = root.deserialize(&mut deserializer) {
+            log::warn!("Unable to read clang's AST dump, so can't tell which header declares each item: {err}");
+            return Self::default();
+        }
+        let headers = walker
+            .declarations
+            .iter()
+            .filter_map(|(name, file)| {
+                let header = walker.top_level_header(file)?;
+                let header = include_list
+                    .iter()
+                    .filter(|entry| is_same_header(header, entry))
+                    .max_by_key(|entry| entry.len())?;
+                Some((name.clone(), header.clone()))
+            })
+            .collect();
+        Self { headers }
+    }
+
+    /// The header declaring this item, named as in the `include_cpp!`
+    /// macro, if it's one of those headers or included from one.
+    pub(crate) fn header_for(&self, cpp_name: &str) -> Option<&str> {
+        self.headers.get(cpp_name).map(String::as_str)
+    }
+}
+
+/// Whether clang's path to a file refers to a header as named in an
+/// `#include` directive.
+fn is_same_header(path: &str, include: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let include = include.replace('\\', "/");
+    path == include || path.ends_with(&format!("/{include}"))
+}
+
+#[derive(Default)]
+struct AstWalker {
+    /// The file of the last source location clang wrote.
+    current_file: Option<String>,
+    /// The file which `#include`d each file.
+    included_from: HashMap<String, String>,
+    /// The file containing each declaration, or its definition if it has
+    /// one.
+    declarations: HashMap<String, String>,
+}
+
+impl AstWalker {
+    /// The header `#include`d directly by the headers we gave clang,
+    /// through which this file was reached.
+    fn top_level_header<'a>(&'a self, mut file: &'a str) -> Option<&'a str> {
+        for _ in 0..self.included_from.len() {
+            let includer = self.included_from.get(file)?;
+            if includer == MAIN_FILE {
+                return Some(file);
+            }
+            file = includer;
+        }
+        None
+    }
+
+    fn record_declaration(&mut self, name: String, file: String, is_definition: bool) {
+        if is_definition || !self.declarations.contains_key(&name) {
+            self.declarations.insert(name, file);
+        }
+    }
+}
+
+/// Whether we record declarations of this kind.
+fn is_recorded_kind(kind: &str) -> bool {
+    matches!(
+        kind,
+        "CXXRecordDecl"
+            | "RecordDecl"
+            | "EnumDecl"
+            | "TypedefDecl"
+            | "TypeAliasDecl"
+            | "FunctionDecl"
+            | "VarDecl"
+    )
+}
+
+/// A node in the AST. `scope` is the namespaces and classes enclosing it,
+/// or `None` if it's within something, such as a function, whose contents
+/// we don't record.
+struct NodeSeed<'w, 's> {
+    walker: &'w mut AstWalker,
+    scope: Option<&'s [String]>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an AST node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut kind = None;
+        let mut name = None;
+        let mut file = None;
+        let mut is_implicit = false;
+        let mut is_definition = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "kind" => kind = Some(map.next_value::<String>()?),
+                "name" => name = Some(map.next_value::<String>()?),
+                "isImplicit" => is_implicit = map.next_value()?,
+                "completeDefinition" => is_definition = map.next_value()?,
+                "loc" => {
+                    map.next_value_seed(LocSeed(self.walker))?;
+                    file = self.walker.current_file.clone();
+                }
+                "range" => map.next_value_seed(RangeSeed(self.walker))?,
+                "inner" => {
+                    // clang writes a node's kind and name before its children.
+                    let inner_scope = match (kind.as_deref(), &name, self.scope) {
+                        (
+                            Some("TranslationUnitDecl" | "LinkageSpecDecl" | "ClassTemplateDecl"),
+                            _,
+                            Some(scope),
+                        ) => Some(scope.to_vec()),
+                        (
+                            Some("NamespaceDecl" | "CXXRecordDecl" | "RecordDecl"),
+                            Some(name),
+                            Some(scope),
+                        ) => Some(scope.iter().cloned().chain([name.clone()]).collect()),
+                        // Anonymous namespaces are named without them.
+                        (Some("NamespaceDecl"), None, Some(scope)) => Some(scope.to_vec()),
+                        _ => None,
+                    };
+                    map.next_value_seed(InnerSeed {
+                        walker: self.walker,
+                        scope: inner_scope.as_deref(),
+                    })?;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if let (Some(scope), Some(kind), Some(name), Some(file)) = (self.scope, kind, name, file) {
+            if is_recorded_kind(&kind) && !is_implicit {
+                let name = scope.iter().chain([&name]).cloned().collect::<Vec<_>>();
+                self.walker
+                    .record_declaration(name.join("::"), file, is_definition);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The children of a node.
+struct InnerSeed<'w, 's> {
+    walker: &'w mut AstWalker,
+    scope: Option<&'s [String]>,
+}
+
+impl<'de> DeserializeSeed<'de> for InnerSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for InnerSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of AST nodes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq
+            .next_element_seed(NodeSeed {
+                walker: self.walker,
+                scope: self.scope,
+            })?
+            .is_some()
+        {}
+        Ok(())
+    }
+}
+
+/// The `begin` and `end` source locations of a node.
+struct RangeSeed<'w>(&'w mut AstWalker);
+
+impl<'de> DeserializeSeed<'de> for RangeSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RangeSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a source range")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "begin" | "end" => map.next_value_seed(LocSeed(self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A source location. Within a macro expansion, this is instead a pair
+/// of locations: where the macro was spelled, and where it was expanded.
+struct LocSeed<'w>(&'w mut AstWalker);
+
+impl<'de> DeserializeSeed<'de> for LocSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for LocSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a source location")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut file = None;
+        let mut included_from = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "file" => file = Some(map.next_value::<String>()?),
+                "includedFrom" => included_from = Some(map.next_value::<IncludedFrom>()?.0),
+                "spellingLoc" | "expansionLoc" => map.next_value_seed(LocSeed(self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if file.is_some() {
+            self.0.current_file = file;
+        }
+        if let (Some(file), Some(included_from)) = (&self.0.current_file, included_from) {
+            self.0.included_from.insert(file.clone(), included_from);
+        }
+        Ok(())
+    }
+}
+
+/// The file containing the `#include` of a source location's file.
+struct IncludedFrom(String);
+
+impl<'de> serde::Deserialize<'de> for IncludedFrom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IncludedFromVisitor;
+
+        impl<'de> Visitor<'de> for IncludedFromVisitor {
+            type Value = IncludedFrom;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an include location")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<IncludedFrom, A::Error> {
+                let mut file = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "file" => file = Some(map.next_value::<String>()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                file.map(IncludedFrom)
+                    .ok_or_else(|| serde::de::Error::missing_field("file"))
+            }
+        }
+
+        deserializer.deserialize_map(IncludedFromVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeclaringHeaders;
+
+    /// The AST dump for two headers `a.h` and `b.h`, where `b.h` declares
+    /// `ns::Bar` and includes `detail/c.h`, which defines `ns::Foo`. `a.h`
+    /// forward declares `ns::Foo` and declares `ns::foo`.
+    const AST: &str = r#"{
+        "id": "0x1", "kind": "TranslationUnitDecl", "loc": {}, "range": {"begin": {}, "end": {}},
+        "inner": [
+            {"id": "0x2", "kind": "TypedefDecl", "loc": {}, "range": {"begin": {}, "end": {}},
+             "isImplicit": true, "name": "__int128_t"},
+            {"id": "0x3", "kind": "NamespaceDecl",
+             "loc": {"offset": 10, "file": "/inc/a.h", "line": 1, "col": 11, "tokLen": 2,
+                     "includedFrom": {"file": "<stdin>"}},
+             "range": {"begin": {"offset": 0, "col": 1, "tokLen": 9}, "end": {"offset": 60, "line": 4, "col": 1, "tokLen": 1}},
+             "name": "ns",
+             "inner": [
+                {"id": "0x4", "kind": "CXXRecordDecl",
+                 "loc": {"offset": 25, "line": 2, "col": 8, "tokLen": 3},
+                 "range": {"begin": {"offset": 18, "col": 1, "tokLen": 6}, "end": {"offset": 25, "col": 8, "tokLen": 3}},
+                 "name": "Foo", "tagUsed": "struct"},
+                {"id": "0x5", "kind": "FunctionDecl",
+                 "loc": {"offset": 40, "line": 3, "col": 6, "tokLen": 3},
+                 "range": {"begin": {"offset": 35, "col": 1, "tokLen": 4}, "end": {"offset": 50, "col": 16, "tokLen": 1}},
+                 "name": "foo", "type": {"qualType": "void ()"}}
+             ]},
+            {"id": "0x6", "kind": "NamespaceDecl",
+             "loc": {"offset": 10, "file": "/inc/detail/c.h", "line": 1, "col": 11, "tokLen": 2,
+                     "includedFrom": {"file": "/inc/b.h"}},
+             "range": {"begin": {"offset": 0, "col": 1, "tokLen": 9}, "end": {"offset": 60, "line": 5, "col": 1, "tokLen": 1}},
+             "name": "ns",
+             "inner": [
+                {"id": "0x7", "kind": "CXXRecordDecl",
+                 "loc": {"offset": 25, "line": 2, "col": 8, "tokLen": 3},
+                 "range": {"begin": {"offset": 18, "col": 1, "tokLen": 6}, "end": {"offset": 45, "line": 4, "col": 1, "tokLen": 1}},
+                 "name": "Foo", "tagUsed": "struct", "completeDefinition": true,
+                 "inner": [
+                    {"id": "0x8", "kind": "CXXRecordDecl",
+                     "loc": {"offset": 25, "line": 2, "col": 8, "tokLen": 3},
+                     "range": {"begin": {"offset": 18, "col": 1, "tokLen": 6}, "end": {"offset": 25, "col": 8, "tokLen": 3}},
+                     "isImplicit": true, "name": "Foo", "tagUsed": "struct"},
+                    {"id": "0x9", "kind": "CXXMethodDecl",
+                     "loc": {"offset": 35, "line": 3, "col": 10, "tokLen": 3},
+                     "range": {"begin": {"offset": 30, "col": 5, "tokLen": 4}, "end": {"offset": 42, "col": 17, "tokLen": 1}},
+                     "name": "get",
+                     "inner": [
+                        {"id": "0xa", "kind": "CompoundStmt",
+                         "range": {"begin": {"spellingLoc": {"offset": 5, "file": "/usr/include/assert.h", "line": 1, "col": 1, "tokLen": 1, "includedFrom": {"file": "/inc/detail/c.h"}},
+                                             "expansionLoc": {"offset": 40, "file": "/inc/detail/c.h", "line": 3, "col": 15, "tokLen": 1}},
+                                   "end": {"offset": 42, "col": 17, "tokLen": 1}}}
+                     ]}
+                 ]}
+             ]},
+            {"id": "0xb", "kind": "NamespaceDecl",
+             "loc": {"offset": 30, "file": "/inc/b.h", "line": 2, "col": 11, "tokLen": 2,
+                     "includedFrom": {"file": "<stdin>"}},
+             "range": {"begin": {"offset": 20, "col": 1, "tokLen": 9}, "end": {"offset": 70, "line": 4, "col": 1, "tokLen": 1}},
+             "name": "ns",
+             "inner": [
+                {"id": "0xc", "kind": "CXXRecordDecl",
+                 "loc": {"offset": 45, "line": 3, "col": 8, "tokLen": 3},
+                 "range": {"begin": {"offset": 38, "col": 1, "tokLen": 6}, "end": {"offset": 60, "col": 23, "tokLen": 1}},
+                 "name": "Bar", "tagUsed": "struct", "completeDefinition": true}
+             ]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_declaring_headers() {
+        let include_list = vec!["a.h".to_string(), "b.h".to_string()];
+        let headers = DeclaringHeaders::from_ast_dump(AST.as_bytes(), &include_list);
+        assert_eq!(headers.header_for("ns::foo"), Some("a.h"));
+        assert_eq!(headers.header_for("ns::Foo"), Some("b.h"));
+        assert_eq!(headers.header_for("ns::Bar"), Some("b.h"));
+        assert_eq!(headers.header_for("ns::Foo::get"), None);
+        assert_eq!(headers.header_for("__int128_t"), None);
+    }
+
+    #[test]
+    fn test_unreadable_ast() {
+        let headers = DeclaringHeaders::from_ast_dump(b"{\"inner\": [", &["a.h".to_string()]);
+        assert_eq!(headers.header_for("ns::foo"), None);
+    }
+}
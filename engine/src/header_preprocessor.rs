@@ -0,0 +1,204 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use tempfile::TempDir;
+
+/// A header named in an `#include` directive within `include_cpp!`, which
+/// a [`HeaderPreprocessor`] may rewrite before bindgen parses it.
+pub struct HeaderToPreprocess {
+    /// The name used in the `#include` directive, e.g. `foo/bar.h`.
+    pub include_name: String,
+    /// Where this header was found within the include directories.
+    pub path: PathBuf,
+    /// The contents of the header. Alter this to have bindgen parse
+    /// something different.
+    pub contents: String,
+}
+
+/// The headers for a single `include_cpp!`, as given to a
+/// [`HeaderPreprocessor`].
+pub struct HeadersToPreprocess {
+    /// Each header named by an `#include` directive which could be found
+    /// in the include directories, in order. Headers which couldn't be
+    /// found there, such as system headers, aren't listed and are passed
+    /// to bindgen untouched.
+    pub headers: Vec<HeaderToPreprocess>,
+    /// Extra C++ to put before all the headers, for example `#define`s.
+    pub prologue: String,
+    /// The arguments with which bindgen will run clang. Changing these
+    /// has no effect.
+    pub clang_args: Vec<String>,
+}
+
+/// A function called with the headers for each `include_cpp!` before
+/// bindgen parses them, which may rewrite them or add to them - for
+/// instance to remove macros which bindgen can't handle, or to add
+/// annotations. autocxx writes any rewritten headers to a temporary
+/// directory, under their original include names, and puts that directory
+/// first in the include path. That way, the rewritten version is also seen
+/// wherever one of the headers includes another. The directory is removed
+/// once bindgen, and any `AUTOCXX_PREPROCESS` or `AUTOCXX_REPRO_CASE`
+/// output, is done with it.
+///
+/// Only bindgen sees the rewritten headers: the generated C++ still
+/// includes the original ones, so the two must declare the same things
+/// with the same layout.
+///
+/// An error stops generation of the bindings.
+pub struct HeaderPreprocessor<'a>(
+    pub Box<dyn 'a + Fn(&mut HeadersToPreprocess) -> Result<(), String>>,
+);
+
+/// The results of running a [`HeaderPreprocessor`]: the text to give
+/// bindgen in place of the usual list of `#include`s, and the directory of
+/// rewritten headers which overlays the include directories.
+pub(crate) struct PreprocessedHeaders {
+    pub(crate) header: String,
+    /// Arguments for clang so that rewritten headers can still find others
+    /// relative to where the originals were.
+    pub(crate) extra_clang_args: Vec<String>,
+    overlay: Option<TempDir>,
+}
+
+impl PreprocessedHeaders {
+    /// The include directories with which to parse the rewritten headers:
+    /// the overlay, if anything was rewritten, then the originals.
+    pub(crate) fn inc_dirs(&self, inc_dirs: &[PathBuf]) -> Vec<PathBuf> {
+        self.overlay
+            .iter()
+            .map(|dir| dir.path().to_path_buf())
+            .chain(inc_dirs.iter().cloned())
+            .collect()
+    }
+}
+
+impl HeaderPreprocessor<'_> {
+    pub(crate) fn run(
+        &self,
+        inclusions: &[String],
+        inc_dirs: &[PathBuf],
+        clang_args: Vec<String>,
+    ) -> Result<PreprocessedHeaders, String> {
+        let mut headers = HeadersToPreprocess {
+            headers: Vec::new(),
+            prologue: String::new(),
+            clang_args,
+        };
+        for include_name in inclusions {
+            let Some(path) = inc_dirs
+                .iter()
+                .map(|dir| dir.join(include_name))
+                .find(|path| path.is_file())
+            else {
+                continue;
+            };
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("unable to read {}: {e}", path.display()))?;
+            headers.headers.push(HeaderToPreprocess {
+                include_name: include_name.clone(),
+                path,
+                contents,
+            });
+        }
+        let originals: Vec<_> = headers
+            .headers
+            .iter()
+            .map(|header| (header.include_name.clone(), header.contents.clone()))
+            .collect();
+        self.0(&mut headers)?;
+
+        let mut overlay: Option<TempDir> = None;
+        let mut extra_clang_args = Vec::new();
+        for header in &headers.headers {
+            let unchanged = originals.iter().any(|(name, contents)| {
+                *name == header.include_name && *contents == header.contents
+            });
+            if unchanged {
+                continue;
+            }
+            let dir = match &overlay {
+                Some(dir) => dir.path(),
+                None => overlay
+                    .insert(tempfile::tempdir().map_err(|e| e.to_string())?)
+                    .path(),
+            };
+            write_rewritten_header(dir, header)?;
+            if let Some(parent) = header.path.parent() {
+                extra_clang_args.push(format!("-iquote{}", parent.display()));
+            }
+        }
+        let header = std::iter::once(headers.prologue)
+            .chain(
+                inclusions
+                    .iter()
+                    .map(|include_name| format!("#include \"{include_name}\"")),
+            )
+            .join("\n");
+        Ok(PreprocessedHeaders {
+            header,
+            extra_clang_args,
+            overlay,
+        })
+    }
+}
+
+fn write_rewritten_header(dir: &Path, header: &HeaderToPreprocess) -> Result<(), String> {
+    let path = dir.join(&header.include_name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, &header.contents)
+        .map_err(|e| format!("unable to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderPreprocessor;
+
+    #[test]
+    fn test_overlay() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/a.h"), "#error unprocessed\nint a();\n").unwrap();
+        std::fs::write(dir.path().join("b.h"), "int b();\n").unwrap();
+        let preprocessor = HeaderPreprocessor(Box::new(|headers| {
+            headers.prologue = "#define PREPROCESSED".into();
+            for header in &mut headers.headers {
+                header.contents = header.contents.replace("#error unprocessed\n", "");
+            }
+            Ok(())
+        }));
+        let inc_dirs = vec![dir.path().to_path_buf()];
+        let preprocessed = preprocessor
+            .run(&["sub/a.h".into(), "b.h".into()], &inc_dirs, Vec::new())
+            .unwrap();
+        assert_eq!(
+            preprocessed.header,
+            "#define PREPROCESSED\n#include \"sub/a.h\"\n#include \"b.h\""
+        );
+        let overlay_inc_dirs = preprocessed.inc_dirs(&inc_dirs);
+        assert_eq!(overlay_inc_dirs.len(), 2);
+        assert_eq!(overlay_inc_dirs[1], inc_dirs[0]);
+        let overlay = &overlay_inc_dirs[0];
+        assert_eq!(
+            std::fs::read_to_string(overlay.join("sub/a.h")).unwrap(),
+            "int a();\n"
+        );
+        // Unchanged headers are found in their original directories.
+        assert!(!overlay.join("b.h").exists());
+        assert_eq!(
+            preprocessed.extra_clang_args,
+            [format!("-iquote{}", dir.path().join("sub").display())]
+        );
+        drop(preprocessed);
+        assert!(!overlay.exists());
+    }
+}
@@ -165,7 +165,13 @@ impl TypeDatabase {
         // when we encounter something like 'std::unique_ptr'
         // in the bindgen-generated bindings, we'll immediately
         // start to refer to that as 'UniquePtr' henceforth.
-        let canonical_name = self.canonical_names.get(ty).unwrap_or(ty);
+        // The standard library may declare some, such as std::pmr::string,
+        // within an inline namespace.
+        let canonical_name = self
+            .canonical_names
+            .get(ty)
+            .or_else(|| self.canonical_names.get(&ty.without_inline_namespaces()))
+            .unwrap_or(ty);
         self.by_rs_name.get(canonical_name)
     }
 
@@ -552,5 +558,21 @@ fn create_type_database() -> TypeDatabase {
         false,
         false,
     ));
+    // std::byte is presented to Rust as a plain u8. Any function taking or
+    // returning one gets a C++ wrapper which casts between the two.
+    db.canonical_names.insert(
+        QualifiedName::new_from_cpp_name("std::byte"),
+        QualifiedName::new_from_cpp_name("u8"),
+    );
+    // Likewise std::pmr containers are presented as their std equivalents,
+    // and the C++ wrapper copies between the two.
+    db.canonical_names.insert(
+        QualifiedName::new_from_cpp_name("std::pmr::string"),
+        QualifiedName::new_from_cpp_name("cxx::CxxString"),
+    );
+    db.canonical_names.insert(
+        QualifiedName::new_from_cpp_name("std::pmr::vector"),
+        QualifiedName::new_from_cpp_name("cxx::CxxVector"),
+    );
     db
 }
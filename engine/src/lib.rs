@@ -13,28 +13,45 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(feature = "nightly", feature(doc_cfg))]
 
+mod api_summary;
 mod ast_discoverer;
+mod config_summary;
 mod conversion;
+mod cpp_tokenizer;
 mod cxxbridge;
+mod declaring_headers;
+mod header_preprocessor;
 mod known_types;
 mod minisyn;
+mod msvc_declarations;
 mod output_generators;
 mod parse_callbacks;
 mod parse_file;
+pub mod plugins;
+mod resumable;
 mod rust_pretty_printer;
+mod source_declarations;
+mod time_report;
 mod types;
 
 #[cfg(any(test, feature = "build"))]
 mod builder;
+#[cfg(any(test, feature = "build"))]
+mod symbol_check;
 
+pub use api_summary::generate_api_summary;
 use autocxx_bindgen::BindgenError;
-use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
+use autocxx_parser::IncludeCppConfig;
+pub use autocxx_parser::{AppliedDirective, UnsafePolicy};
 use conversion::BridgeConverter;
+use declaring_headers::DeclaringHeaders;
 use miette::{SourceOffset, SourceSpan};
+use msvc_declarations::MsvcDeclarations;
 use parse_callbacks::{AutocxxParseCallbacks, ParseCallbackResults, UnindexedParseCallbackResults};
 use parse_file::CppBuildable;
 use proc_macro2::TokenStream as TokenStream2;
 use regex::Regex;
+use source_declarations::is_cpp_source;
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -45,6 +62,7 @@ use std::{
     process::{Command, Stdio},
 };
 use tempfile::NamedTempFile;
+use time_report::PhaseTimer;
 
 use quote::ToTokens;
 use syn::Result as ParseResult;
@@ -67,8 +85,15 @@ use autocxx_bindgen as bindgen;
 pub use builder::{
     Builder, BuilderBuild, BuilderContext, BuilderError, BuilderResult, BuilderSuccess,
 };
-pub use output_generators::{generate_rs_archive, generate_rs_single, RsOutput};
+pub use config_summary::ConfigSummary;
+pub use conversion::IncludeRequirement;
+pub use header_preprocessor::{HeaderPreprocessor, HeaderToPreprocess, HeadersToPreprocess};
+pub use output_generators::{
+    generate_include_metadata, generate_rs_archive, generate_rs_single, RsOutput,
+};
 pub use parse_file::{parse_file, ParseError, ParsedFile};
+pub use resumable::ResumeOptions;
+pub use time_report::TimeReport;
 
 pub use cxx_gen::HEADER;
 
@@ -129,6 +154,18 @@ pub enum Error {
     Conversion(conversion::ConvertError),
     #[error("Using `unsafe_references_wrapped` requires the Rust nightly `arbitrary_self_types` feature")]
     WrappedReferencesButNoArbitrarySelfTypes,
+    #[error("The time budget ran out before bindings for mod {0} could be generated. Run again with the same state directory to carry on from here.")]
+    TimeBudgetExceeded(String),
+    #[error("Unable to save generation state to {0}: {1}")]
+    SavingState(PathBuf, std::io::Error),
+    #[error("Unable to preprocess the headers to find MSVC properties and UUIDs: {0}")]
+    MsvcPreprocessing(std::io::Error),
+    #[error("Unable to parse the headers to find which declares each item: {0}")]
+    FindingDeclaringHeaders(std::io::Error),
+    #[error("The header preprocessor failed: {0}")]
+    HeaderPreprocessing(String),
+    #[error("Unable to find the declarations in the C++ source file {0}: {1}")]
+    SourceDeclarations(String, std::io::Error),
 }
 
 /// Result type.
@@ -140,6 +177,11 @@ struct GenerationResults {
     #[allow(dead_code)]
     inc_dirs: Vec<PathBuf>,
     cxxgen_header_name: String,
+    include_requirements: Vec<IncludeRequirement>,
+    /// Directive misses downgraded by `conversion_errors!(lenient)`.
+    warnings: Vec<String>,
+    /// Absent if these results were loaded from saved state.
+    time_report: Option<TimeReport>,
 }
 enum State {
     NotGenerated,
@@ -155,6 +197,16 @@ pub struct CodegenOptions<'a> {
     pub force_wrapper_gen: bool,
     /// Options about the C++ code generation.
     pub cpp_codegen_options: CppCodegenOptions<'a>,
+    /// If set, save the results for each `include_cpp!` so that later
+    /// runs can skip it, and optionally stop once a time budget runs out.
+    pub resume: Option<ResumeOptions>,
+    /// If set, called to rewrite the headers for each `include_cpp!`
+    /// before bindgen parses them.
+    pub header_preprocessor: Option<HeaderPreprocessor<'a>>,
+    /// Whether to find the header declaring each C++ item, so that the
+    /// include requirements list only that header. This costs an extra
+    /// run of clang.
+    pub find_declaring_headers: bool,
 }
 
 const AUTOCXX_CLANG_ARGS: &[&str; 4] = &["-x", "c++", "-std=c++14", "-DBINDGEN"];
@@ -325,6 +377,32 @@ impl IncludeCppEngine {
         )
     }
 
+    /// The inclusions for the generated C++. These are as for bindgen,
+    /// except that C++ source files are replaced by their declarations.
+    fn build_cpp_inclusions(
+        &self,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+    ) -> Result<String> {
+        self.config
+            .inclusions
+            .iter()
+            .map(|path| {
+                if is_cpp_source(path) {
+                    source_declarations::find_declarations(
+                        path,
+                        inc_dirs,
+                        extra_clang_args,
+                        &self.config,
+                    )
+                    .map_err(|e| Error::SourceDeclarations(path.clone(), e))
+                } else {
+                    Ok(format!("#include \"{path}\"\n"))
+                }
+            })
+            .collect()
+    }
+
     fn make_bindgen_builder(
         &self,
         inc_dirs: &[PathBuf],
@@ -343,6 +421,15 @@ impl IncludeCppEngine {
 
         let mut builder = bindgen::builder()
             .clang_args(make_clang_args(inc_dirs, extra_clang_args))
+            // Later -std arguments override earlier ones, so this beats any
+            // standard given to the builder for the whole build.
+            .clang_args(
+                self.config
+                    .get_cpp_std()
+                    .map(|standard| format!("-std={standard}")),
+            )
+            .clang_args(self.config.get_clang_args())
+            .clang_args(self.config.msvc_extensions().then_some("-fms-extensions"))
             .derive_copy(false)
             .derive_debug(false)
             .default_enum_style(bindgen::EnumVariation::Rust {
@@ -367,7 +454,8 @@ impl IncludeCppEngine {
             .raw_line(raw_line)
             .every_module_raw_line(all_module_raw_line)
             .generate_private_functions(true)
-            .layout_tests(false) // TODO revisit later
+            // We read field offsets from the layout tests, then drop them.
+            .layout_tests(true)
             .wrap_unsafe_ops(true);
 
         // 3. Passes allowlist and other options to the bindgen::Builder equivalent
@@ -387,6 +475,10 @@ impl IncludeCppEngine {
             builder = builder.opaque_type(item);
         }
 
+        for item in self.config.get_bindgen_blocklist() {
+            builder = builder.blocklist_item(item);
+        }
+
         // At this point it woul be great to use `Builder::opaque_type` for
         // everything which is on the allowlist but not on the POD list.
         // This would free us from a large proportion of bindgen bugs which
@@ -410,6 +502,13 @@ impl IncludeCppEngine {
         builder
     }
 
+    /// Returns the effective configuration of this `include_cpp!`, with
+    /// every directive applied to it and where each came from. This
+    /// doesn't need `generate` to have been called.
+    pub fn config_summary(&self) -> ConfigSummary {
+        ConfigSummary::new(&self.config)
+    }
+
     pub fn get_rs_filename(&self) -> String {
         self.config.get_rs_filename()
     }
@@ -426,6 +525,48 @@ impl IncludeCppEngine {
         }
     }
 
+    /// Returns the headers which C++ code needs to `#include` in order to
+    /// use each generated item. Call `generate` first.
+    pub fn get_include_requirements(&self) -> &[IncludeRequirement] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.include_requirements,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// Returns the name of the header generated by cxx, which declares
+    /// any items implemented in Rust. Call `generate` first.
+    pub fn get_cxxgen_header_name(&self) -> Option<&str> {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => Some(&gen_results.cxxgen_header_name),
+            State::ParseOnly => None,
+        }
+    }
+
+    /// Returns any warnings about directives which named something that
+    /// doesn't exist, which `conversion_errors!(lenient)` stopped from being
+    /// errors. Call `generate` first.
+    pub fn get_warnings(&self) -> &[String] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.warnings,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// Returns a breakdown of the time taken to generate these bindings.
+    /// Call `generate` first. Returns `None` if the bindings were loaded
+    /// from state saved by an earlier run.
+    pub fn get_time_report(&self) -> Option<&TimeReport> {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => gen_results.time_report.as_ref(),
+            State::ParseOnly => None,
+        }
+    }
+
     /// Returns the name of the mod which this `include_cpp!` will generate.
     /// Can and should be used to ensure multiple mods in a file don't conflict.
     pub fn get_mod_name(&self) -> String {
@@ -474,23 +615,109 @@ impl IncludeCppEngine {
             return Err(Error::WrappedReferencesButNoArbitrarySelfTypes);
         }
 
+        let mod_name = self.config.get_mod_name();
+        let state_file = codegen_options.resume.as_ref().map(|resume| {
+            resumable::state_file_path(
+                resume,
+                &self.config,
+                &inc_dirs,
+                extra_clang_args,
+                codegen_options,
+            )
+        });
+        if let Some(state_file) = &state_file {
+            if let Some((gen_results, dependencies)) = resumable::load(state_file, inc_dirs.clone())
+            {
+                info!("Reusing saved bindings from {}", state_file.display());
+                if let Some(dep_recorder) = &dep_recorder {
+                    for dependency in &dependencies {
+                        dep_recorder.record_header_file_dependency(dependency);
+                    }
+                }
+                self.state = State::Generated(Box::new(gen_results));
+                return Ok(());
+            }
+        }
+        if codegen_options
+            .resume
+            .as_ref()
+            .map(ResumeOptions::out_of_time)
+            .unwrap_or_default()
+        {
+            return Err(Error::TimeBudgetExceeded(mod_name.to_string()));
+        }
+        // If we're saving state, we need to know which headers it depends upon.
+        let dependencies = Rc::new(RefCell::new(Vec::new()));
+        let dep_recorder: Option<Box<dyn RebuildDependencyRecorder>> = if state_file.is_some() {
+            Some(Box::new(resumable::DependencyCollector::new(
+                dep_recorder,
+                dependencies.clone(),
+            )))
+        } else {
+            dep_recorder
+        };
+
+        let mut timer = PhaseTimer::new();
         let parse_callback_results =
             Rc::new(RefCell::new(UnindexedParseCallbackResults::default()));
-        let mod_name = self.config.get_mod_name();
+        let header_contents = self.build_header();
+        // Any rewritten headers must outlive bindgen's use of them, and
+        // that of any reproduction case.
+        let preprocessed_headers = codegen_options
+            .header_preprocessor
+            .as_ref()
+            .map(|preprocessor| {
+                preprocessor.run(
+                    &self.config.inclusions,
+                    &inc_dirs,
+                    make_clang_args(&inc_dirs, extra_clang_args).collect(),
+                )
+            })
+            .transpose()
+            .map_err(Error::HeaderPreprocessing)?;
+        let mut bindgen_clang_args = extra_clang_args.to_vec();
+        let (bindgen_header, bindgen_inc_dirs) = match &preprocessed_headers {
+            Some(preprocessed_headers) => {
+                bindgen_clang_args.extend(
+                    preprocessed_headers
+                        .extra_clang_args
+                        .iter()
+                        .map(String::as_str),
+                );
+                (
+                    &preprocessed_headers.header,
+                    preprocessed_headers.inc_dirs(&inc_dirs),
+                )
+            }
+            None => (&header_contents, inc_dirs.clone()),
+        };
         let mut builder = self
-            .make_bindgen_builder(&inc_dirs, extra_clang_args)
+            .make_bindgen_builder(&bindgen_inc_dirs, &bindgen_clang_args)
             .parse_callbacks(Box::new(AutocxxParseCallbacks::new(
                 dep_recorder,
                 parse_callback_results.clone(),
             )));
-        let header_contents = self.build_header();
-        self.dump_header_if_so_configured(&header_contents, &inc_dirs, extra_clang_args);
-        let header_and_prelude = format!("{}\n\n{}", known_types().get_prelude(), header_contents);
+        self.dump_header_if_so_configured(bindgen_header, &bindgen_inc_dirs, &bindgen_clang_args);
+        let header_and_prelude = format!("{}\n\n{}", known_types().get_prelude(), bindgen_header);
         log::info!("Header and prelude for bindgen:\n{}", header_and_prelude);
         builder = builder.header_contents("example.hpp", &header_and_prelude);
 
+        let msvc_declarations = if self.config.msvc_extensions() {
+            MsvcDeclarations::find(
+                &header_and_prelude,
+                &bindgen_inc_dirs,
+                &bindgen_clang_args,
+                &self.config,
+            )
+            .map_err(Error::MsvcPreprocessing)?
+        } else {
+            MsvcDeclarations::default()
+        };
+
         let bindings = builder.generate().map_err(Error::Bindgen)?;
+        timer.phase_done("bindgen");
         let bindings = self.parse_bindings(bindings)?;
+        timer.phase_done("parsing bindings");
         let parse_callback_results = parse_callback_results.take();
         log::info!("Parse callback results: {:?}", parse_callback_results);
 
@@ -502,16 +729,41 @@ impl IncludeCppEngine {
             .cloned()
             .unwrap_or_else(|| Rc::new("".to_string()));
 
-        let converter = BridgeConverter::new(&self.config.inclusions, &self.config);
+        // C++ source files are only for bindgen: the generated C++ gets
+        // just their declarations.
+        let cpp_inclusions = self.build_cpp_inclusions(&inc_dirs, extra_clang_args)?;
+        let header_inclusions: Vec<String> = self
+            .config
+            .inclusions
+            .iter()
+            .filter(|path| !is_cpp_source(path))
+            .cloned()
+            .collect();
+        let declaring_headers = if codegen_options.find_declaring_headers {
+            DeclaringHeaders::find(
+                &header_and_prelude,
+                &inc_dirs,
+                extra_clang_args,
+                &self.config,
+                &header_inclusions,
+            )
+            .map_err(Error::FindingDeclaringHeaders)?
+        } else {
+            DeclaringHeaders::default()
+        };
+        let converter = BridgeConverter::new(&header_inclusions, &self.config);
 
         let conversion = converter
             .convert(
                 bindings,
                 parse_callback_results.index(),
                 self.config.unsafe_policy.clone(),
-                header_contents,
+                cpp_inclusions,
                 codegen_options,
                 &source_file_contents,
+                &msvc_declarations,
+                &declaring_headers,
+                &mut timer,
             )
             .map_err(Error::Conversion)?;
         let items = conversion.rs;
@@ -530,12 +782,24 @@ impl IncludeCppEngine {
             "New bindings:\n{}",
             rust_pretty_printer::pretty_print(&new_bindings)
         );
-        self.state = State::Generated(Box::new(GenerationResults {
+        let gen_results = GenerationResults {
             item_mod: new_bindings,
             cpp: conversion.cpp,
             inc_dirs,
             cxxgen_header_name: conversion.cxxgen_header_name,
-        }));
+            include_requirements: conversion.include_requirements,
+            warnings: conversion
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect(),
+            time_report: Some(timer.into_report()),
+        };
+        if let Some(state_file) = state_file {
+            resumable::save(&state_file, &gen_results, &dependencies.borrow())
+                .map_err(|e| Error::SavingState(state_file, e))?;
+        }
+        self.state = State::Generated(Box::new(gen_results));
         Ok(())
     }
 
@@ -771,9 +1035,9 @@ impl AutocxxgenHeaderNamer<'_> {
 }
 
 /// Function to generate the desired name of the header containing cxx's
-/// declarations.
+/// declarations, given the name of the Rust mod containing the bindings.
 /// Newtype wrapper so we can give it a [`Default`].
-pub struct CxxgenHeaderNamer<'a>(pub Box<dyn 'a + Fn() -> String>);
+pub struct CxxgenHeaderNamer<'a>(pub Box<dyn 'a + Fn(String) -> String>);
 
 impl Default for CxxgenHeaderNamer<'static> {
     fn default() -> Self {
@@ -785,7 +1049,7 @@ impl Default for CxxgenHeaderNamer<'static> {
         // that you're generating bindings to multiple include_cpp!
         // or a mix of include_cpp! and #[cxx::bridge] bindings.
         let header_counter = Rc::new(RefCell::new(0));
-        Self(Box::new(move || {
+        Self(Box::new(move |_| {
             let header_counter = header_counter.clone();
             let header_counter_cell = header_counter.as_ref();
             let mut header_counter = header_counter_cell.borrow_mut();
@@ -802,8 +1066,8 @@ impl Default for CxxgenHeaderNamer<'static> {
 }
 
 impl CxxgenHeaderNamer<'_> {
-    fn name_header(&self) -> String {
-        self.0()
+    fn name_header(&self, mod_name: String) -> String {
+        self.0(mod_name)
     }
 }
 
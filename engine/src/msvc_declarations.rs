@@ -0,0 +1,328 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finds MSVC-specific declarations which bindgen doesn't tell us about:
+//! `__declspec(property(get=..., put=...))` members, and the
+//! `__declspec(uuid("..."))` attached to COM interfaces, which C++ code
+//! retrieves using `__uuidof`. This is only done if the `msvc_extensions!`
+//! directive is given, since it needs an extra run of the preprocessor.
+
+use autocxx_parser::IncludeCppConfig;
+use indexmap::map::IndexMap as HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::{cpp_tokenizer::tokenize, get_clang_path, make_clang_args};
+
+/// A `__declspec(property)` member of a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MsvcProperty {
+    /// The class, named as in directives, e.g. `ns::Widget`.
+    class: String,
+    name: String,
+    getter: Option<String>,
+    putter: Option<String>,
+}
+
+/// The MSVC-specific declarations found in a set of headers.
+#[derive(Debug, Default)]
+pub(crate) struct MsvcDeclarations {
+    properties: Vec<MsvcProperty>,
+    uuids: HashMap<String, String>,
+}
+
+impl MsvcDeclarations {
+    /// Preprocess the headers in the same way as bindgen will see them,
+    /// then look for MSVC declarations.
+    pub(crate) fn find(
+        header: &str,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+        config: &IncludeCppConfig,
+    ) -> std::io::Result<Self> {
+        let mut child = Command::new(get_clang_path())
+            .arg("-E")
+            .arg("-P")
+            .args(make_clang_args(inc_dirs, extra_clang_args))
+            .args(
+                config
+                    .get_cpp_std()
+                    .map(|standard| format!("-std={standard}")),
+            )
+            .args(config.get_clang_args())
+            .arg("-fms-extensions")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(header.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(Self::from_preprocessed(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    fn from_preprocessed(text: &str) -> Self {
+        let tokens: Vec<_> = tokenize(text).into_iter().map(|(_, tok)| tok).collect();
+        let mut found = Self::default();
+        // Each open brace, and the namespace or class (if any) it opens.
+        let mut scopes: Vec<Scope> = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            match tokens[i] {
+                "{" => scopes.push(Scope::Other),
+                "}" => {
+                    scopes.pop();
+                }
+                "namespace" => {
+                    if let Some(name) = tokens.get(i + 1).filter(|t| is_ident(t)) {
+                        if tokens.get(i + 2) == Some(&"{") {
+                            scopes.push(Scope::Namespace(name.to_string()));
+                            i += 3;
+                            continue;
+                        }
+                    }
+                }
+                "class" | "struct" if tokens.get(i.wrapping_sub(1)) != Some(&"enum") => {
+                    let (uuid, name, next) = parse_class_head(&tokens, i + 1);
+                    if let Some(name) = name {
+                        let qualified = qualify(&scopes, name);
+                        if let Some(uuid) = uuid {
+                            found.uuids.insert(qualified.clone(), uuid.to_string());
+                        }
+                        if tokens.get(next) == Some(&"{") {
+                            scopes.push(Scope::Class(qualified));
+                            i = next + 1;
+                            continue;
+                        }
+                    }
+                }
+                "__declspec" if tokens.get(i + 2) == Some(&"property") => {
+                    if let Some(Scope::Class(class)) = scopes.last() {
+                        if let Some((property, next)) = parse_property(&tokens, i + 3, class) {
+                            found.properties.push(property);
+                            i = next;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        found
+    }
+
+    /// The names of the Rust accessor methods which should forward to the
+    /// given method, named as in directives, e.g. `ns::Widget::GetWidth`.
+    pub(crate) fn property_accessors(&self, method: &str) -> Vec<String> {
+        self.properties
+            .iter()
+            .flat_map(|prop| {
+                let is = |accessor: &Option<String>| {
+                    accessor
+                        .as_ref()
+                        .is_some_and(|accessor| format!("{}::{}", prop.class, accessor) == method)
+                };
+                let getter = is(&prop.getter).then(|| format!("get_{}", prop.name));
+                let putter = is(&prop.putter).then(|| format!("put_{}", prop.name));
+                getter.into_iter().chain(putter)
+            })
+            .collect()
+    }
+
+    /// The UUID given by `__declspec(uuid)` for this type, if any.
+    pub(crate) fn uuid(&self, ty: &str) -> Option<&str> {
+        self.uuids.get(ty).map(String::as_str)
+    }
+}
+
+enum Scope {
+    Namespace(String),
+    Class(String),
+    Other,
+}
+
+/// Name a type as bindgen will: namespaces separated by `::`, nested
+/// classes by `_`.
+fn qualify(scopes: &[Scope], name: &str) -> String {
+    let mut qualified = String::new();
+    for scope in scopes {
+        match scope {
+            Scope::Namespace(ns) => {
+                qualified.push_str(ns);
+                qualified.push_str("::");
+            }
+            Scope::Class(class) => {
+                qualified = format!("{class}_");
+            }
+            Scope::Other => {}
+        }
+    }
+    qualified.push_str(name);
+    qualified
+}
+
+/// After `class` or `struct`, skip any `__declspec(...)`s (noting a
+/// `uuid`) and find the name. Returns the index of the token after the
+/// name and any base classes, which will be `{` for a definition.
+fn parse_class_head<'a>(
+    tokens: &[&'a str],
+    mut i: usize,
+) -> (Option<&'a str>, Option<&'a str>, usize) {
+    let mut uuid = None;
+    while tokens.get(i) == Some(&"__declspec") {
+        let end = matching_paren(tokens, i + 1);
+        if tokens.get(i + 2) == Some(&"uuid") {
+            uuid = tokens
+                .get(i + 4)
+                .and_then(|t| t.strip_prefix('"'))
+                .and_then(|t| t.strip_suffix('"'));
+        }
+        i = end + 1;
+    }
+    let name = match tokens.get(i) {
+        Some(name) if is_ident(name) => *name,
+        _ => return (uuid, None, i),
+    };
+    i += 1;
+    // Skip `final` and any base classes, which may be templates. A `>`
+    // we didn't open means this was a template parameter.
+    let mut angle_depth = 0;
+    while let Some(tok) = tokens.get(i) {
+        match *tok {
+            "<" => angle_depth += 1,
+            ">" if angle_depth > 0 => angle_depth -= 1,
+            "{" | ";" | "(" | ")" | "=" | ">" | "*" | "&" => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    (uuid, Some(name), i)
+}
+
+/// Parse `(get=X, put=Y)) Type Name;` starting at the `(` after
+/// `property`. Array properties (`Name[]`) aren't supported.
+fn parse_property(tokens: &[&str], i: usize, class: &str) -> Option<(MsvcProperty, usize)> {
+    let args_end = matching_paren(tokens, i);
+    let mut getter = None;
+    let mut putter = None;
+    for arg in tokens[i + 1..args_end].split(|t| *t == ",") {
+        match arg {
+            ["get", "=", accessor] => getter = Some(accessor.to_string()),
+            ["put", "=", accessor] => putter = Some(accessor.to_string()),
+            _ => {}
+        }
+    }
+    let semicolon = i + tokens[i..].iter().position(|t| *t == ";")?;
+    let name = tokens[semicolon - 1];
+    if !is_ident(name) {
+        return None;
+    }
+    Some((
+        MsvcProperty {
+            class: class.to_string(),
+            name: name.to_string(),
+            getter,
+            putter,
+        },
+        semicolon + 1,
+    ))
+}
+
+/// The index of the `)` matching the `(` at `i`.
+fn matching_paren(tokens: &[&str], i: usize) -> usize {
+    let mut depth = 0;
+    for (offset, tok) in tokens[i..].iter().enumerate() {
+        match *tok {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + offset;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+fn is_ident(tok: &str) -> bool {
+    tok.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MsvcDeclarations;
+
+    #[test]
+    fn test_find_properties() {
+        let found = MsvcDeclarations::from_preprocessed(
+            "namespace ui {
+                class Widget {
+                public:
+                    int GetWidth() const;
+                    void PutWidth(int w);
+                    __declspec(property(get = GetWidth, put = PutWidth)) int Width;
+                    __declspec(property(get = GetHeight)) unsigned int Height;
+                    struct Inner {
+                        __declspec(property(get = GetDepth)) int Depth;
+                    };
+                };
+            }",
+        );
+        assert_eq!(
+            found.property_accessors("ui::Widget::GetWidth"),
+            vec!["get_Width".to_string()]
+        );
+        assert_eq!(
+            found.property_accessors("ui::Widget::PutWidth"),
+            vec!["put_Width".to_string()]
+        );
+        assert_eq!(
+            found.property_accessors("ui::Widget::GetHeight"),
+            vec!["get_Height".to_string()]
+        );
+        assert_eq!(
+            found.property_accessors("ui::Widget_Inner::GetDepth"),
+            vec!["get_Depth".to_string()]
+        );
+        assert!(found.property_accessors("ui::Widget::GetDepth").is_empty());
+    }
+
+    #[test]
+    fn test_find_uuids() {
+        let found = MsvcDeclarations::from_preprocessed(
+            r#"struct __declspec(uuid("00000000-0000-0000-C000-000000000046")) __declspec(novtable) IUnknown {
+                virtual unsigned long AddRef() = 0;
+            };
+            namespace com {
+                class __declspec(uuid("12345678-9abc-def0-1234-56789abcdef0")) IWidget;
+                enum class Mode { A, B };
+            }"#,
+        );
+        assert_eq!(
+            found.uuid("IUnknown"),
+            Some("00000000-0000-0000-C000-000000000046")
+        );
+        assert_eq!(
+            found.uuid("com::IWidget"),
+            Some("12345678-9abc-def0-1234-56789abcdef0")
+        );
+        assert_eq!(found.uuid("com::Mode"), None);
+    }
+}
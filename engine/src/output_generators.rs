@@ -7,6 +7,8 @@
 // except according to those terms.
 
 use autocxx_parser::{IncludeCppConfig, MultiBindings};
+
+use crate::IncludeCppEngine;
 use proc_macro2::TokenStream;
 
 /// Opaque structure representing the Rust which needs to be generated
@@ -44,3 +46,29 @@ pub fn generate_rs_single(rs_output: RsOutput) -> RsInclude {
         filename: rs_output.config.get_rs_filename(),
     }
 }
+
+/// Creates a JSON description of the headers which C++ code must `#include`
+/// in order to use each item generated by the given `include_cpp!` macros.
+/// The result maps each mod name to a list of items, each with its Rust
+/// path, required headers, and whether it's implemented in Rust.
+pub fn generate_include_metadata<'a>(
+    engines: impl Iterator<Item = &'a IncludeCppEngine>,
+) -> String {
+    let mods: serde_json::Map<String, serde_json::Value> = engines
+        .map(|engine| {
+            let items = engine
+                .get_include_requirements()
+                .iter()
+                .map(|requirement| {
+                    serde_json::json!({
+                        "item": requirement.rust_path,
+                        "headers": requirement.headers,
+                        "extern_rust": requirement.extern_rust,
+                    })
+                })
+                .collect();
+            (engine.get_mod_name(), serde_json::Value::Array(items))
+        })
+        .collect();
+    serde_json::to_string_pretty(&mods).expect("Unable to encode include metadata")
+}
@@ -173,6 +173,7 @@ fn parse_file_contents(
                                 self.extra_superclasses.push(Subclass {
                                     superclass,
                                     subclass,
+                                    non_virtual_destructor: Default::default(),
                                 })
                             }
                         }
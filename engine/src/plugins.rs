@@ -0,0 +1,212 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Hooks allowing build scripts to customize which APIs autocxx generates.
+//!
+//! **[`AnalysisPass`] is unstable** and may change in any release: it
+//! exposes a view of autocxx's internal analysis which will evolve as
+//! autocxx does. [`NameTransform`] is versioned instead: see
+//! [`NAME_TRANSFORM_VERSION`].
+
+use std::sync::{Arc, Mutex};
+
+use thiserror::Error;
+
+/// The broad category of an API which autocxx is about to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiKind {
+    /// A free function.
+    Function,
+    /// A method, static method or constructor of some type.
+    Method,
+    /// A struct or class.
+    Struct,
+    /// An enum.
+    Enum,
+}
+
+/// A view of a single API, passed to each [`AnalysisPass`].
+pub struct ApiEditor {
+    pub(crate) cpp_name: String,
+    pub(crate) kind: ApiKind,
+    pub(crate) extra_docs: Vec<String>,
+    pub(crate) drop_reason: Option<String>,
+}
+
+impl ApiEditor {
+    /// The fully-qualified C++ name of this API, e.g. `ns::Foo` or
+    /// `ns::Foo::method`.
+    pub fn cpp_name(&self) -> &str {
+        &self.cpp_name
+    }
+
+    pub fn kind(&self) -> ApiKind {
+        self.kind
+    }
+
+    /// Append a line of documentation to the generated Rust item.
+    pub fn add_doc(&mut self, doc: impl Into<String>) {
+        self.extra_docs.push(doc.into())
+    }
+
+    /// Don't generate bindings for this API. As with any other API which
+    /// autocxx can't generate, the reason is recorded in the generated
+    /// documentation, and anything depending on this API is dropped too.
+    pub fn drop_api(&mut self, reason: impl Into<String>) {
+        self.drop_reason = Some(reason.into())
+    }
+}
+
+/// A custom pass over the APIs autocxx has analyzed, run after all of
+/// autocxx's own analysis and before any code is generated. Only
+/// functions, methods, structs and enums are shown to passes; APIs
+/// synthesized internally by autocxx are not.
+pub trait AnalysisPass: Send + Sync {
+    /// A name for this pass, used in diagnostics.
+    fn name(&self) -> &str;
+
+    /// Inspect, and optionally alter, a single API.
+    fn visit(&self, api: &mut ApiEditor);
+}
+
+static PASSES: Mutex<Vec<Arc<dyn AnalysisPass>>> = Mutex::new(Vec::new());
+
+/// Register a pass to be run during all subsequent code generation in
+/// this process. Passes run in the order they're registered. Typically
+/// this is called at the start of a `build.rs`, before using
+/// `autocxx_build::Builder`.
+pub fn register_analysis_pass(pass: impl AnalysisPass + 'static) {
+    PASSES.lock().unwrap().push(Arc::new(pass))
+}
+
+pub(crate) fn registered_passes() -> Vec<Arc<dyn AnalysisPass>> {
+    PASSES.lock().unwrap().clone()
+}
+
+/// The version of the [`NameTransform`] interface which this autocxx
+/// supports. It changes only when the interface changes incompatibly, for
+/// instance if autocxx starts offering names of kinds of item which
+/// transforms written for an earlier version wouldn't expect.
+pub const NAME_TRANSFORM_VERSION: u32 = 1;
+
+/// A function or method whose Rust name a [`NameTransform`] may choose.
+pub struct NameToTransform<'a> {
+    pub(crate) cpp_name: &'a str,
+    pub(crate) kind: ApiKind,
+    pub(crate) rust_name: &'a str,
+}
+
+impl NameToTransform<'_> {
+    /// The fully-qualified C++ name, e.g. `ns::foo` or `ns::Foo::method`.
+    pub fn cpp_name(&self) -> &str {
+        self.cpp_name
+    }
+
+    /// Either [`ApiKind::Function`] or [`ApiKind::Method`].
+    pub fn kind(&self) -> ApiKind {
+        self.kind
+    }
+
+    /// The Rust name autocxx would otherwise give the item, or that chosen
+    /// by an earlier transform. This is unqualified, e.g. `method`.
+    pub fn rust_name(&self) -> &str {
+        self.rust_name
+    }
+}
+
+/// A naming convention applied to the Rust names of free functions and
+/// methods, for instance to strip a project-wide prefix. Transforms run
+/// before overloads are numbered, so `overload_names!` still names the
+/// results. Constructors, destructors and anything autocxx synthesizes keep
+/// their usual names.
+///
+/// Register the same transforms in each process which generates bindings
+/// - typically a `build.rs`, or a binary wrapping [`autocxx-gen`] - so
+/// that every build uses the same names.
+///
+/// [`autocxx-gen`]: https://docs.rs/autocxx-gen
+pub trait NameTransform: Send + Sync {
+    /// The version of this interface for which the transform was written,
+    /// which should be [`NAME_TRANSFORM_VERSION`].
+    fn version(&self) -> u32;
+
+    /// A name for this transform, used in diagnostics. This should differ
+    /// for transforms which would pick different names, for instance by
+    /// including any configuration, since autocxx-gen's `--state-dir`
+    /// relies on it to know when bindings need regenerating.
+    fn name(&self) -> &str;
+
+    /// The Rust name to use for this item, or `None` to keep the name it
+    /// already has. It must be a valid Rust identifier.
+    fn rust_name(&self, item: &NameToTransform) -> Option<String>;
+}
+
+/// A [`NameTransform`] was written for a version of the interface which
+/// this autocxx doesn't support.
+#[derive(Debug, Error)]
+#[error("name transform '{name}' is for version {version} of the interface, but this autocxx supports version {NAME_TRANSFORM_VERSION}")]
+pub struct UnsupportedNameTransformVersion {
+    pub name: String,
+    pub version: u32,
+}
+
+/// A [`NameTransform`] which removes a prefix from the names of functions
+/// and methods, e.g. turning `mylib_open` into `open`. Names which would
+/// become empty or start with a digit are left alone.
+pub struct StripPrefix {
+    prefix: String,
+    name: String,
+}
+
+impl StripPrefix {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        let name = format!("strip_prefix({prefix})");
+        Self { prefix, name }
+    }
+}
+
+impl NameTransform for StripPrefix {
+    fn version(&self) -> u32 {
+        NAME_TRANSFORM_VERSION
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rust_name(&self, item: &NameToTransform) -> Option<String> {
+        item.rust_name()
+            .strip_prefix(self.prefix.as_str())
+            .filter(|stripped| stripped.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+            .map(str::to_string)
+    }
+}
+
+static NAME_TRANSFORMS: Mutex<Vec<Arc<dyn NameTransform>>> = Mutex::new(Vec::new());
+
+/// Register a naming convention to be applied during all subsequent code
+/// generation in this process. Transforms run in the order they're
+/// registered, each seeing the name chosen by the last.
+pub fn register_name_transform(
+    transform: impl NameTransform + 'static,
+) -> Result<(), UnsupportedNameTransformVersion> {
+    if transform.version() != NAME_TRANSFORM_VERSION {
+        return Err(UnsupportedNameTransformVersion {
+            name: transform.name().to_string(),
+            version: transform.version(),
+        });
+    }
+    NAME_TRANSFORMS.lock().unwrap().push(Arc::new(transform));
+    Ok(())
+}
+
+pub(crate) fn registered_name_transforms() -> Vec<Arc<dyn NameTransform>> {
+    NAME_TRANSFORMS.lock().unwrap().clone()
+}
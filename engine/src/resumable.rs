@@ -0,0 +1,279 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Saving the results of generating bindings for each `include_cpp!`, so
+//! that a run against very large headers which was interrupted, or which
+//! ran out of its time budget, can carry on where it left off.
+//!
+//! The unit of saved work is a whole `include_cpp!`. bindgen's own state
+//! can't be serialized, so there's no way to save progress part-way
+//! through a single mod.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+use autocxx_parser::IncludeCppConfig;
+use quote::ToTokens;
+use serde_json::{json, Value};
+use syn::ItemMod;
+
+use crate::{
+    plugins::registered_name_transforms, CodegenOptions, CppFilePair, GenerationResults,
+    IncludeRequirement, RebuildDependencyRecorder,
+};
+
+/// Options for saving generation results so that later runs can resume.
+#[derive(Debug, Clone)]
+pub struct ResumeOptions {
+    /// Directory in which to save the results for each `include_cpp!`
+    /// once it has been generated. Saved results are reused only if the
+    /// configuration, include directories, clang arguments, codegen
+    /// options and registered [`crate::plugins::NameTransform`]s all
+    /// match, and none of the headers involved has changed.
+    /// Custom header namers in [`crate::CppCodegenOptions`] aren't
+    /// taken into account, so clear out this directory if you change them.
+    pub state_dir: PathBuf,
+    /// If set, no further `include_cpp!` will be generated once this time
+    /// has passed; generation stops with [`crate::Error::TimeBudgetExceeded`]
+    /// and can be resumed by running again with the same `state_dir`.
+    pub deadline: Option<Instant>,
+}
+
+impl ResumeOptions {
+    pub(crate) fn out_of_time(&self) -> bool {
+        self.deadline
+            .map(|deadline| Instant::now() >= deadline)
+            .unwrap_or_default()
+    }
+}
+
+/// Where to save the results for a given `include_cpp!`. The filename
+/// incorporates a hash of everything which affects the output other than
+/// the headers themselves.
+pub(crate) fn state_file_path(
+    options: &ResumeOptions,
+    config: &IncludeCppConfig,
+    inc_dirs: &[PathBuf],
+    extra_clang_args: &[&str],
+    codegen_options: &CodegenOptions,
+) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    config.get_hash().hash(&mut hasher);
+    inc_dirs.hash(&mut hasher);
+    extra_clang_args.hash(&mut hasher);
+    codegen_options.force_wrapper_gen.hash(&mut hasher);
+    let cpp_codegen_options = &codegen_options.cpp_codegen_options;
+    cpp_codegen_options
+        .suppress_system_headers
+        .hash(&mut hasher);
+    cpp_codegen_options.path_to_cxx_h.hash(&mut hasher);
+    cpp_codegen_options.path_to_cxxgen_h.hash(&mut hasher);
+    cpp_codegen_options.cxx_impl_annotations.hash(&mut hasher);
+    for transform in registered_name_transforms() {
+        transform.name().hash(&mut hasher);
+        transform.version().hash(&mut hasher);
+    }
+    options.state_dir.join(format!(
+        "{}-{:016x}.json",
+        config.get_mod_name(),
+        hasher.finish()
+    ))
+}
+
+fn hash_file(path: &Path) -> Option<u64> {
+    let contents = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Passes header dependencies on to any other recorder, while keeping a
+/// note of them so that we can tell later whether saved results are stale.
+#[derive(Debug)]
+pub(crate) struct DependencyCollector {
+    inner: Option<Box<dyn RebuildDependencyRecorder>>,
+    dependencies: Rc<RefCell<Vec<String>>>,
+}
+
+impl DependencyCollector {
+    pub(crate) fn new(
+        inner: Option<Box<dyn RebuildDependencyRecorder>>,
+        dependencies: Rc<RefCell<Vec<String>>>,
+    ) -> Self {
+        Self {
+            inner,
+            dependencies,
+        }
+    }
+}
+
+impl RebuildDependencyRecorder for DependencyCollector {
+    fn record_header_file_dependency(&self, filename: &str) {
+        if let Some(inner) = &self.inner {
+            inner.record_header_file_dependency(filename);
+        }
+        self.dependencies.borrow_mut().push(filename.to_string());
+    }
+}
+
+/// Save the results of generation, along with the headers on which they
+/// depend.
+pub(crate) fn save(
+    path: &Path,
+    results: &GenerationResults,
+    dependencies: &[String],
+) -> std::io::Result<()> {
+    let state = json!({
+        "rs": results.item_mod.to_token_stream().to_string(),
+        "cpp": results.cpp.as_ref().map(|cpp| json!({
+            "header": String::from_utf8_lossy(&cpp.header),
+            "implementation": cpp.implementation.as_ref().map(|implementation| String::from_utf8_lossy(implementation)),
+            "header_name": cpp.header_name,
+        })),
+        "cxxgen_header_name": results.cxxgen_header_name,
+        "include_requirements": results.include_requirements.iter().map(|requirement| json!({
+            "rust_path": requirement.rust_path,
+            "headers": requirement.headers,
+            "extern_rust": requirement.extern_rust,
+        })).collect::<Vec<_>>(),
+        "warnings": results.warnings,
+        "dependencies": dependencies.iter().filter_map(|filename| {
+            hash_file(Path::new(filename)).map(|hash| json!({
+                "filename": filename,
+                "hash": hash,
+            }))
+        }).collect::<Vec<_>>(),
+    });
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write and then rename, so that an interrupted run never leaves
+    // behind a half-written file.
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, state.to_string())?;
+    std::fs::rename(&tmp_path, path)
+}
+
+/// Load previously saved results, if there are any and none of the headers
+/// on which they depend has changed since. Also returns those headers.
+pub(crate) fn load(
+    path: &Path,
+    inc_dirs: Vec<PathBuf>,
+) -> Option<(GenerationResults, Vec<String>)> {
+    let state: Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    let dependencies = state["dependencies"]
+        .as_array()?
+        .iter()
+        .map(|dependency| {
+            let filename = dependency["filename"].as_str()?;
+            (hash_file(Path::new(filename))? == dependency["hash"].as_u64()?)
+                .then(|| filename.to_string())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let item_mod = syn::parse_str::<ItemMod>(state["rs"].as_str()?).ok()?;
+    let cpp = match &state["cpp"] {
+        Value::Null => None,
+        cpp => Some(CppFilePair {
+            header: cpp["header"].as_str()?.as_bytes().to_vec(),
+            implementation: cpp["implementation"]
+                .as_str()
+                .map(|implementation| implementation.as_bytes().to_vec()),
+            header_name: cpp["header_name"].as_str()?.to_string(),
+        }),
+    };
+    let include_requirements = state["include_requirements"]
+        .as_array()?
+        .iter()
+        .map(|requirement| {
+            Some(IncludeRequirement {
+                rust_path: requirement["rust_path"].as_str()?.to_string(),
+                headers: requirement["headers"]
+                    .as_array()?
+                    .iter()
+                    .map(|header| header.as_str().map(str::to_string))
+                    .collect::<Option<_>>()?,
+                extern_rust: requirement["extern_rust"].as_bool()?,
+            })
+        })
+        .collect::<Option<_>>()?;
+    // Keep the warnings, so that a build which reuses these results still
+    // shows them.
+    let warnings = state["warnings"]
+        .as_array()?
+        .iter()
+        .map(|warning| warning.as_str().map(str::to_string))
+        .collect::<Option<_>>()?;
+    Some((
+        GenerationResults {
+            item_mod,
+            cpp,
+            inc_dirs,
+            cxxgen_header_name: state["cxxgen_header_name"].as_str()?.to_string(),
+            include_requirements,
+            warnings,
+            time_report: None,
+        },
+        dependencies,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    use super::{load, save};
+    use crate::{CppFilePair, GenerationResults, IncludeRequirement};
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = dir.path().join("foo.h");
+        std::fs::write(&header, "struct Foo {};").unwrap();
+        let header = header.to_str().unwrap().to_string();
+        let state_file = dir.path().join("state").join("ffi.json");
+        let results = GenerationResults {
+            item_mod: parse_quote! {
+                mod ffi {
+                    pub struct Foo;
+                }
+            },
+            cpp: Some(CppFilePair {
+                header: b"#pragma once".to_vec(),
+                implementation: None,
+                header_name: "autocxxgen_ffi.h".into(),
+            }),
+            inc_dirs: Vec::new(),
+            cxxgen_header_name: "cxxgen.h".into(),
+            include_requirements: vec![IncludeRequirement {
+                rust_path: "Foo".into(),
+                headers: vec!["foo.h".into()],
+                extern_rust: false,
+            }],
+            warnings: vec!["name_constructor! matched nothing".into()],
+            time_report: None,
+        };
+        save(&state_file, &results, &[header.clone()]).unwrap();
+        let (loaded, dependencies) = load(&state_file, Vec::new()).unwrap();
+        assert_eq!(dependencies, vec![header.clone()]);
+        assert_eq!(
+            loaded.item_mod.to_token_stream().to_string(),
+            results.item_mod.to_token_stream().to_string()
+        );
+        assert_eq!(loaded.cpp.unwrap().header_name, "autocxxgen_ffi.h");
+        assert_eq!(loaded.include_requirements[0].headers, vec!["foo.h"]);
+        assert_eq!(loaded.warnings, results.warnings);
+        std::fs::write(&header, "struct Foo { int a; };").unwrap();
+        assert!(load(&state_file, Vec::new()).is_none());
+    }
+}
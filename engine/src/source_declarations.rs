@@ -0,0 +1,1049 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lets `include_cpp!` name a C++ source file, such as a vendor's
+//! single-file amalgamation, instead of a header. bindgen parses the
+//! source file as it is, but the generated C++ can't include it, since
+//! everything would then be defined twice when linking against the
+//! separately-compiled original. So the generated C++ gets a copy with
+//! just the declarations.
+//!
+//! We ask clang for a JSON dump of the source file's AST, which gives the
+//! extent of each declaration within it, and decide what to copy from
+//! each declaration's kind. As in `declaring_headers`, the dump is walked
+//! as it's deserialized, since clang only names the file of a source
+//! location when it differs from the last location it wrote.
+
+use autocxx_parser::IncludeCppConfig;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{cpp_tokenizer::tokenize, get_clang_path, make_clang_args};
+
+/// File extensions which mean an `#include` names a C++ source file.
+const SOURCE_EXTENSIONS: &[&str] = &["cpp", "cc", "cxx", "c++", "C"];
+
+/// Kinds of declaration which may appear in every file which uses them,
+/// so are copied as they are.
+const COPIED_KINDS: &[&str] = &[
+    "CXXRecordDecl",
+    "RecordDecl",
+    "EnumDecl",
+    "TypedefDecl",
+    "TypeAliasDecl",
+    "ClassTemplateDecl",
+    "ClassTemplateSpecializationDecl",
+    "ClassTemplatePartialSpecializationDecl",
+    "FunctionTemplateDecl",
+    "TypeAliasTemplateDecl",
+    "VarTemplateDecl",
+    "ConceptDecl",
+    "StaticAssertDecl",
+    "UsingDecl",
+    "UsingEnumDecl",
+    "UsingDirectiveDecl",
+    "NamespaceAliasDecl",
+];
+
+/// Kinds of declaration which are functions.
+const FUNCTION_KINDS: &[&str] = &[
+    "FunctionDecl",
+    "CXXMethodDecl",
+    "CXXConstructorDecl",
+    "CXXDestructorDecl",
+    "CXXConversionDecl",
+];
+
+/// Whether an `#include` within `include_cpp!` names a C++ source file
+/// rather than a header.
+pub(crate) fn is_cpp_source(include_name: &str) -> bool {
+    Path::new(include_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SOURCE_EXTENSIONS.contains(&ext))
+        .unwrap_or_default()
+}
+
+/// Find the C++ source file `include_name` in the include directories,
+/// and return the declarations from it which the generated C++ needs.
+pub(crate) fn find_declarations(
+    include_name: &str,
+    inc_dirs: &[PathBuf],
+    extra_clang_args: &[&str],
+    config: &IncludeCppConfig,
+) -> std::io::Result<String> {
+    let path = inc_dirs
+        .iter()
+        .map(|dir| dir.join(include_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "not found in any include directory",
+            )
+        })?;
+    let source = std::fs::read_to_string(&path)?;
+    let output = Command::new(get_clang_path())
+        .arg("-fsyntax-only")
+        .arg("-Xclang")
+        .arg("-ast-dump=json")
+        .args(make_clang_args(inc_dirs, extra_clang_args))
+        .args(
+            config
+                .get_cpp_std()
+                .map(|standard| format!("-std={standard}")),
+        )
+        .args(config.get_clang_args())
+        .arg(&path)
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    let decls = decls_from_ast_dump(&output.stdout, &path.to_string_lossy())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    let dir = Path::new(include_name)
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .filter(|dir| !dir.is_empty());
+    Ok(extract_declarations(&source, &decls, dir))
+}
+
+/// A location within the source file. Within a macro expansion, this is
+/// where the macro was expanded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Loc {
+    offset: usize,
+    tok_len: usize,
+    in_macro: bool,
+}
+
+/// A declaration within the source file, with what we need to know to
+/// decide what to copy from it.
+#[derive(Debug, Default)]
+struct Decl {
+    kind: String,
+    name: Option<String>,
+    /// The location of the name.
+    loc: Option<Loc>,
+    begin: Loc,
+    end: Loc,
+    is_implicit: bool,
+    is_inline: bool,
+    is_constexpr: bool,
+    storage_class: Option<String>,
+    /// Whether this is declared outside the class or namespace it belongs
+    /// to, as in an out-of-line definition of a member.
+    is_out_of_line: bool,
+    qual_type: Option<String>,
+    /// For a function, where its body starts.
+    body: Option<usize>,
+    /// For a variable, where its initializer starts.
+    init: Option<usize>,
+    /// For a namespace or linkage specification, its declarations.
+    children: Vec<Decl>,
+}
+
+impl Decl {
+    fn is_scope(&self) -> bool {
+        matches!(
+            self.kind.as_str(),
+            "TranslationUnitDecl" | "NamespaceDecl" | "LinkageSpecDecl"
+        )
+    }
+
+    /// Whether a variable is `const` itself, so has internal linkage
+    /// unless declared `extern`.
+    fn is_const_variable(&self) -> bool {
+        let Some(ty) = self.qual_type.as_deref() else {
+            return false;
+        };
+        ty.trim_end().ends_with("const")
+            || (ty.starts_with("const ") && !ty.contains(['*', '&', '(']))
+    }
+}
+
+/// Find the declarations written in `main_file` from clang's AST dump of it.
+fn decls_from_ast_dump(json: &[u8], main_file: &str) -> Result<Vec<Decl>, serde_json::Error> {
+    let mut walker = AstWalker {
+        main_file,
+        current_file: None,
+    };
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let root = NodeSeed {
+        walker: &mut walker,
+        record: true,
+    };
+    Ok(root
+        .deserialize(&mut deserializer)?
+        .map(|tu| tu.children)
+        .unwrap_or_default())
+}
+
+struct AstWalker<'a> {
+    main_file: &'a str,
+    /// The file of the last source location clang wrote.
+    current_file: Option<String>,
+}
+
+impl AstWalker<'_> {
+    fn in_main_file(&self) -> bool {
+        self.current_file.as_deref() == Some(self.main_file)
+    }
+}
+
+/// A node in the AST, which is returned if `record` is set and it's in
+/// the source file.
+struct NodeSeed<'w, 'a> {
+    walker: &'w mut AstWalker<'a>,
+    record: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_, '_> {
+    type Value = Option<Decl>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Decl>, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_, '_> {
+    type Value = Option<Decl>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an AST node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Option<Decl>, A::Error> {
+        let mut decl = Decl::default();
+        let mut in_main_file = false;
+        let mut has_range = false;
+        let mut children = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "kind" => decl.kind = map.next_value()?,
+                "name" => decl.name = Some(map.next_value()?),
+                "isImplicit" => decl.is_implicit = map.next_value()?,
+                "inline" | "isInline" => decl.is_inline = map.next_value()?,
+                "constexpr" => decl.is_constexpr = map.next_value()?,
+                "storageClass" => decl.storage_class = Some(map.next_value()?),
+                "parentDeclContextId" => {
+                    map.next_value::<IgnoredAny>()?;
+                    decl.is_out_of_line = true;
+                }
+                "type" => decl.qual_type = map.next_value::<QualType>()?.0,
+                "loc" => {
+                    decl.loc = map.next_value_seed(LocSeed(self.walker))?;
+                    in_main_file = self.walker.in_main_file();
+                }
+                "range" => {
+                    if let Some((begin, end)) = map.next_value_seed(RangeSeed(self.walker))? {
+                        decl.begin = begin;
+                        decl.end = end;
+                        has_range = true;
+                        if decl.loc.is_none() {
+                            in_main_file = self.walker.in_main_file();
+                        }
+                    }
+                }
+                "inner" => {
+                    // clang writes a node's location before its children.
+                    let record = self.record && (in_main_file || decl.is_scope());
+                    children = map.next_value_seed(InnerSeed {
+                        walker: self.walker,
+                        record,
+                    })?;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let is_translation_unit = decl.kind == "TranslationUnitDecl";
+        if !self.record || !(is_translation_unit || (in_main_file && has_range)) {
+            return Ok(None);
+        }
+        if FUNCTION_KINDS.contains(&decl.kind.as_str()) {
+            decl.body = first_child_offset(&children, |kind| {
+                matches!(kind, "CompoundStmt" | "CXXTryStmt")
+            });
+        } else if decl.kind == "VarDecl" {
+            decl.init = first_child_offset(&children, |kind| {
+                !kind.ends_with("Attr") && !kind.ends_with("Decl")
+            });
+        }
+        if decl.is_scope() {
+            decl.children = children;
+        }
+        Ok(Some(decl))
+    }
+}
+
+/// Where the first of `children` of a kind we want starts.
+fn first_child_offset(children: &[Decl], wanted: impl Fn(&str) -> bool) -> Option<usize> {
+    children
+        .iter()
+        .find(|child| wanted(&child.kind))
+        .map(|child| child.begin.offset)
+}
+
+/// The children of a node.
+struct InnerSeed<'w, 'a> {
+    walker: &'w mut AstWalker<'a>,
+    record: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for InnerSeed<'_, '_> {
+    type Value = Vec<Decl>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Decl>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for InnerSeed<'_, '_> {
+    type Value = Vec<Decl>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of AST nodes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Decl>, A::Error> {
+        let mut children = Vec::new();
+        while let Some(child) = seq.next_element_seed(NodeSeed {
+            walker: self.walker,
+            record: self.record,
+        })? {
+            children.extend(child);
+        }
+        Ok(children)
+    }
+}
+
+/// The `begin` and `end` source locations of a node.
+struct RangeSeed<'w, 'a>(&'w mut AstWalker<'a>);
+
+impl<'de> DeserializeSeed<'de> for RangeSeed<'_, '_> {
+    type Value = Option<(Loc, Loc)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RangeSeed<'_, '_> {
+    type Value = Option<(Loc, Loc)>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a source range")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut begin = None;
+        let mut end = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "begin" => begin = map.next_value_seed(LocSeed(self.0))?,
+                "end" => end = map.next_value_seed(LocSeed(self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(begin.zip(end))
+    }
+}
+
+/// A source location. Within a macro expansion, this is instead a pair
+/// of locations: where the macro was spelled, and where it was expanded.
+struct LocSeed<'w, 'a>(&'w mut AstWalker<'a>);
+
+impl<'de> DeserializeSeed<'de> for LocSeed<'_, '_> {
+    type Value = Option<Loc>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Loc>, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for LocSeed<'_, '_> {
+    type Value = Option<Loc>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a source location")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Option<Loc>, A::Error> {
+        let mut offset = None;
+        let mut tok_len = 0;
+        let mut expansion = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "offset" => offset = Some(map.next_value()?),
+                "tokLen" => tok_len = map.next_value()?,
+                "file" => self.0.current_file = Some(map.next_value()?),
+                "spellingLoc" => {
+                    map.next_value_seed(LocSeed(self.0))?;
+                }
+                "expansionLoc" => expansion = map.next_value_seed(LocSeed(self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(match expansion {
+            Some(expansion) => Some(Loc {
+                in_macro: true,
+                ..expansion
+            }),
+            None => offset.map(|offset| Loc {
+                offset,
+                tok_len,
+                in_macro: false,
+            }),
+        })
+    }
+}
+
+/// The type of a declaration, as written.
+struct QualType(Option<String>);
+
+impl<'de> serde::Deserialize<'de> for QualType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Type {
+            #[serde(rename = "qualType")]
+            qual_type: Option<String>,
+        }
+        Ok(QualType(Type::deserialize(deserializer)?.qual_type))
+    }
+}
+
+/// Turn C++ source into something which can be included alongside the
+/// compiled original:
+/// * Preprocessor directives, type definitions, templates, and inline and
+///   `constexpr` functions and variables are kept as they are.
+/// * Other functions become prototypes. `static` functions, out-of-line
+///   definitions of members, and explicit instantiations are dropped, as
+///   is anything in an anonymous namespace.
+/// * Variables become `extern` declarations, unless they're `const`.
+///
+/// `dir` is where the source file is relative to the include directories,
+/// so that its own `#include "..."`s can still be found.
+fn extract_declarations(source: &str, decls: &[Decl], dir: Option<&str>) -> String {
+    let directives = tokenize(source)
+        .into_iter()
+        .filter(|(_, tok)| tok.starts_with('#'))
+        .collect();
+    let extractor = Extractor {
+        source,
+        directives,
+        dir,
+    };
+    let mut out = String::new();
+    extractor.extract_scope(0..source.len(), decls, &mut out);
+    out
+}
+
+struct Extractor<'a> {
+    source: &'a str,
+    /// Each preprocessor directive, and its offset.
+    directives: Vec<(usize, &'a str)>,
+    dir: Option<&'a str>,
+}
+
+impl Extractor<'_> {
+    /// Copy what's needed from a namespace, or the whole file, which spans
+    /// `range`: its declarations, and the preprocessor directives between
+    /// them.
+    fn extract_scope(&self, range: std::ops::Range<usize>, decls: &[Decl], out: &mut String) {
+        let extents: Vec<_> = decls.iter().map(|decl| self.extent(decl)).collect();
+        let mut directives = self
+            .directives
+            .iter()
+            .filter(|(offset, _)| {
+                range.contains(offset) && !extents.iter().any(|extent| extent.contains(offset))
+            })
+            .peekable();
+        for (i, decl) in decls.iter().enumerate() {
+            while let Some((_, directive)) =
+                directives.next_if(|(offset, _)| *offset < extents[i].start)
+            {
+                out.push_str(&rewrite_include(directive, self.dir));
+                out.push('\n');
+            }
+            // The definition of a type which is part of a later declaration,
+            // as in `typedef struct { ... } Foo;`, is copied along with it.
+            let is_within_later = decl.kind != "VarDecl"
+                && extents[i + 1..]
+                    .iter()
+                    .any(|later| later.start <= extents[i].start && extents[i].end <= later.end);
+            if !is_within_later {
+                let previous = i.checked_sub(1).map(|previous| &decls[previous]);
+                self.extract_decl(decl, previous, out);
+            }
+        }
+        for (_, directive) in directives {
+            out.push_str(&rewrite_include(directive, self.dir));
+            out.push('\n');
+        }
+    }
+
+    fn extract_decl(&self, decl: &Decl, previous: Option<&Decl>, out: &mut String) {
+        if decl.is_implicit {
+            return;
+        }
+        let text = self.text(decl);
+        if is_explicit_instantiation(text) {
+            // Only one translation unit may define an explicit instantiation.
+            return;
+        }
+        let kind = decl.kind.as_str();
+        match kind {
+            "NamespaceDecl" => {
+                // Anything in an anonymous namespace is only for this file.
+                if let Some(name) = &decl.name {
+                    let inline = if decl.is_inline { "inline " } else { "" };
+                    out.push_str(&format!("{inline}namespace {name} {{\n"));
+                    self.extract_scope(self.extent(decl), &decl.children, out);
+                    out.push_str("}\n");
+                }
+            }
+            "LinkageSpecDecl" => {
+                // Braces make no difference to what we copy, since a
+                // variable without an initializer becomes `extern` anyway.
+                let language = text
+                    .split_once('"')
+                    .and_then(|(_, rest)| rest.split_once('"'))
+                    .map_or("C", |(language, _)| language);
+                out.push_str(&format!("extern \"{language}\" {{\n"));
+                self.extract_scope(self.extent(decl), &decl.children, out);
+                out.push_str("}\n");
+            }
+            // A function template's definition needs no `;`.
+            "FunctionTemplateDecl" if text.ends_with('}') => {
+                out.push_str(text);
+                out.push('\n');
+            }
+            _ if COPIED_KINDS.contains(&kind) => {
+                out.push_str(text);
+                out.push_str(";\n");
+            }
+            _ if FUNCTION_KINDS.contains(&kind) => self.extract_function(decl, text, out),
+            "VarDecl" => self.extract_variable(decl, previous, text, out),
+            _ => {}
+        }
+    }
+
+    fn extract_function(&self, decl: &Decl, text: &str, out: &mut String) {
+        if decl.storage_class.as_deref() == Some("static") {
+            // Internal linkage, so nothing else can use it.
+            return;
+        }
+        if decl.is_inline || decl.is_constexpr {
+            out.push_str(text);
+            out.push_str(if decl.body.is_some() { "\n" } else { ";\n" });
+            return;
+        }
+        if decl.is_out_of_line {
+            // The class, or the namespace, already declares it.
+            return;
+        }
+        match decl.body {
+            Some(body) if body > decl.begin.offset => {
+                out.push_str(self.source[decl.begin.offset..body].trim_end());
+                out.push_str(";\n");
+            }
+            Some(_) => log::warn!(
+                "Unable to declare {} from a C++ source file, since its definition comes from a macro",
+                decl.name.as_deref().unwrap_or_default()
+            ),
+            None => {
+                out.push_str(text);
+                out.push_str(";\n");
+            }
+        }
+    }
+
+    fn extract_variable(&self, decl: &Decl, previous: Option<&Decl>, text: &str, out: &mut String) {
+        if decl.storage_class.as_deref() == Some("static") || decl.is_out_of_line {
+            return;
+        }
+        let is_extern = decl.storage_class.as_deref() == Some("extern");
+        if decl.is_inline
+            || decl.is_constexpr
+            || (decl.is_const_variable() && !is_extern)
+            || (is_extern && decl.init.is_none())
+        {
+            out.push_str(text);
+            out.push_str(";\n");
+            return;
+        }
+        // A definition, so make it into an `extern` declaration by removing
+        // its initializer.
+        let name_end = decl
+            .loc
+            .map_or(decl.begin.offset, |loc| loc.offset + loc.tok_len);
+        let end = match decl.init {
+            Some(init) if init >= name_end => init,
+            // A constructor call, which clang locates at the name.
+            Some(_) => name_end,
+            None => self.extent(decl).end,
+        };
+        // In `int a = 1, b = 2;`, each variable's declaration starts at the
+        // `int`.
+        let (specifiers, start) = match previous {
+            Some(previous)
+                if previous.kind == "VarDecl" && previous.begin.offset == decl.begin.offset =>
+            {
+                let previous_name = previous.loc.map_or(previous.begin.offset, |loc| loc.offset);
+                let specifiers = self.source[decl.begin.offset..previous_name]
+                    .trim_end_matches(|c: char| c.is_whitespace() || c == '*' || c == '&');
+                if specifiers.contains('{') {
+                    log::warn!(
+                        "Unable to declare {} from a C++ source file, since its type is defined alongside it",
+                        decl.name.as_deref().unwrap_or_default()
+                    );
+                    return;
+                }
+                let after_previous = self.extent(previous).end;
+                let comma = self.source[after_previous..end]
+                    .find(',')
+                    .map_or(after_previous, |comma| after_previous + comma + 1);
+                (format!("{specifiers} "), comma)
+            }
+            _ => (String::new(), decl.begin.offset),
+        };
+        let declarator = self.source[start..end.max(start)]
+            .trim()
+            .trim_end_matches(['=', '(', '{'])
+            .trim_end();
+        let declarator = declarator.strip_prefix("extern").unwrap_or(declarator);
+        out.push_str(&format!(
+            "extern {specifiers}{};\n",
+            declarator.trim_start()
+        ));
+    }
+
+    /// The byte range of a declaration within the source. If it ends with
+    /// a function-like macro, that includes the macro's arguments.
+    fn extent(&self, decl: &Decl) -> std::ops::Range<usize> {
+        let start = decl.begin.offset.min(self.source.len());
+        let mut end = (decl.end.offset + decl.end.tok_len).clamp(start, self.source.len());
+        if decl.end.in_macro {
+            let rest = &self.source[end..];
+            if rest.trim_start().starts_with('(') {
+                let open = end + rest.find('(').unwrap();
+                end = matching_paren(self.source, open).map_or(end, |close| close + 1);
+            }
+        }
+        start..end
+    }
+
+    fn text(&self, decl: &Decl) -> &str {
+        &self.source[self.extent(decl)]
+    }
+}
+
+/// Whether a declaration is an explicit instantiation of a template, as in
+/// `template class Foo<int>;` or `extern template void f<int>();`.
+fn is_explicit_instantiation(text: &str) -> bool {
+    let text = text
+        .strip_prefix("extern")
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .unwrap_or(text)
+        .trim_start();
+    text.strip_prefix("template")
+        .is_some_and(|rest| !rest.trim_start().starts_with('<'))
+}
+
+/// The offset of the `)` matching the `(` at `open`.
+fn matching_paren(source: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (offset, tok) in tokenize(&source[open..]) {
+        match tok {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Make an `#include "..."` relative to the source file relative to the
+/// include directories instead.
+fn rewrite_include(directive: &str, dir: Option<&str>) -> String {
+    let included = directive[1..]
+        .trim_start()
+        .strip_prefix("include")
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix('"'))
+        .and_then(|rest| rest.split_once('"'));
+    match (dir, included) {
+        (Some(dir), Some((name, _))) => format!("#include \"{dir}/{name}\""),
+        _ => directive.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decls_from_ast_dump, extract_declarations, is_cpp_source, Decl, Loc};
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect()
+    }
+
+    /// A declaration of `kind` spanning the first `text` in `source`, as
+    /// clang would describe it.
+    fn decl(source: &str, kind: &str, text: &str) -> Decl {
+        let begin = source.find(text).expect("declaration not in source");
+        Decl {
+            kind: kind.to_string(),
+            begin: Loc {
+                offset: begin,
+                tok_len: 1,
+                in_macro: false,
+            },
+            end: Loc {
+                offset: begin + text.len() - 1,
+                tok_len: 1,
+                in_macro: false,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// A declaration from the first `from` in `source` up to the end of the
+    /// next `to`.
+    fn span(source: &str, kind: &str, from: &str, to: &str) -> Decl {
+        let begin = source.find(from).expect("declaration not in source");
+        let end = begin + source[begin..].find(to).unwrap() + to.len();
+        decl(source, kind, &source[begin..end])
+    }
+
+    fn named(source: &str, mut decl: Decl, name: &str) -> Decl {
+        let offset = decl.begin.offset + source[decl.begin.offset..].find(name).unwrap();
+        decl.name = Some(name.to_string());
+        decl.loc = Some(Loc {
+            offset,
+            tok_len: name.len(),
+            in_macro: false,
+        });
+        decl
+    }
+
+    fn function(source: &str, text: &str) -> Decl {
+        let mut decl = decl(source, "FunctionDecl", text);
+        decl.body = text.find('{').map(|brace| decl.begin.offset + brace);
+        decl
+    }
+
+    fn variable(source: &str, text: &str, name: &str, ty: &str, init: Option<&str>) -> Decl {
+        let mut decl = named(source, decl(source, "VarDecl", text), name);
+        decl.qual_type = Some(ty.to_string());
+        decl.init = init.map(|init| {
+            let name_end = decl.loc.unwrap().offset + name.len();
+            name_end + source[name_end..].find(init).unwrap()
+        });
+        decl
+    }
+
+    #[test]
+    fn test_is_cpp_source() {
+        assert!(is_cpp_source("vendor/lib.cpp"));
+        assert!(is_cpp_source("amalgamation.cc"));
+        assert!(!is_cpp_source("lib.h"));
+        assert!(!is_cpp_source("vector"));
+    }
+
+    #[test]
+    fn test_extract_declarations() {
+        let source = r#"
+            #include <cstdint>
+            #include "util.h"
+            struct Point {
+                int x;
+            #if BIG
+                int y;
+            #endif
+                int length() const { return x; }
+            };
+            int Point_helper;
+            const int kLimit = 4;
+            static int counter = 0;
+            const char* greeting = "hi";
+            int table[] = {1, 2, 3};
+            static int helper(int a) { return a + counter; }
+            inline int twice(int a) { return a * 2; }
+            template <typename T> T id(T t) { return t; }
+            template <> int id<int>(int t) { return t + 1; }
+            template int id<long>(long t);
+            int add(int a, int b) {
+            #ifdef FAST
+                return a + b;
+            #else
+                return b + a;
+            #endif
+            }
+            namespace lib {
+                void Widget::poke() {}
+                uint32_t version() { return 3; }
+            }
+            namespace {
+                int hidden() { return 1; }
+            }
+            extern "C" {
+                void c_api(void) {}
+            }
+        "#;
+        let mut counter = variable(
+            source,
+            "static int counter = 0",
+            "counter",
+            "int",
+            Some("0"),
+        );
+        counter.storage_class = Some("static".to_string());
+        let mut helper = function(source, "static int helper(int a) { return a + counter; }");
+        helper.storage_class = Some("static".to_string());
+        let mut twice = function(source, "inline int twice(int a) { return a * 2; }");
+        twice.is_inline = true;
+        let mut poke = function(source, "void Widget::poke() {}");
+        poke.is_out_of_line = true;
+        let mut lib = named(
+            source,
+            span(
+                source,
+                "NamespaceDecl",
+                "namespace lib {",
+                "return 3; }\n            }",
+            ),
+            "lib",
+        );
+        lib.children = vec![poke, function(source, "uint32_t version() { return 3; }")];
+        let mut anonymous = span(
+            source,
+            "NamespaceDecl",
+            "namespace {",
+            "return 1; }\n            }",
+        );
+        anonymous.children = vec![function(source, "int hidden() { return 1; }")];
+        let mut c = span(
+            source,
+            "LinkageSpecDecl",
+            "extern \"C\" {",
+            "{}\n            }",
+        );
+        c.children = vec![function(source, "void c_api(void) {}")];
+        let mut add = span(
+            source,
+            "FunctionDecl",
+            "int add(int a, int b) {",
+            "#endif\n            }",
+        );
+        add.body = Some(add.begin.offset + "int add(int a, int b) ".len());
+        let decls = vec![
+            span(
+                source,
+                "CXXRecordDecl",
+                "struct Point {",
+                "return x; }\n            }",
+            ),
+            variable(source, "int Point_helper", "Point_helper", "int", None),
+            variable(
+                source,
+                "const int kLimit = 4",
+                "kLimit",
+                "const int",
+                Some("4"),
+            ),
+            counter,
+            variable(
+                source,
+                "const char* greeting = \"hi\"",
+                "greeting",
+                "const char *",
+                Some("\"hi\""),
+            ),
+            variable(
+                source,
+                "int table[] = {1, 2, 3}",
+                "table",
+                "int[3]",
+                Some("{"),
+            ),
+            helper,
+            twice,
+            decl(
+                source,
+                "FunctionTemplateDecl",
+                "template <typename T> T id(T t) { return t; }",
+            ),
+            function(source, "template <> int id<int>(int t) { return t + 1; }"),
+            function(source, "template int id<long>(long t)"),
+            add,
+            lib,
+            anonymous,
+            c,
+        ];
+        assert_eq!(
+            lines(&extract_declarations(source, &decls, Some("vendor"))),
+            vec![
+                "#include <cstdint>",
+                "#include \"vendor/util.h\"",
+                "struct Point {",
+                "int x;",
+                "#if BIG",
+                "int y;",
+                "#endif",
+                "int length() const { return x; }",
+                "};",
+                "extern int Point_helper;",
+                "const int kLimit = 4;",
+                "extern const char* greeting;",
+                "extern int table[];",
+                "inline int twice(int a) { return a * 2; }",
+                "template <typename T> T id(T t) { return t; }",
+                "template <> int id<int>(int t);",
+                "int add(int a, int b);",
+                "namespace lib {",
+                "uint32_t version();",
+                "}",
+                "extern \"C\" {",
+                "void c_api(void);",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_initializers() {
+        let source = r#"
+            int scores{7};
+            int first = 1, second{2};
+            Point origin(0, 0);
+            typedef struct { int x; } Anonymous;
+        "#;
+        let first = variable(source, "int first = 1", "first", "int", Some("1"));
+        let second = variable(
+            source,
+            "int first = 1, second{2}",
+            "second",
+            "int",
+            Some("{"),
+        );
+        let mut origin = variable(source, "Point origin(0, 0)", "origin", "Point", None);
+        // clang locates a constructor call at the variable's name.
+        origin.init = origin.loc.map(|loc| loc.offset);
+        let decls = vec![
+            variable(source, "int scores{7}", "scores", "int", Some("{")),
+            first,
+            second,
+            origin,
+            decl(source, "RecordDecl", "struct { int x; }"),
+            decl(source, "TypedefDecl", "typedef struct { int x; } Anonymous"),
+        ];
+        assert_eq!(
+            lines(&extract_declarations(source, &decls, None)),
+            vec![
+                "extern int scores;",
+                "extern int first;",
+                "extern int second;",
+                "extern Point origin;",
+                "typedef struct { int x; } Anonymous;",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macro_declarations() {
+        let source = "#define DECLARE_TYPE(name) struct name {}\nDECLARE_TYPE(Foo)\nint after() { return 1; }\n";
+        let mut foo = decl(source, "CXXRecordDecl", "DECLARE_TYPE(Foo)");
+        // clang gives the location of the expansion of the macro's name.
+        foo.end.offset = foo.begin.offset;
+        foo.end.tok_len = "DECLARE_TYPE".len();
+        foo.end.in_macro = true;
+        let decls = vec![foo, function(source, "int after() { return 1; }")];
+        assert_eq!(
+            lines(&extract_declarations(source, &decls, None)),
+            vec![
+                "#define DECLARE_TYPE(name) struct name {}",
+                "DECLARE_TYPE(Foo);",
+                "int after();",
+            ]
+        );
+    }
+
+    /// The AST dump for a source file `/src/a.cpp` which includes `a.h`,
+    /// where `a.h` declares `helper` and `a.cpp` defines `ns::run` and a
+    /// variable `count`.
+    const AST: &str = r#"{
+        "id": "0x1", "kind": "TranslationUnitDecl", "loc": {}, "range": {"begin": {}, "end": {}},
+        "inner": [
+            {"id": "0x2", "kind": "FunctionDecl",
+             "loc": {"offset": 5, "file": "/src/a.h", "line": 1, "col": 6, "tokLen": 6,
+                     "includedFrom": {"file": "/src/a.cpp"}},
+             "range": {"begin": {"offset": 0, "col": 1, "tokLen": 4}, "end": {"offset": 13, "col": 14, "tokLen": 1}},
+             "name": "helper", "type": {"qualType": "void ()"}},
+            {"id": "0x3", "kind": "NamespaceDecl",
+             "loc": {"offset": 30, "file": "/src/a.cpp", "line": 2, "col": 11, "tokLen": 2},
+             "range": {"begin": {"offset": 20, "col": 1, "tokLen": 9}, "end": {"offset": 70, "line": 4, "col": 1, "tokLen": 1}},
+             "name": "ns",
+             "inner": [
+                {"id": "0x4", "kind": "FunctionDecl",
+                 "loc": {"offset": 40, "line": 3, "col": 6, "tokLen": 3},
+                 "range": {"begin": {"offset": 35, "col": 1, "tokLen": 4}, "end": {"offset": 60, "col": 26, "tokLen": 1}},
+                 "name": "run", "type": {"qualType": "void ()"},
+                 "inner": [
+                    {"id": "0x5", "kind": "CompoundStmt",
+                     "range": {"begin": {"spellingLoc": {"offset": 5, "file": "/src/a.h", "line": 1, "col": 1, "tokLen": 1},
+                                         "expansionLoc": {"offset": 46, "file": "/src/a.cpp", "line": 3, "col": 12, "tokLen": 1}},
+                               "end": {"offset": 60, "col": 26, "tokLen": 1}}}
+                 ]}
+             ]},
+            {"id": "0x6", "kind": "VarDecl",
+             "loc": {"offset": 80, "line": 5, "col": 5, "tokLen": 5},
+             "range": {"begin": {"offset": 76, "col": 1, "tokLen": 3}, "end": {"offset": 88, "col": 13, "tokLen": 1}},
+             "name": "count", "type": {"qualType": "int"}, "storageClass": "static", "init": "c",
+             "inner": [
+                {"id": "0x7", "kind": "IntegerLiteral",
+                 "range": {"begin": {"offset": 88, "col": 13, "tokLen": 1}, "end": {"offset": 88, "col": 13, "tokLen": 1}},
+                 "type": {"qualType": "int"}, "valueCategory": "prvalue", "value": "0"}
+             ]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_decls_from_ast_dump() {
+        let decls = decls_from_ast_dump(AST.as_bytes(), "/src/a.cpp").unwrap();
+        assert_eq!(decls.len(), 2);
+        let ns = &decls[0];
+        assert_eq!(ns.kind, "NamespaceDecl");
+        assert_eq!((ns.begin.offset, ns.end.offset), (20, 70));
+        assert_eq!(ns.children.len(), 1);
+        let run = &ns.children[0];
+        assert_eq!(run.name.as_deref(), Some("run"));
+        assert_eq!(run.body, Some(46));
+        let count = &decls[1];
+        assert_eq!(count.storage_class.as_deref(), Some("static"));
+        assert_eq!(count.init, Some(88));
+        assert_eq!(count.qual_type.as_deref(), Some("int"));
+    }
+}
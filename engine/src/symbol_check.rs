@@ -0,0 +1,177 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks that the C++ symbols which our generated shims call are actually
+//! present in the libraries against which they'll be linked. Without this,
+//! a mistyped `generate!` target, or a header which doesn't match the
+//! library version, shows up only as a stream of link errors much later.
+
+use indexmap::set::IndexSet as HashSet;
+use std::path::Path;
+use std::process::Command;
+
+use crate::BuilderError;
+
+/// A symbol, as named in an object file and as demangled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Symbol {
+    pub(crate) mangled: String,
+    pub(crate) demangled: String,
+}
+
+/// The `nm` to use. `llvm-nm` can be used for any platform, including
+/// MSVC, by pointing `AUTOCXX_NM` at it.
+fn nm_path() -> String {
+    std::env::var("AUTOCXX_NM").unwrap_or_else(|_| "nm".to_string())
+}
+
+fn run_nm(args: &[&str], file: &Path) -> Result<String, BuilderError> {
+    let output = Command::new(nm_path())
+        .args(args)
+        .arg(file)
+        .output()
+        .map_err(|e| BuilderError::SymbolCheckFailed(format!("couldn't run nm: {e}")))?;
+    if !output.status.success() {
+        return Err(BuilderError::SymbolCheckFailed(format!(
+            "nm failed on {}: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `nm` prints the symbol name last on each line, after an optional address
+/// and a type letter. Demangled names may contain spaces, so we can't
+/// simply take the last word.
+fn symbol_names(nm_output: &str) -> Vec<String> {
+    nm_output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let mut parts = line.splitn(3, ' ');
+            let first = parts.next()?;
+            match (parts.next(), parts.next()) {
+                // Some platforms' `nm -u` lists just names.
+                (None, None) => Some(first.to_string()),
+                // "U name"
+                (Some(rest), None) if first.len() == 1 => Some(rest.to_string()),
+                // "U demangled name with spaces"
+                (Some(second), Some(rest)) if first.len() == 1 => Some(format!("{second} {rest}")),
+                // "0000000000000000 T name"
+                (Some(_), Some(rest)) => Some(rest.to_string()),
+                _ => None,
+            }
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Symbols referenced, but not defined, by the given object file.
+pub(crate) fn undefined_symbols(object: &Path) -> Result<Vec<Symbol>, BuilderError> {
+    let mangled = symbol_names(&run_nm(&["-u"], object)?);
+    let demangled = symbol_names(&run_nm(&["-u", "-C"], object)?);
+    Ok(mangled
+        .into_iter()
+        .zip(demangled)
+        .map(|(mangled, demangled)| Symbol { mangled, demangled })
+        .collect())
+}
+
+/// Symbols defined by the given object file or library.
+pub(crate) fn defined_symbols(file: &Path) -> Result<HashSet<String>, BuilderError> {
+    let is_shared = file
+        .extension()
+        .map(|ext| ext == "so" || ext == "dylib" || ext == "dll")
+        .unwrap_or_default();
+    let args: &[&str] = if is_shared {
+        &["-D", "--defined-only"]
+    } else {
+        &["-g", "--defined-only"]
+    };
+    Ok(symbol_names(&run_nm(args, file)?).into_iter().collect())
+}
+
+/// Whether a demangled symbol refers to one of the C++ items for which
+/// bindings were requested, e.g. `ns::Foo::bar(int)` for `ns::Foo`. Other
+/// undefined symbols, such as those from the C++ standard library or the
+/// cxx runtime, are expected to be satisfied by something other than the
+/// libraries we were given.
+fn refers_to_requested_item(demangled: &str, requested_items: &[String]) -> bool {
+    requested_items.iter().any(|item| {
+        demangled
+            .strip_prefix(item.as_str())
+            .map(|rest| {
+                rest.is_empty()
+                    || rest.starts_with('(')
+                    || rest.starts_with("::")
+                    || rest.starts_with('<')
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// Return the demangled names of any symbols relating to the requested
+/// items which are needed but not defined.
+pub(crate) fn find_missing_symbols(
+    undefined: &[Symbol],
+    defined: &HashSet<String>,
+    requested_items: &[String],
+) -> Vec<String> {
+    let mut missing: Vec<String> = undefined
+        .iter()
+        .filter(|sym| !defined.contains(&sym.mangled))
+        .filter(|sym| refers_to_requested_item(&sym.demangled, requested_items))
+        .map(|sym| sym.demangled.clone())
+        .collect();
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::set::IndexSet as HashSet;
+
+    use super::{find_missing_symbols, symbol_names, Symbol};
+
+    #[test]
+    fn test_symbol_names() {
+        let output = "                 U _Z7do_mathi\n\
+                      0000000000000010 T _ZN2ns3Foo3barEv\n\
+                      \x20                U ns::Foo::bar(int) const\n";
+        assert_eq!(
+            symbol_names(output),
+            vec![
+                "_Z7do_mathi".to_string(),
+                "_ZN2ns3Foo3barEv".to_string(),
+                "ns::Foo::bar(int) const".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_missing_symbols() {
+        let sym = |mangled: &str, demangled: &str| Symbol {
+            mangled: mangled.to_string(),
+            demangled: demangled.to_string(),
+        };
+        let undefined = vec![
+            sym("_Z7do_mathi", "do_math(int)"),
+            sym("_Z8do_maths", "do_maths(short)"),
+            sym("_ZN2ns3Foo3barEv", "ns::Foo::bar()"),
+            sym("_Znwm", "operator new(unsigned long)"),
+        ];
+        let defined: HashSet<String> = ["_ZN2ns3Foo3barEv".to_string()].into_iter().collect();
+        let requested = vec!["do_math".to_string(), "ns::Foo".to_string()];
+        assert_eq!(
+            find_missing_symbols(&undefined, &defined, &requested),
+            vec!["do_math(int)".to_string()]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indexmap::map::IndexMap as HashMap;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::types::QualifiedName;
+
+/// Phases which happen before we have a list of APIs, and so whose time
+/// can't be attributed to particular namespaces.
+static PRE_ANALYSIS_PHASES: &[&str] = &["bindgen", "parsing bindings"];
+
+/// Where the time went while generating bindings for one `include_cpp!`,
+/// to help work out how to trim its configuration for very large
+/// headers.
+#[derive(Debug, Default, Clone)]
+pub struct TimeReport {
+    phases: Vec<(&'static str, Duration)>,
+    apis_by_namespace: HashMap<String, usize>,
+}
+
+impl TimeReport {
+    /// Time spent in each phase, in order.
+    pub fn phases(&self) -> impl Iterator<Item = (&'static str, Duration)> + '_ {
+        self.phases.iter().cloned()
+    }
+
+    /// Total time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// The number of APIs found in each namespace, and an estimate of the
+    /// analysis and codegen time due to each. We don't time each API
+    /// separately, so this is apportioned according to the number of APIs.
+    /// Sorted with the most expensive namespace first.
+    pub fn namespaces(&self) -> Vec<(&str, usize, Duration)> {
+        let analysis_time: Duration = self
+            .phases
+            .iter()
+            .filter(|(phase, _)| !PRE_ANALYSIS_PHASES.contains(phase))
+            .map(|(_, duration)| *duration)
+            .sum();
+        let total_apis: usize = self.apis_by_namespace.values().sum();
+        let mut namespaces: Vec<_> = self
+            .apis_by_namespace
+            .iter()
+            .map(|(ns, count)| {
+                let share = analysis_time.mul_f64(*count as f64 / total_apis.max(1) as f64);
+                (ns.as_str(), *count, share)
+            })
+            .collect();
+        namespaces.sort_by(|a, b| b.1.cmp(&a.1));
+        namespaces
+    }
+}
+
+impl Display for TimeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.total();
+        writeln!(f, "Phase                          Time      %")?;
+        for (phase, duration) in &self.phases {
+            writeln!(
+                f,
+                "{phase:<30} {:>8.3}s {:>5.1}",
+                duration.as_secs_f64(),
+                100.0 * duration.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON)
+            )?;
+        }
+        writeln!(f, "{:<30} {:>8.3}s", "total", total.as_secs_f64())?;
+        writeln!(f)?;
+        writeln!(f, "Namespace                       APIs  Est. time")?;
+        for (ns, count, duration) in self.namespaces() {
+            let ns = if ns.is_empty() { "(global)" } else { ns };
+            writeln!(f, "{ns:<30} {count:>5} {:>9.3}s", duration.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the time taken by each phase of generation, as it happens.
+pub(crate) struct PhaseTimer {
+    report: TimeReport,
+    phase_start: Instant,
+}
+
+impl PhaseTimer {
+    pub(crate) fn new() -> Self {
+        Self {
+            report: TimeReport::default(),
+            phase_start: Instant::now(),
+        }
+    }
+
+    /// Record that the given phase has just finished, and the next begun.
+    pub(crate) fn phase_done(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.report.phases.push((phase, now - self.phase_start));
+        self.phase_start = now;
+    }
+
+    /// Note how many APIs we found in each namespace.
+    pub(crate) fn record_namespaces<'a>(&mut self, names: impl Iterator<Item = &'a QualifiedName>) {
+        for name in names {
+            *self
+                .report
+                .apis_by_namespace
+                .entry(name.get_namespace().to_string())
+                .or_default() += 1;
+        }
+    }
+
+    pub(crate) fn into_report(self) -> TimeReport {
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TimeReport;
+
+    #[test]
+    fn test_namespace_apportionment() {
+        let report = TimeReport {
+            phases: vec![
+                ("bindgen", Duration::from_secs(10)),
+                ("function analysis", Duration::from_secs(4)),
+            ],
+            apis_by_namespace: [("a".to_string(), 1), ("b".to_string(), 3)]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(report.total(), Duration::from_secs(14));
+        assert_eq!(
+            report.namespaces(),
+            vec![
+                ("b", 3, Duration::from_secs(3)),
+                ("a", 1, Duration::from_secs(1))
+            ]
+        );
+    }
+}
@@ -59,6 +59,24 @@ impl Namespace {
     pub(crate) fn to_cpp_path(&self) -> String {
         self.0.join("::")
     }
+
+    /// Whether any part of this namespace is anonymous. bindgen represents
+    /// anonymous namespaces as mods named `_bindgen_mod_...`.
+    pub(crate) fn contains_anonymous(&self) -> bool {
+        self.iter().any(|ns| ns.starts_with("_bindgen_mod"))
+    }
+
+    /// This namespace without any anonymous parts, which is how C++ code
+    /// elsewhere in the same translation unit refers to their contents.
+    #[must_use]
+    pub(crate) fn without_anonymous(&self) -> Self {
+        Namespace(Arc::new(
+            self.iter()
+                .filter(|ns| !ns.starts_with("_bindgen_mod"))
+                .map(|ns| ns.to_string())
+                .collect(),
+        ))
+    }
 }
 
 impl Display for Namespace {
@@ -139,6 +157,23 @@ impl QualifiedName {
         unreachable!()
     }
 
+    /// This name without any of the inline namespaces which C++ standard
+    /// libraries use for versioning, such as libc++'s `std::__1` or
+    /// libstdc++'s `std::__cxx11`.
+    #[must_use]
+    pub(crate) fn without_inline_namespaces(&self) -> Self {
+        Self(
+            Namespace(Arc::new(
+                self.0
+                    .iter()
+                    .filter(|ns| !ns.starts_with("__"))
+                    .map(|ns| ns.to_string())
+                    .collect(),
+            )),
+            self.1.clone(),
+        )
+    }
+
     /// Return the actual type name, without any namespace
     /// qualification. Avoid unless you have a good reason.
     pub(crate) fn get_final_item(&self) -> &str {
@@ -338,4 +373,19 @@ mod tests {
             "foo1234"
         );
     }
+    #[test]
+    fn test_without_inline_namespaces() {
+        assert_eq!(
+            QualifiedName::new_from_cpp_name("std::pmr::__cxx11::string")
+                .without_inline_namespaces()
+                .to_cpp_name(),
+            "std::pmr::string"
+        );
+        assert_eq!(
+            QualifiedName::new_from_cpp_name("std::__1::pmr::vector")
+                .without_inline_namespaces()
+                .to_cpp_name(),
+            "std::pmr::vector"
+        );
+    }
 }
@@ -12,7 +12,7 @@ use autocxx_engine::{BuilderContext, RebuildDependencyRecorder};
 use indexmap::set::IndexSet as HashSet;
 use std::{io::Write, sync::Mutex};
 
-pub use autocxx_engine::{BuilderError, BuilderSuccess};
+pub use autocxx_engine::{BuilderError, BuilderSuccess, HeaderToPreprocess, HeadersToPreprocess};
 
 pub type Builder = autocxx_engine::Builder<'static, CargoBuilderContext>;
 
@@ -28,6 +28,13 @@ impl BuilderContext for CargoBuilderContext {
     fn get_dependency_recorder() -> Option<Box<dyn RebuildDependencyRecorder>> {
         Some(Box::new(CargoRebuildDependencyRecorder::new()))
     }
+    fn report_warning(message: &str) {
+        // Unlike log messages, these are shown whatever RUST_LOG says.
+        // Each cargo:warning must fit on one line.
+        for line in message.lines() {
+            println!("cargo:warning={line}");
+        }
+    }
 }
 
 #[derive(Debug)]
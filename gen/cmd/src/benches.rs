@@ -0,0 +1,190 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, FnArg, ForeignItem, ImplItem, Item, Path, PathArguments, Signature, Type};
+
+/// Generate a criterion benchmark file which calls each of the given
+/// functions with default-constructed arguments. Each function is named
+/// by the path at which the benchmark should call it, e.g.
+/// `mycrate::ffi::DoMath` or `mycrate::ffi::Goat::new`; we find its
+/// signature by looking for a function with the same final segment(s)
+/// in the generated bindings.
+pub(crate) fn generate_benches(
+    bindings: &[syn::File],
+    functions: &[&str],
+) -> miette::Result<String> {
+    let mut benches = Vec::new();
+    for function in functions {
+        let path: Path = syn::parse_str(function).map_err(|_| {
+            miette::Report::msg(format!("--emit-benches: '{function}' is not a Rust path"))
+        })?;
+        let sig = find_signature(bindings, &path).ok_or_else(|| {
+            miette::Report::msg(format!(
+                "--emit-benches: couldn't find a generated function matching '{function}'"
+            ))
+        })?;
+        let args = sig
+            .inputs
+            .iter()
+            .map(|arg| default_argument(function, arg))
+            .collect::<miette::Result<Vec<_>>>()?;
+        let call = if sig.unsafety.is_some() {
+            quote! { unsafe { #path(#(#args),*) } }
+        } else {
+            quote! { #path(#(#args),*) }
+        };
+        benches.push(quote! {
+            c.bench_function(#function, |b| b.iter(|| black_box(#call)));
+        });
+    }
+    let file: syn::File = parse_quote! {
+        //! FFI call overhead benchmarks generated by autocxx-gen.
+
+        use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+        fn autocxx_ffi_benches(c: &mut Criterion) {
+            #(#benches)*
+        }
+
+        criterion_group!(benches, autocxx_ffi_benches);
+        criterion_main!(benches);
+    };
+    Ok(prettyplease::unparse(&file))
+}
+
+/// Only arguments which can be made using `Default::default()` are
+/// supported. References, pointers, pinned references and `impl Trait`
+/// parameters can't be conjured up in this way.
+fn default_argument(function: &str, arg: &FnArg) -> miette::Result<proc_macro2::TokenStream> {
+    let ty = match arg {
+        FnArg::Receiver(_) => None,
+        FnArg::Typed(pat_type) => match pat_type.pat.as_ref() {
+            syn::Pat::Ident(ident) if ident.ident == "self" => None,
+            _ => Some(pat_type.ty.as_ref()),
+        },
+    };
+    let supported = match ty {
+        None => {
+            return Err(miette::Report::msg(format!(
+                "--emit-benches: '{function}' takes a receiver; only free functions and static methods can be benchmarked"
+            )))
+        }
+        Some(Type::Reference(_) | Type::Ptr(_) | Type::ImplTrait(_)) => false,
+        Some(Type::Path(type_path)) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident != "Pin")
+            .unwrap_or(true),
+        Some(_) => true,
+    };
+    if !supported {
+        return Err(miette::Report::msg(format!(
+            "--emit-benches: '{function}' has a parameter which can't be default-constructed"
+        )));
+    }
+    Ok(quote! { black_box(Default::default()) })
+}
+
+fn find_signature<'a>(bindings: &'a [syn::File], path: &Path) -> Option<&'a Signature> {
+    let mut segments = path.segments.iter().rev();
+    let name = &segments.next()?.ident;
+    let self_ty = segments.next().map(|segment| &segment.ident);
+    bindings.iter().find_map(|file| {
+        self_ty
+            .and_then(|self_ty| find_method(&file.items, self_ty, name))
+            .or_else(|| find_function(&file.items, name))
+    })
+}
+
+fn find_method<'a>(
+    items: &'a [Item],
+    self_ty: &syn::Ident,
+    name: &syn::Ident,
+) -> Option<&'a Signature> {
+    items.iter().find_map(|item| match item {
+        Item::Mod(m) => m
+            .content
+            .as_ref()
+            .and_then(|(_, items)| find_method(items, self_ty, name)),
+        Item::Impl(imp) if imp.trait_.is_none() && type_is_named(&imp.self_ty, self_ty) => {
+            imp.items.iter().find_map(|impl_item| match impl_item {
+                ImplItem::Fn(f) if &f.sig.ident == name => Some(&f.sig),
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+fn find_function<'a>(items: &'a [Item], name: &syn::Ident) -> Option<&'a Signature> {
+    items.iter().find_map(|item| match item {
+        Item::Mod(m) => m
+            .content
+            .as_ref()
+            .and_then(|(_, items)| find_function(items, name)),
+        Item::Fn(f) if &f.sig.ident == name => Some(&f.sig),
+        Item::ForeignMod(fm) => fm.items.iter().find_map(|foreign_item| match foreign_item {
+            ForeignItem::Fn(f) if &f.sig.ident == name => Some(&f.sig),
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+fn type_is_named(ty: &Type, name: &syn::Ident) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| {
+                &segment.ident == name && matches!(segment.arguments, PathArguments::None)
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::generate_benches;
+
+    #[test]
+    fn test_generate_benches() {
+        let bindings: syn::File = parse_quote! {
+            mod ffi {
+                mod bindgen {
+                    pub mod root {
+                        impl Goat {
+                            pub fn count() -> u32 { 0 }
+                            pub fn add_a_horn(self: Pin<&mut Goat>) {}
+                        }
+                    }
+                }
+                #[cxx::bridge]
+                mod cxxbridge {
+                    unsafe extern "C++" {
+                        fn DoMath(a: u32) -> u32;
+                        fn Describe(goat: &Goat) -> UniquePtr<CxxString>;
+                    }
+                }
+            }
+        };
+        let bindings = [bindings];
+        let benches = generate_benches(&bindings, &["ffi::DoMath", "ffi::Goat::count"]).unwrap();
+        assert!(benches.contains("ffi::DoMath(black_box(Default::default()))"));
+        assert!(benches.contains("black_box(ffi::Goat::count())"));
+        assert!(generate_benches(&bindings, &["ffi::Goat::add_a_horn"]).is_err());
+        assert!(generate_benches(&bindings, &["ffi::Describe"]).is_err());
+        assert!(generate_benches(&bindings, &["ffi::Missing"]).is_err());
+    }
+}
@@ -0,0 +1,749 @@
+// Copyright 2020 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![forbid(unsafe_code)]
+
+//! The `autocxx-gen` command-line tool, as a library. This lets you build
+//! your own variant of the tool which registers extra plugins before
+//! running, for instance a [`NameTransform`] which your `build.rs` also
+//! registers, so that both produce the same names:
+//!
+//! ```no_run
+//! fn main() -> miette::Result<()> {
+//!     autocxx_engine::plugins::register_name_transform(
+//!         autocxx_engine::plugins::StripPrefix::new("mylib_"),
+//!     )
+//!     .unwrap();
+//!     autocxx_gen::run()
+//! }
+//! ```
+//!
+//! [`NameTransform`]: autocxx_engine::plugins::NameTransform
+
+mod benches;
+mod depfile;
+mod verify;
+
+use autocxx_engine::{
+    generate_api_summary, generate_include_metadata, generate_rs_archive, generate_rs_single,
+    get_cxx_header_bytes, parse_file, AutocxxgenHeaderNamer, CxxgenHeaderNamer,
+    RebuildDependencyRecorder, ResumeOptions,
+};
+use clap::{crate_authors, crate_version, Arg, ArgGroup, Command};
+use depfile::Depfile;
+use indexmap::IndexSet;
+use miette::IntoDiagnostic;
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::{cell::Cell, fs::File, path::Path};
+
+pub(crate) static BLANK: &str = "// Blank autocxx placeholder";
+
+static LONG_HELP: &str = "
+Command line utility to expand the Rust 'autocxx' include_cpp! directive.
+
+This tool can generate both the C++ and Rust side binding code for
+a Rust file containing an include_cpp! directive.
+
+If you're using cargo, don't use this: use autocxx_build instead,
+which is much easier to include in build.rs build scripts. You'd likely
+use this tool only if you're using some non-Cargo build system. If
+that's you, read on.
+
+This tool has three modes: generate the C++; or generate
+a Rust file which can be included by the autocxx_macro; or generate an archive
+containing multiple Rust files to be expanded by different autocxx macros.
+You may specify multiple modes, or of course, invoke the tool multiple times.
+
+In any mode, you'll need to pass the source Rust file name and the C++
+include path. You may pass multiple Rust files, each of which may contain
+multiple include_cpp! or cxx::bridge macros.
+
+There are three basic ways to use this tool, depending on the flexibility
+of your build system.
+
+Does your build system require fixed output filenames, or can it enumerate
+whatever files are generated?
+
+If it's flexible, then use
+  --gen-rs-include --gen-cpp
+An arbitrary number of .h, .cc and .rs files will be generated, depending
+on how many cxx::bridge and include_cpp macros are encountered and their contents.
+When building the rust code, simply ensure that AUTOCXX_RS or OUT_DIR is set to
+teach rustc where to find these .rs files.
+
+If your build system needs to be told exactly what C++ files are generated,
+additionally use --generate-exact <N> You are then guaranteed to get
+exactly 'n' files as follows:
+  gen<n>.h
+  autocxxgen<n>.h
+  gen<n>.cc
+Some of them may be blank. If the tool finds too many include_cpp or cxx::bridge
+macros to fit within that allowance, the build will fail.
+
+Alternatively, use --fixed-output-names to name the C++ files after the
+Rust mod containing each include_cpp! or cxx::bridge, for instance
+  cxxgen_ffi.h
+  cxxgen_ffi.cc
+  autocxxgen_ffi.h
+  autocxxgen_ffi.cc
+These names don't change as other sections are added or removed. A list of
+all the files generated is written to autocxx_manifest.txt. Each mod name
+must be unique across all the input files. --cpp-subdir puts the C++ files
+into a subdirectory of the output directory.
+
+If your build system additionally requires that Rust files have fixed
+filenames, then you should use
+  --gen-rs-archive
+instead of
+  --gen-rs-include
+and you will need to give AUTOCXX_RS_JSON_ARCHIVE when building the Rust code.
+The output filename is named gen.rs.json. AUTOCXX_RS_JSON_ARCHIVE should be set
+to the path to gen.rs.json. It may optionally have multiple paths separated the
+way as the PATH environment variable for the current platform, see
+[`std::env::split_paths`] for details. The first path which is successfully
+opened will be used.
+
+This teaches rustc (and the autocxx macro) that all the different Rust bindings
+for multiple different autocxx macros have been archived into this single file.
+
+Separately, --emit-benches generates autocxx_benches.rs, a criterion benchmark
+which measures the overhead of calling each of the given functions with
+default-constructed arguments. Place it in your crate's benches directory.
+
+--gen-include-metadata generates autocxx_includes.json, which lists the C++
+headers needed to use each generated item, and --gen-extern-rust-header
+generates autocxx_extern_rust.h, which C++ code calling into Rust can include
+instead of working out which generated header declares which Rust item.
+
+--emit-api-summary writes a summary of the generated Rust API to the given
+file within the output directory: one sorted line per item, with its full
+path and signature. Check it into version control so that code review shows
+exactly how a change to the C++ headers changes the Rust bindings.
+
+For very large headers, --state-dir saves the results for each include_cpp!
+as soon as they're generated, and reuses them on later runs so long as
+neither the configuration nor any of the headers has changed. Combined with
+--time-budget, this allows the work to be split across several runs: once
+the budget is spent, no further include_cpp! is started, and the tool fails
+with a message asking to be run again. --time-report prints the time spent
+in each phase of generation, and an estimate of how much is due to each C++
+namespace, to help decide what to trim from the allowlist.
+
+--verify checks the generated code once it's been written: first that the
+Rust parses, then that it builds, using `cargo check` on a scratch crate
+which also compiles any C++ generated by --gen-cpp. Errors name the
+generated item responsible. The scratch crate depends on the published
+autocxx unless --verify-autocxx-path is given, and is kept for inspection
+if the build fails. Set CARGO_TARGET_DIR to reuse its dependencies between
+runs.
+";
+
+/// Run `autocxx-gen` with the arguments this process was given.
+pub fn run() -> miette::Result<()> {
+    let matches = Command::new("autocxx-gen")
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about("Generates bindings files from Rust files that contain include_cpp! macros")
+        .long_about(LONG_HELP)
+        .arg(
+            Arg::new("INPUT")
+                .help("Sets the input .rs files to use")
+                .required(true)
+                .multiple_occurrences(true)
+        )
+        .arg(
+            Arg::new("outdir")
+                .short('o')
+                .long("outdir")
+                .allow_invalid_utf8(true)
+                .value_name("PATH")
+                .help("output directory path")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::new("inc")
+                .short('I')
+                .long("inc")
+                .multiple_occurrences(true)
+                .number_of_values(1)
+                .value_name("INCLUDE DIRS")
+                .help("include path")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("cpp-extension")
+                .long("cpp-extension")
+                .value_name("EXTENSION")
+                .default_value("cc")
+                .help("C++ filename extension")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("gen-cpp")
+                .long("gen-cpp")
+                .help("whether to generate C++ implementation and header files")
+        )
+        .arg(
+            Arg::new("gen-rs-include")
+                .long("gen-rs-include")
+                .help("whether to generate Rust files for inclusion using autocxx_macro")
+        )
+        .arg(
+            Arg::new("gen-rs-archive")
+                .long("gen-rs-archive")
+                .help("whether to generate an archive of multiple sets of Rust bindings for use by autocxx_macro (suffix will be .rs.json)")
+        )
+        .group(ArgGroup::new("mode")
+            .required(true)
+            .multiple(true)
+            .arg("gen-cpp")
+            .arg("gen-rs-include")
+            .arg("gen-rs-archive")
+            .arg("emit-benches")
+            .arg("gen-include-metadata")
+            .arg("gen-extern-rust-header")
+            .arg("emit-api-summary")
+        )
+        .arg(
+            Arg::new("emit-benches")
+                .long("emit-benches")
+                .value_name("FUNCTION")
+                .help("generate autocxx_benches.rs, a criterion benchmark of the FFI call overhead of each FUNCTION, called with default-constructed arguments. Give the path at which the benchmark should call each function, e.g. mycrate::ffi::DoMath")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::new("gen-include-metadata")
+                .long("gen-include-metadata")
+                .help("generate autocxx_includes.json, listing the C++ headers required to use each generated item")
+        )
+        .arg(
+            Arg::new("gen-extern-rust-header")
+                .long("gen-extern-rust-header")
+                .help("generate autocxx_extern_rust.h, a single header for C++ code which calls into Rust via extern_rust_type!, extern_rust_function! or subclass!")
+        )
+        .arg(
+            Arg::new("emit-api-summary")
+                .long("emit-api-summary")
+                .value_name("FILE")
+                .help("write a summary of the generated Rust API to FILE in the output directory, one line per item with its signature, suitable for checking into version control")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("generate-exact")
+                .long("generate-exact")
+                .value_name("NUM")
+                .help("assume and ensure there are exactly NUM bridge blocks in the file. Only applies for --gen-cpp or --gen-rs-include")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("fixed-output-names")
+                .long("fixed-output-names")
+                .help("name generated C++ files after the Rust mod containing each include_cpp! or cxx::bridge, rather than numbering them, and write autocxx_manifest.txt listing every file generated. Only applies for --gen-cpp")
+                .requires("gen-cpp")
+                .conflicts_with("generate-exact")
+        )
+        .arg(
+            Arg::new("cpp-subdir")
+                .long("cpp-subdir")
+                .value_name("DIR")
+                .help("write generated C++ files into this subdirectory of the output directory. Only applies for --gen-cpp")
+                .requires("gen-cpp")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("fix-rs-include-name")
+                .long("fix-rs-include-name")
+                .help("Make the name of the .rs file predictable (suffix will be .include.rs). You must set AUTOCXX_RS_FILE during Rust build time to educate autocxx_macro about your choice.")
+                .requires("gen-rs-include")
+        )
+        .arg(
+            Arg::new("auto-allowlist")
+                .long("auto-allowlist")
+                .help("Dynamically construct allowlist from real uses of APIs.")
+        )
+        .arg(
+            Arg::new("suppress-system-headers")
+                .long("suppress-system-headers")
+                .help("Do not refer to any system headers from generated code. May be useful for minimization.")
+        )
+        .arg(
+            Arg::new("cxx-impl-annotations")
+                .long("cxx-impl-annotations")
+                .value_name("ANNOTATION")
+                .help("prefix for symbols to be exported from C++ bindings, e.g. __attribute__ ((visibility (\"default\")))")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("generate-cxx-h")
+                .long("generate-cxx-h")
+                .help("whether to generate cxx.h header file. If you already knew where to find cxx.h, consider using --cxx-h-path")
+        )
+        .arg(
+            Arg::new("cxx-h-path")
+                .long("cxx-h-path")
+                .value_name("PREFIX")
+                .help("prefix for path to cxx.h (from the cxx crate) within #include statements. Must end in /")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("cxxgen-h-path")
+                .long("cxxgen-h-path")
+                .value_name("PREFIX")
+                .help("prefix for path to cxxgen.h (which we generate into the output directory) within #include statements. Must end in /")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("depfile")
+                .long("depfile")
+                .value_name("DEPFILE")
+                .help("A .d file to write")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("state-dir")
+                .long("state-dir")
+                .value_name("DIR")
+                .help("save the results for each include_cpp! in this directory, and reuse them on later runs if nothing relevant has changed")
+                .conflicts_with("generate-exact")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("time-budget")
+                .long("time-budget")
+                .value_name("SECONDS")
+                .help("don't start generating any further include_cpp! after this many seconds. Rerun with the same --state-dir to carry on")
+                .requires("state-dir")
+                .validator(parse_time_budget)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("time-report")
+                .long("time-report")
+                .help("print to stderr the time taken by each phase of generating each include_cpp!, and an estimate of the time due to each C++ namespace")
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .help("after generating, check that the generated Rust parses and builds, using cargo check on a scratch crate alongside any generated C++")
+        )
+        .arg(
+            Arg::new("verify-autocxx-path")
+                .long("verify-autocxx-path")
+                .value_name("DIR")
+                .help("build the scratch crate for --verify against the autocxx crate in this directory, rather than the published version")
+                .requires("verify")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("clang-args")
+                .last(true)
+                .multiple_occurrences(true)
+                .help("Extra arguments to pass to Clang"),
+        )
+        .get_matches();
+
+    env_logger::builder().init();
+    let incs = matches
+        .values_of("inc")
+        .unwrap_or_default()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let extra_clang_args: Vec<_> = matches
+        .values_of("clang-args")
+        .unwrap_or_default()
+        .collect();
+    let suppress_system_headers = matches.is_present("suppress-system-headers");
+    let desired_number = matches
+        .value_of("generate-exact")
+        .map(|s| s.parse::<usize>().unwrap());
+    let autocxxgen_header_counter = Cell::new(0);
+    let autocxxgen_header_namer = if desired_number.is_some() {
+        AutocxxgenHeaderNamer(Box::new(|_| {
+            let r = name_autocxxgen_h(autocxxgen_header_counter.get());
+            autocxxgen_header_counter.set(autocxxgen_header_counter.get() + 1);
+            r
+        }))
+    } else {
+        Default::default()
+    };
+    let fixed_output_names = matches.is_present("fixed-output-names");
+    let cxxgen_header_counter = Cell::new(0);
+    let cxxgen_header_namer = if desired_number.is_some() {
+        CxxgenHeaderNamer(Box::new(|_| {
+            let r = name_cxxgen_h(cxxgen_header_counter.get());
+            cxxgen_header_counter.set(cxxgen_header_counter.get() + 1);
+            r
+        }))
+    } else if fixed_output_names {
+        CxxgenHeaderNamer(Box::new(|mod_name: String| format!("cxxgen_{mod_name}.h")))
+    } else {
+        Default::default()
+    };
+    let cpp_codegen_options = autocxx_engine::CppCodegenOptions {
+        suppress_system_headers,
+        cxx_impl_annotations: get_option_string("cxx-impl-annotations", &matches),
+        path_to_cxx_h: get_option_string("cxx-h-path", &matches),
+        path_to_cxxgen_h: get_option_string("cxxgen-h-path", &matches),
+        autocxxgen_header_namer,
+        cxxgen_header_namer,
+    };
+    let deadline = matches
+        .value_of("time-budget")
+        .map(|s| Instant::now() + parse_time_budget(s).expect("validated by clap"));
+    let resume = matches
+        .value_of("state-dir")
+        .map(|state_dir| ResumeOptions {
+            state_dir: PathBuf::from(state_dir),
+            deadline,
+        });
+    let codegen_options = autocxx_engine::CodegenOptions {
+        cpp_codegen_options,
+        resume,
+        find_declaring_headers: matches.is_present("gen-include-metadata"),
+        ..Default::default()
+    };
+    let depfile = match matches.value_of("depfile") {
+        None => None,
+        Some(depfile_path) => {
+            let depfile_path = PathBuf::from(depfile_path);
+            Some(Rc::new(RefCell::new(
+                Depfile::new(&depfile_path).into_diagnostic()?,
+            )))
+        }
+    };
+    let auto_allowlist = matches.is_present("auto-allowlist");
+
+    let mut parsed_files = Vec::new();
+    for input in matches.values_of("INPUT").expect("No INPUT was provided") {
+        // Parse all the .rs files we're asked to process, first.
+        // Spot any fundamental parsing or command line problems before we start
+        // to do the complex processing.
+        let parsed_file = parse_file(input, auto_allowlist)?;
+        parsed_files.push(parsed_file);
+    }
+
+    for parsed_file in parsed_files.iter_mut() {
+        // Now actually handle all the include_cpp directives we found,
+        // which is the complex bit where we interpret all the C+.
+        let dep_recorder: Option<Box<dyn RebuildDependencyRecorder>> = depfile
+            .as_ref()
+            .map(|rc| get_dependency_recorder(rc.clone()));
+        parsed_file.resolve_all(
+            incs.clone(),
+            &extra_clang_args,
+            dep_recorder,
+            &codegen_options,
+        )?;
+    }
+
+    for engine in parsed_files
+        .iter()
+        .flat_map(|parsed_file| parsed_file.get_autocxxes())
+    {
+        for warning in engine.get_warnings() {
+            eprintln!("warning: {warning}");
+        }
+    }
+
+    if matches.is_present("time-report") {
+        for engine in parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_autocxxes())
+        {
+            match engine.get_time_report() {
+                Some(report) => eprintln!("Timings for mod {}:\n{report}", engine.get_mod_name()),
+                None => eprintln!(
+                    "Mod {} was reused from saved state\n",
+                    engine.get_mod_name()
+                ),
+            }
+        }
+    }
+
+    // Finally start to write the C++ and Rust out.
+    let outdir: PathBuf = matches.value_of_os("outdir").unwrap().into();
+
+    if !outdir.exists() {
+        use miette::WrapErr as _;
+        std::fs::create_dir_all(&outdir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to create `outdir` '{}'", outdir.display()))?;
+    }
+
+    let mut writer = FileWriter {
+        depfile: &depfile,
+        outdir: &outdir,
+        written: IndexSet::new(),
+    };
+    // The C++ files written, and the directories they're in, for --verify.
+    let mut cpp_files = Vec::new();
+    let mut cpp_dirs = vec![outdir.clone()];
+    if matches.is_present("gen-cpp") {
+        let cpp = matches.value_of("cpp-extension").unwrap();
+        let cpp_subdir = matches.value_of("cpp-subdir").map(PathBuf::from);
+        if let Some(cpp_subdir) = &cpp_subdir {
+            std::fs::create_dir_all(outdir.join(cpp_subdir)).into_diagnostic()?;
+            cpp_dirs.push(outdir.join(cpp_subdir));
+        }
+        let in_cpp_subdir = |filename: String| match &cpp_subdir {
+            Some(cpp_subdir) => cpp_subdir.join(filename).to_string_lossy().into_owned(),
+            None => filename,
+        };
+        let name_cc_file = |counter| format!("gen{counter}.{cpp}");
+        let mut counter = 0usize;
+        for include_cxx in parsed_files
+            .iter()
+            .flat_map(|file| file.get_cpp_buildables())
+        {
+            let generations = include_cxx
+                .generate_h_and_cxx(&codegen_options.cpp_codegen_options)
+                .expect("Unable to generate header and C++ code");
+            for pair in generations.0 {
+                // With fixed output names, each header is named after its
+                // mod, so the implementation file can be named after it.
+                let cppname = if fixed_output_names {
+                    let stem = pair
+                        .header_name
+                        .strip_suffix(".h")
+                        .unwrap_or(&pair.header_name);
+                    format!("{stem}.{cpp}")
+                } else {
+                    name_cc_file(counter)
+                };
+                let cppname = in_cpp_subdir(cppname);
+                if pair.implementation.is_some() {
+                    cpp_files.push(outdir.join(&cppname));
+                }
+                writer.write_to_file(cppname, &pair.implementation.unwrap_or_default())?;
+                writer.write_to_file(in_cpp_subdir(pair.header_name), &pair.header)?;
+                counter += 1;
+            }
+        }
+        drop(codegen_options);
+        // Write placeholders to ensure we always make exactly 'n' of each file type.
+        writer.write_placeholders(counter, desired_number, name_cc_file)?;
+        writer.write_placeholders(
+            cxxgen_header_counter.into_inner(),
+            desired_number,
+            name_cxxgen_h,
+        )?;
+        writer.write_placeholders(
+            autocxxgen_header_counter.into_inner(),
+            desired_number,
+            name_autocxxgen_h,
+        )?;
+    }
+
+    if matches.is_present("generate-cxx-h") {
+        writer.write_to_file(
+            "cxx.h".to_string(),
+            &get_cxx_header_bytes(suppress_system_headers),
+        )?;
+    }
+
+    if matches.is_present("gen-rs-include") {
+        if !matches.is_present("fix-rs-include-name") && desired_number.is_some() {
+            return Err(miette::Report::msg(
+                "gen-rs-include and generate-exact requires fix-rs-include-name.",
+            ));
+        }
+        let mut counter = 0usize;
+        let rust_buildables = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_rs_outputs());
+        for include_cxx in rust_buildables {
+            let rs_code = generate_rs_single(include_cxx);
+            let fname = if matches.is_present("fix-rs-include-name") {
+                name_include_rs(counter)
+            } else {
+                rs_code.filename
+            };
+            writer.write_to_file(fname, rs_code.code.as_bytes())?;
+            counter += 1;
+        }
+        writer.write_placeholders(counter, desired_number, name_include_rs)?;
+    }
+    if matches.is_present("gen-rs-archive") {
+        let rust_buildables = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_rs_outputs());
+        let json = generate_rs_archive(rust_buildables);
+        writer.write_to_file("gen.rs.json".into(), json.as_bytes())?;
+    }
+    if let Some(functions) = matches.values_of("emit-benches") {
+        let bindings = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_rs_outputs())
+            .map(|rs_output| syn::parse_str(&generate_rs_single(rs_output).code))
+            .collect::<Result<Vec<syn::File>, _>>()
+            .into_diagnostic()?;
+        let functions: Vec<_> = functions.collect();
+        let benches = benches::generate_benches(&bindings, &functions)?;
+        writer.write_to_file("autocxx_benches.rs".into(), benches.as_bytes())?;
+    }
+    if let Some(api_summary) = matches.value_of("emit-api-summary") {
+        let bindings = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_rs_outputs())
+            .map(|rs_output| syn::parse_str(&generate_rs_single(rs_output).code))
+            .collect::<Result<Vec<syn::File>, _>>()
+            .into_diagnostic()?;
+        let summary = generate_api_summary(&bindings);
+        writer.write_to_file(api_summary.into(), summary.as_bytes())?;
+    }
+    if matches.is_present("gen-include-metadata") {
+        let engines = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_autocxxes());
+        let json = generate_include_metadata(engines);
+        writer.write_to_file("autocxx_includes.json".into(), json.as_bytes())?;
+    }
+    if matches.is_present("gen-extern-rust-header") {
+        let cxxgen_h_prefix = get_option_string("cxxgen-h-path", &matches).unwrap_or_default();
+        let inclusions: String = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_autocxxes())
+            .filter(|engine| {
+                engine
+                    .get_include_requirements()
+                    .iter()
+                    .any(|requirement| requirement.extern_rust)
+            })
+            .filter_map(|engine| engine.get_cxxgen_header_name())
+            .map(|header| format!("#include \"{cxxgen_h_prefix}{header}\"\n"))
+            .collect();
+        let header = format!("// Generated using autocxx - do not edit directly.\n// @generated.\n\n#pragma once\n\n{inclusions}");
+        writer.write_to_file("autocxx_extern_rust.h".into(), header.as_bytes())?;
+    }
+    if fixed_output_names {
+        let manifest: String = writer
+            .written
+            .iter()
+            .map(|filename| format!("{filename}\n"))
+            .collect();
+        writer.write_to_file("autocxx_manifest.txt".into(), manifest.as_bytes())?;
+    }
+    if let Some(depfile) = depfile {
+        depfile.borrow_mut().write().into_diagnostic()?;
+    }
+    if matches.is_present("verify") {
+        let rs = parsed_files
+            .iter()
+            .flat_map(|parsed_file| parsed_file.get_rs_outputs())
+            .map(generate_rs_single)
+            .map(|rs_code| (rs_code.filename, rs_code.code))
+            .collect();
+        let include_dirs: Vec<_> = incs.iter().cloned().chain(cpp_dirs).collect();
+        verify::verify(verify::ScratchCrate {
+            rs,
+            cpp_files: &cpp_files,
+            include_dirs: &include_dirs,
+            extra_clang_args: &extra_clang_args,
+            autocxx_path: matches.value_of("verify-autocxx-path").map(Path::new),
+        })?;
+    }
+    Ok(())
+}
+
+fn name_autocxxgen_h(counter: usize) -> String {
+    format!("autocxxgen{counter}.h")
+}
+
+fn name_cxxgen_h(counter: usize) -> String {
+    format!("gen{counter}.h")
+}
+
+fn name_include_rs(counter: usize) -> String {
+    format!("gen{counter}.include.rs")
+}
+
+fn get_dependency_recorder(depfile: Rc<RefCell<Depfile>>) -> Box<dyn RebuildDependencyRecorder> {
+    Box::new(RecordIntoDepfile(depfile))
+}
+
+fn parse_time_budget(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("'{s}' is not a non-negative number of seconds"))
+}
+
+fn get_option_string(option: &str, matches: &clap::ArgMatches) -> Option<String> {
+    let cxx_impl_annotations = matches.value_of(option).map(|s| s.to_string());
+    cxx_impl_annotations
+}
+
+struct FileWriter<'a> {
+    depfile: &'a Option<Rc<RefCell<Depfile>>>,
+    outdir: &'a Path,
+    written: IndexSet<String>,
+}
+
+impl FileWriter<'_> {
+    fn write_placeholders<F: FnOnce(usize) -> String + Copy>(
+        &mut self,
+        mut counter: usize,
+        desired_number: Option<usize>,
+        filename: F,
+    ) -> miette::Result<()> {
+        if let Some(desired_number) = desired_number {
+            if counter > desired_number {
+                return Err(miette::Report::msg(format!("{counter} files were generated. Increase the value passed to --generate-exact or reduce the number of include_cpp! sections.")));
+            }
+            while counter < desired_number {
+                let fname = filename(counter);
+                self.write_to_file(fname, BLANK.as_bytes())?;
+                counter += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_to_file(&mut self, filename: String, content: &[u8]) -> miette::Result<()> {
+        let path = self.outdir.join(&filename);
+        if let Some(depfile) = self.depfile {
+            depfile.borrow_mut().add_output(&path);
+        }
+        if !self.written.insert(filename.clone()) {
+            return Err(miette::Report::msg(format!("autocxx_gen would write two files entitled '{filename}' which would have conflicting contents. Consider using --generate-exact or --fixed-output-names.")));
+        }
+        {
+            let f = File::open(&path);
+            if let Ok(mut f) = f {
+                let mut existing_content = Vec::new();
+                let r = f.read_to_end(&mut existing_content);
+                if r.is_ok() && existing_content == content {
+                    return Ok(()); // don't change timestamp on existing file unnecessarily
+                }
+            }
+        }
+        let mut f = File::create(&path).into_diagnostic()?;
+        f.write_all(content).into_diagnostic()?;
+        Ok(())
+    }
+}
+
+struct RecordIntoDepfile(Rc<RefCell<Depfile>>);
+
+impl RebuildDependencyRecorder for RecordIntoDepfile {
+    fn record_header_file_dependency(&self, filename: &str) {
+        self.0.borrow_mut().add_dependency(&PathBuf::from(filename))
+    }
+}
+
+impl std::fmt::Debug for RecordIntoDepfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<depfile>")
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `--verify` checks that the generated Rust parses, and then that it
+//! builds, by running `cargo check` on a scratch crate containing it along
+//! with any generated C++. Errors are reported against the generated item
+//! responsible, rather than surfacing later in the user's own crate.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use autocxx_engine::get_cxx_header_bytes;
+use miette::IntoDiagnostic;
+
+/// Keywords which introduce the items we name in error messages.
+const ITEM_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "union", "type", "trait", "mod", "impl",
+];
+
+/// The scratch crate to build.
+pub(crate) struct ScratchCrate<'a> {
+    /// Each generated Rust file, as its name and contents.
+    pub(crate) rs: Vec<(String, String)>,
+    pub(crate) cpp_files: &'a [PathBuf],
+    pub(crate) include_dirs: &'a [PathBuf],
+    pub(crate) extra_clang_args: &'a [&'a str],
+    /// Where to find autocxx, if not the published version.
+    pub(crate) autocxx_path: Option<&'a Path>,
+}
+
+pub(crate) fn verify(scratch_crate: ScratchCrate) -> miette::Result<()> {
+    // If any of the Rust doesn't even parse, there's no point asking cargo.
+    let mut pretty = Vec::new();
+    for (filename, code) in &scratch_crate.rs {
+        let file = syn::parse_file(code).map_err(|err| {
+            let start = err.span().start();
+            let offset = byte_offset(code, start.line, start.column);
+            miette::Report::msg(format!(
+                "The generated Rust in {filename} doesn't parse: {err}{}",
+                describe_item(&code[..offset])
+            ))
+        })?;
+        pretty.push(prettyplease::unparse(&file));
+    }
+    let dir = std::env::temp_dir().join(format!("autocxx-verify-{}", std::process::id()));
+    scratch_crate.write(&dir, &pretty).into_diagnostic()?;
+    let output = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+        .args([
+            "check",
+            "--quiet",
+            "--message-format=short",
+            "--manifest-path",
+        ])
+        .arg(dir.join("Cargo.toml"))
+        .output()
+        .into_diagnostic()?;
+    if output.status.success() {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Ok(());
+    }
+    let errors = annotate_errors(&String::from_utf8_lossy(&output.stderr), |file| {
+        std::fs::read_to_string(dir.join(file)).ok()
+    });
+    Err(miette::Report::msg(format!(
+        "The generated bindings don't build. The scratch crate is in {}.\n{errors}",
+        dir.display()
+    )))
+}
+
+impl ScratchCrate<'_> {
+    fn write(&self, dir: &Path, pretty: &[String]) -> std::io::Result<()> {
+        let src = dir.join("src");
+        let include = dir.join("include");
+        std::fs::create_dir_all(&src)?;
+        std::fs::create_dir_all(&include)?;
+        let autocxx = match self.autocxx_path {
+            Some(path) => format!("{{ path = {:?} }}", path.display().to_string()),
+            None => format!("\"={}\"", env!("CARGO_PKG_VERSION")),
+        };
+        let mut manifest = format!(
+            "[package]\nname = \"autocxx-verify\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n[dependencies]\nautocxx = {autocxx}\ncxx = \"1.0\"\n"
+        );
+        let mut lib_rs = String::from("#![allow(warnings)]\n");
+        for (i, code) in pretty.iter().enumerate() {
+            writeln!(lib_rs, "mod gen{i};").unwrap();
+            std::fs::write(src.join(format!("gen{i}.rs")), code)?;
+        }
+        std::fs::write(src.join("lib.rs"), lib_rs)?;
+        if !self.cpp_files.is_empty() {
+            manifest.push_str("\n[build-dependencies]\ncc = \"1.0\"\n");
+            std::fs::write(include.join("cxx.h"), get_cxx_header_bytes(false))?;
+            let include_dirs: Vec<_> = std::iter::once(include.as_path())
+                .chain(self.include_dirs.iter().map(PathBuf::as_path))
+                .collect();
+            let build_rs = format!(
+                "fn main() {{\n    let mut build = cc::Build::new();\n    build\n        .cpp(true)\n        .files({:?})\n        .includes({include_dirs:?})\n        .flag_if_supported(\"-std=c++17\");\n    for flag in {:?} {{\n        build.flag_if_supported(flag);\n    }}\n    build.compile(\"autocxx-verify\");\n}}\n",
+                self.cpp_files, self.extra_clang_args
+            );
+            std::fs::write(dir.join("build.rs"), build_rs)?;
+        }
+        manifest.push_str("\n[workspace]\n");
+        std::fs::write(dir.join("Cargo.toml"), manifest)
+    }
+}
+
+/// The byte offset of a (1-based) line and (0-based, in characters) column.
+fn byte_offset(code: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = code
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    code[line_start..]
+        .char_indices()
+        .nth(column)
+        .map_or(code.len(), |(offset, _)| line_start + offset)
+}
+
+/// Name the item whose declaration most closely precedes the end of this
+/// code, which is likely to be the one containing an error there.
+fn describe_item(preceding: &str) -> String {
+    let words: Vec<&str> = preceding
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .windows(2)
+        .rev()
+        .find(|pair| ITEM_KEYWORDS.contains(&pair[0]))
+        .map(|pair| format!(" (in `{} {}`)", pair[0], pair[1]))
+        .unwrap_or_default()
+}
+
+/// Add to each `file:line:column: message` from `cargo check` the
+/// generated item to which it refers.
+fn annotate_errors(stderr: &str, read_file: impl Fn(&str) -> Option<String>) -> String {
+    stderr
+        .lines()
+        .map(|line| {
+            let mut parts = line.splitn(3, ':');
+            let (Some(file), Some(Ok(line_number))) =
+                (parts.next(), parts.next().map(str::parse::<usize>))
+            else {
+                return format!("{line}\n");
+            };
+            let item = read_file(file)
+                .map(|contents| {
+                    let preceding: Vec<&str> = contents.lines().take(line_number).collect();
+                    describe_item(&preceding.join("\n"))
+                })
+                .unwrap_or_default();
+            format!("{line}{item}\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotate_errors, byte_offset, describe_item};
+
+    #[test]
+    fn test_describe_item() {
+        let code = "mod ffi { pub fn DoMath(a: u32) -> u32 { a } pub struct Goat { horns: u32";
+        assert_eq!(describe_item(code), " (in `struct Goat`)");
+        assert_eq!(describe_item(&code[..30]), " (in `fn DoMath`)");
+        assert_eq!(describe_item("#[allow(unused)]"), "");
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let code = "mod a {\n    fn é() {}\n}";
+        assert_eq!(&code[byte_offset(code, 2, 8)..], "() {}\n}");
+        assert_eq!(byte_offset(code, 3, 5), code.len());
+    }
+
+    #[test]
+    fn test_annotate_errors() {
+        let stderr = "src/gen0.rs:3:20: error[E0412]: cannot find type `Goat` in this scope\nerror: could not compile `autocxx-verify`\n";
+        let annotated = annotate_errors(stderr, |file| {
+            (file == "src/gen0.rs")
+                .then(|| "mod ffi {\n    pub fn feed(\n        goat: &Goat,\n    ) {}\n}\n".into())
+        });
+        assert_eq!(
+            annotated,
+            "src/gen0.rs:3:20: error[E0412]: cannot find type `Goat` in this scope (in `fn feed`)\nerror: could not compile `autocxx-verify`\n"
+        );
+    }
+}
@@ -40,6 +40,24 @@ fn test_help() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_bad_time_budget() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let mut cmd = Command::cargo_bin("autocxx-gen")?;
+    let output = cmd
+        .arg("--state-dir")
+        .arg(tmp_dir.path())
+        .arg("--time-budget")
+        .arg("soon")
+        .arg("main.rs")
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("not a non-negative number of seconds"));
+    assert!(!stderr.contains("panicked"));
+    Ok(())
+}
+
 enum RsGenMode {
     Single,
     Archive,
@@ -306,6 +324,146 @@ fn test_gen_repro() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_gen_dump_phases() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let dump_dir = tmp_dir.path().join("phases");
+    base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+        cmd.env("AUTOCXX_DUMP_PHASES", dump_dir.to_str().unwrap());
+    })?;
+    let gc_dump = std::fs::read_dir(&dump_dir)?
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_str().unwrap().ends_with("-GC.json"))
+        .expect("No dump of the APIs after garbage collection");
+    assert!(std::fs::read_to_string(gc_dump)?.contains("DoMath"));
+    Ok(())
+}
+
+#[test]
+fn test_gen_verify() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let autocxx_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+        cmd.arg("--verify")
+            .arg("--verify-autocxx-path")
+            .arg(autocxx_path.to_str().unwrap());
+    })
+}
+
+#[test]
+fn test_gen_benches() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+        cmd.arg("--emit-benches").arg("autocxx_demo::ffi::DoMath");
+    })?;
+    assert_contains(
+        &tmp_dir,
+        "autocxx_benches.rs",
+        "autocxx_demo::ffi::DoMath(black_box(Default::default()))",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_gen_api_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+        cmd.arg("--emit-api-summary").arg("api.txt");
+    })?;
+    assert_contains(&tmp_dir, "api.txt", "fn ffi::DoMath(a: u32) -> u32");
+    Ok(())
+}
+
+#[test]
+fn test_gen_include_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+        cmd.arg("--gen-include-metadata")
+            .arg("--gen-extern-rust-header");
+    })?;
+    assert_contains(&tmp_dir, "autocxx_includes.json", "\"item\": \"DoMath\"");
+    assert_contains(&tmp_dir, "autocxx_includes.json", "\"input.h\"");
+    assert_contains(&tmp_dir, "autocxx_extern_rust.h", "#pragma once");
+    Ok(())
+}
+
+#[test]
+fn test_gen_include_metadata_per_header() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let mut files = HashMap::new();
+    files.insert("input2.h", INPUT2_H.as_bytes());
+    files.insert("input3.h", INPUT3_H.as_bytes());
+    files.insert(
+        "main.rs",
+        "autocxx::include_cpp! {
+            #include \"input2.h\"
+            #include \"input3.h\"
+            safety!(unsafe_ffi)
+            generate!(\"get_hello\")
+            generate!(\"get_goodbye\")
+        }
+        fn main() {}"
+            .as_bytes(),
+    );
+    base_test_ex(
+        &tmp_dir,
+        RsGenMode::Single,
+        |cmd| {
+            cmd.arg("--gen-include-metadata");
+        },
+        files,
+        vec!["main.rs"],
+    )?;
+    let json = std::fs::read_to_string(tmp_dir.path().join("autocxx_includes.json"))?;
+    let json: serde_json::Value = serde_json::from_str(&json)?;
+    let headers_for = |item: &str| {
+        json["ffi"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|requirement| requirement["item"] == item)
+            .unwrap_or_else(|| panic!("No include requirement for {item}"))["headers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|header| header.as_str().unwrap().to_string())
+            .collect_vec()
+    };
+    let hello_headers = headers_for("get_hello");
+    assert!(hello_headers.contains(&"input2.h".to_string()));
+    assert!(!hello_headers.contains(&"input3.h".to_string()));
+    let goodbye_headers = headers_for("get_goodbye");
+    assert!(goodbye_headers.contains(&"input3.h".to_string()));
+    assert!(!goodbye_headers.contains(&"input2.h".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_gen_fixed_output_names() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let mut files = HashMap::new();
+    files.insert("input.h", INPUT_H.as_bytes());
+    files.insert("main.rs", MAIN_RS.as_bytes());
+    base_test_ex(
+        &tmp_dir,
+        RsGenMode::Single,
+        |cmd| {
+            cmd.arg("--fixed-output-names")
+                .arg("--cpp-subdir")
+                .arg("cpp");
+        },
+        files,
+        vec!["main.rs"],
+    )?;
+    assert_contentful(&tmp_dir, "cpp/cxxgen_ffi.cc");
+    assert_contentful(&tmp_dir, "cpp/cxxgen_ffi.h");
+    assert_contentful(&tmp_dir, "cpp/autocxxgen_ffi.h");
+    assert_contains(&tmp_dir, "cpp/cxxgen_ffi.cc", "cxxgen_ffi.h");
+    assert_contains(&tmp_dir, "autocxx_manifest.txt", "cpp/cxxgen_ffi.cc");
+    assert_contains(&tmp_dir, "autocxx_manifest.txt", "cpp/autocxxgen_ffi.h");
+    Ok(())
+}
+
 fn write_to_file(dir: &Path, filename: &str, content: &[u8]) {
     let path = dir.join(filename);
     let mut f = File::create(path).expect("Unable to create file");
@@ -44,7 +44,6 @@ fn configure_builder(b: &mut BuilderBuild) -> &mut BuilderBuild {
     let target = rust_info::get().target_triple.unwrap();
     b.host(&target)
         .target(&target)
-        .opt_level(1)
         .flag("-std=c++14") // For clang
         .flag_if_supported("/GX") // Enable C++ exceptions for msvc
         .flag_if_supported("-Wall")
@@ -77,6 +76,7 @@ pub fn build_from_folder(
         b.file(folder.join(cpp_file));
     }
     configure_builder(&mut b)
+        .opt_level(1)
         .out_dir(&target_dir)
         .include(folder)
         .include(folder.join("demo"))
@@ -447,8 +447,11 @@ pub fn do_run_test_manual(
     let rs_path = write_rust_to_file(&rust_code);
 
     info!("Path is {:?}", tdir.path());
+    // Set the optimization level through autocxx, so that tests can
+    // override it.
     let builder = Builder::<TestBuilderContext>::new(&rs_path, [tdir.path()])
-        .custom_gendir(target_dir.clone());
+        .custom_gendir(target_dir.clone())
+        .cpp_opt_level("1");
     let builder = if let Some(builder_modifier) = &builder_modifier {
         builder_modifier.modify_autocxx_builder(builder)
     } else {
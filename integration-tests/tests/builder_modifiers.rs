@@ -60,6 +60,17 @@ impl BuilderModifierFns for SetSuppressSystemHeaders {
     }
 }
 
+pub(crate) struct SetCppOptLevel(pub(crate) &'static str);
+
+impl BuilderModifierFns for SetCppOptLevel {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.cpp_opt_level(self.0)
+    }
+}
+
 pub(crate) struct EnableAutodiscover;
 
 impl BuilderModifierFns for EnableAutodiscover {
@@ -70,3 +81,42 @@ impl BuilderModifierFns for EnableAutodiscover {
         builder.auto_allowlist(true)
     }
 }
+
+/// Removes the given line from every header, before bindgen sees it.
+pub(crate) struct HeaderLineStripper(pub(crate) &'static str);
+
+impl BuilderModifierFns for HeaderLineStripper {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        let line = self.0;
+        builder.header_preprocessor(move |headers| {
+            for header in &mut headers.headers {
+                header.contents = header
+                    .contents
+                    .lines()
+                    .filter(|l| l.trim() != line)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Compiles another C++ source file along with the test's own.
+pub(crate) struct SourceFileCompiler(pub(crate) String);
+
+impl BuilderModifierFns for SourceFileCompiler {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder
+    }
+
+    fn modify_cc_builder<'a>(&self, builder: &'a mut cc::Build) -> &'a mut cc::Build {
+        builder.file(&self.0)
+    }
+}
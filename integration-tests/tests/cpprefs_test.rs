@@ -25,6 +25,21 @@ fn run_cpprefs_test(
     rust_code: TokenStream,
     generate: &[&str],
     generate_pods: &[&str],
+) {
+    run_cpprefs_test_ex(
+        cxx_code,
+        header_code,
+        rust_code,
+        directives_from_lists(generate, generate_pods, None),
+    )
+}
+
+/// A positive test with arbitrary directives.
+fn run_cpprefs_test_ex(
+    cxx_code: &str,
+    header_code: &str,
+    rust_code: TokenStream,
+    directives: TokenStream,
 ) {
     if !arbitrary_self_types_supported() {
         // "unsafe_references_wrapped" requires arbitrary_self_types, which requires nightly.
@@ -34,7 +49,7 @@ fn run_cpprefs_test(
         cxx_code,
         header_code,
         rust_code,
-        directives_from_lists(generate, generate_pods, None),
+        directives,
         None,
         None,
         None,
@@ -133,3 +148,200 @@ fn test_return_reference_cpprefs() {
     };
     run_cpprefs_test(cxx, hdr, rs, &["give_bob"], &["Bob"]);
 }
+
+#[test]
+fn test_method_call_mut_from_pin() {
+    run_cpprefs_test(
+        "",
+        indoc! {"
+        #include <cstdint>
+
+        class Goat {
+            public:
+                Goat() : horns(0) {}
+                void add_a_horn() { horns++; }
+                uint32_t get_horns() const { return horns; }
+            private:
+                uint32_t horns;
+        };
+    "},
+        quote! {
+            let mut goat = ffi::Goat::new().within_unique_ptr();
+            let mut goat_ref = autocxx::CppMutRef::from(goat.pin_mut());
+            goat_ref.add_a_horn();
+            let pinned = unsafe { goat_ref.as_pin_mut_unchecked() };
+            autocxx::CppMutRef::from(pinned).add_a_horn();
+            assert_eq!(goat_ref.get_horns(), 2);
+        },
+        &["Goat"],
+        &[],
+    )
+}
+
+#[test]
+fn test_unique_ptr_pin_null() {
+    run_cpprefs_test(
+        "",
+        indoc! {"
+        #include <cstdint>
+
+        class Goat {
+            public:
+                Goat() : horns(0) {}
+                void add_a_horn() { horns++; }
+            private:
+                uint32_t horns;
+        };
+    "},
+        quote! {
+            let null_goat = cxx::UniquePtr::<ffi::Goat>::null();
+            assert_eq!(
+                autocxx::CppUniquePtrPin::try_new(null_goat).err(),
+                Some(autocxx::NullUniquePtr)
+            );
+            let null_goat = autocxx::CppUniquePtrPin::new(cxx::UniquePtr::<ffi::Goat>::null());
+            assert!(null_goat.try_as_cpp_ref().is_err());
+            let goat = ffi::Goat::new().within_unique_ptr();
+            let mut goat = autocxx::CppUniquePtrPin::try_new(goat).unwrap();
+            goat.try_as_cpp_mut_ref().unwrap().add_a_horn();
+        },
+        &["Goat"],
+        &[],
+    )
+}
+
+#[test]
+fn test_cpp_pin_as_base_ref() {
+    run_cpprefs_test(
+        "",
+        indoc! {"
+        #include <cstdint>
+
+        class Horns {
+            public:
+                Horns() : horns(1) {}
+                uint32_t horns;
+        };
+
+        class Hooves {
+            public:
+                Hooves() : hooves(4) {}
+                uint32_t hooves;
+        };
+
+        class Goat : public Horns, public Hooves {
+            public:
+                Goat() {}
+        };
+
+        inline uint32_t count_horns(const Horns& horns) { return horns.horns; }
+        inline uint32_t count_hooves(const Hooves& hooves) { return hooves.hooves; }
+        inline void lose_a_hoof(Hooves& hooves) { hooves.hooves--; }
+    "},
+        quote! {
+            let mut goat = autocxx::CppPin::from_pinned_box(Box::emplace(ffi::Goat::new()));
+            assert_eq!(ffi::count_horns(goat.as_cpp_ref()), 1);
+            // Hooves isn't at the start of Goat, so this relies on C++
+            // adjusting the pointer.
+            assert_eq!(ffi::count_hooves(goat.as_cpp_ref()), 4);
+            ffi::lose_a_hoof(goat.as_cpp_mut_ref());
+            assert_eq!(ffi::count_hooves(goat.as_cpp_ref()), 3);
+        },
+        &[
+            "Goat",
+            "Horns",
+            "Hooves",
+            "count_horns",
+            "count_hooves",
+            "lose_a_hoof",
+        ],
+        &[],
+    )
+}
+
+#[test]
+fn test_return_reference_cpprefs_deleted_address_of() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a;
+            uint32_t b;
+            void operator&() const = delete;
+        };
+        inline const Bob& give_bob(const Bob& input_bob) {
+            return input_bob;
+        }
+    "};
+    let rs = quote! {
+        let b = CppPin::new(ffi::Bob { a: 3, b: 4 });
+        let bob = ffi::give_bob(b.as_cpp_ref());
+        let val = unsafe { bob.as_ref() };
+        assert_eq!(val.b, 4);
+    };
+    run_cpprefs_test("", hdr, rs, &["give_bob"], &["Bob"]);
+}
+
+#[test]
+fn test_error_type_cpprefs() {
+    let hdr = indoc! {"
+        #include <stdexcept>
+        class ParseError : public std::runtime_error {
+        public:
+            ParseError() : std::runtime_error(\"unexpected token\") {}
+        };
+    "};
+    let rs = quote! {
+        let e = CppPin::from_pinned_box(Box::emplace(ffi::ParseError::new()));
+        assert_eq!(e.as_cpp_ref().error_message().to_str().unwrap(), "unexpected token");
+        let e: &dyn std::error::Error = unsafe { e.as_ref() };
+        assert_eq!(e.to_string(), "unexpected token");
+    };
+    run_cpprefs_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ParseError")
+            error_type!("ParseError")
+        },
+    );
+}
+
+#[test]
+fn test_object_identity_cpprefs() {
+    let hdr = indoc! {"
+        class Widget {
+        public:
+            virtual ~Widget() {}
+            int width = 3;
+        };
+        class Observer {
+        public:
+            virtual ~Observer() {}
+            virtual void notify() {}
+        };
+        class Button : public Widget, public Observer {};
+    "};
+    let rs = quote! {
+        let button = CppPin::from_pinned_box(Box::emplace(ffi::Button::new()));
+        // Each of these needs a type, since the button can be referred to
+        // as any of them.
+        let derived: autocxx::CppRef<ffi::Button> = button.as_cpp_ref();
+        let widget: autocxx::CppRef<ffi::Widget> = button.as_cpp_ref();
+        let observer: autocxx::CppRef<ffi::Observer> = button.as_cpp_ref();
+        assert_ne!(widget.as_ptr() as usize, observer.as_ptr() as usize);
+        assert_eq!(widget.object_identity(), observer.object_identity());
+        assert_eq!(derived.object_identity(), observer.object_identity());
+    };
+    run_cpprefs_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Widget")
+            generate!("Observer")
+            generate!("Button")
+            object_identity!()
+        },
+    );
+}
@@ -9,7 +9,7 @@
 use crate::{
     builder_modifiers::{
         make_clang_arg_adder, make_clang_optional_arg_adder, make_cpp17_adder, EnableAutodiscover,
-        SetSuppressSystemHeaders,
+        HeaderLineStripper, SetCppOptLevel, SetSuppressSystemHeaders, SourceFileCompiler,
     },
     code_checkers::{
         make_error_finder, make_rust_code_finder, make_string_finder, CppMatcher,
@@ -4008,6 +4008,28 @@ fn test_root_ns_meth_ret_nonpod() {
     run_test("", hdr, rs, &["Bob"], &["B::C"]);
 }
 
+#[test]
+fn test_extend() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct A {
+            uint32_t a;
+        };
+        inline uint32_t get_a(const A& a) { return a.a; }
+    "};
+    let rs = quote! {
+        #[autocxx::extend(AExt)]
+        impl ffi::A {
+            pub fn doubled(&self) -> u32 {
+                ffi::get_a(self) * 2
+            }
+        }
+        let a = ffi::A { a: 21 };
+        assert_eq!(a.doubled(), 42);
+    };
+    run_test("", hdr, rs, &["get_a"], &["A"]);
+}
+
 #[test]
 fn test_forward_declaration() {
     let hdr = indoc! {"
@@ -4058,6 +4080,43 @@ fn test_forward_declaration() {
     run_test(cpp, hdr, rs, &["B", "get_a", "delete_a"], &[]);
 }
 
+#[test]
+fn test_nested_forward_declaration() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+        struct Outer {
+            struct Inner;
+            static Inner* make_inner();
+            static uint32_t get(const Inner&);
+            static void delete_inner(Inner*);
+        };
+        }
+    "};
+    let cpp = indoc! {"
+        namespace a {
+        struct Outer::Inner {
+            uint32_t val = 42;
+        };
+        Outer::Inner* Outer::make_inner() {
+            return new Inner();
+        }
+        uint32_t Outer::get(const Inner& inner) {
+            return inner.val;
+        }
+        void Outer::delete_inner(Inner* inner) {
+            delete inner;
+        }
+        }
+    "};
+    let rs = quote! {
+        let inner = ffi::a::Outer::make_inner();
+        assert_eq!(ffi::a::Outer::get(unsafe { inner.as_ref().unwrap() }), 42);
+        unsafe { ffi::a::Outer::delete_inner(inner) };
+    };
+    run_test(cpp, hdr, rs, &["a::Outer"], &[]);
+}
+
 #[test]
 fn test_ulong() {
     let hdr = indoc! {"
@@ -9838,6 +9897,31 @@ fn test_suppress_system_includes() {
     );
 }
 
+#[test]
+fn test_cpp_opt_level() {
+    let cxx = indoc! {"
+        #ifndef __OPTIMIZE_SIZE__
+        #error cpp_opt_level should have been applied to the generated C++
+        #endif
+    "};
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t a() { return 3; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::a(), 3);
+    };
+    run_test_ex(
+        cxx,
+        hdr,
+        rs,
+        quote! { generate!("a") },
+        Some(Box::new(SetCppOptLevel("s"))),
+        None,
+        None,
+    );
+}
+
 #[test]
 fn test_no_rvo_move() {
     let hdr = indoc! {"
@@ -12578,11 +12662,4652 @@ fn test_opaque_directive() {
     );
 }
 
-// Yet to test:
-// - Ifdef
-// - Out param pointers
-// - ExcludeUtilities
-// - Struct fields which are typedefs
-// Negative tests:
-// - Private methods
-// - Private fields
+#[test]
+fn test_treat_as_const() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            class Foo {
+            public:
+                Foo() : a(3) {}
+                uint32_t size() { return a; }
+                void set_size(uint32_t b) { a = b; }
+            private:
+                uint32_t a;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut foo = ffi::a::Foo::new().within_unique_ptr();
+        let foo_ref: &ffi::a::Foo = foo.as_ref().unwrap();
+        assert_eq!(foo_ref.size(), 3);
+        foo.pin_mut().set_size(4);
+        assert_eq!(foo.size(), 4);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("a::Foo")
+            treat_as_const!("a::Foo::size")
+        },
+        None,
+        Some(Box::new(CppMatcher::new(&["const_cast<a::Foo&>"], &[]))),
+        None,
+    );
+}
+
+#[test]
+fn test_force_inline() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            struct Vec3 {
+                static uint32_t dims() { return 3; }
+                static uint32_t twice(uint32_t a) { return a * 2; }
+            };
+            inline uint32_t four() { return 4; }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::a::Vec3::dims(), 3);
+        assert_eq!(ffi::a::Vec3::twice(2), 4);
+        assert_eq!(ffi::a::four(), 4);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("a::Vec3")
+            generate!("a::four")
+            force_inline!("a::Vec3::dims")
+            force_inline!("a::four")
+        },
+        None,
+        Some(Box::new(CppMatcher::new(
+            &[
+                "AUTOCXX_ALWAYS_INLINE uint32_t",
+                "inline uint32_t",
+                "four_autocxx_wrapper",
+            ],
+            &[],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_force_inline_missing_function() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t four() { return 4; }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("four")
+            force_inline!("five")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pod_field_offsets() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <cstddef>
+        struct A {
+            uint8_t a;
+            uint32_t b;
+            uint16_t c;
+        };
+        inline size_t offset_of_b() { return offsetof(A, b); }
+        inline size_t offset_of_c() { return offsetof(A, c); }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::A::FIELD_OFFSETS.len(), 3);
+        assert_eq!(ffi::A::FIELD_OFFSETS[0], ("a", 0));
+        assert_eq!(ffi::A::FIELD_OFFSETS[1], ("b", ffi::offset_of_b()));
+        assert_eq!(ffi::A::FIELD_OFFSETS[2], ("c", ffi::offset_of_c()));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["offset_of_b", "offset_of_c"], &["A"], None),
+        None,
+        Some(Box::new(CppMatcher::new(
+            &[
+                "static_assert(offsetof(A, a) == 0",
+                "static_assert(offsetof(A, b) == 4",
+                "static_assert(offsetof(A, c) == 8",
+            ],
+            &[],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_enum_bits() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        enum class Colour : uint8_t {
+            Red = 1,
+            Green = 2,
+            Blue = 200,
+        };
+        inline uint8_t colour_value(Colour c) { return static_cast<uint8_t>(c); }
+    "};
+    let rs = quote! {
+        let blue: u8 = ffi::Colour::Blue.bits();
+        assert_eq!(blue, 200);
+        assert_eq!(ffi::colour_value(ffi::Colour::Green), ffi::Colour::Green.bits());
+        assert!(ffi::Colour::from_bits(2) == Some(ffi::Colour::Green));
+        assert!(ffi::Colour::from_bits(3).is_none());
+    };
+    run_test("", hdr, rs, &["Colour", "colour_value"], &[]);
+}
+
+#[test]
+fn test_enum_variant_names() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        enum class Colour : uint8_t {
+            Red = 1,
+            Green = 2,
+            Blue = 200,
+        };
+    "};
+    let rs = quote! {
+        assert!(
+            ffi::Colour::ALL_VARIANTS
+                == &[ffi::Colour::Red, ffi::Colour::Green, ffi::Colour::Blue]
+        );
+        assert_eq!(ffi::Colour::Blue.name(), "Blue");
+        assert!(ffi::Colour::from_name("Green") == Some(ffi::Colour::Green));
+        assert!(ffi::Colour::from_name("Purple").is_none());
+        for colour in ffi::Colour::ALL_VARIANTS {
+            assert!(ffi::Colour::from_name(colour.name()) == Some(*colour));
+        }
+    };
+    run_test("", hdr, rs, &["Colour"], &[]);
+}
+
+#[test]
+fn test_enum_variant_names_clash_with_helpers() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        enum class Field : uint8_t {
+            name,
+            bits,
+            ALL_VARIANTS,
+        };
+    "};
+    let rs = quote! {
+        assert!(ffi::Field::from_name("bits") == Some(ffi::Field::bits));
+        assert!(ffi::Field::from_bits(2) == Some(ffi::Field::ALL_VARIANTS));
+    };
+    run_test("", hdr, rs, &["Field"], &[]);
+}
+
+#[test]
+fn test_tagged_union() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        struct KeyEvent {
+            uint32_t code;
+        };
+        struct MouseEvent {
+            int32_t x;
+            int32_t y;
+        };
+        enum class EventType : uint8_t {
+            Key = 1,
+            Mouse = 2,
+            Other = 3,
+        };
+        struct Event {
+            EventType type;
+            union {
+                KeyEvent key;
+                MouseEvent mouse;
+            } data;
+        };
+        inline std::unique_ptr<Event> make_mouse_event() {
+            auto e = std::make_unique<Event>();
+            e->type = EventType::Mouse;
+            e->data.mouse.x = 3;
+            e->data.mouse.y = 4;
+            return e;
+        }
+        inline std::unique_ptr<Event> make_other_event() {
+            auto e = std::make_unique<Event>();
+            e->type = EventType::Other;
+            return e;
+        }
+    "};
+    let rs = quote! {
+        use std::convert::TryFrom;
+        let e = ffi::make_mouse_event();
+        match ffi::EventView::try_from(e.as_ref().unwrap()).unwrap() {
+            ffi::EventView::Mouse(m) => assert_eq!(m.y, 4),
+            ffi::EventView::Key(_) => panic!("wrong variant"),
+        }
+        let e = ffi::make_other_event();
+        assert_eq!(
+            ffi::EventView::try_from(e.as_ref().unwrap()).err(),
+            Some(autocxx::UnknownUnionTag(3))
+        );
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Event")
+            generate_pod!("KeyEvent")
+            generate_pod!("MouseEvent")
+            generate!("make_mouse_event")
+            generate!("make_other_event")
+            safety!(unsafe_ffi)
+            tagged_union!("Event", tag = "type", variants = {
+                1 => Key("KeyEvent", "data.key"),
+                2 => Mouse("MouseEvent", "data.mouse"),
+            })
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_generate_async() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t slow_add(uint32_t a, uint32_t b) { return a + b; }
+    "};
+    let rs = quote! {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        struct ThreadWaker(std::thread::Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(ffi::slow_add_async(2, 3));
+        let result = loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => std::thread::park(),
+            }
+        };
+        assert_eq!(result, 5);
+        assert_eq!(ffi::slow_add(2, 3), 5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_async!("slow_add")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_generate_async_ineligible() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t add_to(const uint32_t& a, uint32_t b) { return a + b; }
+    "};
+    let rs = quote! {};
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_async!("add_to")
+        },
+        None,
+        Some(make_string_finder(vec![
+            "generate_async! was requested for add_to".to_string(),
+        ])),
+        None,
+    );
+}
+
+#[test]
+fn test_completion_future() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <functional>
+        namespace ns {
+            inline std::function<void(uint64_t)>& pending() {
+                static std::function<void(uint64_t)> callback;
+                return callback;
+            }
+            inline void fetch(uint32_t key, std::function<void(uint64_t)> done) {
+                pending() = [key, done](uint64_t extra) { done(key + extra); done(0); };
+            }
+            inline void finish(uint64_t extra) {
+                std::function<void(uint64_t)> callback = std::move(pending());
+                pending() = nullptr;
+                callback(extra);
+            }
+            inline void forget(std::function<void()>) {}
+        }
+    "};
+    let rs = quote! {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+        let waker = Arc::new(NoopWaker).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(ffi::ns::fetch_future(40));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        ffi::ns::finish(2);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(42)));
+        let mut fut = Box::pin(ffi::ns::forget_future());
+        assert_eq!(
+            fut.as_mut().poll(&mut cx),
+            Poll::Ready(Err(autocxx::oneshot::Canceled))
+        );
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::finish")
+            safety!(unsafe_ffi)
+            completion_future!("ns::fetch", fn(u32) -> u64)
+            completion_future!("ns::forget", fn())
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_overload_names() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            inline uint32_t get1() { return 7; }
+            inline uint32_t get(uint32_t a) { return a; }
+            inline uint32_t get(uint32_t a, uint32_t b) { return a + b; }
+            class Foo {
+            public:
+                uint32_t set(uint32_t) const { return 1; }
+                uint32_t set(uint32_t, uint32_t) const { return 2; }
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        assert_eq!(ffi::a::get(1), 1);
+        assert_eq!(ffi::a::get2(1, 2), 3);
+        assert_eq!(ffi::a::get1(), 7);
+        let foo = ffi::a::Foo::new().within_unique_ptr();
+        assert_eq!(foo.set_one(0), 1);
+        assert_eq!(foo.set_two(0, 0), 2);
+        assert!(ffi::AUTOCXX_RENAMED_ITEMS.contains(&("a::get", "a::get2")));
+        assert!(ffi::AUTOCXX_RENAMED_ITEMS.contains(&("a::Foo::set", "a::Foo::set_one")));
+        assert!(ffi::AUTOCXX_RENAMED_ITEMS.contains(&("a::Foo::set", "a::Foo::set_two")));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_ns!("a")
+            overload_names!("a::Foo::set", "set_one", "set_two")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_custom_analysis_pass() {
+    use autocxx_engine::plugins::{register_analysis_pass, AnalysisPass, ApiEditor, ApiKind};
+    struct TestPass;
+    impl AnalysisPass for TestPass {
+        fn name(&self) -> &str {
+            "test_pass"
+        }
+        fn visit(&self, api: &mut ApiEditor) {
+            // Passes are global to the process, so only touch this test's items.
+            match api.cpp_name() {
+                "custom_pass_test::drop_me" => api.drop_api("Dropped by policy"),
+                "custom_pass_test::keep_me" if api.kind() == ApiKind::Function => {
+                    api.add_doc("Documented by a custom pass")
+                }
+                _ => {}
+            }
+        }
+    }
+    register_analysis_pass(TestPass);
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace custom_pass_test {
+            inline uint32_t keep_me() { return 3; }
+            inline uint32_t drop_me() { return 4; }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::custom_pass_test::keep_me(), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! { generate_ns!("custom_pass_test") },
+        None,
+        Some(make_string_finder(vec![
+            "Documented by a custom pass".to_string(),
+            "The custom analysis pass 'test_pass' chose not to generate this item: Dropped by policy"
+                .to_string(),
+        ])),
+        None,
+    );
+}
+
+#[test]
+fn test_operator_bool() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            class Status {
+            public:
+                Status(uint32_t code) : code(code) {}
+                explicit operator bool() const { return code == 0; }
+            private:
+                uint32_t code;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        assert!(ffi::a::Status::new(0).within_unique_ptr().is_truthy());
+        assert!(!ffi::a::Status::new(1).within_unique_ptr().is_truthy());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("a::Status")
+            operator_bool!("a::Status")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_conversion_operator() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        namespace a {
+            class Handle {
+            public:
+                Handle(uint32_t id) : id(id) {}
+                operator uint32_t() const { return id; }
+                explicit operator std::string() const { return std::to_string(id); }
+            private:
+                uint32_t id;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let handle = ffi::a::Handle::new(42).within_unique_ptr();
+        assert_eq!(handle.to_uint32_t(), 42);
+        assert_eq!(handle.to_string().to_str().unwrap(), "42");
+        let as_int: u32 = handle.as_ref().unwrap().into();
+        assert_eq!(as_int, 42);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("a::Handle")
+            conversion_operator!("a::Handle", "uint32_t", impl_from)
+            conversion_operator!("a::Handle", "std::string")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_adl_swap_as_method() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            struct Counter {
+                Counter(uint32_t count) : count(count) {}
+                uint32_t get() const { return count; }
+                uint32_t count;
+            };
+        }
+        namespace b {
+            inline void swap(a::Counter& lhs, a::Counter& rhs) {
+                uint32_t temp = lhs.count;
+                lhs.count = rhs.count;
+                rhs.count = temp;
+            }
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut x = ffi::a::Counter::new(1).within_unique_ptr();
+        let mut y = ffi::a::Counter::new(2).within_unique_ptr();
+        x.pin_mut().swap(y.pin_mut());
+        assert_eq!(x.get(), 2);
+        assert_eq!(y.get(), 1);
+        ffi::b::swap(x.pin_mut(), y.pin_mut());
+        assert_eq!(x.get(), 1);
+    };
+    run_test("", hdr, rs, &["a::Counter", "b::swap"], &[]);
+}
+
+#[test]
+fn test_adl_swap_duplicates() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            struct Counter {
+                Counter(uint32_t count) : count(count) {}
+                uint32_t get() const { return count; }
+                uint32_t count;
+            };
+        }
+        namespace b {
+            inline void swap(a::Counter& lhs, a::Counter& rhs) {
+                uint32_t temp = lhs.count;
+                lhs.count = rhs.count;
+                rhs.count = temp;
+            }
+        }
+        namespace c {
+            inline void swap(a::Counter& lhs, a::Counter& rhs) {
+                lhs.count = 0;
+                rhs.count = 0;
+            }
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut x = ffi::a::Counter::new(1).within_unique_ptr();
+        let mut y = ffi::a::Counter::new(2).within_unique_ptr();
+        x.pin_mut().swap(y.pin_mut());
+        assert_eq!(x.get(), 2);
+        assert_eq!(y.get(), 1);
+        ffi::c::swap(x.pin_mut(), y.pin_mut());
+        assert_eq!(x.get(), 0);
+    };
+    run_test("", hdr, rs, &["a::Counter", "b::swap", "c::swap"], &[]);
+}
+
+#[test]
+fn test_adl_operator_as_method() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace a {
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+            };
+        }
+        namespace b {
+            inline a::Point operator+(const a::Point& lhs, const a::Point& rhs) {
+                return a::Point { lhs.x + rhs.x, lhs.y + rhs.y };
+            }
+        }
+    "};
+    let rs = quote! {
+        let p = ffi::a::Point { x: 1, y: 2 };
+        let q = ffi::a::Point { x: 3, y: 4 };
+        let r = p.operator_plus(&q);
+        assert_eq!(r.x, 4);
+        assert_eq!(r.y, 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("a::Point")
+            generate_ns!("b")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_complete_type_in_unique_ptr() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace a {
+            struct Widget;
+            std::unique_ptr<Widget> make_widget(uint32_t value);
+            uint32_t get_value(const Widget& widget);
+        }
+        // Deliberately hidden from bindgen, as if this were in
+        // some header which autocxx can't parse.
+        #ifndef BINDGEN
+        namespace a {
+            struct Widget {
+                uint32_t value;
+                uint32_t spare;
+            };
+        }
+        #endif
+    "};
+    let cpp = indoc! {"
+        namespace a {
+            std::unique_ptr<Widget> make_widget(uint32_t value) {
+                return std::make_unique<Widget>(Widget { value, 0 });
+            }
+            uint32_t get_value(const Widget& widget) {
+                return widget.value;
+            }
+        }
+    "};
+    let rs = quote! {
+        let widget = ffi::a::make_widget(42);
+        assert_eq!(ffi::a::get_value(widget.as_ref().unwrap()), 42);
+    };
+    run_test_ex(
+        cpp,
+        hdr,
+        rs,
+        quote! {
+            generate!("a::make_widget")
+            generate!("a::get_value")
+            complete_type!("a::Widget", size = 8, align = 4)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_rust_equivalent() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace geo {
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+            };
+            inline Point transpose(Point p) {
+                return Point { p.y, p.x };
+            }
+        }
+    "};
+    let rs = quote! {
+        let p = ffi::geo::Point { x: 1, y: 2 };
+        let transposed: crate::Point = ffi::geo::transpose(p).into();
+        assert_eq!(transposed, crate::Point { x: 2, y: 1 });
+        let back: ffi::geo::Point = transposed.into();
+        assert!(back == crate::Point { x: 2, y: 1 });
+        assert!(crate::Point { x: 2, y: 1 } == back);
+        assert!(back != crate::Point { x: 1, y: 2 });
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("geo::transpose")
+            generate_pod!("geo::Point")
+            rust_equivalent!("geo::Point", crate::Point)
+        },
+        None,
+        None,
+        Some(quote! {
+            #[derive(Debug, PartialEq)]
+            pub struct Point {
+                x: u32,
+                y: u32,
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_rust_equivalent_conversion_fns() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace chrono {
+            struct Duration {
+                int64_t seconds;
+                int32_t nanos;
+            };
+            inline Duration doubled(Duration d) {
+                int64_t nanos = int64_t(d.nanos) * 2;
+                return Duration { d.seconds * 2 + nanos / 1000000000, int32_t(nanos % 1000000000) };
+            }
+        }
+    "};
+    let rs = quote! {
+        let doubled = ffi::chrono::doubled(std::time::Duration::new(1, 600_000_000).into());
+        assert!(doubled == std::time::Duration::new(3, 200_000_000));
+        assert!(std::time::Duration::new(3, 200_000_000) == doubled);
+        assert!(doubled != std::time::Duration::new(3, 0));
+        let doubled: std::time::Duration = doubled.into();
+        assert_eq!(doubled, std::time::Duration::new(3, 200_000_000));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("chrono::doubled")
+            generate_pod!("chrono::Duration")
+            rust_equivalent!(
+                "chrono::Duration",
+                std::time::Duration,
+                from_cpp = crate::duration_from_cpp,
+                to_cpp = crate::duration_to_cpp
+            )
+        },
+        None,
+        None,
+        Some(quote! {
+            fn duration_from_cpp(d: &ffi::chrono::Duration) -> std::time::Duration {
+                std::time::Duration::new(d.seconds as u64, d.nanos as u32)
+            }
+            fn duration_to_cpp(d: &std::time::Duration) -> ffi::chrono::Duration {
+                ffi::chrono::Duration {
+                    seconds: d.as_secs() as i64,
+                    nanos: d.subsec_nanos() as i32,
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_rust_equivalent_extern_cpp_type() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace chrono {
+            struct Duration {
+                int64_t seconds;
+                int32_t nanos;
+            };
+            inline int64_t whole_seconds(Duration d) {
+                return d.seconds;
+            }
+        }
+    "};
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        pub mod base {
+            autocxx::include_cpp! {
+                #hexathorpe include "input.h"
+                name!(ffi2)
+                safety!(unsafe_ffi)
+                generate_pod!("chrono::Duration")
+            }
+            pub use ffi2::*;
+        }
+        pub mod dependent {
+            autocxx::include_cpp! {
+                #hexathorpe include "input.h"
+                safety!(unsafe_ffi)
+                generate!("chrono::whole_seconds")
+                extern_cpp_type!("chrono::Duration", crate::base::chrono::Duration)
+                pod!("chrono::Duration")
+                rust_equivalent!(
+                    "chrono::Duration",
+                    std::time::Duration,
+                    from_cpp = crate::duration_from_cpp,
+                    to_cpp = crate::duration_to_cpp
+                )
+            }
+            pub use ffi::*;
+        }
+        fn duration_from_cpp(d: &base::chrono::Duration) -> std::time::Duration {
+            std::time::Duration::new(d.seconds as u64, d.nanos as u32)
+        }
+        fn duration_to_cpp(d: &std::time::Duration) -> base::chrono::Duration {
+            base::chrono::Duration {
+                seconds: d.as_secs() as i64,
+                nanos: d.subsec_nanos() as i32,
+            }
+        }
+        fn main() {
+            let d = std::time::Duration::new(7, 5);
+            assert_eq!(dependent::chrono::whole_seconds(d.into()), 7);
+            let back: std::time::Duration = base::chrono::Duration::from(d).into();
+            assert_eq!(back, d);
+        }
+    };
+    do_run_test_manual("", hdr, rs, None, None).unwrap();
+}
+
+#[test]
+fn test_rust_equivalent_extern_cpp_type_needs_conversions() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace geo {
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+            };
+            inline uint32_t get_x(Point p) {
+                return p.x;
+            }
+        }
+    "};
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        pub mod base {
+            autocxx::include_cpp! {
+                #hexathorpe include "input.h"
+                name!(ffi2)
+                safety!(unsafe_ffi)
+                generate_pod!("geo::Point")
+            }
+            pub use ffi2::*;
+        }
+        pub mod dependent {
+            autocxx::include_cpp! {
+                #hexathorpe include "input.h"
+                safety!(unsafe_ffi)
+                generate!("geo::get_x")
+                extern_cpp_type!("geo::Point", crate::base::geo::Point)
+                pod!("geo::Point")
+                rust_equivalent!("geo::Point", crate::Point)
+            }
+            pub use ffi::*;
+        }
+        pub struct Point {
+            x: u32,
+            y: u32,
+        }
+        fn main() {}
+    };
+    assert!(matches!(
+        do_run_test_manual("", hdr, rs, None, None),
+        Err(TestError::AutoCxx(_))
+    ));
+}
+
+#[test]
+fn test_rust_equivalent_not_pod() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace geo {
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+            };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("geo::Point")
+            rust_equivalent!("geo::Point", crate::Point)
+        },
+        None,
+        None,
+        Some(quote! {
+            pub struct Point {
+                x: u32,
+                y: u32,
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_container_try_get() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <deque>
+        #include <map>
+        #include <memory>
+        inline std::unique_ptr<std::deque<uint32_t>> make_deque() {
+            auto d = std::make_unique<std::deque<uint32_t>>();
+            d->push_back(3);
+            d->push_back(4);
+            return d;
+        }
+        inline std::unique_ptr<std::map<uint32_t, uint32_t>> make_map() {
+            auto m = std::make_unique<std::map<uint32_t, uint32_t>>();
+            (*m)[1] = 10;
+            return m;
+        }
+    "};
+    let rs = quote! {
+        let d = ffi::make_deque();
+        assert_eq!(unsafe { *d.try_get(1).unwrap().as_ref() }, 4);
+        assert!(d.try_get(2).is_none());
+        let m = ffi::make_map();
+        assert_eq!(unsafe { *m.try_get(&1).unwrap().as_ref() }, 10);
+        assert!(m.try_get(&2).is_none());
+    };
+    run_test("", hdr, rs, &["make_deque", "make_map"], &[]);
+}
+
+#[test]
+fn test_singleton() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        class Registry {
+        public:
+            static Registry& instance() {
+                static Registry registry;
+                return registry;
+            }
+            static const Registry& const_instance() {
+                return instance();
+            }
+            Registry(const Registry&) = delete;
+            Registry(Registry&&) = delete;
+            Registry& operator=(const Registry&) = delete;
+            Registry& operator=(Registry&&) = delete;
+            void add(uint32_t n) { total += n; }
+            uint32_t get_total() const { return total; }
+        private:
+            Registry() : total(0) {}
+            ~Registry() {}
+            uint32_t total;
+        };
+    "};
+    let rs = quote! {
+        let registry: ::core::pin::Pin<&'static mut ffi::Registry> =
+            unsafe { ffi::Registry::instance() };
+        registry.add(3);
+        let registry: &'static ffi::Registry = ffi::Registry::const_instance();
+        assert_eq!(registry.get_total(), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Registry")
+            singleton!("Registry")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_view_type_borrows_from_receiver() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Span {
+            const uint32_t* data;
+            uint32_t len;
+        };
+        class Buffer {
+        public:
+            Buffer() : values{1, 2, 3} {}
+            Span span() const { return Span{values, 3}; }
+        private:
+            uint32_t values[3];
+        };
+        inline uint32_t sum(const Span& span) {
+            uint32_t total = 0;
+            for (uint32_t i = 0; i < span.len; i++) {
+                total += span.data[i];
+            }
+            return total;
+        }
+    "};
+    let rs = quote! {
+        let buffer = ffi::Buffer::new().within_box();
+        let span: autocxx::CppView<'_, ffi::Span> = buffer.span();
+        assert_eq!(span.len, 3);
+        assert_eq!(ffi::sum(&span), 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Buffer")
+            generate!("sum")
+            generate_pod!("Span")
+            view_type!("Span")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_mockable() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        class Logger {
+        public:
+            virtual ~Logger() {}
+            virtual void log(uint32_t n) = 0;
+            virtual uint32_t count() const = 0;
+        };
+        class CountingLogger : public Logger {
+        public:
+            void log(uint32_t n) override { total += n; }
+            uint32_t count() const override { return total; }
+        private:
+            uint32_t total = 0;
+        };
+        inline std::unique_ptr<Logger> make_logger() {
+            return std::make_unique<CountingLogger>();
+        }
+    "};
+    let rs = quote! {
+        use ffi::Logger_interface;
+        fn log_twice(logger: &mut impl Logger_interface) -> u32 {
+            logger.log(2);
+            logger.log(3);
+            logger.count()
+        }
+        struct FakeLogger(u32);
+        impl Logger_interface for FakeLogger {
+            fn log(&mut self, n: u32) {
+                self.0 += n * 10;
+            }
+            fn count(&self) -> u32 {
+                self.0
+            }
+        }
+        let mut logger = ffi::make_logger();
+        assert_eq!(log_twice(&mut logger.pin_mut()), 5);
+        assert_eq!(log_twice(&mut FakeLogger(0)), 50);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Logger")
+            generate!("make_logger")
+            mockable!("Logger")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pod_pointer_field_constness() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Buffer {
+            const uint32_t* input;
+            uint32_t* output;
+            uint32_t len;
+        };
+        inline void copy_buffer(Buffer buf) {
+            for (uint32_t i = 0; i < buf.len; i++) {
+                buf.output[i] = buf.input[i];
+            }
+        }
+    "};
+    let rs = quote! {
+        let input = [1u32, 2, 3];
+        let mut output = [0u32; 3];
+        let buf = ffi::Buffer {
+            input: input.as_ptr(),
+            output: output.as_mut_ptr(),
+            len: 3,
+        };
+        ffi::copy_buffer(buf);
+        assert_eq!(output, input);
+    };
+    run_test("", hdr, rs, &["copy_buffer"], &["Buffer"]);
+}
+
+#[test]
+fn test_newtype() {
+    let hdr = indoc! {"
+        using Fd = int;
+        inline Fd open_thing() { return 3; }
+        inline int fd_value(Fd fd) { return fd; }
+    "};
+    let rs = quote! {
+        let fd: ffi::Fd = ffi::open_thing();
+        assert_eq!(ffi::fd_value(fd), autocxx::c_int(3));
+        let fd = ffi::Fd::try_from(4i64).unwrap();
+        assert_eq!(ffi::fd_value(fd), autocxx::c_int(4));
+        assert!(ffi::Fd::try_from(i64::MAX).is_err());
+        let raw: ::std::os::raw::c_int = fd.into();
+        assert_eq!(raw, 4);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("open_thing")
+            generate!("fd_value")
+            newtype!("Fd")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_opaque_handle() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        typedef uint64_t TextureHandle;
+        typedef uint64_t BufferHandle;
+        inline TextureHandle make_texture() { return 7; }
+        inline BufferHandle make_buffer() { return 8; }
+        inline uint64_t texture_id(TextureHandle t) { return t; }
+    "};
+    let rs = quote! {
+        let t: ffi::TextureHandle = ffi::make_texture();
+        let b: ffi::BufferHandle = ffi::make_buffer();
+        assert_eq!(ffi::texture_id(t), 7);
+        assert_eq!(b.to_raw(), 8);
+        let t2 = ffi::TextureHandle::from_raw(7);
+        assert_eq!(t, t2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("make_texture")
+            generate!("make_buffer")
+            generate!("texture_id")
+            opaque_handle!("TextureHandle")
+            opaque_handle!("BufferHandle")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_export_c() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+        class Foo {
+        public:
+            Foo() : a(3) {}
+            uint32_t bar(uint32_t b) const { return a + b; }
+            void set(uint32_t b) { a = b; }
+        private:
+            uint32_t a;
+        };
+        }
+    "};
+    let rs = quote! {
+        extern "C" {
+            fn foo_bar(foo: *const ffi::ns::Foo, b: u32) -> u32;
+            fn foo_set(foo: *mut ffi::ns::Foo, b: u32);
+        }
+        let mut foo = ffi::ns::Foo::new().within_unique_ptr();
+        assert_eq!(unsafe { foo_bar(foo.as_ref().unwrap(), 4) }, 7);
+        unsafe { foo_set(foo.pin_mut().get_unchecked_mut(), 5) };
+        assert_eq!(foo.bar(4), 9);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Foo")
+            export_c!("ns::Foo::bar", "foo_bar")
+            export_c!("ns::Foo::set", "foo_set")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_export_c_unexportable() {
+    // Overloads can't share a symbol, and C can't hold a non-POD type by
+    // value, so those are skipped without affecting anything else.
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        inline uint32_t twice(uint32_t a) { return a * 2; }
+        inline double twice(double a) { return a * 2.0; }
+        inline uint32_t thrice(uint32_t a) { return a * 3; }
+        struct Name {
+            std::string value;
+        };
+        inline uint32_t name_length(Name name) { return name.value.size(); }
+    "};
+    let rs = quote! {
+        extern "C" {
+            fn c_thrice(a: u32) -> u32;
+        }
+        assert_eq!(unsafe { c_thrice(2) }, 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("twice")
+            generate!("thrice")
+            generate!("name_length")
+            export_c!("twice", "c_twice")
+            export_c!("thrice", "c_thrice")
+            export_c!("name_length", "c_name_length")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_generate_ns_options() {
+    let hdr = indoc! {"
+        #include <string>
+        namespace a {
+            struct Point { int x; int y; };
+            struct Named { std::string name; };
+            struct Handle { int fd; ~Handle() {} };
+            inline int get_x(Point p) { return p.x; }
+            namespace detail {
+                inline int hidden() { return 1; }
+            }
+            namespace b {
+                inline int shallow() { return 2; }
+                namespace c {
+                    inline int deep() { return 3; }
+                }
+            }
+        }
+    "};
+    let rs = quote! {
+        let p = ffi::a::Point {
+            x: autocxx::c_int(4),
+            y: autocxx::c_int(5),
+        };
+        assert_eq!(ffi::a::get_x(p), autocxx::c_int(4));
+        assert_eq!(ffi::a::b::shallow(), autocxx::c_int(2));
+        let _named: cxx::UniquePtr<ffi::a::Named> = ffi::a::Named::new().within_unique_ptr();
+        let _handle: cxx::UniquePtr<ffi::a::Handle> = ffi::a::Handle::new().within_unique_ptr();
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_ns!("a", max_depth = 1, skip_detail_namespaces, pod = auto)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_strings_by_value() {
+    let hdr = indoc! {"
+        #include <string>
+        #include <cstdint>
+        inline std::string get_short() { return \"hi\"; }
+        inline std::string get_long(uint32_t n) { return std::string(n, 'x'); }
+        inline uint32_t string_length(const std::string& s) { return s.size(); }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        moveit! { let s = ffi::get_short(); }
+        assert_eq!(s.to_str().unwrap(), "hi");
+        let mut l = Box::emplace(ffi::get_long(100));
+        assert_eq!(l.len(), 100);
+        l.as_mut().pin_mut().push_str("y");
+        assert_eq!(l.len(), 101);
+        moveit! { let m = ffi::make_string_value("made in Rust"); }
+        assert_eq!(m.to_str().unwrap(), "made in Rust");
+        assert_eq!(ffi::string_length(&m), 12);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("get_short")
+            generate!("get_long")
+            generate!("string_length")
+            strings_by_value!()
+        },
+        None,
+        Some(Box::new(CppMatcher::new(
+            &["static_assert(sizeof(std::string)", "~basic_string()"],
+            &[],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_rtti_type_names() {
+    let hdr = indoc! {"
+        #include <memory>
+        namespace ns {
+            class Base {
+            public:
+                virtual ~Base() {}
+                virtual int get() const { return 1; }
+            };
+            class Derived : public Base {
+            public:
+                int get() const override { return 2; }
+            };
+            struct Plain {
+                int a;
+            };
+            inline std::unique_ptr<Base> make_derived() {
+                return std::make_unique<Derived>();
+            }
+        }
+    "};
+    let rs = quote! {
+        let b = ffi::ns::make_derived();
+        let name = b.type_name();
+        assert!(name.to_str().unwrap().ends_with("ns::Derived"));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Base")
+            generate!("ns::Derived")
+            generate!("ns::Plain")
+            generate!("ns::make_derived")
+            rtti_type_names!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_getters() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            class Widget {
+            public:
+                Widget() : width(3) {}
+                uint32_t GetWidth() const { return width; }
+                uint32_t GetArea(uint32_t height) const { return width * height; }
+                void SetWidth(uint32_t w) { width = w; }
+            private:
+                uint32_t width;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut w = ffi::ns::Widget::new().within_unique_ptr();
+        assert_eq!(w.width(), 3);
+        w.pin_mut().SetWidth(4);
+        assert_eq!(w.width(), 4);
+        assert_eq!(w.GetArea(2), 8);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Widget")
+            getters!("ns::Widget", strip_prefix = "Get")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_allocator_hooks() {
+    let hdr = indoc! {"
+        #include <memory>
+        #include <string>
+        #include <utility>
+        inline int& allocation_count() {
+            static int count = 0;
+            return count;
+        }
+        template <typename T, typename... Args>
+        std::unique_ptr<T> counting_make_unique(Args&&... args) {
+            allocation_count()++;
+            return std::make_unique<T>(std::forward<Args>(args)...);
+        }
+        #define AUTOCXX_MAKE_UNIQUE counting_make_unique
+        inline std::string get_name() { return \"Bob\"; }
+        inline int get_allocation_count() { return allocation_count(); }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::get_allocation_count(), 0);
+        let name = ffi::get_name();
+        assert_eq!(name.to_str().unwrap(), "Bob");
+        assert_eq!(ffi::get_allocation_count(), 1);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["get_name", "get_allocation_count"], &[], None),
+        make_clang_arg_adder(&["-DAUTOCXX_ALLOCATOR_HEADER=\"input.h\""]),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_error_type() {
+    let hdr = indoc! {"
+        #include <stdexcept>
+        #include <string>
+        namespace ns {
+            class ParseError : public std::runtime_error {
+            public:
+                ParseError() : std::runtime_error(\"unexpected token\") {}
+            };
+            class Status {
+            public:
+                Status() : code(5) {}
+                std::string message() const { return \"not found\"; }
+            private:
+                int code;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        fn describe(e: &dyn std::error::Error) -> String {
+            e.to_string()
+        }
+        let e = ffi::ns::ParseError::new().within_unique_ptr();
+        assert_eq!(describe(e.as_ref().unwrap()), "unexpected token");
+        assert_eq!(e.error_message().to_str().unwrap(), "unexpected token");
+        let s = ffi::ns::Status::new().within_unique_ptr();
+        assert_eq!(format!("{}", s.as_ref().unwrap()), "not found");
+        assert_eq!(
+            format!("{:?}", s.as_ref().unwrap()),
+            "ns::Status(\"not found\")"
+        );
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::ParseError")
+            generate!("ns::Status")
+            error_type!("ns::ParseError")
+            error_type!("ns::Status", message = "message")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_error_type_unsafe_functions() {
+    // Without safety!(unsafe_ffi), every C++ function is unsafe, but
+    // the error impls are still generated.
+    let hdr = indoc! {"
+        #include <stdexcept>
+        namespace ns {
+            class ParseError : public std::runtime_error {
+            public:
+                ParseError() : std::runtime_error(\"unexpected token\") {}
+            };
+        }
+    "};
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        use autocxx::prelude::*;
+        include_cpp! {
+            #hexathorpe include "input.h"
+            generate!("ns::ParseError")
+            error_type!("ns::ParseError")
+        }
+        fn main() {
+            let e = unsafe { ffi::ns::ParseError::new() }.within_unique_ptr();
+            let e: &dyn std::error::Error = e.as_ref().unwrap();
+            assert_eq!(e.to_string(), "unexpected token");
+        }
+    };
+    do_run_test_manual("", hdr, rs, None, None).unwrap();
+}
+
+#[test]
+fn test_no_drop() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            inline uint32_t& destructions() {
+                static uint32_t count = 0;
+                return count;
+            }
+            class Registry {
+            public:
+                Registry() : id(7) {}
+                ~Registry() { destructions()++; }
+                uint32_t get_id() const { return id; }
+            private:
+                uint32_t id;
+            };
+            inline uint32_t get_destructions() { return destructions(); }
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let r = ffi::ns::Registry::new().within_box();
+        assert_eq!(r.get_id(), 7);
+        drop(r);
+        moveit! {
+            let r = ffi::ns::Registry::new();
+        }
+        assert_eq!(r.get_id(), 7);
+        drop(r);
+        assert_eq!(ffi::ns::get_destructions(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Registry")
+            generate!("ns::get_destructions")
+            no_drop!("ns::Registry")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_assert_not_generated() {
+    let hdr = indoc! {"
+        struct Dangerous {};
+        struct Safe {
+            void harmless() const {}
+        };
+    "};
+    let rs = quote! {
+        ffi::Safe::new().within_unique_ptr().harmless();
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Safe")
+            assert_not_generated!("Dangerous")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_assert_not_generated_dependency() {
+    let hdr = indoc! {"
+        struct Dangerous {};
+        inline void use_dangerous(const Dangerous&) {}
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("use_dangerous")
+            assert_not_generated!("Dangerous")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_assert_not_generated_method() {
+    let hdr = indoc! {"
+        namespace ns {
+        struct Safe {
+            void harmless() const {}
+            void dangerous() {}
+        };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("ns::Safe")
+            assert_not_generated!("ns::Safe::dangerous")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_bindgen_blocklist() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace detail {
+        struct Exotic {
+            uint32_t a;
+        };
+        }
+        struct Holder {
+            uint32_t get() const { return 5; }
+        };
+        inline void take_exotic(const detail::Exotic&) {}
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::Holder::new().within_unique_ptr().get(), 5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Holder")
+            generate!("take_exotic")
+            bindgen_blocklist!("detail::.*")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_clang_arg() {
+    // BINDGEN is defined whenever bindgen parses headers, but not when
+    // the C++ is compiled.
+    let hdr = indoc! {"
+        #include <cstdint>
+        #if defined(BINDGEN) && !defined(FROM_CLANG_ARG)
+        #error FROM_CLANG_ARG should have been passed to bindgen
+        #endif
+        inline uint32_t get_value() { return 2; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::get_value(), 2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("get_value")
+            clang_arg!("-DFROM_CLANG_ARG")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_subclass_non_virtual_destructor() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        ~Observer() {}
+    };
+    inline void bar() {}
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("bar")
+            subclass!("Observer",MyObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use ffi::Observer_methods;
+            #[autocxx::subclass::subclass]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    3
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_subclass_non_virtual_destructor_leak() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        ~Observer() {}
+    };
+    inline uint32_t call_observer(const Observer* obs) { return obs->foo(); }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyObserver::new_cpp_owned(MyObserver::default());
+            let obs = MyObserver::leak_as_Observer_ptr(obs);
+            assert_eq!(unsafe { ffi::call_observer(obs) }, 3);
+        },
+        quote! {
+            generate!("call_observer")
+            subclass!("Observer",MyObserver, leak)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::Observer_methods;
+            #[autocxx::subclass::subclass]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    3
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_subclass_non_virtual_destructor_downcast() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        ~Observer() {}
+    };
+    inline std::unique_ptr<Observer> call_observer(std::unique_ptr<Observer> obs) {
+        obs->foo();
+        return obs;
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyObserver::new_cpp_owned(MyObserver::default());
+            let obs = unsafe { MyObserver::as_Observer_unique_ptr(obs) };
+            let obs = ffi::call_observer(obs);
+            assert!(!DROPPED.load(Ordering::SeqCst));
+            unsafe { MyObserver::delete_Observer_unique_ptr(obs) };
+            assert!(DROPPED.load(Ordering::SeqCst));
+        },
+        quote! {
+            generate!("call_observer")
+            subclass!("Observer",MyObserver, downcast)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::Observer_methods;
+            use std::sync::atomic::{AtomicBool, Ordering};
+            static DROPPED: AtomicBool = AtomicBool::new(false);
+            #[autocxx::subclass::subclass]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    3
+                }
+            }
+            impl Drop for MyObserver {
+                fn drop(&mut self) {
+                    DROPPED.store(true, Ordering::SeqCst);
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_unique_ptr_upcast() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+
+    class Named {
+    public:
+        virtual ~Named() {}
+        virtual uint32_t name() const = 0;
+    };
+    class Counted {
+    public:
+        uint32_t count = 7;
+    };
+    class Thing : public Counted, public Named {
+    public:
+        uint32_t name() const override { return 3; }
+    };
+    inline std::unique_ptr<Thing> make_thing() { return std::make_unique<Thing>(); }
+    inline uint32_t get_name(std::unique_ptr<Named> named) { return named->name(); }
+    "};
+    let rs = quote! {
+        let thing = ffi::make_thing();
+        let named = ffi::Thing::as_Named_unique_ptr(thing);
+        assert_eq!(ffi::get_name(named), 3);
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["make_thing", "get_name", "Thing", "Counted"],
+        &[],
+    );
+}
+
+#[test]
+fn test_name_table() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace a {
+        struct Foo {
+            uint32_t a;
+        };
+        enum class Color { Red, Green };
+    }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::meta::cpp_name_of::<ffi::a::Foo>(), "a::Foo");
+        assert_eq!(ffi::meta::cpp_name_of::<ffi::a::Color>(), "a::Color");
+        assert_eq!(ffi::meta::rust_path_of("a::Foo"), Some("ffi::a::Foo"));
+        assert_eq!(ffi::meta::cpp_name_of_path("ffi::a::Color"), Some("a::Color"));
+        assert_eq!(ffi::meta::rust_path_of("a::Bar"), None);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("a::Foo")
+            generate!("a::Color")
+            name_table!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_typedef_chain_across_namespaces() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace a {
+        struct Point {
+            uint32_t x;
+            uint32_t y;
+        };
+        typedef Point point_t;
+    }
+    namespace b {
+        typedef a::point_t location_t;
+    }
+    namespace c {
+        typedef b::location_t place_t;
+        struct Marker {
+            place_t place;
+        };
+        inline uint32_t get_x(place_t place) { return place.x; }
+    }
+    "};
+    let rs = quote! {
+        let marker = ffi::c::Marker { place: ffi::a::Point { x: 3, y: 4 } };
+        assert_eq!(ffi::c::get_x(marker.place), 3);
+        let _: ffi::c::place_t = ffi::b::location_t { x: 1, y: 2 };
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["c::get_x", "b::location_t", "c::place_t"],
+        &["c::Marker", "a::Point"],
+    );
+}
+
+#[test]
+fn test_cpp_std() {
+    // The tests otherwise parse and build with C++14. Only the parsing is
+    // affected by cpp_std!, so the header must also build as C++14.
+    let hdr = indoc! {"
+        #include <cstdint>
+        #if defined(BINDGEN) && __cplusplus < 201703L
+        #error cpp_std! should have given bindgen C++17
+        #endif
+        inline uint32_t get_value() { return 2; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::get_value(), 2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("get_value")
+            cpp_std!("c++17")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_constructor_from() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+    namespace ns {
+        class Widget {
+        public:
+            static std::unique_ptr<Widget> Create(uint32_t size) {
+                if (size == 0) {
+                    return nullptr;
+                }
+                return std::unique_ptr<Widget>(new Widget(size));
+            }
+            uint32_t get_size() const { return size; }
+        private:
+            Widget(uint32_t size) : size(size) {}
+            uint32_t size;
+        };
+        class Gadget {
+        public:
+            Gadget(uint32_t value) : value(value) {}
+            static Gadget* Make(bool succeed) { return succeed ? new Gadget(7) : nullptr; }
+            uint32_t get_value() const { return value; }
+        private:
+            uint32_t value;
+        };
+    }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let widget = ffi::ns::Widget::new(3).unwrap();
+        assert_eq!(widget.get_size(), 3);
+        assert!(ffi::ns::Widget::new(0).is_none());
+        // Adopting a raw pointer relies on the factory having allocated it.
+        let gadget = unsafe { ffi::ns::Gadget::new(true) }.unwrap();
+        assert_eq!(gadget.get_value(), 7);
+        assert!(unsafe { ffi::ns::Gadget::new(false) }.is_none());
+        // The factory keeps the name new, even though Gadget has a
+        // constructor too.
+        let gadget = ffi::ns::Gadget::new1(5).within_unique_ptr();
+        assert_eq!(gadget.get_value(), 5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Widget")
+            generate!("ns::Gadget")
+            constructor_from!("ns::Widget::Create")
+            constructor_from!("ns::Gadget::Make")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_constructor_from_not_a_factory() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace ns {
+        class Widget {
+        public:
+            static uint32_t Count() { return 0; }
+        };
+    }
+    "};
+    let rs = quote! {
+        ffi::ns::Widget::new();
+    };
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Widget")
+            constructor_from!("ns::Widget::Count")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_context_callback() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            typedef void (*listener)(void* context, uint32_t value, bool last);
+            struct Registration {
+                listener cb = nullptr;
+                void* context = nullptr;
+            };
+            inline Registration& registration() {
+                static Registration r;
+                return r;
+            }
+            inline void add_listener(listener cb, void* context) {
+                registration().cb = cb;
+                registration().context = context;
+            }
+            inline void remove_listener(listener cb, void* context) {
+                if (registration().cb == cb && registration().context == context) {
+                    registration() = Registration();
+                }
+            }
+            inline bool notify(uint32_t value) {
+                if (!registration().cb) {
+                    return false;
+                }
+                registration().cb(registration().context, value, value == 0);
+                return true;
+            }
+            inline void on_exit(void (*)(void*), void*) {}
+        }
+    "};
+    let rs = quote! {
+        use std::sync::{Arc, Mutex};
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_by_callback = seen.clone();
+        let registration = ffi::ns::add_listener_closure(move |value, last| {
+            seen_by_callback.lock().unwrap().push((value, last));
+        });
+        assert!(ffi::ns::notify(3));
+        assert!(ffi::ns::notify(0));
+        drop(registration);
+        assert!(!ffi::ns::notify(1));
+        assert_eq!(*seen.lock().unwrap(), vec![(3, false), (0, true)]);
+        // Dropping the registration freed the closure.
+        assert_eq!(Arc::strong_count(&seen), 1);
+        ffi::ns::on_exit_closure(|| {});
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::notify")
+            safety!(unsafe_ffi)
+            context_callback!("ns::add_listener", fn(u32, bool), unregister = "ns::remove_listener")
+            context_callback!("ns::on_exit", fn())
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_returns_lifetime_of_arg() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            struct Item {
+                uint32_t value;
+            };
+            struct Needle {
+                uint32_t index;
+            };
+            class Haystack {
+            public:
+                Haystack() : items{{10}, {20}, {30}} {}
+                Item items[3];
+            };
+            inline const Item& find(const Haystack& haystack, const Needle& needle) {
+                return haystack.items[needle.index];
+            }
+            class Finder {
+            public:
+                const Item& first(const Haystack& haystack) const {
+                    return haystack.items[0];
+                }
+            };
+        }
+    "};
+    let rs = quote! {
+        let haystack = ffi::ns::Haystack::new().within_box();
+        let item = {
+            let needle = ffi::ns::Needle { index: 2 };
+            ffi::ns::find(&haystack, &needle)
+        };
+        assert_eq!(item.value, 30);
+        let item = {
+            let finder = ffi::ns::Finder::new().within_box();
+            finder.first(&haystack)
+        };
+        assert_eq!(item.value, 10);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Haystack")
+            generate!("ns::Finder")
+            generate!("ns::find")
+            generate_pod!("ns::Item")
+            generate_pod!("ns::Needle")
+            returns_lifetime_of_arg!("ns::find", arg = 0)
+            returns_lifetime_of_arg!("ns::Finder::first", arg = 0)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_strict_pod_names_field() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        namespace ns {
+            struct Inner {
+                uint32_t id;
+                std::string name;
+            };
+            struct Outer {
+                uint32_t count;
+                Inner inner;
+            };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate_pod!("ns::Outer")
+            strict_pod!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_allow_pod_downgrade() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        namespace ns {
+            struct Inner {
+                uint32_t id;
+                std::string name;
+            };
+            struct Outer {
+                uint32_t count;
+                Inner inner;
+            };
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+            };
+            inline uint32_t count_of(const Outer& outer) {
+                return outer.count;
+            }
+        }
+    "};
+    let rs = quote! {
+        let outer = ffi::ns::Outer::new().within_unique_ptr();
+        assert_eq!(ffi::ns::count_of(&outer), 0);
+        let point = ffi::ns::Point { x: 1, y: 2 };
+        assert_eq!(point.x + point.y, 3);
+        assert_eq!(ffi::AUTOCXX_POD_DOWNGRADES.len(), 1);
+        let (cpp_name, reason) = ffi::AUTOCXX_POD_DOWNGRADES[0];
+        assert_eq!(cpp_name, "ns::Outer");
+        assert!(reason.contains("ns::Outer::inner -> ns::Inner::name"));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("ns::Outer")
+            generate_pod!("ns::Point")
+            generate!("ns::count_of")
+            allow_pod_downgrade!("ns::Outer")
+            strict_pod!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_array_references() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Vec2 {
+            float x;
+            float y;
+        };
+        inline float sum(const float (&m)[4]) {
+            return m[0] + m[1] + m[2] + m[3];
+        }
+        inline void fill(uint32_t (&m)[3], uint32_t value) {
+            for (auto& v : m) {
+                v = value;
+            }
+        }
+        inline float first_x(const Vec2 (&points)[2]) {
+            return points[0].x;
+        }
+        struct Matrix {
+            float values[4];
+        };
+        inline const float (&values_of(const Matrix& m))[4] {
+            return m.values;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::sum(&[1.0, 2.0, 3.0, 4.0]), 10.0);
+        let mut values = [0u32; 3];
+        ffi::fill(&mut values, 7);
+        assert_eq!(values, [7, 7, 7]);
+        let points = [ffi::Vec2 { x: 3.0, y: 4.0 }, ffi::Vec2 { x: 5.0, y: 6.0 }];
+        assert_eq!(ffi::first_x(&points), 3.0);
+        let matrix = ffi::Matrix { values: [1.0, 0.0, 0.0, 1.0] };
+        assert_eq!(ffi::values_of(&matrix), &[1.0, 0.0, 0.0, 1.0]);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("sum")
+            generate!("fill")
+            generate!("first_x")
+            generate!("values_of")
+            generate_pod!("Vec2")
+            generate_pod!("Matrix")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_init_function() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace mylib {
+            inline uint32_t& init_count() {
+                static uint32_t count = 0;
+                return count;
+            }
+            inline bool Init() {
+                init_count()++;
+                return true;
+            }
+            inline uint32_t times_initialized() {
+                return init_count();
+            }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::mylib::times_initialized(), 0);
+        ffi::ensure_initialized();
+        ffi::ensure_initialized();
+        assert_eq!(ffi::mylib::times_initialized(), 1);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("mylib::times_initialized")
+            init_function!("mylib::Init")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_init_function_auto_in_debug() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace mylib {
+            inline uint32_t& init_count() {
+                static uint32_t count = 0;
+                return count;
+            }
+            inline void Init() {
+                init_count()++;
+            }
+            inline uint32_t times_initialized() {
+                return init_count();
+            }
+            struct Counter {
+                uint32_t unused;
+                uint32_t times_initialized() const { return init_count(); }
+            };
+        }
+    "};
+    let rs = quote! {
+        let expected = if cfg!(debug_assertions) { 1 } else { 0 };
+        // A method with no wrapper of its own must check too.
+        let counter = ffi::mylib::Counter { unused: 0 };
+        assert_eq!(counter.times_initialized(), expected);
+        assert_eq!(ffi::mylib::times_initialized(), expected);
+        assert_eq!(ffi::mylib::times_initialized(), expected);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("mylib::times_initialized")
+            generate_pod!("mylib::Counter")
+            init_function!("mylib::Init", auto_in_debug)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_anonymous_namespace_function_skipped() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace {
+            inline uint32_t helper(uint32_t a) {
+                return a + 1;
+            }
+        }
+    "};
+    let rs = quote! {};
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! { generate!("helper") },
+        None,
+        Some(make_error_finder("helper")),
+        None,
+    );
+}
+
+#[test]
+fn test_internal_linkage_shims() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace mylib {
+            namespace {
+                inline uint32_t helper(uint32_t a) {
+                    return a + 1;
+                }
+            }
+            inline uint32_t visible() {
+                return 2;
+            }
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::mylib::helper(ffi::mylib::visible()), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_ns!("mylib")
+            internal_linkage_shims!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_thread_affine() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ui {
+            class Widget {
+            public:
+                Widget() : clicks(0) {}
+                void click() { clicks++; }
+                uint32_t get_clicks() const { return clicks; }
+            private:
+                uint32_t clicks;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut w = ffi::ui::Widget::new().within_unique_ptr();
+        w.pin_mut().click();
+        assert_eq!(w.get_clicks(), 1);
+        // Each thread may use the objects it created.
+        let clicks = std::thread::spawn(|| {
+            let mut w = ffi::ui::Widget::new().within_unique_ptr();
+            w.pin_mut().click();
+            w.get_clicks()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(clicks, 1);
+        // But not those owned by another.
+        struct SendPtr(*const ffi::ui::Widget);
+        unsafe impl Send for SendPtr {}
+        let ptr = SendPtr(w.as_ref().unwrap() as *const ffi::ui::Widget);
+        let result = std::thread::spawn(move || {
+            let ptr = ptr;
+            unsafe { &*ptr.0 }.get_clicks()
+        })
+        .join();
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ui::Widget")
+            thread_affine!("ui::Widget")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pod_vector_bulk_transfer() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        #include <vector>
+        struct Sample {
+            uint32_t channel;
+            float value;
+        };
+        inline std::unique_ptr<std::vector<Sample>> make_samples() {
+            auto v = std::make_unique<std::vector<Sample>>();
+            v->push_back(Sample { 1, 0.5f });
+            v->push_back(Sample { 2, 1.5f });
+            return v;
+        }
+        inline uint32_t sum_channels(const std::vector<Sample>& v) {
+            uint32_t total = 0;
+            for (const auto& s : v) {
+                total += s.channel;
+            }
+            return total;
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut samples = ffi::make_samples();
+        let copied = samples.copy_to_vec();
+        assert_eq!(copied.len(), 2);
+        assert_eq!(copied[1].channel, 2);
+        assert_eq!(copied[1].value, 1.5);
+        samples.pin_mut().extend_from_slice(&copied);
+        assert_eq!(samples.as_slice().len(), 4);
+        assert_eq!(samples.as_slice()[3].channel, 2);
+        assert_eq!(ffi::sum_channels(&samples), 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("Sample")
+            generate!("make_samples")
+            generate!("sum_channels")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_deprecated_alias() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ui {
+            class Widget {
+            public:
+                Widget() : width(3) {}
+                uint32_t GetWidth() const { return width; }
+            private:
+                uint32_t width;
+            };
+            inline uint32_t area(const Widget& w) { return w.GetWidth() * 2; }
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        #[allow(deprecated)]
+        let w: cxx::UniquePtr<ffi::ui::OldWidget> = ffi::ui::Widget::new().within_unique_ptr();
+        assert_eq!(w.width(), 3);
+        #[allow(deprecated)]
+        let old_width = w.GetWidth();
+        assert_eq!(old_width, 3);
+        #[allow(deprecated)]
+        let old_area = ffi::ui::widget_area(&w);
+        assert_eq!(old_area, ffi::ui::area(&w));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_ns!("ui")
+            getters!("ui::Widget")
+            deprecated_alias!("ui::Widget::width", "GetWidth")
+            deprecated_alias!("ui::area", "widget_area")
+            deprecated_alias!("ui::Widget", "OldWidget")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_deprecated_alias_missing_item() {
+    // Aliases name the item's Rust name, which getters! has changed.
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ui {
+            class Widget {
+            public:
+                uint32_t GetWidth() const { return 3; }
+            };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("ui::Widget")
+            getters!("ui::Widget")
+            deprecated_alias!("ui::Widget::GetWidth", "get_width")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_extern_cpp_type_generic() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory>
+        namespace ns {
+            template <typename T>
+            struct Holder {
+                T value;
+            };
+        }
+        inline std::unique_ptr<ns::Holder<uint32_t>> make_int_holder() {
+            return std::make_unique<ns::Holder<uint32_t>>(ns::Holder<uint32_t> { 3 });
+        }
+        inline std::unique_ptr<ns::Holder<float>> make_float_holder() {
+            return std::make_unique<ns::Holder<float>>(ns::Holder<float> { 1.5f });
+        }
+        inline uint32_t get_int(const ns::Holder<uint32_t>& h) { return h.value; }
+        inline float get_float(const ns::Holder<float>& h) { return h.value; }
+    "};
+    let rs = quote! {
+        let i: cxx::UniquePtr<crate::Holder<u32>> = ffi::make_int_holder();
+        let f: cxx::UniquePtr<crate::Holder<f32>> = ffi::make_float_holder();
+        assert_eq!(ffi::get_int(&i), 3);
+        assert_eq!(ffi::get_float(&f), 1.5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("make_int_holder")
+            generate!("make_float_holder")
+            generate!("get_int")
+            generate!("get_float")
+            extern_cpp_type!(
+                "ns::Holder<T>",
+                crate::Holder<T>,
+                instantiations = ["uint32_t" => u32, "float" => f32]
+            )
+        },
+        None,
+        None,
+        Some(quote! {
+            pub struct Holder<T> {
+                _pinned: ::core::marker::PhantomPinned,
+                _value: ::core::marker::PhantomData<T>,
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_name_constructor() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        namespace ns {
+            class Rect {
+            public:
+                Rect() : area(0) {}
+                Rect(uint32_t side) : area(side * side) {}
+                Rect(uint32_t w, uint32_t h) : area(w * h) {}
+                Rect(const std::string& s) : area(s.size()) {}
+                uint32_t get_area() const { return area; }
+            private:
+                uint32_t area;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let r = ffi::ns::Rect::from_dimensions(3, 4).within_unique_ptr();
+        assert_eq!(r.get_area(), 12);
+        let_cxx_string!(s = "hello");
+        let r = ffi::ns::Rect::from_string(&s).within_unique_ptr();
+        assert_eq!(r.get_area(), 5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Rect")
+            name_constructor!("ns::Rect(uint32_t, uint32_t)", "from_dimensions")
+            name_constructor!("ns::Rect(const std::string &)", "from_string")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_name_constructor_missing_signature() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            class Rect {
+            public:
+                Rect(uint32_t w, uint32_t h) : area(w * h) {}
+            private:
+                uint32_t area;
+            };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("ns::Rect")
+            name_constructor!("ns::Rect(int, int)", "from_dimensions")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_std_byte() {
+    let hdr = indoc! {"
+        #include <cstddef>
+        #include <cstdint>
+        #include <vector>
+        inline std::byte invert(std::byte b) { return ~b; }
+        inline uint32_t sum(const std::byte* data, size_t len) {
+            uint32_t total = 0;
+            for (size_t i = 0; i < len; ++i) {
+                total += std::to_integer<uint32_t>(data[i]);
+            }
+            return total;
+        }
+        inline size_t count(const std::vector<std::byte>& data) { return data.size(); }
+        inline std::byte last(const std::vector<std::byte>& data) { return data.back(); }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::invert(0x0f), 0xf0);
+        let data = [1u8, 2, 3];
+        assert_eq!(unsafe { ffi::sum(data.as_ptr(), data.len()) }, 6);
+        let mut v = cxx::CxxVector::<u8>::new();
+        v.pin_mut().push(1);
+        v.pin_mut().push(2);
+        assert_eq!(ffi::count(v.as_ref().unwrap()), 2);
+        assert_eq!(ffi::last(v.as_ref().unwrap()), 2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["invert", "sum", "count", "last"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_std_byte_vector_mutable_reference() {
+    // We'd have to copy the vector in and out again, so we don't.
+    let hdr = indoc! {"
+        #include <cstddef>
+        #include <vector>
+        inline void clear(std::vector<std::byte>& data) { data.clear(); }
+    "};
+    let rs = quote! {};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["clear"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+#[cfg(target_env = "msvc")]
+fn test_msvc_extensions() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct __declspec(uuid(\"12345678-9abc-def0-1234-56789abcdef0\")) Widget {
+            Widget() : width(0) {}
+            uint32_t GetWidth() const { return width; }
+            void PutWidth(uint32_t w) { width = w; }
+            __declspec(property(get = GetWidth, put = PutWidth)) uint32_t Width;
+        private:
+            uint32_t width;
+        };
+    "};
+    let rs = quote! {
+        let mut w = ffi::Widget::new().within_box();
+        w.as_mut().put_Width(3);
+        assert_eq!(w.get_Width(), 3);
+        assert_eq!(ffi::Widget::IID, autocxx::Guid {
+            data1: 0x12345678,
+            data2: 0x9abc,
+            data3: 0xdef0,
+            data4: [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0],
+        });
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Widget")
+            msvc_extensions!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_conversion_errors_strict() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t answer() { return 42; }
+        inline void log_all(int, ...) {}
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("answer")
+            generate!("log_all")
+            conversion_errors!(strict)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_conversion_errors_lenient() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t answer() { return 42; }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::answer(), 42);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("answer")
+            generate!("question")
+            conversion_errors!(lenient)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_covariant_return_missing_method() {
+    let hdr = indoc! {"
+    class Shape {
+    public:
+        virtual Shape* duplicate() const { return nullptr; }
+        virtual ~Shape() {}
+    };
+    class Circle : public Shape {};
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("Shape")
+            covariant_return!("Shape::copy", "Circle")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pv_subclass_covariant_return() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Shape {
+    public:
+        Shape() {}
+        virtual Shape* duplicate() const { return nullptr; }
+        virtual ~Shape() {}
+    };
+    class Circle : public Shape {
+    public:
+        uint32_t radius = 2;
+    };
+    inline Circle* unit_circle() { static Circle c; return &c; }
+    inline uint32_t radius_of_duplicate(const Shape& s) {
+        return static_cast<Circle*>(s.duplicate())->radius;
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let s = MyShape::new_rust_owned(MyShape { cpp_peer: Default::default() });
+            assert_eq!(ffi::radius_of_duplicate(s.borrow().as_ref()), 2);
+        },
+        quote! {
+            generate!("unit_circle")
+            generate!("radius_of_duplicate")
+            subclass!("Shape",MyShape)
+            covariant_return!("Shape::duplicate", "Circle")
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::Shape_methods;
+            #[autocxx::subclass::subclass]
+            pub struct MyShape {}
+            impl Shape_methods for MyShape {
+                fn duplicate(&self) -> *mut ffi::Circle {
+                    ffi::unit_circle()
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pod_default() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace geo {
+            struct Point {
+                uint32_t x;
+                uint32_t y;
+                uint32_t z = 7;
+            };
+        }
+        inline uint32_t sum(geo::Point p) { return p.x + p.y + p.z; }
+    "};
+    let rs = quote! {
+        let p = ffi::geo::Point { x: 1, ..Default::default() };
+        assert_eq!(p.y, 0);
+        assert_eq!(p.z, 7);
+        assert_eq!(ffi::sum(p), 8);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("geo::Point")
+            generate!("sum")
+            pod_default!("geo::Point")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pod_default_not_pod() {
+    let hdr = indoc! {"
+        #include <string>
+        struct Named {
+            std::string name;
+        };
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("Named")
+            pod_default!("Named")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_intrusive_ptr() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace gfx {
+            inline uint32_t& texture_count() {
+                static uint32_t count = 0;
+                return count;
+            }
+            inline uint32_t live_textures() { return texture_count(); }
+            class Texture {
+            public:
+                Texture() { texture_count()++; }
+                ~Texture() { texture_count()--; }
+                void AddRef() { refs++; }
+                void Release() {
+                    if (--refs == 0) {
+                        delete this;
+                    }
+                }
+                uint32_t get_refs() const { return refs; }
+            private:
+                uint32_t refs = 1;
+            };
+            inline Texture* make_texture() { return new Texture(); }
+        }
+    "};
+    let rs = quote! {
+        let texture = unsafe { autocxx::IntrusivePtr::from_raw(ffi::gfx::make_texture()) }.unwrap();
+        assert_eq!(ffi::gfx::live_textures(), 1);
+        assert_eq!(texture.get_refs(), 1);
+        let texture2 = texture.clone();
+        assert_eq!(texture.get_refs(), 2);
+        let texture3 = unsafe { autocxx::IntrusivePtr::from_raw_add_ref(texture.as_ptr()) }.unwrap();
+        assert_eq!(texture.get_refs(), 3);
+        drop(texture);
+        drop(texture3);
+        assert_eq!(texture2.get_refs(), 1);
+        assert_eq!(ffi::gfx::live_textures(), 1);
+        drop(texture2);
+        assert_eq!(ffi::gfx::live_textures(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("gfx::Texture")
+            generate!("gfx::make_texture")
+            generate!("gfx::live_textures")
+            intrusive_ptr!("gfx::Texture", add_ref = "AddRef", release = "Release")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_small_pods_in_registers() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace geo {
+            struct Point {
+                int32_t x;
+                int32_t y;
+            };
+            struct Handle {
+                void* ptr;
+            };
+            inline Point add(Point a, Point b) { return Point{a.x + b.x, a.y + b.y}; }
+            inline uint32_t dist(Point p, uint32_t scale) { return static_cast<uint32_t>(p.x + p.y) * scale; }
+            inline bool is_null(Handle h) { return h.ptr == nullptr; }
+        }
+    "};
+    let rs = quote! {
+        let p = ffi::geo::add(
+            ffi::geo::Point { x: 1, y: 2 },
+            ffi::geo::Point { x: 3, y: 4 },
+        );
+        assert_eq!(p.x, 4);
+        assert_eq!(p.y, 6);
+        assert_eq!(ffi::geo::dist(p, 2), 20);
+        assert!(ffi::geo::is_null(ffi::geo::Handle { ptr: std::ptr::null_mut() }));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("geo::Point")
+            generate_pod!("geo::Handle")
+            generate!("geo::add")
+            generate!("geo::dist")
+            generate!("geo::is_null")
+            small_pods_in_registers!()
+        },
+        None,
+        Some(make_string_finder(
+            [
+                "add_autocxx_c_abi",
+                "dist_autocxx_c_abi",
+                "is_null_autocxx_c_abi",
+            ]
+            .map(|s| s.to_string())
+            .to_vec(),
+        )),
+        None,
+    );
+}
+
+#[test]
+fn test_const_global_accessor() {
+    let cpp = indoc! {"
+        namespace cfg {
+            const Config kDefaultConfig = make_default_config();
+        }
+    "};
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace cfg {
+            struct Config {
+                uint32_t retries;
+                uint32_t timeout_ms;
+            };
+            inline Config make_default_config() {
+                return Config { 3, 500 };
+            }
+            extern const Config kDefaultConfig;
+        }
+    "};
+    let rs = quote! {
+        let config: &'static ffi::cfg::Config = ffi::cfg::k_default_config();
+        assert_eq!(config.retries, 3);
+        assert_eq!(config.timeout_ms, 500);
+    };
+    run_test_ex(
+        cpp,
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("cfg::Config")
+            generate!("cfg::kDefaultConfig")
+        },
+        None,
+        Some(make_string_finder(vec!["k_default_config".into()])),
+        None,
+    );
+}
+
+#[test]
+fn test_const_static_member_accessor() {
+    let cpp = indoc! {"
+        namespace cfg {
+            const Config Defaults::kConfig = { 4, 250 };
+        }
+    "};
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace cfg {
+            struct Config {
+                uint32_t retries;
+                uint32_t timeout_ms;
+            };
+            class Defaults {
+            public:
+                static const Config kConfig;
+            };
+        }
+    "};
+    let rs = quote! {
+        let config: &'static ffi::cfg::Config = ffi::cfg::defaults_k_config();
+        assert_eq!(config.retries, 4);
+        assert_eq!(config.timeout_ms, 250);
+    };
+    run_test_ex(
+        cpp,
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("cfg::Config")
+            generate!("cfg::Defaults")
+        },
+        None,
+        Some(Box::new(CppMatcher::new(
+            &["return cfg::Defaults::kConfig;"],
+            &["Defaults_kConfig"],
+        ))),
+        None,
+    );
+}
+
+#[test]
+fn test_header_implementation() {
+    let hdr = indoc! {"
+        #pragma once
+        #include <cstdint>
+        uint32_t triple(uint32_t a);
+        #ifdef TRIPLE_IMPLEMENTATION
+        uint32_t triple(uint32_t a) { return a * TRIPLE_FACTOR; }
+        #endif
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::triple(2), 6);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("triple")
+            header_implementation!("input.h", define = "TRIPLE_IMPLEMENTATION", define = "TRIPLE_FACTOR 3")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_batch_methods() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace gfx {
+            class Canvas {
+            public:
+                Canvas() : sum(0), scale(1.0f), visible(false) {}
+                void SetPixel(uint32_t x, uint32_t y, uint8_t value) { sum += x * y * value; }
+                void SetScale(float s) { scale = s; }
+                void Show(bool v) { visible = v; }
+                uint32_t GetSum() const { return sum; }
+                float GetScale() const { return scale; }
+                bool IsVisible() const { return visible; }
+            private:
+                uint32_t sum;
+                float scale;
+                bool visible;
+            };
+        }
+    "};
+    let rs = quote! {
+        let mut canvas = ffi::gfx::Canvas::new().within_box();
+        let mut batch = ffi::gfx::Canvas_batch::new();
+        batch.SetPixel(2, 3, 4).SetScale(2.5).SetPixel(1, 1, 1).Show(true);
+        assert_eq!(batch.len(), 4);
+        canvas.as_mut().run_batch(&batch);
+        assert_eq!(canvas.GetSum(), 25);
+        assert_eq!(canvas.GetScale(), 2.5);
+        assert!(canvas.IsVisible());
+        batch.clear();
+        assert!(batch.is_empty());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("gfx::Canvas")
+            batch_methods!("gfx::Canvas", "SetPixel", "SetScale", "Show")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_thread_handle() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ui {
+            class Widget {
+            public:
+                Widget() : clicks(0) {}
+                void click(uint32_t times) { clicks += times; }
+                uint32_t get_clicks() const { return clicks; }
+            private:
+                uint32_t clicks;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        use std::future::Future;
+        use std::sync::{mpsc, Arc, Mutex};
+        use std::task::{Context, Poll, Wake};
+        type Task = Box<dyn FnOnce() + Send>;
+        struct QueueDispatcher(Mutex<mpsc::Sender<Task>>);
+        unsafe impl autocxx::Dispatcher for QueueDispatcher {
+            fn dispatch(&self, task: Task) {
+                let _ = self.0.lock().unwrap().send(task);
+            }
+        }
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+        let (sender, queue) = mpsc::channel::<Task>();
+        let dispatcher = Arc::new(QueueDispatcher(Mutex::new(sender)));
+        // Safety: the queue is drained on this thread, which creates the widget.
+        let handle = unsafe {
+            ffi::ui::WidgetHandle::new(ffi::ui::Widget::new().within_unique_ptr(), dispatcher)
+        };
+        let other_handle = handle.clone();
+        let mut clicked = std::thread::spawn(move || other_handle.click(3)).join().unwrap();
+        let mut clicks = handle.get_clicks();
+        let mut doubled = handle.run(|widget| widget.get_clicks() * 2);
+        drop(handle);
+        let waker = Arc::new(NoopWaker).into();
+        let mut cx = Context::from_waker(&waker);
+        assert!(std::pin::Pin::new(&mut clicks).poll(&mut cx).is_pending());
+        // Run the calls on this thread, which owns the widget.
+        for task in queue.try_iter() {
+            task();
+        }
+        assert_eq!(std::pin::Pin::new(&mut clicked).poll(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(std::pin::Pin::new(&mut clicks).poll(&mut cx), Poll::Ready(Ok(3)));
+        assert_eq!(std::pin::Pin::new(&mut doubled).poll(&mut cx), Poll::Ready(Ok(6)));
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ui::Widget")
+            thread_handle!("ui::Widget")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_cpp_new_trait() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        class Rect {
+        public:
+            Rect() : w(1), h(1) {}
+            Rect(uint32_t w_, uint32_t h_) : w(w_), h(h_) {}
+            Rect(const std::string&) : w(0), h(0) {}
+            uint32_t area() const { return w * h; }
+        private:
+            uint32_t w;
+            uint32_t h;
+        };
+        class Square {
+        public:
+            Square(uint32_t side_) : side(side_) {}
+            Square(uint32_t width, uint32_t height) : side(width < height ? width : height) {}
+            uint32_t area() const { return side * side; }
+        private:
+            uint32_t side;
+        };
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        use std::pin::Pin;
+        fn make<T: CppNew<(u32, u32)>>() -> Pin<Box<T>> {
+            T::cpp_new((3, 4)).within_box()
+        }
+        assert_eq!(make::<ffi::Rect>().area(), 12);
+        assert_eq!(make::<ffi::Square>().area(), 9);
+        assert_eq!(ffi::Rect::cpp_new(()).within_box().area(), 1);
+        assert_eq!(ffi::Square::cpp_new((5,)).within_unique_ptr().area(), 25);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Rect")
+            generate!("Square")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_ostream_as_writer() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <ostream>
+        inline void dump(uint32_t v, std::ostream& os) {
+            os << \"value \" << v << std::flush;
+        }
+        class Counter {
+        public:
+            uint32_t count = 3;
+            void describe(std::ostream& os) const { os << \"count=\" << count; }
+        };
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut out = Vec::new();
+        ffi::dump(42, &mut out);
+        assert_eq!(out, b"value 42");
+        let counter = ffi::Counter::new().within_unique_ptr();
+        let mut described = Vec::new();
+        counter.describe(&mut described);
+        assert_eq!(described, b"count=3");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("dump")
+            generate!("Counter")
+            ostream_as_writer!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_protobuf_message() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        #include <utility>
+        #include <vector>
+        namespace google {
+        namespace protobuf {
+        class Arena {};
+        }
+        }
+        namespace shop {
+        class Item {
+        public:
+            int32_t sku() const { return sku_; }
+            void set_sku(int32_t value) { sku_ = value; }
+        private:
+            int32_t sku_ = 0;
+        };
+        class Order {
+        public:
+            Order() {}
+            explicit Order(::google::protobuf::Arena*) {}
+            ::google::protobuf::Arena* GetArena() const { return nullptr; }
+            void InternalSwap(Order* other) { std::swap(quantities_, other->quantities_); }
+            void Swap(Order* other) { InternalSwap(other); }
+            const char* _InternalParse(const char* ptr) { return ptr; }
+            const Item& item() const { return item_; }
+            Item* mutable_item() { return &item_; }
+            int quantities_size() const { return static_cast<int>(quantities_.size()); }
+            uint32_t quantities(int index) const { return quantities_[index]; }
+            void add_quantities(uint32_t value) { quantities_.push_back(value); }
+            std::string SerializeAsString() const {
+                return std::string(quantities_.begin(), quantities_.end());
+            }
+            bool ParseFromArray(const void* data, int size) {
+                const unsigned char* bytes = static_cast<const unsigned char*>(data);
+                quantities_.assign(bytes, bytes + size);
+                return true;
+            }
+        private:
+            Item item_;
+            std::vector<uint32_t> quantities_;
+        };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut order = ffi::shop::Order::new().within_unique_ptr();
+        order.pin_mut().add_quantities(3);
+        order.pin_mut().add_quantities(5);
+        order.pin_mut().item_mut().set_sku(7);
+        assert_eq!(order.item().sku(), 7);
+        assert_eq!(order.quantities_iter().collect::<Vec<_>>(), [3, 5]);
+        let bytes = order.to_bytes();
+        assert_eq!(bytes, [3, 5]);
+        let mut copy = ffi::shop::Order::new().within_unique_ptr();
+        assert!(copy.pin_mut().parse_from_bytes(&bytes));
+        assert_eq!(copy.quantities_iter().collect::<Vec<_>>(), [3, 5]);
+        // Swap is meant for users, unlike InternalSwap.
+        let mut empty = ffi::shop::Order::new().within_unique_ptr();
+        unsafe { empty.pin_mut().Swap(copy.pin_mut().get_unchecked_mut()) };
+        assert_eq!(empty.quantities_iter().collect::<Vec<_>>(), [3, 5]);
+        assert_eq!(copy.quantities_size(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("shop::Order")
+            generate!("shop::Item")
+            protobuf_message!("shop::Order")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_extern_rust_fn_closures() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        uint32_t run_closures();
+    "};
+    let cpp = indoc! {"
+        uint32_t run_closures() {
+            std::function<uint32_t(uint32_t)> add_five = make_adder(5);
+            std::function<uint32_t(uint32_t)> copy = add_five;
+            return apply_twice(copy(1), [](uint32_t x) { return x * 10; });
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::run_closures(), 600);
+    };
+    run_test_ex(
+        cpp,
+        hdr,
+        rs,
+        quote! {
+            generate!("run_closures")
+        },
+        None,
+        None,
+        Some(quote! {
+            #[autocxx::extern_rust::extern_rust_function]
+            pub fn make_adder(n: u32) -> Box<dyn Fn(u32) -> u32 + Send + Sync> {
+                Box::new(move |x| x + n)
+            }
+
+            #[autocxx::extern_rust::extern_rust_function]
+            pub fn apply_twice(x: u32, f: Box<dyn Fn(u32) -> u32>) -> u32 {
+                f(f(x))
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_extern_rust_fn_closure_not_send_sync() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        inline uint32_t give_up() { return 0; }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("give_up")
+        },
+        None,
+        None,
+        Some(quote! {
+            #[autocxx::extern_rust::extern_rust_function]
+            pub fn make_adder(n: u32) -> Box<dyn Fn(u32) -> u32> {
+                Box::new(move |x| x + n)
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_deleted_during_call() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    extern \"C\" void mark_freed() noexcept;
+
+    class TestObserver {
+    public:
+        virtual void a() = 0;
+        virtual ~TestObserver() {
+            mark_freed();
+        }
+    };
+    inline void DeleteTestObserver(TestObserver* obs) {
+        delete obs;
+    }
+    inline void TriggerTestObserverA(TestObserver* obs) {
+        obs->a();
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyTestObserver::new_cpp_owned(MyTestObserver {
+                cpp_peer: Default::default(),
+                data: ExternalEngine::default(),
+            });
+            let obs = obs.into_raw();
+            let obs_superclass = unsafe { core::mem::transmute::<*mut ffi::MyTestObserverCpp, *mut ffi::TestObserver>(obs) };
+            assert!(Lazy::force(&STATUS).lock().unwrap().rust_allocated);
+            unsafe { ffi::TriggerTestObserverA(obs_superclass) };
+            let status = Lazy::force(&STATUS).lock().unwrap();
+            assert!(status.cpp_freed);
+            assert!(!status.peer_alive_after_delete);
+            assert!(status.rust_allocated_after_delete);
+            assert!(!status.rust_allocated);
+        },
+        quote! {
+            generate!("TriggerTestObserverA")
+            generate!("DeleteTestObserver")
+            subclass!("TestObserver",MyTestObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use once_cell::sync::Lazy;
+            use std::sync::Mutex;
+
+            use autocxx::subclass::CppSubclass;
+            use ffi::TestObserver_methods;
+            #[autocxx::subclass::subclass]
+            pub struct MyTestObserver {
+                data: ExternalEngine,
+            }
+            impl TestObserver_methods for MyTestObserver {
+                fn a(&mut self) {
+                    let me = self.peer_mut();
+                    let me = unsafe { core::mem::transmute::<*mut ffi::MyTestObserverCpp, *mut ffi::TestObserver>(me.get_unchecked_mut()) };
+                    unsafe { ffi::DeleteTestObserver(me) };
+                    let alive = self.peer_is_alive();
+                    let mut status = Lazy::force(&STATUS).lock().unwrap();
+                    status.peer_alive_after_delete = alive;
+                    status.rust_allocated_after_delete = status.rust_allocated;
+                }
+            }
+
+            #[no_mangle]
+            pub fn mark_freed() {
+                Lazy::force(&STATUS).lock().unwrap().cpp_freed = true;
+            }
+
+            #[derive(Default)]
+            struct Status {
+                cpp_freed: bool,
+                rust_allocated: bool,
+                peer_alive_after_delete: bool,
+                rust_allocated_after_delete: bool,
+            }
+
+            static STATUS: Lazy<Mutex<Status>> = Lazy::new(|| Mutex::new(Status::default()));
+
+            pub struct ExternalEngine;
+
+            impl Default for ExternalEngine {
+                fn default() -> Self {
+                    Lazy::force(&STATUS).lock().unwrap().rust_allocated = true;
+                    ExternalEngine
+                }
+            }
+
+            impl Drop for ExternalEngine {
+                fn drop(&mut self) {
+                    Lazy::force(&STATUS).lock().unwrap().rust_allocated = false;
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_generate_member() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+    namespace ns {
+    class BigClass {
+    public:
+        BigClass() : value(3) {}
+        uint32_t small_method() const { return value; }
+        uint32_t other_method() const { return value * 2; }
+        static uint32_t static_method() { return 4; }
+        void unsupported_method(std::unique_ptr<uint32_t>&& p) { value = *p; }
+    private:
+        uint32_t value;
+    };
+    }
+    "};
+    let rs = quote! {
+        let big = ffi::ns::BigClass::new().within_unique_ptr();
+        assert_eq!(big.small_method(), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_member!("ns::BigClass::small_method")
+            assert_not_generated!("ns::BigClass::other_method")
+            assert_not_generated!("ns::BigClass::static_method")
+            assert_not_generated!("ns::BigClass::unsupported_method")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_generate_member_missing() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    namespace ns {
+    class BigClass {
+    public:
+        uint32_t small_method() const { return 3; }
+    };
+    }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate_member!("ns::BigClass::smal_method")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_header_preprocessor() {
+    // Only bindgen defines BINDGEN, so only bindgen would see the #error
+    // if the preprocessor didn't remove it.
+    let hdr = indoc! {"
+    #include <cstdint>
+    #ifdef BINDGEN
+    #error \"this header must be preprocessed\"
+    #endif
+    inline uint32_t DoMath(uint32_t a) {
+        return a * 3;
+    }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::DoMath(4), 12);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["DoMath"], &[], None),
+        Some(Box::new(HeaderLineStripper(
+            "#error \"this header must be preprocessed\"",
+        ))),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_iterator_range() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <numeric>
+    namespace ns {
+    inline uint32_t Sum(const uint32_t* begin, const uint32_t* end) {
+        return std::accumulate(begin, end, 0u);
+    }
+    class Scaler {
+    public:
+        Scaler(uint32_t factor) : factor(factor) {}
+        void Scale(uint32_t* begin, uint32_t* end) const {
+            for (auto it = begin; it != end; ++it) {
+                *it *= factor;
+            }
+        }
+        uint32_t factor;
+    };
+    }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::ns::Sum(&[1, 2, 3]), 6);
+        assert_eq!(ffi::ns::Sum(&[]), 0);
+        let scaler = ffi::ns::Scaler::new(2).within_unique_ptr();
+        let mut values = vec![1, 2, 3];
+        scaler.Scale(&mut values);
+        assert_eq!(values, [2, 4, 6]);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Sum")
+            generate!("ns::Scaler")
+            iterator_range!("ns::Sum", begin = 0)
+            iterator_range!("ns::Scaler::Scale", begin = 0)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_iterator_range_returns_lifetime_of_later_arg() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            struct Item {
+                uint32_t value;
+            };
+            class Haystack {
+            public:
+                Haystack() : items{{10}, {20}, {30}} {}
+                Item items[3];
+            };
+            inline const Item& find_last(const uint32_t* begin, const uint32_t* end, const Haystack& haystack) {
+                return haystack.items[*(end - 1)];
+            }
+        }
+    "};
+    let rs = quote! {
+        let haystack = ffi::ns::Haystack::new().within_box();
+        let item = {
+            let indices = vec![0, 2];
+            ffi::ns::find_last(&indices, &haystack)
+        };
+        assert_eq!(item.value, 30);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Haystack")
+            generate!("ns::find_last")
+            generate_pod!("ns::Item")
+            iterator_range!("ns::find_last", begin = 0)
+            returns_lifetime_of_arg!("ns::find_last", arg = 2)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_deleted_address_of() {
+    // DSL-style libraries often overload unary `operator&`, or delete it,
+    // so the generated C++ mustn't take addresses with it.
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <deque>
+    #include <memory>
+    namespace ns {
+    class Expr {
+    public:
+        Expr(uint32_t value) : value(value) {}
+        uint32_t Value() const { return value; }
+        void operator&() const = delete;
+    private:
+        uint32_t value;
+    };
+    struct Term {
+        uint32_t value;
+        const Term* operator&() const { return nullptr; }
+    };
+    inline uint32_t Eval(Expr expr) { return expr.Value(); }
+    inline const Expr& Pick(const Expr& a, const Expr& b, bool first) {
+        return first ? a : b;
+    }
+    inline std::unique_ptr<std::deque<Term>> MakeTerms() {
+        auto terms = std::make_unique<std::deque<Term>>();
+        terms->push_back(Term{4});
+        terms->push_back(Term{5});
+        return terms;
+    }
+    }
+    "};
+    let rs = quote! {
+        let a = ffi::ns::Expr::new(2).within_unique_ptr();
+        let b = ffi::ns::Expr::new(3).within_unique_ptr();
+        assert_eq!(ffi::ns::Pick(&a, &b, false).Value(), 3);
+        assert_eq!(ffi::ns::Eval(a), 2);
+        let terms = ffi::ns::MakeTerms();
+        assert_eq!(unsafe { terms.try_get(1).unwrap().as_ref() }.value, 5);
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["ns::Expr", "ns::Eval", "ns::Pick", "ns::MakeTerms"],
+        &["ns::Term"],
+    );
+}
+
+#[test]
+fn test_panic_free() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+    #include <string>
+    class Widget {
+    public:
+        Widget(uint32_t size) : size(size) {}
+        uint32_t size;
+    };
+    inline std::unique_ptr<Widget> MaybeMakeWidget(bool make) {
+        return make ? std::make_unique<Widget>(3) : nullptr;
+    }
+    inline uint32_t Measure(Widget widget) { return widget.size; }
+    inline uint32_t Consume(Widget&& widget) { return widget.size; }
+    inline std::string Describe(const Widget& widget) {
+        return std::to_string(widget.size);
+    }
+    "};
+    let rs = quote! {
+        assert!(ffi::MaybeMakeWidget(false).is_none());
+        let widget = ffi::MaybeMakeWidget(true).unwrap();
+        assert_eq!(ffi::Measure(&widget), Ok(3));
+        assert_eq!(ffi::Consume(widget), Ok(3));
+        assert_eq!(
+            ffi::Measure(cxx::UniquePtr::<ffi::Widget>::null()),
+            Err(autocxx::NullUniquePtr)
+        );
+        assert_eq!(
+            ffi::Consume(cxx::UniquePtr::<ffi::Widget>::null()),
+            Err(autocxx::NullUniquePtr)
+        );
+        // Functions with nothing to check are unchanged.
+        let widget = ffi::Widget::new(4).within_unique_ptr();
+        assert_eq!(ffi::Describe(&widget).to_str().unwrap(), "4");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Widget")
+            generate!("MaybeMakeWidget")
+            generate!("Measure")
+            generate!("Consume")
+            generate!("Describe")
+            panic_free!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+/// A library shipped as a single source file can be bound without a
+/// header, so long as the source file is compiled too.
+#[test]
+fn test_cpp_source_inclusion() {
+    let amalgamation = indoc! {"
+        #include <cstdint>
+        struct Counter {
+            uint32_t count;
+        };
+        static uint32_t step = 2;
+        uint32_t total = 0;
+        #define DECLARE_LIMIT(name, value) struct name { uint32_t limit = value; }
+        DECLARE_LIMIT(Limit, 10)
+        uint32_t Advance(const Counter& counter) {
+            total += step;
+        #if defined(NEVER_DEFINED)
+            return 0; }
+        #endif
+            return counter.count + step;
+        }
+        inline uint32_t Twice(uint32_t a) { return a * 2; }
+        template <typename T> uint32_t Size() { return sizeof(T); }
+        template <> uint32_t Size<Limit>() { return 1; }
+        uint32_t LimitSize() { return Size<Limit>(); }
+    "};
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("amalgamation.cpp");
+    std::fs::write(&path, amalgamation).unwrap();
+    let path = path.to_str().unwrap().to_string();
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        let counter = ffi::Counter { count: 1 };
+        assert_eq!(ffi::Advance(&counter), 3);
+        assert_eq!(ffi::Twice(4), 8);
+        assert_eq!(ffi::LimitSize(), 1);
+    };
+    run_test_ex(
+        "",
+        "",
+        rs,
+        quote! {
+            #hexathorpe include #path
+            generate_pod!("Counter")
+            generate!("Advance")
+            generate!("Twice")
+            generate!("LimitSize")
+        },
+        Some(Box::new(SourceFileCompiler(path.clone()))),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_copy_string_return() {
+    let hdr = indoc! {"
+        #include <memory>
+        #include <string>
+        struct Document {
+            std::string title;
+            std::string body;
+            const std::string& GetTitle() const { return title; }
+            const std::string& GetBody() const { return body; }
+        };
+        inline std::unique_ptr<Document> MakeDocument() {
+            auto doc = std::make_unique<Document>();
+            doc->title = \"Title\";
+            doc->body = \"Body\";
+            return doc;
+        }
+    "};
+    let rs = quote! {
+        let doc = ffi::MakeDocument();
+        // Borrowed, as usual.
+        let body: &cxx::CxxString = doc.GetBody();
+        assert_eq!(body.to_str().unwrap(), "Body");
+        // Copied, so it outlives the document.
+        let title: cxx::UniquePtr<cxx::CxxString> = doc.GetTitle();
+        drop(doc);
+        assert_eq!(title.to_str().unwrap(), "Title");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("Document")
+            generate!("MakeDocument")
+            copy_string_return!("Document::GetTitle")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_property_accessors() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace ns {
+            class Config {
+            public:
+                Config() : width(3), border_color(1) {}
+                uint32_t GetWidth() const { return width; }
+                void SetWidth(uint32_t w) { width = w; }
+                uint32_t GetBorderColor() const { return border_color; }
+                void SetBorderColor(uint32_t c) { border_color = c; }
+                uint32_t GetArea() const { return width * width; }
+            private:
+                uint32_t width;
+                uint32_t border_color;
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let mut config = ffi::ns::Config::new().within_unique_ptr();
+        let mut fields = config.pin_mut().fields();
+        assert_eq!(fields.width(), 3);
+        fields.set_width(4);
+        fields.set_border_color(2);
+        assert_eq!(fields.border_color(), 2);
+        assert_eq!(config.GetWidth(), 4);
+        assert_eq!(config.GetArea(), 16);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Config")
+            property_accessors!("ns::Config")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_pmr_containers() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <memory_resource>
+        #include <string>
+        #include <vector>
+        inline uint32_t measure(const std::pmr::string& s) { return s.size(); }
+        inline uint32_t measure_value(std::pmr::string s) { return s.size(); }
+        inline std::pmr::string greet() { return std::pmr::string(\"hello\"); }
+        inline uint32_t count(const std::pmr::vector<uint32_t>& v) { return v.size(); }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::measure(ffi::make_string("abc").as_ref().unwrap()), 3);
+        assert_eq!(ffi::measure_value("abcd"), 4);
+        assert_eq!(ffi::greet().to_str().unwrap(), "hello");
+        let mut v = cxx::CxxVector::<u32>::new();
+        v.pin_mut().push(1);
+        v.pin_mut().push(2);
+        assert_eq!(ffi::count(v.as_ref().unwrap()), 2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        directives_from_lists(&["measure", "measure_value", "greet", "count"], &[], None),
+        make_cpp17_adder(),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_outlives() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace net {
+            inline uint32_t& live_sessions() { static uint32_t count = 0; return count; }
+            class Session {
+            public:
+                Session() { live_sessions()++; }
+                ~Session() { live_sessions()--; }
+                uint32_t open_connections() const { return connections; }
+                uint32_t connections = 0;
+            };
+            inline uint32_t get_live_sessions() { return live_sessions(); }
+            class Connection {
+            public:
+                Connection(Session& session) : session(session) { session.connections++; }
+                ~Connection() { session.connections--; }
+                uint32_t session_connections() const { return session.open_connections(); }
+            private:
+                Session& session;
+            };
+        }
+    "};
+    let rs = quote! {
+        let session = autocxx::SharedDependency::new(ffi::net::Session::new().within_unique_ptr());
+        let connection = session.with_pin_mut(|session| {
+            ffi::net::Connection::new(session).within_unique_ptr()
+        });
+        let connection = autocxx::Dependent::new(connection, session.clone());
+        drop(session);
+        assert_eq!(ffi::net::get_live_sessions(), 1);
+        assert_eq!(connection.session_connections(), 1);
+        assert_eq!(connection.dependency().borrow().open_connections(), 1);
+        drop(connection);
+        assert_eq!(ffi::net::get_live_sessions(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("net::Session")
+            generate!("net::Connection")
+            generate!("net::get_live_sessions")
+            outlives!("net::Connection", "net::Session")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_object_identity() {
+    let hdr = indoc! {"
+        namespace ns {
+            class Widget {
+            public:
+                virtual ~Widget() {}
+                int width = 3;
+            };
+            class Observer {
+            public:
+                virtual ~Observer() {}
+                virtual void notify() {}
+            };
+            class Button : public Widget, public Observer {};
+            inline const Widget& as_widget(const Button& button) { return button; }
+            inline const Observer& as_observer(const Button& button) { return button; }
+        }
+    "};
+    let rs = quote! {
+        let button = ffi::ns::Button::new().within_unique_ptr();
+        let widget = ffi::ns::as_widget(&button);
+        let observer = ffi::ns::as_observer(&button);
+        assert_ne!(
+            widget as *const ffi::ns::Widget as usize,
+            observer as *const ffi::ns::Observer as usize
+        );
+        assert_eq!(widget.object_identity(), observer.object_identity());
+        assert_eq!(button.object_identity(), observer.object_identity());
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("ns::Widget")
+            generate!("ns::Observer")
+            generate!("ns::Button")
+            generate!("ns::as_widget")
+            generate!("ns::as_observer")
+            object_identity!()
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_shared_struct() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace net {
+            struct Config {
+                uint32_t port;
+                bool verbose;
+                double timeout;
+                uint8_t address[4];
+            };
+            inline Config default_config() {
+                return Config{ 8080, false, 1.5, { 127, 0, 0, 1 } };
+            }
+            inline uint32_t next_port(Config config) { return config.port + 1; }
+        }
+    "};
+    let rs = quote! {
+        let config = ffi::net::default_config();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.address, [127, 0, 0, 1]);
+        let copy = config;
+        assert_eq!(copy, config);
+        let config = ffi::net::Config {
+            port: 9000,
+            verbose: true,
+            ..config
+        };
+        assert_eq!(ffi::net::next_port(config), 9001);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_pod!("net::Config")
+            shared_struct!("net::Config")
+            generate!("net::default_config")
+            generate!("net::next_port")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_shared_struct_empty() {
+    let hdr = indoc! {"
+        namespace net {
+            struct Empty {};
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate_pod!("net::Empty")
+            shared_struct!("net::Empty")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_scope_guard() {
+    let hdr = indoc! {"
+        namespace db {
+            class Database {
+            public:
+                Database() : depth(0) {}
+                int get_depth() const { return depth; }
+                int depth;
+            };
+            class Transaction {
+            public:
+                Transaction(Database& db) : db(db) { db.depth++; }
+                Transaction(const Transaction&) = delete;
+                Transaction(Transaction&&) = delete;
+                ~Transaction() { db.depth--; }
+                int get_depth() const { return db.depth; }
+            private:
+                Database& db;
+            };
+        }
+    "};
+    let rs = quote! {
+        let mut db = ffi::db::Database::new().within_unique_ptr();
+        {
+            let txn = ffi::db::TransactionGuard::new(db.pin_mut());
+            assert_eq!(txn.get_depth(), 1);
+        }
+        assert_eq!(db.get_depth(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("db::Database")
+            generate!("db::Transaction")
+            scope_guard!("db::Transaction")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_member_fn_field() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace plugin {
+            class Handler {
+            public:
+                Handler() : count(0) {}
+                bool on_event(int32_t id) const { return id > 0; }
+                void reset() { count = 0; }
+                uint32_t get_count() const { return count; }
+                void bump() { count++; }
+            private:
+                uint32_t count;
+            };
+            struct Callbacks {
+                Callbacks() : on_event(&Handler::on_event), reset(nullptr) {}
+                bool (Handler::*on_event)(int32_t) const;
+                void (Handler::*reset)();
+            };
+        }
+    "};
+    let rs = quote! {
+        let handler = ffi::plugin::Handler::new().within_unique_ptr();
+        let mut callbacks = ffi::plugin::Callbacks::new().within_unique_ptr();
+        assert!(callbacks.has_on_event());
+        assert!(callbacks.call_on_event_on(&handler, 3));
+        assert!(!callbacks.call_on_event_on(&handler, -3));
+        assert!(!callbacks.has_reset());
+        let on_event = callbacks.on_event();
+        callbacks.pin_mut().clear_on_event();
+        assert!(!callbacks.has_on_event());
+        let mut other = ffi::plugin::Callbacks::new().within_unique_ptr();
+        other.pin_mut().clear_on_event();
+        other.pin_mut().set_on_event(&on_event);
+        assert!(other.call_on_event_on(&handler, 1));
+        let mut handler = handler;
+        callbacks
+            .pin_mut()
+            .set_reset(&ffi::plugin::Callbacks_reset::bump());
+        callbacks.call_reset_on(handler.pin_mut());
+        callbacks.call_reset_on(handler.pin_mut());
+        assert_eq!(handler.get_count(), 2);
+        callbacks
+            .pin_mut()
+            .set_reset(&ffi::plugin::Callbacks_reset::reset());
+        callbacks.call_reset_on(handler.pin_mut());
+        assert_eq!(handler.get_count(), 0);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("plugin::Handler")
+            generate!("plugin::Callbacks")
+            member_fn_field!("plugin::Callbacks::on_event", fn(&plugin::Handler, i32) -> bool)
+            member_fn_field!("plugin::Callbacks::reset", fn(&mut plugin::Handler), targets = ["reset", "bump"])
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_member_fn_field_struct_not_generated() {
+    let hdr = indoc! {"
+        namespace plugin {
+            class Handler {
+            public:
+                void reset() {}
+            };
+            struct Callbacks {
+                void (Handler::*reset)();
+            };
+        }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("plugin::Handler")
+            member_fn_field!("plugin::Callbacks::reset", fn(&mut plugin::Handler))
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_name_transform() {
+    use autocxx_engine::plugins::{
+        register_name_transform, NameToTransform, NameTransform, NAME_TRANSFORM_VERSION,
+    };
+    struct TestTransform(u32);
+    impl NameTransform for TestTransform {
+        fn version(&self) -> u32 {
+            self.0
+        }
+        fn name(&self) -> &str {
+            "test_transform"
+        }
+        fn rust_name(&self, item: &NameToTransform) -> Option<String> {
+            // Transforms are global to the process, so only touch this test's items.
+            if !item.cpp_name().starts_with("name_transform_test::") {
+                return None;
+            }
+            item.rust_name()
+                .strip_prefix("NtLib")
+                .map(str::to_lowercase)
+        }
+    }
+    assert!(register_name_transform(TestTransform(NAME_TRANSFORM_VERSION + 1)).is_err());
+    register_name_transform(TestTransform(NAME_TRANSFORM_VERSION)).unwrap();
+    let hdr = indoc! {"
+        #include <cstdint>
+        namespace name_transform_test {
+            inline uint32_t NtLibOpen() { return 3; }
+            class Widget {
+            public:
+                Widget() {}
+                uint32_t NtLibSize() const { return 4; }
+                uint32_t NtLibGetDepth() const { return 5; }
+            };
+        }
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        assert_eq!(ffi::name_transform_test::open(), 3);
+        let widget = ffi::name_transform_test::Widget::new().within_unique_ptr();
+        assert_eq!(widget.size(), 4);
+        // getters! matches the C++ name, and wins over the transform.
+        assert_eq!(widget.depth(), 5);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate_ns!("name_transform_test")
+            getters!("name_transform_test::Widget", strip_prefix = "NtLibGet")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_vector_slice() {
+    let hdr = indoc! {"
+        #include <vector>
+        struct Point {
+            double x;
+            double y;
+        };
+        class Samples {
+        public:
+            Samples() : values_{1.5, 2.5, 3.5}, points_{{1.0, 2.0}, {3.0, 4.0}} {}
+            const std::vector<double>& values() const { return values_; }
+            const std::vector<Point>& points() const { return points_; }
+            const std::vector<double>& none() const { return none_; }
+            const std::vector<double>& sizes() const { return values_; }
+            size_t sizes_as_slice() const { return 42; }
+        private:
+            std::vector<double> values_;
+            std::vector<Point> points_;
+            std::vector<double> none_;
+        };
+    "};
+    let rs = quote! {
+        use autocxx::prelude::*;
+        let samples = ffi::Samples::new().within_unique_ptr();
+        assert_eq!(samples.values_as_slice(), &[1.5, 2.5, 3.5]);
+        let points = samples.points_as_slice();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].x, 3.0);
+        assert_eq!(points[1].y, 4.0);
+        assert!(samples.none_as_slice().is_empty());
+        assert_eq!(samples.values().len(), 3);
+        // The C++ method of that name wins.
+        assert_eq!(samples.sizes_as_slice(), 42);
+    };
+    run_test("", hdr, rs, &["Samples"], &["Point"]);
+}
+
+// Yet to test:
+// - Ifdef
+// - Out param pointers
+// - ExcludeUtilities
+// - Struct fields which are typedefs
+// Negative tests:
+// - Private methods
+// - Private fields
+
+#[test]
+fn test_iterator_range_iterator_class() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <numeric>
+    #include <vector>
+    namespace ns {
+    inline uint32_t Sum(std::vector<uint32_t>::const_iterator begin,
+                        std::vector<uint32_t>::const_iterator end) {
+        return std::accumulate(begin, end, 0u);
+    }
+    }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("ns::Sum")
+            iterator_range!("ns::Sum", begin = 0)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_iterator_range_container_reference() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <numeric>
+    #include <vector>
+    namespace ns {
+    inline uint32_t Sum(const std::vector<uint32_t>& values, uint32_t initial) {
+        return std::accumulate(values.begin(), values.end(), initial);
+    }
+    }
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("ns::Sum")
+            iterator_range!("ns::Sum", begin = 0)
+        },
+        None,
+        None,
+        None,
+    );
+}
@@ -13,8 +13,10 @@ use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use proc_macro_error::{abort, proc_macro_error};
 use quote::quote;
-use syn::parse::Parser;
-use syn::{parse_macro_input, parse_quote, Fields, Item, ItemStruct, Visibility};
+use syn::parse::{ParseStream, Parser};
+use syn::{
+    parse_macro_input, parse_quote, Fields, ImplItem, Item, ItemImpl, ItemStruct, Visibility,
+};
 
 /// Implementation of the `include_cpp` macro. See documentation for `autocxx` crate.
 #[proc_macro_error]
@@ -81,6 +83,68 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
     toks.into()
 }
 
+/// Attribute to turn an inherent `impl` block for a generated type into
+/// a sealed extension trait, plus an implementation of it. See
+/// documentation for `autocxx::extend`.
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn extend(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| -> syn::Result<(Visibility, Ident)> {
+        Ok((input.parse()?, input.parse()?))
+    };
+    let (vis, trait_id) = parser.parse(attr).unwrap_or_else(|_| {
+        abort!(
+            Span::call_site(),
+            "Expected a trait name, e.g. extend(pub FooExt)"
+        )
+    });
+    let imp: ItemImpl =
+        syn::parse(item).unwrap_or_else(|_| abort!(Span::call_site(), "Expected an impl block"));
+    if let Some((_, path, _)) = &imp.trait_ {
+        abort!(path, "Expected an inherent impl block, not a trait impl");
+    }
+    if !imp.generics.params.is_empty() {
+        abort!(imp.generics, "Generic impl blocks can't be extended");
+    }
+    let self_ty = &imp.self_ty;
+    let mut trait_items = Vec::new();
+    let mut impl_items = Vec::new();
+    for item in imp.items {
+        match item {
+            ImplItem::Fn(mut f) => {
+                let sig = &f.sig;
+                let attrs = &f.attrs;
+                trait_items.push(quote! {
+                    #(#attrs)*
+                    #sig;
+                });
+                f.vis = Visibility::Inherited;
+                impl_items.push(f);
+            }
+            other => abort!(other, "Only methods can be added to generated types"),
+        }
+    }
+    let sealed_mod = Ident::new(&format!("__{trait_id}_sealed"), Span::call_site());
+    let toks = quote! {
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #sealed_mod {
+            pub trait Sealed {}
+        }
+
+        #vis trait #trait_id: #sealed_mod::Sealed {
+            #(#trait_items)*
+        }
+
+        impl #sealed_mod::Sealed for #self_ty {}
+
+        impl #trait_id for #self_ty {
+            #(#impl_items)*
+        }
+    };
+    toks.into()
+}
+
 /// Attribute to state that a Rust type is to be exported to C++
 /// in the `extern "Rust"` section of the generated `cxx` bindings.
 #[proc_macro_error]
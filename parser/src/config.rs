@@ -11,6 +11,7 @@ use indexmap::set::IndexSet as HashSet;
 use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 
 use itertools::Itertools;
 use proc_macro2::Span;
@@ -19,8 +20,9 @@ use quote::ToTokens;
 #[cfg(feature = "reproduction_case")]
 use quote::format_ident;
 use syn::{
+    buffer::Cursor,
     parse::{Parse, ParseStream},
-    Signature, Token, TypePath,
+    parse_quote, PathArguments, Signature, Token, Type, TypePath,
 };
 use syn::{Ident, Result as ParseResult};
 use thiserror::Error;
@@ -90,20 +92,88 @@ impl UnsafePolicy {
     }
 }
 
+/// How strictly to treat items which can't be generated, as given by
+/// `conversion_errors!`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Default)]
+pub enum ConversionErrorPolicy {
+    /// Fail if a directive names something which doesn't exist, but
+    /// otherwise skip anything which can't be generated, leaving a
+    /// note in the documentation.
+    #[default]
+    Default,
+    /// Also fail if anything requested by `generate!`, `generate_pod!` or
+    /// `generate_ns!` was skipped, listing every such item.
+    Strict,
+    /// Only warn if a directive names something which doesn't exist.
+    Lenient,
+}
+
 /// An entry in the allowlist.
 #[derive(Hash, Debug)]
 pub enum AllowlistEntry {
     Item(String),
-    Namespace(String),
+    Namespace(String, NamespaceOptions),
 }
 
 impl AllowlistEntry {
     fn to_bindgen_item(&self) -> String {
         match self {
             AllowlistEntry::Item(i) => i.clone(),
-            AllowlistEntry::Namespace(ns) => format!("{ns}::.*"),
+            AllowlistEntry::Namespace(ns, _) => format!("{ns}::.*"),
         }
     }
+
+    fn matches(&self, cpp_name: &str) -> bool {
+        match self {
+            AllowlistEntry::Item(i) => i == cpp_name,
+            AllowlistEntry::Namespace(ns, options) => cpp_name
+                .strip_prefix(ns.as_str())
+                .map(|rest| options.permits(rest.trim_start_matches("::")))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Nested namespaces which conventionally hold implementation details,
+/// skipped by `generate_ns!` if asked.
+static DETAIL_NAMESPACES: &[&str] = &["detail", "details", "internal", "impl"];
+
+/// Whether types within a namespace given to `generate_ns!` should be
+/// made POD.
+#[derive(Hash, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NamespacePodPolicy {
+    /// Only those types also listed in `generate_pod!`.
+    #[default]
+    Never,
+    /// Every type which can safely be POD.
+    Auto,
+}
+
+/// Options given to `generate_ns!` to restrict which items within the
+/// namespace are generated, and how.
+#[derive(Hash, Debug, Default, Clone)]
+pub struct NamespaceOptions {
+    /// How many levels of nested namespace to descend into, if limited.
+    /// Zero means only items directly within the namespace.
+    pub max_depth: Option<usize>,
+    pub skip_detail_namespaces: bool,
+    pub pod: NamespacePodPolicy,
+}
+
+impl NamespaceOptions {
+    /// Whether these options allow an item, named relative to the
+    /// namespace, e.g. `inner::Foo` for `ns::inner::Foo`.
+    fn permits(&self, relative_name: &str) -> bool {
+        let mut nested_namespaces: Vec<_> = relative_name.split("::").collect();
+        nested_namespaces.pop();
+        self.max_depth
+            .map(|max_depth| nested_namespaces.len() <= max_depth)
+            .unwrap_or(true)
+            && !(self.skip_detail_namespaces
+                && nested_namespaces
+                    .iter()
+                    .any(|ns| DETAIL_NAMESPACES.contains(ns)))
+    }
 }
 
 /// Allowlist configuration.
@@ -159,6 +229,201 @@ impl Default for Allowlist {
 pub struct Subclass {
     pub superclass: String,
     pub subclass: Ident,
+    pub non_virtual_destructor: NonVirtualDestructor,
+}
+
+/// What to do if the superclass named in a `subclass!` has a public
+/// destructor which isn't virtual, such that deleting the subclass through
+/// a pointer to the superclass would be undefined behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NonVirtualDestructor {
+    /// Refuse to generate bindings.
+    #[default]
+    Error,
+    /// Don't offer a `UniquePtr` to the superclass; instead, allow the
+    /// subclass to be leaked as a raw superclass pointer, so it's never
+    /// destroyed through that pointer.
+    Leak,
+    /// Offer a `UniquePtr` to the superclass as usual, along with a
+    /// function which downcasts it back to the subclass before deleting it.
+    Downcast,
+}
+
+/// A C++ struct containing a tag field and a union, which should be
+/// presented to Rust as an enum. See `tagged_union!`.
+#[derive(Debug, Hash)]
+pub struct TaggedUnion {
+    pub ty: String,
+    pub tag: String,
+    pub variants: Vec<TaggedUnionVariant>,
+}
+
+/// One possible interpretation of a [`TaggedUnion`].
+#[derive(Debug, Hash)]
+pub struct TaggedUnionVariant {
+    /// The value of the tag field for which this variant is active.
+    pub tag_value: i64,
+    /// Name of the variant in the Rust enum.
+    pub name: Ident,
+    /// C++ type of the union member.
+    pub ty: String,
+    /// Path to the union member within the struct, e.g. `data.key`.
+    pub member: String,
+}
+
+/// A C++ conversion operator, e.g. `operator int()`, which should be
+/// exposed as a method. See `conversion_operator!`.
+#[derive(Debug, Hash)]
+pub struct ConversionOperator {
+    /// The C++ type which has the conversion operator.
+    pub ty: String,
+    /// The C++ type to which it converts.
+    pub to: String,
+    /// Whether to implement `From<&ty>` for the resulting Rust type.
+    pub impl_from: bool,
+}
+
+/// Methods of a type which should be treated as pure accessors and
+/// renamed accordingly. See `getters!`.
+#[derive(Debug, Hash)]
+pub(crate) struct Getters {
+    pub(crate) ty: String,
+    pub(crate) strip_prefix: String,
+}
+
+/// What should happen when Rust drops an object of a type listed in
+/// `no_drop!`, which is owned by C++ and mustn't be destroyed from Rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropBehaviour {
+    /// Don't call the destructor.
+    Leak,
+    /// Don't call the destructor, and fail a C++ `assert` unless `NDEBUG`
+    /// is defined.
+    Assert,
+}
+
+/// A C++ exception or status type which should implement
+/// `std::error::Error` in Rust. See `error_type!`.
+#[derive(Debug, Hash)]
+pub(crate) struct ErrorType {
+    pub(crate) ty: String,
+    /// The method which returns the message, e.g. `what`.
+    pub(crate) message: String,
+}
+
+/// A reference-counted C++ type which should be owned from Rust through
+/// `autocxx::IntrusivePtr`. See `intrusive_ptr!`.
+#[derive(Debug, Hash)]
+pub(crate) struct IntrusivePtr {
+    pub(crate) ty: String,
+    /// The method which increments the reference count, e.g. `AddRef`.
+    pub(crate) add_ref: String,
+    /// The method which decrements it, e.g. `Release`.
+    pub(crate) release: String,
+}
+
+/// A C++ function which reports its result later, through a callback
+/// passed as its final parameter, and for which we should generate a
+/// Rust function returning a future. See `completion_future!`.
+#[derive(Debug, Hash)]
+pub struct CompletionFuture {
+    /// The C++ function.
+    pub function: String,
+    /// The Rust primitive types of the parameters other than the callback.
+    pub params: Vec<Ident>,
+    /// The Rust primitive type passed to the callback, if any.
+    pub result: Option<Ident>,
+}
+
+/// A C++ function which registers a C-style callback, taking a function
+/// pointer `void (*)(void*, ...)` followed by the `void*` context to pass
+/// to it, for which we should generate a Rust function accepting a
+/// closure. See `context_callback!`.
+#[derive(Debug, Hash)]
+pub struct ContextCallback {
+    /// The C++ registration function.
+    pub function: String,
+    /// The Rust primitive types of the callback's parameters, after the
+    /// context.
+    pub params: Vec<Ident>,
+    /// The C++ function which takes the same callback and context and
+    /// unregisters them, if any.
+    pub unregister: Option<String>,
+}
+
+/// A field of a C++ struct which holds a pointer to a member function,
+/// for which we should generate opaque storage and shims to read, set
+/// and call it. See `member_fn_field!`.
+#[derive(Debug, Hash)]
+pub struct MemberFnField {
+    /// The struct containing the field.
+    pub table: String,
+    /// The name of the field.
+    pub field: String,
+    /// The class whose member function the field points to.
+    pub class: String,
+    /// Whether the member function is `const`.
+    pub is_const: bool,
+    /// The Rust primitive types of the member function's parameters.
+    pub params: Vec<Ident>,
+    /// The Rust primitive type it returns, if any.
+    pub result: Option<Ident>,
+    /// Member functions of the class for which to generate constructors
+    /// of pointers to them, so that Rust can populate the field.
+    pub targets: Vec<String>,
+}
+
+/// A C++ function which must be called before anything else in the
+/// library, for which we should generate `ensure_initialized()`.
+/// See `init_function!`.
+#[derive(Debug, Hash)]
+pub struct InitFunction {
+    /// The C++ function.
+    pub function: String,
+    /// Whether generated wrapper functions should call
+    /// `ensure_initialized()` first, in debug builds.
+    pub auto_in_debug: bool,
+}
+
+/// A type which the parsed headers only forward-declare, but which is
+/// known to be complete wherever the generated C++ is compiled.
+/// See `complete_type!`.
+#[derive(Debug, Hash)]
+pub struct CompleteType {
+    /// The C++ type.
+    pub ty: String,
+    /// The expected `sizeof` the type.
+    pub size: usize,
+    /// The expected `alignof` the type.
+    pub align: usize,
+    /// A header containing the complete definition, if the parsed
+    /// headers don't themselves pull it in for the C++ compiler.
+    pub header: Option<String>,
+}
+
+/// A Rust type which is equivalent to a C++ POD type, such that
+/// conversions and comparisons can be generated. See `rust_equivalent!`.
+#[derive(Debug, Hash)]
+pub struct RustEquivalent {
+    /// The C++ type.
+    pub ty: String,
+    /// The equivalent Rust type.
+    pub rust_path: TypePath,
+    /// Functions which convert between the types, if their fields don't
+    /// correspond by name.
+    pub conversions: Option<RustEquivalentConversions>,
+}
+
+/// The functions given to `rust_equivalent!` to convert between a C++ type
+/// and its Rust equivalent.
+#[derive(Debug, Hash)]
+pub struct RustEquivalentConversions {
+    /// A function taking a reference to the C++ type and returning the
+    /// Rust type.
+    pub from_cpp: syn::Path,
+    /// A function taking a reference to the Rust type and returning the
+    /// C++ type.
+    pub to_cpp: syn::Path,
 }
 
 #[derive(Clone, Hash)]
@@ -183,6 +448,35 @@ pub struct ExternCppType {
     pub opaque: bool,
 }
 
+/// A C++ class template which corresponds to a generic Rust type, for each
+/// of a listed set of type arguments. See `extern_cpp_type!`.
+#[derive(Debug, Clone, Hash)]
+pub struct GenericExternCppType {
+    /// The C++ template, without its parameter, e.g. `ns::Span`.
+    pub template: String,
+    /// The generic Rust type, with its parameter, e.g. `crate::Span<T>`.
+    pub rust_path: TypePath,
+    /// Each C++ type argument, with the Rust type to substitute for it.
+    pub instantiations: Vec<(String, Type)>,
+    pub opaque: bool,
+}
+
+impl GenericExternCppType {
+    /// The Rust type corresponding to this C++ instantiation of the
+    /// template, if it's one of those listed.
+    fn rust_type_for(&self, cpp_definition: &str) -> Option<Type> {
+        let strip_spaces = |s: &str| s.replace(' ', "");
+        let wanted = strip_spaces(cpp_definition);
+        let (_, rust_arg) = self.instantiations.iter().find(|(cpp_arg, _)| {
+            strip_spaces(&format!("{}<{}>", self.template, cpp_arg)) == wanted
+        })?;
+        let mut rust_path = self.rust_path.clone();
+        rust_path.path.segments.last_mut()?.arguments =
+            PathArguments::AngleBracketed(parse_quote! { <#rust_arg> });
+        Some(Type::Path(rust_path))
+    }
+}
+
 /// Newtype wrapper so we can implement Hash.
 #[derive(Debug, Default)]
 pub struct ExternCppTypeMap(pub HashMap<String, ExternCppType>);
@@ -209,6 +503,35 @@ impl std::hash::Hash for ConcretesMap {
     }
 }
 
+/// Where in the user's source each item was named by a directive, so that
+/// errors about the item can point there. Spans don't affect the generated
+/// code, so aren't hashed.
+#[derive(Debug, Default)]
+pub(crate) struct DirectiveSpans {
+    spans: HashMap<String, Span>,
+    /// While reading a config file, the span of the outermost
+    /// `config_file!` directive, which is recorded in place of spans
+    /// within the file since those can't point anywhere useful.
+    pub(crate) config_file: Option<Span>,
+}
+
+impl std::hash::Hash for DirectiveSpans {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+/// A directive which was applied to an `include_cpp!`, in the order in
+/// which it was applied.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppliedDirective {
+    /// The directive's name, e.g. `generate`.
+    pub name: String,
+    /// The directive's arguments, as written, e.g. `"ns::Foo"`.
+    pub args: String,
+    /// The `config_file!` from which the directive was read, if it wasn't
+    /// written in the `include_cpp!` itself.
+    pub config_file: Option<String>,
+}
+
 #[derive(Debug, Default, Hash)]
 pub struct IncludeCppConfig {
     pub inclusions: Vec<String>,
@@ -219,55 +542,158 @@ pub struct IncludeCppConfig {
     pub allowlist: Allowlist,
     pub(crate) blocklist: Vec<String>,
     pub(crate) constructor_blocklist: Vec<String>,
+    pub(crate) bindgen_blocklist: Vec<String>,
+    pub(crate) clang_args: Vec<String>,
+    pub(crate) treat_as_const: Vec<String>,
+    pub(crate) force_inline: Vec<String>,
+    pub(crate) copy_string_returns: Vec<String>,
+    pub(crate) async_functions: Vec<String>,
+    pub(crate) operator_bool_types: Vec<String>,
+    pub(crate) singletons: Vec<String>,
+    pub(crate) constructor_factories: Vec<String>,
+    pub(crate) no_drop: Vec<(String, DropBehaviour)>,
+    pub(crate) view_types: Vec<String>,
+    pub(crate) mockables: Vec<String>,
+    pub(crate) property_accessor_types: Vec<String>,
+    pub(crate) newtypes: Vec<String>,
+    pub(crate) opaque_handles: Vec<String>,
+    pub(crate) asserted_not_generated: Vec<String>,
+    pub(crate) pod_downgrades_allowed: Vec<String>,
+    pub(crate) thread_affine_types: Vec<String>,
+    pub(crate) thread_handle_types: Vec<String>,
+    pub(crate) protobuf_messages: Vec<String>,
+    pub(crate) pod_defaults: Vec<String>,
+    pub(crate) shared_structs: Vec<String>,
+    pub(crate) scope_guards: Vec<String>,
+    pub(crate) config_files: Vec<String>,
+    pub(crate) imported_modules: Vec<String>,
+    pub(crate) conversion_operators: Vec<ConversionOperator>,
     pub instantiable: Vec<String>,
     pub(crate) exclude_utilities: bool,
+    pub(crate) rtti_type_names: bool,
+    pub(crate) object_identity: bool,
+    pub(crate) strings_by_value: bool,
+    pub(crate) name_table: bool,
+    pub(crate) strict_pod: bool,
+    pub(crate) internal_linkage_shims: bool,
+    pub(crate) msvc_extensions: bool,
+    pub(crate) small_pods_in_registers: bool,
+    pub(crate) ostream_as_writer: bool,
+    pub(crate) panic_free: bool,
+    pub(crate) conversion_error_policy: ConversionErrorPolicy,
     pub(crate) mod_name: Option<Ident>,
+    pub(crate) cpp_std: Option<String>,
     pub rust_types: Vec<RustPath>,
     pub subclasses: Vec<Subclass>,
     pub extern_rust_funs: Vec<RustFun>,
     pub concretes: ConcretesMap,
     pub externs: ExternCppTypeMap,
+    pub(crate) generic_externs: Vec<GenericExternCppType>,
     pub opaquelist: Vec<String>,
+    pub tagged_unions: Vec<TaggedUnion>,
+    pub(crate) overload_names: Vec<(String, Vec<String>)>,
+    pub(crate) c_exports: Vec<(String, String)>,
+    pub(crate) returns_lifetime_of_arg: Vec<(String, usize)>,
+    pub(crate) iterator_ranges: Vec<(String, usize)>,
+    pub(crate) getters: Vec<Getters>,
+    pub(crate) error_types: Vec<ErrorType>,
+    pub(crate) complete_types: Vec<CompleteType>,
+    pub(crate) completion_futures: Vec<CompletionFuture>,
+    pub(crate) context_callbacks: Vec<ContextCallback>,
+    pub(crate) member_fn_fields: Vec<MemberFnField>,
+    pub(crate) init_function: Option<InitFunction>,
+    pub(crate) rust_equivalents: Vec<RustEquivalent>,
+    pub(crate) deprecated_aliases: Vec<(String, String)>,
+    pub(crate) constructor_names: Vec<(String, String)>,
+    pub(crate) member_allowlist: Vec<(String, String)>,
+    pub(crate) covariant_returns: Vec<(String, String)>,
+    pub(crate) intrusive_ptrs: Vec<IntrusivePtr>,
+    pub(crate) header_implementations: Vec<(String, Vec<String>)>,
+    pub(crate) batch_methods: Vec<(String, Vec<String>)>,
+    pub(crate) outlives: Vec<(String, String)>,
+    pub(crate) directive_spans: DirectiveSpans,
+    pub(crate) applied_directives: Vec<AppliedDirective>,
 }
 
 impl Parse for IncludeCppConfig {
     fn parse(input: ParseStream) -> ParseResult<Self> {
         let mut config = IncludeCppConfig::default();
+        parse_directives(input, &mut config)?;
+        Ok(config)
+    }
+}
 
-        while !input.is_empty() {
-            let has_hexathorpe = input.parse::<Option<syn::token::Pound>>()?.is_some();
-            let ident: syn::Ident = input.parse()?;
-            let args;
-            let (possible_directives, to_parse, parse_completely) = if has_hexathorpe {
-                (&get_directives().need_hexathorpe, input, false)
-            } else {
-                input.parse::<Option<syn::token::Not>>()?;
-                syn::parenthesized!(args in input);
-                (&get_directives().need_exclamation, &args, true)
-            };
-            let all_possible = possible_directives.keys().join(", ");
-            let ident_str = ident.to_string();
-            match possible_directives.get(&ident_str) {
-                None => {
-                    return Err(syn::Error::new(
-                        ident.span(),
-                        format!("expected {all_possible}"),
-                    ));
-                }
-                Some(directive) => directive.parse(to_parse, &mut config, &ident.span())?,
-            }
-            if parse_completely && !to_parse.is_empty() {
+/// Parse a sequence of directives, adding them to the given config. This is
+/// used both for the contents of `include_cpp!` and for config files.
+pub(crate) fn parse_directives(
+    input: ParseStream,
+    config: &mut IncludeCppConfig,
+) -> ParseResult<()> {
+    while !input.is_empty() {
+        let has_hexathorpe = input.parse::<Option<syn::token::Pound>>()?.is_some();
+        let ident: syn::Ident = input.parse()?;
+        let args;
+        let (possible_directives, to_parse, parse_completely) = if has_hexathorpe {
+            (&get_directives().need_hexathorpe, input, false)
+        } else {
+            input.parse::<Option<syn::token::Not>>()?;
+            syn::parenthesized!(args in input);
+            (&get_directives().need_exclamation, &args, true)
+        };
+        let all_possible = possible_directives.keys().join(", ");
+        let ident_str = ident.to_string();
+        let start = to_parse.cursor();
+        // Most directives name an item first, so that errors about the
+        // item can point here.
+        if let Ok(item) = to_parse.fork().parse::<syn::LitStr>() {
+            config.record_directive_span(&item.value(), item.span());
+        }
+        // Directives applied from within this one, by config_file!, come
+        // after it.
+        let applied_index = config.applied_directives.len();
+        match possible_directives.get(&ident_str) {
+            None => {
                 return Err(syn::Error::new(
                     ident.span(),
-                    format!("found unexpected input within the directive {ident_str}"),
+                    format!("expected {all_possible}"),
                 ));
             }
-            if input.is_empty() {
-                break;
+            Some(directive) => directive.parse(to_parse, config, &ident.span())?,
+        }
+        config.applied_directives.insert(
+            applied_index,
+            AppliedDirective {
+                name: ident_str.clone(),
+                args: tokens_between(start, to_parse.cursor()).to_string(),
+                config_file: None,
+            },
+        );
+        if parse_completely && !to_parse.is_empty() {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("found unexpected input within the directive {ident_str}"),
+            ));
+        }
+        if input.is_empty() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The tokens from `start` up to, but not including, `end`.
+fn tokens_between(mut start: Cursor, end: Cursor) -> proc_macro2::TokenStream {
+    let mut tokens = proc_macro2::TokenStream::new();
+    while start != end {
+        match start.token_tree() {
+            Some((tt, next)) => {
+                tokens.extend(std::iter::once(tt));
+                start = next;
             }
+            None => break,
         }
-        Ok(config)
     }
+    tokens
 }
 
 impl IncludeCppConfig {
@@ -275,6 +701,12 @@ impl IncludeCppConfig {
         &self.pod_requests
     }
 
+    /// The C++ standard, such as `c++23`, with which to parse the headers
+    /// for this `include_cpp!`, if it differs from the default.
+    pub fn get_cpp_std(&self) -> Option<&str> {
+        self.cpp_std.as_deref()
+    }
+
     pub fn get_mod_name(&self) -> Ident {
         self.mod_name
             .as_ref()
@@ -288,6 +720,128 @@ impl IncludeCppConfig {
         self.exclude_utilities
     }
 
+    /// Whether to give each polymorphic type a `type_name()` method
+    /// reporting its most-derived type, found using C++ RTTI.
+    pub fn rtti_type_names(&self) -> bool {
+        self.rtti_type_names
+    }
+
+    /// Whether to give each polymorphic type an `object_identity()` method
+    /// returning the address of its most-derived object.
+    pub fn object_identity(&self) -> bool {
+        self.object_identity
+    }
+
+    /// Whether functions returning `std::string` by value should return
+    /// an `impl New` rather than a `UniquePtr`.
+    pub fn strings_by_value(&self) -> bool {
+        self.strings_by_value
+    }
+
+    /// Whether to generate a `meta` mod mapping between C++ names and
+    /// Rust paths at runtime.
+    pub fn name_table(&self) -> bool {
+        self.name_table
+    }
+
+    /// Whether a `generate_pod!` type which can't be POD should be
+    /// reported with the chain of members responsible.
+    pub fn strict_pod(&self) -> bool {
+        self.strict_pod
+    }
+
+    /// Whether free functions in anonymous namespaces should be called
+    /// through C++ wrappers, as requested by `internal_linkage_shims!`.
+    pub fn internal_linkage_shims(&self) -> bool {
+        self.internal_linkage_shims
+    }
+
+    /// Whether to parse the headers with MSVC extensions and look for
+    /// `__declspec(property)` and `__declspec(uuid)`, as requested by
+    /// `msvc_extensions!`.
+    pub fn msvc_extensions(&self) -> bool {
+        self.msvc_extensions
+    }
+
+    /// Whether functions taking or returning small POD types by value
+    /// should be called through plain `extern "C"` functions, as requested
+    /// by `small_pods_in_registers!`.
+    pub fn small_pods_in_registers(&self) -> bool {
+        self.small_pods_in_registers
+    }
+
+    /// Whether `std::ostream&` parameters should be presented to Rust as
+    /// `&mut dyn std::io::Write`, as requested by `ostream_as_writer!`.
+    pub fn ostream_as_writer(&self) -> bool {
+        self.ostream_as_writer
+    }
+
+    /// Whether generated functions should report failures which would
+    /// otherwise panic through their return types, as requested by
+    /// `panic_free!`.
+    pub fn panic_free(&self) -> bool {
+        self.panic_free
+    }
+
+    /// How to treat items which can't be generated, as given by
+    /// `conversion_errors!`.
+    pub fn conversion_error_policy(&self) -> ConversionErrorPolicy {
+        self.conversion_error_policy
+    }
+
+    /// Whether this item was explicitly requested by `generate!`,
+    /// `generate_pod!` or `generate_ns!`.
+    pub fn is_explicitly_requested(&self, cpp_name: &str) -> bool {
+        self.pod_requests.iter().any(|item| item == cpp_name)
+            || match &self.allowlist {
+                Allowlist::Specific(items) => items.iter().any(|entry| entry.matches(cpp_name)),
+                _ => false,
+            }
+    }
+
+    /// Whether this `generate_pod!` type may instead be generated as
+    /// non-POD if it can't be POD, as allowed by `allow_pod_downgrade!`.
+    pub fn is_pod_downgrade_allowed(&self, cpp_name: &str) -> bool {
+        self.pod_downgrades_allowed.iter().any(|ty| ty == cpp_name)
+    }
+
+    /// Whether this type may only be used from one thread, as declared
+    /// by `thread_affine!` or `thread_handle!`.
+    pub fn is_thread_affine(&self, cpp_name: &str) -> bool {
+        self.thread_affine_types.iter().any(|ty| ty == cpp_name) || self.has_thread_handle(cpp_name)
+    }
+
+    /// Whether to generate a `Send` handle to this type, whose methods
+    /// run on the type's own thread, as declared by `thread_handle!`.
+    pub fn has_thread_handle(&self, cpp_name: &str) -> bool {
+        self.thread_handle_types.iter().any(|ty| ty == cpp_name)
+    }
+
+    /// Whether this type is a message class generated by protoc, as
+    /// declared by `protobuf_message!`.
+    pub fn is_protobuf_message(&self, cpp_name: &str) -> bool {
+        self.protobuf_messages.iter().any(|ty| ty == cpp_name)
+    }
+
+    /// Whether this POD type should be given to cxx as a shared struct,
+    /// as requested by `shared_struct!`.
+    pub fn is_shared_struct(&self, cpp_name: &str) -> bool {
+        self.shared_structs.iter().any(|ty| ty == cpp_name)
+    }
+
+    /// Whether this RAII type should be given a Rust guard wrapper which
+    /// borrows whatever its constructor was given, as requested by
+    /// `scope_guard!`.
+    pub fn is_scope_guard(&self, cpp_name: &str) -> bool {
+        self.scope_guards.iter().any(|ty| ty == cpp_name)
+    }
+
+    /// Whether this POD type should implement `Default` using C++
+    /// value-initialization, as requested by `pod_default!`.
+    pub fn has_pod_default(&self, cpp_name: &str) -> bool {
+        self.pod_defaults.iter().any(|ty| ty == cpp_name)
+    }
+
     /// Items which the user has explicitly asked us to generate;
     /// we should raise an error if we weren't able to do so.
     pub fn must_generate_list(&self) -> Box<dyn Iterator<Item = String> + '_> {
@@ -297,7 +851,7 @@ impl IncludeCppConfig {
                     .iter()
                     .filter_map(|i| match i {
                         AllowlistEntry::Item(i) => Some(i),
-                        AllowlistEntry::Namespace(_) => None,
+                        AllowlistEntry::Namespace(..) => None,
                     })
                     .chain(self.pod_requests.iter())
                     .cloned(),
@@ -307,6 +861,26 @@ impl IncludeCppConfig {
         }
     }
 
+    /// The span of the first directive which named this item. For
+    /// directives read from a config file, this is the span of the
+    /// `config_file!` directive.
+    pub fn get_directive_span(&self, item: &str) -> Option<Span> {
+        self.directive_spans.spans.get(item).copied()
+    }
+
+    pub(crate) fn record_directive_span(&mut self, item: &str, span: Span) {
+        let span = self.directive_spans.config_file.unwrap_or(span);
+        self.directive_spans
+            .spans
+            .entry(item.to_string())
+            .or_insert(span);
+    }
+
+    /// Whether `generate_all!` asked for everything to be generated.
+    pub fn is_allowlist_all(&self) -> bool {
+        matches!(self.allowlist, Allowlist::All)
+    }
+
     /// The allowlist of items to be passed into bindgen, if any.
     pub fn bindgen_allowlist(&self) -> Option<Box<dyn Iterator<Item = String> + '_>> {
         match &self.allowlist {
@@ -316,6 +890,7 @@ impl IncludeCppConfig {
                     .iter()
                     .map(AllowlistEntry::to_bindgen_item)
                     .chain(self.pod_requests.iter().cloned())
+                    .chain(self.covariant_returns.iter().map(|(_, ty)| ty.clone()))
                     .chain(self.active_utilities())
                     .chain(self.subclasses.iter().flat_map(|sc| {
                         [
@@ -369,13 +944,30 @@ impl IncludeCppConfig {
             || match &self.allowlist {
                 Allowlist::Unspecified(_) => panic!("Eek no allowlist yet"),
                 Allowlist::All => true,
-                Allowlist::Specific(items) => items.iter().any(|entry| match entry {
-                    AllowlistEntry::Item(i) => i == cpp_name,
-                    AllowlistEntry::Namespace(ns) => cpp_name.starts_with(ns),
-                }),
+                Allowlist::Specific(items) => items.iter().any(|entry| entry.matches(cpp_name)),
             }
     }
 
+    /// Whether this type is within a namespace for which `generate_ns!`
+    /// asked that types be made POD wherever possible.
+    pub fn is_pod_if_possible(&self, cpp_name: &str) -> bool {
+        match &self.allowlist {
+            Allowlist::Specific(items) => items.iter().any(|entry| {
+                matches!(
+                    entry,
+                    AllowlistEntry::Namespace(
+                        _,
+                        NamespaceOptions {
+                            pod: NamespacePodPolicy::Auto,
+                            ..
+                        }
+                    )
+                ) && entry.matches(cpp_name)
+            }),
+            _ => false,
+        }
+    }
+
     pub fn is_on_blocklist(&self, cpp_name: &str) -> bool {
         self.blocklist.contains(&cpp_name.to_string())
     }
@@ -384,6 +976,356 @@ impl IncludeCppConfig {
         self.constructor_blocklist.contains(&cpp_name.to_string())
     }
 
+    /// Whether the given method (identified as `ns::Type::method`) should
+    /// be given a `&self` receiver even though it's not `const` in C++.
+    pub fn is_treated_as_const(&self, cpp_method_name: &str) -> bool {
+        self.treat_as_const.contains(&cpp_method_name.to_string())
+    }
+
+    /// Whether the C++ wrapper for the given function or method (identified
+    /// as e.g. `ns::Type::method`) should be forcibly inlined.
+    pub fn is_force_inline(&self, cpp_name: &str) -> bool {
+        self.force_inline.contains(&cpp_name.to_string())
+    }
+
+    /// All the functions and methods named by `force_inline!` directives.
+    pub fn get_force_inline_functions(&self) -> impl Iterator<Item = &str> {
+        self.force_inline.iter().map(String::as_str)
+    }
+
+    /// Whether the given function or method (identified as e.g.
+    /// `ns::Type::method`) should return a copy of the string to which it
+    /// returns a reference, as requested by `copy_string_return!`.
+    pub fn is_copy_string_return(&self, cpp_name: &str) -> bool {
+        self.copy_string_returns.contains(&cpp_name.to_string())
+    }
+
+    /// Whether an async wrapper should be generated for this function.
+    pub fn is_async_function(&self, cpp_name: &str) -> bool {
+        self.async_functions.contains(&cpp_name.to_string())
+    }
+
+    /// Rust names requested for each overload of the given function or
+    /// method, in declaration order. Empty if the user didn't specify any.
+    pub fn get_overload_names(&self, cpp_name: &str) -> &[String] {
+        self.overload_names
+            .iter()
+            .find(|(name, _)| name == cpp_name)
+            .map(|(_, names)| names.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The name of the flat `extern "C"` symbol which should be generated
+    /// for the given function or method, if requested by `export_c!`.
+    pub fn get_c_export_name(&self, cpp_name: &str) -> Option<&str> {
+        self.c_exports
+            .iter()
+            .find(|(name, _)| name == cpp_name)
+            .map(|(_, symbol)| symbol.as_str())
+    }
+
+    /// The index of the argument, not counting any receiver, from which the
+    /// return value of the given function or method borrows, if specified
+    /// by `returns_lifetime_of_arg!`.
+    pub fn get_returns_lifetime_of_arg(&self, cpp_name: &str) -> Option<usize> {
+        self.returns_lifetime_of_arg
+            .iter()
+            .find(|(name, _)| name == cpp_name)
+            .map(|(_, arg)| *arg)
+    }
+
+    /// The index of the first of the pair of `(begin, end)` iterator
+    /// arguments, not counting any receiver, which `iterator_range!` asks
+    /// to present to Rust as a single slice.
+    pub fn get_iterator_range(&self, cpp_name: &str) -> Option<usize> {
+        self.iterator_ranges
+            .iter()
+            .find(|(name, _)| name == cpp_name)
+            .map(|(_, begin)| *begin)
+    }
+
+    /// All the functions named by `iterator_range!` directives.
+    pub fn get_iterator_range_functions(&self) -> impl Iterator<Item = &str> {
+        self.iterator_ranges.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// If `getters!` applies to the given method of the given type, the
+    /// Rust name it should have: the method name with the prefix stripped,
+    /// in snake case. `GetWidth` would become `width`.
+    pub fn get_getter_name(&self, ty: &str, method: &str) -> Option<String> {
+        let rest = self
+            .getters
+            .iter()
+            .filter(|getters| getters.ty == ty)
+            .find_map(|getters| method.strip_prefix(getters.strip_prefix.as_str()))?;
+        // Don't turn `Getaway` into `away`.
+        if !rest.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return None;
+        }
+        Some(to_snake_case(rest))
+    }
+
+    /// Whether the given type has been declared to have an `operator bool`.
+    pub fn has_operator_bool(&self, cpp_name: &str) -> bool {
+        self.operator_bool_types.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the given type is a singleton, whose static methods return
+    /// references to an instance which lives forever.
+    pub fn is_singleton(&self, cpp_name: &str) -> bool {
+        self.singletons.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the given static method is a factory which should be
+    /// presented as a constructor, as requested by `constructor_from!`.
+    pub fn is_constructor_factory(&self, cpp_name: &str) -> bool {
+        self.constructor_factories.contains(&cpp_name.to_string())
+    }
+
+    /// If the given type is listed in `no_drop!`, what to do instead of
+    /// destroying it.
+    pub fn get_drop_behaviour(&self, cpp_name: &str) -> Option<DropBehaviour> {
+        self.no_drop
+            .iter()
+            .find(|(ty, _)| ty == cpp_name)
+            .map(|(_, behaviour)| *behaviour)
+    }
+
+    /// Whether the given type is a view into some other object, such as
+    /// an iterator or span, which mustn't outlive it.
+    pub fn is_view_type(&self, cpp_name: &str) -> bool {
+        self.view_types.contains(&cpp_name.to_string())
+    }
+
+    /// Whether we should generate a view of the given type with
+    /// property-style accessors for its getter and setter pairs.
+    pub fn has_property_accessors(&self, cpp_name: &str) -> bool {
+        self.property_accessor_types.contains(&cpp_name.to_string())
+    }
+
+    /// Whether we should generate a trait, suitable for mocking, containing
+    /// the methods of the given type.
+    pub fn is_mockable(&self, cpp_name: &str) -> bool {
+        self.mockables.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the given typedef should become a distinct Rust newtype,
+    /// rather than an alias of the type to which it refers. This includes
+    /// opaque handles.
+    pub fn is_newtype(&self, cpp_name: &str) -> bool {
+        self.newtypes.contains(&cpp_name.to_string()) || self.is_opaque_handle(cpp_name)
+    }
+
+    /// Whether the given typedef should become a newtype which doesn't
+    /// expose its underlying integer.
+    pub fn is_opaque_handle(&self, cpp_name: &str) -> bool {
+        self.opaque_handles.contains(&cpp_name.to_string())
+    }
+
+    pub fn get_conversion_operators<'a>(
+        &'a self,
+        cpp_name: &'a str,
+    ) -> impl Iterator<Item = &'a ConversionOperator> + 'a {
+        self.conversion_operators
+            .iter()
+            .filter(move |conversion| conversion.ty == cpp_name)
+    }
+
+    /// If the given type was listed in `error_type!`, the name of the
+    /// C++ method which returns its message.
+    pub fn get_error_message_method(&self, cpp_name: &str) -> Option<&str> {
+        self.error_types
+            .iter()
+            .find(|et| et.ty == cpp_name)
+            .map(|et| et.message.as_str())
+    }
+
+    pub fn get_tagged_union(&self, cpp_name: &str) -> Option<&TaggedUnion> {
+        self.tagged_unions.iter().find(|tu| tu.ty == cpp_name)
+    }
+
+    pub fn get_complete_type(&self, cpp_name: &str) -> Option<&CompleteType> {
+        self.complete_types.iter().find(|ct| ct.ty == cpp_name)
+    }
+
+    pub fn get_complete_types(&self) -> impl Iterator<Item = &CompleteType> {
+        self.complete_types.iter()
+    }
+
+    /// Items which must not appear in the generated bindings.
+    pub fn get_asserted_not_generated(&self) -> impl Iterator<Item = &str> {
+        self.asserted_not_generated.iter().map(String::as_str)
+    }
+
+    pub fn get_completion_futures(&self) -> impl Iterator<Item = &CompletionFuture> {
+        self.completion_futures.iter()
+    }
+
+    pub fn get_context_callbacks(&self) -> impl Iterator<Item = &ContextCallback> {
+        self.context_callbacks.iter()
+    }
+
+    pub fn get_member_fn_fields(&self) -> impl Iterator<Item = &MemberFnField> {
+        self.member_fn_fields.iter()
+    }
+
+    /// Whether this field of this struct was named in `member_fn_field!`.
+    pub fn is_member_fn_field(&self, table: &str, field: &str) -> bool {
+        self.member_fn_fields
+            .iter()
+            .any(|mff| mff.table == table && mff.field == field)
+    }
+
+    pub fn get_init_function(&self) -> Option<&InitFunction> {
+        self.init_function.as_ref()
+    }
+
+    /// If this instantiation of a C++ template corresponds to a generic
+    /// Rust type listed in `extern_cpp_type!`, that Rust type.
+    pub fn get_extern_instantiation(&self, cpp_definition: &str) -> Option<Type> {
+        self.generic_externs
+            .iter()
+            .find_map(|ge| ge.rust_type_for(cpp_definition))
+    }
+
+    pub fn get_rust_equivalents(&self) -> impl Iterator<Item = &RustEquivalent> {
+        self.rust_equivalents.iter()
+    }
+
+    /// The old names under which `deprecated_alias!` asks for an item to
+    /// remain available. Items are named as in `AUTOCXX_RENAMED_ITEMS`,
+    /// i.e. by namespace, then type for methods, then Rust name.
+    pub fn get_deprecated_aliases<'a>(
+        &'a self,
+        rust_name: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.deprecated_aliases
+            .iter()
+            .filter(move |(name, _)| name == rust_name)
+            .map(|(_, alias)| alias.as_str())
+    }
+
+    /// All `deprecated_alias!` directives, as pairs of item and old name.
+    pub fn get_all_deprecated_aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.deprecated_aliases
+            .iter()
+            .map(|(name, alias)| (name.as_str(), alias.as_str()))
+    }
+
+    /// The Rust name chosen by `name_constructor!` for the constructor
+    /// with this signature, e.g. `ns::Foo(int, const std::string&)`.
+    /// Whitespace is ignored when comparing signatures.
+    pub fn get_constructor_name(&self, signature: &str) -> Option<&str> {
+        let strip_spaces = |s: &str| s.split_whitespace().collect::<String>();
+        let wanted = strip_spaces(signature);
+        self.constructor_names
+            .iter()
+            .find(|(sig, _)| strip_spaces(sig) == wanted)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// All `name_constructor!` directives, as pairs of signature and
+    /// Rust name.
+    pub fn get_constructor_names(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.constructor_names
+            .iter()
+            .map(|(sig, name)| (sig.as_str(), name.as_str()))
+    }
+
+    /// All `generate_member!` directives, as pairs of type and member
+    /// function.
+    pub fn get_allowed_members(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.member_allowlist
+            .iter()
+            .map(|(ty, member)| (ty.as_str(), member.as_str()))
+    }
+
+    /// Whether the given member function of the given type should be
+    /// generated. If `generate_member!` named any members of the type, only
+    /// those are; otherwise they all are.
+    pub fn is_member_allowed(&self, cpp_type: &str, member: &str) -> bool {
+        let mut listed = self
+            .member_allowlist
+            .iter()
+            .filter(|(ty, _)| ty == cpp_type)
+            .peekable();
+        listed.peek().is_none() || listed.any(|(_, m)| m == member)
+    }
+
+    /// All `covariant_return!` directives, as pairs of virtual method and
+    /// the type its overrides should return.
+    pub fn get_covariant_returns(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.covariant_returns
+            .iter()
+            .map(|(method, ty)| (method.as_str(), ty.as_str()))
+    }
+
+    /// The type named by `covariant_return!` for subclass overrides of this
+    /// virtual method, e.g. `ns::Base::clone`, to return in place of the
+    /// superclass's own return type.
+    pub fn get_covariant_return(&self, method: &str) -> Option<&str> {
+        self.covariant_returns
+            .iter()
+            .find(|(m, _)| m == method)
+            .map(|(_, ty)| ty.as_str())
+    }
+
+    /// If the given type was listed in `intrusive_ptr!`, the names of its
+    /// methods which increment and decrement its reference count.
+    pub fn get_intrusive_ptr_methods(&self, cpp_name: &str) -> Option<(&str, &str)> {
+        self.intrusive_ptrs
+            .iter()
+            .find(|ip| ip.ty == cpp_name)
+            .map(|ip| (ip.add_ref.as_str(), ip.release.as_str()))
+    }
+
+    /// Header-only libraries whose out-of-line definitions we should
+    /// compile into our generated C++, by including each header with the
+    /// given macros defined. See `header_implementation!`.
+    pub fn get_header_implementations(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.header_implementations
+            .iter()
+            .map(|(header, defines)| (header.as_str(), defines.as_slice()))
+    }
+
+    /// Types for which we should generate a way to make many calls to the
+    /// given methods in a single call across the FFI boundary. See
+    /// `batch_methods!`.
+    pub fn get_batch_methods(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.batch_methods
+            .iter()
+            .map(|(ty, methods)| (ty.as_str(), methods.as_slice()))
+    }
+
+    /// If `outlives!` says that objects of the given type must be
+    /// destroyed before some other object, the type of that other object.
+    pub fn get_outlives_dependency(&self, cpp_name: &str) -> Option<&str> {
+        self.outlives
+            .iter()
+            .find(|(dependent, _)| dependent == cpp_name)
+            .map(|(_, dependency)| dependency.as_str())
+    }
+
+    /// C++20 modules which should be imported, from `import_module!`
+    /// directives.
+    pub fn get_imported_modules(&self) -> impl Iterator<Item = &String> {
+        self.imported_modules.iter()
+    }
+
+    /// Every directive applied to this `include_cpp!`, including those
+    /// read from config files, in order.
+    pub fn get_applied_directives(&self) -> &[AppliedDirective] {
+        &self.applied_directives
+    }
+
+    /// Files from which directives were loaded using `config_file!`,
+    /// which must be watched for changes.
+    pub fn get_config_files(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.config_files
+            .iter()
+            .map(|path| resolve_config_file_path(path))
+    }
+
     pub fn get_blocklist(&self) -> impl Iterator<Item = &String> {
         self.blocklist.iter()
     }
@@ -392,6 +1334,17 @@ impl IncludeCppConfig {
         self.opaquelist.iter()
     }
 
+    /// Regexes of items which bindgen itself should never generate.
+    pub fn get_bindgen_blocklist(&self) -> impl Iterator<Item = &String> {
+        self.bindgen_blocklist.iter()
+    }
+
+    /// Additional arguments to pass to clang when parsing the headers
+    /// for this mod.
+    pub fn get_clang_args(&self) -> impl Iterator<Item = &String> {
+        self.clang_args.iter()
+    }
+
     fn is_concrete_type(&self, cpp_name: &str) -> bool {
         self.concretes.0.values().any(|val| *val == cpp_name)
     }
@@ -438,6 +1391,16 @@ impl IncludeCppConfig {
         uniquified.into_iter()
     }
 
+    /// What to do if the superclass of the given `subclass!` has a
+    /// public, non-virtual destructor.
+    pub fn get_non_virtual_destructor_policy(&self, subclass: &str) -> NonVirtualDestructor {
+        self.subclasses
+            .iter()
+            .find(|sc| sc.subclass == subclass)
+            .map(|sc| sc.non_virtual_destructor)
+            .unwrap_or_default()
+    }
+
     pub fn is_subclass_holder(&self, id: &str) -> bool {
         self.subclasses
             .iter()
@@ -499,6 +1462,51 @@ impl ToTokens for IncludeCppConfig {
     }
 }
 
+/// Config files named by `config_file!` are found relative to the
+/// directory containing the crate's `Cargo.toml`, or if we're not being
+/// built by cargo, relative to the current directory.
+pub(crate) fn resolve_config_file_path(path: &str) -> PathBuf {
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) => Path::new(&manifest_dir).join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Convert a C++ `CamelCase` name to `snake_case`. A new word starts at
+/// each capital which follows a lowercase letter or digit, or which is
+/// followed by one but ends an acronym, so that `HTTPServer` becomes
+/// `http_server`. An underscore is appended if the result would be a Rust
+/// keyword.
+pub fn to_snake_case(cpp_name: &str) -> String {
+    let chars: Vec<char> = cpp_name.chars().collect();
+    let mut name = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                name.push('_');
+            }
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    if syn::parse_str::<syn::Ident>(&name).is_err() {
+        // e.g. `Type`
+        name.push('_');
+    }
+    name
+}
+
+/// The name of the header which we generate to `import` a C++20 module,
+/// so that the module can be treated just like any other `#include` by
+/// bindgen, cxx and our own generated C++.
+pub fn module_import_header_name(module: &str) -> String {
+    format!("autocxx_module_{}.h", module.replace(['.', ':'], "_"))
+}
+
 #[cfg(test)]
 mod parse_tests {
     use crate::config::UnsafePolicy;
@@ -19,12 +19,16 @@ use quote::{quote, ToTokens};
 use syn::parse::ParseStream;
 
 use crate::config::AllowlistErr;
+use crate::config::{module_import_header_name, parse_directives, resolve_config_file_path};
 
 #[cfg(feature = "reproduction_case")]
 use crate::config::Allowlist;
 
 use crate::directive_names::{EXTERN_RUST_FUN, EXTERN_RUST_TYPE, SUBCLASS};
-use crate::{AllowlistEntry, IncludeCppConfig};
+use crate::{
+    AllowlistEntry, ConversionErrorPolicy, DropBehaviour, IncludeCppConfig, NamespaceOptions,
+    NamespacePodPolicy, NonVirtualDestructor,
+};
 use crate::{ParseResult, RustFun, RustPath};
 
 pub(crate) struct DirectivesMap {
@@ -41,7 +45,9 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
         let mut need_exclamation: HashMap<String, Box<dyn Directive>> = HashMap::new();
         need_exclamation.insert("generate".into(), Box::new(Generate(false)));
         need_exclamation.insert("generate_pod".into(), Box::new(Generate(true)));
+        need_exclamation.insert("generate_async".into(), Box::new(GenerateAsync));
         need_exclamation.insert("generate_ns".into(), Box::new(GenerateNs));
+        need_exclamation.insert("generate_member".into(), Box::new(GenerateMember));
         need_exclamation.insert("generate_all".into(), Box::new(GenerateAll));
         need_exclamation.insert("safety".into(), Box::new(Safety));
         need_exclamation.insert(
@@ -65,6 +71,20 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.opaquelist,
             )),
         );
+        need_exclamation.insert(
+            "bindgen_blocklist".into(),
+            Box::new(StringList(
+                |config| &mut config.bindgen_blocklist,
+                |config| &config.bindgen_blocklist,
+            )),
+        );
+        need_exclamation.insert(
+            "clang_arg".into(),
+            Box::new(StringList(
+                |config| &mut config.clang_args,
+                |config| &config.clang_args,
+            )),
+        );
         need_exclamation.insert(
             "block_constructors".into(),
             Box::new(StringList(
@@ -72,6 +92,141 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.constructor_blocklist,
             )),
         );
+        need_exclamation.insert(
+            "treat_as_const".into(),
+            Box::new(StringList(
+                |config| &mut config.treat_as_const,
+                |config| &config.treat_as_const,
+            )),
+        );
+        need_exclamation.insert(
+            "force_inline".into(),
+            Box::new(StringList(
+                |config| &mut config.force_inline,
+                |config| &config.force_inline,
+            )),
+        );
+        need_exclamation.insert(
+            "copy_string_return".into(),
+            Box::new(StringList(
+                |config| &mut config.copy_string_returns,
+                |config| &config.copy_string_returns,
+            )),
+        );
+        need_exclamation.insert(
+            "operator_bool".into(),
+            Box::new(StringList(
+                |config| &mut config.operator_bool_types,
+                |config| &config.operator_bool_types,
+            )),
+        );
+        need_exclamation.insert(
+            "singleton".into(),
+            Box::new(StringList(
+                |config| &mut config.singletons,
+                |config| &config.singletons,
+            )),
+        );
+        need_exclamation.insert(
+            "constructor_from".into(),
+            Box::new(StringList(
+                |config| &mut config.constructor_factories,
+                |config| &config.constructor_factories,
+            )),
+        );
+        need_exclamation.insert("import_module".into(), Box::new(ImportModule));
+        need_exclamation.insert("config_file".into(), Box::new(ConfigFile));
+        need_exclamation.insert(
+            "view_type".into(),
+            Box::new(StringList(
+                |config| &mut config.view_types,
+                |config| &config.view_types,
+            )),
+        );
+        need_exclamation.insert(
+            "mockable".into(),
+            Box::new(StringList(
+                |config| &mut config.mockables,
+                |config| &config.mockables,
+            )),
+        );
+        need_exclamation.insert(
+            "property_accessors".into(),
+            Box::new(StringList(
+                |config| &mut config.property_accessor_types,
+                |config| &config.property_accessor_types,
+            )),
+        );
+        need_exclamation.insert(
+            "newtype".into(),
+            Box::new(StringList(
+                |config| &mut config.newtypes,
+                |config| &config.newtypes,
+            )),
+        );
+        need_exclamation.insert(
+            "opaque_handle".into(),
+            Box::new(StringList(
+                |config| &mut config.opaque_handles,
+                |config| &config.opaque_handles,
+            )),
+        );
+        need_exclamation.insert(
+            "assert_not_generated".into(),
+            Box::new(StringList(
+                |config| &mut config.asserted_not_generated,
+                |config| &config.asserted_not_generated,
+            )),
+        );
+        need_exclamation.insert(
+            "allow_pod_downgrade".into(),
+            Box::new(StringList(
+                |config| &mut config.pod_downgrades_allowed,
+                |config| &config.pod_downgrades_allowed,
+            )),
+        );
+        need_exclamation.insert(
+            "thread_affine".into(),
+            Box::new(StringList(
+                |config| &mut config.thread_affine_types,
+                |config| &config.thread_affine_types,
+            )),
+        );
+        need_exclamation.insert(
+            "thread_handle".into(),
+            Box::new(StringList(
+                |config| &mut config.thread_handle_types,
+                |config| &config.thread_handle_types,
+            )),
+        );
+        need_exclamation.insert(
+            "protobuf_message".into(),
+            Box::new(StringList(
+                |config| &mut config.protobuf_messages,
+                |config| &config.protobuf_messages,
+            )),
+        );
+        need_exclamation.insert(
+            "shared_struct".into(),
+            Box::new(StringList(
+                |config| &mut config.shared_structs,
+                |config| &config.shared_structs,
+            )),
+        );
+        need_exclamation.insert(
+            "scope_guard".into(),
+            Box::new(StringList(
+                |config| &mut config.scope_guards,
+                |config| &config.scope_guards,
+            )),
+        );
+        need_exclamation.insert(
+            "pod_default".into(),
+            Box::new(StringList(
+                |config| &mut config.pod_defaults,
+                |config| &config.pod_defaults,
+            )),
+        );
         need_exclamation.insert(
             "instantiable".into(),
             Box::new(StringList(
@@ -100,7 +255,87 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.exclude_utilities,
             )),
         );
+        need_exclamation.insert(
+            "rtti_type_names".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.rtti_type_names,
+                |config| &config.rtti_type_names,
+            )),
+        );
+        need_exclamation.insert(
+            "object_identity".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.object_identity,
+                |config| &config.object_identity,
+            )),
+        );
+        need_exclamation.insert(
+            "name_table".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.name_table,
+                |config| &config.name_table,
+            )),
+        );
+        need_exclamation.insert(
+            "internal_linkage_shims".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.internal_linkage_shims,
+                |config| &config.internal_linkage_shims,
+            )),
+        );
+        need_exclamation.insert(
+            "strict_pod".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.strict_pod,
+                |config| &config.strict_pod,
+            )),
+        );
+        need_exclamation.insert(
+            "strings_by_value".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.strings_by_value,
+                |config| &config.strings_by_value,
+            )),
+        );
+        need_exclamation.insert(
+            "msvc_extensions".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.msvc_extensions,
+                |config| &config.msvc_extensions,
+            )),
+        );
+        need_exclamation.insert(
+            "small_pods_in_registers".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.small_pods_in_registers,
+                |config| &config.small_pods_in_registers,
+            )),
+        );
+        need_exclamation.insert(
+            "ostream_as_writer".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.ostream_as_writer,
+                |config| &config.ostream_as_writer,
+            )),
+        );
+        need_exclamation.insert(
+            "panic_free".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.panic_free,
+                |config| &config.panic_free,
+            )),
+        );
+        need_exclamation.insert("conversion_errors".into(), Box::new(ConversionErrors));
+        need_exclamation.insert("covariant_return".into(), Box::new(CovariantReturn));
+        need_exclamation.insert("intrusive_ptr".into(), Box::new(IntrusivePtrDirective));
+        need_exclamation.insert(
+            "header_implementation".into(),
+            Box::new(HeaderImplementation),
+        );
+        need_exclamation.insert("batch_methods".into(), Box::new(BatchMethods));
+        need_exclamation.insert("outlives".into(), Box::new(Outlives));
         need_exclamation.insert("name".into(), Box::new(ModName));
+        need_exclamation.insert("cpp_std".into(), Box::new(CppStd));
         need_exclamation.insert("concrete".into(), Box::new(Concrete));
         need_exclamation.insert("rust_type".into(), Box::new(RustType { output: false }));
         need_exclamation.insert(EXTERN_RUST_TYPE.into(), Box::new(RustType { output: true }));
@@ -114,6 +349,35 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
             "extern_cpp_opaque_type".into(),
             Box::new(ExternCppType { opaque: true }),
         );
+        need_exclamation.insert("tagged_union".into(), Box::new(TaggedUnion));
+        need_exclamation.insert("overload_names".into(), Box::new(OverloadNames));
+        need_exclamation.insert("export_c".into(), Box::new(ExportC));
+        need_exclamation.insert("getters".into(), Box::new(Getters));
+        need_exclamation.insert(
+            "returns_lifetime_of_arg".into(),
+            Box::new(ReturnsLifetimeOfArg),
+        );
+        need_exclamation.insert("iterator_range".into(), Box::new(IteratorRange));
+        need_exclamation.insert("error_type".into(), Box::new(ErrorType));
+        need_exclamation.insert("no_drop".into(), Box::new(NoDrop));
+        need_exclamation.insert(
+            "conversion_operator".into(),
+            Box::new(ConversionOperatorDirective),
+        );
+        need_exclamation.insert("complete_type".into(), Box::new(CompleteTypeDirective));
+        need_exclamation.insert(
+            "completion_future".into(),
+            Box::new(CompletionFutureDirective),
+        );
+        need_exclamation.insert(
+            "context_callback".into(),
+            Box::new(ContextCallbackDirective),
+        );
+        need_exclamation.insert("member_fn_field".into(), Box::new(MemberFnFieldDirective));
+        need_exclamation.insert("init_function".into(), Box::new(InitFunctionDirective));
+        need_exclamation.insert("rust_equivalent".into(), Box::new(RustEquivalentDirective));
+        need_exclamation.insert("deprecated_alias".into(), Box::new(DeprecatedAlias));
+        need_exclamation.insert("name_constructor".into(), Box::new(NameConstructor));
 
         DirectivesMap {
             need_hexathorpe,
@@ -157,7 +421,18 @@ impl Directive for Inclusion {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
-        Box::new(config.inclusions.iter().map(|val| quote! { #val }))
+        let module_headers: Vec<_> = config
+            .imported_modules
+            .iter()
+            .map(|module| module_import_header_name(module))
+            .collect();
+        Box::new(
+            config
+                .inclusions
+                .iter()
+                .filter(move |val| !module_headers.contains(val))
+                .map(|val| quote! { #val }),
+        )
     }
 }
 
@@ -203,6 +478,77 @@ impl Directive for Generate {
     }
 }
 
+/// Directive for `generate_async!`, which is like `generate!` but
+/// also asks for an async wrapper.
+struct GenerateAsync;
+
+impl Directive for GenerateAsync {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        span: &Span,
+    ) -> ParseResult<()> {
+        let generate: syn::LitStr = args.parse()?;
+        config
+            .allowlist
+            .push(AllowlistEntry::Item(generate.value()))
+            .map_err(|e| allowlist_err_to_syn_err(e, span))?;
+        config.async_functions.push(generate.value());
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.async_functions.iter().map(|s| quote! { #s }))
+    }
+}
+
+/// Directive for `generate_member!`, which generates a type with only
+/// the listed member functions.
+struct GenerateMember;
+
+impl Directive for GenerateMember {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        span: &Span,
+    ) -> ParseResult<()> {
+        let member: syn::LitStr = args.parse()?;
+        let value = member.value();
+        let Some((ty, member_name)) = value.rsplit_once("::") else {
+            return Err(syn::Error::new(
+                member.span(),
+                "expected a member function such as \"ns::Foo::bar\"",
+            ));
+        };
+        config
+            .allowlist
+            .push(AllowlistEntry::Item(ty.to_string()))
+            .map_err(|e| allowlist_err_to_syn_err(e, span))?;
+        config.record_directive_span(ty, *span);
+        config
+            .member_allowlist
+            .push((ty.to_string(), member_name.to_string()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.member_allowlist.iter().map(|(ty, member)| {
+            let member = format!("{ty}::{member}");
+            quote! { #member }
+        }))
+    }
+}
+
 struct GenerateNs;
 
 impl Directive for GenerateNs {
@@ -213,9 +559,35 @@ impl Directive for GenerateNs {
         span: &Span,
     ) -> ParseResult<()> {
         let generate: syn::LitStr = args.parse()?;
+        let mut options = NamespaceOptions::default();
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let option: syn::Ident = args.parse()?;
+            if option == "max_depth" {
+                args.parse::<syn::token::Eq>()?;
+                let max_depth: syn::LitInt = args.parse()?;
+                options.max_depth = Some(max_depth.base10_parse()?);
+            } else if option == "skip_detail_namespaces" {
+                options.skip_detail_namespaces = true;
+            } else if option == "pod" {
+                args.parse::<syn::token::Eq>()?;
+                let policy: syn::Ident = args.parse()?;
+                options.pod = if policy == "auto" {
+                    NamespacePodPolicy::Auto
+                } else if policy == "never" {
+                    NamespacePodPolicy::Never
+                } else {
+                    return Err(syn::Error::new(policy.span(), "expected auto or never"));
+                };
+            } else {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "expected max_depth, skip_detail_namespaces or pod",
+                ));
+            }
+        }
         config
             .allowlist
-            .push(AllowlistEntry::Namespace(generate.value()))
+            .push(AllowlistEntry::Namespace(generate.value(), options))
             .map_err(|e| allowlist_err_to_syn_err(e, span))?;
         Ok(())
     }
@@ -230,10 +602,21 @@ impl Directive for GenerateNs {
                 items
                     .iter()
                     .flat_map(|i| match i {
-                        AllowlistEntry::Namespace(s) => Some(s),
+                        AllowlistEntry::Namespace(s, options) => Some((s, options)),
                         _ => None,
                     })
-                    .map(|s| quote! { #s }),
+                    .map(|(s, options)| {
+                        let max_depth = options
+                            .max_depth
+                            .map(proc_macro2::Literal::usize_unsuffixed)
+                            .map(|max_depth| quote! { , max_depth = #max_depth });
+                        let skip_detail_namespaces = options
+                            .skip_detail_namespaces
+                            .then(|| quote! { , skip_detail_namespaces });
+                        let pod = (options.pod == NamespacePodPolicy::Auto)
+                            .then(|| quote! { , pod = auto });
+                        quote! { #s #max_depth #skip_detail_namespaces #pod }
+                    }),
             ),
             Allowlist::Unspecified(_) => panic!("Allowlist mode not yet determined"),
             _ => Box::new(std::iter::empty()),
@@ -300,6 +683,93 @@ fn allowlist_err_to_syn_err(err: AllowlistErr, span: &Span) -> syn::Error {
     syn::Error::new(*span, format!("{err}"))
 }
 
+struct ImportModule;
+
+impl Directive for ImportModule {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let module: syn::LitStr = args.parse()?;
+        let module = module.value();
+        // The module is imported via a generated header, so that everything
+        // downstream can treat it as just another inclusion.
+        config.inclusions.push(module_import_header_name(&module));
+        config.imported_modules.push(module);
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.imported_modules.iter().map(|val| quote! { #val }))
+    }
+}
+
+struct ConfigFile;
+
+impl Directive for ConfigFile {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let path: syn::LitStr = args.parse()?;
+        let path_str = path.value();
+        if config.config_files.contains(&path_str) {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("config file {path_str} was loaded more than once"),
+            ));
+        }
+        let resolved = resolve_config_file_path(&path_str);
+        let contents = std::fs::read_to_string(&resolved).map_err(|e| {
+            syn::Error::new(
+                path.span(),
+                format!("unable to read config file {}: {e}", resolved.display()),
+            )
+        })?;
+        config.config_files.push(path_str.clone());
+        // Spans within the file can't point anywhere useful, so report
+        // any problem against the outermost config_file! directive.
+        let outer_config_file = config.directive_spans.config_file;
+        config
+            .directive_spans
+            .config_file
+            .get_or_insert(path.span());
+        let applied_before = config.applied_directives.len();
+        syn::parse::Parser::parse_str(
+            |input: ParseStream| parse_directives(input, config),
+            &contents,
+        )
+        .map_err(|e| {
+            syn::Error::new(
+                path.span(),
+                format!("in config file {}: {e}", resolved.display()),
+            )
+        })?;
+        config.directive_spans.config_file = outer_config_file;
+        for applied in &mut config.applied_directives[applied_before..] {
+            applied.config_file.get_or_insert_with(|| path_str.clone());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        _config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        // The directives within the file are output individually.
+        Box::new(std::iter::empty())
+    }
+}
+
 struct StringList<SET, GET>(SET, GET)
 where
     SET: Fn(&mut IncludeCppConfig) -> &mut Vec<String>,
@@ -393,6 +863,50 @@ impl Directive for ModName {
     }
 }
 
+struct CppStd;
+
+impl Directive for CppStd {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let standard: syn::LitStr = args.parse()?;
+        let value = standard.value();
+        let version = value
+            .strip_prefix("c++")
+            .or_else(|| value.strip_prefix("gnu++"));
+        if !version
+            .map(|version| {
+                !version.is_empty() && version.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+            .unwrap_or_default()
+        {
+            return Err(syn::Error::new(
+                standard.span(),
+                "expected a C++ standard such as \"c++17\" or \"gnu++23\"",
+            ));
+        }
+        if config.cpp_std.is_some() {
+            return Err(syn::Error::new(
+                standard.span(),
+                "cpp_std! may only be given once",
+            ));
+        }
+        config.cpp_std = Some(value);
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.cpp_std.iter().map(|standard| quote! { #standard }))
+    }
+}
+
 struct Concrete;
 
 impl Directive for Concrete {
@@ -464,9 +978,26 @@ impl Directive for Subclass {
         let superclass: syn::LitStr = args.parse()?;
         args.parse::<syn::token::Comma>()?;
         let subclass: syn::Ident = args.parse()?;
+        let non_virtual_destructor = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let mode: syn::Ident = args.parse()?;
+            match mode.to_string().as_str() {
+                "error" => NonVirtualDestructor::Error,
+                "leak" => NonVirtualDestructor::Leak,
+                "downcast" => NonVirtualDestructor::Downcast,
+                _ => {
+                    return Err(syn::Error::new(
+                        mode.span(),
+                        "expected error, leak or downcast",
+                    ))
+                }
+            }
+        } else {
+            NonVirtualDestructor::Error
+        };
         config.subclasses.push(crate::config::Subclass {
             superclass: superclass.value(),
             subclass,
+            non_virtual_destructor,
         });
         Ok(())
     }
@@ -479,8 +1010,16 @@ impl Directive for Subclass {
         Box::new(config.subclasses.iter().map(|sc| {
             let superclass = &sc.superclass;
             let subclass = &sc.subclass;
-            quote! {
-                #superclass,#subclass
+            match sc.non_virtual_destructor {
+                NonVirtualDestructor::Error => quote! {
+                    #superclass,#subclass
+                },
+                NonVirtualDestructor::Leak => quote! {
+                    #superclass,#subclass, leak
+                },
+                NonVirtualDestructor::Downcast => quote! {
+                    #superclass,#subclass, downcast
+                },
             }
         }))
     }
@@ -533,6 +1072,11 @@ impl Directive for ExternCppType {
         let definition: syn::LitStr = args.parse()?;
         args.parse::<syn::token::Comma>()?;
         let rust_path: syn::TypePath = args.parse()?;
+        if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let generic = parse_generic_extern(args, &definition, rust_path, self.opaque)?;
+            config.generic_externs.push(generic);
+            return Ok(());
+        }
         config.externs.0.insert(
             definition.value(),
             crate::config::ExternCppType {
@@ -563,7 +1107,1250 @@ impl Directive for ExternCppType {
                     } else {
                         None
                     }
-                }),
+                })
+                .chain(
+                    config
+                        .generic_externs
+                        .iter()
+                        .filter(move |ge| ge.opaque == opaque_needed)
+                        .map(|ge| {
+                            let rust_path = &ge.rust_path;
+                            let definition = format!(
+                                "{}<{}>",
+                                ge.template,
+                                generic_param(rust_path).unwrap()
+                            );
+                            let (cpp_args, rust_args): (Vec<_>, Vec<_>) =
+                                ge.instantiations.iter().cloned().unzip();
+                            quote! {
+                                #definition, #rust_path, instantiations = [#(#cpp_args => #rust_args),*]
+                            }
+                        }),
+                ),
+        )
+    }
+}
+
+/// Parse the `instantiations = ["cpp_type" => RustType, ...]` which makes
+/// an `extern_cpp_type!` generic, checking that the C++ template and the
+/// Rust type each have the same single type parameter.
+fn parse_generic_extern(
+    args: ParseStream,
+    definition: &syn::LitStr,
+    rust_path: syn::TypePath,
+    opaque: bool,
+) -> ParseResult<crate::config::GenericExternCppType> {
+    let key: syn::Ident = args.parse()?;
+    if key != "instantiations" {
+        return Err(syn::Error::new(key.span(), "expected instantiations"));
+    }
+    args.parse::<syn::token::Eq>()?;
+    let list;
+    syn::bracketed!(list in args);
+    let mut instantiations = Vec::new();
+    while !list.is_empty() {
+        let cpp_arg: syn::LitStr = list.parse()?;
+        list.parse::<syn::token::FatArrow>()?;
+        let rust_arg: syn::Type = list.parse()?;
+        instantiations.push((cpp_arg.value(), rust_arg));
+        if list.parse::<Option<syn::token::Comma>>()?.is_none() {
+            break;
+        }
+    }
+    let definition_str = definition.value();
+    let template = generic_param(&rust_path)
+        .and_then(|param| definition_str.strip_suffix(&format!("<{param}>")))
+        .ok_or_else(|| {
+            syn::Error::new(
+                definition.span(),
+                "with instantiations, the C++ type must be a template such as \"ns::Span<T>\", with the same single type parameter as the Rust type",
+            )
+        })?;
+    Ok(crate::config::GenericExternCppType {
+        template: template.to_string(),
+        rust_path,
+        instantiations,
+        opaque,
+    })
+}
+
+/// The name of the single type parameter of a Rust type such as
+/// `crate::Span<T>`, if it has exactly one.
+fn generic_param(rust_path: &syn::TypePath) -> Option<String> {
+    match &rust_path.path.segments.last()?.arguments {
+        syn::PathArguments::AngleBracketed(ab) if ab.args.len() == 1 => match ab.args.first()? {
+            syn::GenericArgument::Type(syn::Type::Path(param)) => {
+                Some(param.path.get_ident()?.to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parse the `key =` part of a `key = value` argument to a directive.
+fn parse_key(args: ParseStream, expected: &str) -> ParseResult<()> {
+    let key: syn::Ident = args.parse()?;
+    if key != expected {
+        return Err(syn::Error::new(key.span(), format!("expected {expected}")));
+    }
+    args.parse::<syn::token::Eq>()?;
+    Ok(())
+}
+
+struct TaggedUnion;
+
+impl TaggedUnion {
+    fn parse_variant(args: ParseStream) -> ParseResult<crate::config::TaggedUnionVariant> {
+        let negative = args.parse::<Option<syn::token::Minus>>()?.is_some();
+        let tag_value: syn::LitInt = args.parse()?;
+        let tag_value = tag_value.base10_parse::<i64>()?;
+        args.parse::<syn::token::FatArrow>()?;
+        let name: syn::Ident = args.parse()?;
+        let details;
+        syn::parenthesized!(details in args);
+        let ty: syn::LitStr = details.parse()?;
+        details.parse::<syn::token::Comma>()?;
+        let member: syn::LitStr = details.parse()?;
+        Ok(crate::config::TaggedUnionVariant {
+            tag_value: if negative { -tag_value } else { tag_value },
+            name,
+            ty: ty.value(),
+            member: member.value(),
+        })
+    }
+}
+
+impl Directive for TaggedUnion {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        parse_key(args, "tag")?;
+        let tag: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        parse_key(args, "variants")?;
+        let variants;
+        syn::braced!(variants in args);
+        let variants = variants.parse_terminated(Self::parse_variant, syn::token::Comma)?;
+        config.tagged_unions.push(crate::config::TaggedUnion {
+            ty: ty.value(),
+            tag: tag.value(),
+            variants: variants.into_iter().collect(),
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.tagged_unions.iter().map(|tu| {
+            let ty = &tu.ty;
+            let tag = &tu.tag;
+            let variants = tu.variants.iter().map(|v| {
+                let tag_value = v.tag_value;
+                let name = &v.name;
+                let ty = &v.ty;
+                let member = &v.member;
+                quote! { #tag_value => #name(#ty, #member) }
+            });
+            quote! {
+                #ty, tag = #tag, variants = { #(#variants),* }
+            }
+        }))
+    }
+}
+
+struct OverloadNames;
+
+impl Directive for OverloadNames {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let cpp_name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let names =
+            args.parse_terminated(<syn::LitStr as syn::parse::Parse>::parse, syn::token::Comma)?;
+        let names = names
+            .into_iter()
+            .map(|name| name.parse::<syn::Ident>().map(|_| name.value()))
+            .collect::<ParseResult<Vec<_>>>()?;
+        config.overload_names.push((cpp_name.value(), names));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .overload_names
+                .iter()
+                .map(|(cpp_name, names)| quote! { #cpp_name, #(#names),* }),
+        )
+    }
+}
+
+struct ExportC;
+
+impl Directive for ExportC {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let cpp_name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let symbol: syn::LitStr = args.parse()?;
+        symbol.parse::<syn::Ident>()?;
+        if config
+            .c_exports
+            .iter()
+            .any(|(_, existing)| *existing == symbol.value())
+        {
+            return Err(syn::Error::new(
+                symbol.span(),
+                format!("C symbol {} is exported more than once", symbol.value()),
+            ));
+        }
+        config.c_exports.push((cpp_name.value(), symbol.value()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .c_exports
+                .iter()
+                .map(|(cpp_name, symbol)| quote! { #cpp_name, #symbol }),
+        )
+    }
+}
+
+struct ReturnsLifetimeOfArg;
+
+impl Directive for ReturnsLifetimeOfArg {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let cpp_name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        parse_key(args, "arg")?;
+        let arg: syn::LitInt = args.parse()?;
+        config
+            .returns_lifetime_of_arg
+            .push((cpp_name.value(), arg.base10_parse()?));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .returns_lifetime_of_arg
+                .iter()
+                .map(|(cpp_name, arg)| quote! { #cpp_name, arg = #arg }),
+        )
+    }
+}
+
+struct IteratorRange;
+
+impl Directive for IteratorRange {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let cpp_name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        parse_key(args, "begin")?;
+        let begin: syn::LitInt = args.parse()?;
+        config
+            .iterator_ranges
+            .push((cpp_name.value(), begin.base10_parse()?));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .iterator_ranges
+                .iter()
+                .map(|(cpp_name, begin)| quote! { #cpp_name, begin = #begin }),
+        )
+    }
+}
+
+struct Getters;
+
+impl Directive for Getters {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let strip_prefix = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let key: syn::Ident = args.parse()?;
+            if key != "strip_prefix" {
+                return Err(syn::Error::new(key.span(), "expected strip_prefix"));
+            }
+            args.parse::<syn::token::Eq>()?;
+            let prefix: syn::LitStr = args.parse()?;
+            if prefix.value().is_empty() {
+                return Err(syn::Error::new(
+                    prefix.span(),
+                    "strip_prefix must not be empty",
+                ));
+            }
+            prefix.value()
+        } else {
+            "Get".to_string()
+        };
+        config.getters.push(crate::config::Getters {
+            ty: ty.value(),
+            strip_prefix,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.getters.iter().map(|getters| {
+            let ty = &getters.ty;
+            let strip_prefix = &getters.strip_prefix;
+            quote! { #ty, strip_prefix = #strip_prefix }
+        }))
+    }
+}
+
+struct NoDrop;
+
+impl Directive for NoDrop {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let behaviour = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let mode: syn::Ident = args.parse()?;
+            match mode.to_string().as_str() {
+                "leak" => DropBehaviour::Leak,
+                "assert" => DropBehaviour::Assert,
+                _ => return Err(syn::Error::new(mode.span(), "expected leak or assert")),
+            }
+        } else {
+            DropBehaviour::Leak
+        };
+        config.no_drop.push((ty.value(), behaviour));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .no_drop
+                .iter()
+                .map(|(ty, behaviour)| match behaviour {
+                    DropBehaviour::Leak => quote! { #ty, leak },
+                    DropBehaviour::Assert => quote! { #ty, assert },
+                }),
+        )
+    }
+}
+
+struct ErrorType;
+
+impl Directive for ErrorType {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let message = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let key: syn::Ident = args.parse()?;
+            if key != "message" {
+                return Err(syn::Error::new(key.span(), "expected message"));
+            }
+            args.parse::<syn::token::Eq>()?;
+            let method: syn::LitStr = args.parse()?;
+            if syn::parse_str::<syn::Ident>(&method.value()).is_err() {
+                return Err(syn::Error::new(
+                    method.span(),
+                    "message must be the name of a method",
+                ));
+            }
+            method.value()
+        } else {
+            "what".to_string()
+        };
+        if config.error_types.iter().any(|et| et.ty == ty.value()) {
+            return Err(syn::Error::new(
+                ty.span(),
+                "error_type! was already given for this type",
+            ));
+        }
+        config.error_types.push(crate::config::ErrorType {
+            ty: ty.value(),
+            message,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.error_types.iter().map(|error_type| {
+            let ty = &error_type.ty;
+            let message = &error_type.message;
+            quote! { #ty, message = #message }
+        }))
+    }
+}
+
+struct ConversionOperatorDirective;
+
+impl Directive for ConversionOperatorDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let to: syn::LitStr = args.parse()?;
+        let impl_from = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let flag: syn::Ident = args.parse()?;
+            if flag != "impl_from" {
+                return Err(syn::Error::new(flag.span(), "expected impl_from"));
+            }
+            true
+        } else {
+            false
+        };
+        config
+            .conversion_operators
+            .push(crate::config::ConversionOperator {
+                ty: ty.value(),
+                to: to.value(),
+                impl_from,
+            });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.conversion_operators.iter().map(|conversion| {
+            let ty = &conversion.ty;
+            let to = &conversion.to;
+            if conversion.impl_from {
+                quote! { #ty, #to, impl_from }
+            } else {
+                quote! { #ty, #to }
+            }
+        }))
+    }
+}
+
+/// The types which can be passed between Rust and C++ without any
+/// conversion.
+const PRIMITIVES: &[&str] = &[
+    "bool", "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "usize", "f32", "f64",
+];
+
+fn parse_primitive(ty: &syn::Type, directive: &str) -> ParseResult<Ident> {
+    let id = match ty {
+        syn::Type::Path(typ) if typ.qself.is_none() => typ.path.get_ident(),
+        _ => None,
+    };
+    match id {
+        Some(id) if PRIMITIVES.contains(&id.to_string().as_str()) => Ok(id.clone()),
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            format!("{directive}! only supports primitive parameter and result types"),
+        )),
+    }
+}
+
+struct CompletionFutureDirective;
+
+impl Directive for CompletionFutureDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let sig: syn::TypeBareFn = args.parse()?;
+        if let Some(variadic) = &sig.variadic {
+            return Err(syn::Error::new_spanned(
+                variadic,
+                "completion_future! doesn't support variadic functions",
+            ));
+        }
+        let params = sig
+            .inputs
+            .iter()
+            .map(|arg| parse_primitive(&arg.ty, "completion_future"))
+            .collect::<ParseResult<_>>()?;
+        let result = match &sig.output {
+            syn::ReturnType::Default => None,
+            syn::ReturnType::Type(_, ty) => match ty.as_ref() {
+                syn::Type::Tuple(tuple) if tuple.elems.is_empty() => None,
+                ty => Some(parse_primitive(ty, "completion_future")?),
+            },
+        };
+        config
+            .completion_futures
+            .push(crate::config::CompletionFuture {
+                function: function.value(),
+                params,
+                result,
+            });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.completion_futures.iter().map(|cf| {
+            let function = &cf.function;
+            let params = &cf.params;
+            let result = cf.result.iter();
+            quote! { #function, fn(#(#params),*) #(-> #result)* }
+        }))
+    }
+}
+
+struct ContextCallbackDirective;
+
+impl Directive for ContextCallbackDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let sig: syn::TypeBareFn = args.parse()?;
+        if let Some(variadic) = &sig.variadic {
+            return Err(syn::Error::new_spanned(
+                variadic,
+                "context_callback! doesn't support variadic callbacks",
+            ));
+        }
+        if !matches!(sig.output, syn::ReturnType::Default) {
+            return Err(syn::Error::new_spanned(
+                &sig.output,
+                "context_callback! only supports callbacks returning void",
+            ));
+        }
+        let params = sig
+            .inputs
+            .iter()
+            .map(|arg| parse_primitive(&arg.ty, "context_callback"))
+            .collect::<ParseResult<_>>()?;
+        let unregister = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            parse_key(args, "unregister")?;
+            Some(args.parse::<syn::LitStr>()?.value())
+        } else {
+            None
+        };
+        config
+            .context_callbacks
+            .push(crate::config::ContextCallback {
+                function: function.value(),
+                params,
+                unregister,
+            });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.context_callbacks.iter().map(|cc| {
+            let function = &cc.function;
+            let params = &cc.params;
+            let unregister = cc.unregister.iter();
+            quote! { #function, fn(#(#params),*) #(, unregister = #unregister)* }
+        }))
+    }
+}
+
+struct MemberFnFieldDirective;
+
+impl Directive for MemberFnFieldDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let path: syn::LitStr = args.parse()?;
+        let Some((table, field)) = path
+            .value()
+            .rsplit_once("::")
+            .map(|(table, field)| (table.to_string(), field.to_string()))
+        else {
+            return Err(syn::Error::new_spanned(
+                &path,
+                "member_fn_field! needs the field's full name, e.g. \"ns::Struct::field\"",
+            ));
+        };
+        args.parse::<syn::token::Comma>()?;
+        let sig: syn::TypeBareFn = args.parse()?;
+        if let Some(variadic) = &sig.variadic {
+            return Err(syn::Error::new_spanned(
+                variadic,
+                "member_fn_field! doesn't support variadic member functions",
+            ));
+        }
+        let mut inputs = sig.inputs.iter();
+        let receiver = match inputs.next().map(|arg| &arg.ty) {
+            Some(syn::Type::Reference(receiver)) => match receiver.elem.as_ref() {
+                syn::Type::Path(typ) if typ.qself.is_none() => Some((receiver, typ)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some((receiver, class)) = receiver else {
+            return Err(syn::Error::new_spanned(
+                &sig,
+                "member_fn_field! needs the first parameter to be a reference to the class, e.g. fn(&ns::Class, i32)",
+            ));
+        };
+        let params = inputs
+            .map(|arg| parse_primitive(&arg.ty, "member_fn_field"))
+            .collect::<ParseResult<_>>()?;
+        let result = match &sig.output {
+            syn::ReturnType::Default => None,
+            syn::ReturnType::Type(_, ty) => match ty.as_ref() {
+                syn::Type::Tuple(tuple) if tuple.elems.is_empty() => None,
+                ty => Some(parse_primitive(ty, "member_fn_field")?),
+            },
+        };
+        let targets = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            parse_key(args, "targets")?;
+            let list;
+            syn::bracketed!(list in args);
+            let mut targets = Vec::new();
+            while !list.is_empty() {
+                let target: syn::LitStr = list.parse()?;
+                if syn::parse_str::<syn::Ident>(&target.value()).is_err() {
+                    return Err(syn::Error::new_spanned(
+                        &target,
+                        "member_fn_field! targets must be the names of member functions, which are also valid Rust identifiers",
+                    ));
+                }
+                targets.push(target.value());
+                if list.parse::<Option<syn::token::Comma>>()?.is_none() {
+                    break;
+                }
+            }
+            targets
+        } else {
+            Vec::new()
+        };
+        if config.is_member_fn_field(&table, &field) {
+            return Err(syn::Error::new_spanned(
+                &path,
+                "member_fn_field! was already given for this field",
+            ));
+        }
+        config.member_fn_fields.push(crate::config::MemberFnField {
+            table,
+            field,
+            class: class
+                .path
+                .segments
+                .iter()
+                .map(|seg| seg.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::"),
+            is_const: receiver.mutability.is_none(),
+            params,
+            result,
+            targets,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.member_fn_fields.iter().map(|mff| {
+            let path = format!("{}::{}", mff.table, mff.field);
+            let class: syn::Path = syn::parse_str(&mff.class).unwrap();
+            let mutability = (!mff.is_const).then(|| quote! { mut });
+            let params = &mff.params;
+            let result = mff.result.iter();
+            let targets = (!mff.targets.is_empty()).then(|| {
+                let targets = &mff.targets;
+                quote! { , targets = [#(#targets),*] }
+            });
+            quote! { #path, fn(&#mutability #class #(, #params)*) #(-> #result)* #targets }
+        }))
+    }
+}
+
+struct InitFunctionDirective;
+
+impl Directive for InitFunctionDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let auto_in_debug = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let flag: syn::Ident = args.parse()?;
+            if flag != "auto_in_debug" {
+                return Err(syn::Error::new(flag.span(), "expected auto_in_debug"));
+            }
+            true
+        } else {
+            false
+        };
+        if config.init_function.is_some() {
+            return Err(syn::Error::new(
+                function.span(),
+                "init_function! may only be given once",
+            ));
+        }
+        config.init_function = Some(crate::config::InitFunction {
+            function: function.value(),
+            auto_in_debug,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.init_function.iter().map(|init| {
+            let function = &init.function;
+            let auto_in_debug = init.auto_in_debug.then(|| quote! { , auto_in_debug });
+            quote! { #function #auto_in_debug }
+        }))
+    }
+}
+
+struct CompleteTypeDirective;
+
+impl CompleteTypeDirective {
+    fn parse_layout(args: ParseStream, expected: &str) -> ParseResult<usize> {
+        args.parse::<syn::token::Comma>()?;
+        parse_key(args, expected)?;
+        let value: syn::LitInt = args.parse()?;
+        value.base10_parse()
+    }
+}
+
+impl Directive for CompleteTypeDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let size = Self::parse_layout(args, "size")?;
+        let align_span = args.span();
+        let align = Self::parse_layout(args, "align")?;
+        if !align.is_power_of_two() {
+            return Err(syn::Error::new(align_span, "align must be a power of two"));
+        }
+        let header = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            parse_key(args, "header")?;
+            let header: syn::LitStr = args.parse()?;
+            Some(header.value())
+        } else {
+            None
+        };
+        config.complete_types.push(crate::config::CompleteType {
+            ty: ty.value(),
+            size,
+            align,
+            header,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.complete_types.iter().map(|ct| {
+            let ty = &ct.ty;
+            let size = ct.size;
+            let align = ct.align;
+            match &ct.header {
+                Some(header) => quote! { #ty, size = #size, align = #align, header = #header },
+                None => quote! { #ty, size = #size, align = #align },
+            }
+        }))
+    }
+}
+
+struct RustEquivalentDirective;
+
+impl Directive for RustEquivalentDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let rust_path: syn::TypePath = args.parse()?;
+        let conversions = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            parse_key(args, "from_cpp")?;
+            let from_cpp: syn::Path = args.parse()?;
+            args.parse::<syn::token::Comma>()?;
+            parse_key(args, "to_cpp")?;
+            let to_cpp: syn::Path = args.parse()?;
+            Some(crate::config::RustEquivalentConversions { from_cpp, to_cpp })
+        } else {
+            None
+        };
+        config.rust_equivalents.push(crate::config::RustEquivalent {
+            ty: ty.value(),
+            rust_path,
+            conversions,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.rust_equivalents.iter().map(|re| {
+            let ty = &re.ty;
+            let rust_path = &re.rust_path;
+            let conversions = re.conversions.iter().map(|conversions| {
+                let from_cpp = &conversions.from_cpp;
+                let to_cpp = &conversions.to_cpp;
+                quote! { , from_cpp = #from_cpp, to_cpp = #to_cpp }
+            });
+            quote! { #ty, #rust_path #(#conversions)* }
+        }))
+    }
+}
+
+struct DeprecatedAlias;
+
+impl Directive for DeprecatedAlias {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let rust_name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let alias: syn::LitStr = args.parse()?;
+        alias.parse::<syn::Ident>()?;
+        if config
+            .deprecated_aliases
+            .iter()
+            .any(|(name, existing)| *name == rust_name.value() && *existing == alias.value())
+        {
+            return Err(syn::Error::new(
+                alias.span(),
+                format!(
+                    "{} is already a deprecated alias of {}",
+                    alias.value(),
+                    rust_name.value()
+                ),
+            ));
+        }
+        config
+            .deprecated_aliases
+            .push((rust_name.value(), alias.value()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .deprecated_aliases
+                .iter()
+                .map(|(rust_name, alias)| quote! { #rust_name, #alias }),
+        )
+    }
+}
+
+struct NameConstructor;
+
+impl Directive for NameConstructor {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let signature: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let name: syn::LitStr = args.parse()?;
+        name.parse::<syn::Ident>()?;
+        let sig = signature.value();
+        if !sig.trim_end().ends_with(')') || !sig.contains('(') {
+            return Err(syn::Error::new(
+                signature.span(),
+                "expected a constructor signature such as \"ns::Foo(int, int)\"",
+            ));
+        }
+        if config.get_constructor_name(&sig).is_some() {
+            return Err(syn::Error::new(
+                signature.span(),
+                format!("{sig} has already been named by name_constructor!"),
+            ));
+        }
+        config.constructor_names.push((sig, name.value()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .constructor_names
+                .iter()
+                .map(|(signature, name)| quote! { #signature, #name }),
+        )
+    }
+}
+
+struct ConversionErrors;
+
+impl Directive for ConversionErrors {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let policy: syn::Ident = args.parse()?;
+        config.conversion_error_policy = match policy.to_string().as_str() {
+            "strict" => ConversionErrorPolicy::Strict,
+            "lenient" => ConversionErrorPolicy::Lenient,
+            _ => return Err(syn::Error::new(policy.span(), "expected strict or lenient")),
+        };
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        let policy = match config.conversion_error_policy {
+            ConversionErrorPolicy::Default => None,
+            ConversionErrorPolicy::Strict => Some(quote! { strict }),
+            ConversionErrorPolicy::Lenient => Some(quote! { lenient }),
+        };
+        Box::new(policy.into_iter())
+    }
+}
+
+struct CovariantReturn;
+
+impl Directive for CovariantReturn {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let method: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let ty: syn::LitStr = args.parse()?;
+        let method_name = method.value();
+        if !method_name.contains("::") {
+            return Err(syn::Error::new(
+                method.span(),
+                "expected a virtual method such as \"ns::Base::clone\"",
+            ));
+        }
+        if config.get_covariant_return(&method_name).is_some() {
+            return Err(syn::Error::new(
+                method.span(),
+                format!("{method_name} already has a covariant_return!"),
+            ));
+        }
+        config.covariant_returns.push((method_name, ty.value()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .covariant_returns
+                .iter()
+                .map(|(method, ty)| quote! { #method, #ty }),
+        )
+    }
+}
+
+struct IntrusivePtrDirective;
+
+impl IntrusivePtrDirective {
+    fn parse_method(args: ParseStream, expected: &str) -> ParseResult<String> {
+        args.parse::<syn::token::Comma>()?;
+        let key: syn::Ident = args.parse()?;
+        if key != expected {
+            return Err(syn::Error::new(key.span(), format!("expected {expected}")));
+        }
+        args.parse::<syn::token::Eq>()?;
+        let method: syn::LitStr = args.parse()?;
+        if syn::parse_str::<syn::Ident>(&method.value()).is_err() {
+            return Err(syn::Error::new(
+                method.span(),
+                format!("{expected} must be the name of a method"),
+            ));
+        }
+        Ok(method.value())
+    }
+}
+
+impl Directive for IntrusivePtrDirective {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let add_ref = Self::parse_method(args, "add_ref")?;
+        let release = Self::parse_method(args, "release")?;
+        if config.get_intrusive_ptr_methods(&ty.value()).is_some() {
+            return Err(syn::Error::new(
+                ty.span(),
+                "intrusive_ptr! was already given for this type",
+            ));
+        }
+        config.intrusive_ptrs.push(crate::config::IntrusivePtr {
+            ty: ty.value(),
+            add_ref,
+            release,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.intrusive_ptrs.iter().map(|ip| {
+            let ty = &ip.ty;
+            let add_ref = &ip.add_ref;
+            let release = &ip.release;
+            quote! { #ty, add_ref = #add_ref, release = #release }
+        }))
+    }
+}
+
+struct HeaderImplementation;
+
+impl Directive for HeaderImplementation {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let header: syn::LitStr = args.parse()?;
+        let mut defines = Vec::new();
+        while !args.is_empty() {
+            args.parse::<syn::token::Comma>()?;
+            let key: syn::Ident = args.parse()?;
+            if key != "define" {
+                return Err(syn::Error::new(key.span(), "expected define"));
+            }
+            args.parse::<syn::token::Eq>()?;
+            let define: syn::LitStr = args.parse()?;
+            defines.push(define.value());
+        }
+        if defines.is_empty() {
+            return Err(syn::Error::new(
+                header.span(),
+                "header_implementation! needs at least one define = \"...\"",
+            ));
+        }
+        config
+            .header_implementations
+            .push((header.value(), defines));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .header_implementations
+                .iter()
+                .map(|(header, defines)| quote! { #header #(, define = #defines)* }),
+        )
+    }
+}
+
+struct BatchMethods;
+
+impl Directive for BatchMethods {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let methods = syn::punctuated::Punctuated::<syn::LitStr, syn::token::Comma>::parse_separated_nonempty(args)?;
+        if config.batch_methods.iter().any(|(t, _)| *t == ty.value()) {
+            return Err(syn::Error::new(
+                ty.span(),
+                "batch_methods! was already given for this type",
+            ));
+        }
+        config.batch_methods.push((
+            ty.value(),
+            methods.into_iter().map(|method| method.value()).collect(),
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .batch_methods
+                .iter()
+                .map(|(ty, methods)| quote! { #ty, #(#methods),* }),
+        )
+    }
+}
+
+struct Outlives;
+
+impl Directive for Outlives {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let dependent: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let dependency: syn::LitStr = args.parse()?;
+        if dependent.value() == dependency.value() {
+            return Err(syn::Error::new(
+                dependency.span(),
+                "a type can't be required to outlive itself",
+            ));
+        }
+        if config.get_outlives_dependency(&dependent.value()).is_some() {
+            return Err(syn::Error::new(
+                dependent.span(),
+                "outlives! was already given for this type",
+            ));
+        }
+        config
+            .outlives
+            .push((dependent.value(), dependency.value()));
+        Ok(())
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .outlives
+                .iter()
+                .map(|(dependent, dependency)| quote! { #dependent, #dependency }),
         )
     }
 }
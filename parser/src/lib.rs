@@ -16,7 +16,11 @@ mod path;
 mod subclass_attrs;
 
 pub use config::{
-    AllowlistEntry, ExternCppType, IncludeCppConfig, RustFun, Subclass, UnsafePolicy,
+    module_import_header_name, to_snake_case, AllowlistEntry, AppliedDirective, CompleteType,
+    CompletionFuture, ContextCallback, ConversionErrorPolicy, ConversionOperator, DropBehaviour,
+    ExternCppType, IncludeCppConfig, InitFunction, MemberFnField, NamespaceOptions,
+    NamespacePodPolicy, NonVirtualDestructor, RustEquivalent, RustEquivalentConversions, RustFun,
+    Subclass, TaggedUnion, TaggedUnionVariant, UnsafePolicy,
 };
 use file_locations::FileLocationStrategy;
 pub use multi_bindings::{MultiBindings, MultiBindingsErr};
@@ -71,7 +75,7 @@ impl IncludeCpp {
 
 #[cfg(test)]
 mod parse_tests {
-    use crate::IncludeCpp;
+    use crate::{ConversionErrorPolicy, DropBehaviour, IncludeCpp};
     use syn::parse_quote;
 
     #[test]
@@ -80,4 +84,535 @@ mod parse_tests {
             generate_all!()
         };
     }
+
+    #[test]
+    fn test_tagged_union() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("Event")
+            tagged_union!("Event", tag = "type", variants = {
+                1 => Key("KeyEvent", "data.key"),
+                -2 => Mouse("MouseEvent", "data.mouse"),
+            })
+        };
+        let tu = i.get_config().get_tagged_union("Event").unwrap();
+        assert_eq!(tu.tag, "type");
+        assert_eq!(tu.variants.len(), 2);
+        assert_eq!(tu.variants[1].tag_value, -2);
+        assert_eq!(tu.variants[1].name, "Mouse");
+        assert_eq!(tu.variants[1].member, "data.mouse");
+    }
+
+    #[test]
+    fn test_config_file() {
+        let path = std::env::temp_dir().join(format!(
+            "autocxx_test_config_file_{}.directives",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "#include \"bar.h\"\ngenerate!(\"Bar\")\nblock!(\"Baz\")\n",
+        )
+        .unwrap();
+        let path_str = path.to_str().unwrap();
+        let i: IncludeCpp = parse_quote! {
+            config_file!(#path_str)
+            generate!("Foo")
+        };
+        std::fs::remove_file(&path).unwrap();
+        let config = i.get_config();
+        assert_eq!(config.inclusions, vec!["bar.h"]);
+        assert!(config.is_on_allowlist("Bar"));
+        assert!(config.is_on_allowlist("Foo"));
+        assert!(config.is_on_blocklist("Baz"));
+        assert_eq!(
+            config.get_config_files().collect::<Vec<_>>(),
+            vec![path.clone()]
+        );
+        let applied: Vec<_> = config
+            .get_applied_directives()
+            .iter()
+            .map(|applied| {
+                (
+                    applied.name.as_str(),
+                    applied.args.as_str(),
+                    applied.config_file.is_some(),
+                )
+            })
+            .collect();
+        let quoted_path = format!("\"{path_str}\"");
+        assert_eq!(
+            applied,
+            vec![
+                ("config_file", quoted_path.as_str(), false),
+                ("include", "\"bar.h\"", true),
+                ("generate", "\"Bar\"", true),
+                ("block", "\"Baz\"", true),
+                ("generate", "\"Foo\"", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_module() {
+        let i: IncludeCpp = parse_quote! {
+            import_module!("math.geometry")
+            generate!("Point")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_imported_modules().collect::<Vec<_>>(),
+            vec!["math.geometry"]
+        );
+        assert_eq!(config.inclusions, vec!["autocxx_module_math_geometry.h"]);
+    }
+
+    #[test]
+    fn test_export_c() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("ns::Foo")
+            export_c!("ns::Foo::bar", "foo_bar")
+        };
+        assert_eq!(
+            i.get_config().get_c_export_name("ns::Foo::bar"),
+            Some("foo_bar")
+        );
+        assert_eq!(i.get_config().get_c_export_name("ns::Foo::baz"), None);
+        let duplicate: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            export_c!("ns::Foo::bar", "foo_bar")
+            export_c!("ns::Foo::baz", "foo_bar")
+        });
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn test_getters() {
+        let i: IncludeCpp = parse_quote! {
+            getters!("ns::Widget", strip_prefix = "Get")
+            getters!("ns::Gadget")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_getter_name("ns::Widget", "GetWidth"),
+            Some("width".to_string())
+        );
+        assert_eq!(
+            config.get_getter_name("ns::Widget", "GetBorderColor"),
+            Some("border_color".to_string())
+        );
+        assert_eq!(
+            config.get_getter_name("ns::Widget", "GetType"),
+            Some("type_".to_string())
+        );
+        assert_eq!(config.get_getter_name("ns::Widget", "Getaway"), None);
+        assert_eq!(config.get_getter_name("ns::Widget", "SetWidth"), None);
+        assert_eq!(
+            config.get_getter_name("ns::Gadget", "GetURL"),
+            Some("url".to_string())
+        );
+        assert_eq!(
+            config.get_getter_name("ns::Gadget", "GetHTTPServer"),
+            Some("http_server".to_string())
+        );
+        assert_eq!(config.get_getter_name("ns::Other", "GetWidth"), None);
+    }
+
+    #[test]
+    fn test_rust_equivalent() {
+        let i: IncludeCpp = parse_quote! {
+            rust_equivalent!("geo::Point", crate::Point)
+            rust_equivalent!(
+                "chrono::Duration",
+                std::time::Duration,
+                from_cpp = crate::duration_from_cpp,
+                to_cpp = crate::duration_to_cpp
+            )
+        };
+        let equivalents: Vec<_> = i.get_config().get_rust_equivalents().collect();
+        assert_eq!(equivalents.len(), 2);
+        assert!(equivalents[0].conversions.is_none());
+        let conversions = equivalents[1].conversions.as_ref().unwrap();
+        assert_eq!(
+            conversions.from_cpp,
+            parse_quote! { crate::duration_from_cpp }
+        );
+        assert_eq!(conversions.to_cpp, parse_quote! { crate::duration_to_cpp });
+        let only_from: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            rust_equivalent!("chrono::Duration", std::time::Duration, from_cpp = crate::f)
+        });
+        assert!(only_from.is_err());
+    }
+
+    #[test]
+    fn test_completion_future() {
+        let i: IncludeCpp = parse_quote! {
+            completion_future!("ns::fetch", fn(key: u32, urgent: bool) -> u64)
+            completion_future!("ns::flush", fn())
+        };
+        let config = i.get_config();
+        let futures: Vec<_> = config.get_completion_futures().collect();
+        assert_eq!(futures.len(), 2);
+        assert_eq!(futures[0].function, "ns::fetch");
+        assert_eq!(futures[0].params, vec!["u32", "bool"]);
+        assert_eq!(futures[0].result.as_ref().unwrap(), "u64");
+        assert!(futures[1].params.is_empty());
+        assert!(futures[1].result.is_none());
+        let not_primitive: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            completion_future!("ns::fetch", fn(&str) -> u64)
+        });
+        assert!(not_primitive.is_err());
+    }
+
+    #[test]
+    fn test_context_callback() {
+        let i: IncludeCpp = parse_quote! {
+            context_callback!("ns::on_tick", fn(u32, bool))
+            context_callback!("ns::add_listener", fn(), unregister = "ns::remove_listener")
+        };
+        let config = i.get_config();
+        let callbacks: Vec<_> = config.get_context_callbacks().collect();
+        assert_eq!(callbacks.len(), 2);
+        assert_eq!(callbacks[0].function, "ns::on_tick");
+        assert_eq!(callbacks[0].params, vec!["u32", "bool"]);
+        assert!(callbacks[0].unregister.is_none());
+        assert!(callbacks[1].params.is_empty());
+        assert_eq!(
+            callbacks[1].unregister.as_deref(),
+            Some("ns::remove_listener")
+        );
+        let returns_value: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            context_callback!("ns::on_tick", fn(u32) -> bool)
+        });
+        assert!(returns_value.is_err());
+    }
+
+    #[test]
+    fn test_member_fn_field() {
+        let i: IncludeCpp = parse_quote! {
+            member_fn_field!("plugin::Callbacks::on_event", fn(&plugin::Handler, i32) -> bool, targets = ["handle", "ignore"])
+            member_fn_field!("plugin::Callbacks::reset", fn(&mut plugin::Handler))
+        };
+        let config = i.get_config();
+        let fields: Vec<_> = config.get_member_fn_fields().collect();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].table, "plugin::Callbacks");
+        assert_eq!(fields[0].field, "on_event");
+        assert_eq!(fields[0].class, "plugin::Handler");
+        assert!(fields[0].is_const);
+        assert_eq!(fields[0].params, vec!["i32"]);
+        assert_eq!(fields[0].result.as_ref().unwrap(), "bool");
+        assert_eq!(fields[0].targets, ["handle", "ignore"]);
+        assert!(!fields[1].is_const);
+        assert!(fields[1].targets.is_empty());
+        assert!(fields[1].params.is_empty());
+        assert!(fields[1].result.is_none());
+        assert!(config.is_member_fn_field("plugin::Callbacks", "reset"));
+        let no_receiver: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            member_fn_field!("plugin::Callbacks::on_event", fn(i32) -> bool)
+        });
+        assert!(no_receiver.is_err());
+        let bad_target: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            member_fn_field!("plugin::Callbacks::reset", fn(&mut plugin::Handler), targets = ["operator()"])
+        });
+        assert!(bad_target.is_err());
+    }
+
+    #[test]
+    fn test_init_function() {
+        let i: IncludeCpp = parse_quote! {
+            init_function!("mylib::Init", auto_in_debug)
+        };
+        let init = i.get_config().get_init_function().unwrap();
+        assert_eq!(init.function, "mylib::Init");
+        assert!(init.auto_in_debug);
+        let twice: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            init_function!("mylib::Init")
+            init_function!("mylib::InitAgain")
+        });
+        assert!(twice.is_err());
+    }
+
+    #[test]
+    fn test_directive_spans() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("A")
+            generate_pod!("B")
+            block!("D")
+            iterator_range!("E", begin = 0)
+            safety!(unsafe)
+        };
+        let config = i.get_config();
+        assert!(config.get_directive_span("A").is_some());
+        assert!(config.get_directive_span("B").is_some());
+        assert!(config.get_directive_span("C").is_none());
+        assert!(config.get_directive_span("D").is_some());
+        assert!(config.get_directive_span("E").is_some());
+    }
+
+    #[test]
+    fn test_name_constructor() {
+        let i: IncludeCpp = parse_quote! {
+            name_constructor!("ns::Foo(int, int)", "from_dimensions")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_constructor_name("ns::Foo(int,int)"),
+            Some("from_dimensions")
+        );
+        assert!(config.get_constructor_name("ns::Foo(int)").is_none());
+        let not_a_signature: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            name_constructor!("ns::Foo", "from_dimensions")
+        });
+        assert!(not_a_signature.is_err());
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("Foo")
+            generate_ns!("bar")
+            conversion_errors!(strict)
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.conversion_error_policy(),
+            ConversionErrorPolicy::Strict
+        );
+        assert!(config.is_explicitly_requested("Foo"));
+        assert!(config.is_explicitly_requested("bar::Baz"));
+        assert!(!config.is_explicitly_requested("Baz"));
+        let unknown: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            conversion_errors!(pedantic)
+        });
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_covariant_return() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("ns::Shape")
+            subclass!("ns::Shape", MyShape)
+            covariant_return!("ns::Shape::clone", "ns::Circle")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_covariant_return("ns::Shape::clone"),
+            Some("ns::Circle")
+        );
+        assert!(config.get_covariant_return("ns::Shape::area").is_none());
+        assert!(config
+            .bindgen_allowlist()
+            .unwrap()
+            .any(|item| item == "ns::Circle"));
+    }
+
+    #[test]
+    fn test_intrusive_ptr() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("ns::Texture")
+            intrusive_ptr!("ns::Texture", add_ref = "AddRef", release = "Release")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_intrusive_ptr_methods("ns::Texture"),
+            Some(("AddRef", "Release"))
+        );
+        assert!(config.get_intrusive_ptr_methods("ns::Mesh").is_none());
+        let missing_release: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            intrusive_ptr!("ns::Texture", add_ref = "AddRef")
+        });
+        assert!(missing_release.is_err());
+    }
+
+    #[test]
+    fn test_header_implementation() {
+        let i: IncludeCpp = parse_quote! {
+            header_implementation!("miniaudio.h", define = "MINIAUDIO_IMPLEMENTATION", define = "MA_NO_DECODING")
+        };
+        let config = i.get_config();
+        let implementations: Vec<_> = config.get_header_implementations().collect();
+        assert_eq!(implementations.len(), 1);
+        assert_eq!(implementations[0].0, "miniaudio.h");
+        assert_eq!(
+            implementations[0].1,
+            ["MINIAUDIO_IMPLEMENTATION", "MA_NO_DECODING"]
+        );
+        let missing_define: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            header_implementation!("miniaudio.h")
+        });
+        assert!(missing_define.is_err());
+    }
+
+    #[test]
+    fn test_batch_methods() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("gfx::Canvas")
+            batch_methods!("gfx::Canvas", "SetPixel", "SetColor")
+        };
+        let config = i.get_config();
+        let batches: Vec<_> = config.get_batch_methods().collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].0, "gfx::Canvas");
+        assert_eq!(batches[0].1, ["SetPixel", "SetColor"]);
+        let no_methods: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            batch_methods!("gfx::Canvas")
+        });
+        assert!(no_methods.is_err());
+    }
+
+    #[test]
+    fn test_outlives() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("net::Connection")
+            generate!("net::Session")
+            outlives!("net::Connection", "net::Session")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_outlives_dependency("net::Connection"),
+            Some("net::Session")
+        );
+        assert_eq!(config.get_outlives_dependency("net::Session"), None);
+        let twice: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            outlives!("net::Connection", "net::Session")
+            outlives!("net::Connection", "net::Server")
+        });
+        assert!(twice.is_err());
+    }
+
+    #[test]
+    fn test_thread_handle() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("ui::Widget")
+            thread_handle!("ui::Widget")
+        };
+        let config = i.get_config();
+        assert!(config.has_thread_handle("ui::Widget"));
+        assert!(config.is_thread_affine("ui::Widget"));
+        assert!(!config.has_thread_handle("ui::Window"));
+    }
+
+    #[test]
+    fn test_protobuf_message() {
+        let i: IncludeCpp = parse_quote! {
+            generate!("shop::Order")
+            protobuf_message!("shop::Order")
+        };
+        let config = i.get_config();
+        assert!(config.is_protobuf_message("shop::Order"));
+        assert!(!config.is_protobuf_message("shop::Customer"));
+    }
+
+    #[test]
+    fn test_generic_extern_cpp_type() {
+        let i: IncludeCpp = parse_quote! {
+            extern_cpp_type!("ns::Span<T>", crate::Span<T>, instantiations = ["uint32_t" => u32])
+        };
+        let config = i.get_config();
+        let expected: syn::Type = parse_quote! { crate::Span<u32> };
+        assert_eq!(
+            config.get_extern_instantiation("ns::Span< uint32_t >"),
+            Some(expected)
+        );
+        assert!(config.get_extern_instantiation("ns::Span<float>").is_none());
+        let mismatched: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            extern_cpp_type!("ns::Span<U>", crate::Span<T>, instantiations = ["uint32_t" => u32])
+        });
+        assert!(mismatched.is_err());
+    }
+
+    #[test]
+    fn test_returns_lifetime_of_arg() {
+        let i: IncludeCpp = parse_quote! {
+            returns_lifetime_of_arg!("ns::find", arg = 1)
+        };
+        let config = i.get_config();
+        assert_eq!(config.get_returns_lifetime_of_arg("ns::find"), Some(1));
+        assert_eq!(config.get_returns_lifetime_of_arg("ns::other"), None);
+        let wrong_key: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            returns_lifetime_of_arg!("ns::find", param = 1)
+        });
+        assert!(wrong_key.is_err());
+    }
+
+    #[test]
+    fn test_no_drop() {
+        let i: IncludeCpp = parse_quote! {
+            no_drop!("ns::Registry")
+            no_drop!("ns::Pool", assert)
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_drop_behaviour("ns::Registry"),
+            Some(DropBehaviour::Leak)
+        );
+        assert_eq!(
+            config.get_drop_behaviour("ns::Pool"),
+            Some(DropBehaviour::Assert)
+        );
+        assert_eq!(config.get_drop_behaviour("ns::Other"), None);
+        let bad_mode: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            no_drop!("ns::Registry", panic)
+        });
+        assert!(bad_mode.is_err());
+    }
+
+    #[test]
+    fn test_error_type() {
+        let i: IncludeCpp = parse_quote! {
+            error_type!("ns::ParseError")
+            error_type!("ns::Status", message = "message")
+        };
+        let config = i.get_config();
+        assert_eq!(
+            config.get_error_message_method("ns::ParseError"),
+            Some("what")
+        );
+        assert_eq!(
+            config.get_error_message_method("ns::Status"),
+            Some("message")
+        );
+        assert_eq!(config.get_error_message_method("ns::Other"), None);
+        let duplicate: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            error_type!("ns::Status")
+            error_type!("ns::Status", message = "message")
+        });
+        assert!(duplicate.is_err());
+        let bad_method: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            error_type!("ns::Status", message = "message()")
+        });
+        assert!(bad_method.is_err());
+    }
+
+    #[test]
+    fn test_generate_ns_options() {
+        let i: IncludeCpp = parse_quote! {
+            generate_ns!("a", max_depth = 1, skip_detail_namespaces, pod = auto)
+            generate_ns!("b")
+        };
+        let config = i.get_config();
+        assert!(config.is_on_allowlist("a::Foo"));
+        assert!(config.is_on_allowlist("a::b::Foo"));
+        assert!(!config.is_on_allowlist("a::b::c::Foo"));
+        assert!(!config.is_on_allowlist("a::detail::Foo"));
+        assert!(config.is_on_allowlist("b::detail::c::Foo"));
+        assert!(config.is_pod_if_possible("a::Foo"));
+        assert!(!config.is_pod_if_possible("b::Foo"));
+    }
+
+    #[test]
+    fn test_cpp_std() {
+        let i: IncludeCpp = parse_quote! {
+            cpp_std!("c++23")
+        };
+        assert_eq!(i.get_config().get_cpp_std(), Some("c++23"));
+        let bad: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            cpp_std!("-std=c++23")
+        });
+        assert!(bad.is_err());
+        let duplicate: syn::Result<IncludeCpp> = syn::parse2(quote::quote! {
+            cpp_std!("c++17")
+            cpp_std!("gnu++2c")
+        });
+        assert!(duplicate.is_err());
+    }
 }
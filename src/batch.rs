@@ -0,0 +1,40 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// A type which can be an argument to a method call recorded in a batch,
+/// as generated by `batch_methods!`. Each argument is packed into a
+/// 64-bit word, from which the C++ copies `sizeof` the parameter type.
+pub trait BatchArg: Copy {
+    #[doc(hidden)]
+    fn to_batch_word(self) -> u64;
+}
+
+macro_rules! batch_arg {
+    ($($t:ty),*) => {
+        $(
+            impl BatchArg for $t {
+                fn to_batch_word(self) -> u64 {
+                    // Put the bytes first, whatever the endianness, as
+                    // that's where C++'s memcpy will find them.
+                    let mut word = [0u8; 8];
+                    let bytes = self.to_ne_bytes();
+                    word[..bytes.len()].copy_from_slice(&bytes);
+                    u64::from_ne_bytes(word)
+                }
+            }
+        )*
+    };
+}
+
+batch_arg!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize, f32, f64);
+
+impl BatchArg for bool {
+    fn to_batch_word(self) -> u64 {
+        u8::from(self).to_batch_word()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for calling long-running C++ functions from async Rust.
+//! See [`generate_async`](crate::generate_async).
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+
+/// Something which can run blocking tasks without holding up an async
+/// runtime. Typically this would hand the task to the runtime's own pool
+/// of threads for blocking work, for example `tokio::task::spawn_blocking`.
+///
+/// Register one of these using [`set_blocking_executor`]. If none is
+/// registered, each task is run on a newly spawned thread.
+pub trait BlockingExecutor: Send + Sync {
+    /// Run the task to completion, at some point, on some thread.
+    fn execute(&self, task: Box<dyn FnOnce() + Send + 'static>);
+}
+
+static EXECUTOR: OnceLock<Box<dyn BlockingExecutor>> = OnceLock::new();
+
+/// Returned by [`set_blocking_executor`] if an executor was already registered.
+#[derive(Debug)]
+pub struct ExecutorAlreadySet;
+
+impl std::fmt::Display for ExecutorAlreadySet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a blocking executor has already been set")
+    }
+}
+
+impl std::error::Error for ExecutorAlreadySet {}
+
+/// Register the executor to be used by all `async` wrappers generated
+/// by autocxx. This can be done only once per process.
+pub fn set_blocking_executor(
+    executor: impl BlockingExecutor + 'static,
+) -> Result<(), ExecutorAlreadySet> {
+    EXECUTOR
+        .set(Box::new(executor))
+        .map_err(|_| ExecutorAlreadySet)
+}
+
+struct Shared<R> {
+    result: Option<std::thread::Result<R>>,
+    waker: Option<Waker>,
+}
+
+/// A future representing a blocking call running elsewhere.
+/// Created by [`run_blocking`].
+pub struct BlockingTask<R> {
+    shared: Arc<Mutex<Shared<R>>>,
+}
+
+impl<R> Future for BlockingTask<R> {
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Run a blocking function using the registered [`BlockingExecutor`],
+/// returning a future which completes when the function does. If the
+/// function panics, the panic is propagated when the future is polled.
+pub fn run_blocking<F, R>(f: F) -> BlockingTask<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    let task_shared = shared.clone();
+    let task = Box::new(move || {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+        let mut shared = task_shared.lock().unwrap();
+        shared.result = Some(result);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    });
+    match EXECUTOR.get() {
+        Some(executor) => executor.execute(task),
+        None => {
+            std::thread::spawn(task);
+        }
+    }
+    BlockingTask { shared }
+}
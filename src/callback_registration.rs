@@ -0,0 +1,47 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A guard which keeps a Rust closure registered as a C++ callback.
+//! See [`context_callback`](crate::context_callback).
+
+/// Keeps a Rust closure registered with C++ as a callback. Dropping
+/// this unregisters the callback and then frees the closure.
+#[must_use = "dropping this unregisters the callback immediately"]
+pub struct CallbackRegistration {
+    unregister: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl CallbackRegistration {
+    #[doc(hidden)]
+    pub fn new(unregister: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            unregister: Some(Box::new(unregister)),
+        }
+    }
+
+    /// Leave the callback registered for the rest of the program. The
+    /// closure is leaked.
+    pub fn forget(mut self) {
+        self.unregister = None;
+    }
+}
+
+impl Drop for CallbackRegistration {
+    fn drop(&mut self) {
+        if let Some(unregister) = self.unregister.take() {
+            unregister()
+        }
+    }
+}
+
+impl std::fmt::Debug for CallbackRegistration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackRegistration")
+            .finish_non_exhaustive()
+    }
+}
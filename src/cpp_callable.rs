@@ -0,0 +1,47 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rust ownership of the C++ `std::function`s passed to an
+//! [`extern_rust_function`](crate::extern_rust::extern_rust_function) which
+//! takes a `Box<dyn Fn(..)>`.
+
+/// Owns a heap-allocated C++ `std::function`, known only by its address,
+/// and deletes it when dropped. Generated closures capture one of these
+/// and call the `std::function` through [`CppCallable::get`].
+///
+/// This is deliberately neither `Send` nor `Sync`: we know nothing about
+/// whether the C++ callable may be used from other threads.
+#[doc(hidden)]
+pub struct CppCallable {
+    function: *mut (),
+    delete: unsafe fn(usize),
+}
+
+impl CppCallable {
+    /// # Safety
+    ///
+    /// `function` must be the address of a `std::function` which nothing
+    /// else will delete, and calling `delete` with it must delete it.
+    pub unsafe fn new(function: usize, delete: unsafe fn(usize)) -> Self {
+        Self {
+            function: function as *mut (),
+            delete,
+        }
+    }
+
+    /// The address of the `std::function`, valid until this is dropped.
+    pub fn get(&self) -> usize {
+        self.function as usize
+    }
+}
+
+impl Drop for CppCallable {
+    fn drop(&mut self) {
+        unsafe { (self.delete)(self.function as usize) }
+    }
+}
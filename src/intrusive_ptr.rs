@@ -0,0 +1,114 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::ptr::NonNull;
+
+/// A C++ type which manages its own lifetime through a reference count,
+/// in the style of COM's `AddRef` and `Release`. autocxx implements this
+/// for each type listed in `intrusive_ptr!`; you shouldn't need to
+/// implement it yourself.
+///
+/// # Safety
+///
+/// `add_ref` must take a new reference to the object, and `release` must
+/// give one up, destroying the object once none remain.
+pub unsafe trait IntrusiveRefCounted {
+    #[doc(hidden)]
+    unsafe fn add_ref(this: *mut Self);
+    #[doc(hidden)]
+    unsafe fn release(this: *mut Self);
+}
+
+/// An owning pointer to a reference-counted C++ object. Cloning it takes
+/// a new reference, and dropping it gives one up, much like
+/// `boost::intrusive_ptr` or `Microsoft::WRL::ComPtr` in C++.
+pub struct IntrusivePtr<T: IntrusiveRefCounted>(NonNull<T>);
+
+impl<T: IntrusiveRefCounted> IntrusivePtr<T> {
+    /// Take ownership of a reference which the caller already holds, for
+    /// instance one returned by a C++ factory function. Returns `None` if
+    /// the pointer is null.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a live object, and the caller must own
+    /// one of its references, which becomes owned by the result.
+    pub unsafe fn from_raw(ptr: *mut T) -> Option<Self> {
+        NonNull::new(ptr).map(Self)
+    }
+
+    /// Take a new reference to an object which is owned elsewhere.
+    /// Returns `None` if the pointer is null.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a live object.
+    pub unsafe fn from_raw_add_ref(ptr: *mut T) -> Option<Self> {
+        let result = Self::from_raw(ptr)?;
+        T::add_ref(ptr);
+        Some(result)
+    }
+
+    /// The pointer to the object, without affecting its reference count.
+    pub fn as_ptr(&self) -> *mut T {
+        self.0.as_ptr()
+    }
+
+    /// Give up this pointer without releasing its reference, for instance
+    /// to return it to C++ which will release it later.
+    pub fn into_raw(self) -> *mut T {
+        let ptr = self.as_ptr();
+        std::mem::forget(self);
+        ptr
+    }
+
+    /// Get a mutable reference to the object, in order to call non-const
+    /// C++ methods.
+    ///
+    /// # Safety
+    ///
+    /// Other `IntrusivePtr`s, or C++ code, may also refer to the object,
+    /// so the caller must ensure that nothing else accesses it while the
+    /// result exists.
+    pub unsafe fn pin_mut(&mut self) -> Pin<&mut T> {
+        Pin::new_unchecked(self.0.as_mut())
+    }
+}
+
+impl<T: IntrusiveRefCounted> Clone for IntrusivePtr<T> {
+    fn clone(&self) -> Self {
+        // Safety: we hold a reference, so the object is alive.
+        unsafe { T::add_ref(self.as_ptr()) };
+        Self(self.0)
+    }
+}
+
+impl<T: IntrusiveRefCounted> Drop for IntrusivePtr<T> {
+    fn drop(&mut self) {
+        // Safety: we hold a reference, which we now give up.
+        unsafe { T::release(self.as_ptr()) }
+    }
+}
+
+impl<T: IntrusiveRefCounted> Deref for IntrusivePtr<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: we hold a reference, so the object is alive.
+        unsafe { self.0.as_ref() }
+    }
+}
+
+impl<T: IntrusiveRefCounted> fmt::Debug for IntrusivePtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IntrusivePtr").field(&self.0).finish()
+    }
+}
@@ -17,225 +17,1526 @@
 // do anything - all the magic is handled entirely by
 // autocxx_macro::include_cpp_impl.
 
+mod batch;
+pub mod blocking;
+mod callback_registration;
+mod cpp_callable;
+mod intrusive_ptr;
+pub mod oneshot;
+mod ostream_writer;
+mod outlives;
+mod pod_vector;
+mod protobuf_message;
 mod reference_wrapper;
 mod rvalue_param;
+mod string_value;
 pub mod subclass;
+mod thread_affinity;
+mod thread_handle;
 mod value_param;
 
+pub use batch::BatchArg;
+pub use callback_registration::CallbackRegistration;
+#[doc(hidden)]
+pub use cpp_callable::CppCallable;
+pub use intrusive_ptr::{IntrusivePtr, IntrusiveRefCounted};
+#[doc(hidden)]
+pub use ostream_writer::OstreamWriter;
+pub use outlives::{Dependent, DependsOn, SharedDependency};
+pub use pod_vector::{PodVectorElement, PodVectorExt};
+pub use protobuf_message::ProtobufMessage;
 pub use reference_wrapper::{
     AsCppMutRef, AsCppRef, CppLtRef, CppMutLtRef, CppMutRef, CppPin, CppRef, CppUniquePtrPin,
+    CppView, NullUniquePtr,
 };
+pub use string_value::CxxStringValue;
+#[doc(hidden)]
+pub use thread_affinity::ThreadAffinity;
+pub use thread_handle::{Dispatcher, ThreadHandle};
+
+#[cfg_attr(doc, aquamarine::aquamarine)]
+/// Include some C++ headers in your Rust project.
+///
+/// This macro allows you to include one or more C++ headers within
+/// your Rust code, and call their functions fairly naturally.
+///
+/// # Examples
+///
+/// C++ header (`input.h`):
+/// ```cpp
+/// #include <cstdint>
+///
+/// uint32_t do_math(uint32_t a);
+/// ```
+///
+/// Rust code:
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "input.h"
+///     generate!("do_math")
+///     safety!(unsafe)
+/// );
+///
+/// # mod ffi { pub fn do_math(a: u32) -> u32 { a+3 } }
+/// # fn main() {
+/// ffi::do_math(3);
+/// # }
+/// ```
+///
+/// The resulting bindings will use idiomatic Rust wrappers for types from the [cxx]
+/// crate, for example [`cxx::UniquePtr`] or [`cxx::CxxString`]. Due to the care and thought
+/// that's gone into the [cxx] crate, such bindings are pleasant and idiomatic to use
+/// from Rust, and usually don't require the `unsafe` keyword.
+///
+/// For full documentation, see [the manual](https://google.github.io/autocxx/).
+///
+/// # The [`include_cpp`] macro
+///
+/// Within the braces of the `include_cpp!{...}` macro, you should provide
+/// a list of at least the following:
+///
+/// * `#include "cpp_header.h"`: a header filename to parse and include
+/// * `generate!("type_or_function_name")`: a type or function name whose declaration
+///   should be made available to C++. (See the section on Allowlisting, below).
+/// * Optionally, `safety!(unsafe)` - see discussion of [`safety`].
+///
+/// Other directives are possible as documented in this crate.
+///
+/// The file in an `#include` may also be a C++ source file (`.cpp`, `.cc`,
+/// `.cxx` or `.c++`), such as a library shipped as a single amalgamated
+/// source file without a header. autocxx parses it just like a header, but
+/// the C++ it generates gets only the declarations from it: compile and link
+/// the source file yourself, as usual.
+///
+/// Now, try to build your Rust project. `autocxx` may fail to generate bindings
+/// for some of the items you specified with [generate] directives: remove
+/// those directives for now, then see the next section for advice.
+///
+/// # Allowlisting
+///
+/// How do you inform autocxx which bindings to generate? There are three
+/// strategies:
+///
+/// * *Recommended*: provide various [`generate`] directives in the
+///   [`include_cpp`] macro. This can specify functions or types.
+/// * *Not recommended*: in your `build.rs`, call `Builder::auto_allowlist`.
+///   This will attempt to spot _uses_ of FFI bindings anywhere in your Rust code
+///   and build the allowlist that way. This is experimental and has known limitations.
+/// * *Strongly not recommended*: use [`generate_all`]. This will attempt to
+///   generate Rust bindings for _any_ C++ type or function discovered in the
+///   header files. This is generally a disaster if you're including any
+///   remotely complex header file: we'll try to generate bindings for all sorts
+///   of STL types. This will be slow, and some may well cause problems.
+///   Effectively this is just a debug option to discover such problems. Don't
+///   use it!
+///
+/// # Internals
+///
+/// For documentation on how this all actually _works_, see
+/// `IncludeCppEngine` within the `autocxx_engine` crate.
+#[macro_export]
+macro_rules! include_cpp {
+    (
+        $(#$include:ident $lit:literal)*
+        $($mac:ident!($($arg:tt)*))*
+    ) => {
+        $($crate::$include!{__docs})*
+        $($crate::$mac!{__docs})*
+        $crate::include_cpp_impl! {
+            $(#include $lit)*
+            $($mac!($($arg)*))*
+        }
+    };
+}
+
+/// Include a C++ header. A directive to be included inside
+/// [include_cpp] - see [include_cpp] for details
+#[macro_export]
+macro_rules! include {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate Rust bindings for the given C++ type or function.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate_pod].
+#[macro_export]
+macro_rules! generate {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate as "plain old data" and add to allowlist.
+/// Generate Rust bindings for the given C++ type such that
+/// it can be passed and owned by value in Rust. This only works
+/// for C++ types which have trivial move constructors and no
+/// destructor - you'll encounter a compile error otherwise.
+/// If your type doesn't match that description, use [generate]
+/// instead, and own the type using [UniquePtr][cxx::UniquePtr].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! generate_pod {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make any [generate_pod] type which can't be POD a dedicated error,
+/// naming the chain of fields which leads to the problem, for example
+/// `ns::Outer::inner -> ns::Inner::name`. That makes it easy to see which
+/// header change broke POD-ness. See also [allow_pod_downgrade].
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! strict_pod {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make functions in anonymous namespaces callable. Such functions have
+/// internal linkage, so Rust can't link to them, and by default autocxx
+/// skips them, including when expanding [generate_ns]. With this
+/// directive, autocxx instead calls each one from a generated C++
+/// wrapper, which works if the function is defined inline in a header:
+/// the wrapper's translation unit then has its own copy. Any `static`
+/// local variables within such a function are therefore separate from
+/// those seen by other C++ translation units.
+///
+/// These functions appear in Rust without the anonymous namespace, so
+/// `namespace mylib { namespace { inline int helper(); } }` becomes
+/// `ffi::mylib::helper`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! internal_linkage_shims {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Confine a [generate]d type to a single thread, for example
+/// `thread_affine!("ns::Widget")`, for C++ classes which must only be
+/// touched by the thread that created them (typically GUI toolkit
+/// objects). The type is neither `Send` nor `Sync`, and in debug builds
+/// each of its methods panics if called on an object from a thread other
+/// than the one which owns it. An object constructed from Rust is owned by
+/// the thread which constructed it; one created by C++ is owned by the
+/// first thread to call a method on it. Static methods and free functions
+/// taking the type aren't checked. Not allowed for [generate_pod] types,
+/// since Rust could copy those between threads.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! thread_affine {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// `thread_handle!("ns::Widget")` declares a type [thread_affine], and
+/// also generates a `WidgetHandle` which can be sent to, and cloned
+/// across, other threads. Its `unsafe` constructor takes ownership of a
+/// `UniquePtr<Widget>` along with a [Dispatcher] which runs tasks on the
+/// widget's own thread, typically by posting them to its event loop; the
+/// caller promises that's the thread which owns the widget. Each method of the handle
+/// hands the call to the dispatcher and returns a future which resolves
+/// to the result. Only methods whose parameters and return value are
+/// numbers or `bool`s are forwarded; for anything else, `run` calls a
+/// closure with the widget on its own thread. The widget is destroyed on
+/// its own thread once the last handle is dropped.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! thread_handle {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Allow a [generate_pod] type to be generated as non-POD, as if by
+/// [generate], if it can't be POD, for example
+/// `allow_pod_downgrade!("ns::Config")`. Rather than failing, autocxx then
+/// lists the type and the reason in an `AUTOCXX_POD_DOWNGRADES` constant
+/// in the generated mod, so the fallback is visible in the API.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! allow_pod_downgrade {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate Rust bindings for a C++ type, but with only the listed
+/// member functions, for example
+/// `generate_member!("ns::BigClass::small_method")`. Use this
+/// directive once for each member function wanted. Other member
+/// functions of the type are ignored entirely, which cuts down the
+/// generated code and avoids any problems in generating them.
+/// Overloads of a listed member function are all generated. The type's
+/// default, copy and move constructors, destructor and assignment
+/// operators are always generated; to get other constructors, list them
+/// by the type's name, for example `generate_member!("ns::BigClass::BigClass")`.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate].
+#[macro_export]
+macro_rules! generate_member {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate Rust bindings for all C++ types and functions
+/// in a given namespace.
+///
+/// Options may follow the namespace name:
+/// * `max_depth = N` includes items within at most `N` levels of nested
+///   namespace; `0` means only items directly within the namespace.
+/// * `skip_detail_namespaces` leaves out any nested namespace named
+///   `detail`, `details`, `internal` or `impl`.
+/// * `pod = auto` makes each type "plain old data", as if it were listed
+///   in [generate_pod], if that's possible: that is, if it and all its
+///   fields have no user-written destructor, copy or move constructor,
+///   and no field which can't be held by value in Rust. Other types are
+///   generated as normal.
+///
+/// ```ignore
+/// generate_ns!("mylib", max_depth = 1, skip_detail_namespaces, pod = auto)
+/// ```
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate].
+#[macro_export]
+macro_rules! generate_ns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate Rust bindings for all C++ types and functions
+/// found. Highly experimental and not recommended.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate].
+#[macro_export]
+macro_rules! generate_all {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate as "plain old data". For use with [generate_all]
+/// and similarly experimental.
+#[macro_export]
+macro_rules! pod {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Skip the normal generation of a `make_string` function
+/// and other utilities which we might generate normally.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! exclude_utilities {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Give each polymorphic C++ type (that is, each with virtual functions)
+/// a `type_name()` method, which uses C++ RTTI to return the name of the
+/// most-derived type of the object. This is useful for logging: if you
+/// have a reference to a base class, it tells you which subclass it really
+/// is. The names are demangled where the C++ standard library permits.
+/// The C++ must be built with RTTI enabled.
+///
+/// ```ignore
+/// rtti_type_names!()
+/// ```
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! rtti_type_names {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Give each polymorphic C++ type an `object_identity()` method, which
+/// returns the address of the most-derived object as a `usize`, found
+/// using `dynamic_cast<const void*>`. References to different base
+/// subobjects of the same object have different addresses, but the same
+/// identity, so this is the value to use as a key when, for instance,
+/// keeping a map of observers some of which were registered through a
+/// base class and some through a derived one.
+///
+/// ```ignore
+/// object_identity!()
+/// ```
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! object_identity {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Return C++ `std::string`s by value rather than within a
+/// [`cxx::UniquePtr`]. Functions which return a `std::string` will instead
+/// return an `impl New<Output = CxxStringValue>`, so that the string can be
+/// put on the stack with `moveit!` (or into a `Box` with `Box::emplace`).
+/// This avoids the heap allocation for the `UniquePtr` itself, and for
+/// short strings, commonly any allocation at all. A `make_string_value()`
+/// function is also generated, to create such a string from Rust.
+/// Only return values are affected: `std::string` parameters are still
+/// taken as `UniquePtr<CxxString>` or `&CxxString`.
+///
+/// ```ignore
+/// strings_by_value!()
+/// ```
+/// See [`CxxStringValue`] for the platform assumptions this relies upon.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! strings_by_value {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Pass small POD types by value in registers. cxx passes any struct
+/// argument or return value through memory, via a pointer, even if it's
+/// just a pointer or a pair of integers. Given this directive, functions
+/// whose parameters and return value are all such small POD types (of
+/// at most two scalar or pointer fields) or primitives are instead
+/// called through a plain `extern "C"` function, so the platform's C
+/// calling convention applies.
+///
+/// ```ignore
+/// small_pods_in_registers!()
+/// ```
+/// The generated C++ uses `static_assert` to check that each such type is
+/// trivially copyable and no bigger than 16 bytes, since otherwise C++
+/// would pass it differently.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! small_pods_in_registers {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Present `std::ostream&` parameters to Rust as `&mut dyn std::io::Write`,
+/// so that C++ functions which log or dump to a stream can write straight
+/// into a Rust writer.
+///
+/// ```ignore
+/// ostream_as_writer!()
+/// ```
+/// The C++ function is given a stream whose buffer passes everything
+/// written to the Rust writer as it's written, and `flush`es it when the
+/// stream is flushed. Write errors put the stream into a failed state,
+/// as usual for C++ streams, rather than being reported to Rust.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! ostream_as_writer {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make generated functions report problems through their return types
+/// rather than by panicking, for code which can't tolerate panics.
+///
+/// ```ignore
+/// panic_free!()
+/// ```
+/// This changes the generated APIs as follows:
+/// * A function or method which returns a `std::unique_ptr` returns an
+///   `Option<UniquePtr<T>>`, which is `None` if C++ returned null, rather
+///   than a `UniquePtr` which might be null.
+/// * A function, method or constructor which takes any parameter by value
+///   or by rvalue reference (as an [`impl ValueParam<T>`](ValueParam) or
+///   [`impl RValueParam<T>`](RValueParam)) returns a `Result` whose error
+///   is [`NullUniquePtr`] if any such parameter is a null `UniquePtr`,
+///   instead of panicking. The parameters are checked before C++ is called.
+///
+/// Other generated code doesn't panic in the first place: enums convert
+/// from their underlying values with `from_bits`, which returns an
+/// `Option`, and strings are only ever converted lossily. The exception is
+/// a Rust subclass whose method is called by C++ after the subclass has
+/// been destroyed, or reentrantly; there's no way to report that to C++.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! panic_free {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Bind a message class generated by protoc.
+///
+/// ```ignore
+/// generate!("shop::Order")
+/// protobuf_message!("shop::Order")
+/// ```
+/// Methods which only the protobuf runtime uses, such as those dealing
+/// with arenas, descriptors and reflection, or with internals named with
+/// a leading underscore, are omitted entirely rather than reported as
+/// skipped. So are those which transfer ownership through raw pointers,
+/// such as `release_foo` and `set_allocated_foo`.
+///
+/// The type implements [`ProtobufMessage`], to serialize and parse it,
+/// and gets these accessors for its fields:
+/// * `foo_mut()`, which safely wraps `mutable_foo()`, returning a
+///   `Pin<&mut>` rather than a raw pointer.
+/// * `foo_iter()` for a repeated field of numbers, strings or messages,
+///   which iterates over it using `foo_size()` and `foo(index)`.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! protobuf_message {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implement `Default` for a POD type using C++ value-initialization, so
+/// that it can be built from a Rust struct literal naming only some of
+/// its fields, much like a C++ designated initializer.
+///
+/// ```ignore
+/// generate_pod!("geo::Point")
+/// pod_default!("geo::Point")
+/// ```
+/// Then `ffi::geo::Point { x: 1, ..Default::default() }` gives the same
+/// result as `geo::Point{ .x = 1 }` in C++: fields with default member
+/// initializers get those values, and the rest are zeroed. The default is
+/// also available as `ffi::geo::Point::value_initialized()`. The type must
+/// be POD; see [`generate_pod`].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! pod_default {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Give a simple POD type to cxx as a
+/// [shared struct](https://cxx.rs/shared.html), rather than as a type
+/// defined by bindgen, for structs such as configuration or messages
+/// which are designed to be shared between the languages.
+///
+/// ```ignore
+/// generate_pod!("net::Config")
+/// shared_struct!("net::Config")
+/// ```
+/// Then `ffi::net::Config` is defined within the `cxx::bridge`, and so
+/// can be used wherever cxx permits shared structs - for instance, by
+/// value in the signatures of `extern_rust_function!`s, or within a
+/// `Vec`. It derives `Clone`, `Copy`, `Debug` and `PartialEq`. The
+/// generated C++ stops cxx from defining the type again, and checks at
+/// compile time that the C++ type is trivially copyable and that Rust
+/// lays it out in the same way.
+///
+/// The type must also be generated with [`generate_pod`], mustn't be a
+/// template, nested in another type or have base classes, and all of its
+/// fields must be public numbers, `bool`s, or arrays of them. Other POD
+/// types with a field of this type still refer to bindgen's version of
+/// it.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! shared_struct {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate a scoped Rust guard for an RAII type - a type such as a lock
+/// guard or transaction which takes a reference to some owner in its
+/// constructor, does something to the owner, and undoes it in its
+/// destructor.
+///
+/// ```ignore
+/// generate!("db::Database")
+/// generate!("db::Transaction")
+/// scope_guard!("db::Transaction")
+/// ```
+/// Then `ffi::db::TransactionGuard<'a>` has a function for each of
+/// `Transaction`'s constructors, which constructs a `Transaction` on the
+/// C++ heap - so it needn't be movable - and borrows whatever was passed
+/// to the constructor for the lifetime `'a`. The `Transaction` is
+/// destroyed when the guard is dropped. The guard dereferences to the
+/// `Transaction`, and `pin_mut` gives mutable access to it.
+///
+/// ```ignore
+/// let mut db = ffi::db::Database::new().within_unique_ptr();
+/// {
+///     let _txn = ffi::db::TransactionGuard::new(db.pin_mut());
+///     // db can't be used again until _txn is dropped.
+/// }
+/// ```
+///
+/// Constructors which need conversion in Rust, such as those taking C++
+/// objects by value, and unsafe constructors, aren't included. Templated
+/// guards such as `std::lock_guard` must be given a concrete name with a
+/// `typedef` first.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! scope_guard {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make subclass overrides of a virtual method return a pointer or
+/// reference to a derived type, where the superclass's method returns one
+/// to the superclass, as C++ allows for covariant return types.
+///
+/// ```ignore
+/// subclass!("Shape", MyShape)
+/// covariant_return!("Shape::duplicate", "Circle")
+/// ```
+/// If `Shape::duplicate` returns `Shape*`, the C++ override generated for
+/// `MyShape` returns `Circle*`, and so does `duplicate` in the
+/// `Shape_methods` trait. `Circle` must be derived from `Shape`, and is
+/// generated as if it were named in [`generate`]. Since the superclass's
+/// implementation doesn't return a `Circle*`, there's no `duplicate_super`
+/// and every subclass must implement `duplicate`. This applies to all
+/// subclasses of `Shape`, since they share the `Shape_methods` trait.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! covariant_return {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Own a reference-counted C++ type from Rust using [`IntrusivePtr`],
+/// which calls the type's own methods to manage its reference count.
+///
+/// ```ignore
+/// generate!("gfx::Texture")
+/// intrusive_ptr!("gfx::Texture", add_ref = "AddRef", release = "Release")
+/// ```
+/// Then `IntrusivePtr<ffi::gfx::Texture>` calls `AddRef` when cloned and
+/// `Release` when dropped. Use [`IntrusivePtr::from_raw`] to adopt a
+/// pointer returned by C++ which already carries a reference, or
+/// [`IntrusivePtr::from_raw_add_ref`] to take a new one. This suits COM
+/// interfaces and the similar schemes used by game engines. The type
+/// must also be generated, for instance using [`generate`].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! intrusive_ptr {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Compile the out-of-line definitions of a header-only library, such as
+/// the `stb` libraries, into the C++ which autocxx generates.
+///
+/// ```ignore
+/// header_implementation!("stb_image.h", define = "STB_IMAGE_IMPLEMENTATION")
+/// ```
+/// The generated C++ file then starts with
+/// `#define STB_IMAGE_IMPLEMENTATION` and `#include "stb_image.h"`, so
+/// there's no need for a hand-written `.cc` file to provide them. Give
+/// `define` more than once to define several macros, or to give one a
+/// value, as in `define = "STBI_MAX_DIMENSIONS 4096"`. Each library must
+/// be named in only one `include_cpp!`, or its definitions will be
+/// duplicated at link time.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! header_implementation {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make many calls to simple methods of a type with a single call into
+/// C++, where the overhead of calling across the FFI boundary for each
+/// would dominate.
+///
+/// ```ignore
+/// generate!("gfx::Canvas")
+/// batch_methods!("gfx::Canvas", "SetPixel", "SetScale")
+/// ```
+/// This generates `ffi::gfx::Canvas_batch`, which has a method to record a
+/// call to each of `SetPixel` and `SetScale`, taking the same arguments,
+/// and `Canvas::run_batch`, which makes all the calls recorded in a batch:
+/// ```ignore
+/// let mut batch = ffi::gfx::Canvas_batch::new();
+/// for x in 0..1000 {
+///     batch.SetPixel(x, 0, 255);
+/// }
+/// canvas.as_mut().run_batch(&batch);
+/// ```
+/// Each method must return nothing and take only numbers or `bool`s, each
+/// of which implements [`BatchArg`], and mustn't be overloaded or static.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! batch_methods {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Ensure that objects of one C++ type are destroyed before the objects
+/// they depend on, where C++ merely documents that requirement.
+///
+/// ```ignore
+/// generate!("net::Connection")
+/// generate!("net::Session")
+/// outlives!("net::Connection", "net::Session")
+/// ```
+/// Then `net::Connection` implements [`DependsOn`], and can be owned by a
+/// [`Dependent`], which holds a [`SharedDependency`] on the session
+/// alongside the connection:
+/// ```ignore
+/// let session = autocxx::SharedDependency::new(ffi::net::Session::new().within_unique_ptr());
+/// let connection =
+///     session.with_pin_mut(|session| ffi::net::Connection::new(session).within_unique_ptr());
+/// let connection = autocxx::Dependent::new(connection, session.clone());
+/// drop(session); // the session lives on until the connection is dropped
+/// ```
+/// A type can depend on only one other type. Both must also be generated,
+/// for instance using [`generate`].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! outlives {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Choose how strictly to treat items which can't be generated.
+///
+/// ```ignore
+/// conversion_errors!(strict)
+/// ```
+/// By default, a directive which names something that doesn't exist is
+/// an error, but an item which exists yet can't be generated is skipped,
+/// with the reason given in its documentation. With `strict`, anything
+/// requested by [`generate`], [`generate_pod`] or [`generate_ns`] which was
+/// skipped also fails the build, and the error lists every such item
+/// and why it was skipped. Methods of requested types may still be
+/// skipped. With `lenient`, directives naming things which don't exist
+/// only produce warnings, which `autocxx_build` shows as cargo warnings and
+/// `autocxx-gen` prints to stderr. That's handy when exploring a new API.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! conversion_errors {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Parse the headers with Microsoft extensions enabled, and map the
+/// MSVC-specific declarations used by COM-style APIs.
+///
+/// ```ignore
+/// msvc_extensions!()
+/// ```
+/// For each `__declspec(property(get = GetWidth, put = PutWidth)) int Width;`
+/// member, the class gains `get_Width` and `put_Width` methods which call
+/// the underlying accessors, so long as those accessors are themselves
+/// generated. For each type declared with `__declspec(uuid("..."))`, such
+/// that C++ code can use `__uuidof`, the Rust type gains an `IID` constant
+/// of type [`Guid`]. The generated C++ must be built with MSVC or
+/// `clang-cl`, or with clang's `-fms-extensions`.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! msvc_extensions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate a `meta` mod within the output mod, which maps between the
+/// original C++ names of types and their Rust paths at runtime. This helps
+/// when logging or reporting errors to people who know only the C++ API.
+///
+/// ```ignore
+/// name_table!()
+/// ```
+/// With this, `ffi::meta::cpp_name_of::<ffi::a::Foo>()` returns `"a::Foo"`,
+/// and `ffi::meta::rust_path_of("a::Foo")` returns `Some("ffi::a::Foo")`.
+/// `ffi::meta::NAMES` lists every pair. Only structs, enums and template
+/// instantiations are included; typedefs are just aliases in Rust so can't
+/// be told apart from their targets. This can't be used if the C++ has a
+/// top-level namespace called `meta`.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! name_table {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate Rust bindings for the given C++ function, and also an
+/// `async fn` wrapper with an `_async` suffix. For example,
+/// `generate_async!("fetch_data")` results in both `fetch_data` and
+/// `fetch_data_async`. The async version runs the underlying call
+/// using the executor registered with
+/// [`blocking::set_blocking_executor`], so that long-running C++
+/// calls don't block an async runtime.
+///
+/// This only applies to free functions (not methods), and only where
+/// all the parameters and the return value can be passed by value
+/// without conversion. Otherwise, neither function is generated, and the
+/// reason is given in the documentation of the item generated in its
+/// place; use `generate!` instead if you just want the normal function.
+/// The parameters and return type must be `Send`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! generate_async {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate an `async`-friendly wrapper for a C++ function which reports
+/// its result later by calling a callback, rather than returning it.
+/// The callback must be the function's final parameter, and be something
+/// which can be constructed from a lambda, such as a `std::function`.
+/// The directive gives the Rust types of the other parameters and of the
+/// value passed to the callback:
+///
+/// ```ignore
+/// completion_future!("ns::fetch", fn(u32) -> u64)
+/// ```
+///
+/// for C++ such as `void fetch(uint32_t key, std::function<void(uint64_t)> done)`.
+/// This results in `ffi::ns::fetch_future(key: u32)`, which returns an
+/// `impl Future<Output = Result<u64, autocxx::oneshot::Canceled>>`. The
+/// future resolves once C++ calls the callback, on whatever thread; if
+/// C++ instead destroys the callback without calling it, the result is
+/// [`oneshot::Canceled`]. Calls of the callback after the first are
+/// ignored. For a callback which takes no value, leave out the `-> T`.
+///
+/// Only primitive types (`bool`, integers and floats) are supported.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! completion_future {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Let a Rust closure be registered with a C++ function which takes a
+/// C-style callback: a function pointer whose first parameter is a
+/// `void*` context, followed by the context to pass it. These must be the
+/// function's only parameters. The directive gives the Rust types of the
+/// callback's remaining parameters:
+///
+/// ```ignore
+/// context_callback!("ns::add_listener", fn(u32, bool), unregister = "ns::remove_listener")
+/// ```
+///
+/// for C++ such as
+/// `void add_listener(void (*cb)(void* context, uint32_t id, bool up), void* context)`,
+/// and a matching `remove_listener` which takes the same two arguments.
+/// This results in `ffi::ns::add_listener_closure(callback)`, which takes
+/// any `Fn(u32, bool) + Send + Sync + 'static` and returns a
+/// [`CallbackRegistration`]. Dropping that calls `remove_listener` and
+/// then frees the closure, so `remove_listener` must not return while the
+/// callback may still be running or about to run.
+///
+/// Without `unregister = ...`, the generated function returns nothing and
+/// the closure is never freed, which suits callbacks registered once for
+/// the life of the program.
+///
+/// Only primitive types (`bool`, integers and floats) are supported, and
+/// the callback must return `void`. C++ may call the callback on any
+/// thread, even concurrently or re-entrantly, which is why the closure
+/// must be `Fn + Sync`; use a `Mutex` or atomics for any state it
+/// changes. A panic in the closure aborts the process.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! context_callback {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make a struct field which holds a pointer to a member function usable
+/// from Rust, as in the callback tables of many plugin ABIs. Rust can't
+/// represent such a pointer itself, so the directive gives the field's
+/// full name and the member function's signature, with a reference to the
+/// class as the first parameter - `&` for a `const` member function and
+/// `&mut` otherwise:
+///
+/// ```ignore
+/// generate!("plugin::Callbacks")
+/// generate!("plugin::Handler")
+/// member_fn_field!("plugin::Callbacks::on_event", fn(&plugin::Handler, i32) -> bool)
+/// ```
+///
+/// for C++ such as
+/// `struct Callbacks { bool (Handler::*on_event)(int32_t) const; };`.
+/// Then `ffi::plugin::Callbacks` has these methods:
+/// * `on_event()` returns a copy of the pointer, held in C++ as the opaque
+///   type `ffi::plugin::Callbacks_on_event`;
+/// * `set_on_event(ptr)` stores such a copy back into the field, and
+///   `clear_on_event()` sets it to null;
+/// * `has_on_event()` returns whether it's non-null;
+/// * `call_on_event_on(&handler, 42)` calls the member function on an
+///   object, panicking if the pointer is null.
+///
+/// To populate the field from Rust, name the member functions it may point
+/// to as `targets`:
+///
+/// ```ignore
+/// member_fn_field!("plugin::Callbacks::on_event", fn(&plugin::Handler, i32) -> bool, targets = ["handle", "ignore"])
+/// ```
+///
+/// Then `ffi::plugin::Callbacks_on_event::handle()` returns a pointer to
+/// `plugin::Handler::handle`, ready to pass to `set_on_event`.
+///
+/// The generated C++ checks that the signature matches the field. Only
+/// primitive types (`bool`, integers and floats) are supported as
+/// parameters and results. The struct can't be POD, since Rust doesn't
+/// know how large the pointer is, so must be generated with [`generate`],
+/// as must the class; it's an error if either isn't generated.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! member_fn_field {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Name a C++ function which must run before the rest of the library is
+/// used, such as an `Init()` or something which runs static registrars:
+///
+/// ```ignore
+/// init_function!("mylib::Init")
+/// ```
+///
+/// This generates `ffi::ensure_initialized()`, which calls `mylib::Init`
+/// the first time it's called and does nothing thereafter, even if called
+/// from several threads at once. Anything the function returns is
+/// discarded. `ensure_initialized()` is safe to call whatever the
+/// [safety] policy, so the initialization function must be safe to call
+/// at any time.
+///
+/// With `init_function!("mylib::Init", auto_in_debug)`, every generated
+/// free function, and every method for which autocxx generates a Rust
+/// wrapper, calls `ensure_initialized()` first in debug builds. Release
+/// builds omit these calls, so code must still call
+/// `ensure_initialized()` itself.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! init_function {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Entirely block some type from appearing in the generated
+/// code. This can be useful if there is a type which is not
+/// understood by bindgen or autocxx, and incorrect code is
+/// otherwise generated.
+/// This is 'greedy' in the sense that any functions/methods
+/// which take or return such a type will _also_ be blocked.
+/// See also [`opaque`].
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Instruct `bindgen` to generate a type as an opaque type -
+/// that is, without fields inside it. This should be used
+/// only when there's a need to workaround some `bindgen`
+/// issue where it's incorrectly generating the type.
+/// See the [bindgen documentation](https://rust-lang.github.io/rust-bindgen/opaque.html)
+/// for what exactly this means.
+///
+/// At first glance, this might seem to have no effect for
+/// types which are marked non-POD. However, it prevents
+/// autocxx from inferring whether the type has implicit
+/// constructors, and thus limits the options for even
+/// allocating the type. This should therefore only be used
+/// when trying to work around a bug.
+///
+/// The types which are generated when you use this are
+/// rather useless - it's hard to interact with them at all
+/// from within the generated Rust. Worse still, if they're
+/// included as members of any other type, those types are
+/// "infected" and also become useless (in the sense that
+/// we can't figure out what constructors those types might
+/// have). Use with caution and only when really needed.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! opaque {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Tell bindgen not to generate anything for items matching the given
+/// regex, for example `bindgen_blocklist!("detail::.*")`. Unlike
+/// [`block`], which works on the output of bindgen, this stops bindgen
+/// from looking at the items at all, which can help with C++ which it
+/// can't handle. Anything which refers to such items will be left out
+/// of the generated bindings, much as for [`block`]. Similarly,
+/// [`opaque`] is passed directly to bindgen and so may also be a regex.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! bindgen_blocklist {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Pass an extra argument to clang when it parses the headers for this
+/// [include_cpp], for example `clang_arg!("-DNO_EXOTIC_TEMPLATES")`.
+/// This affects only how bindgen sees the headers; it isn't used when
+/// the generated C++ is compiled, so take care not to change the layout
+/// of any types.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! clang_arg {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Parse the headers for this [include_cpp] using the given C++ standard,
+/// for example `cpp_std!("c++23")`, overriding any `-std` argument given
+/// to the builder for the build as a whole. This lets one set of headers
+/// use a later standard while the rest of the project stays on an earlier
+/// one. As with [`clang_arg`], this affects only how bindgen sees the
+/// headers; the generated C++ for this [include_cpp] must also be compiled
+/// with a suitable standard.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! cpp_std {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Avoid generating implicit constructors for this type.
+/// The rules for when to generate C++ implicit constructors
+/// are complex, and if autocxx gets it wrong, you can block
+/// such constructors using this.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block_constructors {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declare that a C++ type has an `operator bool`, which is common for
+/// status- and handle-like classes, for example `operator_bool!("ns::Status")`.
+/// The type then gets an `is_truthy(&self) -> bool` method, which performs
+/// the conversion (even if the operator is `explicit`). This is needed
+/// because conversion operators aren't otherwise visible to autocxx.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! operator_bool {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Import a C++20 module, for example `import_module!("geometry")`.
+/// Declarations exported by the module can then be named in `generate!`
+/// and similar directives, just like those from `#include`d headers.
+///
+/// The module's interface unit must be supplied to the build, for example
+/// using `autocxx_build::Builder::cpp_module`, which precompiles it before
+/// bindings are generated. C++20 or later is required.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! import_module {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declare that a C++ type is a view into some other object, for example
+/// `view_type!("ns::Span")`. Iterators, spans and string views are typical
+/// examples. Any method which returns such a type by value then returns
+/// a [`CppView`] which borrows from the method's receiver, so that
+/// the borrow checker can catch views which outlive their container.
+///
+/// This works only where the view is returned directly, which in practice
+/// means that the view type should be POD (see `generate_pod!`) or
+/// returned within a `std::unique_ptr`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! view_type {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declare which argument a function's returned reference borrows from,
+/// for example `returns_lifetime_of_arg!("ns::find", arg = 0)`. Arguments
+/// are counted from zero, not including the receiver of a method.
+///
+/// Ordinarily, a function returning a reference must take exactly one
+/// reference parameter, for the result to borrow from. With this
+/// directive, it may take any number: the generated signature is like
+/// `fn find<'a>(haystack: &'a Haystack, needle: &Needle) -> &'a Item`,
+/// so the result borrows from the named argument alone, even for a method.
+/// The same goes for a type declared with [`view_type!`], which is then
+/// returned as a [`CppView`] borrowing from that argument rather than
+/// from the receiver.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! returns_lifetime_of_arg {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Present a pair of `(begin, end)` iterator arguments to Rust as a single
+/// slice, for example `iterator_range!("ns::sum", begin = 0)` for
+/// `uint32_t sum(const uint32_t* first, const uint32_t* last)`, which
+/// becomes `fn sum(first: &[u32]) -> u32`. `begin` gives the index of the
+/// first of the pair, counted from zero, not including the receiver of a
+/// method; the next argument is the end.
+///
+/// Both arguments must be pointers to the same type, which must be a
+/// primitive or a [`generate_pod!`] type. They become a `&[T]` if they're
+/// `const`, or a `&mut [T]` otherwise, and the generated C++ passes the
+/// start and end of that slice. This suits STL-algorithm-style functions
+/// over contiguous data, which can then be called on a `Vec`, an array or
+/// a [`CxxVector`](cxx::CxxVector)'s `as_slice()` without creating any
+/// iterators.
+///
+/// Only pointers are supported: iterator classes such as
+/// `std::vector<T>::iterator`, and references to containers, aren't.
+/// Nor are virtual functions. If the named function can't be generated
+/// with a slice for any of these reasons, code generation fails.
+///
+/// Other directives which refer to arguments by index, such as
+/// [`returns_lifetime_of_arg!`], still count them as C++ does.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! iterator_range {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
 
-#[cfg_attr(doc, aquamarine::aquamarine)]
-/// Include some C++ headers in your Rust project.
+/// Generate a trait containing the methods of a C++ type, typically an
+/// abstract interface, so that Rust code can be written against the trait
+/// and unit tested without the real C++ implementation. For example,
+/// `mockable!("ns::Logger")` generates `ffi::ns::Logger_interface`, which
+/// is implemented for `Pin<&mut Logger>` by calling into C++.
 ///
-/// This macro allows you to include one or more C++ headers within
-/// your Rust code, and call their functions fairly naturally.
+/// The trait is annotated with `#[cfg_attr(feature = "mockall",
+/// mockall::automock)]`. If your crate has a `mockall` feature which
+/// enables a dependency on the [mockall](https://docs.rs/mockall) crate,
+/// a `MockLogger_interface` type will be generated too.
 ///
-/// # Examples
+/// Methods are only included if they take and return types which can be
+/// passed by value or by plain reference; methods returning references,
+/// or needing parameter conversions such as `impl ToCppString`, are
+/// omitted from the trait.
 ///
-/// C++ header (`input.h`):
-/// ```cpp
-/// #include <cstdint>
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! mockable {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Load further directives from a file, for example
+/// `config_file!("autocxx/networking.directives")`. The file contains
+/// directives written just as they would be within [include_cpp], so
+/// long lists of directives can be split up by subsystem, or shared
+/// between several [include_cpp] invocations. Config files may
+/// themselves contain `config_file!` directives.
 ///
-/// uint32_t do_math(uint32_t a);
-/// ```
+/// The path is relative to the directory containing your `Cargo.toml`,
+/// or to the current directory if you're building without cargo (for
+/// example, using `autocxx-gen`).
 ///
-/// Rust code:
-/// ```
-/// # use autocxx_macro::include_cpp_impl as include_cpp;
-/// include_cpp!(
-/// #   parse_only!()
-///     #include "input.h"
-///     generate!("do_math")
-///     safety!(unsafe)
-/// );
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! config_file {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Additionally expose a bound function or method as a flat `extern "C"`
+/// symbol, so that other languages (for example Python using `ctypes`, or
+/// Go using `cgo`) can call it through the same shim that autocxx
+/// generates for Rust. For example, `export_c!("ns::Foo::bar", "foo_bar")`
+/// generates a C++ function `foo_bar` whose first parameter is a pointer
+/// to the `ns::Foo` on which to call `bar`.
 ///
-/// # mod ffi { pub fn do_math(a: u32) -> u32 { a+3 } }
-/// # fn main() {
-/// ffi::do_math(3);
-/// # }
-/// ```
+/// References become pointers. Functions which take or return types that
+/// C can't represent, such as a `std::unique_ptr` or a C++ type by value
+/// which isn't POD, can't be exported, and an error is reported in their
+/// place. Overloaded functions can't be exported, since each overload
+/// would need its own symbol.
 ///
-/// The resulting bindings will use idiomatic Rust wrappers for types from the [cxx]
-/// crate, for example [`cxx::UniquePtr`] or [`cxx::CxxString`]. Due to the care and thought
-/// that's gone into the [cxx] crate, such bindings are pleasant and idiomatic to use
-/// from Rust, and usually don't require the `unsafe` keyword.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! export_c {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Represent a C++ typedef of an integer handle as an opaque Rust newtype,
+/// for example `opaque_handle!("TextureHandle")` given
+/// `typedef uint64_t TextureHandle;`. This is like [`newtype`], except
+/// that the underlying integer is private and there are no conversions
+/// to or from integers, so a handle of one kind can't accidentally be
+/// passed where a different kind, or a plain number, is expected.
+/// Explicit `from_raw` and `to_raw` methods are available for the rare
+/// occasions when the underlying value is needed.
 ///
-/// For full documentation, see [the manual](https://google.github.io/autocxx/).
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! opaque_handle {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Fail the build if the given item would be generated, for example
+/// `assert_not_generated!("ns::Dangerous")`. Items can be generated even
+/// though they're not in a `generate!` directive, because something
+/// which is generated depends on them; this directive can be used to be
+/// sure that APIs which have been ruled out (for instance, during a
+/// security review) don't sneak back in when headers change. Methods are
+/// named as `ns::Type::method`.
 ///
-/// # The [`include_cpp`] macro
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! assert_not_generated {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Represent a C++ typedef of an integer type as a distinct Rust newtype,
+/// rather than as a plain alias. For example, given `using Fd = int;`,
+/// `newtype!("Fd")` generates
+/// `pub struct Fd(pub autocxx::c_int)`, and every function which
+/// takes or returns an `Fd` uses that type rather than `c_int`, so the
+/// intent of the typedef isn't lost.
 ///
-/// Within the braces of the `include_cpp!{...}` macro, you should provide
-/// a list of at least the following:
+/// The newtype implements `TryFrom<i64>` and `TryFrom<u64>`, which fail
+/// if the value doesn't fit in the underlying type, and can be converted
+/// back into the underlying type using `Into`.
 ///
-/// * `#include "cpp_header.h"`: a header filename to parse and include
-/// * `generate!("type_or_function_name")`: a type or function name whose declaration
-///   should be made available to C++. (See the section on Allowlisting, below).
-/// * Optionally, `safety!(unsafe)` - see discussion of [`safety`].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! newtype {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Present a static factory method as a constructor, for example
+/// `constructor_from!("ns::Foo::Create")`. The factory must return either
+/// a `std::unique_ptr<Foo>` or a `Foo*` whose ownership passes to the
+/// caller. It then appears in Rust as `Foo::new(...)`, returning
+/// `Option<UniquePtr<Foo>>`, which is `None` if the factory returned null.
+/// If `Foo` also has constructors, they're numbered `new1` and so on
+/// instead.
 ///
-/// Other directives are possible as documented in this crate.
+/// A factory returning a `Foo*` is `unsafe` to call, since the pointer
+/// is adopted into a `UniquePtr` and so must have been allocated with
+/// `new` and owned by nobody else.
 ///
-/// Now, try to build your Rust project. `autocxx` may fail to generate bindings
-/// for some of the items you specified with [generate] directives: remove
-/// those directives for now, then see the next section for advice.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! constructor_from {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declare that a C++ type is a singleton, for example
+/// `singleton!("ns::Registry")`. Such types typically delete their copy
+/// and move constructors and are reached only through a static accessor
+/// such as `static Registry& instance()`. The references returned by the
+/// type's static methods are given a `'static` lifetime, so
+/// `ffi::ns::Registry::const_instance()` returns `&'static Registry`.
+/// A static method returning a mutable reference, such as
+/// `ffi::ns::Registry::instance()`, returns `Pin<&'static mut Registry>`
+/// but is `unsafe`: calling it twice would give two mutable references to
+/// the same object, so the caller must ensure only one is live at a time.
+/// No `UniquePtr` or other owning smart pointer support is generated for
+/// the type.
 ///
-/// # Allowlisting
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! singleton {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Declare that objects of a C++ type are owned by C++, for example by a
+/// registry, and must never be destroyed from Rust:
+/// `no_drop!("ns::Registry")`. When Rust drops such an object, for
+/// instance one placed in a `Box` or on the stack with `moveit!`, its
+/// destructor isn't called. With `no_drop!("ns::Registry", assert)` a C++
+/// `assert` also fails, unless `NDEBUG` is defined, which helps to find
+/// the code which dropped it. The default behaviour can be spelled out
+/// as `no_drop!("ns::Registry", leak)`.
 ///
-/// How do you inform autocxx which bindings to generate? There are three
-/// strategies:
+/// Only the destructor is skipped: the memory which Rust allocated for the
+/// object, such as the `Box` or the stack slot, is still freed, so C++
+/// mustn't keep pointers to objects which Rust constructed. To keep such
+/// an object alive for good, use `Box::leak` or `std::mem::forget` rather
+/// than dropping it.
 ///
-/// * *Recommended*: provide various [`generate`] directives in the
-///   [`include_cpp`] macro. This can specify functions or types.
-/// * *Not recommended*: in your `build.rs`, call `Builder::auto_allowlist`.
-///   This will attempt to spot _uses_ of FFI bindings anywhere in your Rust code
-///   and build the allowlist that way. This is experimental and has known limitations.
-/// * *Strongly not recommended*: use [`generate_all`]. This will attempt to
-///   generate Rust bindings for _any_ C++ type or function discovered in the
-///   header files. This is generally a disaster if you're including any
-///   remotely complex header file: we'll try to generate bindings for all sorts
-///   of STL types. This will be slow, and some may well cause problems.
-///   Effectively this is just a debug option to discover such problems. Don't
-///   use it!
+/// No `UniquePtr`, `SharedPtr` or `WeakPtr` support is generated for the
+/// type, since those would delete the object from the C++ side.
 ///
-/// # Internals
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! no_drop {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Treat the accessors of a C++ type as Rust-style getters. Any method
+/// which takes no parameters, returns something, and whose name starts
+/// with the given prefix followed by a capital letter, is renamed by
+/// stripping the prefix and converting the rest to snake case, and is
+/// marked `#[must_use]`. For example,
+/// `getters!("ns::Widget", strip_prefix = "Get")` turns
+/// `Widget::GetWidth()` into `width()` and `Widget::GetBorderColor()`
+/// into `border_color()`. The prefix defaults to `Get`.
 ///
-/// For documentation on how this all actually _works_, see
-/// `IncludeCppEngine` within the `autocxx_engine` crate.
+/// Other methods of the type are unaffected, so this is a more targeted
+/// alternative to converting the case of every name.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! include_cpp {
-    (
-        $(#$include:ident $lit:literal)*
-        $($mac:ident!($($arg:tt)*))*
-    ) => {
-        $($crate::$include!{__docs})*
-        $($crate::$mac!{__docs})*
-        $crate::include_cpp_impl! {
-            $(#include $lit)*
-            $($mac!($($arg)*))*
-        }
-    };
+macro_rules! getters {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Include a C++ header. A directive to be included inside
-/// [include_cpp] - see [include_cpp] for details
+/// Group the getter and setter pairs of a C++ type into a Rust-style view,
+/// for example `property_accessors!("ns::Config")`. For each pair of
+/// methods `GetX()` and `SetX(value)`, where the getter is `const`, takes
+/// no parameters and returns something, and the setter takes a single
+/// parameter and returns nothing, the generated `ConfigFields` has `x()`
+/// and `set_x(value)` methods which call them. It's obtained with
+/// `Config::fields` from a `Pin<&mut Config>`, so
+/// `config.pin_mut().fields().set_width(3)` calls `SetWidth(3)`. Each
+/// accessor's documentation names the C++ method it calls.
+///
+/// The original methods remain available. A getter or setter which doesn't
+/// have a partner, or which returns a reference or needs parameter
+/// conversions, isn't included.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! include {
+macro_rules! property_accessors {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Generate Rust bindings for the given C++ type or function.
+/// Implement `std::error::Error` and `Display` for a C++ exception or
+/// status type, so that it can be formatted, used as a `&dyn Error`
+/// source, or turned into an `anyhow` error with `anyhow!("{e}")`,
+/// without writing a wrapper by hand. The message comes from a method
+/// which returns a `const char*` or `std::string`: `what` by default,
+/// which suits anything derived from `std::exception`, or another method
+/// given as `error_type!("absl::Status", message = "message")`.
+///
+/// The type also gets an `error_message()` method returning the message
+/// as a `UniquePtr<CxxString>`, and a `Debug` implementation which
+/// includes it.
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
-/// See also [generate_pod].
 #[macro_export]
-macro_rules! generate {
+macro_rules! error_type {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Generate as "plain old data" and add to allowlist.
-/// Generate Rust bindings for the given C++ type such that
-/// it can be passed and owned by value in Rust. This only works
-/// for C++ types which have trivial move constructors and no
-/// destructor - you'll encounter a compile error otherwise.
-/// If your type doesn't match that description, use [generate]
-/// instead, and own the type using [UniquePtr][cxx::UniquePtr].
+/// Expose a C++ conversion operator, such as `operator int()` or
+/// `operator std::string() const`, as a method. For example,
+/// `conversion_operator!("ns::Foo", "std::string")` gives `Foo` a
+/// `to_string(&self)` method, which returns whatever autocxx would
+/// return for a C++ function returning `std::string` - in this case
+/// a `UniquePtr<CxxString>`. The method name is `to_` followed by the
+/// final part of the target type name, in snake case.
+///
+/// Add a third argument, `impl_from`, to also implement `From<&Foo>`
+/// for the returned type, so that you can use `.into()`. This is only
+/// done if the conversion method is safe to call.
+///
+/// Conversion operators aren't otherwise visible to autocxx, so it's
+/// up to you to ensure that the operator exists.
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! generate_pod {
+macro_rules! conversion_operator {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Generate Rust bindings for all C++ types and functions
-/// in a given namespace.
+/// Declare that a type which the parsed headers only forward-declare
+/// is in fact complete wherever the generated C++ is compiled, e.g.
+/// `complete_type!("ns::Foo", size = 16, align = 8)`. Normally
+/// autocxx refuses to put forward-declared types into a `UniquePtr`
+/// because it can't know that they can be destroyed; with this
+/// directive, `UniquePtr<Foo>`, `SharedPtr<Foo>` and `WeakPtr<Foo>`
+/// are allowed, and dropping them runs the C++ destructor. The type
+/// still can't be held by value in Rust.
+///
+/// If the complete definition lives in a header which autocxx can't
+/// parse, name it with a final `header = "foo_impl.h"` argument and
+/// it will be included only by the generated C++. Either way, the
+/// generated C++ contains a `static_assert` that the size and
+/// alignment match what you've declared, so the build fails if the
+/// type is still incomplete or has changed layout.
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
-/// See also [generate].
 #[macro_export]
-macro_rules! generate_ns {
+macro_rules! complete_type {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Generate Rust bindings for all C++ types and functions
-/// found. Highly experimental and not recommended.
+/// Declare that a POD type has an equivalent Rust type, for example
+/// `rust_equivalent!("ns::Point", crate::geometry::Point)`. autocxx
+/// then generates `From` conversions in both directions, and
+/// `PartialEq` in both directions, so you don't need to write adapter
+/// functions by hand.
+///
+/// By default, the Rust type must have the same field names as the C++
+/// type: each field is converted using `Into` and compared using `==`.
+/// Otherwise, give functions which convert each way, and the comparisons
+/// will convert the C++ value to the Rust type:
+///
+/// ```ignore
+/// rust_equivalent!(
+///     "chrono::Duration",
+///     std::time::Duration,
+///     from_cpp = crate::duration_from_cpp, // fn(&ffi::chrono::Duration) -> std::time::Duration
+///     to_cpp = crate::duration_to_cpp      // fn(&std::time::Duration) -> ffi::chrono::Duration
+/// )
+/// ```
+///
+/// The C++ type must also be listed in [`generate_pod`], or else be given
+/// by [`extern_cpp_type`], in which case its fields aren't known, so
+/// conversion functions are required. Paths should be absolute (e.g.
+/// starting with `crate::`).
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
-/// See also [generate].
 #[macro_export]
-macro_rules! generate_all {
+macro_rules! rust_equivalent {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Generate as "plain old data". For use with [generate_all]
-/// and similarly experimental.
+/// Keep a renamed item available under its old name for a while, so that
+/// crates using these bindings can migrate gradually. For example, after
+/// adding `getters!("ns::Widget")`, the directive
+/// `deprecated_alias!("ns::Widget::width", "GetWidth")` also generates a
+/// `GetWidth()` method which does the same as `width()` but is marked
+/// `#[deprecated]`. The first argument is the new name, as listed in
+/// `AUTOCXX_RENAMED_ITEMS`: namespace, then type for a method, then Rust
+/// name. It may also name a free function or a type, for which a
+/// deprecated type alias is generated.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! pod {
+macro_rules! deprecated_alias {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Skip the normal generation of a `make_string` function
-/// and other utilities which we might generate normally.
+/// Choose the Rust name for one particular constructor, identified by
+/// its C++ signature, for example
+/// `name_constructor!("ns::Foo(int, int)", "from_dimensions")`.
+/// Constructors are otherwise called `new`, `new1`, `new2` and so on in
+/// declaration order, so those names change whenever a constructor is
+/// added or removed. Parameter types are spelled as in C++ (e.g.
+/// `const std::string&`); whitespace doesn't matter.
+///
+/// It's an error if no constructor has the given signature, so a change
+/// to the header can't silently switch your code to calling a different
+/// constructor.
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! exclude_utilities {
+macro_rules! name_constructor {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Entirely block some type from appearing in the generated
-/// code. This can be useful if there is a type which is not
-/// understood by bindgen or autocxx, and incorrect code is
-/// otherwise generated.
-/// This is 'greedy' in the sense that any functions/methods
-/// which take or return such a type will _also_ be blocked.
-/// See also [`opaque`].
+/// Choose the Rust names for each overload of a C++ function or method,
+/// in the order they're declared. For example,
+/// `overload_names!("ns::Foo::set", "set_int", "set_string")`.
+/// By default the first overload keeps its name and later ones have
+/// a number appended (`set1`, `set2`...), skipping any name already
+/// in use. Overloads beyond those listed here get that default naming.
+///
+/// Every function or method which ends up with a Rust name different
+/// from its C++ name is listed in the `AUTOCXX_RENAMED_ITEMS` constant
+/// at the top level of the generated mod.
 ///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! block {
+macro_rules! overload_names {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Instruct `bindgen` to generate a type as an opaque type -
-/// that is, without fields inside it. This should be used
-/// only when there's a need to workaround some `bindgen`
-/// issue where it's incorrectly generating the type.
-/// See the [bindgen documentation](https://rust-lang.github.io/rust-bindgen/opaque.html)
-/// for what exactly this means.
+/// Treat a non-`const` C++ method as though it were `const`,
+/// for example `treat_as_const!("ns::Foo::size")`.
+/// Some (often legacy) C++ methods don't modify their object but
+/// aren't marked `const`, which would otherwise force callers to
+/// use a `Pin<&mut T>` receiver. Methods listed here instead take
+/// `&self`, and the generated C++ shim uses `const_cast` before
+/// calling the original method. This applies to all overloads of
+/// the named method, but not to virtual methods. It's your
+/// responsibility to ensure the method really doesn't mutate the object.
 ///
-/// At first glance, this might seem to have no effect for
-/// types which are marked non-POD. However, it prevents
-/// autocxx from inferring whether the type has implicit
-/// constructors, and thus limits the options for even
-/// allocating the type. This should therefore only be used
-/// when trying to work around a bug.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! treat_as_const {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Mark the C++ wrapper which autocxx generates for a function or method
+/// as always to be inlined, for example `force_inline!("ns::Vec3::length")`.
+/// Wrappers live in a header, so this lets the compiler fold them into
+/// the `cxx`-generated shim which calls them, even in unoptimized builds
+/// or where cross-language LTO isn't available. Functions which cxx could
+/// otherwise call directly are given a wrapper so that there's something
+/// to inline. It's an error if no function or method has the given name,
+/// unless [`conversion_errors`] is `lenient`.
 ///
-/// The types which are generated when you use this are
-/// rather useless - it's hard to interact with them at all
-/// from within the generated Rust. Worse still, if they're
-/// included as members of any other type, those types are
-/// "infected" and also become useless (in the sense that
-/// we can't figure out what constructors those types might
-/// have). Use with caution and only when really needed.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! force_inline {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Return a copy of the string to which a C++ function or method returns a
+/// reference, for example `copy_string_return!("ns::Document::title")`.
+/// A function returning `const std::string&` is normally given a Rust
+/// return type of `&CxxString` (or a [`CppRef`] if C++ references are in
+/// use), which borrows the string without copying it, while one returning
+/// `std::string` by value returns an owned `UniquePtr<CxxString>`. This
+/// directive gives the former the same owned shape as the latter: the
+/// generated C++ copies the string, so the result doesn't borrow anything.
+/// That's handy for short strings which must outlive their owner, but
+/// leave it off anything which might return a large string, since every
+/// call then copies it. If [`strings_by_value!`] is also given, the copy is
+/// returned as an `impl New<Output = CxxStringValue>`.
 ///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! opaque {
+macro_rules! copy_string_return {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
-/// Avoid generating implicit constructors for this type.
-/// The rules for when to generate C++ implicit constructors
-/// are complex, and if autocxx gets it wrong, you can block
-/// such constructors using this.
+/// Present a C++ struct containing a tag and a union as a Rust enum,
+/// for example:
+/// ```ignore
+/// tagged_union!("ns::Event", tag = "type", variants = {
+///     1 => Key("ns::KeyEvent", "data.key"),
+///     2 => Mouse("ns::MouseEvent", "data.mouse"),
+/// })
+/// ```
+/// Each variant gives the value of the tag field for which it's active,
+/// a name for the Rust enum variant, the C++ type of the union member,
+/// and the path to that member from the outer struct.
+///
+/// This generates an `EventView<'a>` enum, with one variant per union
+/// member holding a reference to that member, and an implementation of
+/// `TryFrom<&Event>` which inspects the tag. The tag must be an integer
+/// or enum; unrecognized tags result in [`UnknownUnionTag`]. The outer
+/// struct and the member types must also be listed using `generate!`
+/// or similar.
 ///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
-macro_rules! block_constructors {
+macro_rules! tagged_union {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
@@ -336,6 +1637,19 @@ macro_rules! exclude_impls {
 /// all the right requirements. It's possible - but fragile - to
 /// define such types yourself.
 ///
+/// # Templates
+///
+/// A C++ class template with one type parameter may correspond to a
+/// generic Rust type, for a listed set of type arguments:
+/// `extern_cpp_type!("ns::Span<T>", crate::Span<T>, instantiations = ["uint32_t" => u32, "float" => f32])`.
+/// Each listed instantiation, such as `ns::Span<uint32_t>`, is then
+/// represented in Rust by the corresponding instantiation of the Rust
+/// type, such as `crate::Span<u32>`, and autocxx implements
+/// [`cxx::ExternType`] for it. Since autocxx writes those implementations,
+/// the generic Rust type must be defined in the same crate. These types
+/// can be used by reference or within a [`cxx::UniquePtr`], but not by
+/// value.
+///
 /// # Requirements for externally defined Rust types
 ///
 /// It's generally expected that you would make such a type
@@ -448,6 +1762,27 @@ pub use autocxx_macro::include_cpp_impl;
 #[doc(hidden)]
 pub use autocxx_macro::cpp_semantics;
 
+/// Add Rust methods to a type generated by [`include_cpp`]. Apply this to
+/// an inherent `impl` block, naming an extension trait to be generated:
+/// ```
+/// # use autocxx_macro::extend as extend;
+/// # mod ffi { pub struct Foo(pub u32); }
+/// #[extend(pub FooExt)]
+/// impl ffi::Foo {
+///     /// Twice the value.
+///     pub fn doubled(&self) -> u32 {
+///         self.0 * 2
+///     }
+/// }
+/// ```
+/// This produces a trait `FooExt` containing each of the methods, and an
+/// implementation of it for `ffi::Foo`. Callers bring the methods into
+/// scope by importing the trait, which works even from crates other than
+/// the one containing the bindings, where an inherent `impl` wouldn't be
+/// permitted. The trait is sealed, so it can't be implemented for any
+/// other type.
+pub use autocxx_macro::extend;
+
 macro_rules! ctype_wrapper {
     ($r:ident, $c:expr, $d:expr) => {
         #[doc=$d]
@@ -476,6 +1811,12 @@ macro_rules! ctype_wrapper {
                 val.0
             }
         }
+
+        impl crate::BatchArg for $r {
+            fn to_batch_word(self) -> u64 {
+                self.0.to_batch_word()
+            }
+        }
     };
 }
 
@@ -595,6 +1936,23 @@ pub mod extern_rust {
     /// pub fn call_me_from_cpp() { }
     /// ```
     ///
+    /// Such a function may take or return closures as `Box<dyn Fn(..) -> ..>`,
+    /// which C++ sees as `std::function`:
+    /// ```
+    /// # use autocxx_macro::extern_rust_function as extern_rust_function;
+    /// #[extern_rust_function]
+    /// pub fn make_adder(n: u32) -> Box<dyn Fn(u32) -> u32 + Send + Sync> {
+    ///     Box::new(move |x| x + n)
+    /// }
+    /// ```
+    /// The closures, and the function's other parameters and return value,
+    /// may only use numbers and `bool`. A closure passed from C++ owns the
+    /// `std::function`, which is destroyed when the closure is dropped; it
+    /// can't be `Send` or `Sync`, since the C++ callable may not be. A
+    /// closure returned to C++ must be `Send + Sync`, since C++ may copy the
+    /// `std::function` and call or destroy the copies on any thread. It's
+    /// dropped once the last copy is destroyed.
+    ///
     /// See [`extern_rust_type`] for details of how to find the generated
     /// declarations from C++.
     pub use autocxx_macro::extern_rust_function;
@@ -700,6 +2058,53 @@ where
     }
 }
 
+/// Constructs a C++ object from a tuple of the arguments to one of its
+/// constructors, so that generic code can construct any type for which a
+/// suitable constructor exists:
+///
+/// ```ignore
+/// fn make_fixture<T: CppNew<(u32, u32)>>() -> Pin<Box<T>> {
+///     T::cpp_new((640, 480)).within_box()
+/// }
+/// ```
+///
+/// autocxx implements this for each constructor which is safe to call and
+/// which takes its parameters by value, with `Args` being the tuple of
+/// their Rust types (`()` for the default constructor). Constructors
+/// taking references, or non-POD types by value, aren't included, since
+/// their Rust parameter types are generic. If two overloads take the same
+/// Rust types, only the first declared implements this. Automatically
+/// imported by the autocxx prelude.
+pub trait CppNew<Args>: Sized {
+    /// Construct this type, much as the inherent `new` function would.
+    fn cpp_new(args: Args) -> impl New<Output = Self>;
+}
+
+/// The error returned when converting a C++ tagged union (see
+/// [`tagged_union`]) into its Rust enum view, if its tag doesn't
+/// correspond to any of the variants listed in the directive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownUnionTag(pub i64);
+
+impl std::fmt::Display for UnknownUnionTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown tagged union tag {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownUnionTag {}
+
+/// A COM-style GUID, as given by `__declspec(uuid)` and retrieved in C++
+/// by `__uuidof`. See [`msvc_extensions`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
 use cxx::memory::UniquePtrTarget;
 use cxx::UniquePtr;
 use moveit::New;
@@ -731,10 +2136,13 @@ pub mod prelude {
     pub use crate::AsCppMutRef;
     pub use crate::AsCppRef;
     pub use crate::CppMutRef;
+    pub use crate::CppNew;
     pub use crate::CppPin;
     pub use crate::CppRef;
     pub use crate::CppUniquePtrPin;
     pub use crate::PinMut;
+    pub use crate::PodVectorExt;
+    pub use crate::ProtobufMessage;
     pub use crate::RValueParam;
     pub use crate::ValueParam;
     pub use crate::WithinBox;
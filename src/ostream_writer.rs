@@ -0,0 +1,54 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rust writers behind the C++ streams passed to functions taking
+//! `std::ostream&`, given [`ostream_as_writer`](crate::ostream_as_writer!).
+
+use std::io::Write;
+
+/// Holds a writer for the duration of a call to C++, which refers to it
+/// by the address returned from [`OstreamWriter::as_context`]. The C++
+/// stream buffer calls back into [`OstreamWriter::write`] and
+/// [`OstreamWriter::flush`] with that address.
+#[doc(hidden)]
+pub struct OstreamWriter<'a> {
+    writer: &'a mut dyn Write,
+}
+
+impl<'a> OstreamWriter<'a> {
+    pub fn new(writer: &'a mut dyn Write) -> Self {
+        Self { writer }
+    }
+
+    /// The address to give to C++, valid as long as this is neither moved
+    /// nor dropped.
+    pub fn as_context(&mut self) -> usize {
+        &mut self.writer as *mut &'a mut dyn Write as usize
+    }
+
+    /// Write all of `data`, returning whether that succeeded.
+    ///
+    /// # Safety
+    ///
+    /// `context` must have come from [`OstreamWriter::as_context`] on a
+    /// writer which is still alive and not otherwise in use.
+    pub unsafe fn write(context: usize, data: &[u8]) -> bool {
+        let writer = unsafe { &mut *(context as *mut &mut dyn Write) };
+        writer.write_all(data).is_ok()
+    }
+
+    /// Flush the writer, returning whether that succeeded.
+    ///
+    /// # Safety
+    ///
+    /// As for [`OstreamWriter::write`].
+    pub unsafe fn flush(context: usize) -> bool {
+        let writer = unsafe { &mut *(context as *mut &mut dyn Write) };
+        writer.flush().is_ok()
+    }
+}
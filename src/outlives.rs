@@ -0,0 +1,120 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Owning pointers to [`outlives`](crate::outlives!) types, which keep
+//! alive the object each depends on.
+
+use std::cell::{Ref, RefCell};
+use std::ops::Deref;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use cxx::memory::UniquePtrTarget;
+use cxx::UniquePtr;
+
+/// A C++ type whose objects must be destroyed before some other object
+/// which they depend on, for instance a connection which must be closed
+/// before its session. autocxx implements this for the first type in
+/// each `outlives!` directive; you shouldn't need to implement it
+/// yourself.
+pub trait DependsOn: UniquePtrTarget {
+    /// The type of the object which must outlive this one.
+    type Dependency: UniquePtrTarget;
+}
+
+/// A shared owner of an object which [`Dependent`] objects depend on. The
+/// object is destroyed once the last clone is dropped, including those
+/// held by its dependents.
+///
+/// The object can be borrowed, but never replaced or taken, since that
+/// would destroy it while dependents still need it.
+pub struct SharedDependency<T: UniquePtrTarget>(Rc<RefCell<UniquePtr<T>>>);
+
+impl<T: UniquePtrTarget> SharedDependency<T> {
+    /// Take ownership of `object`.
+    ///
+    /// # Panics
+    ///
+    /// If the pointer is null.
+    pub fn new(object: UniquePtr<T>) -> Self {
+        assert!(!object.is_null(), "SharedDependency of a null UniquePtr");
+        Self(Rc::new(RefCell::new(object)))
+    }
+
+    /// Borrow the object.
+    ///
+    /// # Panics
+    ///
+    /// If it's currently borrowed by [`with_pin_mut`](Self::with_pin_mut).
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.0.borrow(), |object| object.as_ref().unwrap())
+    }
+
+    /// Call `f` with a mutable reference to the object.
+    ///
+    /// # Panics
+    ///
+    /// If the object is already borrowed.
+    pub fn with_pin_mut<R>(&self, f: impl FnOnce(Pin<&mut T>) -> R) -> R {
+        f(self.0.borrow_mut().pin_mut())
+    }
+}
+
+impl<T: UniquePtrTarget> Clone for SharedDependency<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// An owning pointer to a C++ object, together with a shared owner of the
+/// object it depends on, so that the latter is destroyed only after the
+/// former.
+///
+/// Nothing checks that the dependency given to [`Dependent::new`] is
+/// really the object which `object` depends on; only that it's of the
+/// right type.
+pub struct Dependent<T: DependsOn> {
+    // Fields are dropped in order, so this must come first.
+    object: UniquePtr<T>,
+    dependency: SharedDependency<T::Dependency>,
+}
+
+impl<T: DependsOn> Dependent<T> {
+    /// Take ownership of `object`, keeping `dependency` alive until it's
+    /// destroyed.
+    pub fn new(object: UniquePtr<T>, dependency: SharedDependency<T::Dependency>) -> Self {
+        Self { object, dependency }
+    }
+
+    /// The object which this one depends on.
+    pub fn dependency(&self) -> &SharedDependency<T::Dependency> {
+        &self.dependency
+    }
+
+    /// The object, or `None` if the pointer is null.
+    pub fn as_ref(&self) -> Option<&T> {
+        self.object.as_ref()
+    }
+
+    /// A mutable reference to the object.
+    ///
+    /// # Panics
+    ///
+    /// If the pointer is null.
+    pub fn pin_mut(&mut self) -> Pin<&mut T> {
+        self.object.pin_mut()
+    }
+}
+
+impl<T: DependsOn> Deref for Dependent<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.object
+    }
+}
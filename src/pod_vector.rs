@@ -0,0 +1,60 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use cxx::kind::Trivial;
+use cxx::vector::VectorElement;
+use cxx::{CxxVector, ExternType};
+use std::pin::Pin;
+
+/// A POD type for which autocxx has generated C++ functions to copy
+/// whole buffers into and out of a `std::vector`. autocxx implements this
+/// for each POD type which can be put in a [`CxxVector`]; you shouldn't
+/// need to implement it yourself.
+///
+/// # Safety
+///
+/// `extend_vector` must append exactly `len` elements copied from `data`,
+/// and `copy_from_vector` must initialize `vec.len()` elements at `out`.
+pub unsafe trait PodVectorElement: VectorElement + ExternType<Kind = Trivial> {
+    #[doc(hidden)]
+    unsafe fn extend_vector(vec: Pin<&mut CxxVector<Self>>, data: *const Self, len: usize);
+    #[doc(hidden)]
+    unsafe fn copy_from_vector(vec: &CxxVector<Self>, out: *mut Self);
+}
+
+/// Bulk transfer between Rust and a C++ `std::vector` of POD types, each
+/// of which is a single call into C++, rather than one per element as
+/// with [`CxxVector::push`]. To view the elements in place, without
+/// copying, use [`CxxVector::as_slice`], which cxx provides for such
+/// vectors.
+pub trait PodVectorExt<T> {
+    /// Copy every element into a new [`Vec`].
+    fn copy_to_vec(&self) -> Vec<T>;
+
+    /// Append copies of every element of `items`.
+    fn extend_from_slice(self: Pin<&mut Self>, items: &[T]);
+}
+
+impl<T: PodVectorElement> PodVectorExt<T> for CxxVector<T> {
+    fn copy_to_vec(&self) -> Vec<T> {
+        let len = self.len();
+        let mut result = Vec::with_capacity(len);
+        // Safety: the C++ copy-constructs exactly `len` elements into the
+        // spare capacity.
+        unsafe {
+            T::copy_from_vector(self, result.as_mut_ptr());
+            result.set_len(len);
+        }
+        result
+    }
+
+    fn extend_from_slice(self: Pin<&mut Self>, items: &[T]) {
+        // Safety: the pointer and length come from a valid slice.
+        unsafe { T::extend_vector(self, items.as_ptr(), items.len()) }
+    }
+}
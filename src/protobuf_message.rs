@@ -0,0 +1,30 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Serialization of [`protobuf_message`](crate::protobuf_message!) types.
+
+use std::pin::Pin;
+
+/// A C++ protobuf message, implemented by each type listed in
+/// `protobuf_message!`. The serialized bytes are the protobuf wire
+/// format, so this is also the way to convert to and from Rust protobuf
+/// types, for example with `prost`:
+///
+/// ```ignore
+/// use prost::Message;
+/// let order = my_protos::Order::decode(cpp_order.to_bytes().as_slice())?;
+/// cpp_order.as_mut().parse_from_bytes(&order.encode_to_vec());
+/// ```
+pub trait ProtobufMessage {
+    /// Serialize this message, as `SerializeAsString` does.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Replace the contents of this message with those parsed from `data`,
+    /// as `ParseFromArray` does, returning whether that succeeded.
+    fn parse_from_bytes(self: Pin<&mut Self>, data: &[u8]) -> bool;
+}
@@ -215,6 +215,56 @@ impl<T: ?Sized> CppLtRef<'_, T> {
     }
 }
 
+/// A C++ object which points into some other object, such as an iterator,
+/// span or string view, tagged with the lifetime of that other object.
+///
+/// Methods which return a type listed in a `view_type!` directive return
+/// one of these, borrowing from the receiver. This means that the borrow
+/// checker will complain if the view outlives the container from which it
+/// came, or if that container is mutated while the view exists. The view
+/// itself can be used via `Deref`.
+#[repr(transparent)]
+pub struct CppView<'a, T> {
+    view: T,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<T> CppView<'_, T> {
+    /// Tag a view with a lifetime. This is called by generated code.
+    pub fn new(view: T) -> Self {
+        Self {
+            view,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Detach the view from the lifetime of the object into which it
+    /// points.
+    ///
+    /// # Safety
+    ///
+    /// As with [`CppLtRef::lifetime_cast`], misuse can't cause undefined
+    /// behavior in Rust unless the view type itself dereferences its
+    /// contents in Rust, but can easily cause it in C++ - use only
+    /// if you know the underlying object will outlive the view.
+    pub fn lifetime_cast(self) -> T {
+        self.view
+    }
+}
+
+impl<T> Deref for CppView<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.view
+    }
+}
+
+impl<T> DerefMut for CppView<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.view
+    }
+}
+
 /// A C++ non-const reference. These are different from Rust's `&mut T` in that
 /// several C++ references can exist to the same underlying data ("aliasing")
 /// and that's not permitted for regular Rust references.
@@ -246,6 +296,19 @@ impl<T: ?Sized> CppMutRef<T> {
         &mut *self.as_mut_ptr()
     }
 
+    /// Get a pinned Rust mutable reference out of this C++ reference. This
+    /// is for calling the few APIs which take `Pin<&mut T>` even under the
+    /// `unsafe_references_wrapped` policy, for example [`crate::PinMut`]
+    /// or functions in a handwritten `cxx::bridge`.
+    ///
+    /// # Safety
+    ///
+    /// The same rules apply as for [`CppMutRef::as_mut`]. The referent is
+    /// C++ data which Rust can't move, so it's always OK to pin it.
+    pub unsafe fn as_pin_mut_unchecked(&mut self) -> Pin<&mut T> {
+        Pin::new_unchecked(self.as_mut())
+    }
+
     /// Create a C++ reference from a raw pointer.
     pub fn from_ptr(ptr: *mut T) -> Self {
         Self(ptr)
@@ -272,6 +335,16 @@ impl<T: ?Sized> Clone for CppMutRef<T> {
 
 impl<T: ?Sized> Copy for CppMutRef<T> {}
 
+/// A pinned Rust reference can always be turned into a C++ reference,
+/// which allows methods generated to take `CppMutRef<Self>` receivers to be
+/// called on, for example, [`cxx::UniquePtr::pin_mut`].
+impl<T: ?Sized> From<Pin<&mut T>> for CppMutRef<T> {
+    fn from(mut pinned: Pin<&mut T>) -> Self {
+        // Safety: we take only a pointer, and never move the referent.
+        Self(unsafe { pinned.as_mut().get_unchecked_mut() })
+    }
+}
+
 impl<T> From<CppMutRef<T>> for CppRef<T> {
     fn from(mutable: CppMutRef<T>) -> Self {
         Self(mutable.0)
@@ -304,6 +377,12 @@ impl<T: ?Sized> CppMutLtRef<'_, T> {
 impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<CppMutRef<U>> for CppMutRef<T> {}
 
 /// Any type which can return a C++ reference to its contents.
+///
+/// autocxx also implements this (and [`AsCppMutRef`]) for a
+/// [`CppPin`] of any generated type, yielding a reference to each of its
+/// public base classes which is also generated. The conversion is done in C++, so it's correct
+/// even where multiple inheritance means the base isn't at the start of
+/// the object.
 pub trait AsCppRef<T: ?Sized> {
     /// Returns a reference which obeys C++ reference semantics
     fn as_cpp_ref(&self) -> CppRef<T>;
@@ -524,20 +603,76 @@ impl<T: UniquePtrTarget> CppUniquePtrPin<T> {
     /// Imprison the type within a `CppPin`. This eliminates any remaining
     /// Rust references (since we take the item by value) and this object
     /// subsequently only vends C++ style references, not Rust references.
+    ///
+    /// A null `UniquePtr` is accepted, but [`Self::as_ptr`] and the
+    /// [`AsCppRef`] implementation will then panic. Use [`Self::try_new`]
+    /// to reject null pointers up front.
     pub fn new(item: UniquePtr<T>) -> Self {
         let ptr = item.as_mut_ptr();
         Self(item, CppMutRef::from_ptr(ptr))
     }
 
+    /// Like [`Self::new`], but fails if the `UniquePtr` is null, so that
+    /// any `CppUniquePtrPin` successfully created this way can always
+    /// vend references.
+    pub fn try_new(item: UniquePtr<T>) -> Result<Self, NullUniquePtr> {
+        if item.is_null() {
+            Err(NullUniquePtr)
+        } else {
+            Ok(Self::new(item))
+        }
+    }
+
     /// Get an immutable pointer to the underlying object.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `UniquePtr` is null. See [`Self::try_as_ptr`].
     pub fn as_ptr(&self) -> *const T {
-        // TODO - avoid brief reference here
-        self.0
-            .as_ref()
+        self.try_as_ptr()
             .expect("UniquePtr was null; we can't make a C++ reference")
     }
+
+    /// Get an immutable pointer to the underlying object, or an error if
+    /// the `UniquePtr` is null.
+    pub fn try_as_ptr(&self) -> Result<*const T, NullUniquePtr> {
+        // TODO - avoid brief reference here
+        self.0.as_ref().map(|r| r as *const T).ok_or(NullUniquePtr)
+    }
+
+    /// Get a C++ reference to the underlying object, or an error if the
+    /// `UniquePtr` is null. This is the non-panicking equivalent of
+    /// [`AsCppRef::as_cpp_ref`].
+    pub fn try_as_cpp_ref(&self) -> Result<CppRef<T>, NullUniquePtr> {
+        self.try_as_ptr().map(CppRef::from_ptr)
+    }
+
+    /// Get a mutable C++ reference to the underlying object, or an error
+    /// if the `UniquePtr` is null. This is the non-panicking equivalent of
+    /// [`AsCppMutRef::as_cpp_mut_ref`].
+    pub fn try_as_cpp_mut_ref(&mut self) -> Result<CppMutRef<T>, NullUniquePtr> {
+        if self.0.is_null() {
+            Err(NullUniquePtr)
+        } else {
+            Ok(self.1)
+        }
+    }
+}
+
+/// The error returned by the fallible methods of [`CppUniquePtrPin`] when
+/// the contained `UniquePtr` is null, and by functions generated with
+/// `panic_free!` when given a null `UniquePtr` as a value parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullUniquePtr;
+
+impl std::fmt::Display for NullUniquePtr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UniquePtr was null")
+    }
 }
 
+impl std::error::Error for NullUniquePtr {}
+
 impl<T: UniquePtrTarget> AsCppRef<T> for CppUniquePtrPin<T> {
     fn as_cpp_ref(&self) -> CppRef<T> {
         CppRef::from_ptr(self.as_ptr())
@@ -22,7 +22,8 @@ use std::{
 /// # Panics
 ///
 /// The implementations of this trait which take a [`cxx::UniquePtr`] will
-/// panic if the pointer is NULL.
+/// panic if the pointer is NULL, unless the bindings were generated with
+/// `panic_free!`, in which case the generated function returns an error.
 ///
 /// # Safety
 ///
@@ -34,6 +35,12 @@ pub unsafe trait RValueParam<T>: Sized {
     /// and therefore may be mutated.
     #[doc(hidden)]
     fn get_ptr(stack: Pin<&mut Self>) -> *mut T;
+    /// Whether this is a null pointer, which can't be passed to C++.
+    /// Generated code checks this first if `panic_free!` is in force.
+    #[doc(hidden)]
+    fn is_null(&self) -> bool {
+        false
+    }
 }
 
 unsafe impl<T> RValueParam<T> for UniquePtr<T>
@@ -51,6 +58,10 @@ where
             )) as *mut T
         }
     }
+
+    fn is_null(&self) -> bool {
+        UniquePtr::is_null(self)
+    }
 }
 
 unsafe impl<T> RValueParam<T> for Pin<Box<T>> {
@@ -0,0 +1,124 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use cxx::CxxString;
+use moveit::new::New;
+use std::fmt;
+use std::marker::PhantomPinned;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::pin::Pin;
+
+// The size of a `std::string`, in words. libc++ uses three words; libstdc++
+// and the MSVC STL (in release builds) use two words plus a 16-byte inline
+// buffer. The C++ which autocxx generates to construct and destroy strings
+// has `static_assert`s to check that the C++ standard library in use agrees.
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "fuchsia"
+))]
+const STRING_WORDS: usize = 3;
+#[cfg(not(any(
+    target_vendor = "apple",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "fuchsia"
+)))]
+const STRING_WORDS: usize = 16 / std::mem::size_of::<usize>() + 2;
+
+/// A C++ `std::string` held by value, rather than within a
+/// [`cxx::UniquePtr`]. Functions which return a `std::string` produce
+/// one of these (via [`New`]) if the `strings_by_value!()` directive is
+/// used, so that a string can be put on the stack using `moveit!` without
+/// any heap allocation beyond that which C++ does itself. Short strings
+/// usually need none at all. The same directive generates
+/// `make_string_value()` to create one from Rust. Parameters are
+/// unaffected: functions still take strings as `UniquePtr<CxxString>` or
+/// `&CxxString`, which a `CxxStringValue` derefs to.
+///
+/// The string may point into itself, so it must stay pinned. Use it via
+/// [`Deref`] as a [`CxxString`], or [`CxxStringValue::pin_mut`] to
+/// modify it.
+#[repr(C)]
+pub struct CxxStringValue {
+    storage: [MaybeUninit<usize>; STRING_WORDS],
+    // The generated C++ function which destroys the string. Each
+    // include_cpp! generates its own, so we need to remember which.
+    destroy: unsafe fn(*mut CxxString),
+    _pinned: PhantomPinned,
+}
+
+impl CxxStringValue {
+    /// Used by `make_string_value()` to construct a string from some
+    /// bytes.
+    ///
+    /// # Safety
+    ///
+    /// `init` must construct a `std::string` containing the given bytes
+    /// at the given address, and `destroy` must destroy it.
+    #[doc(hidden)]
+    pub unsafe fn new_with(
+        bytes: impl AsRef<[u8]>,
+        init: unsafe fn(*mut CxxString, *const u8, usize),
+        destroy: unsafe fn(*mut CxxString),
+    ) -> impl New<Output = Self> {
+        moveit::new::by_raw(move |this: Pin<&mut MaybeUninit<Self>>| {
+            let bytes = bytes.as_ref();
+            let this = this.get_unchecked_mut().as_mut_ptr();
+            Self::set_destroy(this, destroy);
+            init(this.cast(), bytes.as_ptr(), bytes.len());
+        })
+    }
+
+    /// Used by generated code to record how to destroy the string which
+    /// it's about to construct at `this`.
+    ///
+    /// # Safety
+    ///
+    /// `this` must point to storage for a `CxxStringValue`, and `destroy`
+    /// must be able to destroy the string which is constructed there.
+    #[doc(hidden)]
+    pub unsafe fn set_destroy(this: *mut Self, destroy: unsafe fn(*mut CxxString)) {
+        std::ptr::addr_of_mut!((*this).destroy).write(destroy);
+    }
+
+    /// Get a pinned mutable reference to the underlying [`CxxString`].
+    pub fn pin_mut(self: Pin<&mut Self>) -> Pin<&mut CxxString> {
+        unsafe { self.map_unchecked_mut(|this| &mut *(this as *mut Self).cast::<CxxString>()) }
+    }
+}
+
+impl Deref for CxxStringValue {
+    type Target = CxxString;
+
+    fn deref(&self) -> &CxxString {
+        unsafe { &*(self as *const Self).cast::<CxxString>() }
+    }
+}
+
+impl Drop for CxxStringValue {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)((self as *mut Self).cast()) }
+    }
+}
+
+impl fmt::Display for CxxStringValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl fmt::Debug for CxxStringValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
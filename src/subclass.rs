@@ -10,7 +10,7 @@
 // except according to those terms.
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     pin::Pin,
     rc::{Rc, Weak},
 };
@@ -53,6 +53,19 @@ pub use autocxx_macro::subclass as is_subclass;
 /// See [`CppSubclass`] for information about the
 /// multiple steps you need to take to be able to make Rust
 /// subclasses of a C++ class.
+///
+/// If the superclass has a public destructor which isn't virtual, C++
+/// can't safely delete the subclass through a pointer to the superclass,
+/// so autocxx refuses to generate the subclass unless the `subclass!`
+/// directive has a third argument saying what to do instead:
+/// * `leak`: rather than `as_<superclass>_unique_ptr`, the subclass gets
+///   `leak_as_<superclass>_ptr`, which gives up ownership of the C++ peer
+///   and returns a raw pointer to the superclass. The peer is never
+///   destroyed.
+/// * `downcast`: `as_<superclass>_unique_ptr` is generated, but is
+///   unsafe, since the resulting `UniquePtr` mustn't be dropped. It must
+///   instead be destroyed using the unsafe `delete_<superclass>_unique_ptr`
+///   function, which deletes it as the subclass.
 pub use autocxx_macro::subclass;
 
 /// A prelude containing all the traits and macros required to create
@@ -74,28 +87,75 @@ pub trait CppSubclassCppPeer: UniquePtrTarget {
     fn relinquish_ownership(&self);
 }
 
+/// Whether the C++ side of a Rust/C++ subclass pair still exists. This is
+/// shared between the [`CppSubclassRustPeerHolder`] within the C++ object,
+/// which clears it when C++ destroys that object, and the Rust side, which
+/// may outlive the C++ side while one of its methods is running.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct CppPeerLiveness(Rc<Cell<bool>>);
+
+impl CppPeerLiveness {
+    fn new() -> Self {
+        Self(Rc::new(Cell::new(true)))
+    }
+
+    fn is_alive(&self) -> bool {
+        self.0.get()
+    }
+}
+
 /// A type used for how the C++ side of a Rust/C++ subclass pair refers to
-/// the Rust side.
+/// the Rust side. This lives within the C++ object, so is dropped exactly
+/// when C++ destroys it.
 #[doc(hidden)]
-pub enum CppSubclassRustPeerHolder<T> {
+pub struct CppSubclassRustPeerHolder<T> {
+    peer: RustPeer<T>,
+    liveness: CppPeerLiveness,
+}
+
+enum RustPeer<T> {
     Owned(Rc<RefCell<T>>),
     Unowned(Weak<RefCell<T>>),
 }
 
 impl<T> CppSubclassRustPeerHolder<T> {
+    fn owned(peer: Rc<RefCell<T>>) -> Self {
+        Self {
+            peer: RustPeer::Owned(peer),
+            liveness: CppPeerLiveness::new(),
+        }
+    }
+
+    fn unowned(peer: Weak<RefCell<T>>) -> Self {
+        Self {
+            peer: RustPeer::Unowned(peer),
+            liveness: CppPeerLiveness::new(),
+        }
+    }
+
+    /// Returns a strong reference to the Rust side, if it still exists.
+    /// Generated code holds this for the duration of each call into a Rust
+    /// method, so that if C++ destroys its side during the call, the Rust
+    /// side isn't destroyed until the call returns.
     pub fn get(&self) -> Option<Rc<RefCell<T>>> {
-        match self {
-            CppSubclassRustPeerHolder::Owned(strong) => Some(strong.clone()),
-            CppSubclassRustPeerHolder::Unowned(weak) => weak.upgrade(),
+        match &self.peer {
+            RustPeer::Owned(strong) => Some(strong.clone()),
+            RustPeer::Unowned(weak) => weak.upgrade(),
         }
     }
-    pub fn relinquish_ownership(self) -> Self {
-        match self {
-            CppSubclassRustPeerHolder::Owned(strong) => {
-                CppSubclassRustPeerHolder::Unowned(Rc::downgrade(&strong))
-            }
-            _ => self,
+
+    pub fn relinquish_ownership(mut self) -> Self {
+        if let RustPeer::Owned(strong) = &self.peer {
+            self.peer = RustPeer::Unowned(Rc::downgrade(strong));
         }
+        self
+    }
+}
+
+impl<T> Drop for CppSubclassRustPeerHolder<T> {
+    fn drop(&mut self) {
+        self.liveness.0.set(false);
     }
 }
 
@@ -106,16 +166,17 @@ impl<T> CppSubclassRustPeerHolder<T> {
 pub enum CppSubclassCppPeerHolder<CppPeer: CppSubclassCppPeer> {
     #[default]
     Empty,
-    Owned(Box<UniquePtr<CppPeer>>),
-    Unowned(*mut CppPeer),
+    Owned(Box<UniquePtr<CppPeer>>, CppPeerLiveness),
+    Unowned(*mut CppPeer, CppPeerLiveness),
 }
 
 impl<CppPeer: CppSubclassCppPeer> CppSubclassCppPeerHolder<CppPeer> {
     fn pin_mut(&mut self) -> Pin<&mut CppPeer> {
+        self.check_alive();
         match self {
             CppSubclassCppPeerHolder::Empty => panic!("Peer not set up"),
-            CppSubclassCppPeerHolder::Owned(peer) => peer.pin_mut(),
-            CppSubclassCppPeerHolder::Unowned(peer) => unsafe {
+            CppSubclassCppPeerHolder::Owned(peer, _) => peer.pin_mut(),
+            CppSubclassCppPeerHolder::Unowned(peer, _) => unsafe {
                 // Safety: guaranteed safe because this is a pointer to a C++ object,
                 // and C++ never moves things in memory.
                 Pin::new_unchecked(peer.as_mut().unwrap())
@@ -123,23 +184,49 @@ impl<CppPeer: CppSubclassCppPeer> CppSubclassCppPeerHolder<CppPeer> {
         }
     }
     fn get(&self) -> &CppPeer {
+        self.check_alive();
         match self {
             CppSubclassCppPeerHolder::Empty => panic!("Peer not set up"),
-            CppSubclassCppPeerHolder::Owned(peer) => peer.as_ref(),
+            CppSubclassCppPeerHolder::Owned(peer, _) => peer.as_ref(),
             // Safety: guaranteed safe because this is a pointer to a C++ object,
             // and C++ never moves things in memory.
-            CppSubclassCppPeerHolder::Unowned(peer) => unsafe { peer.as_ref().unwrap() },
+            CppSubclassCppPeerHolder::Unowned(peer, _) => unsafe { peer.as_ref().unwrap() },
+        }
+    }
+    fn is_alive(&self) -> bool {
+        match self {
+            CppSubclassCppPeerHolder::Empty => false,
+            CppSubclassCppPeerHolder::Owned(_, liveness)
+            | CppSubclassCppPeerHolder::Unowned(_, liveness) => liveness.is_alive(),
+        }
+    }
+    fn check_alive(&self) {
+        if !matches!(self, CppSubclassCppPeerHolder::Empty) && !self.is_alive() {
+            panic!("The C++ peer of this subclass has been destroyed, probably by C++ during a call to one of its methods");
         }
     }
-    fn set_owned(&mut self, peer: UniquePtr<CppPeer>) {
-        *self = Self::Owned(Box::new(peer));
+    fn set_owned(&mut self, peer: UniquePtr<CppPeer>, liveness: CppPeerLiveness) {
+        *self = Self::Owned(Box::new(peer), liveness);
     }
-    fn set_unowned(&mut self, peer: &mut UniquePtr<CppPeer>) {
+    fn set_unowned(&mut self, peer: &mut UniquePtr<CppPeer>, liveness: CppPeerLiveness) {
         // Safety: guaranteed safe because this is a pointer to a C++ object,
         // and C++ never moves things in memory.
-        *self = Self::Unowned(unsafe {
-            std::pin::Pin::<&mut CppPeer>::into_inner_unchecked(peer.pin_mut())
-        });
+        *self = Self::Unowned(
+            unsafe { std::pin::Pin::<&mut CppPeer>::into_inner_unchecked(peer.pin_mut()) },
+            liveness,
+        );
+    }
+}
+
+impl<CppPeer: CppSubclassCppPeer> Drop for CppSubclassCppPeerHolder<CppPeer> {
+    fn drop(&mut self) {
+        if let CppSubclassCppPeerHolder::Owned(peer, liveness) = self {
+            if !liveness.is_alive() {
+                // C++ has already destroyed the peer, despite our owning it.
+                // Deleting it again would be a double free.
+                std::mem::forget(std::mem::replace(&mut **peer, UniquePtr::null()));
+            }
+        }
     }
 }
 
@@ -157,11 +244,12 @@ where
 {
     let me = Rc::new(RefCell::new(me));
     let holder = peer_boxer(me.clone());
+    let liveness = holder.liveness.clone();
     let cpp_side = peer_constructor(&mut me.as_ref().borrow_mut(), holder);
     me.as_ref()
         .borrow_mut()
         .peer_holder_mut()
-        .set_owned(cpp_side);
+        .set_owned(cpp_side, liveness);
     me
 }
 
@@ -261,6 +349,13 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   with existing C++ interfaces. If you need this, indicate support on
 ///   [this issue](https://github.com/google/autocxx/issues/622).
 ///
+/// * *Destruction during a method call*. C++ may destroy an object it owns
+///   while a Rust method of that object is running, for instance if the
+///   method calls back into C++ which deletes it. The Rust side then
+///   lives on until the method returns, and is destroyed then. Meanwhile
+///   [`CppSubclass::peer_is_alive`] returns `false`, and any attempt to use
+///   the C++ side panics rather than touching freed memory.
+///
 /// * *Thread safety*. The subclass object is not thread-safe and shouldn't
 ///   be passed to different threads in C++. A future version of this code
 ///   will give the option to use `Arc` and `Mutex` internally rather than
@@ -294,15 +389,26 @@ pub trait CppSubclass<CppPeer: CppSubclassCppPeer>: CppPeerConstructor<CppPeer>
         self.peer_holder_mut().pin_mut()
     }
 
+    /// Whether the C++ part of this object pair still exists. It may not,
+    /// if C++ has destroyed it during a call to one of this object's
+    /// methods. In that case, [`CppSubclass::peer`] and
+    /// [`CppSubclass::peer_mut`] panic, as do calls to superclass methods.
+    fn peer_is_alive(&self) -> bool {
+        self.peer_holder().is_alive()
+    }
+
     /// Creates a new instance of this subclass. This instance is owned by the
     /// returned [`cxx::UniquePtr`] and thus would typically be returned immediately
     /// to C++ such that it can be owned on the C++ side.
     fn new_cpp_owned(me: Self) -> UniquePtr<CppPeer> {
         let me = Rc::new(RefCell::new(me));
-        let holder = CppSubclassRustPeerHolder::Owned(me.clone());
+        let holder = CppSubclassRustPeerHolder::owned(me.clone());
+        let liveness = holder.liveness.clone();
         let mut borrowed = me.as_ref().borrow_mut();
         let mut cpp_side = borrowed.make_peer(holder);
-        borrowed.peer_holder_mut().set_unowned(&mut cpp_side);
+        borrowed
+            .peer_holder_mut()
+            .set_unowned(&mut cpp_side, liveness);
         cpp_side
     }
 
@@ -313,7 +419,7 @@ pub trait CppSubclass<CppPeer: CppSubclassCppPeer>: CppPeerConstructor<CppPeer>
         make_owning_peer(
             me,
             |obj, holder| obj.make_peer(holder),
-            |me| CppSubclassRustPeerHolder::Unowned(Rc::downgrade(&me)),
+            |me| CppSubclassRustPeerHolder::unowned(Rc::downgrade(&me)),
         )
     }
 }
@@ -333,7 +439,7 @@ pub trait CppSubclassSelfOwned<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer
         make_owning_peer(
             me,
             |obj, holder| obj.make_peer(holder),
-            CppSubclassRustPeerHolder::Owned,
+            CppSubclassRustPeerHolder::owned,
         )
     }
 
@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runtime checks for [`thread_affine`](crate::thread_affine) types.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+/// Remembers which thread owns each object of a `thread_affine!` type,
+/// keyed by its address, so that generated wrappers can panic if it's used
+/// from any other. Objects constructed from Rust are owned by the thread
+/// which constructed them; others by the first thread to call a method.
+#[doc(hidden)]
+pub struct ThreadAffinity(Mutex<BTreeMap<usize, ThreadId>>);
+
+impl ThreadAffinity {
+    pub const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    /// Record that the object about to be constructed at `object` belongs
+    /// to this thread. This replaces any record for a previous object
+    /// which lived at the same address.
+    pub fn record_owner(&self, object: usize) {
+        self.owners().insert(object, thread::current().id());
+    }
+
+    #[track_caller]
+    pub fn check(&self, object: usize, type_name: &str) {
+        let current = thread::current().id();
+        let owner = *self.owners().entry(object).or_insert(current);
+        if owner != current {
+            panic!(
+                "this {type_name} is thread_affine! so may only be used on the thread which owns it ({owner:?}), not {current:?}"
+            );
+        }
+    }
+
+    fn owners(&self) -> std::sync::MutexGuard<'_, BTreeMap<usize, ThreadId>> {
+        // A panic in check() happens after the guard is dropped, so the
+        // map is never left half-updated.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ThreadAffinity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
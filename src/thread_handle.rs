@@ -0,0 +1,120 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `Send` handles to [`thread_handle`](crate::thread_handle!) types, which
+//! make each call on the thread that owns the object.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use cxx::memory::UniquePtrTarget;
+use cxx::UniquePtr;
+
+use crate::oneshot::{self, Receiver};
+
+/// Runs tasks on the thread which owns some thread-affine C++ objects,
+/// typically by posting them to that thread's event loop.
+///
+/// # Safety
+///
+/// Every task must be run on the same thread, and one at a time. A task
+/// mustn't be run from within the call to `dispatch` which queued it, since
+/// it may be queued from within another task. A task which is never run
+/// should be dropped, which cancels the future waiting for its result.
+pub unsafe trait Dispatcher: Send + Sync {
+    fn dispatch(&self, task: Box<dyn FnOnce() + Send>);
+}
+
+/// A raw pointer which we promise to use only from within tasks run by
+/// the [`Dispatcher`].
+struct OwnerThreadPtr<T>(*mut T);
+
+// Safety: see above.
+unsafe impl<T> Send for OwnerThreadPtr<T> {}
+unsafe impl<T> Sync for OwnerThreadPtr<T> {}
+
+impl<T> OwnerThreadPtr<T> {
+    // A method, rather than access to the field, so that closures capture
+    // the whole of this rather than just the raw pointer.
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+struct Owned<T: UniquePtrTarget + 'static> {
+    object: OwnerThreadPtr<T>,
+    dispatcher: Arc<dyn Dispatcher>,
+}
+
+impl<T: UniquePtrTarget + 'static> Drop for Owned<T> {
+    fn drop(&mut self) {
+        // The last handle may be dropped on any thread, so destroy the
+        // object on its own. If the task is never run, it's leaked.
+        let object = OwnerThreadPtr(self.object.get());
+        self.dispatcher.dispatch(Box::new(move || {
+            // Safety: the pointer came from UniquePtr::into_raw, and this
+            // is the last use of it.
+            drop(unsafe { UniquePtr::from_raw(object.get()) })
+        }));
+    }
+}
+
+/// An owning handle to a C++ object which may only be used on one thread,
+/// but which may itself be sent to, and shared between, other threads.
+/// Each call is made by a task given to a [`Dispatcher`] for that thread,
+/// and returns a future which resolves once it's done. autocxx wraps this
+/// in a `TypeHandle` for each type listed in `thread_handle!`, with
+/// a method for each of its methods which can be called this way.
+pub struct ThreadHandle<T: UniquePtrTarget + 'static>(Arc<Owned<T>>);
+
+impl<T: UniquePtrTarget + 'static> ThreadHandle<T> {
+    /// Take ownership of an object belonging to the dispatcher's thread.
+    /// It's destroyed on that thread once the last clone of this handle
+    /// is dropped.
+    ///
+    /// # Panics
+    ///
+    /// If `object` is null.
+    ///
+    /// # Safety
+    ///
+    /// `object` must belong to the thread on which `dispatcher` runs its
+    /// tasks, and nothing else may use it on any other thread, since the
+    /// handle calls it from those tasks without checking.
+    pub unsafe fn new(object: UniquePtr<T>, dispatcher: Arc<dyn Dispatcher>) -> Self {
+        assert!(!object.is_null(), "ThreadHandle of a null UniquePtr");
+        Self(Arc::new(Owned {
+            object: OwnerThreadPtr(object.into_raw()),
+            dispatcher,
+        }))
+    }
+
+    /// Call `f` with the object on its own thread. The result resolves to
+    /// the value returned, or to [`Canceled`](oneshot::Canceled) if the
+    /// dispatcher dropped the task without running it.
+    pub fn run<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(Pin<&mut T>) -> R + Send + 'static,
+    ) -> Receiver<R> {
+        let (sender, receiver) = oneshot::channel();
+        let owned = self.0.clone();
+        self.0.dispatcher.dispatch(Box::new(move || {
+            // Safety: we're on the object's thread, where no other task is
+            // running, and the object lives at least as long as `owned`.
+            let object = unsafe { Pin::new_unchecked(&mut *owned.object.get()) };
+            sender.send(f(object));
+        }));
+        receiver
+    }
+}
+
+impl<T: UniquePtrTarget + 'static> Clone for ThreadHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
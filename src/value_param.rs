@@ -49,7 +49,8 @@ use std::{marker::PhantomPinned, mem::MaybeUninit, ops::Deref, pin::Pin};
 /// # Panics
 ///
 /// The implementations of this trait which take a [`cxx::UniquePtr`] will
-/// panic if the pointer is NULL.
+/// panic if the pointer is NULL, unless the bindings were generated with
+/// `panic_free!`, in which case the generated function returns an error.
 ///
 /// # Safety
 ///
@@ -81,6 +82,12 @@ pub unsafe trait ValueParam<T> {
     /// such as `UniquePtr`; it's only necessary if it's something where
     /// manual management is required such as `MaybeUninit`.
     fn do_drop(_stack: Pin<&mut Self::StackStorage>) {}
+    /// Whether this is a null pointer, which can't be passed to C++.
+    /// Generated code checks this first if `panic_free!` is in force.
+    #[doc(hidden)]
+    fn is_null(&self) -> bool {
+        false
+    }
 }
 
 unsafe impl<T> ValueParam<T> for &T
@@ -133,6 +140,10 @@ where
             )) as *mut T
         }
     }
+
+    fn is_null(&self) -> bool {
+        UniquePtr::is_null(self)
+    }
 }
 
 unsafe impl<T> ValueParam<T> for Pin<Box<T>> {
@@ -171,6 +182,10 @@ where
     fn do_drop(stack: Pin<&mut Self::StackStorage>) {
         <&'a T as ValueParam<T>>::do_drop(stack)
     }
+
+    fn is_null(&self) -> bool {
+        UniquePtr::is_null(self)
+    }
 }
 
 unsafe impl<'a, T: 'a> ValueParam<T> for &'a Pin<Box<T>>
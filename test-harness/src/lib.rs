@@ -0,0 +1,271 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test harness for downstream users of autocxx.
+//!
+//! This crate lets you write tests for your own `include_cpp!` configurations:
+//! that some Rust compiles against the generated bindings, that some other
+//! Rust does not, that a set of directives is rejected, and that the API
+//! generated from a header hasn't changed unexpectedly.
+//!
+//! ```no_run
+//! use autocxx_test_harness::ApiTest;
+//! use quote::quote;
+//!
+//! let test = ApiTest::new(
+//!     "inline uint32_t do_math(uint32_t a, uint32_t b) { return a + b; }",
+//!     quote! { generate!("do_math") },
+//! );
+//! test.assert_compiles(quote! { assert_eq!(ffi::do_math(12, 13), 25); });
+//! test.assert_compile_fails(quote! { ffi::do_math("hello", 13); });
+//! test.assert_api_matches("tests/golden/do_math.txt");
+//! ```
+//!
+//! Golden files are written, rather than checked, if the `AUTOCXX_BLESS`
+//! environment variable is set.
+//!
+//! Tests are built using `trybuild`, within the context of the crate
+//! containing the tests, so that crate must have `autocxx` and `cxx` as
+//! dependencies (or dev-dependencies). A C++ compiler is also required.
+
+mod surface;
+
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use autocxx_integration_tests::{do_run_test, CodeCheckerFns};
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+
+pub use autocxx_engine::generate_api_summary;
+pub use autocxx_integration_tests::TestError;
+#[allow(deprecated)]
+pub use surface::render_api_surface;
+
+/// Set this environment variable to update golden files rather than
+/// comparing against them.
+pub const BLESS_ENV_VAR: &str = "AUTOCXX_BLESS";
+
+/// A C++ header plus the `include_cpp!` directives used to generate
+/// bindings for it.
+pub struct ApiTest {
+    header_code: String,
+    cxx_code: String,
+    directives: TokenStream,
+    extra_rust: Option<TokenStream>,
+    safety_policy: String,
+}
+
+impl ApiTest {
+    /// Create a test of bindings for the given header code. The
+    /// directives should not include `#include` or `safety!`, which are
+    /// added automatically.
+    pub fn new(header_code: &str, directives: TokenStream) -> Self {
+        Self {
+            header_code: header_code.to_string(),
+            cxx_code: String::new(),
+            directives,
+            extra_rust: None,
+            safety_policy: "unsafe_ffi".into(),
+        }
+    }
+
+    /// C++ code to compile and link alongside the header, e.g. definitions
+    /// of functions which the header only declares.
+    pub fn cxx_code(mut self, cxx_code: &str) -> Self {
+        self.cxx_code = cxx_code.to_string();
+        self
+    }
+
+    /// Rust items to place alongside the `include_cpp!` macro, outside
+    /// of `fn main`.
+    pub fn extra_rust(mut self, extra_rust: TokenStream) -> Self {
+        self.extra_rust = Some(extra_rust);
+        self
+    }
+
+    /// Use a safety policy other than `unsafe_ffi`, for instance
+    /// `unsafe_references_wrapped`.
+    pub fn safety_policy(mut self, safety_policy: &str) -> Self {
+        self.safety_policy = safety_policy.to_string();
+        self
+    }
+
+    fn run(
+        &self,
+        rust_code: TokenStream,
+        code_checker: Option<Box<dyn CodeCheckerFns>>,
+    ) -> Result<(), TestError> {
+        do_run_test(
+            &self.cxx_code,
+            &self.header_code,
+            rust_code,
+            self.directives.clone(),
+            None,
+            code_checker,
+            self.extra_rust.clone(),
+            &self.safety_policy,
+            None,
+        )
+    }
+
+    /// Assert that the given code, placed into `fn main`, builds against
+    /// the generated bindings.
+    #[track_caller]
+    pub fn assert_compiles(&self, rust_code: TokenStream) {
+        if let Err(e) = self.run(rust_code, None) {
+            panic!("Expected code to compile against the generated bindings, but: {e:?}");
+        }
+    }
+
+    /// Assert that bindings can be generated, but that the given code,
+    /// placed into `fn main`, does not build against them.
+    #[track_caller]
+    pub fn assert_compile_fails(&self, rust_code: TokenStream) {
+        match self.run(rust_code, None) {
+            Err(TestError::RsBuild) => {}
+            Ok(()) => panic!("Expected code not to compile, but it did"),
+            Err(e) => panic!("Expected only the Rust code to fail to compile, but: {e:?}"),
+        }
+    }
+
+    /// Assert that autocxx refuses to generate bindings at all, for
+    /// instance because a directive names something which doesn't exist.
+    #[track_caller]
+    pub fn assert_rejected(&self) {
+        match self.run(TokenStream::new(), Some(Box::new(SkipBuild))) {
+            Err(TestError::AutoCxx(_)) => {}
+            Ok(()) => panic!("Expected autocxx to reject these directives, but it did not"),
+            Err(e) => panic!("Expected autocxx to reject these directives, but: {e:?}"),
+        }
+    }
+
+    /// Generate bindings and return a summary of their API, one line per
+    /// item, as described in [`generate_api_summary`]. This is the same
+    /// summary as `autocxx-gen --emit-api-summary` writes. This doesn't
+    /// build the bindings.
+    pub fn api_summary(&self) -> Result<String, TestError> {
+        self.record_rust(|rs| generate_api_summary(&[rs]))
+    }
+
+    /// Generate bindings and return a textual rendering of their API
+    /// surface, as described in [`render_api_surface`]. This doesn't
+    /// build the bindings.
+    #[deprecated(
+        since = "0.30.0",
+        note = "use api_summary, which matches autocxx-gen --emit-api-summary"
+    )]
+    #[allow(deprecated)]
+    pub fn api_surface(&self) -> Result<String, TestError> {
+        self.record_rust(render_api_surface)
+    }
+
+    /// Generate bindings, without building them, and describe the Rust
+    /// using `describe`.
+    fn record_rust(&self, describe: fn(syn::File) -> String) -> Result<String, TestError> {
+        let description = Rc::new(RefCell::new(None));
+        self.run(
+            TokenStream::new(),
+            Some(Box::new(RustRecorder(description.clone(), describe))),
+        )?;
+        let description = description.borrow_mut().take();
+        description.ok_or(TestError::NoRs)
+    }
+
+    /// Assert that the API summary of the generated bindings matches the
+    /// given golden file. If [`BLESS_ENV_VAR`] is set, the golden file is
+    /// instead (re)written.
+    #[track_caller]
+    pub fn assert_api_matches(&self, golden_file: impl AsRef<Path>) {
+        let golden_file = golden_file.as_ref();
+        let actual = self
+            .api_summary()
+            .unwrap_or_else(|e| panic!("Unable to generate bindings: {e:?}"));
+        if std::env::var_os(BLESS_ENV_VAR).is_some() {
+            if let Some(parent) = golden_file.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            std::fs::write(golden_file, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(golden_file).unwrap_or_else(|e| {
+            panic!(
+                "Unable to read golden file {}: {e}. Set {BLESS_ENV_VAR}=1 to create it.",
+                golden_file.display()
+            )
+        });
+        if let Some(mismatch) = first_mismatch(&expected, &actual) {
+            panic!(
+                "Generated API differs from golden file {} at {mismatch}.\n\
+                 If this change is intended, rerun with {BLESS_ENV_VAR}=1.\n\
+                 Actual API:\n{actual}",
+                golden_file.display()
+            );
+        }
+    }
+}
+
+/// Describe the first line at which two summaries differ.
+fn first_mismatch(expected: &str, actual: &str) -> Option<String> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line_number = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (e, a) if e != a => {
+                return Some(format!(
+                    "line {line_number}: expected {:?}, got {:?}",
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                ))
+            }
+            _ => line_number += 1,
+        }
+    }
+}
+
+struct SkipBuild;
+
+impl CodeCheckerFns for SkipBuild {
+    fn skip_build(&self) -> bool {
+        true
+    }
+}
+
+struct RustRecorder(Rc<RefCell<Option<String>>>, fn(syn::File) -> String);
+
+impl CodeCheckerFns for RustRecorder {
+    fn check_rust(&self, rs: syn1::File) -> Result<(), TestError> {
+        let rs: syn::File = syn::parse2(rs.into_token_stream())
+            .map_err(|e| TestError::RsCodeExaminationFail(e.to_string()))?;
+        *self.0.borrow_mut() = Some((self.1)(rs));
+        Ok(())
+    }
+
+    fn skip_build(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::first_mismatch;
+
+    #[test]
+    fn test_first_mismatch() {
+        assert_eq!(first_mismatch("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            first_mismatch("a\nb\n", "a\nc\n").unwrap(),
+            "line 2: expected \"b\", got \"c\""
+        );
+        assert_eq!(
+            first_mismatch("a\n", "a\nb\n").unwrap(),
+            "line 2: expected \"<end of file>\", got \"b\""
+        );
+    }
+}
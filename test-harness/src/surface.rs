@@ -0,0 +1,152 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use syn::{parse_quote, Attribute, Block, File, ImplItem, Item, Visibility};
+
+/// Render the public API surface of some generated bindings as
+/// pretty-printed Rust.
+///
+/// Function bodies, documentation and private items are removed, so that
+/// the rendering changes only when the set of generated APIs or their
+/// signatures change. Modules are kept whatever their visibility because
+/// autocxx re-exports their contents, but their attributes are dropped.
+#[deprecated(
+    since = "0.30.0",
+    note = "use generate_api_summary, which matches autocxx-gen --emit-api-summary"
+)]
+pub fn render_api_surface(mut file: File) -> String {
+    file.attrs.clear();
+    file.items = strip_items(file.items);
+    prettyplease::unparse(&file)
+}
+
+fn strip_items(items: Vec<Item>) -> Vec<Item> {
+    items.into_iter().filter_map(strip_item).collect()
+}
+
+fn strip_item(item: Item) -> Option<Item> {
+    let mut item = item;
+    match &mut item {
+        Item::Mod(m) => {
+            m.attrs.clear();
+            if let Some((_, items)) = m.content.take() {
+                let items = strip_items(items);
+                if items.is_empty() {
+                    return None;
+                }
+                m.content = Some((Default::default(), items));
+            }
+        }
+        Item::Fn(f) => {
+            if !is_public(&f.vis) {
+                return None;
+            }
+            strip_docs(&mut f.attrs);
+            *f.block = empty_block();
+        }
+        Item::Impl(i) => {
+            let is_trait_impl = i.trait_.is_some();
+            strip_docs(&mut i.attrs);
+            i.items.retain_mut(|impl_item| match impl_item {
+                ImplItem::Fn(f) => {
+                    if !is_trait_impl && !is_public(&f.vis) {
+                        return false;
+                    }
+                    strip_docs(&mut f.attrs);
+                    f.block = empty_block();
+                    true
+                }
+                ImplItem::Const(c) => is_trait_impl || is_public(&c.vis),
+                _ => true,
+            });
+            if !is_trait_impl && i.items.is_empty() {
+                return None;
+            }
+        }
+        Item::Struct(s) if is_public(&s.vis) => strip_docs(&mut s.attrs),
+        Item::Enum(e) if is_public(&e.vis) => {
+            strip_docs(&mut e.attrs);
+            for variant in e.variants.iter_mut() {
+                strip_docs(&mut variant.attrs);
+            }
+        }
+        Item::Type(t) if is_public(&t.vis) => strip_docs(&mut t.attrs),
+        Item::Const(c) if is_public(&c.vis) => strip_docs(&mut c.attrs),
+        Item::Trait(t) if is_public(&t.vis) => strip_docs(&mut t.attrs),
+        Item::Use(u) if is_public(&u.vis) => {}
+        Item::ForeignMod(f) => {
+            strip_docs(&mut f.attrs);
+            for foreign_item in f.items.iter_mut() {
+                if let syn::ForeignItem::Fn(f) = foreign_item {
+                    strip_docs(&mut f.attrs);
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(item)
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn strip_docs(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|attr| !attr.path().is_ident("doc"))
+}
+
+fn empty_block() -> Block {
+    parse_quote! { {} }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use indoc::indoc;
+    use syn::parse_quote;
+
+    use super::render_api_surface;
+
+    #[test]
+    fn test_render_api_surface() {
+        let file = parse_quote! {
+            #[allow(non_snake_case)]
+            mod ffi {
+                /// A widget.
+                pub struct Widget {
+                    _pinned: core::marker::PhantomPinned,
+                }
+                impl Widget {
+                    /// Frobs it.
+                    pub fn frob(&self) -> u32 {
+                        cxxbridge::frob(self)
+                    }
+                    fn helper(&self) {}
+                }
+                struct Private;
+                pub fn do_math(a: u32) -> u32 {
+                    a + 1
+                }
+            }
+        };
+        assert_eq!(
+            render_api_surface(file),
+            indoc! {"
+                mod ffi {
+                    pub struct Widget {
+                        _pinned: core::marker::PhantomPinned,
+                    }
+                    impl Widget {
+                        pub fn frob(&self) -> u32 {}
+                    }
+                    pub fn do_math(a: u32) -> u32 {}
+                }
+            "}
+        );
+    }
+}